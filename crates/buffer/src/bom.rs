@@ -0,0 +1,64 @@
+//! UTF-8 BOM 的识别与还原。
+//!
+//! 缓冲区里永远不带 BOM，这样第一行的列号、发给 LSP 的偏移和 git diff
+//! 都按去掉 BOM 后的文本计算；保存时再按文件原来的情况写回。
+
+/// UTF-8 BOM（U+FEFF）
+pub const UTF8_BOM: char = '\u{feff}';
+
+/// 去掉开头的 BOM，返回剩余文本以及原文本是否带 BOM。
+pub fn strip_bom(text: &str) -> (&str, bool) {
+    match text.strip_prefix(UTF8_BOM) {
+        Some(rest) => (rest, true),
+        None => (text, false),
+    }
+}
+
+/// 生成写回磁盘的文本，`bom` 为 true 时在开头加上 BOM。
+pub fn with_bom(content: &str, bom: bool) -> String {
+    if bom {
+        let mut text = String::with_capacity(content.len() + UTF8_BOM.len_utf8());
+        text.push(UTF8_BOM);
+        text.push_str(content);
+        text
+    } else {
+        content.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EditorCore;
+
+    #[test]
+    fn test_bom_only_file() {
+        let (content, bom) = strip_bom("\u{feff}");
+        assert_eq!(content, "");
+        assert!(bom);
+        assert_eq!(with_bom(content, bom), "\u{feff}");
+        assert_eq!(strip_bom(""), ("", false));
+    }
+
+    #[test]
+    fn test_bom_with_crlf_keeps_first_line_columns() {
+        let raw = "\u{feff}类 A\r\n  b\r\n";
+        let (content, bom) = strip_bom(raw);
+        assert!(bom);
+        assert_eq!(content, "类 A\r\n  b\r\n");
+
+        // 第一行从第 0 列开始，不会被不可见的 BOM 占掉一列
+        let core = EditorCore::from_text(content);
+        assert_eq!(core.line_col_for_offset(0), (0, 0));
+        assert_eq!(core.offset_for_line_col(1, 2), "类 A\r\n  ".len());
+
+        assert_eq!(with_bom(content, bom), raw);
+        assert_eq!(with_bom(content, false), content);
+    }
+
+    #[test]
+    fn test_bom_only_stripped_at_start() {
+        let text = "a\u{feff}b";
+        assert_eq!(strip_bom(text), (text, false));
+    }
+}
@@ -0,0 +1,870 @@
+use std::ops::Range;
+use ropey::Rope;
+use crate::changes::{ChangeLog, ContentChange};
+use crate::selection::Selection;
+use crate::undo::{UndoHistory, EditOperation};
+use crate::version::{next_version, EditLog};
+
+/// Text content plus the selections and history that edit it.
+pub struct EditorCore {
+    pub content: Rope,
+    pub selections: Vec<Selection>,
+    /// IME composition range, cleared by every edit.
+    pub marked_range: Option<Range<usize>>,
+    pub history: UndoHistory,
+    /// Changes with every edit; see [`version`](Self::version).
+    version: u64,
+    edit_log: EditLog,
+    changes: ChangeLog,
+}
+
+impl EditorCore {
+    pub fn new() -> Self {
+        let version = next_version();
+        Self {
+            content: Rope::new(),
+            selections: vec![Selection::new(0, 0)],
+            marked_range: None,
+            history: UndoHistory::new(),
+            version,
+            edit_log: EditLog::new(version),
+            changes: ChangeLog::default(),
+        }
+    }
+
+    /// Creates a buffer holding `text` with a cursor at the start.
+    pub fn from_text(text: &str) -> Self {
+        let mut core = Self::new();
+        core.content = Rope::from(text);
+        core
+    }
+
+    /// Replaces the whole text without recording undo history, e.g. when the
+    /// file is reloaded from disk. Results stamped with an earlier version can
+    /// no longer be mapped.
+    pub fn set_text(&mut self, text: &str) {
+        self.content = Rope::from(text);
+        self.version = next_version();
+        self.edit_log.reset(self.version);
+        self.changes.reset();
+    }
+
+    /// The document version, stamped on work done against the current text
+    /// (completions, diagnostics, diffs) so it can be checked or mapped with
+    /// [`map_range`](Self::map_range) when it comes back.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Maps a byte range computed against version `since` to the current text.
+    /// Returns `None` when the range can't be trusted any more: an edit landed
+    /// inside it, or `since` is older than the recent edits kept.
+    pub fn map_range(&self, since: u64, range: Range<usize>) -> Option<Range<usize>> {
+        self.edit_log.map_range(since, range)
+    }
+
+    /// The edits since the last call, for following the text incrementally;
+    /// `None` after [`set_text`](Self::set_text) or when too many piled up,
+    /// in which case the whole text has to be reloaded.
+    pub fn take_changes(&mut self) -> Option<Vec<ContentChange>> {
+        self.changes.take()
+    }
+
+    /// Records a change about to be applied; call before mutating `content`.
+    fn record_change(&mut self, range: Range<usize>, text: &str) {
+        let change = ContentChange {
+            start: self.position_utf16(range.start),
+            end: self.position_utf16(range.end),
+            range,
+            text: text.to_string(),
+        };
+        self.changes.push(change);
+    }
+
+    fn record_edit(&mut self, range: Range<usize>, new_len: usize) {
+        self.version = next_version();
+        self.edit_log.push(self.version, range, new_len);
+    }
+
+    /// The most recently added selection, which drives scrolling and IME.
+    pub fn primary_selection(&self) -> Selection {
+        self.selections.last().cloned().unwrap_or(Selection::new(0, 0))
+    }
+
+    pub fn set_cursor(&mut self, index: usize) {
+        self.selections = vec![Selection::new(index, index)];
+        self.marked_range = None;
+    }
+
+    pub fn add_cursor(&mut self, index: usize) {
+        self.selections.push(Selection::new(index, index));
+        self.merge_selections();
+    }
+
+    pub fn select_to(&mut self, index: usize) {
+        if let Some(last) = self.selections.last_mut() {
+            last.head = index;
+            last.preferred_column = None;
+        }
+        self.merge_selections();
+        self.marked_range = None;
+    }
+
+    pub fn select_all(&mut self) {
+        let len = self.content.len_bytes();
+        self.selections = vec![Selection::new(0, len)];
+        self.marked_range = None;
+    }
+
+    /// Sorts selections and merges the ones that overlap or touch.
+    pub fn merge_selections(&mut self) {
+        // Sort by start position
+        self.selections.sort_by_key(|s| s.range().start);
+
+        let mut merged = Vec::new();
+        if let Some(mut current) = self.selections.first().cloned() {
+            for next in self.selections.iter().skip(1) {
+                let current_range = current.range();
+                let next_range = next.range();
+
+                if current_range.end >= next_range.start {
+                    // Overlapping or adjacent, merge
+                    // We need to decide anchor/head direction. 
+                    // For simplicity, if we merge, we might lose directionality or try to preserve "outer" bounds.
+                    // Let's just create a forward selection covering both.
+                    let start = current_range.start.min(next_range.start);
+                    let end = current_range.end.max(next_range.end);
+                    current = Selection::new(start, end);
+                } else {
+                    merged.push(current);
+                    current = next.clone();
+                }
+            }
+            merged.push(current);
+        }
+        self.selections = merged;
+    }
+
+    /// Applies several independent edits as one undo step. Ranges refer to the
+    /// content before any of the edits.
+    /// Never coalesces with typing before or after it.
+    pub fn apply_edits(&mut self, mut edits: Vec<(Range<usize>, String)>) {
+        self.history.break_group();
+        self.history.begin_transaction(&self.selections);
+        // Sort descending by start to avoid offset issues
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.0.start));
+        
+        for (range, text) in edits {
+            self.replace_range_internal(range, &text);
+        }
+        self.end_transaction();
+        self.history.break_group();
+        self.marked_range = None;
+    }
+
+    /// Replaces every selection with `text` as one undo step, leaving a cursor
+    /// after each insertion.
+    pub fn replace_selections(&mut self, text: &str) {
+        self.replace_selections_with(|_, _| text.to_string());
+    }
+
+    /// Like [`replace_selections`](Self::replace_selections), but asks
+    /// `text_for` for each selection's replacement. Selections are processed
+    /// bottom to top, so the content before the given range is still the
+    /// original text when `text_for` runs.
+    pub fn replace_selections_with(&mut self, mut text_for: impl FnMut(&Rope, Range<usize>) -> String) {
+        self.merge_selections();
+        self.history.begin_transaction(&self.selections);
+        
+        // Process from bottom to top to preserve indices of earlier selections
+        // Sort selections descending by start index
+        let mut sorted_indices: Vec<usize> = (0..self.selections.len()).collect();
+        sorted_indices.sort_by(|&a, &b| {
+            self.selections[b].range().start.cmp(&self.selections[a].range().start)
+        });
+
+        for i in 0..sorted_indices.len() {
+            let idx = sorted_indices[i];
+            let selection = self.selections[idx].clone();
+            let range = selection.range();
+            
+            // Apply edit
+            let text = text_for(&self.content, range.clone());
+            self.replace_range_internal(range.clone(), &text);
+            
+            // Calculate delta
+            let old_len = range.end - range.start;
+            let new_len = text.len();
+            let delta = new_len as isize - old_len as isize;
+             
+            // Update THIS selection
+            let new_pos = range.start + new_len;
+            self.selections[idx] = Selection::new(new_pos, new_pos);
+
+            // Update previously processed selections (which are physically AFTER this one)
+            if delta != 0 {
+                for &prev_idx in &sorted_indices[0..i] {
+                    let mut sel = self.selections[prev_idx].clone();
+                    // We must use isize for calculation to allow negative delta (deletion)
+                    // Ensure we don't underflow usize
+                    let new_anchor = (sel.anchor as isize + delta).max(0) as usize;
+                    let new_head = (sel.head as isize + delta).max(0) as usize;
+                    
+                    sel.anchor = new_anchor;
+                    sel.head = new_head;
+                    self.selections[prev_idx] = sel;
+                }
+            }
+        }
+        
+        self.end_transaction();
+        self.marked_range = None;
+    }
+
+    // Internal helper that doesn't manage transaction/selection update logic directly (or does it?)
+    // Actually `replace_range` in previous code managed history.
+    fn replace_range_internal(&mut self, range: Range<usize>, text: &str) {
+        let len = self.content.len_bytes();
+        let start = range.start.min(len);
+        let end = range.end.min(len);
+
+        if start > end {
+            eprintln!(
+                "Warning: replace_range_internal invalid range: {}..{}",
+                start, end
+            );
+            return;
+        }
+
+        if start < end || !text.is_empty() {
+            self.record_change(start..end, text);
+        }
+        let start_char_idx = self.content.byte_to_char(start);
+        let end_char_idx = self.content.byte_to_char(end);
+
+        if start_char_idx < end_char_idx {
+            let deleted_text = self
+                .content
+                .slice(start_char_idx..end_char_idx)
+                .to_string();
+            self.content.remove(start_char_idx..end_char_idx);
+            self.history.push(EditOperation::Delete {
+                range: start..end,
+                text: deleted_text,
+            });
+        }
+
+        if !text.is_empty() {
+            self.content.insert(start_char_idx, text);
+            self.history.push(EditOperation::Insert {
+                range: start..start + text.len(),
+                text: text.to_string(),
+            });
+        }
+        if start < end || !text.is_empty() {
+            self.record_edit(start..end, text.len());
+        }
+    }
+    
+    /// Replaces `range` with `text` as one undo step and collapses the
+    /// selections to a single cursor after the inserted text.
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) {
+         self.history.begin_transaction(&self.selections);
+         let len = self.content.len_bytes();
+         let start = range.start.min(len);
+         let end = range.end.min(len);
+         self.replace_range_internal(start..end, text);
+         
+         // Update selections?
+         // If we use this method, we assume single selection or manual control.
+         // Let's just update the primary selection to match behavior.
+         let new_pos = start + text.len();
+         self.selections = vec![Selection::new(new_pos, new_pos)];
+         self.marked_range = None;
+         
+         self.end_transaction();
+    }
+
+    /// Ends the open undo group with the current selections.
+    fn end_transaction(&mut self) {
+        let head = self.primary_selection().head.min(self.content.len_bytes());
+        let line = self.content.byte_to_line(head);
+        self.history.end_transaction(&self.selections, line);
+    }
+
+    /// Makes the next edit start a new undo step instead of joining the
+    /// typing or deleting before it.
+    pub fn break_undo_group(&mut self) {
+        self.history.break_group();
+    }
+
+    pub fn insert_text(&mut self, text: &str) {
+        self.replace_selections(text);
+    }
+
+    pub fn delete_range(&mut self, range: Range<usize>) {
+        self.replace_range(range, "");
+    }
+    
+    pub fn delete_selection(&mut self) {
+        self.replace_selections("");
+    }
+
+    /// Deletes every selection as one undo step; a plain cursor first extends
+    /// to the offset `target` gives for it. Undo puts the cursors back where
+    /// they were rather than selecting the deleted text.
+    pub fn delete_at_cursors(&mut self, target: impl Fn(&Rope, usize) -> usize) {
+        self.history.begin_transaction(&self.selections);
+        for selection in self.selections.iter_mut().filter(|selection| selection.is_empty()) {
+            *selection = Selection::new(target(&self.content, selection.head), selection.head);
+        }
+        self.delete_selection();
+    }
+    
+    /// Reverts the last undo step and restores the selections from before it.
+    pub fn undo(&mut self) {
+        if let Some((ops, selections)) = self.history.undo() {
+            self.apply_ops(ops, selections);
+        }
+    }
+
+    /// Re-applies the last undone step and restores the selections after it.
+    pub fn redo(&mut self) {
+        if let Some((ops, selections)) = self.history.redo() {
+            self.apply_ops(ops, selections);
+        }
+    }
+
+    fn apply_ops(&mut self, ops: Vec<EditOperation>, selections: Vec<Selection>) {
+        for op in ops {
+            self.apply_op(op);
+        }
+        // Steps recorded without selections leave the cursor after the last edit
+        if !selections.is_empty() {
+            let len = self.content.len_bytes();
+            self.selections = selections
+                .into_iter()
+                .map(|selection| Selection::new(selection.anchor.min(len), selection.head.min(len)))
+                .collect();
+        }
+        self.marked_range = None;
+    }
+
+    fn apply_op(&mut self, op: EditOperation) {
+        let len = self.content.len_bytes();
+        match op {
+            EditOperation::Insert { range, text } => {
+                let start = range.start.min(len);
+                self.record_change(start..start, &text);
+                let start_char_idx = self.content.byte_to_char(start);
+                self.content.insert(start_char_idx, &text);
+                self.record_edit(start..start, text.len());
+                // Update cursor
+                let new_pos = start + text.len();
+                self.selections = vec![Selection::new(new_pos, new_pos)];
+            },
+            EditOperation::Delete { range, .. } => {
+                 let start = range.start.min(len);
+                 let end = range.end.min(len);
+                 
+                 if start < end {
+                     self.record_change(start..end, "");
+                     let start_char_idx = self.content.byte_to_char(start);
+                     let end_char_idx = self.content.byte_to_char(end);
+                     self.content.remove(start_char_idx..end_char_idx);
+                     self.record_edit(start..end, 0);
+                 }
+                 self.selections = vec![Selection::new(start, start)];
+            }
+        }
+    }
+
+    /// Zero-based `(line, column)` for a byte offset, with the column in bytes.
+    /// Offsets past the end are clamped.
+    pub fn line_col_for_offset(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.content.len_bytes());
+        let line = self.content.byte_to_line(offset);
+        (line, offset - self.content.line_to_byte(line))
+    }
+
+    /// Byte offset for a zero-based `(line, column)`. The column is clamped to
+    /// the end of the line, before its line break, and snapped back to a char
+    /// boundary; lines past the end map to the end of the content.
+    pub fn offset_for_line_col(&self, line: usize, col: usize) -> usize {
+        if line >= self.content.len_lines() {
+            return self.content.len_bytes();
+        }
+        let line_start = self.content.line_to_byte(line);
+        let slice = self.content.line(line);
+        let mut len = slice.len_bytes();
+        let line_end = line_start + len;
+        if len > 0 && self.content.byte(line_end - 1) == b'\n' {
+            len -= 1;
+            if len > 0 && self.content.byte(line_end - 2) == b'\r' {
+                len -= 1;
+            }
+        }
+        let offset = line_start + col.min(len);
+        self.content.char_to_byte(self.content.byte_to_char(offset))
+    }
+
+    /// Zero-based `(line, column)` for a byte offset, with the column in UTF-16
+    /// code units. Offsets past the end are clamped.
+    pub fn position_utf16(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.content.len_bytes());
+        let line = self.content.byte_to_line(offset);
+        let line_start = self.content.line_to_char(line);
+        let column = self.content.slice(line_start..self.content.byte_to_char(offset)).len_utf16_cu();
+        (line, column)
+    }
+
+    /// Converts a byte offset to a UTF-16 code unit offset.
+    pub fn offset_to_utf16(&self, offset: usize) -> usize {
+        let len = self.content.len_bytes();
+        if offset > len {
+            eprintln!(
+                "Warning: offset_to_utf16 out of bounds: {} > {}",
+                offset, len
+            );
+            return self.content.len_utf16_cu();
+        }
+        let char_idx = self.content.byte_to_char(offset);
+        self.content.slice(0..char_idx).len_utf16_cu()
+    }
+
+    /// Converts a byte range to UTF-16 code units.
+    pub fn range_to_utf16(&self, range: &Range<usize>) -> Range<usize> {
+        let start = self.offset_to_utf16(range.start);
+        let end = self.offset_to_utf16(range.end);
+        start..end
+    }
+
+    /// Converts a UTF-16 range back to bytes, clamping to the content and
+    /// normalising reversed ranges.
+    pub fn range_from_utf16(&self, range_utf16: &Range<usize>) -> Range<usize> {
+        let len_utf16 = self.content.len_utf16_cu();
+        let start = range_utf16.start.min(len_utf16);
+        let end = range_utf16.end.min(len_utf16);
+
+        let start_char = self.content.utf16_cu_to_char(start);
+        let end_char = self.content.utf16_cu_to_char(end);
+        let start_byte = self.content.char_to_byte(start_char);
+        let end_byte = self.content.char_to_byte(end_char);
+        if start_byte <= end_byte {
+            start_byte..end_byte
+        } else {
+            end_byte..start_byte
+        }
+    }
+}
+
+impl Default for EditorCore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_selections() {
+        let mut core = EditorCore::new();
+        core.content = Rope::from("abcdef");
+        
+        // Overlapping selections
+        core.selections = vec![
+            Selection::new(0, 2), // "ab"
+            Selection::new(1, 3), // "bc"
+        ];
+        core.merge_selections();
+        assert_eq!(core.selections.len(), 1);
+        assert_eq!(core.selections[0].range(), 0..3);
+
+        // Adjacent selections
+        core.selections = vec![
+            Selection::new(0, 2), // "ab"
+            Selection::new(2, 4), // "cd"
+        ];
+        core.merge_selections();
+        assert_eq!(core.selections.len(), 1);
+        assert_eq!(core.selections[0].range(), 0..4);
+
+        // Disjoint selections
+        core.selections = vec![
+            Selection::new(0, 1), // "a"
+            Selection::new(2, 3), // "c"
+        ];
+        core.merge_selections();
+        assert_eq!(core.selections.len(), 2);
+    }
+
+    #[test]
+    fn test_multi_cursor_insert() {
+        let mut core = EditorCore::new();
+        core.content = Rope::from("abc\ndef\n");
+        
+        // Cursors at start of each line
+        core.selections = vec![
+            Selection::new(0, 0),
+            Selection::new(4, 4),
+        ];
+        
+        core.insert_text("- ");
+        
+        assert_eq!(core.content.to_string(), "- abc\n- def\n");
+        // Check cursors moved
+        assert_eq!(core.selections.len(), 2);
+        // Original 0 -> 2
+        // Original 4 -> 4 + 2 (first) + 2 (second) = 8
+        // Let's check ranges
+        core.selections.sort_by_key(|s| s.head);
+        assert_eq!(core.selections[0].head, 2);
+        assert_eq!(core.selections[1].head, 8);
+    }
+
+    #[test]
+    fn test_replace_selections_with_per_cursor_text() {
+        let mut core = EditorCore::from_text("a\n    b\n");
+        core.selections = vec![Selection::new(1, 1), Selection::new(7, 7)];
+
+        // Each cursor gets the indentation of its own line, as one undo step
+        core.replace_selections_with(|content, range| {
+            let line = content.byte_to_line(range.start);
+            let indent: String = content.line(line).chars().take_while(|c| *c == ' ').collect();
+            format!("\n{}", indent)
+        });
+        assert_eq!(core.content.to_string(), "a\n\n    b\n    \n");
+
+        core.undo();
+        assert_eq!(core.content.to_string(), "a\n    b\n");
+    }
+
+    #[test]
+    fn test_multi_cursor_delete() {
+        let mut core = EditorCore::new();
+        core.content = Rope::from("abc1\ndef1\n");
+        
+        // Cursors at '1's (indices 3 and 8)
+        // "abc1" -> 0,1,2,3(1),4(\n)
+        // "def1" -> 5,6,7,8(1),9(\n)
+        core.selections = vec![
+            Selection::new(3, 4), // Select '1'
+            Selection::new(8, 9), // Select '1'
+        ];
+        
+        core.delete_selection();
+        
+        assert_eq!(core.content.to_string(), "abc\ndef\n");
+        
+        // Check cursors
+        // First selection: 3..4 deleted. Cursor should be at 3.
+        // Second selection: 8..9 deleted. 
+        // Index 8 shifted by -1 (first deletion) -> 7.
+        // Then deleted -> cursor at 7.
+        // So cursors at 3 and 7.
+        core.selections.sort_by_key(|s| s.head);
+        assert_eq!(core.selections.len(), 2);
+        assert_eq!(core.selections[0].head, 3);
+        assert_eq!(core.selections[1].head, 7);
+    }
+
+    #[test]
+    fn test_select_all() {
+        let mut core = EditorCore::new();
+        core.content = Rope::from("abc\ndef");
+        core.select_all();
+        assert_eq!(core.selections.len(), 1);
+        assert_eq!(core.selections[0].range(), 0..core.content.len_bytes());
+    }
+
+    #[test]
+    fn test_utf16_conversions_and_ime_crash_simulation() {
+        let mut core = EditorCore::new();
+        // "Hello"
+        core.content = Rope::from("Hello");
+        
+        // Test basic conversion
+        let range_utf16 = 0..5;
+        let range = core.range_from_utf16(&range_utf16);
+        assert_eq!(range, 0..5);
+        
+        let range_utf16_back = core.range_to_utf16(&range);
+        assert_eq!(range_utf16_back, 0..5);
+        
+        // Test Chinese (3 bytes per char, 1 UTF-16 unit)
+        // "你好" -> 6 bytes, 2 chars, 2 UTF-16 units
+        core.content = Rope::from("你好");
+        let range_utf16 = 0..2;
+        let range = core.range_from_utf16(&range_utf16);
+        assert_eq!(range, 0..6);
+        
+        let range_utf16_back = core.range_to_utf16(&range);
+        assert_eq!(range_utf16_back, 0..2);
+        
+        // Test partial Chinese char (should not happen normally but check safety)
+        // 1 UTF-16 unit -> 1 char -> 3 bytes
+        let range_utf16 = 0..1;
+        let range = core.range_from_utf16(&range_utf16);
+        assert_eq!(range, 0..3);
+        
+        // Test Emoji (4 bytes, 2 UTF-16 units)
+        // "👋" -> \u{1F44B} -> 4 bytes. UTF-16: 0xD83D 0xDC4B (2 units)
+        core.content = Rope::from("👋");
+        assert_eq!(core.content.len_bytes(), 4);
+        assert_eq!(core.content.len_utf16_cu(), 2);
+        
+        let range_utf16 = 0..2;
+        let range = core.range_from_utf16(&range_utf16);
+        assert_eq!(range, 0..4);
+        
+        // Simulation of IME crash
+        // 1. User types "z"
+        core.content = Rope::from("");
+        core.replace_range(0..0, "z");
+        core.marked_range = Some(0..1); // "z"
+        
+        // 2. User types "h"
+        // Replace marked range "z" (0..1) with "zh"
+        let range = core.marked_range.clone().unwrap();
+        core.replace_range(range.clone(), "zh");
+        // Update marked range
+        let new_end = range.start + 2; // "zh".len()
+        core.marked_range = Some(range.start..new_end);
+        
+        // 3. User selects "中" (3 bytes)
+        // Replace marked range "zh" (0..2) with "中"
+        let range = core.marked_range.clone().unwrap();
+        // range is 0..2. content is "zh" (2 bytes).
+        // replace_range(0..2, "中")
+        core.replace_range(range, "中");
+        // marked_range cleared by replace_range
+        assert!(core.marked_range.is_none());
+        assert_eq!(core.content.to_string(), "中");
+        
+        // Test Out of Bounds
+        core.content = Rope::from("a");
+        // range_utf16 out of bounds
+        let range_utf16 = 0..100;
+        let range = core.range_from_utf16(&range_utf16);
+        // Should clamp to 0..1 (byte range)
+        assert_eq!(range, 0..1);
+        
+        // range_utf16 start out of bounds
+        let range_utf16 = 50..100;
+        let range = core.range_from_utf16(&range_utf16);
+        // Should clamp to 1..1 (empty at end)
+        assert_eq!(range, 1..1);
+    }
+
+    #[test]
+    fn test_line_col_helpers() {
+        let core = EditorCore::from_text("ab\r\n中文\nx");
+        assert_eq!(core.line_col_for_offset(0), (0, 0));
+        assert_eq!(core.line_col_for_offset(4), (1, 0));
+        assert_eq!(core.line_col_for_offset(7), (1, 3));
+        assert_eq!(core.line_col_for_offset(100), (2, 1));
+
+        // column is clamped before CRLF
+        assert_eq!(core.offset_for_line_col(0, 10), 2);
+        // column inside a multi-byte char snaps back to its start
+        assert_eq!(core.offset_for_line_col(1, 4), 7);
+        assert_eq!(core.offset_for_line_col(1, 10), 10);
+        assert_eq!(core.offset_for_line_col(9, 0), core.content.len_bytes());
+    }
+
+    /// Small deterministic xorshift generator so the randomized tests need no
+    /// extra dependencies and failures are reproducible from the seed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n.max(1) as u64) as usize
+        }
+
+        /// A random char boundary of `text`.
+        fn boundary(&mut self, text: &str) -> usize {
+            let boundaries: Vec<usize> = text
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(text.len()))
+                .collect();
+            boundaries[self.below(boundaries.len())]
+        }
+
+        fn text(&mut self) -> String {
+            const PIECES: [&str; 8] = ["a", "bc", "\n", "\r\n", "中", "文字", "👋", ""];
+            (0..self.below(4)).map(|_| PIECES[self.below(PIECES.len())]).collect()
+        }
+    }
+
+    fn random_edit(rng: &mut Rng, model: &str) -> (Range<usize>, String) {
+        let a = rng.boundary(model);
+        let b = rng.boundary(model);
+        (a.min(b)..a.max(b), rng.text())
+    }
+
+    #[test]
+    fn test_random_edits_match_string_model() {
+        for seed in 1..200u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let mut core = EditorCore::new();
+            let mut model = String::new();
+
+            for _ in 0..40 {
+                let (range, text) = random_edit(&mut rng, &model);
+                core.replace_range(range.clone(), &text);
+                model.replace_range(range.clone(), &text);
+
+                assert_eq!(core.content.to_string(), model, "seed {}", seed);
+                assert_eq!(core.primary_selection().head, range.start + text.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_edits_undo_redo_roundtrip() {
+        for seed in 1..200u64 {
+            let mut rng = Rng(seed.wrapping_mul(0xD1B5_4A32_D192_ED03));
+            let initial: String = (0..6).map(|_| rng.text()).collect();
+            let mut core = EditorCore::from_text(&initial);
+            let mut snapshots = vec![initial.clone()];
+
+            for _ in 0..20 {
+                let current = core.content.to_string();
+                if rng.below(3) == 0 {
+                    // multi-cursor typing over random disjoint selections
+                    let mut points: Vec<usize> = (0..4).map(|_| rng.boundary(&current)).collect();
+                    points.sort();
+                    core.selections = points
+                        .chunks(2)
+                        .map(|p| Selection::new(p[0], p[1]))
+                        .collect();
+                    let text = rng.text();
+                    core.insert_text(&text);
+                } else {
+                    let (range, text) = random_edit(&mut rng, &current);
+                    core.replace_range(range, &text);
+                }
+                let after = core.content.to_string();
+                if after != *snapshots.last().unwrap() {
+                    snapshots.push(after);
+                }
+            }
+
+            // Each undo step lands on an earlier snapshot, and undoing
+            // everything restores the initial text.
+            while core.history.can_undo() {
+                core.undo();
+                assert!(snapshots.contains(&core.content.to_string()), "seed {}", seed);
+            }
+            assert_eq!(core.content.to_string(), initial, "seed {}", seed);
+
+            while core.history.can_redo() {
+                core.redo();
+            }
+            assert_eq!(&core.content.to_string(), snapshots.last().unwrap(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_map_range_after_edits_and_undo() {
+        let mut core = EditorCore::from_text("let a = 1;\nlet b = 2;\n");
+        let stamped = core.version();
+        // A stale result pointing at `b`, computed before the edits below
+        let b = 15..16;
+
+        core.replace_range(0..0, "// hi\n");
+        assert_eq!(core.map_range(stamped, b.clone()), Some(21..22));
+        core.replace_range(4..6, "");
+        assert_eq!(core.map_range(stamped, b.clone()), Some(19..20));
+        core.undo();
+        assert_eq!(core.map_range(stamped, b.clone()), Some(21..22));
+        assert_eq!(&core.content.to_string()[21..22], "b");
+
+        let before_rename = core.version();
+        core.replace_range(21..22, "bb");
+        assert_eq!(core.map_range(before_rename, 21..22), None);
+        assert_ne!(core.version(), before_rename);
+
+        core.set_text("reloaded");
+        assert_eq!(core.map_range(stamped, b), None);
+        assert_eq!(core.map_range(core.version(), 0..8), Some(0..8));
+    }
+
+    #[test]
+    fn test_changes_replay_onto_a_copy() {
+        // Line breaks are "\n", "\r\n" and a lone "\r", as in ropey
+        fn position(text: &str, offset: usize) -> (usize, usize) {
+            let (mut line, mut line_start) = (0, 0);
+            for (i, ch) in text[..offset].char_indices() {
+                if ch == '\n' || (ch == '\r' && !text[i + 1..].starts_with('\n')) {
+                    line += 1;
+                    line_start = i + 1;
+                }
+            }
+            (line, text[line_start..offset].encode_utf16().count())
+        }
+
+        for seed in 1..100u64 {
+            let mut rng = Rng(seed.wrapping_mul(0xA24B_AED4_963E_E407));
+            let initial: String = (0..6).map(|_| rng.text()).collect();
+            let mut core = EditorCore::from_text(&initial);
+            let mut copy = initial.clone();
+
+            for _ in 0..20 {
+                let current = core.content.to_string();
+                match rng.below(4) {
+                    0 => core.undo(),
+                    1 => {
+                        let mut points: Vec<usize> = (0..4).map(|_| rng.boundary(&current)).collect();
+                        points.sort();
+                        core.selections = points.chunks(2).map(|p| Selection::new(p[0], p[1])).collect();
+                        core.insert_text(&rng.text());
+                    }
+                    _ => {
+                        let (range, text) = random_edit(&mut rng, &current);
+                        core.replace_range(range, &text);
+                    }
+                }
+                // Each change is addressed against the text just before it
+                for change in core.take_changes().unwrap() {
+                    assert_eq!(change.start, position(&copy, change.range.start), "seed {}", seed);
+                    assert_eq!(change.end, position(&copy, change.range.end), "seed {}", seed);
+                    copy.replace_range(change.range, &change.text);
+                }
+                assert_eq!(copy, core.content.to_string(), "seed {}", seed);
+            }
+        }
+
+        let mut core = EditorCore::from_text("a");
+        core.replace_range(1..1, "b");
+        core.set_text("reloaded");
+        assert_eq!(core.take_changes(), None);
+        assert_eq!(core.take_changes(), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_random_utf16_roundtrip() {
+        let mut rng = Rng(0x5EED);
+        for _ in 0..200 {
+            let text: String = (0..8).map(|_| rng.text()).collect();
+            let core = EditorCore::from_text(&text);
+            let a = rng.boundary(&text);
+            let b = rng.boundary(&text);
+            let range = a.min(b)..a.max(b);
+
+            let utf16 = core.range_to_utf16(&range);
+            assert_eq!(core.range_from_utf16(&utf16), range);
+            assert_eq!(utf16.end, text[..range.end].encode_utf16().count());
+        }
+    }
+}
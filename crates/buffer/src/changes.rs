@@ -0,0 +1,59 @@
+use std::ops::Range;
+
+/// Beyond this many undelivered changes the log gives up and asks for a full
+/// resync instead of growing without bound.
+const MAX_PENDING_CHANGES: usize = 4096;
+
+/// One primitive edit as it looked just before it was applied: `range` of the
+/// text at that moment was replaced by `text`. `start` and `end` are the
+/// range's zero-based `(line, column)` with columns in UTF-16 code units, the
+/// way the highlighter and language servers address text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentChange {
+    pub range: Range<usize>,
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub text: String,
+}
+
+impl ContentChange {
+    /// Whether the edit added or removed a line break.
+    pub fn changes_lines(&self) -> bool {
+        self.start.0 != self.end.0 || self.text.contains(['\n', '\r'])
+    }
+}
+
+/// Changes made since consumers last caught up, so they can follow the text
+/// edit by edit instead of copying all of it after every keystroke.
+#[derive(Debug, Default)]
+pub struct ChangeLog {
+    changes: Vec<ContentChange>,
+    /// Set when changes were dropped or the text was replaced wholesale.
+    resync: bool,
+}
+
+impl ChangeLog {
+    pub fn push(&mut self, change: ContentChange) {
+        if self.resync {
+            return;
+        }
+        if self.changes.len() == MAX_PENDING_CHANGES {
+            self.reset();
+            return;
+        }
+        self.changes.push(change);
+    }
+
+    /// Drops the pending changes; consumers have to reload the whole text.
+    pub fn reset(&mut self) {
+        self.changes.clear();
+        self.resync = true;
+    }
+
+    /// The changes since the last call, in the order they were applied, or
+    /// `None` when consumers must reload the whole text instead.
+    pub fn take(&mut self) -> Option<Vec<ContentChange>> {
+        let changes = std::mem::take(&mut self.changes);
+        (!std::mem::take(&mut self.resync)).then_some(changes)
+    }
+}
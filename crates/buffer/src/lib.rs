@@ -0,0 +1,21 @@
+//! 编辑器的无界面文本缓冲区。
+//!
+//! `EditorCore` 把 `Rope` 文本、多光标选区、撤销历史以及 UTF-8 / UTF-16
+//! 偏移换算放在一起，不依赖 gpui，可以在插件宿主、测试或命令行工具里直接使用。
+//! 所有偏移都是 UTF-8 字节偏移，除非方法名里带 `utf16`。
+
+pub mod bom;
+mod buffer;
+pub mod changes;
+pub mod line_ending;
+mod selection;
+pub mod undo;
+mod version;
+
+pub use bom::{strip_bom, with_bom};
+pub use buffer::EditorCore;
+pub use changes::ContentChange;
+pub use line_ending::LineEnding;
+pub use ropey::Rope;
+pub use selection::Selection;
+pub use undo::{EditOperation, UndoHistory};
@@ -0,0 +1,134 @@
+//! 换行符的识别与转换。
+//!
+//! 缓冲区保留文件原来的换行符；编辑器记下文件中占多数的一种，回车和粘贴
+//! 插入的换行都按它写入，同一个文件里就不会越改越混用 LF 和 CRLF。
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use ropey::Rope;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    /// 状态栏中显示的名称
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+
+    /// 文本中占多数的换行符；没有换行或两种一样多时为 LF。
+    pub fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            Self::Crlf
+        } else {
+            Self::Lf
+        }
+    }
+
+    /// 把文本中的换行（LF、CRLF 和单独的 CR）统一为这一种；已经一致时不复制。
+    pub fn normalize(self, text: &str) -> Cow<'_, str> {
+        let edits = self.edits(text.chars());
+        if edits.is_empty() {
+            return Cow::Borrowed(text);
+        }
+        let mut normalized = String::with_capacity(text.len() + edits.len());
+        let mut copied = 0;
+        for range in edits {
+            normalized.push_str(&text[copied..range.start]);
+            normalized.push_str(self.as_str());
+            copied = range.end;
+        }
+        normalized.push_str(&text[copied..]);
+        Cow::Owned(normalized)
+    }
+
+    /// 把 `content` 中的换行统一为这一种所需的编辑，范围为原文中的字节偏移；
+    /// 已经一致时为空。
+    pub fn conversion_edits(self, content: &Rope) -> Vec<(Range<usize>, String)> {
+        self.edits(content.chars())
+            .into_iter()
+            .map(|range| (range, self.as_str().to_string()))
+            .collect()
+    }
+
+    /// 与这一种不同的换行符所在的字节范围
+    fn edits(self, chars: impl Iterator<Item = char>) -> Vec<Range<usize>> {
+        let mut edits = Vec::new();
+        let mut offset = 0;
+        let mut chars = chars.peekable();
+        while let Some(c) = chars.next() {
+            let found = match c {
+                '\r' if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                    "\r\n"
+                }
+                '\r' => "\r",
+                '\n' => "\n",
+                _ => {
+                    offset += c.len_utf8();
+                    continue;
+                }
+            };
+            if found != self.as_str() {
+                edits.push(offset..offset + found.len());
+            }
+            offset += found.len();
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EditorCore;
+
+    #[test]
+    fn test_detect_dominant_line_ending() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\nb\r\nc\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("单行"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_normalize_converts_every_kind_of_break() {
+        let mixed = "甲\r\n乙\n丙\r丁";
+        assert_eq!(LineEnding::Lf.normalize(mixed), "甲\n乙\n丙\n丁");
+        assert_eq!(LineEnding::Crlf.normalize(mixed), "甲\r\n乙\r\n丙\r\n丁");
+        assert!(matches!(LineEnding::Crlf.normalize("a\r\nb"), Cow::Borrowed(_)));
+        assert!(matches!(LineEnding::Lf.normalize("a\nb\n"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_conversion_is_one_undo_step() {
+        let mut core = EditorCore::from_text("甲\n乙\r\n丙\n");
+        let edits = LineEnding::Crlf.conversion_edits(&core.content);
+        assert_eq!(edits.len(), 2);
+        core.apply_edits(edits);
+        assert_eq!(core.content.to_string(), "甲\r\n乙\r\n丙\r\n");
+        assert!(LineEnding::Crlf.conversion_edits(&core.content).is_empty());
+
+        core.apply_edits(LineEnding::Lf.conversion_edits(&core.content));
+        assert_eq!(core.content.to_string(), "甲\n乙\n丙\n");
+        core.undo();
+        assert_eq!(core.content.to_string(), "甲\r\n乙\r\n丙\r\n");
+    }
+}
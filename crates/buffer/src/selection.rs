@@ -0,0 +1,32 @@
+use std::ops::Range;
+
+/// A cursor or selection in byte offsets.
+///
+/// `anchor` is where the selection started and `head` is where the cursor is;
+/// they are equal for a plain cursor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Selection {
+    pub anchor: usize,
+    pub head: usize,
+    /// Column to aim for when moving vertically across shorter lines.
+    pub preferred_column: Option<usize>,
+}
+
+impl Selection {
+    pub fn new(anchor: usize, head: usize) -> Self {
+        Self { anchor, head, preferred_column: None }
+    }
+
+    /// The selected range with `start <= end`, regardless of direction.
+    pub fn range(&self) -> Range<usize> {
+        if self.anchor <= self.head {
+            self.anchor..self.head
+        } else {
+            self.head..self.anchor
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+}
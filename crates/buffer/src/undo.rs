@@ -0,0 +1,225 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::selection::Selection;
+
+/// How long after the previous keystroke a new one still joins its undo step.
+pub const COALESCE_WINDOW: Duration = Duration::from_secs(1);
+
+/// A single primitive edit, recorded with the text it inserted or removed so it
+/// can be inverted.
+#[derive(Clone, Debug)]
+pub enum EditOperation {
+    Insert { range: Range<usize>, text: String },
+    Delete { range: Range<usize>, text: String },
+}
+
+impl EditOperation {
+    /// The operation that undoes this one.
+    pub fn inverse(&self) -> Self {
+        match self {
+            EditOperation::Insert { range, text } => EditOperation::Delete {
+                range: range.clone(),
+                text: text.clone(),
+            },
+            EditOperation::Delete { range, text } => EditOperation::Insert {
+                range: range.clone(),
+                text: text.clone(),
+            },
+        }
+    }
+}
+
+/// What a group of edits did. Consecutive typing or deleting coalesces into
+/// one undo step; anything else always gets its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditKind {
+    /// Every cursor inserted one character that isn't a line break.
+    Typing,
+    /// Every cursor removed one character that isn't a line break.
+    Deleting,
+    Other,
+}
+
+impl EditKind {
+    /// Classifies a group by the shape of its operations.
+    pub fn of(ops: &[EditOperation]) -> Self {
+        fn single_char(text: &str) -> bool {
+            let mut chars = text.chars();
+            matches!((chars.next(), chars.next()), (Some(ch), None) if ch != '\n' && ch != '\r')
+        }
+        if ops.is_empty() {
+            Self::Other
+        } else if ops.iter().all(|op| matches!(op, EditOperation::Insert { text, .. } if single_char(text))) {
+            Self::Typing
+        } else if ops.iter().all(|op| matches!(op, EditOperation::Delete { text, .. } if single_char(text))) {
+            Self::Deleting
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// One undo step: its edits and the selections just before and after them.
+struct UndoGroup {
+    ops: Vec<EditOperation>,
+    selections_before: Vec<Selection>,
+    selections_after: Vec<Selection>,
+    kind: EditKind,
+    /// Line of the primary cursor after the edits.
+    line: usize,
+    last_edit: Instant,
+}
+
+impl UndoGroup {
+    /// Whether `next`, made right after this group, continues it.
+    fn continued_by(&self, next: &UndoGroup, window: Duration) -> bool {
+        next.kind != EditKind::Other
+            && next.kind == self.kind
+            && next.line == self.line
+            && same_cursors(&next.selections_before, &self.selections_after)
+            && next.last_edit.saturating_duration_since(self.last_edit) < window
+    }
+}
+
+/// Compares positions only; the preferred column doesn't matter for undo.
+fn same_cursors(a: &[Selection], b: &[Selection]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.anchor == b.anchor && a.head == b.head)
+}
+
+struct Transaction {
+    ops: Vec<EditOperation>,
+    selections_before: Vec<Selection>,
+}
+
+/// Undo/redo stacks of edit groups.
+///
+/// Edits pushed between `begin_transaction` and `end_transaction` are undone
+/// and redone together. A typing or deleting group made within
+/// [`COALESCE_WINDOW`] of the previous one, on the same line and from where
+/// its cursors ended, joins it unless [`break_group`](Self::break_group) was
+/// called in between.
+pub struct UndoHistory {
+    undo_stack: Vec<UndoGroup>,
+    redo_stack: Vec<UndoGroup>,
+    current_transaction: Option<Transaction>,
+    /// The next group starts a new undo step.
+    sealed: bool,
+    coalesce_window: Duration,
+}
+
+impl UndoHistory {
+    pub fn new() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            current_transaction: None,
+            sealed: false,
+            coalesce_window: COALESCE_WINDOW,
+        }
+    }
+
+    /// Changes how long consecutive keystrokes keep joining one undo step.
+    pub fn set_coalesce_window(&mut self, window: Duration) {
+        self.coalesce_window = window;
+    }
+
+    /// Records an edit. Outside a transaction it becomes its own undo step,
+    /// without selections to restore.
+    pub fn push(&mut self, op: EditOperation) {
+        if let Some(transaction) = &mut self.current_transaction {
+            transaction.ops.push(op);
+        } else {
+            self.commit(vec![op], Vec::new(), Vec::new(), 0);
+            self.sealed = true;
+        }
+        self.redo_stack.clear();
+    }
+
+    /// Starts a group; `selections` are restored when it is undone.
+    pub fn begin_transaction(&mut self, selections: &[Selection]) {
+        if self.current_transaction.is_none() {
+            self.current_transaction = Some(Transaction { ops: Vec::new(), selections_before: selections.to_vec() });
+        }
+    }
+
+    /// Ends the group; `selections` are restored when it is redone and `line`
+    /// is where the primary cursor ended up.
+    pub fn end_transaction(&mut self, selections: &[Selection], line: usize) {
+        if let Some(transaction) = self.current_transaction.take() {
+            if !transaction.ops.is_empty() {
+                self.commit(transaction.ops, transaction.selections_before, selections.to_vec(), line);
+            }
+        }
+    }
+
+    fn commit(&mut self, ops: Vec<EditOperation>, selections_before: Vec<Selection>, selections_after: Vec<Selection>, line: usize) {
+        let group = UndoGroup {
+            kind: EditKind::of(&ops),
+            ops,
+            selections_before,
+            selections_after,
+            line,
+            last_edit: Instant::now(),
+        };
+        let sealed = std::mem::take(&mut self.sealed);
+        let window = self.coalesce_window;
+        match self.undo_stack.last_mut() {
+            Some(last) if !sealed && last.continued_by(&group, window) => {
+                last.ops.extend(group.ops);
+                last.selections_after = group.selections_after;
+                last.last_edit = group.last_edit;
+            }
+            _ => self.undo_stack.push(group),
+        }
+    }
+
+    /// Makes the next edit start a new undo step, e.g. after Enter, a paste or
+    /// an edit made by a command.
+    pub fn break_group(&mut self) {
+        self.sealed = true;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Number of groups that can currently be undone.
+    pub fn undo_depth(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Pops the last group and returns the operations that revert it, in the
+    /// order they must be applied, with the selections from before it.
+    pub fn undo(&mut self) -> Option<(Vec<EditOperation>, Vec<Selection>)> {
+        let group = self.undo_stack.pop()?;
+        let inverted = group.ops.iter().rev().map(|op| op.inverse()).collect();
+        let selections = group.selections_before.clone();
+        self.redo_stack.push(group);
+        self.sealed = true;
+        Some((inverted, selections))
+    }
+
+    /// Pops the last undone group and returns the operations that re-apply it,
+    /// with the selections from after it.
+    pub fn redo(&mut self) -> Option<(Vec<EditOperation>, Vec<Selection>)> {
+        let group = self.redo_stack.pop()?;
+        // Re-apply in the original order; a replacement is a delete
+        // followed by an insert at the same offset.
+        let ops = group.ops.clone();
+        let selections = group.selections_after.clone();
+        self.undo_stack.push(group);
+        self.sealed = true;
+        Some((ops, selections))
+    }
+}
+
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,105 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// How many recent edits are kept for mapping results computed against older
+/// versions. Anything older is simply rejected as stale.
+const MAX_LOGGED_EDITS: usize = 256;
+
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(1);
+
+/// A fresh document version. Versions are unique across all buffers, so a
+/// result stamped by one buffer never matches another that replaced it.
+pub fn next_version() -> u64 {
+    NEXT_VERSION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// One primitive edit: `range` of the previous version was replaced by
+/// `new_len` bytes, producing `version`.
+#[derive(Clone, Debug)]
+struct LoggedEdit {
+    version: u64,
+    range: Range<usize>,
+    new_len: usize,
+}
+
+/// The most recent edits of a buffer, used to carry byte ranges computed
+/// against an older version (by a background task, say) over to the current
+/// text.
+#[derive(Clone, Debug)]
+pub struct EditLog {
+    /// The version before the oldest logged edit.
+    base: u64,
+    edits: Vec<LoggedEdit>,
+}
+
+impl EditLog {
+    pub fn new(version: u64) -> Self {
+        Self { base: version, edits: Vec::new() }
+    }
+
+    /// Records that `range` was replaced by `new_len` bytes, producing `version`.
+    pub fn push(&mut self, version: u64, range: Range<usize>, new_len: usize) {
+        if self.edits.len() == MAX_LOGGED_EDITS {
+            self.base = self.edits.remove(0).version;
+        }
+        self.edits.push(LoggedEdit { version, range, new_len });
+    }
+
+    /// Forgets all edits; only results stamped with `version` stay valid.
+    pub fn reset(&mut self, version: u64) {
+        self.base = version;
+        self.edits.clear();
+    }
+
+    /// Maps `range`, computed against version `since`, to the current text.
+    /// Returns `None` when `since` is too old or unknown, or when an edit
+    /// touched the inside of the range, since there is no faithful mapping
+    /// then.
+    pub fn map_range(&self, since: u64, range: Range<usize>) -> Option<Range<usize>> {
+        let first = if since == self.base {
+            0
+        } else {
+            self.edits.iter().position(|e| e.version == since)? + 1
+        };
+        let mut range = range;
+        for edit in &self.edits[first..] {
+            let removed = edit.range.end - edit.range.start;
+            // Text inserted right at the end of the range stays after it
+            if range.end <= edit.range.start {
+                continue;
+            }
+            if range.start >= edit.range.end {
+                range = range.start - removed + edit.new_len..range.end - removed + edit.new_len;
+                continue;
+            }
+            return None;
+        }
+        Some(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_range_through_edits() {
+        let mut log = EditLog::new(10);
+        // "hello world": insert "big " before "world", then delete "hello "
+        log.push(11, 6..6, 4);
+        log.push(12, 0..6, 0);
+
+        assert_eq!(log.map_range(10, 6..11), Some(4..9));
+        assert_eq!(log.map_range(11, 10..15), Some(4..9));
+        assert_eq!(log.map_range(12, 4..9), Some(4..9));
+        // The range covering "hello" was deleted
+        assert_eq!(log.map_range(10, 0..5), None);
+        // An insertion inside the range
+        assert_eq!(log.map_range(10, 4..8), None);
+        assert_eq!(log.map_range(9, 0..1), None);
+
+        log.reset(13);
+        assert_eq!(log.map_range(12, 4..9), None);
+        assert_eq!(log.map_range(13, 4..9), Some(4..9));
+    }
+}
@@ -1,10 +1,32 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// 宿主支持的插件接口版本；动态库插件的清单和 [`PluginDeclaration`] 都要与它一致。
+/// 版本 2 加入了 [`EditorHost`] 和 [`Plugin::execute_command`]，版本 3 加入了 [`PluginEvent`] 订阅，
+/// 版本 4 为 [`Plugin::execute_command`] 加入了命令参数
+pub const API_VERSION: u32 = 4;
+
+/// 文件树右键菜单，[`Contributions::menus`] 中的位置
+pub const FILE_TREE_CONTEXT_MENU: &str = "fileTree/context";
+
+/// 动态库中导出 [`PluginDeclaration`] 的符号名，由 [`declare_plugin!`] 生成
+pub const DECLARATION_SYMBOL: &[u8] = b"tiecode_plugin_declaration\0";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PluginManifest {
     pub id: String,
     pub name: String,
     pub version: String,
+    /// 插件编写时的接口版本，提供 `entry` 时必须填写
+    #[serde(default)]
+    pub api_version: Option<u32>,
+    /// 实现 [`Plugin`] 的动态库，相对插件目录；激活时加载
+    #[serde(default)]
+    pub entry: Option<String>,
     #[serde(default)]
     pub activation_events: Vec<String>,
     #[serde(default)]
@@ -17,6 +39,30 @@ pub struct Contributions {
     pub commands: Vec<CommandContribution>,
     #[serde(default)]
     pub keybindings: Vec<KeybindingContribution>,
+    #[serde(default)]
+    pub icon_themes: Vec<IconThemeContribution>,
+    #[serde(default)]
+    pub handlers: Vec<CommandHandlerContribution>,
+    #[serde(default)]
+    pub enter_rules: Vec<EnterRuleContribution>,
+    #[serde(default)]
+    pub status_items: Vec<StatusItemContribution>,
+    #[serde(default)]
+    pub grammars: Vec<GrammarContribution>,
+    /// 菜单位置到菜单项，目前支持 [`FILE_TREE_CONTEXT_MENU`]
+    #[serde(default)]
+    pub menus: BTreeMap<String, Vec<MenuContribution>>,
+}
+
+/// 菜单项，例如 `{ "command": "demo.compress", "title": "压缩", "when": "isDir" }`。
+/// `when` 为 `isDir`、`isFile` 或文件名的通配符（如 `*.json`），不写时总是显示；
+/// 文件树菜单执行命令时以点击的路径为参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MenuContribution {
+    pub command: String,
+    pub title: String,
+    #[serde(default)]
+    pub when: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +70,9 @@ pub struct CommandContribution {
     pub command: String,
     pub title: String,
     pub category: Option<String>,
+    /// 工具栏按钮的图标，SVG 文件相对插件目录
+    #[serde(default)]
+    pub icon: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,7 +82,214 @@ pub struct KeybindingContribution {
     pub when: Option<String>,
 }
 
-pub trait Plugin {
+/// 文件图标主题；`path` 为主题 JSON 文件，相对插件目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconThemeContribution {
+    pub id: String,
+    pub label: String,
+    pub path: String,
+}
+
+/// 回车补全块结构的规则，`language` 为文件扩展名（不含点），例如
+/// `{ "language": "lua", "pattern": "^\\s*function\\b", "insert": "\n\t$0\nend" }`；
+/// `insert` 中的 `$0` 为光标位置，下一行已含有 `closer`（默认为最后一行）时不插入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnterRuleContribution {
+    pub language: String,
+    pub pattern: String,
+    pub insert: String,
+    #[serde(default)]
+    pub closer: Option<String>,
+}
+
+/// sweetline 语法文件，`path` 相对插件目录；`extensions` 并入语法的 `fileExtensions`，
+/// 例如 `{ "path": "grammars/lua.json", "extensions": [".lua"] }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrammarContribution {
+    pub path: String,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+/// 状态栏项在哪一侧
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StatusAlignment {
+    #[default]
+    Left,
+    Right,
+}
+
+/// 状态栏项，例如
+/// `{ "id": "demo.status", "alignment": "right", "priority": 10, "text": "Demo", "command": "demo.run" }`；
+/// 同一侧优先级高的靠左，点击时执行 `command`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusItemContribution {
+    pub id: String,
+    #[serde(default)]
+    pub alignment: StatusAlignment,
+    #[serde(default)]
+    pub priority: i32,
+    pub text: String,
+    #[serde(default)]
+    pub tooltip: Option<String>,
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+/// 插件命令的处理方式，例如
+/// `{ "command": "demo.build", "type": "process", "program": "make", "args": ["all"] }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandHandlerContribution {
+    pub command: String,
+    #[serde(flatten)]
+    pub handler: CommandHandler,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum CommandHandler {
+    /// 执行编辑器的内置命令，例如 `core.save`
+    Action { action: String },
+    /// 在插件目录中启动外部程序，命令的参数加在 `args` 之后
+    Process {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// 交给插件动态库的 [`Plugin::execute_command`]，清单须提供 `entry`
+    Native,
+}
+
+/// 宿主提供的编辑器接口，作用于当前标签；偏移为文本中的字节位置，须落在字符边界上。
+/// 可以在任何线程调用：调用交给编辑器的界面线程执行，并等待结果
+pub trait EditorHost: Send + Sync {
+    fn get_text(&self) -> anyhow::Result<String>;
+    /// 替换一段文本，作为一步撤销；之后光标在插入的文本之后
+    fn replace_range(&self, range: Range<usize>, text: &str) -> anyhow::Result<()>;
+    fn cursor_offset(&self) -> anyhow::Result<usize>;
+    /// 所有选区，每项为 (锚点, 光标)，按位置排列
+    fn selections(&self) -> anyhow::Result<Vec<(usize, usize)>>;
+    fn set_selections(&self, selections: Vec<(usize, usize)>) -> anyhow::Result<()>;
+    /// 当前标签的文件，未保存过的新文件为 None
+    fn active_file_path(&self) -> anyhow::Result<Option<PathBuf>>;
+}
+
+/// 编辑器发布给插件的事件
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginEvent {
+    FileOpened(PathBuf),
+    FileSaved(PathBuf),
+    /// 缓冲区内容变化；`version` 随编辑增大，连续的编辑可能合并为一次
+    BufferChanged { path: PathBuf, version: u64 },
+    /// 打开了文件夹
+    WorkspaceOpened(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginEventKind {
+    FileOpened,
+    FileSaved,
+    BufferChanged,
+    WorkspaceOpened,
+}
+
+impl PluginEvent {
+    pub fn kind(&self) -> PluginEventKind {
+        match self {
+            PluginEvent::FileOpened(_) => PluginEventKind::FileOpened,
+            PluginEvent::FileSaved(_) => PluginEventKind::FileSaved,
+            PluginEvent::BufferChanged { .. } => PluginEventKind::BufferChanged,
+            PluginEvent::WorkspaceOpened(_) => PluginEventKind::WorkspaceOpened,
+        }
+    }
+}
+
+pub trait Plugin: Send + Sync {
     fn activate(&self) -> anyhow::Result<()>;
     fn deactivate(&self) -> anyhow::Result<()>;
+
+    /// 执行处理方式为 `native` 的命令。宿主在后台线程调用，可以通过 `editor` 读写当前编辑器；
+    /// `argument` 为调用方传入的参数，例如从文件树菜单执行时为点击的路径
+    fn execute_command(
+        &self,
+        command: &str,
+        _argument: Option<&str>,
+        _editor: Arc<dyn EditorHost>,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("插件没有实现命令 {}", command)
+    }
+
+    /// 激活后订阅的事件，停用时自动取消
+    fn subscriptions(&self) -> Vec<PluginEventKind> {
+        Vec::new()
+    }
+
+    /// 收到订阅的事件。宿主在这个插件专用的后台线程上按发布顺序逐个调用，处理得慢不会卡住界面
+    fn on_event(&self, _event: &PluginEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// 动态库插件的入口。`create` 返回 `Box::into_raw(Box::new(Box::new(插件) as Box<dyn Plugin>))`，
+/// 所有权交给宿主，失败时返回空指针。插件与宿主须用同一版本的编译器构建
+#[repr(C)]
+pub struct PluginDeclaration {
+    pub api_version: u32,
+    pub create: unsafe extern "C" fn() -> *mut Box<dyn Plugin>,
+}
+
+/// 在插件一侧捕获 panic 并转为错误。动态库中的 panic 对宿主来说是外部异常，宿主无法捕获
+pub struct Guarded<P>(pub P);
+
+impl<P: Plugin> Plugin for Guarded<P> {
+    fn activate(&self) -> anyhow::Result<()> {
+        catch_unwind(AssertUnwindSafe(|| self.0.activate())).unwrap_or_else(|_| Err(anyhow::anyhow!("activate panicked")))
+    }
+
+    fn deactivate(&self) -> anyhow::Result<()> {
+        catch_unwind(AssertUnwindSafe(|| self.0.deactivate()))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("deactivate panicked")))
+    }
+
+    fn execute_command(
+        &self,
+        command: &str,
+        argument: Option<&str>,
+        editor: Arc<dyn EditorHost>,
+    ) -> anyhow::Result<()> {
+        catch_unwind(AssertUnwindSafe(|| self.0.execute_command(command, argument, editor)))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("execute_command panicked")))
+    }
+
+    fn subscriptions(&self) -> Vec<PluginEventKind> {
+        catch_unwind(AssertUnwindSafe(|| self.0.subscriptions())).unwrap_or_default()
+    }
+
+    fn on_event(&self, event: &PluginEvent) -> anyhow::Result<()> {
+        catch_unwind(AssertUnwindSafe(|| self.0.on_event(event)))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("on_event panicked")))
+    }
+}
+
+/// 在插件的 cdylib 中导出入口，例如 `declare_plugin!(DemoPlugin::default);`
+#[macro_export]
+macro_rules! declare_plugin {
+    ($constructor:path) => {
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static tiecode_plugin_declaration: $crate::PluginDeclaration = $crate::PluginDeclaration {
+            api_version: $crate::API_VERSION,
+            create: {
+                unsafe extern "C" fn create() -> *mut Box<dyn $crate::Plugin> {
+                    std::panic::catch_unwind(|| {
+                        let plugin: Box<dyn $crate::Plugin> = Box::new($crate::Guarded($constructor()));
+                        Box::into_raw(Box::new(plugin))
+                    })
+                    .unwrap_or(std::ptr::null_mut())
+                }
+                create
+            },
+        };
+    };
 }
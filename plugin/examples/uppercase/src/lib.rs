@@ -0,0 +1,74 @@
+//! 示例插件：命令 `uppercase.selection` 把当前编辑器中选中的文本转为大写，之后保持原来的选区。
+//! 构建后把动态库和 package.json 放进配置目录的 plugins/example.uppercase 中，
+//! 非 Windows 平台把清单中的 `entry` 改为对应的文件名（libtiecode_plugin_uppercase.so 等）
+
+use std::ops::Range;
+use std::sync::Arc;
+use tiecode_plugin_api::{declare_plugin, EditorHost, Plugin};
+
+#[derive(Default)]
+pub struct UppercasePlugin;
+
+impl Plugin for UppercasePlugin {
+    fn activate(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn deactivate(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn execute_command(&self, command: &str, _argument: Option<&str>, editor: Arc<dyn EditorHost>) -> anyhow::Result<()> {
+        if command != "uppercase.selection" {
+            anyhow::bail!("未知命令 {}", command);
+        }
+        let text = editor.get_text()?;
+        let selections = editor.selections()?;
+        let (edits, selections) = uppercase_selections(&text, &selections);
+        if edits.is_empty() {
+            return Ok(());
+        }
+        // 从后往前替换，前面的偏移不受影响
+        for (range, replacement) in edits.into_iter().rev() {
+            editor.replace_range(range, &replacement)?;
+        }
+        editor.set_selections(selections)
+    }
+}
+
+type Edit = (Range<usize>, String);
+
+/// 每个非空选区转为大写后的替换，以及替换后的选区；大写可能改变字节长度，之后的选区随之移动
+fn uppercase_selections(text: &str, selections: &[(usize, usize)]) -> (Vec<Edit>, Vec<(usize, usize)>) {
+    let mut edits = Vec::new();
+    let mut moved = Vec::new();
+    let mut shift = 0isize;
+    for &(anchor, head) in selections {
+        let range = anchor.min(head)..anchor.max(head);
+        let upper = text[range.clone()].to_uppercase();
+        let start = (range.start as isize + shift) as usize;
+        let end = start + upper.len();
+        shift += upper.len() as isize - range.len() as isize;
+        moved.push(if anchor <= head { (start, end) } else { (end, start) });
+        if upper != text[range.clone()] {
+            edits.push((range, upper));
+        }
+    }
+    (edits, moved)
+}
+
+declare_plugin!(UppercasePlugin::default);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uppercases_each_selection() {
+        let text = "ﬁx 变量 abc";
+        let (edits, selections) = uppercase_selections(text, &[(0, 4), (5, 11), (15, 12), (15, 15)]);
+        assert_eq!(edits, vec![(0..4, "FIX".to_string()), (12..15, "ABC".to_string())]);
+        // "ﬁ" 转为 "FI" 少了一个字节，之后的选区前移，方向保持不变
+        assert_eq!(selections, vec![(0, 3), (4, 10), (14, 11), (14, 14)]);
+    }
+}
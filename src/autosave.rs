@@ -0,0 +1,114 @@
+//! 自动保存：当前标签停止编辑超过设定的延迟后写入磁盘。持续输入时不断推迟，
+//! 同一版本只尝试一次；写入失败的文件在手动保存成功之前不再自动保存
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::editor::untitled_name;
+
+/// 当前标签最近一次变化的版本
+struct Idle {
+    path: PathBuf,
+    version: u64,
+    since: Instant,
+    attempted: bool,
+}
+
+pub struct Autosave {
+    pub enabled: bool,
+    delay: Duration,
+    idle: Option<Idle>,
+    failed: Vec<PathBuf>,
+}
+
+impl Autosave {
+    pub fn new(delay: Duration) -> Self {
+        Self { enabled: false, delay, idle: None, failed: Vec::new() }
+    }
+
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    /// 开启或关闭，返回新的状态
+    pub fn toggle(&mut self) -> bool {
+        self.enabled = !self.enabled;
+        self.idle = None;
+        self.enabled
+    }
+
+    /// 可以自动保存的文件：未命名标签只能另存为，写入失败过的等手动保存
+    pub fn can_save(&self, path: &Path) -> bool {
+        self.enabled && untitled_name(path).is_none() && !self.failed.iter().any(|p| p == path)
+    }
+
+    /// 记下当前标签的版本；版本停留超过延迟时返回 true，同一版本只返回一次
+    pub fn poll(&mut self, path: &Path, version: u64, now: Instant) -> bool {
+        if !self.can_save(path) {
+            self.idle = None;
+            return false;
+        }
+        match self.idle.as_mut() {
+            Some(idle) if idle.path == path && idle.version == version => {
+                if idle.attempted || now.duration_since(idle.since) < self.delay {
+                    return false;
+                }
+                idle.attempted = true;
+                true
+            }
+            _ => {
+                self.idle = Some(Idle { path: path.to_path_buf(), version, since: now, attempted: false });
+                false
+            }
+        }
+    }
+
+    pub fn save_failed(&mut self, path: &Path) {
+        if !self.failed.iter().any(|p| p == path) {
+            self.failed.push(path.to_path_buf());
+        }
+    }
+
+    /// 手动保存成功后恢复自动保存
+    pub fn saved_manually(&mut self, path: &Path) {
+        self.failed.retain(|p| p != path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waits_for_idle_and_fires_once_per_version() {
+        let mut autosave = Autosave::new(Duration::from_millis(1000));
+        let path = Path::new("/project/a.t");
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        assert!(!autosave.poll(path, 1, start), "disabled");
+
+        autosave.toggle();
+        assert!(!autosave.poll(path, 1, at(0)));
+        // 持续输入时每次变化都重新计时
+        assert!(!autosave.poll(path, 2, at(900)));
+        assert!(!autosave.poll(path, 3, at(1800)));
+        assert!(autosave.poll(path, 3, at(2800)));
+        assert!(!autosave.poll(path, 3, at(4000)));
+        assert!(!autosave.poll(path, 4, at(4100)));
+        assert!(autosave.poll(path, 4, at(5100)));
+    }
+
+    #[test]
+    fn test_skips_untitled_and_failed_files() {
+        let mut autosave = Autosave::new(Duration::ZERO);
+        autosave.toggle();
+        assert!(!autosave.can_save(&crate::editor::untitled_path(1)));
+
+        let path = Path::new("/project/a.t");
+        autosave.save_failed(path);
+        assert!(!autosave.can_save(path));
+        assert!(!autosave.poll(path, 1, Instant::now()));
+        autosave.saved_manually(path);
+        assert!(autosave.can_save(path));
+    }
+}
@@ -0,0 +1,88 @@
+//! 命令行参数
+//!
+//! `tiecode --diff <左> <右>` 以比较工具的方式启动，可以配置为 git difftool：
+//!
+//! ```text
+//! git config difftool.tiecode.cmd 'tiecode --wait --diff "$LOCAL" "$REMOTE"'
+//! ```
+//!
+//! 这种模式下程序在比较标签关闭后退出。程序本身就在前台运行到退出为止，
+//! `--wait` 只是为了与其它编辑器的 difftool 配置写法一致而接受
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffRequest {
+    pub left: PathBuf,
+    pub right: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CliArgs {
+    pub diff: Option<DiffRequest>,
+    pub wait: bool,
+}
+
+/// 解析命令行参数，不含程序名。不认识的参数忽略，系统或启动器有时会附加参数
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<CliArgs, String> {
+    let mut parsed = CliArgs::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wait" => parsed.wait = true,
+            "--diff" => {
+                let (Some(left), Some(right)) = (args.next(), args.next()) else {
+                    return Err("--diff 需要两个文件路径：tiecode --diff <左> <右>".to_string());
+                };
+                parsed.diff = Some(DiffRequest {
+                    left: PathBuf::from(left),
+                    right: PathBuf::from(right),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(parsed)
+}
+
+fn read_side(path: &Path) -> Result<String, String> {
+    let raw = std::fs::read(path).map_err(|err| format!("无法读取 {}：{}", path.display(), err))?;
+    let text = String::from_utf8(raw).map_err(|_| format!("{} 不是 UTF-8 文本", path.display()))?;
+    Ok(tiecode_buffer::strip_bom(&text).0.to_string())
+}
+
+impl DiffRequest {
+    /// 读取两侧的文本；任一侧读取失败时返回说明，调用方打印后以非零状态退出
+    pub fn read(&self) -> Result<(String, String), String> {
+        Ok((read_side(&self.left)?, read_side(&self.right)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_diff_args_and_unreadable_side() {
+        assert_eq!(parse(args(&[])).unwrap(), CliArgs::default());
+        let parsed = parse(args(&["--wait", "--diff", "a.t", "b.t"])).unwrap();
+        assert!(parsed.wait);
+        assert_eq!(
+            parsed.diff,
+            Some(DiffRequest { left: PathBuf::from("a.t"), right: PathBuf::from("b.t") })
+        );
+        assert!(parse(args(&["--diff", "a.t"])).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let left = dir.path().join("a.t");
+        std::fs::write(&left, "\u{feff}甲\n").unwrap();
+        let request = DiffRequest { left: left.clone(), right: dir.path().join("missing.t") };
+        assert!(request.read().unwrap_err().contains("missing.t"));
+        let request = DiffRequest { left: left.clone(), right: left };
+        assert_eq!(request.read().unwrap(), ("甲\n".to_string(), "甲\n".to_string()));
+    }
+}
@@ -0,0 +1,128 @@
+//! 命令日志：记录最近执行的命令、时间和结果，用于命令历史和命令面板中最近使用的排序，
+//! 并写入日志方便排查问题。命令没有单独的参数，例如图标主题的 id 包含在命令 id 中
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 保留的条数
+const CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandOutcome {
+    Done,
+    Failed(String),
+    /// 没有对应的处理
+    Unhandled,
+}
+
+/// 命令修改了文档时，执行后文档的撤销步数；之后撤销到此之前就不能再撤销这条命令了
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndoMark {
+    pub path: PathBuf,
+    pub depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub command: String,
+    pub at: SystemTime,
+    pub outcome: CommandOutcome,
+    pub undo: Option<UndoMark>,
+}
+
+impl JournalEntry {
+    /// 写入日志的一行
+    pub fn log_line(&self) -> String {
+        let seconds = self.at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let outcome = match &self.outcome {
+            CommandOutcome::Done => "ok".to_string(),
+            CommandOutcome::Failed(error) => format!("failed: {}", error),
+            CommandOutcome::Unhandled => "unhandled".to_string(),
+        };
+        format!("[{}] {} -> {}", seconds, self.command, outcome)
+    }
+
+    /// 命令的效果是否仍可撤销：当前文件是修改的文件，且撤销步数没有回到修改之前
+    pub fn undoable(&self, active: Option<&PathBuf>, depth: usize) -> bool {
+        self.undo
+            .as_ref()
+            .is_some_and(|mark| active == Some(&mark.path) && depth >= mark.depth)
+    }
+}
+
+/// 距现在的时间，例如“3 分钟前”
+pub fn elapsed_label(at: SystemTime, now: SystemTime) -> String {
+    let elapsed = now.duration_since(at).unwrap_or(Duration::ZERO).as_secs();
+    match elapsed {
+        0..=9 => "刚刚".to_string(),
+        10..=59 => format!("{} 秒前", elapsed),
+        60..=3599 => format!("{} 分钟前", elapsed / 60),
+        _ => format!("{} 小时前", elapsed / 3600),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommandJournal {
+    /// 最新的在前
+    entries: VecDeque<JournalEntry>,
+}
+
+impl CommandJournal {
+    pub fn record(&mut self, entry: JournalEntry) -> &JournalEntry {
+        self.entries.push_front(entry);
+        self.entries.truncate(CAPACITY);
+        &self.entries[0]
+    }
+
+    /// 最新的在前
+    pub fn entries(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.entries.iter()
+    }
+
+    /// 最近成功执行过的命令，不重复，最近的在前
+    pub fn recent_commands(&self) -> Vec<String> {
+        let mut commands: Vec<String> = Vec::new();
+        for entry in &self.entries {
+            if entry.outcome == CommandOutcome::Done && !commands.contains(&entry.command) {
+                commands.push(entry.command.clone());
+            }
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(command: &str, outcome: CommandOutcome, at: SystemTime) -> JournalEntry {
+        JournalEntry { command: command.to_string(), at, outcome, undo: None }
+    }
+
+    #[test]
+    fn test_journal_keeps_recent_entries_and_undo_hints() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut journal = CommandJournal::default();
+        for i in 0..CAPACITY + 5 {
+            journal.record(entry("core.save", CommandOutcome::Done, start + Duration::from_secs(i as u64)));
+        }
+        journal.record(entry("core.paste", CommandOutcome::Done, start));
+        journal.record(entry("file.open_terminal", CommandOutcome::Failed("没有打开的文件".to_string()), start));
+        assert_eq!(journal.entries().count(), CAPACITY);
+        assert_eq!(journal.recent_commands(), vec!["core.paste", "core.save"]);
+        assert_eq!(
+            journal.entries().next().unwrap().log_line(),
+            "[1000] file.open_terminal -> failed: 没有打开的文件"
+        );
+
+        let path = PathBuf::from("/p/a.t");
+        let mut paste = entry("core.paste", CommandOutcome::Done, start);
+        paste.undo = Some(UndoMark { path: path.clone(), depth: 3 });
+        assert!(paste.undoable(Some(&path), 3));
+        assert!(!paste.undoable(Some(&path), 2));
+        assert!(!paste.undoable(Some(&PathBuf::from("/p/b.t")), 5));
+
+        assert_eq!(elapsed_label(start, start + Duration::from_secs(125)), "2 分钟前");
+    }
+}
@@ -1,6 +1,25 @@
 use gpui::*;
+use super::fuzzy::{fuzzy_match, match_ranges};
+use super::quick_open::{rank_files, workspace_files, WorkspaceFiles, MAX_RESULTS};
+use super::search_panel::PREVIEW_DELAY;
+use super::theme;
+use crate::editor::outline::{OutlineSymbol, WorkspaceSymbol};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use tiecode_plugin_api::CommandContribution;
 
+/// 列表中的一项；命令历史中 `detail` 为执行的时间和结果
+#[derive(Clone)]
+pub struct PaletteItem {
+    pub command: CommandContribution,
+    pub detail: Option<String>,
+}
+
+/// 快速打开时列出的文件；后台读取完成前为 None
+struct FileSource {
+    files: Option<WorkspaceFiles>,
+}
+
 pub struct CommandPalette {
     pub focus_handle: FocusHandle,
     input: String,
@@ -9,19 +28,67 @@ pub struct CommandPalette {
     input_marked_range: Option<std::ops::Range<usize>>,
     selected_index: usize,
     all_commands: Vec<CommandContribution>,
-    filtered_commands: Vec<CommandContribution>,
+    /// 最近执行过的命令，最近的在前；输入为空时排在前面
+    recent: Vec<String>,
+    /// 命令 id 到格式化后的快捷键，显示在行的右侧
+    keybindings: HashMap<String, String>,
+    /// 固定在工具栏上的命令，右键菜单据此显示固定或取消固定
+    pinned: Vec<String>,
+    /// 右键命令弹出的菜单：命令 id 和菜单位置
+    pin_menu: Option<(String, Point<Pixels>)>,
+    /// 显示命令历史时为历史中的各项，选中后重新执行
+    history: Option<Vec<PaletteItem>>,
+    /// 快速打开文件时的文件列表，此时各项的命令 id 和标题都是相对路径
+    file_source: Option<FileSource>,
+    _file_load: Option<Task<()>>,
+    /// 快速打开中每次移动选中项加一，用于丢弃过期的延迟预览
+    preview_generation: u64,
+    /// 已发出预览且尚未打开或取消
+    previewing: bool,
+    /// 输入以 `@` 开头时列出的当前文档大纲，此时各项的命令 id 是大纲中的序号；
+    /// 第一次进入时向窗口请求，面板关闭前不再更新
+    symbols: Option<Vec<OutlineSymbol>>,
+    symbols_requested: bool,
+    /// 输入以 `#` 开头时在整个项目中搜索到的符号，此时各项的命令 id 是其中的序号；
+    /// `workspace_keyword` 为最近一次请求的关键词，关键词变化时重新请求
+    workspace_symbols: Vec<WorkspaceSymbol>,
+    workspace_keyword: Option<String>,
+    /// 筛选后的项及标题中匹配到的字符位置
+    filtered_commands: Vec<(PaletteItem, Vec<usize>)>,
     list_state: ListState,
     visible: bool,
     input_bounds: Option<Bounds<Pixels>>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum CommandPaletteEvent {
     ExecuteCommand(String),
+    /// 快速打开中选中的文件
+    OpenFile(PathBuf),
+    /// 快速打开中用方向键停留在匹配的文件上，只读预览它，不打开标签
+    PreviewMatch(PathBuf),
+    /// 放弃预览，回到之前的标签
+    CancelPreview,
+    /// 输入 `@` 后需要当前文档的大纲，由窗口调用 [`CommandPalette::set_symbols`]
+    RequestSymbols,
+    /// 跳到大纲中的符号，行列从 0 开始，列按字符计
+    GoToSymbol { line: usize, column: usize },
+    /// 输入 `:` 加行号（可再加 `:列号`）后跳到该处，行列从 0 开始，列按字符计
+    GoToLine { line: usize, column: usize },
+    /// 输入 `#` 后按关键词搜索项目中的符号，由窗口调用 [`CommandPalette::set_workspace_symbols`]
+    RequestWorkspaceSymbols(String),
+    /// 打开项目符号所在的文件并跳到符号处，行列从 0 开始
+    OpenLocation { path: PathBuf, line: usize, column: usize },
+    /// 在工具栏上固定或取消固定命令
+    TogglePinned(String),
     Dismiss,
 }
 
 impl EventEmitter<CommandPaletteEvent> for CommandPalette {}
 
+/// 最近执行的命令在匹配得分上的加分
+const RECENT_BOOST: i64 = 24;
+
 impl CommandPalette {
     pub fn new(cx: &mut Context<Self>) -> Self {
         Self {
@@ -32,6 +99,19 @@ impl CommandPalette {
             input_marked_range: None,
             selected_index: 0,
             all_commands: Vec::new(),
+            recent: Vec::new(),
+            keybindings: HashMap::new(),
+            pinned: Vec::new(),
+            pin_menu: None,
+            history: None,
+            file_source: None,
+            _file_load: None,
+            preview_generation: 0,
+            previewing: false,
+            symbols: None,
+            symbols_requested: false,
+            workspace_symbols: Vec::new(),
+            workspace_keyword: None,
             filtered_commands: Vec::new(),
             list_state: ListState::new(0, ListAlignment::Top, px(24.0)), // Height of item
             visible: false,
@@ -39,13 +119,91 @@ impl CommandPalette {
         }
     }
 
-    pub fn set_commands(&mut self, commands: Vec<CommandContribution>, cx: &mut Context<Self>) {
+    pub fn set_commands(
+        &mut self,
+        commands: Vec<CommandContribution>,
+        recent: Vec<String>,
+        keybindings: HashMap<String, String>,
+        cx: &mut Context<Self>,
+    ) {
         self.all_commands = commands;
+        self.recent = recent;
+        self.keybindings = keybindings;
         self.update_filter(cx);
     }
 
+    pub fn set_pinned(&mut self, pinned: Vec<String>, cx: &mut Context<Self>) {
+        self.pinned = pinned;
+        cx.notify();
+    }
+
     pub fn show(&mut self, cx: &mut Context<Self>) {
+        self.history = None;
+        self.close_files();
+        self.open(cx);
+    }
+
+    /// 打开面板并预先填入前缀，例如转到行时的 `:`
+    pub fn show_prefixed(&mut self, prefix: &str, cx: &mut Context<Self>) {
+        self.show(cx);
+        self.input = prefix.to_string();
+        self.input_cursor = self.input.len();
+        self.update_filter(cx);
+    }
+
+    /// 显示命令历史，最近的在前
+    pub fn show_history(&mut self, items: Vec<PaletteItem>, cx: &mut Context<Self>) {
+        self.history = Some(items);
+        self.close_files();
+        self.open(cx);
+    }
+
+    /// 快速打开各根目录下的文件；文件列表在后台读取，读取期间可以继续输入
+    pub fn show_files(&mut self, roots: Vec<PathBuf>, cx: &mut Context<Self>) {
+        self.history = None;
+        self.file_source = Some(FileSource { files: None });
+        let executor = cx.background_executor().clone();
+        self._file_load = Some(cx.spawn(move |view: WeakEntity<CommandPalette>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let files = executor.spawn(async move { workspace_files(&roots) }).await;
+                view.update(&mut cx, |this, cx| {
+                    if let Some(source) = this.file_source.as_mut() {
+                        source.files = Some(files);
+                    }
+                    this.update_filter(cx);
+                })
+                .ok();
+            }
+        }));
+        self.open(cx);
+    }
+
+    pub fn set_symbols(&mut self, symbols: Vec<OutlineSymbol>, cx: &mut Context<Self>) {
+        self.symbols = Some(symbols);
+        self.update_filter(cx);
+    }
+
+    /// 关键词已经变化时丢弃结果，等待下一次请求的结果
+    pub fn set_workspace_symbols(&mut self, keyword: &str, symbols: Vec<WorkspaceSymbol>, cx: &mut Context<Self>) {
+        if self.workspace_keyword.as_deref() == Some(keyword) {
+            self.workspace_symbols = symbols;
+            self.update_filter(cx);
+        }
+    }
+
+    fn close_files(&mut self) {
+        self.file_source = None;
+        self._file_load = None;
+    }
+
+    fn open(&mut self, cx: &mut Context<Self>) {
         self.visible = true;
+        self.pin_menu = None;
+        self.symbols = None;
+        self.symbols_requested = false;
+        self.workspace_symbols.clear();
+        self.workspace_keyword = None;
         self.input.clear();
         self.input_cursor = 0;
         self.input_selection = None;
@@ -62,6 +220,10 @@ impl CommandPalette {
     }
 
     pub fn dismiss(&mut self, cx: &mut Context<Self>) {
+        self.preview_generation += 1;
+        if std::mem::take(&mut self.previewing) {
+            cx.emit(CommandPaletteEvent::CancelPreview);
+        }
         cx.emit(CommandPaletteEvent::Dismiss);
         self.hide(cx);
     }
@@ -106,78 +268,162 @@ impl CommandPalette {
     }
 
     fn update_filter(&mut self, cx: &mut Context<Self>) {
+        self.preview_generation += 1;
+        if self.file_source.is_some() && self.input.starts_with('>') {
+            // 与 VS Code 一样，快速打开中输入 `>` 切换到命令
+            if std::mem::take(&mut self.previewing) {
+                cx.emit(CommandPaletteEvent::CancelPreview);
+            }
+            self.close_files();
+            self.input.remove(0);
+            self.input_cursor = self.input_cursor.saturating_sub(1);
+            self.input_selection = None;
+            self.input_marked_range = None;
+        }
+        if let Some(query) = self.input.strip_prefix(':') {
+            let title = match parse_line_target(query) {
+                Some((line, Some(column))) => format!("转到第 {} 行第 {} 列", line, column),
+                Some((line, None)) => format!("转到第 {} 行", line),
+                None => "输入要转到的行号，例如 :42 或 :42:5".to_string(),
+            };
+            let command = CommandContribution { command: String::new(), title, category: None, icon: None };
+            self.filtered_commands = vec![(PaletteItem { command, detail: None }, Vec::new())];
+            self.selected_index = 0;
+            self.list_state.reset(self.filtered_commands.len());
+            cx.notify();
+            return;
+        }
+        if let Some(query) = self.input.strip_prefix('#') {
+            let keyword = query.trim().to_string();
+            if keyword.is_empty() {
+                self.workspace_symbols.clear();
+                self.workspace_keyword = None;
+            } else if self.workspace_keyword.as_deref() != Some(keyword.as_str()) {
+                // 结果返回前仍显示上一个关键词的结果
+                self.workspace_keyword = Some(keyword.clone());
+                cx.emit(CommandPaletteEvent::RequestWorkspaceSymbols(keyword.clone()));
+            }
+            let mut scored: Vec<(i64, usize, Vec<usize>)> = self
+                .workspace_symbols
+                .iter()
+                .enumerate()
+                .filter_map(|(index, found)| {
+                    fuzzy_match(&keyword, &found.symbol.name).map(|m| (m.score, index, m.positions))
+                })
+                .collect();
+            scored.sort_by(|a, b| b.0.cmp(&a.0));
+            self.filtered_commands = scored
+                .into_iter()
+                .map(|(_, index, positions)| {
+                    let found = &self.workspace_symbols[index];
+                    let command = CommandContribution {
+                        command: index.to_string(),
+                        title: found.symbol.name.clone(),
+                        category: Some(found.symbol.kind.label().to_string()),
+                        icon: None,
+                    };
+                    let file_name = found.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                    let detail = format!("{}:{}", file_name, found.symbol.line + 1);
+                    (PaletteItem { command, detail: Some(detail) }, positions)
+                })
+                .collect();
+            self.selected_index = 0;
+            self.list_state.reset(self.filtered_commands.len());
+            cx.notify();
+            return;
+        }
+        if let Some(query) = self.input.strip_prefix('@') {
+            if self.symbols.is_none() && !self.symbols_requested {
+                self.symbols_requested = true;
+                cx.emit(CommandPaletteEvent::RequestSymbols);
+            }
+            let symbols = self.symbols.as_deref().unwrap_or_default();
+            let mut scored: Vec<(i64, usize, Vec<usize>)> = symbols
+                .iter()
+                .enumerate()
+                .filter_map(|(index, symbol)| fuzzy_match(query, &symbol.name).map(|m| (m.score, index, m.positions)))
+                .collect();
+            // 查询为空时得分都是 0，保持文档顺序
+            scored.sort_by(|a, b| b.0.cmp(&a.0));
+            self.filtered_commands = scored
+                .into_iter()
+                .map(|(_, index, positions)| {
+                    let symbol = &symbols[index];
+                    let command = CommandContribution {
+                        command: index.to_string(),
+                        title: symbol.name.clone(),
+                        category: Some(symbol.kind.label().to_string()),
+                        icon: None,
+                    };
+                    (PaletteItem { command, detail: Some(format!("第 {} 行", symbol.line + 1)) }, positions)
+                })
+                .collect();
+            self.selected_index = 0;
+            self.list_state.reset(self.filtered_commands.len());
+            cx.notify();
+            return;
+        }
+        if let Some(source) = &self.file_source {
+            let files = source.files.as_ref().map(|files| files.labels.as_slice()).unwrap_or_default();
+            self.filtered_commands = rank_files(&self.input, files, MAX_RESULTS)
+                .into_iter()
+                .map(|(path, positions)| {
+                    let command = CommandContribution { command: path.clone(), title: path, category: None, icon: None };
+                    (PaletteItem { command, detail: None }, positions)
+                })
+                .collect();
+            self.selected_index = 0;
+            self.list_state.reset(self.filtered_commands.len());
+            cx.notify();
+            return;
+        }
+        let items = match &self.history {
+            Some(history) => history.clone(),
+            None => {
+                // 最近执行过的命令排在前面，其余保持原来的顺序
+                let mut commands = self.all_commands.clone();
+                commands.sort_by_key(|cmd| self.recent.iter().position(|c| c == &cmd.command).unwrap_or(usize::MAX));
+                commands.into_iter().map(|command| PaletteItem { command, detail: None }).collect()
+            }
+        };
         if self.input.is_empty() {
-            self.filtered_commands = self.all_commands.clone();
+            self.filtered_commands = items.into_iter().map(|item| (item, Vec::new())).collect();
         } else {
             let input = self.input.clone();
-            let mut scored: Vec<(i64, CommandContribution)> = self
-                .all_commands
-                .iter()
-                .filter_map(|cmd| {
-                    let score_title = Self::fuzzy_score(&input, &cmd.title);
-                    let score_cmd = Self::fuzzy_score(&input, &cmd.command);
-                    
-                    match (score_title, score_cmd) {
-                        (Some(s1), Some(s2)) => Some((s1.max(s2), cmd.clone())),
-                        (Some(s), None) => Some((s, cmd.clone())),
-                        (None, Some(s)) => Some((s, cmd.clone())),
-                        (None, None) => None,
-                    }
+            let mut scored: Vec<(i64, PaletteItem, Vec<usize>)> = items
+                .into_iter()
+                .filter_map(|item| {
+                    let title = fuzzy_match(&input, &item.command.title);
+                    let command = fuzzy_match(&input, &item.command.command);
+                    // 只高亮标题中的匹配；只有命令 id 匹配时不高亮
+                    let (score, positions) = match (title, command) {
+                        (Some(t), Some(c)) => (t.score.max(c.score), t.positions),
+                        (Some(t), None) => (t.score, t.positions),
+                        (None, Some(c)) => (c.score, Vec::new()),
+                        (None, None) => return None,
+                    };
+                    let score = score + self.recent_boost(&item.command.command);
+                    Some((score, item, positions))
                 })
                 .collect();
             
             scored.sort_by(|a, b| b.0.cmp(&a.0));
             
-            self.filtered_commands = scored.into_iter().map(|(_, cmd)| cmd).collect();
+            self.filtered_commands = scored.into_iter().map(|(_, item, positions)| (item, positions)).collect();
         }
         self.selected_index = 0;
         self.list_state.reset(self.filtered_commands.len());
         cx.notify();
     }
 
-    fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
-        if pattern.is_empty() {
-            return Some(0);
+    /// 最近执行过的命令加分，越近加得越多；命令历史中不加分
+    fn recent_boost(&self, command: &str) -> i64 {
+        if self.history.is_some() {
+            return 0;
         }
-        
-        let pattern_chars: Vec<char> = pattern.to_lowercase().chars().collect();
-        let text_chars: Vec<char> = text.chars().collect();
-        
-        let mut pattern_idx = 0;
-        let mut score: i64 = 0;
-        let mut last_match_idx: Option<usize> = None;
-        
-        for (i, c) in text_chars.iter().enumerate() {
-            if pattern_idx >= pattern_chars.len() {
-                break;
-            }
-            
-            let c_lower = c.to_lowercase().next().unwrap_or(*c);
-            
-            if c_lower == pattern_chars[pattern_idx] {
-                pattern_idx += 1;
-                score += 10;
-                
-                let is_start = i == 0 || text_chars[i-1].is_whitespace() || text_chars[i-1] == '_' || text_chars[i-1] == '-' || text_chars[i-1] == '.';
-                if is_start {
-                    score += 20;
-                }
-                
-                if let Some(last) = last_match_idx {
-                    if i == last + 1 {
-                        score += 5;
-                    }
-                }
-                
-                last_match_idx = Some(i);
-            }
-        }
-        
-        if pattern_idx == pattern_chars.len() {
-            score -= text.len() as i64;
-            Some(score)
-        } else {
-            None
+        match self.recent.iter().position(|c| c == command) {
+            Some(rank) => (RECENT_BOOST - rank as i64 * 4).max(4),
+            None => 0,
         }
     }
 
@@ -187,6 +433,7 @@ impl CommandPalette {
         }
         self.selected_index = (self.selected_index + 1) % self.filtered_commands.len();
         self.list_state.scroll_to_reveal_item(self.selected_index);
+        self.schedule_preview(cx);
         cx.notify();
     }
 
@@ -200,12 +447,91 @@ impl CommandPalette {
             self.selected_index = self.filtered_commands.len() - 1;
         }
         self.list_state.scroll_to_reveal_item(self.selected_index);
+        self.schedule_preview(cx);
         cx.notify();
     }
 
+    /// 快速打开中选中的文件停留 [`PREVIEW_DELAY`] 后发出预览；期间选中项变化则作废
+    fn schedule_preview(&mut self, cx: &mut Context<Self>) {
+        self.preview_generation += 1;
+        if self.file_source.is_none() {
+            return;
+        }
+        let generation = self.preview_generation;
+        cx.spawn(move |view: WeakEntity<CommandPalette>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(PREVIEW_DELAY).await;
+                view.update(&mut cx, |this, cx| {
+                    if this.preview_generation != generation || !this.visible {
+                        return;
+                    }
+                    if let Some(path) = this.selected_file().cloned() {
+                        this.previewing = true;
+                        cx.emit(CommandPaletteEvent::PreviewMatch(path));
+                    }
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    /// 快速打开中选中项对应的完整路径
+    fn selected_file(&self) -> Option<&PathBuf> {
+        let (item, _) = self.filtered_commands.get(self.selected_index)?;
+        self.file_source.as_ref()?.files.as_ref()?.path_for(&item.command.command)
+    }
+
     fn confirm_selection(&mut self, cx: &mut Context<Self>) {
-        if let Some(cmd) = self.filtered_commands.get(self.selected_index) {
-            cx.emit(CommandPaletteEvent::ExecuteCommand(cmd.command.clone()));
+        if let Some(query) = self.input.strip_prefix(':') {
+            // 行号无效时保持面板打开
+            if let Some((line, column)) = parse_line_target(query) {
+                let (line, column) = (line.saturating_sub(1), column.unwrap_or(1).saturating_sub(1));
+                cx.emit(CommandPaletteEvent::GoToLine { line, column });
+                self.close_files();
+                self.hide(cx);
+            }
+            return;
+        }
+        if let Some((item, _)) = self.filtered_commands.get(self.selected_index) {
+            if self.input.starts_with('#') {
+                let found = item.command.command.parse::<usize>().ok().and_then(|index| self.workspace_symbols.get(index));
+                if let Some(found) = found {
+                    cx.emit(CommandPaletteEvent::OpenLocation {
+                        path: found.path.clone(),
+                        line: found.symbol.line,
+                        column: found.symbol.column,
+                    });
+                }
+                self.close_files();
+                self.hide(cx);
+                return;
+            }
+            if self.input.starts_with('@') {
+                let symbol = item.command.command.parse::<usize>().ok().and_then(|index| self.symbols.as_ref()?.get(index));
+                if let Some(symbol) = symbol {
+                    cx.emit(CommandPaletteEvent::GoToSymbol { line: symbol.line, column: symbol.column });
+                }
+                self.close_files();
+                self.hide(cx);
+                return;
+            }
+            if self.file_source.is_some() {
+                self.preview_generation += 1;
+                self.previewing = false;
+                if let Some(path) = self.selected_file() {
+                    cx.emit(CommandPaletteEvent::OpenFile(path.clone()));
+                }
+                self.close_files();
+                self.hide(cx);
+                return;
+            }
+            let command = item.command.command.clone();
+            // 下次打开前窗口会用命令日志重新设置，这里先更新以便立即生效
+            self.recent.retain(|c| c != &command);
+            self.recent.insert(0, command.clone());
+            cx.emit(CommandPaletteEvent::ExecuteCommand(command));
             self.hide(cx);
         }
     }
@@ -213,6 +539,13 @@ impl CommandPalette {
     fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
         let key = event.keystroke.key.as_str();
 
+        if self.pin_menu.take().is_some() {
+            cx.notify();
+            if key == "escape" {
+                return;
+            }
+        }
+
         if key == "enter" {
             self.confirm_selection(cx);
             return;
@@ -271,6 +604,18 @@ impl CommandPalette {
     }
 }
 
+/// 解析转到行的输入 `行号` 或 `行号:列号`，均从 1 开始
+fn parse_line_target(query: &str) -> Option<(usize, Option<usize>)> {
+    let query = query.trim();
+    let (line, column) = query.split_once(':').unwrap_or((query, ""));
+    // 还没输入列号时按行号处理
+    let column = match column.trim() {
+        "" => None,
+        column => Some(column.parse().ok()?),
+    };
+    Some((line.trim().parse().ok()?, column))
+}
+
 fn prev_char_boundary(text: &str, index: usize) -> usize {
     if index == 0 {
         return 0;
@@ -413,7 +758,7 @@ impl EntityInputHandler for CommandPalette {
         let run = TextRun {
             len: self.input.len(),
             font: style.font(),
-            color: rgb(0xffcccccc).into(),
+            color: Hsla::default(),
             background_color: None,
             underline: None,
             strikethrough: None,
@@ -446,7 +791,7 @@ impl EntityInputHandler for CommandPalette {
         let run = TextRun {
             len: self.input.len(),
             font: style.font(),
-            color: rgb(0xffcccccc).into(),
+            color: Hsla::default(),
             background_color: None,
             underline: None,
             strikethrough: None,
@@ -471,15 +816,53 @@ impl Render for CommandPalette {
             return div().into_any_element();
         }
 
-        let theme_bg = rgb(0xff252526);
-        let theme_border = rgb(0xff3c474d);
-        let theme_text = rgb(0xffcccccc);
-        let theme_selected = rgb(0xff37373d);
+        let theme = theme(cx);
+        let theme_bg = theme.panel;
+        let theme_border = theme.border;
+        let theme_text = theme.text;
+        let theme_selected = theme.list_selection;
 
         let filtered_commands = self.filtered_commands.clone();
+        let keybindings = self.keybindings.clone();
+        let list_palette = cx.entity();
+        let pin_menu = self.pin_menu.clone().map(|(command, position)| {
+            let pinned = self.pinned.contains(&command);
+            div()
+                .absolute()
+                .left(position.x)
+                .top(position.y)
+                .py(px(4.0))
+                .bg(theme_bg)
+                .border_1()
+                .border_color(theme_border)
+                .rounded_md()
+                .shadow(theme.overlay_shadow())
+                .text_size(px(12.0))
+                .text_color(theme_text)
+                .child(
+                    div()
+                        .px(px(12.0))
+                        .py(px(4.0))
+                        .cursor_pointer()
+                        .hover(|s| s.bg(theme.list_hover))
+                        .child(if pinned { "Unpin from Toolbar" } else { "Pin to Toolbar" })
+                        .on_mouse_down(MouseButton::Left, cx.listener(move |this, _, _, cx| {
+                            cx.stop_propagation();
+                            this.pin_menu = None;
+                            cx.emit(CommandPaletteEvent::TogglePinned(command.clone()));
+                            cx.notify();
+                        })),
+                )
+        });
         let selected_index = self.selected_index;
         let palette = cx.entity();
         let input_focus = self.focus_handle.clone();
+        let placeholder = match (&self.file_source, &self.history) {
+            (Some(FileSource { files: None, .. }), _) => "Loading files...",
+            (Some(_), _) => "Search files by name (type > for commands, : for line, @ for symbols, # for project symbols)...",
+            (None, Some(_)) => "Search command history...",
+            (None, None) => "Type a command (: for line, @ for symbols, # for project symbols)...",
+        };
 
         div()
             .absolute()
@@ -497,7 +880,7 @@ impl Render for CommandPalette {
                     .left(px(0.0))
                     .w_full()
                     .h_full()
-                    .bg(rgba(0x00000080))
+                    .bg(theme.overlay_bg)
                     // Click outside to dismiss
                     .on_mouse_down(MouseButton::Left, cx.listener(|this, _, _, cx| {
                          cx.stop_propagation();
@@ -512,10 +895,15 @@ impl Render for CommandPalette {
                     .border_1()
                     .border_color(theme_border)
                     .rounded_lg()
-                    .shadow_lg()
+                    .shadow(theme.overlay_shadow())
                     .flex()
                     .flex_col()
                     .track_focus(&self.focus_handle)
+                    .on_mouse_down(MouseButton::Left, cx.listener(|this, _, _, cx| {
+                        if this.pin_menu.take().is_some() {
+                            cx.notify();
+                        }
+                    }))
                     .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
                         this.on_key_down(event, window, cx);
                     }))
@@ -527,10 +915,10 @@ impl Render for CommandPalette {
                             .child(
                                 div()
                                     .w_full()
-                                    .bg(rgb(0xff3c3c3c))
+                                    .bg(theme.input_bg)
                                     .rounded_md()
                                     .border_1()
-                                    .border_color(rgb(0xff007fd4))
+                                    .border_color(theme.accent)
                                     .px(px(8.0))
                                     .py(px(4.0))
                                     .text_color(theme_text)
@@ -538,7 +926,7 @@ impl Render for CommandPalette {
                                     .overflow_hidden()
                                     .children({
                                         if self.input.is_empty() {
-                                            vec![div().child("Type a command...")]
+                                            vec![div().child(placeholder)]
                                         } else {
                                             let mut children = Vec::new();
                                             let msg = self.input.clone();
@@ -564,7 +952,7 @@ impl Render for CommandPalette {
                                                     if is_marked {
                                                         segment = segment.border_b_1().border_color(theme_text);
                                                     } else {
-                                                        segment = segment.bg(rgb(0xff264f78)); // Selection color
+                                                        segment = segment.bg(theme.list_selection);
                                                     }
                                                     children.push(segment);
                                                 }
@@ -649,7 +1037,7 @@ impl Render for CommandPalette {
                                                 let run = TextRun {
                                                     len: msg.len(),
                                                     font: style.font(),
-                                                    color: theme_text.into(),
+                                                    color: theme_text,
                                                     background_color: None,
                                                     underline: None,
                                                     strikethrough: None,
@@ -668,7 +1056,7 @@ impl Render for CommandPalette {
                                                 
                                                 window.paint_quad(fill(
                                                     Bounds::new(point(x, y_start), size(px(1.5), height)),
-                                                    rgb(0xff007fd4),
+                                                    theme.accent,
                                                 ));
                                             }
                                         }
@@ -687,9 +1075,16 @@ impl Render for CommandPalette {
                             if index >= filtered_commands.len() {
                                 return div().into_any_element();
                             }
-                            let cmd = &filtered_commands[index];
+                            let (item, positions) = &filtered_commands[index];
+                            let cmd = &item.command;
+                            let highlight = HighlightStyle { color: Some(theme.accent), ..Default::default() };
+                            let title = StyledText::new(cmd.title.clone()).with_highlights(
+                                match_ranges(&cmd.title, positions).into_iter().map(|range| (range, highlight)),
+                            );
                             let is_selected = index == selected_index;
-                            
+                            let command_id = cmd.command.clone();
+                            let palette = list_palette.clone();
+
                             div()
                                 .w_full()
                                 .px(px(12.0))
@@ -698,29 +1093,50 @@ impl Render for CommandPalette {
                                 .justify_between()
                                 .items_center()
                                 .bg(if is_selected { theme_selected } else { theme_bg })
+                                .hover(move |s| if is_selected { s } else { s.bg(theme.list_hover) })
                                 .text_color(theme_text)
                                 .child(
                                     div()
                                         .flex()
                                         .items_center()
-                                        .child(cmd.title.clone())
+                                        .child(title)
                                         .child(
                                             if let Some(cat) = &cmd.category {
                                                 div()
                                                     .ml(px(8.0))
                                                     .text_size(px(10.0))
-                                                    .text_color(rgb(0xff888888))
+                                                    .text_color(theme.muted_text)
                                                     .child(cat.clone())
                                             } else {
                                                 div()
                                             }
                                         )
                                 )
+                                .child(
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap(px(12.0))
+                                        .text_size(px(11.0))
+                                        .text_color(theme.muted_text)
+                                        .child(item.detail.clone().unwrap_or_default())
+                                        .child(keybindings.get(&cmd.command).cloned().unwrap_or_default()),
+                                )
+                                .on_mouse_down(MouseButton::Right, move |event, _window, cx| {
+                                    palette.update(cx, |this, cx| {
+                                        if this.file_source.is_some() || this.input.starts_with(['@', ':', '#']) {
+                                            return;
+                                        }
+                                        this.pin_menu = Some((command_id.clone(), event.position));
+                                        cx.notify();
+                                    });
+                                })
                                 .into_any_element()
                         })
                         .h_full()
                     )
             )
+            .children(pin_menu)
             .into_any_element()
     }
 }
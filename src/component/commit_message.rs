@@ -0,0 +1,209 @@
+//! 提交信息输入框的辅助：首行长度提示、约定式提交前缀与变更中名称的补全、用上下键翻阅以前的提交信息
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// 首行超过该长度时提示偏长
+pub const SUBJECT_SOFT_LIMIT: usize = 50;
+/// 首行超过该长度时提示过长
+pub const SUBJECT_HARD_LIMIT: usize = 72;
+
+pub const CONVENTIONAL_PREFIXES: &[&str] = &[
+    "feat", "fix", "refactor", "docs", "test", "perf", "style", "build", "ci", "chore", "revert",
+];
+
+/// 从变更中最多收集的名称数
+const MAX_DIFF_WORDS: usize = 2000;
+const MAX_COMPLETIONS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubjectLength {
+    Fits,
+    Long,
+    TooLong,
+}
+
+/// 首行的字符数及其长度等级
+pub fn subject_length(message: &str) -> (usize, SubjectLength) {
+    let count = message.lines().next().unwrap_or("").chars().count();
+    let level = if count > SUBJECT_HARD_LIMIT {
+        SubjectLength::TooLong
+    } else if count > SUBJECT_SOFT_LIMIT {
+        SubjectLength::Long
+    } else {
+        SubjectLength::Fits
+    };
+    (count, level)
+}
+
+/// 补全候选，选中后替换 `range`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completions {
+    pub range: Range<usize>,
+    pub items: Vec<String>,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '-'
+}
+
+/// 光标前的单词的补全：位于消息开头时补全约定式提交前缀，否则补全变更中的文件名和标识符
+pub fn complete(message: &str, cursor: usize, words: &[String]) -> Option<Completions> {
+    let before = message.get(..cursor)?;
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map(|(i, _)| i)
+        .unwrap_or(cursor);
+    let token = &message[start..cursor];
+    if token.is_empty() {
+        return None;
+    }
+    // 光标后紧跟单词字符时是在单词中间，不补全
+    if message[cursor..].chars().next().is_some_and(is_word_char) {
+        return None;
+    }
+
+    let mut items: Vec<String> = Vec::new();
+    if start == 0 {
+        items.extend(
+            CONVENTIONAL_PREFIXES
+                .iter()
+                .filter(|prefix| prefix.starts_with(token))
+                .map(|prefix| format!("{}: ", prefix)),
+        );
+    }
+    if token.chars().count() >= 2 {
+        let lower = token.to_lowercase();
+        items.extend(
+            words
+                .iter()
+                .filter(|word| word.len() > token.len() && word.to_lowercase().starts_with(&lower))
+                .cloned(),
+        );
+    }
+    items.truncate(MAX_COMPLETIONS);
+    if items.is_empty() {
+        None
+    } else {
+        Some(Completions { range: start..cursor, items })
+    }
+}
+
+/// 变更中出现的名称：文件名、去掉扩展名的文件名，以及增删行中的标识符
+pub fn diff_words<'a>(paths: &[String], lines: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut words = BTreeSet::new();
+    for path in paths {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        words.insert(name.to_string());
+        if let Some((stem, _)) = name.rsplit_once('.') {
+            if !stem.is_empty() {
+                words.insert(stem.to_string());
+            }
+        }
+    }
+    'lines: for line in lines {
+        for ident in line.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+            let starts_ok = ident.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_');
+            if starts_ok && ident.chars().count() >= 4 {
+                words.insert(ident.to_string());
+                if words.len() >= MAX_DIFF_WORDS {
+                    break 'lines;
+                }
+            }
+        }
+    }
+    words.into_iter().collect()
+}
+
+/// 像 shell 一样翻阅以前的提交信息；开始翻阅前正在写的内容在翻回最新处时恢复
+#[derive(Debug, Default)]
+pub struct MessageHistory {
+    /// 最新的在前
+    entries: Vec<String>,
+    index: Option<usize>,
+    draft: String,
+}
+
+impl MessageHistory {
+    /// 面板刷新时重新设置；内容没变时不打断正在进行的翻阅
+    pub fn set_entries(&mut self, entries: Vec<String>) {
+        if self.entries != entries {
+            self.entries = entries;
+            self.index = None;
+        }
+    }
+
+    /// 上一条（更早的）消息；`current` 为当前输入框内容
+    pub fn older(&mut self, current: &str) -> Option<String> {
+        let next = match self.index {
+            None => 0,
+            Some(index) => index + 1,
+        };
+        let entry = self.entries.get(next)?.clone();
+        if self.index.is_none() {
+            self.draft = current.to_string();
+        }
+        self.index = Some(next);
+        Some(entry)
+    }
+
+    /// 下一条（更新的）消息，翻过最新一条后回到原先的草稿
+    pub fn newer(&mut self) -> Option<String> {
+        match self.index? {
+            0 => {
+                self.index = None;
+                Some(std::mem::take(&mut self.draft))
+            }
+            index => {
+                self.index = Some(index - 1);
+                self.entries.get(index - 1).cloned()
+            }
+        }
+    }
+
+    /// 用户编辑后不再处于翻阅状态
+    pub fn reset(&mut self) {
+        self.index = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subject_guide_completion_and_history() {
+        assert_eq!(subject_length("fix: typo\n\nbody").0, 9);
+        assert_eq!(subject_length(&"a".repeat(51)).1, SubjectLength::Long);
+        assert_eq!(subject_length(&"字".repeat(73)).1, SubjectLength::TooLong);
+
+        let words = diff_words(
+            &["src/editor/mod.rs".to_string()],
+            ["+    fn schedule_git_diff(&mut self) {"],
+        );
+        assert!(words.contains(&"mod.rs".to_string()));
+        assert!(words.contains(&"schedule_git_diff".to_string()));
+        assert!(!words.contains(&"fn".to_string()));
+
+        let prefix = complete("fe", 2, &words).unwrap();
+        assert_eq!(prefix, Completions { range: 0..2, items: vec!["feat: ".to_string()] });
+        let name = complete("fix: Sched", 10, &words).unwrap();
+        assert_eq!(name.range, 5..10);
+        assert_eq!(name.items, vec!["schedule_git_diff".to_string()]);
+        assert_eq!(complete("fix: s", 6, &words), None);
+        assert_eq!(complete("fix: ", 5, &words), None);
+
+        let mut history = MessageHistory::default();
+        history.set_entries(vec!["second".to_string(), "first".to_string()]);
+        assert_eq!(history.newer(), None);
+        assert_eq!(history.older("draft").as_deref(), Some("second"));
+        assert_eq!(history.older("second").as_deref(), Some("first"));
+        assert_eq!(history.older("first"), None);
+        assert_eq!(history.newer().as_deref(), Some("second"));
+        assert_eq!(history.newer().as_deref(), Some("draft"));
+        assert_eq!(history.newer(), None);
+    }
+}
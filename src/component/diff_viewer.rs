@@ -0,0 +1,162 @@
+//! 左右对照的差异视图，用于 `--diff` 启动的比较标签
+
+use super::side_by_side::{rows, stats, DiffRow, RowKind};
+use super::Theme;
+use gpui::*;
+use std::path::{Path, PathBuf};
+
+/// 比较标签的占位路径前缀，这类路径不对应磁盘上的文件
+const DIFF_PREFIX: &str = "diff:";
+
+/// 比较两个文件的标签的占位路径
+pub fn diff_tab_path(left: &Path, right: &Path) -> PathBuf {
+    PathBuf::from(format!("{}{} ↔ {}", DIFF_PREFIX, left.display(), right.display()))
+}
+
+/// 比较标签的显示名称：两侧的文件名；普通文件返回 None
+pub fn diff_tab_label(path: &Path) -> Option<String> {
+    let rest = path.to_str()?.strip_prefix(DIFF_PREFIX)?;
+    let name = |side: &str| {
+        Path::new(side)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| side.to_string())
+    };
+    Some(match rest.split_once(" ↔ ") {
+        Some((left, right)) => format!("{} ↔ {}", name(left), name(right)),
+        None => rest.to_string(),
+    })
+}
+
+const ROW_HEIGHT: f32 = 20.0;
+
+pub struct DiffViewer {
+    left_title: String,
+    right_title: String,
+    left: Vec<SharedString>,
+    right: Vec<SharedString>,
+    rows: Vec<DiffRow>,
+    list_state: ListState,
+}
+
+fn display_lines(text: &str) -> Vec<SharedString> {
+    text.lines().map(|line| line.replace('\t', "    ").into()).collect()
+}
+
+impl DiffViewer {
+    pub fn new(_cx: &mut Context<Self>) -> Self {
+        Self {
+            left_title: String::new(),
+            right_title: String::new(),
+            left: Vec::new(),
+            right: Vec::new(),
+            rows: Vec::new(),
+            list_state: ListState::new(0, ListAlignment::Top, px(ROW_HEIGHT)),
+        }
+    }
+
+    pub fn set_files(&mut self, left: &Path, left_text: &str, right: &Path, right_text: &str, cx: &mut Context<Self>) {
+        self.left_title = left.display().to_string();
+        self.right_title = right.display().to_string();
+        self.left = display_lines(left_text);
+        self.right = display_lines(right_text);
+        self.rows = rows(left_text, right_text);
+        self.list_state.reset(self.rows.len());
+        cx.notify();
+    }
+}
+
+fn side_cell(number: Option<usize>, text: Option<SharedString>, bg: Hsla, theme: &Theme) -> Div {
+    div()
+        .flex_1()
+        .min_w(px(0.0))
+        .h_full()
+        .flex()
+        .items_center()
+        .overflow_hidden()
+        .bg(bg)
+        .child(
+            div()
+                .w(px(44.0))
+                .flex_none()
+                .pr(px(8.0))
+                .flex()
+                .justify_end()
+                .text_color(theme.muted_text)
+                .child(number.map(|n| (n + 1).to_string()).unwrap_or_default()),
+        )
+        .child(
+            div()
+                .whitespace_nowrap()
+                .text_color(theme.text)
+                .child(text.unwrap_or_default()),
+        )
+}
+
+impl Render for DiffViewer {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        let (added, removed) = stats(&self.rows);
+        let left = self.left.clone();
+        let right = self.right.clone();
+        let rows = self.rows.clone();
+
+        let title = |text: &str| {
+            div()
+                .flex_1()
+                .min_w(px(0.0))
+                .px(px(8.0))
+                .overflow_hidden()
+                .whitespace_nowrap()
+                .child(text.to_string())
+        };
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(theme.panel)
+            .text_size(px(13.0))
+            .child(
+                div()
+                    .h(px(26.0))
+                    .flex()
+                    .items_center()
+                    .border_b_1()
+                    .border_color(theme.border)
+                    .text_color(theme.text)
+                    .child(title(&self.left_title))
+                    .child(title(&self.right_title))
+                    .child(
+                        div()
+                            .px(px(8.0))
+                            .flex_none()
+                            .text_color(theme.muted_text)
+                            .child(format!("+{} -{}", added, removed)),
+                    ),
+            )
+            .child(
+                list(self.list_state.clone(), move |ix, _window, _cx| {
+                    let Some(row) = rows.get(ix).copied() else {
+                        return div().into_any_element();
+                    };
+                    let (left_bg, right_bg) = match row.kind {
+                        RowKind::Equal => (transparent_black(), transparent_black()),
+                        RowKind::Changed => (theme.diff_removed_bg, theme.diff_added_bg),
+                        RowKind::Removed => (theme.diff_removed_bg, theme.diff_filler_bg),
+                        RowKind::Added => (theme.diff_filler_bg, theme.diff_added_bg),
+                    };
+                    div()
+                        .h(px(ROW_HEIGHT))
+                        .w_full()
+                        .flex()
+                        .child(side_cell(row.left, row.left.and_then(|l| left.get(l).cloned()), left_bg, &theme))
+                        .child(div().w(px(1.0)).h_full().bg(theme.border))
+                        .child(side_cell(row.right, row.right.and_then(|r| right.get(r).cloned()), right_bg, &theme))
+                        .into_any_element()
+                })
+                .flex_1()
+                .w_full(),
+            )
+    }
+}
@@ -0,0 +1,115 @@
+//! 文件树的复制、剪切和粘贴：记住要复制或移动的项，粘贴到文件夹时处理重名和递归复制
+
+use super::name_input::stem_end;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClipboardOp {
+    Copy,
+    Cut,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileClipboard {
+    pub path: PathBuf,
+    pub op: ClipboardOp,
+}
+
+/// 粘贴的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasteOutcome {
+    Copied(PathBuf),
+    Moved { from: PathBuf, to: PathBuf },
+    /// 剪切后粘贴回原来的文件夹，什么也不做
+    Unchanged,
+}
+
+/// `dir` 中放置 `name` 的路径；重名时依次尝试 `名称 copy`、`名称 copy 2`……，扩展名保持不变
+pub fn unique_target(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let (stem, extension) = name.split_at(stem_end(name));
+    let mut n = 1;
+    loop {
+        let suffix = if n == 1 { " copy".to_string() } else { format!(" copy {}", n) };
+        let candidate = dir.join(format!("{}{}{}", stem, suffix, extension));
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// 复制文件，或递归复制文件夹
+pub fn copy_recursive(src: &Path, dst: &Path) -> io::Result<()> {
+    if !src.is_dir() {
+        return std::fs::copy(src, dst).map(|_| ());
+    }
+    std::fs::create_dir(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        copy_recursive(&entry.path(), &dst.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+impl FileClipboard {
+    /// 粘贴到文件夹 `dir`。文件夹不能粘贴到自身内部
+    pub fn paste_into(&self, dir: &Path) -> io::Result<PasteOutcome> {
+        let name = self
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "无法粘贴根目录"))?;
+        if dir.starts_with(&self.path) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "不能粘贴到自身内部"));
+        }
+        match self.op {
+            ClipboardOp::Copy => {
+                let target = unique_target(dir, &name);
+                copy_recursive(&self.path, &target)?;
+                Ok(PasteOutcome::Copied(target))
+            }
+            ClipboardOp::Cut if self.path.parent() == Some(dir) => Ok(PasteOutcome::Unchanged),
+            ClipboardOp::Cut => {
+                let target = unique_target(dir, &name);
+                std::fs::rename(&self.path, &target)?;
+                Ok(PasteOutcome::Moved { from: self.path.clone(), to: target })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paste_copies_recursively_and_suffixes_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/ui")).unwrap();
+        std::fs::write(root.join("src/ui/a.t"), "甲").unwrap();
+        std::fs::write(root.join("main.t"), "").unwrap();
+
+        let copy = FileClipboard { path: root.join("main.t"), op: ClipboardOp::Copy };
+        assert_eq!(copy.paste_into(root).unwrap(), PasteOutcome::Copied(root.join("main copy.t")));
+        assert_eq!(copy.paste_into(root).unwrap(), PasteOutcome::Copied(root.join("main copy 2.t")));
+
+        let copy_dir = FileClipboard { path: root.join("src"), op: ClipboardOp::Copy };
+        assert_eq!(copy_dir.paste_into(root).unwrap(), PasteOutcome::Copied(root.join("src copy")));
+        assert_eq!(std::fs::read_to_string(root.join("src copy/ui/a.t")).unwrap(), "甲");
+        assert!(copy_dir.paste_into(&root.join("src/ui")).is_err());
+
+        let cut = FileClipboard { path: root.join("main.t"), op: ClipboardOp::Cut };
+        assert_eq!(cut.paste_into(root).unwrap(), PasteOutcome::Unchanged);
+        assert_eq!(
+            cut.paste_into(&root.join("src")).unwrap(),
+            PasteOutcome::Moved { from: root.join("main.t"), to: root.join("src/main.t") }
+        );
+        assert!(!root.join("main.t").exists());
+    }
+}
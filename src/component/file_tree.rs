@@ -1,7 +1,15 @@
-use super::tie_svg::tie_svg;
+use super::git_status::GitStatusMap;
+use super::icon_theme::{file_icon, folder_icon};
+use super::ignore_rules::{is_hidden, IgnoreRules};
+use super::name_input::NameInput;
+use super::tree_filter::{project_paths, TreeFilter};
+use super::tree_selection::TreeSelection;
+use crate::session::TreeState;
+use super::tree_watch::{is_ignored, rename_path, watched_dirs, ChangeBatch, TreeChange};
 use gpui::*;
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::{HashMap, HashSet};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
@@ -14,91 +22,135 @@ pub struct FileEntry {
     pub is_dir: bool,
     pub depth: usize,
     pub is_expanded: bool,
+    /// 筛选时本身不匹配、只因包含匹配项而显示，淡化显示
+    pub dimmed: bool,
+    /// 被 .gitignore 忽略，文字用暗色显示
+    pub ignored: bool,
 }
 
+/// 内联输入行：新建时插在锚点之后，重命名时代替被重命名的那一行
 #[derive(Clone)]
 struct InlineNewItem {
     anchor_path: PathBuf,
     anchor_is_dir: bool,
-    name: String,
+    input: NameInput,
+    /// 正在重命名的路径；为 None 时是新建
+    rename: Option<PathBuf>,
     is_dir: bool,
     depth: usize,
     insert_index: usize,
     editing: bool,
+    /// 输入校验失败的提示，显示在输入行下方
+    error: Option<String>,
 }
 
-#[derive(Clone)]
-struct VirtualNode {
-    name: String,
-    is_dir: bool,
-}
-
+#[derive(Clone, Debug, PartialEq)]
 pub enum FileTreeEvent {
     OpenFile(PathBuf),
     ContextMenu {
         position: Point<Pixels>,
         path: PathBuf,
         is_dir: bool,
+        /// 右键的项属于多选时为所有最上层的选中项及其是否为文件夹，否则为空
+        selection: Vec<(PathBuf, bool)>,
     },
+    /// 拖动多选时包含每个最上层的选中项
     RequestMove {
-        src: PathBuf,
-        dst: PathBuf,
+        moves: Vec<(PathBuf, PathBuf)>,
     },
     RequestDelete {
-        path: PathBuf,
-        is_dir: bool,
+        entries: Vec<(PathBuf, bool)>,
+    },
+    /// 已在磁盘上重命名
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
     },
 }
 
 pub struct FileTree {
     root_path: Option<PathBuf>,
+    /// 后来加入工作区的文件夹，各自显示为一个顶层节点
+    extra_roots: Vec<PathBuf>,
     expanded_paths: HashSet<PathBuf>,
     visible_entries: Vec<FileEntry>,
     focus_handle: FocusHandle,
     list_state: ListState,
     fs_watch_active: bool,
     fs_watcher: Option<RecommendedWatcher>,
-    fs_watcher_root: Option<PathBuf>,
-    fs_event_rx: Option<mpsc::Receiver<()>>,
+    /// 监视器创建时的各个根目录
+    fs_watcher_roots: Vec<PathBuf>,
+    /// 当前监视的目录：根目录和已展开的目录
+    fs_watched_dirs: HashSet<PathBuf>,
+    fs_event_rx: Option<mpsc::Receiver<TreeChange>>,
+    fs_batch: ChangeBatch,
     drag_source: Option<PathBuf>,
     drag_hover: Option<PathBuf>,
     drag_active: bool,
     mouse_position: Point<Pixels>,
     drag_start_position: Option<Point<Pixels>>,
     selected_path: Option<PathBuf>,
+    /// Ctrl/Shift 点击的多选；只选中一项时与 `selected_path` 相同
+    selection: TreeSelection,
     selection_time: Option<Instant>,
     animating: bool,
     pending_new_item: Option<InlineNewItem>,
-    virtual_nodes: HashMap<PathBuf, Vec<VirtualNode>>,
     transparent: bool,
+    git_status: GitStatusMap,
+    /// 顶部筛选框的输入；`filter_focused` 为 true 时键盘输入进入筛选框
+    filter: NameInput,
+    filter_focused: bool,
+    /// 当前筛选结果；筛选框为空时为 None
+    filter_result: Option<TreeFilter>,
+    filter_task: Option<Task<()>>,
+    /// 开始筛选前的展开状态，清空筛选时恢复
+    expanded_before_filter: Option<HashSet<PathBuf>>,
+    /// 为 false 时不显示以 `.` 开头的项
+    show_hidden: bool,
+    ignore_rules: IgnoreRules,
 }
 
+/// 筛选框输入停顿这么久之后重新筛选
+const FILTER_DELAY: Duration = Duration::from_millis(100);
+
 impl EventEmitter<FileTreeEvent> for FileTree {}
 
 impl FileTree {
     pub fn new(root_path: Option<PathBuf>, cx: &mut Context<Self>) -> Self {
         let mut tree = Self {
             root_path: root_path.clone(),
+            extra_roots: Vec::new(),
             expanded_paths: HashSet::new(),
             visible_entries: Vec::new(),
             focus_handle: cx.focus_handle(),
             list_state: ListState::new(0, ListAlignment::Top, px(20.0)),
             fs_watch_active: false,
             fs_watcher: None,
-            fs_watcher_root: None,
+            fs_watcher_roots: Vec::new(),
+            fs_watched_dirs: HashSet::new(),
             fs_event_rx: None,
+            fs_batch: ChangeBatch::default(),
             drag_source: None,
             drag_hover: None,
             drag_active: false,
             mouse_position: Point::default(),
             drag_start_position: None,
             selected_path: None,
+            selection: TreeSelection::default(),
             selection_time: None,
             animating: false,
             pending_new_item: None,
-            virtual_nodes: HashMap::new(),
             transparent: false,
+            git_status: GitStatusMap::default(),
+            filter: NameInput::default(),
+            filter_focused: false,
+            filter_result: None,
+            filter_task: None,
+            expanded_before_filter: None,
+            show_hidden: false,
+            ignore_rules: IgnoreRules::default(),
         };
+        tree.ignore_rules = IgnoreRules::new(&tree.roots());
         tree.refresh_internal(false);
         tree.ensure_fs_watch(cx);
         tree
@@ -123,8 +175,8 @@ impl FileTree {
                         .await;
                     let updated = entity.update(&mut cx, |this, cx| {
                         this.sync_fs_watcher();
-                        if this.drain_fs_events() {
-                            this.refresh_internal(true);
+                        if let Some(changes) = this.drain_fs_events() {
+                            this.apply_fs_changes(changes);
                             cx.notify();
                         }
                     });
@@ -137,51 +189,85 @@ impl FileTree {
         .detach();
     }
 
+    /// 根目录变化时重建监视器，并让监视的目录与已展开的目录保持一致
     fn sync_fs_watcher(&mut self) {
-        let Some(root_path) = self.root_path.as_ref() else {
+        let roots = self.roots();
+        if roots.is_empty() {
             self.fs_event_rx = None;
             self.fs_watcher = None;
-            self.fs_watcher_root = None;
-            return;
-        };
-
-        if self.fs_watcher_root.as_ref() == Some(root_path) && self.fs_watcher.is_some() {
+            self.fs_watcher_roots.clear();
+            self.fs_watched_dirs.clear();
             return;
         }
 
-        self.fs_event_rx = None;
-        self.fs_watcher = None;
-        self.fs_watcher_root = None;
-
-        let (tx, rx) = mpsc::channel::<()>();
-        let mut watcher = match notify::recommended_watcher(move |_res| {
-            let _ = tx.send(());
-        }) {
-            Ok(watcher) => watcher,
-            Err(err) => {
-                println!("FileTree fs watcher init failed: {:?}", err);
-                return;
+        if self.fs_watcher_roots != roots || self.fs_watcher.is_none() {
+            self.fs_event_rx = None;
+            self.fs_watcher = None;
+            self.fs_watcher_roots.clear();
+            self.fs_watched_dirs.clear();
+            self.fs_batch = ChangeBatch::default();
+
+            let (tx, rx) = mpsc::channel::<TreeChange>();
+            let watcher_roots = roots.clone();
+            let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+                let Ok(event) = res else {
+                    return;
+                };
+                let change = match (event.kind, event.paths.as_slice()) {
+                    (EventKind::Access(_), _) => return,
+                    (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+                        TreeChange::Renamed { from: from.clone(), to: to.clone() }
+                    }
+                    (_, [path, ..]) => TreeChange::Changed(path.clone()),
+                    (_, []) => return,
+                };
+                let path = match &change {
+                    TreeChange::Changed(path) | TreeChange::Renamed { to: path, .. } => path,
+                };
+                if !watcher_roots.iter().any(|root| is_ignored(root, path)) {
+                    let _ = tx.send(change);
+                }
+            });
+            match watcher {
+                Ok(watcher) => {
+                    self.fs_watcher = Some(watcher);
+                    self.fs_watcher_roots = roots.clone();
+                    self.fs_event_rx = Some(rx);
+                }
+                Err(err) => {
+                    println!("FileTree fs watcher init failed: {:?}", err);
+                    return;
+                }
             }
-        };
+        }
 
-        if let Err(err) = watcher.watch(root_path, RecursiveMode::Recursive) {
-            println!("FileTree fs watcher watch failed: {:?} ({:?})", err, root_path);
+        let Some(watcher) = self.fs_watcher.as_mut() else {
             return;
+        };
+        let dirs: HashSet<PathBuf> = roots
+            .iter()
+            .flat_map(|root| watched_dirs(root, &self.expanded_paths))
+            .collect();
+        for dir in self.fs_watched_dirs.difference(&dirs) {
+            let _ = watcher.unwatch(dir);
         }
-
-        self.fs_watcher = Some(watcher);
-        self.fs_watcher_root = Some(root_path.clone());
-        self.fs_event_rx = Some(rx);
+        for dir in dirs.difference(&self.fs_watched_dirs) {
+            if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                println!("FileTree fs watcher watch failed: {:?} ({:?})", err, dir);
+            }
+        }
+        self.fs_watched_dirs = dirs;
     }
 
-    fn drain_fs_events(&mut self) -> bool {
-        let mut changed = false;
+    /// 收集新事件；一批事件安静下来后返回整批
+    fn drain_fs_events(&mut self) -> Option<Vec<TreeChange>> {
         let mut disconnected = false;
+        let now = Instant::now();
 
         if let Some(rx) = self.fs_event_rx.as_ref() {
             loop {
                 match rx.try_recv() {
-                    Ok(()) => changed = true,
+                    Ok(change) => self.fs_batch.push(change, now),
                     Err(mpsc::TryRecvError::Empty) => break,
                     Err(mpsc::TryRecvError::Disconnected) => {
                         disconnected = true;
@@ -194,10 +280,34 @@ impl FileTree {
         if disconnected {
             self.fs_event_rx = None;
             self.fs_watcher = None;
-            self.fs_watcher_root = None;
+            self.fs_watcher_roots.clear();
+            self.fs_watched_dirs.clear();
         }
 
-        changed
+        self.fs_batch.take_ready(now)
+    }
+
+    /// 重新读取目录，展开状态和滚动位置保持不变；改名的节点保持选中和展开
+    fn apply_fs_changes(&mut self, changes: Vec<TreeChange>) {
+        for change in changes {
+            if let TreeChange::Renamed { from, to } = change {
+                self.remap_renamed(&from, &to);
+            }
+        }
+        self.refresh_internal(true);
+    }
+
+    /// 选中和展开状态跟随改名后的路径
+    fn remap_renamed(&mut self, from: &Path, to: &Path) {
+        if let Some(selected) = self.selected_path.as_ref().and_then(|p| rename_path(p, from, to)) {
+            self.selected_path = Some(selected);
+        }
+        self.selection.remap(from, to);
+        self.expanded_paths = self
+            .expanded_paths
+            .drain()
+            .map(|p| rename_path(&p, from, to).unwrap_or(p))
+            .collect();
     }
 
     fn refresh_internal(&mut self, preserve_scroll: bool) {
@@ -208,29 +318,45 @@ impl FileTree {
         };
 
         self.visible_entries.clear();
-        let root_path = match self.root_path.as_ref() {
-            Some(path) => path.clone(),
-            None => {
-                self.list_state.reset(0);
-                return;
+        // 已删除或移走的项不再保留在多选中
+        self.selection.retain(|p| p.exists());
+        let roots = self.roots();
+        if roots.is_empty() {
+            self.list_state.reset(0);
+            return;
+        }
+        for root_path in roots {
+            let root_name = root_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| root_path.to_string_lossy().to_string());
+            let root_expanded = self.expanded_paths.contains(&root_path);
+            self.visible_entries.push(FileEntry {
+                path: root_path.clone(),
+                name: root_name,
+                is_dir: true,
+                depth: 0,
+                is_expanded: root_expanded,
+                dimmed: self.filter_result.is_some(),
+                ignored: false,
+            });
+            if root_expanded {
+                self.append_entries(&root_path, 1);
             }
-        };
-        let root_name = root_path
-            .file_name()
-            .map(|name| name.to_string_lossy().to_string())
-            .unwrap_or_else(|| root_path.to_string_lossy().to_string());
-        let root_expanded = self.expanded_paths.contains(&root_path);
-        self.visible_entries.push(FileEntry {
-            path: root_path.clone(),
-            name: root_name,
-            is_dir: true,
-            depth: 0,
-            is_expanded: root_expanded,
-        });
-        if root_expanded {
-            self.append_entries(&root_path.clone(), 1);
         }
-        if self.pending_new_item.is_some() {
+        if let Some(path) = self.pending_new_item.as_ref().and_then(|p| p.rename.clone()) {
+            // 被重命名的项已经不在列表中（例如在外部被删除）时放弃输入
+            match self.visible_entries.iter().position(|e| e.path == path) {
+                Some(index) => {
+                    if let Some(pending) = self.pending_new_item.as_mut() {
+                        pending.insert_index = index;
+                        pending.depth = self.visible_entries[index].depth;
+                    }
+                }
+                None => self.pending_new_item = None,
+            }
+            self.list_state.reset(self.visible_entries.len());
+        } else if self.pending_new_item.is_some() {
             let (insert_index, depth) = {
                 let pending = self.pending_new_item.as_ref().expect("checked above");
                 self.inline_insert_position(&pending.anchor_path, pending.anchor_is_dir)
@@ -251,16 +377,278 @@ impl FileTree {
 
     pub fn set_root_path(&mut self, path: PathBuf, cx: &mut Context<Self>) {
         self.root_path = Some(path);
+        self.extra_roots.clear();
+        self.filter = NameInput::default();
+        self.filter_result = None;
+        self.filter_task = None;
+        self.expanded_before_filter = None;
         self.expanded_paths.clear();
+        self.selection.clear();
+        self.ignore_rules = IgnoreRules::new(&self.roots());
         self.refresh_internal(false);
         self.sync_fs_watcher();
         cx.notify();
     }
 
+    pub fn root_path(&self) -> Option<&PathBuf> {
+        self.root_path.as_ref()
+    }
+
+    pub fn extra_roots(&self) -> &[PathBuf] {
+        &self.extra_roots
+    }
+
+    /// 工作区的所有根目录，第一个是打开的文件夹
+    pub fn roots(&self) -> Vec<PathBuf> {
+        self.root_path.iter().chain(&self.extra_roots).cloned().collect()
+    }
+
+    /// 包含 `path` 的根目录；根目录相互嵌套时取最内层的
+    pub fn root_for(&self, path: &Path) -> Option<&PathBuf> {
+        self.root_path
+            .iter()
+            .chain(&self.extra_roots)
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+    }
+
+    /// 把文件夹加入工作区；还没有打开文件夹时等同于打开它。已在工作区中时返回 false
+    pub fn add_root(&mut self, path: PathBuf, cx: &mut Context<Self>) -> bool {
+        if self.root_path.is_none() {
+            self.set_root_path(path, cx);
+            return true;
+        }
+        if self.roots().contains(&path) {
+            return false;
+        }
+        self.extra_roots.push(path);
+        self.ignore_rules = IgnoreRules::new(&self.roots());
+        self.refresh_internal(true);
+        self.sync_fs_watcher();
+        if self.filter_result.is_some() {
+            self.schedule_filter(cx);
+        }
+        cx.notify();
+        true
+    }
+
+    /// 根目录 `root` 下的展开状态；`root` 是打开的文件夹时还记录滚动位置
+    pub fn tree_state(&self, root: &Path) -> TreeState {
+        // 筛选时展开的是匹配项的上层目录，记录筛选前的状态
+        let expanded_paths = self.expanded_before_filter.as_ref().unwrap_or(&self.expanded_paths);
+        let mut expanded: Vec<PathBuf> = expanded_paths
+            .iter()
+            .filter(|path| self.root_for(path).is_some_and(|r| r == root))
+            .filter_map(|path| path.strip_prefix(root).ok().map(Path::to_path_buf))
+            .collect();
+        expanded.sort();
+        let mut state = TreeState { expanded, ..TreeState::default() };
+        if self.root_path.as_deref() == Some(root) {
+            let top = self.list_state.logical_scroll_top();
+            state.scroll_item = top.item_ix;
+            state.scroll_offset = f32::from(top.offset_in_item);
+        }
+        state
+    }
+
+    /// 恢复根目录 `root` 下的展开状态；已不存在的文件夹跳过
+    pub fn restore_tree_state(&mut self, root: &Path, state: &TreeState, cx: &mut Context<Self>) {
+        for dir in &state.expanded {
+            let path = root.join(dir);
+            if path.is_dir() {
+                self.expanded_paths.insert(path.components().collect());
+            }
+        }
+        self.refresh_internal(true);
+        if self.root_path.as_deref() == Some(root) {
+            let item_ix = state.scroll_item.min(self.visible_entries.len().saturating_sub(1));
+            self.list_state.scroll_to(ListOffset { item_ix, offset_in_item: px(state.scroll_offset) });
+        }
+        self.sync_fs_watcher();
+        cx.notify();
+    }
+
+    /// 切换是否显示以 `.` 开头的文件和文件夹
+    pub fn toggle_hidden(&mut self, cx: &mut Context<Self>) {
+        self.show_hidden = !self.show_hidden;
+        self.refresh_internal(true);
+        cx.notify();
+    }
+
+    /// 重新读取 .gitignore，例如在编辑器中保存了它之后
+    pub fn reload_ignore_rules(&mut self, cx: &mut Context<Self>) {
+        self.ignore_rules = IgnoreRules::new(&self.roots());
+        self.refresh_internal(true);
+        cx.notify();
+    }
+
+    /// 从工作区移除后来加入的文件夹；打开的文件夹本身不能移除
+    pub fn remove_root(&mut self, path: &Path, cx: &mut Context<Self>) {
+        let before = self.extra_roots.len();
+        self.extra_roots.retain(|root| root != path);
+        if self.extra_roots.len() == before {
+            return;
+        }
+        // 只清理不再属于任何根目录的状态，嵌套的根目录保留自己的展开项
+        let roots = self.roots();
+        let orphaned = |p: &PathBuf| p.starts_with(path) && !roots.iter().any(|root| p.starts_with(root));
+        self.expanded_paths.retain(|p| !orphaned(p));
+        if self.selected_path.as_ref().is_some_and(orphaned) {
+            self.selected_path = None;
+        }
+        self.selection.retain(|p| !orphaned(&p.to_path_buf()));
+        if let Some(expanded) = self.expanded_before_filter.as_mut() {
+            expanded.retain(|p| !orphaned(p));
+        }
+        self.ignore_rules = IgnoreRules::new(&roots);
+        self.refresh_internal(true);
+        self.sync_fs_watcher();
+        if self.filter_result.is_some() {
+            self.schedule_filter(cx);
+        }
+        cx.notify();
+    }
+
     pub fn toggle_dir(&mut self, path: PathBuf, cx: &mut Context<Self>) {
         self.toggle_expand(path, cx);
     }
 
+    /// 把键盘输入交给顶部的筛选框
+    pub fn focus_filter(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.focus_handle.focus(window);
+        self.filter_focused = true;
+        cx.notify();
+    }
+
+    fn edit_filter(&mut self, cx: &mut Context<Self>, edit: impl FnOnce(&mut NameInput)) {
+        let before = self.filter.text().to_string();
+        edit(&mut self.filter);
+        if self.filter.text() != before {
+            self.schedule_filter(cx);
+        }
+        cx.notify();
+    }
+
+    /// 在 [`FILTER_DELAY`] 内没有新的输入时，在后台遍历项目并筛选；筛选框清空时立即恢复原来的展开状态
+    fn schedule_filter(&mut self, cx: &mut Context<Self>) {
+        let query = self.filter.text().trim().to_string();
+        let roots = self.roots();
+        if roots.is_empty() || query.is_empty() {
+            self.filter_task = None;
+            if self.filter_result.take().is_some() {
+                if let Some(expanded) = self.expanded_before_filter.take() {
+                    self.expanded_paths = expanded;
+                }
+                self.refresh_internal(false);
+            }
+            return;
+        }
+        let executor = cx.background_executor().clone();
+        self.filter_task = Some(cx.spawn(move |view: WeakEntity<FileTree>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(FILTER_DELAY).await;
+                let filter = executor
+                    .spawn(async move {
+                        let mut filter = TreeFilter::default();
+                        for root in &roots {
+                            filter.extend(TreeFilter::new(root, &query, project_paths(root)));
+                        }
+                        filter
+                    })
+                    .await;
+                view.update(&mut cx, |this, cx| {
+                    let expanded = this
+                        .expanded_before_filter
+                        .get_or_insert_with(|| this.expanded_paths.clone())
+                        .clone();
+                    this.expanded_paths = expanded.union(filter.ancestors()).cloned().collect();
+                    this.filter_result = Some(filter);
+                    this.refresh_internal(false);
+                    cx.notify();
+                })
+                .ok();
+            }
+        }));
+    }
+
+    /// 筛选框获得焦点时的按键；返回 false 表示按键不由筛选框处理
+    fn filter_key_down(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) -> bool {
+        let key = event.keystroke.key.as_str();
+        match key {
+            "escape" => {
+                // 先清空筛选，再按一次离开筛选框
+                if self.filter.text().is_empty() {
+                    self.filter_focused = false;
+                    cx.notify();
+                } else {
+                    self.edit_filter(cx, |input| *input = NameInput::default());
+                }
+                return true;
+            }
+            "enter" | "down" => {
+                self.filter_focused = false;
+                cx.notify();
+                return true;
+            }
+            "backspace" => {
+                self.edit_filter(cx, NameInput::backspace);
+                return true;
+            }
+            "delete" => {
+                self.edit_filter(cx, NameInput::delete);
+                return true;
+            }
+            "left" | "right" | "home" | "end" => {
+                self.edit_filter(cx, |input| match key {
+                    "left" => input.move_left(),
+                    "right" => input.move_right(),
+                    "home" => input.move_home(),
+                    _ => input.move_end(),
+                });
+                return true;
+            }
+            _ => {}
+        }
+
+        let modifiers = event.keystroke.modifiers;
+        if modifiers.control || modifiers.alt || modifiers.platform || modifiers.function {
+            return false;
+        }
+        if let Some(text) = event.keystroke.key_char.as_ref() {
+            if !text.is_empty() && !text.chars().all(|c| c.is_control()) {
+                self.edit_filter(cx, |input| input.insert(text));
+                return true;
+            }
+        } else if key == "space" {
+            self.edit_filter(cx, |input| input.insert(" "));
+            return true;
+        }
+        false
+    }
+
+    /// 展开 `path` 的各级上层目录，选中它并滚动到可见处；不在当前根目录下时返回 false
+    pub fn reveal_path(&mut self, path: &Path, cx: &mut Context<Self>) -> bool {
+        let Some(root) = self.root_for(path).cloned() else {
+            return false;
+        };
+        let mut dir = path.parent();
+        while let Some(ancestor) = dir.filter(|d| d.starts_with(&root)) {
+            self.expanded_paths.insert(ancestor.to_path_buf());
+            dir = ancestor.parent();
+        }
+        self.refresh_internal(true);
+        let Some(index) = self.visible_entries.iter().position(|e| e.path == path) else {
+            cx.notify();
+            return false;
+        };
+        self.selected_path = Some(path.to_path_buf());
+        self.selection_time = Some(Instant::now());
+        self.list_state.scroll_to_reveal_item(index);
+        cx.notify();
+        true
+    }
+
     pub fn begin_inline_create(
         &mut self,
         anchor_path: PathBuf,
@@ -278,16 +666,56 @@ impl FileTree {
         self.pending_new_item = Some(InlineNewItem {
             anchor_path,
             anchor_is_dir,
-            name: String::new(),
+            input: NameInput::default(),
+            rename: None,
             is_dir: create_dir,
             depth,
             insert_index,
             editing: true,
+            error: None,
         });
         self.list_state.splice(insert_index..insert_index, 1);
         cx.notify();
     }
 
+    /// 在该项所在的行上直接编辑名称，预填原名并选中扩展名之前的部分
+    pub fn begin_inline_rename(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        self.remove_inline_item();
+        let Some((index, entry)) = self
+            .visible_entries
+            .iter()
+            .enumerate()
+            .find(|(_, entry)| entry.path == path)
+        else {
+            return;
+        };
+        // 根目录不能在这里重命名
+        if self.roots().contains(&path) {
+            return;
+        }
+        self.pending_new_item = Some(InlineNewItem {
+            anchor_path: path.clone(),
+            anchor_is_dir: false,
+            input: NameInput::for_rename(&entry.name),
+            rename: Some(path),
+            is_dir: entry.is_dir,
+            depth: entry.depth,
+            insert_index: index,
+            editing: true,
+            error: None,
+        });
+        self.list_state.splice(index..index + 1, 1);
+        cx.notify();
+    }
+
+    /// 由 git 面板在刷新后推送；不是 git 仓库时为空，不显示标记
+    pub fn set_git_status(&mut self, status: GitStatusMap, cx: &mut Context<Self>) {
+        if self.git_status != status {
+            self.git_status = status;
+            cx.notify();
+        }
+    }
+
     pub fn set_transparent(&mut self, transparent: bool, cx: &mut Context<Self>) {
         if self.transparent != transparent {
             self.transparent = transparent;
@@ -318,8 +746,9 @@ impl FileTree {
 
     fn remove_inline_item(&mut self) {
         if let Some(pending) = self.pending_new_item.take() {
+            let count = if pending.rename.is_some() { 1 } else { 0 };
             self.list_state
-                .splice(pending.insert_index..pending.insert_index + 1, 0);
+                .splice(pending.insert_index..pending.insert_index + 1, count);
         }
     }
 
@@ -328,97 +757,147 @@ impl FileTree {
         cx.notify();
     }
 
+    fn inline_parent(&self, pending: &InlineNewItem) -> Option<PathBuf> {
+        if pending.anchor_is_dir {
+            return Some(pending.anchor_path.clone());
+        }
+        let root_path = self.root_for(&pending.anchor_path)?;
+        Some(pending.anchor_path.parent().unwrap_or(root_path).to_path_buf())
+    }
+
+    fn validate_inline_item(&mut self) {
+        let Some(pending) = self.pending_new_item.as_ref() else {
+            return;
+        };
+        let name = pending.input.text().trim();
+        let error = match &pending.rename {
+            Some(from) => validate_rename(from, name).err(),
+            None => self
+                .inline_parent(pending)
+                .and_then(|parent| validate_new_entry_name(&parent, name).err()),
+        };
+        if let Some(pending) = self.pending_new_item.as_mut() {
+            pending.error = error;
+        }
+    }
+
     fn commit_inline_item(&mut self, cx: &mut Context<Self>) {
-        let pending = match self.pending_new_item.take() {
-            Some(pending) => pending,
+        let pending = match self.pending_new_item.as_ref() {
+            Some(pending) => pending.clone(),
             None => return,
         };
-
-        self.list_state
-            .splice(pending.insert_index..pending.insert_index + 1, 0);
-
-        let name = pending.name.trim().to_string();
-        if name.is_empty() {
-            cx.notify();
+        if let Some(from) = pending.rename.clone() {
+            self.commit_inline_rename(from, pending.input.text().trim().to_string(), cx);
             return;
         }
 
-        let root_path = match self.root_path.as_ref() {
-            Some(path) => path,
-            None => {
-                cx.notify();
+        let name = pending.input.text().trim().to_string();
+        let parent = match self.inline_parent(&pending) {
+            Some(parent) if !name.is_empty() => parent,
+            _ => {
+                self.cancel_inline_item(cx);
                 return;
             }
         };
-        let parent = if pending.anchor_is_dir {
-            pending.anchor_path
+        if let Err(error) = validate_new_entry_name(&parent, &name) {
+            // 保持输入状态，让用户修改
+            if let Some(pending) = self.pending_new_item.as_mut() {
+                pending.error = Some(error);
+            }
+            cx.notify();
+            return;
+        }
+
+        // "src/utils/helper.t" 会依次创建中间文件夹
+        let target = parent.join(&name);
+        let result = if pending.is_dir {
+            fs::create_dir_all(&target)
         } else {
-            pending
-                .anchor_path
+            target
                 .parent()
-                .unwrap_or(root_path)
-                .to_path_buf()
+                .map(fs::create_dir_all)
+                .unwrap_or(Ok(()))
+                .and_then(|_| {
+                    fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&target)
+                        .map(|_| ())
+                })
         };
-
-        let segments: Vec<String> = name
-            .split(|c| c == '/' || c == '\\')
-            .filter(|part| !part.is_empty())
-            .map(|part| part.to_string())
-            .collect();
-        if segments.is_empty() {
+        if let Err(err) = result {
+            if let Some(pending) = self.pending_new_item.as_mut() {
+                pending.error = Some(format!("创建失败：{}", err));
+            }
             cx.notify();
             return;
         }
 
-        let mut current_parent = parent.clone();
-        if pending.is_dir {
-            for segment in segments {
-                let child_path = current_parent.join(&segment);
-                self.add_virtual_node(current_parent.clone(), segment, true);
-                self.expanded_paths.insert(current_parent.clone());
-                current_parent = child_path;
-            }
-            self.expanded_paths.insert(current_parent.clone());
-            self.selected_path = Some(current_parent);
+        self.remove_inline_item();
+        let mut dir = if pending.is_dir {
+            Some(target.as_path())
         } else {
-            let (dir_parts, file_part) = segments.split_at(segments.len() - 1);
-            for segment in dir_parts {
-                let child_path = current_parent.join(segment);
-                self.add_virtual_node(current_parent.clone(), segment.clone(), true);
-                self.expanded_paths.insert(current_parent.clone());
-                current_parent = child_path;
+            target.parent()
+        };
+        while let Some(path) = dir {
+            self.expanded_paths.insert(path.to_path_buf());
+            if path == parent {
+                break;
             }
-            let file_name = file_part[0].clone();
-            self.add_virtual_node(current_parent.clone(), file_name.clone(), false);
-            self.expanded_paths.insert(current_parent.clone());
-            self.selected_path = Some(current_parent.join(file_name));
+            dir = path.parent();
         }
-
+        self.selected_path = Some(target.clone());
         self.selection_time = Some(Instant::now());
         self.refresh_internal(true);
+        if let Some(index) = self.visible_entries.iter().position(|e| e.path == target) {
+            self.list_state.scroll_to_reveal_item(index);
+        }
+        if !pending.is_dir {
+            cx.emit(FileTreeEvent::OpenFile(target));
+        }
         cx.notify();
     }
 
-    fn backspace_inline_item(&mut self, cx: &mut Context<Self>) {
-        if let Some(pending) = self.pending_new_item.as_mut() {
-            if !pending.editing || pending.name.is_empty() {
+    fn commit_inline_rename(&mut self, from: PathBuf, name: String, cx: &mut Context<Self>) {
+        let unchanged = from.file_name().is_some_and(|old| old.to_string_lossy() == name.as_str());
+        if name.is_empty() || unchanged {
+            self.cancel_inline_item(cx);
+            return;
+        }
+        let result = validate_rename(&from, &name).and_then(|_| {
+            let to = from.with_file_name(&name);
+            fs::rename(&from, &to)
+                .map(|_| to)
+                .map_err(|err| format!("重命名失败：{}", err))
+        });
+        let to = match result {
+            Ok(to) => to,
+            Err(error) => {
+                // 保持输入状态，让用户修改；不会覆盖已有的文件
+                if let Some(pending) = self.pending_new_item.as_mut() {
+                    pending.error = Some(error);
+                }
+                cx.notify();
                 return;
             }
-            let prev = prev_char_boundary(&pending.name, pending.name.len());
-            pending.name.truncate(prev);
-            cx.notify();
-        }
+        };
+
+        self.remove_inline_item();
+        self.remap_renamed(&from, &to);
+        self.selected_path = Some(to.clone());
+        self.refresh_internal(true);
+        cx.emit(FileTreeEvent::Renamed { from, to });
+        cx.notify();
     }
 
-    fn append_inline_text(&mut self, text: &str, cx: &mut Context<Self>) {
-        if text.is_empty() {
-            return;
-        }
+    /// 编辑内联输入的文本；`edit` 返回后重新校验
+    fn edit_inline_item(&mut self, cx: &mut Context<Self>, edit: impl FnOnce(&mut NameInput)) {
         if let Some(pending) = self.pending_new_item.as_mut() {
             if !pending.editing {
                 return;
             }
-            pending.name.push_str(text);
+            edit(&mut pending.input);
+            self.validate_inline_item();
             cx.notify();
         }
     }
@@ -435,13 +914,6 @@ impl FileTree {
             }
         }
 
-        if let Some(virtuals) = self.virtual_nodes.get(path) {
-            for virtual_node in virtuals {
-                let child_path = path.join(&virtual_node.name);
-                children.push((child_path, virtual_node.name.clone(), virtual_node.is_dir));
-            }
-        }
-
         // Sort: Directories first, then files
         children.sort_by(|a, b| {
             if a.2 == b.2 {
@@ -452,7 +924,16 @@ impl FileTree {
         });
 
         for (child_path, name, is_dir) in children {
+            if !self.show_hidden && is_hidden(&name) {
+                continue;
+            }
+            let dimmed = match &self.filter_result {
+                Some(filter) if !filter.shows(&child_path) => continue,
+                Some(filter) => !filter.is_match(&child_path),
+                None => false,
+            };
             let is_expanded = self.expanded_paths.contains(&child_path);
+            let ignored = self.ignore_rules.is_ignored(&child_path, is_dir);
 
             self.visible_entries.push(FileEntry {
                 path: child_path.clone(),
@@ -460,6 +941,8 @@ impl FileTree {
                 is_dir,
                 depth,
                 is_expanded,
+                dimmed,
+                ignored,
             });
 
             if is_dir && is_expanded {
@@ -519,6 +1002,105 @@ impl FileTree {
         self.drag_source.clone()
     }
 
+    /// 正在拖动的项数：拖动的是多选中的一项时为最上层选中项的个数
+    pub fn drag_count(&self) -> usize {
+        match &self.drag_source {
+            Some(source) if self.selection.contains(source) => self.selection.top_level().len(),
+            Some(_) => 1,
+            None => 0,
+        }
+    }
+
+    /// 拖动中鼠标移到文件树外时更新拖动图标的位置
+    pub fn drag_moved(&mut self, position: Point<Pixels>, cx: &mut Context<Self>) {
+        self.mouse_position = position;
+        cx.notify();
+    }
+
+    /// 拖到其它放置区域上时取消文件夹的放置高亮
+    pub fn drag_outside(&mut self, position: Point<Pixels>, cx: &mut Context<Self>) {
+        self.drag_hover = None;
+        self.drag_moved(position, cx);
+    }
+
+    /// 在文件树外松开时结束拖动，返回拖动的各项；没有在拖动时为空
+    pub fn take_drag(&mut self, cx: &mut Context<Self>) -> Vec<PathBuf> {
+        let active = self.drag_active;
+        self.drag_active = false;
+        self.drag_start_position = None;
+        self.drag_hover = None;
+        let Some(source) = self.drag_source.take().filter(|_| active) else {
+            return Vec::new();
+        };
+        cx.notify();
+        if self.selection.contains(&source) {
+            self.selection.top_level()
+        } else {
+            vec![source]
+        }
+    }
+
+    pub fn cancel_drag(&mut self, cx: &mut Context<Self>) {
+        self.take_drag(cx);
+    }
+
+    /// 展开文件夹 `path` 并滚动到它
+    pub fn expand_and_reveal(&mut self, path: &Path, cx: &mut Context<Self>) -> bool {
+        self.expanded_paths.insert(path.to_path_buf());
+        self.reveal_path(path, cx)
+    }
+
+    fn visible_paths(&self) -> Vec<PathBuf> {
+        self.visible_entries.iter().map(|e| e.path.clone()).collect()
+    }
+
+    /// 最上层的选中项及其是否为文件夹；只选中一项时为空
+    fn multi_selection(&self) -> Vec<(PathBuf, bool)> {
+        if self.selection.len() < 2 {
+            return Vec::new();
+        }
+        self.selection.top_level().into_iter().map(|path| {
+            let is_dir = path.is_dir();
+            (path, is_dir)
+        }).collect()
+    }
+
+    /// Ctrl 点击切换、Shift 点击扩展选区；返回 false 表示是普通点击
+    fn click_select(&mut self, path: &Path, modifiers: Modifiers) -> bool {
+        if modifiers.shift {
+            let visible = self.visible_paths();
+            self.selection.extend_to(&visible, path);
+        } else if modifiers.secondary() {
+            self.selection.toggle(path);
+        } else {
+            self.selection.select_only(path);
+            return false;
+        }
+        self.selected_path = Some(path.to_path_buf());
+        true
+    }
+
+    /// 把拖动的项移到 `dst_dir`，跳过移到自身或自身内部的项
+    fn finish_drag(&mut self, src: PathBuf, dst_dir: PathBuf, cx: &mut Context<Self>) {
+        let sources = if self.selection.contains(&src) { self.selection.top_level() } else { vec![src] };
+        let mut moves = Vec::new();
+        for src in sources {
+            if dst_dir == src || self.is_descendant(&src, &dst_dir) {
+                println!("Invalid move: {:?} -> {:?}", src, dst_dir);
+                continue;
+            }
+            if let Some(name) = src.file_name() {
+                let dst = dst_dir.join(name);
+                if dst != src {
+                    moves.push((src, dst));
+                }
+            }
+        }
+        if !moves.is_empty() {
+            cx.emit(FileTreeEvent::RequestMove { moves });
+        }
+    }
+
     pub fn mouse_position(&self) -> Point<Pixels> {
         self.mouse_position
     }
@@ -548,24 +1130,43 @@ impl FileTree {
         false
     }
 
-    fn add_virtual_node(&mut self, parent: PathBuf, name: String, is_dir: bool) {
-        let entry = self.virtual_nodes.entry(parent).or_insert_with(Vec::new);
-        if entry
-            .iter()
-            .any(|node| node.name == name && node.is_dir == is_dir)
-        {
-            return;
-        }
-        entry.push(VirtualNode { name, is_dir });
-    }
-
     fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
         let editing = self
             .pending_new_item
             .as_ref()
             .map(|item| item.editing)
             .unwrap_or(false);
+        if !editing && self.filter_focused {
+            if self.filter_key_down(event, cx) {
+                cx.stop_propagation();
+            }
+            return;
+        }
         if !editing {
+            let key = event.keystroke.key.as_str();
+            if key == "escape" && self.drag_active {
+                self.cancel_drag(cx);
+                cx.stop_propagation();
+            } else if key == "f2" {
+                if let Some(path) = self.selected_path.clone() {
+                    self.begin_inline_rename(path, cx);
+                    cx.stop_propagation();
+                }
+            } else if key == "a" && event.keystroke.modifiers.secondary() {
+                if let Some(path) = self.selected_path.clone() {
+                    let visible = self.visible_paths();
+                    self.selection.select_siblings(&visible, &path);
+                    cx.stop_propagation();
+                    cx.notify();
+                }
+            } else if key == "delete" && !self.selection.is_empty() {
+                let entries = self.selection.top_level().into_iter().map(|path| {
+                    let is_dir = path.is_dir();
+                    (path, is_dir)
+                }).collect();
+                cx.emit(FileTreeEvent::RequestDelete { entries });
+                cx.stop_propagation();
+            }
             return;
         }
 
@@ -581,8 +1182,23 @@ impl FileTree {
                 cx.stop_propagation();
                 return;
             }
-            "backspace" | "delete" => {
-                self.backspace_inline_item(cx);
+            "backspace" => {
+                self.edit_inline_item(cx, NameInput::backspace);
+                cx.stop_propagation();
+                return;
+            }
+            "delete" => {
+                self.edit_inline_item(cx, NameInput::delete);
+                cx.stop_propagation();
+                return;
+            }
+            "left" | "right" | "home" | "end" => {
+                self.edit_inline_item(cx, |input| match key {
+                    "left" => input.move_left(),
+                    "right" => input.move_right(),
+                    "home" => input.move_home(),
+                    _ => input.move_end(),
+                });
                 cx.stop_propagation();
                 return;
             }
@@ -595,12 +1211,12 @@ impl FileTree {
         }
 
         if let Some(text) = event.keystroke.key_char.as_ref() {
-            if !text.chars().all(|c| c.is_control()) {
-                self.append_inline_text(text, cx);
+            if !text.is_empty() && !text.chars().all(|c| c.is_control()) {
+                self.edit_inline_item(cx, |input| input.insert(text));
                 cx.stop_propagation();
             }
         } else if key == "space" {
-            self.append_inline_text(" ", cx);
+            self.edit_inline_item(cx, |input| input.insert(" "));
             cx.stop_propagation();
         }
     }
@@ -608,11 +1224,13 @@ impl FileTree {
 
 impl Render for FileTree {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let theme_surface = if self.transparent { rgba(0x00000000) } else { rgb(0xff252526) };
-        let drop_highlight = rgb(0xff3c474d);
+        let theme = crate::component::theme(cx);
+        let theme_surface = if self.transparent { transparent_black() } else { theme.sidebar };
+        let drop_highlight = theme.border;
         let visible_entries = self.visible_entries.clone();
         let drag_hover = self.drag_hover.clone();
         let pending_new_item = self.pending_new_item.clone();
+        let git_status = self.git_status.clone();
         let view = cx.entity().clone();
 
         let view_mousemove = view.clone();
@@ -628,11 +1246,12 @@ impl Render for FileTree {
                     .items_center()
                     .justify_center()
                     .text_size(px(12.0))
-                    .text_color(rgb(0xff8b949e))
+                    .text_color(theme.muted_text)
                     .child("拖拽文件夹打开");
             }
         };
         let selected_path = self.selected_path.clone();
+        let selection = self.selection.clone();
         let selection_time = self.selection_time;
         let selected_parent_path = selected_path
             .as_ref()
@@ -640,9 +1259,13 @@ impl Render for FileTree {
             .map(|path| path.to_path_buf());
         let selected_parent_depth = selected_parent_path
             .as_ref()
-            .and_then(|path| path.strip_prefix(&root_path).ok())
+            .and_then(|path| path.strip_prefix(self.root_for(path).unwrap_or(&root_path)).ok())
             .map(|relative| relative.components().count());
-        let pending_index = pending_new_item.as_ref().map(|item| item.insert_index);
+        // 只有新建时输入行是额外插入的一行；重命名时它代替原来的行
+        let pending_index = pending_new_item
+            .as_ref()
+            .filter(|item| item.rename.is_none())
+            .map(|item| item.insert_index);
         let selected_row_index = selected_path
             .as_ref()
             .and_then(|path| visible_entries.iter().position(|e| &e.path == path))
@@ -658,9 +1281,13 @@ impl Render for FileTree {
                 }
             });
 
+        let filter_box = self.render_filter_box(cx);
+
         div()
             .w_full()
             .h_full()
+            .flex()
+            .flex_col()
             .bg(theme_surface)
             .track_focus(&self.focus_handle)
             .on_key_down(cx.listener(Self::on_key_down))
@@ -672,39 +1299,74 @@ impl Render for FileTree {
                     }
                 });
             })
+            .child(filter_box)
             .child(
-                list(self.list_state.clone(), move |ix, _window, _cx| {
-                    let total_len =
-                        visible_entries.len() + pending_new_item.as_ref().map(|_| 1).unwrap_or(0);
+                list(self.list_state.clone(), move |ix, _window, cx| {
+                    let total_len = visible_entries.len() + pending_index.map(|_| 1).unwrap_or(0);
                     if ix >= total_len {
                         return div().id("empty").into_any_element();
                     }
 
                     if let Some(pending) = pending_new_item.as_ref() {
                         if ix == pending.insert_index {
-                            let theme_text = rgb(0xffcccccc);
-                            let placeholder_color = rgb(0xff8b949e);
-                            let theme_selected = rgb(0xff37373d);
-                            let placeholder = if pending.is_dir {
-                                "新建文件夹"
-                            } else {
-                                "新建文件"
+                            let theme_text = theme.text;
+                            let placeholder_color = theme.muted_text;
+                            let theme_selected = theme.list_selection;
+                            let placeholder = match (&pending.rename, pending.is_dir) {
+                                (Some(path), _) => path
+                                    .file_name()
+                                    .map(|n| n.to_string_lossy().to_string())
+                                    .unwrap_or_default(),
+                                (None, true) => "新建文件夹".to_string(),
+                                (None, false) => "新建文件".to_string(),
                             };
-                            let display_text = if pending.name.is_empty() {
-                                placeholder.to_string()
+                            let name = pending.input.text();
+                            let icon = if pending.is_dir {
+                                folder_icon(name, false, cx).into_any_element()
                             } else {
-                                pending.name.clone()
+                                file_icon(name, cx).into_any_element()
                             };
-                            let text_color = if pending.name.is_empty() {
-                                placeholder_color
-                            } else {
-                                theme_text
+                            let caret = || {
+                                if pending.editing {
+                                    div()
+                                        .w(px(1.0))
+                                        .h(px(14.0))
+                                        .bg(theme_text)
+                                        .into_any_element()
+                                } else {
+                                    div().into_any_element()
+                                }
                             };
-                            let icon = if pending.is_dir {
-                                folder_icon().into_any_element()
+                            // 选中的部分加底色，光标画在选区靠近它的一端
+                            let mut text = div()
+                                .flex()
+                                .items_center()
+                                .text_size(px(13.0))
+                                .text_color(theme_text);
+                            if name.is_empty() {
+                                text = text
+                                    .text_color(placeholder_color)
+                                    .child(placeholder)
+                                    .child(div().ml(px(2.0)).child(caret()));
                             } else {
-                                file_icon(&pending.name).into_any_element()
-                            };
+                                let cursor = pending.input.cursor();
+                                let selection = pending.input.selection().unwrap_or(cursor..cursor);
+                                text = text.child(name[..selection.start].to_string());
+                                if cursor == selection.start {
+                                    text = text.child(caret());
+                                }
+                                if !selection.is_empty() {
+                                    text = text.child(
+                                        div()
+                                            .bg(theme.text_selection)
+                                            .child(name[selection.clone()].to_string()),
+                                    );
+                                }
+                                if cursor != selection.start {
+                                    text = text.child(caret());
+                                }
+                                text = text.child(name[selection.end..].to_string());
+                            }
 
                             let mut row = div()
                                 .id(SharedString::from(format!(
@@ -730,28 +1392,29 @@ impl Render for FileTree {
                                         .top(px(0.0))
                                         .w(px(1.0))
                                         .h_full()
-                                        .bg(rgb(0x303030)),
+                                        .bg(theme.list_hover),
                                 );
                             }
 
-                            row = row.child(div().mr(px(6.0)).child(icon)).child(
-                                div()
+                            row = row.child(div().mr(px(6.0)).child(icon)).child(text);
+
+                            if let Some(error) = pending.error.clone() {
+                                return div()
                                     .flex()
-                                    .items_center()
-                                    .text_size(px(13.0))
-                                    .text_color(text_color)
-                                    .child(display_text)
-                                    .child(if pending.editing {
+                                    .flex_col()
+                                    .child(row)
+                                    .child(
                                         div()
-                                            .ml(px(2.0))
-                                            .w(px(1.0))
-                                            .h(px(14.0))
-                                            .bg(theme_text)
-                                            .into_any_element()
-                                    } else {
-                                        div().into_any_element()
-                                    }),
-                            );
+                                            .pl(px(26.0 + pending.depth as f32 * 10.0))
+                                            .pr(px(8.0))
+                                            .py(px(2.0))
+                                            .text_size(px(12.0))
+                                            .text_color(theme.error)
+                                            .bg(theme.error.opacity(0.1))
+                                            .child(error),
+                                    )
+                                    .into_any_element();
+                            }
 
                             return row.into_any_element();
                         }
@@ -772,18 +1435,23 @@ impl Render for FileTree {
                     let is_dir = entry.is_dir;
                     let depth = entry.depth;
                     let is_expanded = entry.is_expanded;
+                    let dimmed = entry.dimmed;
+                    let ignored = entry.ignored;
                     let name = entry.name.clone();
+                    let file_status = git_status.file(&path);
+                    let dir_status = if is_dir { git_status.dir(&path) } else { None };
 
                     let view = view.clone();
                     let path_clone = path.clone();
 
-                    let theme_hover = rgb(0xff2a2d2e);
-                    let theme_text = rgb(0xffcccccc);
-                    let theme_selected = rgb(0xff37373d);
+                    let theme_hover = theme.list_hover;
+                    let theme_text = if ignored { theme.muted_text } else { theme.text };
+                    let theme_selected = theme.list_selection;
 
                     let is_drop_target =
                         drag_hover.as_ref().map(|p| p == &path).unwrap_or(false) && is_dir;
-                    let is_selected = selected_path.as_ref().map(|p| p == &path).unwrap_or(false);
+                    let is_selected = selected_path.as_ref().map(|p| p == &path).unwrap_or(false)
+                        || selection.contains(&path);
 
                     let view_click = view.clone();
                     let view_down = view.clone();
@@ -847,16 +1515,21 @@ impl Render for FileTree {
                                 .w(px(1.0))
                                 .h_full()
                                 .bg(if is_active {
-                                    rgb(0x505050)
+                                    theme.list_active
                                 } else {
-                                    rgb(0x303030)
+                                    theme.list_hover
                                 }),
                         );
                     }
 
                     row = row
-                        .on_click(move |_, _window, cx| {
+                        .on_click(move |e, _window, cx| {
                             view_click.update(cx, |this, cx| {
+                                this.filter_focused = false;
+                                if this.click_select(&path_click, e.modifiers()) {
+                                    cx.notify();
+                                    return;
+                                }
                                 this.selected_path = Some(path_click.clone());
                                 this.selection_time = Some(Instant::now());
                                 this.ensure_animation(cx);
@@ -880,6 +1553,10 @@ impl Render for FileTree {
                         .on_mouse_down(MouseButton::Right, move |e, _window, cx| {
                             view_right.update(cx, |this, cx| {
                                 println!("FileTree right-click position: {:?}", e.position);
+                                // 在多选中的项上右键时保留多选
+                                if !this.selection.contains(&path_right) {
+                                    this.selection.select_only(&path_right);
+                                }
                                 this.selected_path = Some(path_right.clone());
                                 this.selection_time = Some(Instant::now());
                                 this.ensure_animation(cx);
@@ -887,6 +1564,7 @@ impl Render for FileTree {
                                     position: e.position,
                                     path: path_right.clone(),
                                     is_dir,
+                                    selection: this.multi_selection(),
                                 });
                                 cx.notify();
                             });
@@ -924,16 +1602,7 @@ impl Render for FileTree {
                                     let dst = this.drag_hover.take();
                                     this.drag_active = false;
                                     if let (Some(src), Some(dst_dir)) = (src, dst) {
-                                        if dst_dir != src && !this.is_descendant(&src, &dst_dir) {
-                                            if let Some(name) = src.file_name() {
-                                                let dst = dst_dir.join(name);
-                                                if dst != src {
-                                                    cx.emit(FileTreeEvent::RequestMove { src, dst });
-                                                }
-                                            }
-                                        } else {
-                                            println!("Invalid move: {:?} -> {:?}", src, dst_dir);
-                                        }
+                                        this.finish_drag(src, dst_dir, cx);
                                     }
                                 } else {
                                     this.drag_source = None;
@@ -941,101 +1610,258 @@ impl Render for FileTree {
                             });
                         })
                         .child(div().mr(px(6.0)).child(if is_dir {
-                            if is_expanded {
-                                folder_open_icon().into_any_element()
-                            } else {
-                                folder_icon().into_any_element()
-                            }
+                            folder_icon(&name, is_expanded, cx).into_any_element()
                         } else {
-                            file_icon(&name).into_any_element()
+                            file_icon(&name, cx).into_any_element()
                         }))
-                        .child(div().text_size(px(13.0)).text_color(theme_text).child(name));
+                        .child(
+                            div()
+                                .flex_1()
+                                .min_w(px(0.0))
+                                .overflow_hidden()
+                                .whitespace_nowrap()
+                                .text_size(px(13.0))
+                                .text_color(file_status.map(|s| s.color(&theme)).unwrap_or(theme_text))
+                                .opacity(if dimmed { 0.5 } else { 1.0 })
+                                .child(name),
+                        );
+
+                    // 右侧的状态字母；文件夹内有变更时显示圆点
+                    if let Some(status) = file_status {
+                        row = row.child(
+                            div()
+                                .flex_none()
+                                .pl(px(6.0))
+                                .pr(px(10.0))
+                                .text_size(px(12.0))
+                                .text_color(status.color(&theme))
+                                .child(status.letter()),
+                        );
+                    } else if let Some(status) = dir_status {
+                        row = row.child(
+                            div()
+                                .flex_none()
+                                .mr(px(12.0))
+                                .size(px(6.0))
+                                .rounded_full()
+                                .bg(status.color(&theme)),
+                        );
+                    }
 
                     row.into_any_element()
                 })
                 .w_full()
-                .h_full(),
+                .flex_1(),
             )
     }
 }
 
-fn get_icon_path(name: &str) -> String {
-    let name_lower = name.to_lowercase();
-    match name_lower.as_str() {
-        "cargo.toml" => return "assets/icons/cargo_dark.svg".to_string(),
-        "cargo.lock" => return "assets/icons/cargoLock_dark.svg".to_string(),
-        ".gitignore" => return "assets/icons/gitignore.svg".to_string(),
-        "cmakelists.txt" => return "assets/icons/CMake_dark.svg".to_string(),
-        _ => {}
-    }
-
-    let ext = std::path::Path::new(name)
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-    let icon = match ext.as_str() {
-        "t" => "tie_file.svg",
-        "rs" => "rustFile_dark.svg",
-        "toml" => "toml_dark.svg",
-        "json" => "json_dark.svg",
-        "md" => "markdown_dark.svg",
-        "js" => "javaScript_dark.svg",
-        "ts" => "typeScript_dark.svg",
-        "tsx" => "tsx_dark.svg",
-        "jsx" => "jsx_dark.svg",
-        "html" => "html_dark.svg",
-        "css" => "css.svg",
-        "svg" | "png" | "jpg" | "jpeg" | "ico" => "image_dark.svg",
-        "lock" => "lock_dark.svg",
-        "yml" | "yaml" => "yaml_dark.svg",
-        "xml" => "xml_dark.svg",
-        "sql" => "sql_dark.svg",
-        "sh" => "shell_dark.svg",
-        "c" => "c_dark.svg",
-        "cpp" | "cc" | "cxx" => "cpp_dark.svg",
-        "h" => "h_dark.svg",
-        "py" => "python.svg",
-        "java" => "java_dark.svg",
-        "lua" => "lua.svg",
-        "go" => "go_dark.svg",
-        "php" => "php_dark.svg",
-        "rb" => "ruby_dark.svg",
-        "zip" | "tar" | "gz" | "7z" | "rar" => "archive_dark.svg",
-        "txt" => "text_dark.svg",
-        _ => "anyType_dark.svg",
-    };
-    format!("assets/icons/{}", icon)
+impl FileTree {
+    fn render_filter_box(&self, cx: &mut Context<Self>) -> Div {
+        let theme = crate::component::theme(cx);
+        let caret = || div().w(px(1.5)).h(px(14.0)).bg(theme.accent);
+        let text = self.filter.text();
+        let mut input = div()
+            .flex()
+            .items_center()
+            .overflow_hidden()
+            .whitespace_nowrap()
+            .text_size(px(12.0))
+            .text_color(theme.text);
+        if text.is_empty() {
+            if self.filter_focused {
+                input = input.child(caret());
+            }
+            input = input.child(div().text_color(theme.muted_text).child("筛选文件"));
+        } else {
+            let cursor = self.filter.cursor();
+            input = input.child(text[..cursor].to_string());
+            if self.filter_focused {
+                input = input.child(caret());
+            }
+            input = input.child(text[cursor..].to_string());
+        }
+
+        div()
+            .flex_none()
+            .m(px(6.0))
+            .h(px(24.0))
+            .px(px(6.0))
+            .flex()
+            .items_center()
+            .bg(theme.input_bg)
+            .border_1()
+            .border_color(if self.filter_focused { theme.accent } else { theme.input_border })
+            .rounded(px(3.0))
+            .cursor(CursorStyle::IBeam)
+            .child(input)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| {
+                    cx.stop_propagation();
+                    this.focus_filter(window, cx);
+                }),
+            )
+    }
 }
 
-fn folder_icon() -> impl IntoElement {
-    tie_svg()
-        .path("assets/icons/folder_dark.svg")
-        .size(px(16.0))
-        .original_colors(true)
+/// 校验新建项名称；`/` 表示有意创建中间文件夹，其它分隔符和系统不允许的字符会被拒绝
+fn validate_new_entry_name(parent: &Path, name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Ok(());
+    }
+    if name.contains('\\') {
+        return Err("名称中不能包含 \\，创建子文件夹请使用 /".to_string());
+    }
+    let segments: Vec<&str> = name.split('/').collect();
+    for segment in &segments {
+        validate_name_segment(segment)?;
+    }
+    let mut current = parent.to_path_buf();
+    for (i, segment) in segments.iter().enumerate() {
+        let is_last = i + 1 == segments.len();
+        match find_sibling(&current, segment) {
+            Some(existing) if is_last || !existing.is_dir() => {
+                return Err("已存在同名文件".to_string());
+            }
+            Some(existing) => current = existing,
+            None => return Ok(()),
+        }
+    }
+    Ok(())
 }
 
-fn folder_open_icon() -> impl IntoElement {
-    tie_svg()
-        .path("assets/icons/folder_dark.svg") // No open variant in list
-        .size(px(16.0))
-        .original_colors(true)
+/// 校验重命名后的名称：只能改名、不能移动，且不能与同目录下的其它项重名。
+/// 只改大小写时在不区分大小写的系统上找到的是自身，允许
+fn validate_rename(from: &Path, name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Ok(());
+    }
+    if name.contains(['/', '\\']) {
+        return Err("名称中不能包含 / 或 \\".to_string());
+    }
+    validate_name_segment(name)?;
+    let parent = from.parent().ok_or_else(|| "无法重命名根目录".to_string())?;
+    match find_sibling(parent, name) {
+        Some(existing) if existing != from => Err("已存在同名文件".to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn validate_name_segment(segment: &str) -> Result<(), String> {
+    if segment.is_empty() {
+        return Err("名称中不能有空的路径段".to_string());
+    }
+    if segment == "." || segment == ".." {
+        return Err(format!("不能使用 {} 作为名称", segment));
+    }
+    let invalid: &[char] = if cfg!(windows) {
+        &['<', '>', ':', '"', '|', '?', '*']
+    } else if cfg!(target_os = "macos") {
+        &[':']
+    } else {
+        &[]
+    };
+    if let Some(c) = segment
+        .chars()
+        .find(|c| c.is_control() || invalid.contains(c))
+    {
+        return Err(if c.is_control() {
+            "名称中不能包含控制字符".to_string()
+        } else {
+            format!("名称中不能包含 {}", c)
+        });
+    }
+    if cfg!(windows) {
+        if segment.ends_with('.') || segment.ends_with(' ') {
+            return Err("名称不能以点或空格结尾".to_string());
+        }
+        let stem = segment.split('.').next().unwrap_or("").to_ascii_uppercase();
+        let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+            || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+                && stem.len() == 4
+                && stem.as_bytes()[3].is_ascii_digit());
+        if reserved {
+            return Err(format!("{} 是系统保留名称", segment));
+        }
+    }
+    Ok(())
 }
 
-pub fn file_icon(name: &str) -> impl IntoElement {
-    tie_svg()
-        .path(get_icon_path(name))
-        .size(px(16.0))
-        .original_colors(true)
+/// 查找同名的已有项；Windows 和 macOS 的文件系统默认不区分大小写
+fn find_sibling(parent: &Path, name: &str) -> Option<PathBuf> {
+    find_sibling_with_case(parent, name, cfg!(any(windows, target_os = "macos")))
 }
 
-fn prev_char_boundary(text: &str, index: usize) -> usize {
-    if index == 0 {
-        return 0;
+fn find_sibling_with_case(parent: &Path, name: &str, ignore_case: bool) -> Option<PathBuf> {
+    if !ignore_case {
+        let path = parent.join(name);
+        return if path.exists() { Some(path) } else { None };
     }
-    let mut i = index.saturating_sub(1);
-    while i > 0 && !text.is_char_boundary(i) {
-        i = i.saturating_sub(1);
+    let lower = name.to_lowercase();
+    fs::read_dir(parent)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_name().to_string_lossy().to_lowercase() == lower)
+        .map(|entry| entry.path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_sibling_with_case, validate_new_entry_name, validate_rename};
+    use std::fs;
+
+    #[test]
+    fn test_new_entry_name_rejects_invalid_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        assert!(validate_new_entry_name(root, "").is_ok());
+        assert!(validate_new_entry_name(root, "a.t").is_ok());
+        assert!(validate_new_entry_name(root, "src/ui/a.t").is_ok());
+        for name in ["a\\b.t", "src//a.t", "/a.t", "src/", ".", "..", "src/../a.t", "a\tb.t", "a\u{7}.t"] {
+            assert!(validate_new_entry_name(root, name).is_err(), "{:?}", name);
+        }
+        if cfg!(windows) {
+            for name in ["a?.t", "a:b", "con", "COM1.txt", "a.", "a "] {
+                assert!(validate_new_entry_name(root, name).is_err(), "{:?}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_new_entry_name_detects_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("Src")).unwrap();
+        fs::write(root.join("Src/Main.t"), "").unwrap();
+        fs::write(root.join("notes"), "").unwrap();
+
+        assert!(validate_new_entry_name(root, "Src").is_err());
+        assert!(validate_new_entry_name(root, "Src/Main.t").is_err());
+        assert!(validate_new_entry_name(root, "Src/Other.t").is_ok());
+        // 中间路径段是已有的文件时无法在其下创建
+        assert!(validate_new_entry_name(root, "notes/a.t").is_err());
+
+        // 不区分大小写的文件系统上只有大小写不同也算重名
+        let ignore_case = cfg!(any(windows, target_os = "macos"));
+        assert_eq!(validate_new_entry_name(root, "src/main.t").is_err(), ignore_case);
+        assert_eq!(validate_new_entry_name(root, "NOTES").is_err(), ignore_case);
+        assert_eq!(find_sibling_with_case(root, "src", true), Some(root.join("Src")));
+        assert_eq!(find_sibling_with_case(&root.join("Src"), "MAIN.T", true), Some(root.join("Src/Main.t")));
+        assert_eq!(find_sibling_with_case(root, "Src", false), Some(root.join("Src")));
+        assert_eq!(find_sibling_with_case(root, "other", true), None);
+    }
+
+    #[test]
+    fn test_rename_allows_case_only_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.t"), "").unwrap();
+        fs::write(root.join("b.t"), "").unwrap();
+        let from = root.join("a.t");
+        assert!(validate_rename(&from, "A.t").is_ok());
+        assert!(validate_rename(&from, "c.t").is_ok());
+        assert!(validate_rename(&from, "b.t").is_err());
+        assert!(validate_rename(&from, "sub/c.t").is_err());
     }
-    i
 }
@@ -0,0 +1,106 @@
+//! 命令面板的模糊匹配：查询中的字符（忽略空白、不区分大小写）按顺序出现在文本中即为匹配。
+//! 在所有匹配方式中取得分最高的一种，单词开头和连续的字符得分更高，例如 “tft” 匹配 “Toggle File Tree”
+
+use std::ops::Range;
+
+const MATCH: i64 = 16;
+/// 匹配在单词开头：文本开头、分隔符之后或小写后的大写字母
+const WORD_START: i64 = 24;
+/// 紧接上一个匹配的字符
+const CONSECUTIVE: i64 = 12;
+/// 两个匹配之间每跳过一个字符的扣分
+const GAP: i64 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// 匹配到的字符在文本中的字节偏移，按顺序排列
+    pub positions: Vec<usize>,
+}
+
+fn is_word_start(chars: &[char], index: usize) -> bool {
+    let Some(prev) = index.checked_sub(1).map(|i| chars[i]) else {
+        return true;
+    };
+    let current = chars[index];
+    matches!(prev, ' ' | '_' | '-' | '.' | ':' | '/') || (prev.is_lowercase() && current.is_uppercase())
+}
+
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+    }
+    let (offsets, chars): (Vec<usize>, Vec<char>) = text.char_indices().unzip();
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let n = chars.len();
+
+    // best[i][j]：模式的前 i + 1 个字符已匹配、第 i 个字符匹配在文本第 j 个字符时的最高分
+    let mut best: Vec<Vec<Option<i64>>> = vec![vec![None; n]; pattern.len()];
+    let mut from: Vec<Vec<usize>> = vec![vec![0; n]; pattern.len()];
+    for (i, &p) in pattern.iter().enumerate() {
+        for j in i..n {
+            if lower[j] != p {
+                continue;
+            }
+            let base = MATCH + if is_word_start(&chars, j) { WORD_START } else { 0 };
+            if i == 0 {
+                best[i][j] = Some(base);
+                continue;
+            }
+            let previous = (i - 1..j)
+                .filter_map(|k| {
+                    let score = best[i - 1][k]?;
+                    let link = if k + 1 == j { CONSECUTIVE } else { -GAP * (j - k - 1) as i64 };
+                    Some((score + link, k))
+                })
+                .max_by_key(|(score, k)| (*score, std::cmp::Reverse(*k)));
+            if let Some((score, k)) = previous {
+                best[i][j] = Some(base + score);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (score, mut j) = (0..n).filter_map(|j| best[last][j].map(|score| (score, j))).max_by_key(|(score, _)| *score)?;
+    let mut positions = vec![0; pattern.len()];
+    for i in (0..pattern.len()).rev() {
+        positions[i] = offsets[j];
+        j = from[i][j];
+    }
+    // 同样的匹配下较短的文本排在前面
+    Some(FuzzyMatch { score: score - n as i64, positions })
+}
+
+/// 把匹配到的字符合并成连续的字节范围，用于高亮
+pub fn match_ranges(text: &str, positions: &[usize]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for &start in positions {
+        let end = start + text[start..].chars().next().map(char::len_utf8).unwrap_or(0);
+        match ranges.last_mut() {
+            Some(range) if range.end == start => range.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefers_word_starts_and_runs() {
+        let toggle = fuzzy_match("tft", "Toggle File Tree").unwrap();
+        assert_eq!(toggle.positions, vec![0, 7, 12]);
+        // 单词开头的匹配比贪心的第一个匹配得分更高
+        assert_eq!(fuzzy_match("ft", "Toggle File Tree").unwrap().positions, vec![7, 12]);
+        assert_eq!(fuzzy_match("sa", "core.save_as").unwrap().positions, vec![5, 10]);
+        assert_eq!(fuzzy_match("file", "Toggle File Tree").unwrap().positions, vec![7, 8, 9, 10]);
+        assert!(fuzzy_match("tft", "Toggle File Tree").unwrap().score > fuzzy_match("tft", "Toggle Hidden Files in File Tree").unwrap().score);
+        assert_eq!(fuzzy_match("xyz", "Toggle File Tree"), None);
+
+        assert_eq!(match_ranges("文件 File", &[0, 3, 7, 8]), vec![0..6, 7..9]);
+    }
+}
@@ -1,9 +1,14 @@
 use gpui::*;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use git2::{Repository, Status, StatusOptions, IndexAddOption};
 use super::tie_svg::tie_svg;
-use super::file_tree::file_icon;
+use super::icon_theme::file_icon;
+use super::git_status::GitStatusMap;
+use super::commit_message::{complete, diff_words, subject_length, Completions, MessageHistory, SubjectLength, SUBJECT_SOFT_LIMIT};
 use crate::component::mod_rs_helpers::{byte_index_to_utf16, utf16_index_to_byte};
+use crate::output::{log_channel, OutputChannel};
 
 #[derive(Clone)]
 pub struct GitChange {
@@ -11,6 +16,13 @@ pub struct GitChange {
     pub status: String,
 }
 
+pub enum GitPanelEvent {
+    /// HEAD 指向的分支发生变化（面板内切换或外部 git 命令）
+    BranchChanged { from: String, to: String },
+    /// 刷新后文件的 git 状态有变化，文件树据此更新标记
+    StatusChanged,
+}
+
 #[derive(Clone, Copy, PartialEq)]
 enum GitPanelMode {
     Changes,
@@ -39,6 +51,8 @@ pub struct GitPanel {
     branch: String,
     branches: Vec<String>,
     branch_list_state: ListState,
+    /// 分支监视任务最后一次看到的 HEAD，用于发现外部切换
+    observed_head: String,
     ahead: i32,
     behind: i32,
     list_state: ListState,
@@ -49,6 +63,16 @@ pub struct GitPanel {
     selected_commit_index: Option<usize>,
     commit_changes: Vec<GitChange>,
     commit_changes_list_state: ListState,
+    /// 其它仓库尚未提交的提交信息草稿
+    drafts: HashMap<PathBuf, String>,
+    history: MessageHistory,
+    completion: Option<Completions>,
+    completion_index: usize,
+    /// 待提交变更中的文件名和标识符，用于补全
+    diff_words: Vec<String>,
+    /// 正在修改上次提交；保存开启前输入框中的内容，关闭时恢复
+    amend: Option<String>,
+    file_status: GitStatusMap,
 }
 
 impl GitPanel {
@@ -66,6 +90,7 @@ impl GitPanel {
             branch: String::new(),
             branches: Vec::new(),
             branch_list_state: ListState::new(0, ListAlignment::Top, px(24.0)),
+            observed_head: String::new(),
             ahead: 0,
             behind: 0,
             list_state: ListState::new(0, ListAlignment::Top, px(24.0)),
@@ -76,21 +101,84 @@ impl GitPanel {
             selected_commit_index: None,
             commit_changes: Vec::new(),
             commit_changes_list_state: ListState::new(0, ListAlignment::Top, px(24.0)),
+            drafts: HashMap::new(),
+            history: MessageHistory::default(),
+            completion: None,
+            completion_index: 0,
+            diff_words: Vec::new(),
+            amend: None,
+            file_status: GitStatusMap::default(),
         };
-        this.refresh();
+        this.refresh(cx);
+        this.start_branch_watch(cx);
         this
     }
 
     pub fn set_repo_root(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        self.observed_head = current_branch_name(&path).unwrap_or_default();
+        if self.repo_root.as_ref() != Some(&path) {
+            // 换仓库时保存当前草稿，取出新仓库的草稿
+            let draft = self.amend.take().unwrap_or_else(|| std::mem::take(&mut self.commit_message));
+            if let Some(old) = self.repo_root.take() {
+                if !draft.is_empty() {
+                    self.drafts.insert(old, draft);
+                }
+            }
+            let message = self.drafts.remove(&path).unwrap_or_default();
+            self.set_message(message);
+        }
         self.repo_root = Some(path);
-        self.refresh();
+        self.refresh(cx);
         cx.notify();
     }
 
-    fn init_repo(&mut self) {
+    fn start_branch_watch(&mut self, cx: &mut Context<Self>) {
+        // 面板释放后 update 失败，循环随之结束
+        cx.spawn(|view: WeakEntity<GitPanel>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    cx.background_executor().timer(Duration::from_secs(2)).await;
+                    let root = match view.update(&mut cx, |this, _| this.repo_root.clone()) {
+                        Ok(root) => root,
+                        Err(_) => break,
+                    };
+                    let Some(root) = root else { continue };
+                    let head = cx.background_executor().spawn(async move {
+                        current_branch_name(&root)
+                    }).await;
+                    view.update(&mut cx, |this, cx| this.observe_head(head, cx)).ok();
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn observe_head(&mut self, head: Option<String>, cx: &mut Context<Self>) {
+        let Some(head) = head else { return };
+        if self.observed_head == head {
+            return;
+        }
+        let from = std::mem::replace(&mut self.observed_head, head.clone());
+        self.refresh(cx);
+        cx.notify();
+        if !from.is_empty() {
+            cx.emit(GitPanelEvent::BranchChanged { from, to: head });
+        }
+    }
+
+    fn switch_branch(&mut self, name: String, cx: &mut Context<Self>) {
+        let Some(root) = self.repo_root.clone() else { return };
+        match checkout_local_branch(&root, &name) {
+            Ok(()) => self.observe_head(Some(name), cx),
+            Err(e) => log_channel(OutputChannel::Git, format!("Failed to switch branch: {}", e)),
+        }
+    }
+
+    fn init_repo(&mut self, cx: &mut Context<Self>) {
         if let Some(root) = &self.repo_root {
             if Repository::init(root).is_ok() {
-                self.refresh();
+                self.refresh(cx);
             }
         }
     }
@@ -105,6 +193,23 @@ impl GitPanel {
         }
     }
 
+    /// 将要提交的变更（工作区相对 HEAD）中出现的名称
+    fn load_diff_words(&self, repo: &Repository) -> Vec<String> {
+        const MAX_DIFF_LINES: usize = 5000;
+        let paths: Vec<String> = self.changes.iter().map(|c| c.path.clone()).collect();
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let mut lines = Vec::new();
+        if let Ok(diff) = repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), None) {
+            let _ = diff.print(git2::DiffFormat::Patch, |_, _, line| {
+                if matches!(line.origin(), '+' | '-') {
+                    lines.push(String::from_utf8_lossy(line.content()).to_string());
+                }
+                lines.len() < MAX_DIFF_LINES
+            });
+        }
+        diff_words(&paths, lines.iter().map(String::as_str))
+    }
+
     fn load_history(&mut self) {
         self.selected_commit_index = None;
         self.commits.clear();
@@ -126,12 +231,21 @@ impl GitPanel {
             return;
         }
         revwalk.set_sorting(git2::Sort::TIME).ok();
+        // 上下键翻阅的是自己写过的提交信息
+        let me = repo.signature().ok().and_then(|s| s.name().map(str::to_string));
+        let mut my_messages: Vec<String> = Vec::new();
         
         for id in revwalk.take(100) {
             if let Ok(id) = id {
                 if let Ok(commit) = repo.find_commit(id) {
                     let message = commit.summary().unwrap_or("").to_string();
                     let author = commit.author().name().unwrap_or("").to_string();
+                    if me.as_ref().is_none_or(|me| me == &author) {
+                        let full = commit.message().unwrap_or("").trim_end().to_string();
+                        if !full.is_empty() && !my_messages.contains(&full) {
+                            my_messages.push(full);
+                        }
+                    }
                     let time = commit.time().seconds();
                     let short_id = id.to_string()[..7].to_string();
                     
@@ -146,6 +260,7 @@ impl GitPanel {
             }
         }
         self.history_list_state = ListState::new(self.commits.len(), ListAlignment::Top, px(50.0));
+        self.history.set_entries(my_messages);
     }
 
     fn load_commit_changes(&mut self, index: usize) {
@@ -199,7 +314,103 @@ impl GitPanel {
         self.commit_changes_list_state = ListState::new(self.commit_changes.len(), ListAlignment::Top, px(24.0));
     }
 
-    pub fn refresh(&mut self) {
+    /// 各仓库的提交信息草稿，用于保存会话
+    pub fn commit_drafts(&self) -> BTreeMap<PathBuf, String> {
+        let mut drafts: BTreeMap<PathBuf, String> =
+            self.drafts.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        let current = self.amend.as_ref().unwrap_or(&self.commit_message);
+        if let Some(root) = &self.repo_root {
+            if !current.is_empty() {
+                drafts.insert(root.clone(), current.clone());
+            }
+        }
+        drafts
+    }
+
+    pub fn restore_commit_drafts(&mut self, drafts: BTreeMap<PathBuf, String>, cx: &mut Context<Self>) {
+        self.drafts.extend(drafts);
+        if let Some(root) = &self.repo_root {
+            if self.commit_message.is_empty() {
+                if let Some(message) = self.drafts.remove(root) {
+                    self.set_message(message);
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    fn set_message(&mut self, message: String) {
+        self.commit_message = message;
+        self.commit_cursor = self.commit_message.len();
+        self.commit_selection = None;
+        self.commit_message_marked_range = None;
+        self.completion = None;
+    }
+
+    /// 输入框内容被用户修改后更新补全，并结束历史翻阅
+    fn message_edited(&mut self) {
+        self.history.reset();
+        self.completion = complete(&self.commit_message, self.commit_cursor, &self.diff_words);
+        self.completion_index = 0;
+    }
+
+    fn accept_completion(&mut self) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        let Some(item) = completion.items.get(self.completion_index) else {
+            return;
+        };
+        self.commit_message.replace_range(completion.range.clone(), item);
+        self.commit_cursor = completion.range.start + item.len();
+        self.commit_selection = None;
+    }
+
+    /// 开启时用上次提交的信息填充输入框，关闭时恢复原先的内容
+    fn toggle_amend(&mut self) {
+        if let Some(draft) = self.amend.take() {
+            self.set_message(draft);
+            return;
+        }
+        let Some(root) = &self.repo_root else { return };
+        let head_message = Repository::open(root).ok().and_then(|repo| {
+            let commit = repo.head().ok()?.peel_to_commit().ok()?;
+            commit.message().map(|m| m.trim_end().to_string())
+        });
+        if let Some(message) = head_message {
+            self.amend = Some(std::mem::take(&mut self.commit_message));
+            self.set_message(message);
+        }
+    }
+
+    /// 切回“更改”页并把光标放到提交信息末尾
+    pub fn focus_commit_input(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.mode = GitPanelMode::Changes;
+        self.commit_cursor = self.commit_message.len();
+        self.commit_selection = None;
+        self.focus_handle.focus(window);
+        cx.notify();
+    }
+
+    /// 各文件的 git 状态，供文件树显示
+    pub fn repo_root(&self) -> Option<&PathBuf> {
+        self.repo_root.as_ref()
+    }
+
+    pub fn file_status(&self) -> &GitStatusMap {
+        &self.file_status
+    }
+
+    pub fn refresh(&mut self, cx: &mut Context<Self>) {
+        let file_status = self.refresh_repo();
+        if file_status != self.file_status {
+            self.file_status = file_status;
+            cx.emit(GitPanelEvent::StatusChanged);
+        }
+    }
+
+    /// 重新读取仓库，返回新的文件状态；不是仓库时为空
+    fn refresh_repo(&mut self) -> GitStatusMap {
         let root = match &self.repo_root {
             Some(r) => r,
             None => {
@@ -208,7 +419,7 @@ impl GitPanel {
                 self.branches.clear();
                 self.changes.clear();
                 self.commits.clear();
-                return;
+                return GitStatusMap::default();
             }
         };
 
@@ -222,7 +433,7 @@ impl GitPanel {
                 self.branch = "No Git Repo".to_string();
                 self.branches.clear();
                 self.commits.clear();
-                return;
+                return GitStatusMap::default();
             }
         };
 
@@ -270,20 +481,31 @@ impl GitPanel {
         let mut opts = StatusOptions::new();
         opts.include_untracked(true);
         
-        if let Ok(statuses) = repo.statuses(Some(&mut opts)) {
-            for entry in statuses.iter() {
-                let path = entry.path().unwrap_or("").to_string();
-                let status = entry.status();
-                let status_str = format_status(status);
-                self.changes.push(GitChange { path, status: status_str });
+        let mut file_status = GitStatusMap::default();
+        match repo.statuses(Some(&mut opts)) {
+            Ok(statuses) => {
+                let mut entries = Vec::new();
+                for entry in statuses.iter() {
+                    let path = entry.path().unwrap_or("").to_string();
+                    let status = entry.status();
+                    let status_str = format_status(status);
+                    entries.push((path.clone(), status));
+                    self.changes.push(GitChange { path, status: status_str });
+                }
+                let workdir = repo.workdir().unwrap_or(root);
+                file_status =
+                    GitStatusMap::new(workdir, entries.iter().map(|(path, status)| (path.as_str(), *status)));
             }
+            Err(e) => log_channel(OutputChannel::Git, format!("Failed to read status of {:?}: {}", root, e)),
         }
         self.list_state = ListState::new(self.changes.len(), ListAlignment::Top, px(24.0));
+        self.diff_words = self.load_diff_words(&repo);
         
         self.load_history();
+        file_status
     }
 
-    fn commit_all(&mut self) {
+    fn commit_all(&mut self, cx: &mut Context<Self>) {
         if self.commit_message.trim().is_empty() {
             return;
         }
@@ -336,30 +558,66 @@ impl GitPanel {
             vec![]
         };
 
-        let _ = repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            &self.commit_message,
-            &tree,
-            &parents,
-        );
+        let result = match (&self.amend, &parent_commit) {
+            (Some(_), Some(head)) => {
+                head.amend(Some("HEAD"), None, None, None, Some(&self.commit_message), Some(&tree))
+            }
+            _ => repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &self.commit_message,
+                &tree,
+                &parents,
+            ),
+        };
+        if let Err(e) = result {
+            log_channel(OutputChannel::Git, format!("Commit failed: {}", e));
+            return;
+        }
         
-        self.commit_message.clear();
-        self.commit_cursor = 0;
-        self.refresh();
+        self.amend = None;
+        self.set_message(String::new());
+        self.refresh(cx);
     }
 
     fn on_key_down(&mut self, event: &KeyDownEvent, window: &mut Window, cx: &mut Context<Self>) {
         let key = event.keystroke.key.as_str();
+        if let Some(completion) = &self.completion {
+            let modifiers = &event.keystroke.modifiers;
+            match key {
+                "up" => {
+                    self.completion_index = self.completion_index.saturating_sub(1);
+                    cx.notify();
+                    return;
+                }
+                "down" => {
+                    self.completion_index = (self.completion_index + 1).min(completion.items.len() - 1);
+                    cx.notify();
+                    return;
+                }
+                "tab" | "enter" if !modifiers.control => {
+                    self.accept_completion();
+                    cx.notify();
+                    return;
+                }
+                "escape" => {
+                    self.completion = None;
+                    cx.notify();
+                    return;
+                }
+                _ => {}
+            }
+        }
         if event.keystroke.modifiers.control && key == "enter" {
-            self.commit_all();
+            self.commit_all(cx);
             cx.notify();
             return;
         }
         if key == "enter" {
             self.commit_message.insert(self.commit_cursor, '\n');
             self.commit_cursor += 1;
+            self.completion = None;
             cx.notify();
             return;
         }
@@ -369,10 +627,29 @@ impl GitPanel {
                 self.commit_message
                     .replace_range(prev..self.commit_cursor, "");
                 self.commit_cursor = prev;
+                self.message_edited();
                 cx.notify();
             }
             return;
         }
+        // 光标在首行按上、在末行按下时翻阅以前的提交信息
+        if key == "up" && !self.commit_message[..self.commit_cursor].contains('\n') {
+            if let Some(message) = self.history.older(&self.commit_message) {
+                self.set_message(message);
+                cx.notify();
+                return;
+            }
+        }
+        if key == "down" && !self.commit_message[self.commit_cursor..].contains('\n') {
+            if let Some(message) = self.history.newer() {
+                self.set_message(message);
+                cx.notify();
+                return;
+            }
+        }
+        if key == "left" || key == "right" {
+            self.completion = None;
+        }
         if key == "left" {
             self.commit_cursor = prev_char_boundary(&self.commit_message, self.commit_cursor);
             cx.notify();
@@ -585,12 +862,12 @@ fn next_char_boundary(text: &str, index: usize) -> usize {
 
 impl Render for GitPanel {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let theme_bg = rgb(0xff252526);
-        let _theme_border = rgb(0xff3c474d); // Border color
-        let theme_text = rgb(0xffcccccc); // Main text
-        let theme_muted = rgb(0xff858585); // Muted text
-        let theme_hover = rgb(0xff2a2d2e); // List item hover
-        let theme_header_bg = rgb(0xff252526); // Header background
+        let theme = crate::component::theme(cx);
+        let theme_bg = theme.sidebar;
+        let theme_text = theme.text;
+        let theme_muted = theme.muted_text;
+        let theme_hover = theme.list_hover;
+        let theme_header_bg = theme.sidebar;
         let panel = cx.entity();
         let focus = self.focus_handle.clone();
         let changes = self.changes.clone();
@@ -631,14 +908,14 @@ impl Render for GitPanel {
                         .px(px(12.0))
                         .py(px(6.0))
                         .rounded_md()
-                        .bg(rgb(0xff238636)) // GitHub green
-                        .text_color(rgb(0xffffffff))
+                        .bg(theme.primary_button)
+                        .text_color(theme.primary_button_text)
                         .cursor_pointer()
-                        .hover(|s| s.bg(rgb(0xff2ea043)))
+                        .hover(|s| s.bg(theme.primary_button_hover))
                         .child("初始化仓库")
                         .on_mouse_down(MouseButton::Left, move |_, _w, cx| {
-                            panel.update(cx, |this, _cx| {
-                                this.init_repo();
+                            panel.update(cx, |this, cx| {
+                                this.init_repo(cx);
                             });
                         }),
                 )
@@ -706,7 +983,7 @@ impl Render for GitPanel {
                                 div()
                                     .text_color(theme_text)
                                     .cursor_pointer()
-                                    .hover(|s| s.text_color(rgb(0xffffffff)))
+                                    .hover(|s| s.text_color(theme.accent))
                                     .child(
                                         tie_svg()
                                             .path("assets/icons/sync.svg")
@@ -754,10 +1031,14 @@ impl Render for GitPanel {
                         .child(
                             list(
                                 self.branch_list_state.clone(),
+                                {
+                                let panel = panel.clone();
                                 move |index, _window, _cx| {
                                     if index >= branches.len() { return div().into_any_element(); }
                                     let b = &branches[index];
                                     let is_current = b == &branch;
+                                    let panel = panel.clone();
+                                    let name = b.clone();
                                     div()
                                         .w_full()
                                         .h(px(24.0))
@@ -769,7 +1050,7 @@ impl Render for GitPanel {
                                         .justify_between()
                                         .child(
                                             div()
-                                                .text_color(if is_current { rgb(0xff2ea043) } else { theme_text })
+                                                .text_color(if is_current { theme.success } else { theme_text })
                                                 .text_size(px(13.0))
                                                 .child(b.clone())
                                         )
@@ -779,16 +1060,24 @@ impl Render for GitPanel {
                                                     tie_svg()
                                                         .path("assets/icons/check.svg")
                                                         .size(px(12.0))
-                                                        .text_color(rgb(0xff2ea043))
+                                                        .text_color(theme.success)
                                                         .into_any_element()
                                                 )
                                             } else {
                                                 div()
                                             }
                                         )
+                                        .on_mouse_down(MouseButton::Left, move |_, _, cx| {
+                                            if is_current {
+                                                return;
+                                            }
+                                            panel.update(cx, |this, cx| {
+                                                this.switch_branch(name.clone(), cx);
+                                            });
+                                        })
                                         .into_any_element()
                                 }
-                            )
+                            })
                             .w_full()
                             .h_full()
                         )
@@ -797,6 +1086,53 @@ impl Render for GitPanel {
             div()
         };
 
+        let (subject_len, subject_level) = subject_length(&self.commit_message);
+        let subject_color = match subject_level {
+            SubjectLength::Fits => theme_muted,
+            SubjectLength::Long => theme.warning,
+            SubjectLength::TooLong => theme.error,
+        };
+        let amend_on = self.amend.is_some();
+        let completion_list = match &self.completion {
+            Some(completion) => {
+                let mut list = div()
+                    .mt(px(4.0))
+                    .w_full()
+                    .flex()
+                    .flex_col()
+                    .bg(theme.panel)
+                    .border_1()
+                    .border_color(theme.border)
+                    .rounded_md()
+                    .py(px(2.0));
+                for (i, item) in completion.items.iter().enumerate() {
+                    let selected = i == self.completion_index;
+                    list = list.child(
+                        div()
+                            .px(px(8.0))
+                            .py(px(2.0))
+                            .text_size(px(12.0))
+                            .text_color(theme_text)
+                            .bg(if selected { theme.list_selection } else { transparent_black() })
+                            .cursor_pointer()
+                            .child(item.trim_end().to_string())
+                            .on_mouse_down(MouseButton::Left, {
+                                let panel = panel.clone();
+                                move |_, _, cx| {
+                                    panel.update(cx, |this, cx| {
+                                        this.completion_index = i;
+                                        this.accept_completion();
+                                        cx.notify();
+                                    });
+                                }
+                            }),
+                    );
+                }
+                list
+            }
+            None => div(),
+        };
+
         let commit_input = {
             let msg = self.commit_message.clone();
             let is_empty = msg.is_empty();
@@ -809,10 +1145,10 @@ impl Render for GitPanel {
                 .child(
                     div()
                         .w_full()
-                        .bg(rgb(0xff3c3c3c))
+                        .bg(theme.input_bg)
                         .rounded_md()
                         .border_1()
-                        .border_color(rgb(0xff454545))
+                        .border_color(theme.input_border)
                         .px(px(10.0))
                         .py(px(8.0))
                         .child(
@@ -876,7 +1212,7 @@ impl Render for GitPanel {
                                                     let run = TextRun {
                                                         len: clean_line.len(),
                                                         font: font.clone(),
-                                                        color: text_color.into(),
+                                                        color: text_color,
                                                         background_color: None,
                                                         underline: None,
                                                         strikethrough: None,
@@ -909,7 +1245,7 @@ impl Render for GitPanel {
                                                                         point(bounds.left() + x0, line_y),
                                                                         point(bounds.left() + x1, line_y + line_height),
                                                                     ),
-                                                                    rgb(0x264f78),
+                                                                    theme.text_selection,
                                                                 ));
                                                             }
                                                         }
@@ -946,7 +1282,7 @@ impl Render for GitPanel {
                                                                  let x = shaped_line.unwrapped_layout.x_for_index(local_idx);
                                                                  window.paint_quad(fill(
                                                                      Bounds::new(point(bounds.left() + x, line_y), size(px(1.5), line_height)),
-                                                                     rgb(0xff007fd4),
+                                                                     theme.accent,
                                                                  ));
                                                              } else if cursor_idx == current_line_end {
                                                                  let is_last_shaped = i == shaped_count - 1;
@@ -955,7 +1291,7 @@ impl Render for GitPanel {
                                                                            let x = shaped_line.unwrapped_layout.x_for_index(line_len);
                                                                            window.paint_quad(fill(
                                                                                Bounds::new(point(bounds.left() + x, line_y), size(px(1.5), line_height)),
-                                                                               rgb(0xff007fd4),
+                                                                               theme.accent,
                                                                            ));
                                                                       }
                                                                  }
@@ -963,7 +1299,7 @@ impl Render for GitPanel {
                                                         } else if is_focused && is_empty && byte_offset == 0 {
                                                              window.paint_quad(fill(
                                                                  Bounds::new(point(bounds.left(), line_y), size(px(1.5), line_height)),
-                                                                 rgb(0xff007fd4),
+                                                                 theme.accent,
                                                              ));
                                                         }
                                                         
@@ -978,7 +1314,7 @@ impl Render for GitPanel {
                                                     if ends_with_newline {
                                                         window.paint_quad(fill(
                                                             Bounds::new(point(bounds.left(), line_y), size(px(1.5), line_height)),
-                                                            rgb(0xff007fd4),
+                                                            theme.accent,
                                                         ));
                                                     }
                                                 }
@@ -998,6 +1334,7 @@ impl Render for GitPanel {
                                 focus.focus(window);
                                 panel.update(cx, |this, cx| {
                                     let idx = this.index_for_point(event.position, window);
+                                    this.completion = None;
                                     this.commit_cursor = idx;
                                     this.commit_selection = None;
                                     this.drag_start_index = Some(idx);
@@ -1034,29 +1371,59 @@ impl Render for GitPanel {
                              }
                         })
                 )
+                .child(completion_list)
                 .child(
                     div()
                         .mt(px(8.0))
                         .w_full()
                         .flex()
-                        .justify_end()
+                        .items_center()
+                        .justify_between()
+                        .child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .gap(px(12.0))
+                                .text_size(px(11.0))
+                                .child(
+                                    div()
+                                        .text_color(subject_color)
+                                        .child(format!("首行 {}/{}", subject_len, SUBJECT_SOFT_LIMIT)),
+                                )
+                                .child(
+                                    div()
+                                        .text_color(if amend_on { theme_text } else { theme_muted })
+                                        .cursor_pointer()
+                                        .hover(|s| s.text_color(theme.accent))
+                                        .child(if amend_on { "☑ 修改上次提交" } else { "☐ 修改上次提交" })
+                                        .on_mouse_down(MouseButton::Left, {
+                                            let panel = panel.clone();
+                                            move |_, _, cx| {
+                                                panel.update(cx, |this, cx| {
+                                                    this.toggle_amend();
+                                                    cx.notify();
+                                                });
+                                            }
+                                        }),
+                                ),
+                        )
                         .child(
                             div()
                                 .px(px(14.0))
                                 .py(px(6.0))
                                 .rounded_md()
-                                .bg(rgb(0xff238636))
-                                .text_color(rgb(0xffffffff))
+                                .bg(theme.primary_button)
+                                .text_color(theme.primary_button_text)
                                 .text_size(px(12.0))
                                 .font_weight(FontWeight::BOLD)
                                 .cursor_pointer()
-                                .hover(|s| s.bg(rgb(0xff2ea043)))
+                                .hover(|s| s.bg(theme.primary_button_hover))
                                 .child("提交")
                                 .on_mouse_down(MouseButton::Left, {
                                     let panel = panel.clone();
                                     move |_, _w, cx| {
                                         panel.update(cx, |this, cx_inner| {
-                                            this.commit_all();
+                                            this.commit_all(cx_inner);
                                             cx_inner.notify();
                                         });
                                     }
@@ -1068,17 +1435,17 @@ impl Render for GitPanel {
         let changes_len = changes.len();
         let list_changes = list(
             self.list_state.clone(),
-            move |index, _window, _cx| {
+            move |index, _window, cx| {
             if index >= changes.len() {
                 return div().into_any_element();
             }
             let ch = &changes[index];
             let status_color = if ch.status.contains('M') {
-                rgb(0xffe2c08d) // Modified (Yellowish)
+                theme.warning
             } else if ch.status.contains('A') || ch.status.contains('?') {
-                rgb(0xff73c991) // Added (Greenish)
+                theme.success
             } else if ch.status.contains('D') {
-                rgb(0xfff14c4c) // Deleted (Red)
+                theme.error
             } else {
                 theme_muted
             };
@@ -1117,7 +1484,7 @@ impl Render for GitPanel {
                                 .flex()
                                 .items_center()
                                 .gap(px(6.0))
-                                .child(file_icon(&ch.path))
+                                .child(file_icon(&ch.path, cx))
                                 .child(
                                     div()
                                         .text_size(px(13.0))
@@ -1186,7 +1553,7 @@ impl Render for GitPanel {
                                 .px(px(16.0))
                                 .pb(px(12.0))
                                 .border_b_1()
-                                .border_color(rgb(0xff3c474d))
+                                .border_color(theme.border)
                                 .flex()
                                 .flex_col()
                                 .gap(px(6.0))
@@ -1213,15 +1580,15 @@ impl Render for GitPanel {
                         .child(
                              list(
                                   self.commit_changes_list_state.clone(),
-                                  move |index, _, _| {
+                                  move |index, _, cx| {
                                       if index >= commit_changes.len() { return div().into_any_element(); }
                                       let ch = &commit_changes[index];
                                       let status_color = if ch.status.contains('M') {
-                                          rgb(0xffe2c08d)
+                                          theme.warning
                                       } else if ch.status.contains('A') || ch.status.contains('?') {
-                                          rgb(0xff73c991)
+                                          theme.success
                                       } else if ch.status.contains('D') {
-                                          rgb(0xfff14c4c)
+                                          theme.error
                                       } else {
                                           theme_muted
                                       };
@@ -1257,7 +1624,7 @@ impl Render for GitPanel {
                                                           .flex()
                                                           .items_center()
                                                           .gap(px(6.0))
-                                                          .child(file_icon(&ch.path))
+                                                          .child(file_icon(&ch.path, cx))
                                                           .child(
                                                               div()
                                                                   .text_size(px(13.0))
@@ -1295,7 +1662,7 @@ impl Render for GitPanel {
                                     .py(px(6.0))
                                     .px(px(16.0))
                                     .border_b_1()
-                                    .border_color(rgb(0xff3c474d))
+                                    .border_color(theme.border)
                                     .cursor_pointer()
                                     .hover(|s| s.bg(theme_hover))
                                     .flex()
@@ -1374,7 +1741,7 @@ impl Render for GitPanel {
                                 .px(px(6.0))
                                 .py(px(2.0))
                                 .rounded_md()
-                                .bg(rgb(0xff3c3c3c))
+                                .bg(theme.border)
                                 .text_color(theme_text)
                                 .text_size(px(11.0))
                                 .child(changes_len.to_string())
@@ -1467,6 +1834,7 @@ impl EntityInputHandler for GitPanel {
         self.commit_cursor = start + new_text.len();
         self.commit_selection = None;
         self.commit_message_marked_range = None;
+        self.message_edited();
         cx.notify();
     }
 
@@ -1586,6 +1954,27 @@ impl EntityInputHandler for GitPanel {
     }
 }
 
+impl EventEmitter<GitPanelEvent> for GitPanel {}
+
+/// 当前 HEAD 所在的分支名，分离 HEAD 时为 "DETACHED"
+pub fn current_branch_name(repo_root: &Path) -> Option<String> {
+    let repo = Repository::open(repo_root).ok()?;
+    let head = repo.head().ok()?;
+    Some(head.shorthand().unwrap_or("DETACHED").to_string())
+}
+
+/// 切换到本地分支；工作区中与目标分支冲突的未提交修改会让切换失败，而不是被覆盖
+pub fn checkout_local_branch(repo_root: &Path, name: &str) -> Result<(), git2::Error> {
+    let repo = Repository::open(repo_root)?;
+    let refname = format!("refs/heads/{}", name);
+    let target = repo.revparse_single(&refname)?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.safe();
+    repo.checkout_tree(&target, Some(&mut checkout))?;
+    repo.set_head(&refname)?;
+    Ok(())
+}
+
 // Helper to missing range conversion (copied from mod_rs_helpers to avoid import ambiguity if needed, but we imported it)
 fn utf16_range_to_byte_range(text: &str, range: std::ops::Range<usize>) -> std::ops::Range<usize> {
     let start = utf16_index_to_byte(text, range.start);
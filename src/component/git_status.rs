@@ -0,0 +1,160 @@
+//! 文件树上显示的 git 状态：每个变更文件的状态，以及包含变更的文件夹应显示的状态
+
+use crate::component::Theme;
+use git2::{Repository, Status, StatusOptions};
+use gpui::Hsla;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 按显示优先级从低到高排列，文件夹显示其中优先级最高的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitFileStatus {
+    Untracked,
+    Added,
+    Deleted,
+    Modified,
+    Conflicted,
+}
+
+impl GitFileStatus {
+    /// 忽略的和未修改的文件返回 None
+    pub fn from_status(status: Status) -> Option<Self> {
+        if status.is_conflicted() {
+            Some(Self::Conflicted)
+        } else if status.is_index_new() {
+            Some(Self::Added)
+        } else if status.is_wt_new() {
+            Some(Self::Untracked)
+        } else if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+            Some(Self::Deleted)
+        } else if status.intersects(
+            Status::INDEX_MODIFIED
+                | Status::WT_MODIFIED
+                | Status::INDEX_RENAMED
+                | Status::WT_RENAMED
+                | Status::INDEX_TYPECHANGE
+                | Status::WT_TYPECHANGE,
+        ) {
+            Some(Self::Modified)
+        } else {
+            None
+        }
+    }
+
+    pub fn letter(self) -> &'static str {
+        match self {
+            Self::Untracked => "U",
+            Self::Added => "A",
+            Self::Deleted => "D",
+            Self::Modified => "M",
+            Self::Conflicted => "C",
+        }
+    }
+
+    pub fn color(self, theme: &Theme) -> Hsla {
+        match self {
+            Self::Untracked | Self::Added => theme.success,
+            Self::Deleted | Self::Conflicted => theme.error,
+            Self::Modified => theme.warning,
+        }
+    }
+}
+
+/// 仓库状态按绝对路径整理；不是 git 仓库时为空，文件树不显示任何标记
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitStatusMap {
+    files: HashMap<PathBuf, GitFileStatus>,
+    dirs: HashMap<PathBuf, GitFileStatus>,
+}
+
+impl GitStatusMap {
+    /// `entries` 为相对仓库根目录的路径及其状态，未跟踪的文件夹以 `/` 结尾
+    pub fn new<'a>(root: &Path, entries: impl IntoIterator<Item = (&'a str, Status)>) -> Self {
+        let mut map = Self::default();
+        for (relative, status) in entries {
+            let Some(status) = GitFileStatus::from_status(status) else {
+                continue;
+            };
+            let path = root.join(relative.trim_end_matches('/'));
+            let mut dir = path.parent();
+            while let Some(parent) = dir.filter(|d| d.starts_with(root) && *d != root) {
+                let entry = map.dirs.entry(parent.to_path_buf()).or_insert(status);
+                *entry = (*entry).max(status);
+                dir = parent.parent();
+            }
+            map.files.insert(path, status);
+        }
+        map
+    }
+
+    /// 读取 `dir` 所在仓库的状态，用于工作区中后来加入的文件夹；不在仓库中时为空
+    pub fn for_repo(dir: &Path) -> Self {
+        let Ok(repo) = Repository::discover(dir) else {
+            return Self::default();
+        };
+        let Some(workdir) = repo.workdir().map(Path::to_path_buf) else {
+            return Self::default();
+        };
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true);
+        let Ok(statuses) = repo.statuses(Some(&mut opts)) else {
+            return Self::default();
+        };
+        let entries: Vec<(String, Status)> = statuses
+            .iter()
+            .map(|entry| (entry.path().unwrap_or("").to_string(), entry.status()))
+            .collect();
+        Self::new(&workdir, entries.iter().map(|(path, status)| (path.as_str(), *status)))
+    }
+
+    /// 合并另一个仓库的状态
+    pub fn extend(&mut self, other: GitStatusMap) {
+        self.files.extend(other.files);
+        for (dir, status) in other.dirs {
+            let entry = self.dirs.entry(dir).or_insert(status);
+            *entry = (*entry).max(status);
+        }
+    }
+
+    pub fn file(&self, path: &Path) -> Option<GitFileStatus> {
+        self.files.get(path).copied()
+    }
+
+    /// 文件夹内有变更时文件夹上的圆点所用的状态
+    pub fn dir(&self, path: &Path) -> Option<GitFileStatus> {
+        self.dirs.get(path).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statuses_and_folder_dots() {
+        assert_eq!(GitFileStatus::from_status(Status::WT_MODIFIED), Some(GitFileStatus::Modified));
+        assert_eq!(
+            GitFileStatus::from_status(Status::INDEX_NEW | Status::WT_MODIFIED),
+            Some(GitFileStatus::Added)
+        );
+        assert_eq!(GitFileStatus::from_status(Status::IGNORED), None);
+
+        let root = Path::new("/p");
+        let map = GitStatusMap::new(
+            root,
+            [
+                ("src/a.rs", Status::WT_NEW),
+                ("src/ui/b.rs", Status::WT_MODIFIED),
+                ("new_dir/", Status::WT_NEW),
+                ("README.md", Status::CONFLICTED),
+                ("clean.rs", Status::CURRENT),
+            ],
+        );
+        assert_eq!(map.file(Path::new("/p/src/a.rs")), Some(GitFileStatus::Untracked));
+        assert_eq!(map.file(Path::new("/p/new_dir")), Some(GitFileStatus::Untracked));
+        assert_eq!(map.file(Path::new("/p/clean.rs")), None);
+        assert_eq!(map.dir(Path::new("/p/src")), Some(GitFileStatus::Modified));
+        assert_eq!(map.dir(Path::new("/p/src/ui")), Some(GitFileStatus::Modified));
+        assert_eq!(map.dir(root), None);
+    }
+}
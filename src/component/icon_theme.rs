@@ -0,0 +1,258 @@
+//! 文件图标主题：按顺序匹配文件名、扩展名和文件夹名的规则，第一条匹配的规则决定图标
+//!
+//! ```json
+//! { "id": "default", "file": "anyType_dark.svg", "folder": "folder_dark.svg",
+//!   "rules": [ { "fileName": "cargo.toml", "icon": "cargo_dark.svg" },
+//!              { "extension": "test.ts", "icon": "tsTest_dark.svg" },
+//!              { "folderName": "tests", "icon": "folderTest_dark.svg" } ] }
+//! ```
+//!
+//! 图标路径相对主题文件所在目录，匹配不区分大小写；扩展名可以是 `test.ts` 这样的多段后缀。
+//! 主题在启动时读取一次并作为全局状态共享，文件树、标签、最近文件列表和拖拽浮层都从这里取图标
+
+use anyhow::{Context, Result};
+use gpui::{px, App, Global, IntoElement, SharedString, Styled};
+use log::{info, warn};
+use serde::Deserialize;
+use std::path::Path;
+
+use super::tie_svg::tie_svg;
+
+/// 内置主题相对资源目录的位置
+pub const DEFAULT_ICON_THEME: &str = "assets/icons/theme.json";
+
+/// 没有可用主题、或主题的默认图标缺失时使用
+const FALLBACK_FILE_ICON: &str = "assets/icons/anyType_dark.svg";
+const FALLBACK_FOLDER_ICON: &str = "assets/icons/folder_dark.svg";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThemeFile {
+    id: String,
+    file: Option<String>,
+    folder: Option<String>,
+    folder_open: Option<String>,
+    #[serde(default)]
+    rules: Vec<RuleFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleFile {
+    file_name: Option<String>,
+    extension: Option<String>,
+    folder_name: Option<String>,
+    icon: String,
+    icon_open: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Matcher {
+    FileName(String),
+    Extension(String),
+    FolderName(String),
+}
+
+impl Matcher {
+    /// `name` 已转为小写
+    fn matches(&self, name: &str, is_dir: bool) -> bool {
+        match self {
+            Matcher::FileName(expected) => !is_dir && name == expected,
+            Matcher::Extension(ext) => {
+                !is_dir
+                    && name.len() > ext.len() + 1
+                    && name.ends_with(ext.as_str())
+                    && name[..name.len() - ext.len()].ends_with('.')
+            }
+            Matcher::FolderName(expected) => is_dir && name == expected,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct IconRule {
+    matcher: Matcher,
+    icon: SharedString,
+    icon_open: Option<SharedString>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IconTheme {
+    pub id: String,
+    file: SharedString,
+    folder: SharedString,
+    folder_open: SharedString,
+    rules: Vec<IconRule>,
+}
+
+impl Global for IconTheme {}
+
+impl IconTheme {
+    /// 不含任何规则、只有通用图标的主题
+    pub fn fallback() -> Self {
+        Self {
+            id: "fallback".to_string(),
+            file: FALLBACK_FILE_ICON.into(),
+            folder: FALLBACK_FOLDER_ICON.into(),
+            folder_open: FALLBACK_FOLDER_ICON.into(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// 读取主题文件。`base` 是被扩展的主题：自身规则优先匹配，之后依次尝试 `base` 的规则，
+    /// 未写明的默认图标也沿用 `base` 的
+    pub fn load(path: &Path, base: Option<&IconTheme>) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read icon theme at {:?}", path))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&content, dir, base)
+    }
+
+    fn parse(content: &str, dir: &Path, base: Option<&IconTheme>) -> Result<Self> {
+        let file: ThemeFile =
+            serde_json::from_str(content).with_context(|| "Failed to parse icon theme")?;
+        let fallback = base.cloned().unwrap_or_else(Self::fallback);
+        // 缺失的图标不渲染成空白，而是跳过这条规则或退回通用图标
+        let resolve = |icon: &str| -> Option<SharedString> {
+            let path = dir.join(icon);
+            if path.is_file() {
+                Some(path.to_string_lossy().to_string().into())
+            } else {
+                warn!("Icon theme {} references missing icon {:?}", file.id, path);
+                None
+            }
+        };
+
+        let mut rules = Vec::new();
+        for rule in &file.rules {
+            let matcher = match (&rule.file_name, &rule.extension, &rule.folder_name) {
+                (Some(name), None, None) => Matcher::FileName(name.to_lowercase()),
+                (None, Some(ext), None) => Matcher::Extension(ext.trim_start_matches('.').to_lowercase()),
+                (None, None, Some(name)) => Matcher::FolderName(name.to_lowercase()),
+                _ => {
+                    warn!(
+                        "Icon theme {}: rule for {} must set exactly one of fileName, extension, folderName",
+                        file.id, rule.icon
+                    );
+                    continue;
+                }
+            };
+            let Some(icon) = resolve(&rule.icon) else {
+                continue;
+            };
+            let icon_open = rule.icon_open.as_deref().and_then(resolve);
+            rules.push(IconRule { matcher, icon, icon_open });
+        }
+        rules.extend(base.map(|b| b.rules.clone()).unwrap_or_default());
+
+        let file_icon = file.file.as_deref().and_then(resolve).unwrap_or(fallback.file);
+        let folder = file.folder.as_deref().and_then(resolve).unwrap_or(fallback.folder);
+        let folder_open = file
+            .folder_open
+            .as_deref()
+            .and_then(resolve)
+            .unwrap_or_else(|| folder.clone());
+        Ok(Self {
+            id: file.id,
+            file: file_icon,
+            folder,
+            folder_open,
+            rules,
+        })
+    }
+
+    /// `name` 可以带目录，只按最后一段匹配
+    pub fn icon_for(&self, name: &str, is_dir: bool, is_expanded: bool) -> SharedString {
+        let name = name.rsplit(['/', '\\']).next().unwrap_or(name).to_lowercase();
+        if let Some(rule) = self.rules.iter().find(|rule| rule.matcher.matches(&name, is_dir)) {
+            if is_expanded {
+                if let Some(open) = &rule.icon_open {
+                    return open.clone();
+                }
+            }
+            return rule.icon.clone();
+        }
+        match (is_dir, is_expanded) {
+            (false, _) => self.file.clone(),
+            (true, false) => self.folder.clone(),
+            (true, true) => self.folder_open.clone(),
+        }
+    }
+}
+
+/// 切换图标主题，所有窗口在下一帧使用新图标
+pub fn set_icon_theme(theme: IconTheme, cx: &mut App) {
+    info!("Using icon theme {}", theme.id);
+    cx.set_global(theme);
+    cx.refresh_windows();
+}
+
+fn icon_path(name: &str, is_dir: bool, is_expanded: bool, cx: &App) -> SharedString {
+    match cx.try_global::<IconTheme>() {
+        Some(theme) => theme.icon_for(name, is_dir, is_expanded),
+        None if is_dir => FALLBACK_FOLDER_ICON.into(),
+        None => FALLBACK_FILE_ICON.into(),
+    }
+}
+
+fn theme_icon(path: SharedString) -> impl IntoElement {
+    tie_svg().path(path).size(px(16.0)).original_colors(true)
+}
+
+pub fn file_icon(name: &str, cx: &App) -> impl IntoElement {
+    theme_icon(icon_path(name, false, false, cx))
+}
+
+pub fn folder_icon(name: &str, is_expanded: bool, cx: &App) -> impl IntoElement {
+    theme_icon(icon_path(name, true, is_expanded, cx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_match_in_order_and_skip_missing_icons() {
+        let dir = tempfile::tempdir().unwrap();
+        for icon in ["file.svg", "folder.svg", "cargo.svg", "ts.svg", "tstest.svg", "tests.svg", "docker.svg"] {
+            std::fs::write(dir.path().join(icon), "<svg/>").unwrap();
+        }
+        let base = IconTheme::parse(
+            r#"{ "id": "base", "file": "file.svg", "folder": "folder.svg",
+                 "rules": [ { "fileName": "Cargo.toml", "icon": "cargo.svg" },
+                            { "extension": "test.ts", "icon": "tstest.svg" },
+                            { "extension": "ts", "icon": "ts.svg" },
+                            { "extension": "rs", "icon": "missing.svg" },
+                            { "folderName": "tests", "icon": "tests.svg" } ] }"#,
+            dir.path(),
+            None,
+        )
+        .unwrap();
+        let icon = |theme: &IconTheme, name: &str, is_dir: bool| {
+            let path = theme.icon_for(name, is_dir, false);
+            Path::new(path.as_ref()).file_name().unwrap().to_string_lossy().to_string()
+        };
+        assert_eq!(icon(&base, "crates/buffer/Cargo.toml", false), "cargo.svg");
+        assert_eq!(icon(&base, "app.test.ts", false), "tstest.svg");
+        assert_eq!(icon(&base, "app.ts", false), "ts.svg");
+        assert_eq!(icon(&base, "ts", false), "file.svg");
+        assert_eq!(icon(&base, "main.rs", false), "file.svg");
+        assert_eq!(icon(&base, "tests", true), "tests.svg");
+        assert_eq!(icon(&base, "tests", false), "file.svg");
+        assert_eq!(icon(&base, "src", true), "folder.svg");
+
+        // 插件主题的规则排在前面，其余沿用被扩展的主题
+        let plugin = IconTheme::parse(
+            r#"{ "id": "plugin",
+                 "rules": [ { "fileName": "dockerfile", "icon": "docker.svg" },
+                            { "extension": "ts", "icon": "cargo.svg" } ] }"#,
+            dir.path(),
+            Some(&base),
+        )
+        .unwrap();
+        assert_eq!(icon(&plugin, "Dockerfile", false), "docker.svg");
+        assert_eq!(icon(&plugin, "app.ts", false), "cargo.svg");
+        assert_eq!(icon(&plugin, "tests", true), "tests.svg");
+        assert_eq!(icon(&plugin, "README", false), "file.svg");
+    }
+}
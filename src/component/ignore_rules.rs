@@ -0,0 +1,83 @@
+//! 文件树的隐藏项和 .gitignore 忽略项：以 `.` 开头的项默认隐藏，
+//! 被所在 git 仓库忽略的项淡化显示
+
+use git2::Repository;
+use std::path::{Path, PathBuf};
+
+/// 以 `.` 开头的文件和文件夹
+pub fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+/// 各个根目录所在仓库的忽略规则；不在仓库中的根目录没有忽略项
+#[derive(Default)]
+pub struct IgnoreRules {
+    repos: Vec<(PathBuf, Repository)>,
+}
+
+impl IgnoreRules {
+    /// 重新读取各根目录所在仓库的 .gitignore；根目录变化或 .gitignore 保存后调用
+    pub fn new(roots: &[PathBuf]) -> Self {
+        let mut repos: Vec<(PathBuf, Repository)> = Vec::new();
+        for root in roots {
+            let Ok(repo) = Repository::discover(root) else {
+                continue;
+            };
+            let Some(workdir) = repo.workdir().map(Path::to_path_buf) else {
+                continue;
+            };
+            if repos.iter().all(|(dir, _)| *dir != workdir) {
+                repos.push((workdir, repo));
+            }
+        }
+        // 嵌套的仓库优先匹配
+        repos.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+        Self { repos }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Some((workdir, repo)) = self.repos.iter().find(|(dir, _)| path.starts_with(dir)) else {
+            return false;
+        };
+        let Ok(relative) = path.strip_prefix(workdir) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        // 只对文件夹生效的规则（如 `target/`）需要以 `/` 结尾的路径
+        let mut relative = relative.to_string_lossy().replace('\\', "/");
+        if is_dir {
+            relative.push('/');
+        }
+        repo.is_path_ignored(Path::new(&relative)).unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_rules_and_hidden_names() {
+        assert!(is_hidden(".git"));
+        assert!(!is_hidden("main.t"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        Repository::init(&root).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+
+        let rules = IgnoreRules::new(&[root.join("src")]);
+        assert!(rules.is_ignored(&root.join("target"), true));
+        assert!(rules.is_ignored(&root.join("target/debug"), true));
+        assert!(rules.is_ignored(&root.join("src/build.log"), false));
+        assert!(!rules.is_ignored(&root.join("src/main.t"), false));
+        assert!(!rules.is_ignored(&root, true));
+
+        let outside = tempfile::tempdir().unwrap();
+        assert!(!IgnoreRules::new(&[outside.path().to_path_buf()]).is_ignored(&outside.path().join("a.log"), false));
+    }
+}
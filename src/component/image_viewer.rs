@@ -65,6 +65,7 @@ impl ImageViewer {
 impl Render for ImageViewer {
     fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
         let entity = _cx.entity();
+        let theme = crate::component::theme(_cx);
         let view_for_down = entity.clone();
         let view_for_up = entity.clone();
         let view_for_move = entity.clone();
@@ -75,7 +76,7 @@ impl Render for ImageViewer {
             .flex_col()
             .w_full()
             .h_full()
-            .bg(rgb(0xff2d353b))
+            .bg(theme.panel)
             .on_mouse_down(MouseButton::Left, move |event, _window, cx| {
                 view_for_down.update(cx, |this, cx_inner| {
                     (*this).dragging = true;
@@ -143,7 +144,7 @@ impl Render for ImageViewer {
                     .items_center()
                     .justify_center()
                     .text_size(px(12.0))
-                    .text_color(rgb(0xffa9b1b6))
+                    .text_color(theme.muted_text)
                     .child("打开图片以预览"),
             )
         }
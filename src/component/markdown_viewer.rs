@@ -7,6 +7,8 @@ pub struct MarkdownViewer {
     content: String,
     blocks: Vec<Block>,
     sweetline_engine: Engine,
+    /// 代码块的语法在第一次显示 Markdown 时才编译，不拖慢启动
+    grammars_compiled: bool,
     style_cache: HashMap<u32, Hsla>,
     focus_handle: FocusHandle,
     scroll_offset_y: Pixels,
@@ -23,22 +25,11 @@ enum Block {
 
 impl MarkdownViewer {
     pub fn new(cx: &mut Context<Self>) -> Self {
-        let engine = Engine::new(true);
-        let _ = engine.compile_json(RUST_GRAMMAR);
-        let _ = engine.compile_json(CPP_GRAMMAR);
-        let _ = engine.compile_json(JSON_GRAMMAR);
-        let _ = engine.compile_json(TOML_GRAMMAR);
-        let _ = engine.compile_json(YAML_GRAMMAR);
-        let _ = engine.compile_json(PYTHON_GRAMMAR);
-        let _ = engine.compile_json(JAVASCRIPT_GRAMMAR);
-        let _ = engine.compile_json(TYPESCRIPT_GRAMMAR);
-        let _ = engine.compile_json(HTML_GRAMMAR);
-        let _ = engine.compile_json(CSS_GRAMMAR);
-        let _ = engine.compile_json(SHELL_GRAMMAR);
         Self {
             content: String::new(),
             blocks: Vec::new(),
-            sweetline_engine: engine,
+            sweetline_engine: Engine::new(true),
+            grammars_compiled: false,
             style_cache: HashMap::new(),
             focus_handle: cx.focus_handle(),
             scroll_offset_y: px(0.0),
@@ -47,6 +38,24 @@ impl MarkdownViewer {
     }
 
     pub fn set_content(&mut self, text: String, _cx: &mut Context<Self>) {
+        if !self.grammars_compiled {
+            self.grammars_compiled = true;
+            for grammar in [
+                RUST_GRAMMAR,
+                CPP_GRAMMAR,
+                JSON_GRAMMAR,
+                TOML_GRAMMAR,
+                YAML_GRAMMAR,
+                PYTHON_GRAMMAR,
+                JAVASCRIPT_GRAMMAR,
+                TYPESCRIPT_GRAMMAR,
+                HTML_GRAMMAR,
+                CSS_GRAMMAR,
+                SHELL_GRAMMAR,
+            ] {
+                let _ = self.sweetline_engine.compile_json(grammar);
+            }
+        }
         self.content = text;
         self.blocks = Self::parse_blocks(&self.content);
         self.prepare_code_blocks();
@@ -180,6 +189,7 @@ impl MarkdownViewer {
         window: &Window,
         text: &str,
         font_size: Pixels,
+        text_color: Hsla,
         highlights: &[(std::ops::Range<usize>, Hsla)],
     ) -> ShapedLine {
         let mut runs = Vec::new();
@@ -190,7 +200,7 @@ impl MarkdownViewer {
                 runs.push(TextRun {
                     len: range.start - last_end,
                     font: style.font(),
-                    color: rgb(0xcccccc).into(),
+                    color: text_color,
                     background_color: None,
                     underline: None,
                     strikethrough: None,
@@ -210,7 +220,7 @@ impl MarkdownViewer {
             runs.push(TextRun {
                 len: text.len() - last_end,
                 font: style.font(),
-                color: rgb(0xcccccc).into(),
+                color: text_color,
                 background_color: None,
                 underline: None,
                 strikethrough: None,
@@ -291,7 +301,8 @@ impl Render for MarkdownViewer {
         let c = canvas(
             |bounds, _window, _cx| bounds,
             move |bounds, _layout, window, cx| {
-                window.paint_quad(fill(bounds, rgb(0xff2d353b)));
+                let theme = crate::component::theme(cx);
+                window.paint_quad(fill(bounds, theme.panel));
                 let font_size = px(13.0);
                 let line_height = font_size * 1.6;
                 let (scroll, _) = {
@@ -319,7 +330,7 @@ impl Render for MarkdownViewer {
                                 };
                                 let fs = font_size * scale as f32;
                                 let lh = fs * 1.5;
-                                let line = Self::shape_line(window, &text, rgb(0xffe6e0d9).into(), fs);
+                                let line = Self::shape_line(window, &text, theme.text, fs);
                                 let origin = point(left, y_paint);
                                 let _ = line.paint(origin, lh, window, cx);
                                 y_paint += lh + px(6.0);
@@ -330,7 +341,7 @@ impl Render for MarkdownViewer {
                                     let line = Self::shape_line(
                                         window,
                                         para_line,
-                                        rgb(0xffc9d1d9).into(),
+                                        theme.text,
                                         font_size,
                                     );
                                     let origin = point(left, y_paint);
@@ -346,7 +357,7 @@ impl Render for MarkdownViewer {
                                     point(left, y_paint + px(6.0)),
                                     point(bounds.right() - px(16.0), y_paint + px(7.0)),
                                 );
-                                window.paint_quad(fill(hr_bounds, rgb(0xff3c474d)));
+                                window.paint_quad(fill(hr_bounds, theme.border));
                                 y_paint += px(12.0);
                                 y_content += px(12.0);
                             }
@@ -357,7 +368,7 @@ impl Render for MarkdownViewer {
                                     point(left - px(4.0), y_paint - px(2.0)),
                                     point(bg_right, y_paint + line_height * lines.len() + px(6.0)),
                                 );
-                                window.paint_quad(fill(block_bg, rgb(0xff232a2e)));
+                                window.paint_quad(fill(block_bg, theme.header));
                                 for l in lines {
                                     let hl = Self::code_highlights_for_line(
                                         &spans,
@@ -365,7 +376,7 @@ impl Render for MarkdownViewer {
                                         acc_chars,
                                         &l,
                                     );
-                                    let line = Self::shape_code_line(window, &l, font_size, &hl);
+                                    let line = Self::shape_code_line(window, &l, font_size, theme.text, &hl);
                                     let origin = point(left, y_paint);
                                     let _ = line.paint(origin, line_height, window, cx);
                                     acc_chars += l.chars().count() + 1;
@@ -402,7 +413,7 @@ impl Render for MarkdownViewer {
                         point(bounds.right() - px(6.0), bounds.top() + px(4.0) + scroll_pos),
                         point(bounds.right() - px(2.0), bounds.top() + px(4.0) + scroll_pos + thumb_h),
                     );
-                    window.paint_quad(fill(thumb_bounds, rgba(0xffffff55)));
+                    window.paint_quad(fill(thumb_bounds, theme.scrollbar_thumb));
                 }
             },
         )
@@ -1,15 +1,39 @@
 use gpui::*;
 pub mod file_tree;
+pub mod tree_watch;
+pub mod tree_selection;
+pub mod tree_filter;
+pub mod ignore_rules;
+pub mod name_input;
+pub mod file_clipboard;
+pub mod side_by_side;
+pub mod diff_viewer;
+pub mod icon_theme;
 pub mod command_palette;
+pub mod fuzzy;
+pub mod quick_open;
+pub mod reference_update;
 pub mod measure_bounds;
 pub mod modal;
 pub mod popover;
 pub mod tie_svg;
 pub mod status_bar;
+pub mod toolbar;
 pub mod image_viewer;
 pub mod markdown_viewer;
 pub mod tool_panel;
 pub mod git_panel;
+pub mod git_status;
+pub mod search_panel;
+pub mod panel_list;
+pub mod commit_message;
+pub mod review;
+pub mod review_panel;
+pub mod plugin_panel;
+pub mod output_panel;
+pub mod problems_panel;
+pub mod project_settings_panel;
+pub mod references_panel;
 
 pub mod mod_rs_helpers {
     use std::ops::Range;
@@ -43,8 +67,9 @@ pub mod mod_rs_helpers {
 use std::ops::Range;
 use std::time::{Duration, Instant};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub struct Theme {
+    pub is_dark: bool,
     pub surface: Hsla,
     pub panel: Hsla,
     pub border: Hsla,
@@ -54,11 +79,46 @@ pub struct Theme {
     pub accent_border: Hsla,
     pub input_bg: Hsla,
     pub input_border: Hsla,
+    /// 模态框、命令面板后面的遮罩
+    pub overlay_bg: Hsla,
+    pub list_hover: Hsla,
+    pub list_selection: Hsla,
+    /// 比列表悬停更深的按下、选中态
+    pub list_active: Hsla,
+    /// 文件树、Git 等侧边栏的底色
+    pub sidebar: Hsla,
+    /// 标题栏、工具面板标题栏、代码块等比面板略深的底色
+    pub header: Hsla,
+    /// 输入框里选中文本的底色
+    pub text_selection: Hsla,
+    pub error: Hsla,
+    pub warning: Hsla,
+    pub success: Hsla,
+    /// 错误提示条的底色
+    pub error_bg: Hsla,
+    /// 成功提示条的底色
+    pub success_bg: Hsla,
+    /// 提交、确认等主要按钮
+    pub primary_button: Hsla,
+    pub primary_button_hover: Hsla,
+    pub primary_button_text: Hsla,
+    /// 差异视图中删除、新增行和对侧空行的底色
+    pub diff_removed_bg: Hsla,
+    pub diff_added_bg: Hsla,
+    pub diff_filler_bg: Hsla,
+    /// 搜索、引用结果中命中文字的底色
+    pub match_bg: Hsla,
+    pub scrollbar_thumb: Hsla,
+    /// 浮层阴影的不透明度
+    pub shadow_alpha: f32,
 }
 
+impl Global for Theme {}
+
 impl Theme {
     pub fn dark() -> Self {
         Self {
+            is_dark: true,
             surface: rgb(0xff1f2428).into(),
             panel: rgb(0xff2d353b).into(),
             border: rgb(0xff3c474d).into(),
@@ -68,8 +128,126 @@ impl Theme {
             accent_border: rgb(0xff89b482).into(),
             input_bg: rgb(0xff20262b).into(),
             input_border: rgb(0xff424f57).into(),
+            overlay_bg: rgba(0x00000080).into(),
+            list_hover: rgba(0xffffff12).into(),
+            list_selection: rgb(0xff37373d).into(),
+            list_active: rgba(0xffffff24).into(),
+            sidebar: rgb(0xff252526).into(),
+            header: rgb(0xff232a2e).into(),
+            text_selection: rgb(0xff264f78).into(),
+            error: rgb(0xffe67e80).into(),
+            warning: rgb(0xffdbbc7f).into(),
+            success: rgb(0xffa7c080).into(),
+            error_bg: rgb(0xff3a2f2a).into(),
+            success_bg: rgb(0xff2f3a33).into(),
+            primary_button: rgb(0xff2d6cdf).into(),
+            primary_button_hover: rgb(0xff3b7bff).into(),
+            primary_button_text: rgb(0xffffffff).into(),
+            diff_removed_bg: rgba(0xf851492e).into(),
+            diff_added_bg: rgba(0x2ea0432e).into(),
+            diff_filler_bg: rgba(0xffffff08).into(),
+            match_bg: rgba(0xd7992155).into(),
+            scrollbar_thumb: rgba(0xffffff55).into(),
+            shadow_alpha: 0.5,
         }
     }
+
+    pub fn light() -> Self {
+        Self {
+            is_dark: false,
+            surface: rgb(0xfff3f4f6).into(),
+            panel: rgb(0xffffffff).into(),
+            border: rgb(0xffd0d7de).into(),
+            text: rgb(0xff24292f).into(),
+            muted_text: rgb(0xff6e7781).into(),
+            accent: rgb(0xff3a94c5).into(),
+            accent_border: rgb(0xff8da101).into(),
+            input_bg: rgb(0xffffffff).into(),
+            input_border: rgb(0xffc5ccd3).into(),
+            overlay_bg: rgba(0x00000033).into(),
+            list_hover: rgba(0x0000000f).into(),
+            list_selection: rgb(0xffdbe9f9).into(),
+            list_active: rgba(0x0000001f).into(),
+            sidebar: rgb(0xfff6f8fa).into(),
+            header: rgb(0xffeaeef2).into(),
+            text_selection: rgb(0xffb6d7ff).into(),
+            error: rgb(0xffcf222e).into(),
+            warning: rgb(0xff9a6700).into(),
+            success: rgb(0xff1a7f37).into(),
+            error_bg: rgb(0xffffebe9).into(),
+            success_bg: rgb(0xffdafbe1).into(),
+            primary_button: rgb(0xff0969da).into(),
+            primary_button_hover: rgb(0xff0860ca).into(),
+            primary_button_text: rgb(0xffffffff).into(),
+            diff_removed_bg: rgba(0xff818226).into(),
+            diff_added_bg: rgba(0x4ac26b26).into(),
+            diff_filler_bg: rgba(0x0000000a).into(),
+            match_bg: rgba(0xd4a72c66).into(),
+            scrollbar_thumb: rgba(0x00000040).into(),
+            shadow_alpha: 0.15,
+        }
+    }
+
+    /// 深浅主题互换
+    pub fn toggled(&self) -> Self {
+        if self.is_dark {
+            Self::light()
+        } else {
+            Self::dark()
+        }
+    }
+
+    /// 浮层（命令面板、模态框、弹出框）统一使用的阴影
+    pub fn overlay_shadow(&self) -> Vec<BoxShadow> {
+        vec![BoxShadow {
+            color: hsla(0.0, 0.0, 0.0, self.shadow_alpha),
+            offset: point(px(0.0), px(8.0)),
+            blur_radius: px(24.0),
+            spread_radius: px(0.0),
+        }]
+    }
+
+    /// 用键盘聚焦按钮、标签等控件时的焦点框，用阴影画出，不影响布局
+    pub fn focus_ring(&self) -> Vec<BoxShadow> {
+        vec![BoxShadow {
+            color: self.accent,
+            offset: point(px(0.0), px(0.0)),
+            blur_radius: px(0.0),
+            spread_radius: px(2.0),
+        }]
+    }
+}
+
+/// 工具栏、标签栏、状态栏等界面区域中可用键盘操作的控件
+pub trait FocusRing: InteractiveElement {
+    /// 加入 Tab 顺序，获得焦点时显示焦点框；需要先设置 id
+    fn focus_ring(self, cx: &App) -> Self {
+        let ring = theme(cx).focus_ring();
+        self.tab_index(0).focus(move |s| s.shadow(ring))
+    }
+
+    /// 作为一个焦点区域：Tab 在区域内循环，鼠标点击区域内的控件不会把焦点从编辑器移走
+    fn focus_region(self, handle: &FocusHandle) -> Self {
+        self.track_focus(handle)
+            .tab_group()
+            .capture_any_mouse_down(|_, window, _| window.prevent_default())
+    }
+}
+
+impl<E: InteractiveElement> FocusRing for E {}
+
+/// 当前主题；组件在渲染时读取，切换主题后下一帧即可生效
+pub fn theme(cx: &App) -> Theme {
+    cx.try_global::<Theme>().copied().unwrap_or_else(Theme::dark)
+}
+
+pub fn set_theme(theme: Theme, cx: &mut App) {
+    cx.set_global(theme);
+    cx.refresh_windows();
+}
+
+pub fn toggle_theme(cx: &mut App) {
+    set_theme(theme(cx).toggled(), cx);
 }
 
 const SELECT_OPTIONS: [&str; 3] = ["选项 A", "选项 B", "选项 C"];
@@ -834,7 +1012,7 @@ fn component_canvas(
             );
 
             let button_base_color = if hovered == Some(Control::Button) {
-                rgb(0xff88b9ae).into()
+                theme.accent_border
             } else {
                 theme.accent
             };
@@ -853,7 +1031,7 @@ fn component_canvas(
                 window,
                 "按钮",
                 layout.button,
-                rgb(0xff102022).into(),
+                theme.surface,
                 font_size,
                 cx,
             );
@@ -952,7 +1130,7 @@ fn component_canvas(
                 point(layout.switch_track.left() + thumb_offset, layout.switch_track.top() + px(3.0)),
                 size(px(18.0), px(18.0)),
             );
-            let mut thumb_quad = fill(thumb_bounds, rgb(0xffffffff));
+            let mut thumb_quad = fill(thumb_bounds, theme.primary_button_text);
             thumb_quad.corner_radii = Corners::all(px(9.0));
             window.paint_quad(thumb_quad);
             paint_text(
@@ -1012,11 +1190,11 @@ fn component_canvas(
                         );
                         if let Some(hovered_index) = select_option_hovered {
                             if hovered_index == index {
-                                window.paint_quad(fill(item_bounds, rgba(0xffffff10)));
+                                window.paint_quad(fill(item_bounds, theme.list_hover));
                             }
                         }
                         if index == select_index {
-                            window.paint_quad(fill(item_bounds, rgba(0xffffff18)));
+                            window.paint_quad(fill(item_bounds, theme.list_active));
                         }
                         paint_text(
                             window,
@@ -1107,3 +1285,49 @@ fn byte_index_to_utf16(text: &str, byte_index: usize) -> usize {
     }
     count
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{set_theme, theme, toggle_theme, Theme};
+    use gpui::{div, Context, Hsla, IntoElement, Render, Styled, TestAppContext, Window};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// 记录每次渲染时读到的面板底色
+    struct ThemeProbe(Rc<RefCell<Vec<Hsla>>>);
+
+    impl Render for ThemeProbe {
+        fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+            let panel = theme(cx).panel;
+            self.0.borrow_mut().push(panel);
+            div().size_full().bg(panel)
+        }
+    }
+
+    #[test]
+    fn test_toggled_theme_resolves_other_palette() {
+        let dark = Theme::dark();
+        let light = dark.toggled();
+        assert!(!light.is_dark);
+        assert_eq!(light.overlay_bg, Theme::light().overlay_bg);
+        assert_eq!(light.list_selection, Theme::light().list_selection);
+        assert_ne!(light.panel, dark.panel);
+        assert_ne!(light.text, dark.text);
+        assert!(light.toggled() == dark);
+    }
+
+    #[gpui::test]
+    fn test_set_theme_restyles_open_windows(cx: &mut TestAppContext) {
+        let rendered = Rc::new(RefCell::new(Vec::new()));
+        let probe = rendered.clone();
+        cx.add_window(move |_, _| ThemeProbe(probe));
+        cx.run_until_parked();
+        assert_eq!(rendered.borrow().last(), Some(&Theme::dark().panel));
+
+        // 切换主题后已打开的窗口立即重绘，组件读到新的配色
+        cx.update(|cx| set_theme(Theme::light(), cx));
+        assert_eq!(rendered.borrow().last(), Some(&Theme::light().panel));
+        cx.update(toggle_theme);
+        assert_eq!(rendered.borrow().last(), Some(&Theme::dark().panel));
+    }
+}
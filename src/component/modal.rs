@@ -1,15 +1,21 @@
 use gpui::*;
+use super::{theme, FocusRing};
 use std::rc::Rc;
 
+#[derive(IntoElement)]
 pub struct Modal {
     open: bool,
     title: Option<SharedString>,
     body: Option<AnyElement>,
     footer: Option<AnyElement>,
     on_dismiss: Option<Rc<dyn Fn(&mut Window, &mut App)>>,
+    /// 焦点在模态框本身时按 Enter 触发
+    on_confirm: Option<Rc<dyn Fn(&mut Window, &mut App)>>,
+    focus_handle: Option<FocusHandle>,
     dismiss_on_backdrop: bool,
     show_close_button: bool,
-    backdrop_color: Hsla,
+    /// 未设置时使用主题的遮罩色
+    backdrop_color: Option<Hsla>,
     style: StyleRefinement,
 }
 
@@ -18,9 +24,7 @@ pub fn modal() -> Modal {
     let style = StyleRefinement::default()
         .flex()
         .flex_col()
-        .bg(rgb(0xff2d353b))
         .border_1()
-        .border_color(rgb(0xff3c474d))
         .rounded_md()
         .p(px(16.0))
         .w(px(420.0));
@@ -31,9 +35,11 @@ pub fn modal() -> Modal {
         body: None,
         footer: None,
         on_dismiss: None,
+        on_confirm: None,
+        focus_handle: None,
         dismiss_on_backdrop: true,
         show_close_button: true,
-        backdrop_color: rgba(0x00000080).into(),
+        backdrop_color: None,
         style,
     }
 }
@@ -64,6 +70,18 @@ impl Modal {
         self
     }
 
+    pub fn on_confirm(mut self, on_confirm: impl Fn(&mut Window, &mut App) + 'static) -> Self {
+        self.on_confirm = Some(Rc::new(on_confirm));
+        self
+    }
+
+    /// 跟踪焦点：焦点在模态框内时 Escape 关闭（触发 `on_dismiss`），
+    /// 其中的按钮组成一个 Tab 循环
+    pub fn track_focus(mut self, handle: &FocusHandle) -> Self {
+        self.focus_handle = Some(handle.clone());
+        self
+    }
+
     pub fn dismiss_on_backdrop(mut self, dismiss_on_backdrop: bool) -> Self {
         self.dismiss_on_backdrop = dismiss_on_backdrop;
         self
@@ -75,7 +93,7 @@ impl Modal {
     }
 
     pub fn backdrop_color(mut self, color: impl Into<Hsla>) -> Self {
-        self.backdrop_color = color.into();
+        self.backdrop_color = Some(color.into());
         self
     }
 }
@@ -86,14 +104,13 @@ impl Styled for Modal {
     }
 }
 
-impl IntoElement for Modal {
-    type Element = AnyElement;
-
-    fn into_element(self) -> Self::Element {
+impl RenderOnce for Modal {
+    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
         if !self.open {
             return div().into_any_element();
         }
 
+        let theme = theme(cx);
         let on_dismiss = self.on_dismiss.clone();
         let dismiss_on_backdrop = self.dismiss_on_backdrop;
 
@@ -108,7 +125,7 @@ impl IntoElement for Modal {
             .items_center()
             .justify_center()
             .p(px(24.0))
-            .bg(self.backdrop_color)
+            .bg(self.backdrop_color.unwrap_or(theme.overlay_bg))
             .on_mouse_down(MouseButton::Left, move |_, window, cx| {
                 cx.stop_propagation();
                 if dismiss_on_backdrop {
@@ -123,8 +140,38 @@ impl IntoElement for Modal {
         let title = self.title.clone();
         let style = self.style;
 
-        let mut panel = div().on_any_mouse_down(|_, _window, cx| cx.stop_propagation());
-        *panel.style() = style;
+        // 主题色作为默认值，调用方通过 Styled 设置的样式优先
+        let mut panel = div()
+            .bg(theme.panel)
+            .border_color(theme.border)
+            .shadow(theme.overlay_shadow())
+            .on_any_mouse_down(|_, _window, cx| cx.stop_propagation());
+        panel.style().refine(&style);
+        if let Some(handle) = self.focus_handle.clone() {
+            let on_dismiss = self.on_dismiss.clone();
+            let on_confirm = self.on_confirm.clone();
+            panel = panel
+                .track_focus(&handle)
+                .tab_group()
+                .on_key_down(move |event: &KeyDownEvent, window, cx| {
+                    match event.keystroke.key.as_str() {
+                        "escape" => {
+                            if let Some(on_dismiss) = on_dismiss.as_ref() {
+                                cx.stop_propagation();
+                                on_dismiss(window, cx);
+                            }
+                        }
+                        // 焦点在按钮上时由按钮自己响应 Enter
+                        "enter" if handle.is_focused(window) => {
+                            if let Some(on_confirm) = on_confirm.as_ref() {
+                                cx.stop_propagation();
+                                on_confirm(window, cx);
+                            }
+                        }
+                        _ => {}
+                    }
+                });
+        }
 
         if title.is_some() || show_close_button {
             let mut header = div()
@@ -137,7 +184,7 @@ impl IntoElement for Modal {
                 header = header.child(
                     div()
                         .text_size(px(14.0))
-                        .text_color(rgb(0xffe6e0d9))
+                        .text_color(theme.text)
                         .child(title),
                 );
             } else {
@@ -146,16 +193,25 @@ impl IntoElement for Modal {
 
             if show_close_button {
                 let on_dismiss = on_dismiss_for_close.clone().expect("checked above");
+                let on_keyboard_dismiss = on_dismiss.clone();
                 header = header.child(
                     div()
+                        .id("modal-close")
+                        .focus_ring(cx)
+                        .rounded_sm()
                         .cursor_pointer()
                         .text_size(px(16.0))
-                        .text_color(rgb(0xffa9b1b6))
-                        .hover(|s| s.text_color(rgb(0xffe6e0d9)))
+                        .text_color(theme.muted_text)
+                        .hover(move |s| s.text_color(theme.text))
                         .child("×")
                         .on_mouse_down(MouseButton::Left, move |_, window, cx| {
                             cx.stop_propagation();
                             on_dismiss(window, cx);
+                        })
+                        .on_click(move |event, window, cx| {
+                            if event.is_keyboard() {
+                                on_keyboard_dismiss(window, cx);
+                            }
                         }),
                 );
             }
@@ -0,0 +1,151 @@
+//! 文件树内联输入框的文本与光标。新建时从空白开始；重命名时预填原名，并选中扩展名之前的部分
+
+use std::ops::Range;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NameInput {
+    text: String,
+    cursor: usize,
+    /// 选区的另一端，没有选区时为 None
+    anchor: Option<usize>,
+}
+
+/// 名称中扩展名之前的部分的结束位置；以点开头的名称（如 `.gitignore`）整体视为主名
+pub fn stem_end(name: &str) -> usize {
+    match name.rfind('.') {
+        Some(index) if index > 0 => index,
+        _ => name.len(),
+    }
+}
+
+fn prev_boundary(text: &str, index: usize) -> usize {
+    text[..index].char_indices().next_back().map(|(i, _)| i).unwrap_or(0)
+}
+
+fn next_boundary(text: &str, index: usize) -> usize {
+    text[index..].chars().next().map(|c| index + c.len_utf8()).unwrap_or(index)
+}
+
+impl NameInput {
+    pub fn for_rename(name: &str) -> Self {
+        Self {
+            text: name.to_string(),
+            cursor: stem_end(name),
+            anchor: Some(0),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// 非空的选区
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        let range = anchor.min(self.cursor)..anchor.max(self.cursor);
+        if range.is_empty() {
+            None
+        } else {
+            Some(range)
+        }
+    }
+
+    /// 删除选中的文本，返回是否有选区
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.anchor = None;
+        match selection {
+            Some(range) => {
+                self.cursor = range.start;
+                self.text.replace_range(range, "");
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor > 0 {
+            let start = prev_boundary(&self.text, self.cursor);
+            self.text.replace_range(start..self.cursor, "");
+            self.cursor = start;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if !self.delete_selection() && self.cursor < self.text.len() {
+            let end = next_boundary(&self.text, self.cursor);
+            self.text.replace_range(self.cursor..end, "");
+        }
+    }
+
+    /// 有选区时移到选区的对应一端
+    pub fn move_left(&mut self) {
+        match self.selection() {
+            Some(range) => self.cursor = range.start,
+            None => self.cursor = prev_boundary(&self.text, self.cursor),
+        }
+        self.anchor = None;
+    }
+
+    pub fn move_right(&mut self) {
+        match self.selection() {
+            Some(range) => self.cursor = range.end,
+            None => self.cursor = next_boundary(&self.text, self.cursor),
+        }
+        self.anchor = None;
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+        self.anchor = None;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.len();
+        self.anchor = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_selects_stem_and_typing_keeps_extension() {
+        let mut input = NameInput::for_rename("main.rs");
+        assert_eq!(input.selection(), Some(0..4));
+        input.insert("主程序");
+        assert_eq!(input.text(), "主程序.rs");
+        input.backspace();
+        assert_eq!(input.text(), "主程.rs");
+        input.move_end();
+        input.insert("x");
+        assert_eq!(input.text(), "主程.rsx");
+
+        assert_eq!(NameInput::for_rename(".gitignore").selection(), Some(0..10));
+        assert_eq!(NameInput::for_rename("src").selection(), Some(0..3));
+
+        let mut input = NameInput::for_rename("a.tar.gz");
+        input.move_right();
+        input.delete();
+        assert_eq!(input.text(), "a.targz");
+
+        let mut input = NameInput::default();
+        input.backspace();
+        input.insert("新");
+        input.move_left();
+        input.insert("x");
+        assert_eq!((input.text(), input.cursor()), ("x新", 1));
+    }
+}
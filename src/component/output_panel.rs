@@ -0,0 +1,158 @@
+use gpui::*;
+use std::time::Duration;
+use crate::component::FocusRing;
+use crate::output::{self, OutputChannel, CAPACITY};
+
+/// 输出面板：底部停靠，显示一个频道的日志，可以切换频道和清空。列表停在底部时随新内容滚动，
+/// 向上滚动查看时保持位置
+pub struct OutputPanel {
+    focus_handle: FocusHandle,
+    channel: OutputChannel,
+    /// 当前频道已取到的行
+    lines: Vec<SharedString>,
+    /// 当前频道已取到的总行数，见 [`output::lines_since`]
+    seen: u64,
+    list_state: ListState,
+}
+
+impl OutputPanel {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        cx.spawn(|view: WeakEntity<OutputPanel>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    cx.background_executor().timer(Duration::from_millis(250)).await;
+                    if view.update(&mut cx, |this, cx| this.poll(cx)).is_err() {
+                        break;
+                    }
+                }
+            }
+        })
+        .detach();
+        let mut this = Self {
+            focus_handle: cx.focus_handle(),
+            channel: OutputChannel::App,
+            lines: Vec::new(),
+            seen: 0,
+            list_state: ListState::new(0, ListAlignment::Bottom, px(200.0)),
+        };
+        this.poll(cx);
+        this
+    }
+
+    pub fn set_channel(&mut self, channel: OutputChannel, cx: &mut Context<Self>) {
+        if self.channel == channel {
+            return;
+        }
+        self.channel = channel;
+        self.lines.clear();
+        self.seen = 0;
+        self.list_state.reset(0);
+        self.poll(cx);
+        cx.notify();
+    }
+
+    pub fn clear(&mut self, cx: &mut Context<Self>) {
+        output::clear(self.channel);
+        self.lines.clear();
+        self.list_state.reset(0);
+        cx.notify();
+    }
+
+    /// 取当前频道新写入的行
+    fn poll(&mut self, cx: &mut Context<Self>) {
+        let (added, total) = output::lines_since(self.channel, self.seen);
+        if total == self.seen {
+            return;
+        }
+        self.seen = total;
+        let start = self.lines.len();
+        self.list_state.splice(start..start, added.len());
+        self.lines.extend(added.into_iter().map(SharedString::from));
+        // 与输出中保留的行数一致
+        if self.lines.len() > CAPACITY {
+            let excess = self.lines.len() - CAPACITY;
+            self.lines.drain(..excess);
+            self.list_state.splice(0..excess, 0);
+        }
+        cx.notify();
+    }
+}
+
+impl Render for OutputPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        let theme_text = theme.text;
+        let theme_muted = theme.muted_text;
+        let panel = cx.entity();
+
+        let header = div()
+            .h(px(28.0))
+            .px(px(8.0))
+            .flex()
+            .items_center()
+            .gap(px(4.0))
+            .text_size(px(12.0))
+            .border_b_1()
+            .border_color(theme.border)
+            .child(div().mr(px(4.0)).text_color(theme_text).child("输出"))
+            .children(OutputChannel::ALL.into_iter().enumerate().map(|(index, channel)| {
+                let selected = channel == self.channel;
+                let panel = panel.clone();
+                div()
+                    .id(("output-channel", index))
+                    .focus_ring(cx)
+                    .px(px(6.0))
+                    .py(px(1.0))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .bg(if selected { theme.panel } else { transparent_black() })
+                    .text_color(if selected { theme.text } else { theme_muted })
+                    .hover(|s| s.bg(theme.list_hover))
+                    .child(channel.label())
+                    .on_click(move |_, _window, cx| {
+                        panel.update(cx, |panel, cx| panel.set_channel(channel, cx));
+                    })
+            }))
+            .child(div().flex_1())
+            .child(
+                div()
+                    .id("output-clear")
+                    .focus_ring(cx)
+                    .px(px(6.0))
+                    .py(px(1.0))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .text_color(theme_muted)
+                    .hover(|s| s.bg(theme.list_hover).text_color(theme.text))
+                    .child("清空")
+                    .on_click(move |_, _window, cx| {
+                        panel.update(cx, |panel, cx| panel.clear(cx));
+                    }),
+            );
+
+        let lines = self.lines.clone();
+        let body = list(self.list_state.clone(), move |index, _, _| {
+            let Some(line) = lines.get(index) else {
+                return div().into_any_element();
+            };
+            div()
+                .w_full()
+                .px(px(8.0))
+                .text_size(px(12.0))
+                .text_color(theme_text)
+                .child(line.clone())
+                .into_any_element()
+        })
+        .flex_1()
+        .w_full();
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .track_focus(&self.focus_handle)
+            .child(header)
+            .child(body)
+    }
+}
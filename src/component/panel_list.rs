@@ -0,0 +1,122 @@
+//! 面板里可过滤列表的通用键盘行为：上下移动选中项、Escape 逐级退出，以及 F6 在各区域间切换焦点
+
+/// 列表中的选中项，移动时限制在列表范围内
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ListSelection {
+    pub index: usize,
+}
+
+impl ListSelection {
+    /// 按键对应的移动；返回 true 表示选中项变化，需要滚动到可见并重绘
+    pub fn handle_key(&mut self, key: &str, len: usize) -> bool {
+        let target = match key {
+            "up" => self.index.saturating_sub(1),
+            "down" => (self.index + 1).min(len.saturating_sub(1)),
+            "pageup" => self.index.saturating_sub(10),
+            "pagedown" => (self.index + 10).min(len.saturating_sub(1)),
+            _ => return false,
+        };
+        let changed = target != self.index;
+        self.index = target;
+        changed
+    }
+
+    pub fn reset(&mut self) {
+        self.index = 0;
+    }
+}
+
+/// 面板内按 Escape 时应做的事，依次为：清空过滤 → 离开面板回到编辑器
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PanelEscape {
+    ClearFilter,
+    LeavePanel,
+}
+
+impl PanelEscape {
+    pub fn for_filter(filter: &str) -> Self {
+        if filter.is_empty() {
+            PanelEscape::LeavePanel
+        } else {
+            PanelEscape::ClearFilter
+        }
+    }
+}
+
+/// F6 循环经过的焦点区域，按切换顺序排列
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FocusRegion {
+    Editor,
+    StatusBar,
+    Toolbar,
+    ToolPanel,
+    TabBar,
+}
+
+impl FocusRegion {
+    /// 从编辑器向下到状态栏，再从窗口顶部的工具栏往下回到编辑器
+    pub const ORDER: [FocusRegion; 5] = [
+        FocusRegion::Editor,
+        FocusRegion::StatusBar,
+        FocusRegion::Toolbar,
+        FocusRegion::ToolPanel,
+        FocusRegion::TabBar,
+    ];
+
+    /// 从 `current` 出发的下一个（`backwards` 时为上一个）可用区域；
+    /// 当前焦点不在任何区域时从编辑器开始
+    pub fn cycle(current: Option<Self>, available: &[Self], backwards: bool) -> Option<Self> {
+        let order: Vec<Self> = Self::ORDER.into_iter().filter(|r| available.contains(r)).collect();
+        if order.is_empty() {
+            return None;
+        }
+        let Some(index) = current.and_then(|c| order.iter().position(|r| *r == c)) else {
+            return order.first().copied();
+        };
+        let next = if backwards {
+            (index + order.len() - 1) % order.len()
+        } else {
+            (index + 1) % order.len()
+        };
+        Some(order[next])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_cycle_order() {
+        use FocusRegion::*;
+        let panels = [Editor, ToolPanel];
+        assert_eq!(FocusRegion::cycle(Some(Editor), &panels, false), Some(ToolPanel));
+        assert_eq!(FocusRegion::cycle(Some(ToolPanel), &panels, false), Some(Editor));
+        assert_eq!(FocusRegion::cycle(Some(Editor), &panels, true), Some(ToolPanel));
+        assert_eq!(FocusRegion::cycle(None, &panels, false), Some(Editor));
+        let all = FocusRegion::ORDER;
+        assert_eq!(FocusRegion::cycle(Some(Editor), &all, false), Some(StatusBar));
+        assert_eq!(FocusRegion::cycle(Some(StatusBar), &all, false), Some(Toolbar));
+        assert_eq!(FocusRegion::cycle(Some(TabBar), &all, false), Some(Editor));
+        assert_eq!(FocusRegion::cycle(Some(Editor), &all, true), Some(TabBar));
+        assert_eq!(FocusRegion::cycle(Some(StatusBar), &[Editor, StatusBar, ToolPanel], false), Some(ToolPanel));
+        // 隐藏的区域被跳过
+        assert_eq!(FocusRegion::cycle(Some(Editor), &[Editor], false), Some(Editor));
+        assert_eq!(FocusRegion::cycle(Some(Editor), &[], false), None);
+    }
+
+    #[test]
+    fn test_list_selection_and_escape_steps() {
+        let mut selection = ListSelection::default();
+        assert!(!selection.handle_key("up", 3));
+        assert!(selection.handle_key("down", 3));
+        assert!(selection.handle_key("pagedown", 3));
+        assert_eq!(selection.index, 2);
+        assert!(!selection.handle_key("down", 3));
+        let mut empty = ListSelection::default();
+        assert!(!empty.handle_key("down", 0));
+
+        assert_eq!(PanelEscape::for_filter("abc"), PanelEscape::ClearFilter);
+        assert_eq!(PanelEscape::for_filter(""), PanelEscape::LeavePanel);
+    }
+}
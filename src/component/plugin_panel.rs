@@ -0,0 +1,187 @@
+use gpui::*;
+use crate::component::{FocusRing, Theme};
+use crate::plugin::manager::{PluginState, PluginSummary};
+
+pub enum PluginPanelEvent {
+    /// 点击了插件的启用开关
+    SetEnabled { id: String, enabled: bool },
+    /// 点击了“重新扫描插件”
+    Rescan,
+}
+
+impl EventEmitter<PluginPanelEvent> for PluginPanel {}
+
+/// 插件页：列出发现的插件及其状态，可以启用、停用插件和重新扫描插件目录
+pub struct PluginPanel {
+    focus_handle: FocusHandle,
+    plugins: Vec<PluginSummary>,
+    load_errors: Vec<String>,
+}
+
+fn state_label(state: &PluginState, theme: &Theme) -> (String, Hsla) {
+    match state {
+        PluginState::Discovered => ("未激活".to_string(), theme.muted_text),
+        PluginState::Activated => ("已激活".to_string(), theme.success),
+        PluginState::Failed(reason) => (format!("出错：{}", reason), theme.error),
+        PluginState::Disabled => ("已停用".to_string(), theme.muted_text),
+    }
+}
+
+impl PluginPanel {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self { focus_handle: cx.focus_handle(), plugins: Vec::new(), load_errors: Vec::new() }
+    }
+
+    pub fn focus(&self, window: &mut Window) {
+        self.focus_handle.focus(window);
+    }
+
+    /// 插件扫描、启用或停用之后刷新列表
+    pub fn set_plugins(&mut self, plugins: Vec<PluginSummary>, load_errors: Vec<String>, cx: &mut Context<Self>) {
+        self.plugins = plugins;
+        self.load_errors = load_errors;
+        cx.notify();
+    }
+}
+
+impl Render for PluginPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        let theme_text = theme.text;
+        let theme_muted = theme.muted_text;
+        let theme_error = theme.error;
+        let panel = cx.entity();
+
+        let header = div()
+            .h(px(30.0))
+            .px(px(8.0))
+            .flex()
+            .items_center()
+            .justify_between()
+            .text_size(px(13.0))
+            .text_color(theme_text)
+            .child(format!("插件（{}）", self.plugins.len()))
+            .child(
+                div()
+                    .id("plugin-rescan")
+                    .focus_ring(cx)
+                    .px(px(6.0))
+                    .py(px(2.0))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .text_size(px(12.0))
+                    .text_color(theme_muted)
+                    .hover(|s| s.bg(theme.list_hover).text_color(theme.text))
+                    .child("重新扫描插件")
+                    .on_click({
+                        let panel = panel.clone();
+                        move |_, _window, cx| {
+                            panel.update(cx, |_, cx| cx.emit(PluginPanelEvent::Rescan));
+                        }
+                    }),
+            );
+
+        let mut body = div()
+            .id("plugin-list")
+            .flex_1()
+            .w_full()
+            .overflow_y_scroll()
+            .flex()
+            .flex_col();
+        if self.plugins.is_empty() && self.load_errors.is_empty() {
+            body = body.child(
+                div().px(px(8.0)).py(px(6.0)).text_size(px(12.0)).text_color(theme_muted).child("没有发现插件"),
+            );
+        }
+        for (index, plugin) in self.plugins.iter().enumerate() {
+            let enabled = plugin.state != PluginState::Disabled;
+            let (state, state_color) = state_label(&plugin.state, &theme);
+            let toggle = {
+                let panel = panel.clone();
+                let id = plugin.id.clone();
+                div()
+                    .id(("plugin-toggle", index))
+                    .focus_ring(cx)
+                    .flex_none()
+                    .px(px(6.0))
+                    .py(px(1.0))
+                    .rounded_md()
+                    .border_1()
+                    .border_color(if enabled { theme.success } else { theme_muted })
+                    .cursor_pointer()
+                    .text_size(px(11.0))
+                    .text_color(if enabled { theme.success } else { theme_muted })
+                    .hover(|s| s.bg(theme.list_hover))
+                    .child(if enabled { "已启用" } else { "已停用" })
+                    .on_click(move |_, _window, cx| {
+                        let event = PluginPanelEvent::SetEnabled { id: id.clone(), enabled: !enabled };
+                        panel.update(cx, |_, cx| cx.emit(event));
+                    })
+            };
+            body = body.child(
+                div()
+                    .w_full()
+                    .px(px(8.0))
+                    .py(px(6.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(2.0))
+                    .text_size(px(12.0))
+                    .border_b_1()
+                    .border_color(theme.border)
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap(px(6.0))
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .min_w(px(0.0))
+                                    .overflow_hidden()
+                                    .whitespace_nowrap()
+                                    .text_color(if enabled { theme_text } else { theme_muted })
+                                    .child(plugin.name.clone()),
+                            )
+                            .child(div().flex_none().text_color(theme_muted).child(plugin.version.clone()))
+                            .child(toggle),
+                    )
+                    .child(
+                        div()
+                            .text_size(px(11.0))
+                            .text_color(theme_muted)
+                            .child(format!("{} · {} 个命令", plugin.id, plugin.commands)),
+                    )
+                    .child(div().text_size(px(11.0)).text_color(state_color).child(state))
+                    .children(
+                        plugin
+                            .problems
+                            .iter()
+                            .map(|problem| div().text_size(px(11.0)).text_color(theme_error).child(problem.clone())),
+                    ),
+            );
+        }
+        if !self.load_errors.is_empty() {
+            body = body.child(
+                div()
+                    .px(px(8.0))
+                    .pt(px(8.0))
+                    .flex()
+                    .flex_col()
+                    .gap(px(2.0))
+                    .text_size(px(11.0))
+                    .text_color(theme_error)
+                    .child(div().text_color(theme_text).child("无法读取的插件清单"))
+                    .children(self.load_errors.iter().map(|error| div().child(error.clone()))),
+            );
+        }
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .track_focus(&self.focus_handle)
+            .child(header)
+            .child(body)
+    }
+}
@@ -1,12 +1,15 @@
 use gpui::*;
+use super::theme;
 use std::rc::Rc;
 
+#[derive(IntoElement)]
 pub struct Popover {
     open: bool,
     position: Point<Pixels>,
     content: Option<AnyElement>,
     on_dismiss: Option<Rc<dyn Fn(&mut Window, &mut App)>>,
     dismiss_on_outside_click: bool,
+    focus_handle: Option<FocusHandle>,
     style: StyleRefinement,
 }
 
@@ -18,9 +21,7 @@ pub fn popover() -> Popover {
     let style = StyleRefinement::default()
         .flex()
         .flex_col()
-        .bg(rgb(0xff2d353b))
         .border_1()
-        .border_color(rgb(0xff3c474d))
         .rounded_md()
         .p(px(12.0))
         .w(px(220.0));
@@ -31,6 +32,7 @@ pub fn popover() -> Popover {
         content: None,
         on_dismiss: None,
         dismiss_on_outside_click: true,
+        focus_handle: None,
         style,
     }
 }
@@ -62,6 +64,13 @@ impl Popover {
         self.dismiss_on_outside_click = dismiss_on_outside_click;
         self
     }
+
+    /// Track focus on the popover: Escape inside it triggers `on_dismiss`, and its
+    /// focusable items form one tab group.
+    pub fn track_focus(mut self, handle: &FocusHandle) -> Self {
+        self.focus_handle = Some(handle.clone());
+        self
+    }
 }
 
 impl Styled for Popover {
@@ -70,14 +79,13 @@ impl Styled for Popover {
     }
 }
 
-impl IntoElement for Popover {
-    type Element = AnyElement;
-
-    fn into_element(self) -> Self::Element {
+impl RenderOnce for Popover {
+    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
         if !self.open {
             return div().into_any_element();
         }
 
+        let theme = theme(cx);
         let on_dismiss = self.on_dismiss.clone();
         let dismiss_on_outside_click = self.dismiss_on_outside_click;
 
@@ -96,9 +104,26 @@ impl IntoElement for Popover {
 
         let position = self.position;
         let style = self.style;
-        let mut panel = div().on_any_mouse_down(|_, _window, cx| cx.stop_propagation());
-        *panel.style() = style;
+        let mut panel = div()
+            .bg(theme.panel)
+            .border_color(theme.border)
+            .shadow(theme.overlay_shadow())
+            .on_any_mouse_down(|_, _window, cx| cx.stop_propagation());
+        panel.style().refine(&style);
         panel = panel.absolute().top(position.y).left(position.x);
+        if let Some(handle) = &self.focus_handle {
+            panel = panel
+                .track_focus(handle)
+                .tab_group()
+                .on_key_down(move |event: &KeyDownEvent, window, cx| {
+                    if event.keystroke.key == "escape" {
+                        if let Some(on_dismiss) = on_dismiss.as_ref() {
+                            cx.stop_propagation();
+                            on_dismiss(window, cx);
+                        }
+                    }
+                });
+        }
 
         if let Some(content) = self.content {
             panel = panel.child(content);
@@ -0,0 +1,151 @@
+use gpui::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use crate::component::FocusRing;
+use crate::editor::lsp_integration::{Problem, ProblemSeverity};
+
+pub enum ProblemsPanelEvent {
+    /// 点击了一条问题（行列均从 0 开始，列按字符计）
+    Open { path: PathBuf, line: usize, column: usize },
+}
+
+impl EventEmitter<ProblemsPanelEvent> for ProblemsPanel {}
+
+/// 问题页：按文件列出查错得到的错误和警告，点击跳到所在位置
+pub struct ProblemsPanel {
+    focus_handle: FocusHandle,
+    problems: BTreeMap<PathBuf, Vec<Problem>>,
+}
+
+impl ProblemsPanel {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self { focus_handle: cx.focus_handle(), problems: BTreeMap::new() }
+    }
+
+    pub fn focus(&self, window: &mut Window) {
+        self.focus_handle.focus(window);
+    }
+
+    /// 用文件最新的查错结果替换它的问题；没有问题时从列表中移除
+    pub fn set_problems(&mut self, path: PathBuf, problems: Vec<Problem>, cx: &mut Context<Self>) {
+        if problems.is_empty() {
+            if self.problems.remove(&path).is_none() {
+                return;
+            }
+        } else {
+            self.problems.insert(path, problems);
+        }
+        cx.notify();
+    }
+
+    /// 文件关闭或删除后移除它的问题
+    pub fn remove_path(&mut self, path: &Path, cx: &mut Context<Self>) {
+        if self.problems.remove(path).is_some() {
+            cx.notify();
+        }
+    }
+
+    fn counts(&self) -> (usize, usize) {
+        let all = self.problems.values().flatten();
+        let errors = all.clone().filter(|p| p.severity == ProblemSeverity::Error).count();
+        (errors, all.count() - errors)
+    }
+}
+
+impl Render for ProblemsPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        let theme_text = theme.text;
+        let theme_muted = theme.muted_text;
+        let panel = cx.entity();
+        let (errors, warnings) = self.counts();
+
+        let header = div()
+            .h(px(30.0))
+            .px(px(8.0))
+            .flex()
+            .items_center()
+            .text_size(px(13.0))
+            .text_color(theme_text)
+            .child(format!("问题（错误 {}，警告 {}）", errors, warnings));
+
+        let mut body = div()
+            .id("problem-list")
+            .flex_1()
+            .w_full()
+            .overflow_y_scroll()
+            .flex()
+            .flex_col();
+        if self.problems.is_empty() {
+            body = body.child(
+                div().px(px(8.0)).py(px(6.0)).text_size(px(12.0)).text_color(theme_muted).child("没有发现问题"),
+            );
+        }
+        let mut index = 0usize;
+        for (path, problems) in &self.problems {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            body = body.child(
+                div()
+                    .px(px(8.0))
+                    .pt(px(6.0))
+                    .pb(px(2.0))
+                    .flex()
+                    .gap(px(6.0))
+                    .text_size(px(12.0))
+                    .child(div().text_color(theme_text).child(name))
+                    .child(div().text_color(theme_muted).child(problems.len().to_string())),
+            );
+            for problem in problems {
+                let (mark, color) = match problem.severity {
+                    ProblemSeverity::Error => ("✕", theme.error),
+                    ProblemSeverity::Warning => ("⚠", theme.warning),
+                };
+                let panel = panel.clone();
+                let (path, line, column) = (path.clone(), problem.line, problem.column);
+                body = body.child(
+                    div()
+                        .id(("problem", index))
+                        .focus_ring(cx)
+                        .w_full()
+                        .pl(px(16.0))
+                        .pr(px(8.0))
+                        .py(px(2.0))
+                        .flex()
+                        .gap(px(6.0))
+                        .text_size(px(12.0))
+                        .cursor_pointer()
+                        .hover(|s| s.bg(theme.list_hover))
+                        .child(div().flex_none().text_color(color).child(mark))
+                        .child(
+                            div()
+                                .flex_1()
+                                .min_w(px(0.0))
+                                .overflow_hidden()
+                                .whitespace_nowrap()
+                                .text_color(theme_text)
+                                .child(problem.message.clone()),
+                        )
+                        .child(
+                            div()
+                                .flex_none()
+                                .text_color(theme_muted)
+                                .child(format!("[{}, {}]", line + 1, column + 1)),
+                        )
+                        .on_click(move |_, _window, cx| {
+                            let event = ProblemsPanelEvent::Open { path: path.clone(), line, column };
+                            panel.update(cx, |_, cx| cx.emit(event));
+                        }),
+                );
+                index += 1;
+            }
+        }
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .track_focus(&self.focus_handle)
+            .child(header)
+            .child(body)
+    }
+}
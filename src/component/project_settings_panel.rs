@@ -0,0 +1,375 @@
+use gpui::*;
+use std::path::PathBuf;
+use crate::component::name_input::NameInput;
+use crate::component::FocusRing;
+use crate::lsp::tiec::settings::{TargetPlatform, TiecSettings, PROJECT_SETTINGS_FILE};
+
+pub enum ProjectSettingsPanelEvent {
+    /// 点击了保存，`content` 为写入 tiec 一节后的整个设置文件
+    Save { path: PathBuf, content: String },
+}
+
+impl EventEmitter<ProjectSettingsPanelEvent> for ProjectSettingsPanel {}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    PackageName,
+    NewPath,
+}
+
+/// 项目设置页：编辑设置文件的 tiec 一节，包括包名、目标平台和类库搜索路径
+pub struct ProjectSettingsPanel {
+    focus_handle: FocusHandle,
+    root: Option<PathBuf>,
+    settings: TiecSettings,
+    package_name: NameInput,
+    new_path: NameInput,
+    focused: Option<Field>,
+    platform_open: bool,
+    /// 读取或写回设置文件失败的原因
+    error: Option<String>,
+}
+
+fn input_with(text: &str) -> NameInput {
+    let mut input = NameInput::default();
+    input.insert(text);
+    input
+}
+
+impl ProjectSettingsPanel {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self {
+            focus_handle: cx.focus_handle(),
+            root: None,
+            settings: TiecSettings::default(),
+            package_name: NameInput::default(),
+            new_path: NameInput::default(),
+            focused: None,
+            platform_open: false,
+            error: None,
+        }
+    }
+
+    pub fn focus(&self, window: &mut Window) {
+        self.focus_handle.focus(window);
+    }
+
+    /// 打开文件夹或设置文件被保存后重新读取设置
+    pub fn set_project_root(&mut self, root: PathBuf, cx: &mut Context<Self>) {
+        let (settings, error) = match TiecSettings::load(&root) {
+            Ok(settings) => (settings.unwrap_or_default(), None),
+            Err(err) => (TiecSettings::default(), Some(format!("{err:#}"))),
+        };
+        self.package_name = input_with(settings.package_name.as_deref().unwrap_or_default());
+        self.new_path = NameInput::default();
+        self.settings = settings;
+        self.error = error;
+        self.root = Some(root);
+        self.platform_open = false;
+        cx.notify();
+    }
+
+    fn set_platform(&mut self, platform: Option<TargetPlatform>, cx: &mut Context<Self>) {
+        self.settings.platform = platform;
+        self.platform_open = false;
+        cx.notify();
+    }
+
+    fn add_search_path(&mut self, cx: &mut Context<Self>) {
+        let path = self.new_path.text().trim().to_string();
+        if !path.is_empty() && !self.settings.search_paths.contains(&path) {
+            self.settings.search_paths.push(path);
+        }
+        self.new_path = NameInput::default();
+        cx.notify();
+    }
+
+    fn remove_search_path(&mut self, index: usize, cx: &mut Context<Self>) {
+        if index < self.settings.search_paths.len() {
+            self.settings.search_paths.remove(index);
+            cx.notify();
+        }
+    }
+
+    /// 写回 tiec 一节，设置文件中的其他键保持不变；校验和重启服务由保存流程负责
+    fn save(&mut self, cx: &mut Context<Self>) {
+        let Some(root) = self.root.clone() else {
+            return;
+        };
+        let name = self.package_name.text().trim();
+        self.settings.package_name = (!name.is_empty()).then(|| name.to_string());
+        let path = root.join(PROJECT_SETTINGS_FILE);
+        let existing = std::fs::read_to_string(&path).unwrap_or_default();
+        match self.settings.merge_into(&existing) {
+            Ok(content) => {
+                self.error = None;
+                cx.emit(ProjectSettingsPanelEvent::Save { path, content });
+            }
+            Err(err) => self.error = Some(format!("{err:#}")),
+        }
+        cx.notify();
+    }
+
+    fn focus_field(&mut self, field: Field, window: &mut Window, cx: &mut Context<Self>) {
+        self.focused = Some(field);
+        self.focus_handle.focus(window);
+        cx.notify();
+    }
+
+    fn edit_field(&mut self, cx: &mut Context<Self>, edit: impl FnOnce(&mut NameInput)) {
+        match self.focused {
+            Some(Field::PackageName) => edit(&mut self.package_name),
+            Some(Field::NewPath) => edit(&mut self.new_path),
+            None => return,
+        }
+        cx.notify();
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let key = event.keystroke.key.as_str();
+        if key == "escape" {
+            self.platform_open = false;
+            self.focused = None;
+            cx.notify();
+            return;
+        }
+        let Some(field) = self.focused else {
+            return;
+        };
+        match key {
+            "enter" => {
+                if field == Field::NewPath {
+                    self.add_search_path(cx);
+                } else {
+                    self.focused = None;
+                    cx.notify();
+                }
+                return;
+            }
+            "backspace" => return self.edit_field(cx, NameInput::backspace),
+            "delete" => return self.edit_field(cx, NameInput::delete),
+            "left" => return self.edit_field(cx, NameInput::move_left),
+            "right" => return self.edit_field(cx, NameInput::move_right),
+            "home" => return self.edit_field(cx, NameInput::move_home),
+            "end" => return self.edit_field(cx, NameInput::move_end),
+            _ => {}
+        }
+        let modifiers = event.keystroke.modifiers;
+        if modifiers.control || modifiers.alt || modifiers.platform || modifiers.function {
+            return;
+        }
+        if let Some(text) = event.keystroke.key_char.as_ref() {
+            if !text.is_empty() && !text.chars().all(|c| c.is_control()) {
+                self.edit_field(cx, |input| input.insert(text));
+            }
+        } else if key == "space" {
+            self.edit_field(cx, |input| input.insert(" "));
+        }
+    }
+
+    fn render_input(&self, field: Field, placeholder: &'static str, cx: &mut Context<Self>) -> Stateful<Div> {
+        let theme = crate::component::theme(cx);
+        let input = match field {
+            Field::PackageName => &self.package_name,
+            Field::NewPath => &self.new_path,
+        };
+        let focused = self.focused == Some(field);
+        let caret = || div().w(px(1.5)).h(px(14.0)).bg(theme.accent);
+        let text = input.text();
+        let mut content = div().flex().items_center().overflow_hidden().whitespace_nowrap();
+        if text.is_empty() {
+            if focused {
+                content = content.child(caret());
+            }
+            content = content.child(div().text_color(theme.muted_text).child(placeholder));
+        } else {
+            let cursor = input.cursor();
+            content = content.child(text[..cursor].to_string());
+            if focused {
+                content = content.child(caret());
+            }
+            content = content.child(text[cursor..].to_string());
+        }
+        let id = match field {
+            Field::PackageName => "settings-package-name",
+            Field::NewPath => "settings-new-path",
+        };
+        div()
+            .id(id)
+            .flex_1()
+            .min_w(px(0.0))
+            .h(px(24.0))
+            .px(px(6.0))
+            .flex()
+            .items_center()
+            .bg(theme.input_bg)
+            .border_1()
+            .border_color(if focused { theme.accent } else { theme.input_border })
+            .rounded(px(3.0))
+            .cursor(CursorStyle::IBeam)
+            .child(content)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _, window, cx| {
+                    cx.stop_propagation();
+                    this.focus_field(field, window, cx);
+                }),
+            )
+    }
+}
+
+fn platform_label(platform: Option<TargetPlatform>) -> String {
+    match platform {
+        Some(platform) => platform.target_name().to_string(),
+        None => "默认（android）".to_string(),
+    }
+}
+
+impl Render for ProjectSettingsPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        let label = |text: &'static str| div().mt(px(10.0)).mb(px(4.0)).text_color(theme.muted_text).child(text);
+        let button = |id: ElementId, text: &'static str| {
+            div()
+                .id(id)
+                .flex_none()
+                .px(px(8.0))
+                .py(px(2.0))
+                .rounded_md()
+                .border_1()
+                .border_color(theme.border)
+                .cursor_pointer()
+                .hover(|s| s.bg(theme.list_hover))
+                .child(text)
+        };
+
+        let mut body = div()
+            .id("project-settings")
+            .flex_1()
+            .w_full()
+            .overflow_y_scroll()
+            .px(px(8.0))
+            .py(px(6.0))
+            .flex()
+            .flex_col()
+            .text_size(px(12.0))
+            .text_color(theme.text);
+        if self.root.is_none() {
+            return div()
+                .track_focus(&self.focus_handle)
+                .size_full()
+                .px(px(8.0))
+                .py(px(6.0))
+                .text_size(px(12.0))
+                .text_color(theme.muted_text)
+                .child("打开文件夹后可以编辑项目设置");
+        }
+
+        body = body
+            .child(div().text_size(px(13.0)).child("结绳编译选项"))
+            .child(label("包名"))
+            .child(self.render_input(Field::PackageName, "默认为文件夹名", cx))
+            .child(label("目标平台"));
+
+        let mut platform = div().flex().flex_col().child(
+            div()
+                .id("settings-platform")
+                .focus_ring(cx)
+                .h(px(24.0))
+                .px(px(6.0))
+                .flex()
+                .items_center()
+                .justify_between()
+                .bg(theme.input_bg)
+                .border_1()
+                .border_color(theme.input_border)
+                .rounded(px(3.0))
+                .cursor_pointer()
+                .child(platform_label(self.settings.platform))
+                .child(div().text_color(theme.muted_text).child(if self.platform_open { "▴" } else { "▾" }))
+                .on_click(cx.listener(|this, _, _window, cx| {
+                    this.platform_open = !this.platform_open;
+                    cx.notify();
+                })),
+        );
+        if self.platform_open {
+            let mut options = div()
+                .flex()
+                .flex_col()
+                .py(px(2.0))
+                .bg(theme.surface)
+                .border_1()
+                .border_color(theme.border)
+                .rounded(px(3.0));
+            let choices = std::iter::once(None).chain(TargetPlatform::ALL.into_iter().map(Some));
+            for (index, choice) in choices.enumerate() {
+                let selected = choice == self.settings.platform;
+                options = options.child(
+                    div()
+                        .id(("settings-platform-option", index))
+                        .focus_ring(cx)
+                        .px(px(6.0))
+                        .py(px(3.0))
+                        .cursor_pointer()
+                        .bg(if selected { theme.list_selection } else { transparent_black() })
+                        .hover(|s| s.bg(theme.list_hover))
+                        .child(platform_label(choice))
+                        .on_click(cx.listener(move |this, _, _window, cx| this.set_platform(choice, cx))),
+                );
+            }
+            platform = platform.child(options);
+        }
+        body = body.child(platform).child(label("类库搜索路径"));
+
+        if self.settings.search_paths.is_empty() {
+            body = body.child(div().text_color(theme.muted_text).child("没有额外的搜索路径"));
+        }
+        for (index, path) in self.settings.search_paths.iter().enumerate() {
+            body = body.child(
+                div()
+                    .py(px(2.0))
+                    .flex()
+                    .items_center()
+                    .gap(px(6.0))
+                    .child(div().flex_1().min_w(px(0.0)).overflow_hidden().whitespace_nowrap().child(path.clone()))
+                    .child(
+                        button(("settings-remove-path", index).into(), "移除")
+                            .focus_ring(cx)
+                            .on_click(cx.listener(move |this, _, _window, cx| this.remove_search_path(index, cx))),
+                    ),
+            );
+        }
+        body = body.child(
+            div()
+                .mt(px(4.0))
+                .flex()
+                .items_center()
+                .gap(px(6.0))
+                .child(self.render_input(Field::NewPath, "相对项目根目录，支持 ~ 和环境变量", cx))
+                .child(
+                    button("settings-add-path".into(), "添加")
+                        .focus_ring(cx)
+                        .on_click(cx.listener(|this, _, _window, cx| this.add_search_path(cx))),
+                ),
+        );
+
+        if let Some(error) = &self.error {
+            body = body.child(div().mt(px(10.0)).text_color(theme.error).child(error.clone()));
+        }
+        body = body.child(
+            div().mt(px(12.0)).flex().child(
+                button("settings-save".into(), "保存")
+                    .focus_ring(cx)
+                    .on_click(cx.listener(|this, _, _window, cx| this.save(cx))),
+            ),
+        );
+
+        div()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .size_full()
+            .flex()
+            .flex_col()
+            .child(body)
+    }
+}
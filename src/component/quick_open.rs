@@ -0,0 +1,142 @@
+//! 快速打开（Ctrl+P）：列出文件夹下的所有文件，按相对路径模糊匹配，得分最高的排在前面。
+//! 跳过构建和版本库目录以及 .gitignore 忽略的项，在后台线程中遍历
+
+use super::fuzzy::fuzzy_match;
+use super::ignore_rules::IgnoreRules;
+use super::search_panel::SKIPPED_DIRS;
+use std::path::{Path, PathBuf};
+
+/// 列表中最多显示的文件数
+pub const MAX_RESULTS: usize = 100;
+
+/// 根目录下所有文件相对根目录的路径，用 `/` 分隔，按字母顺序排列
+pub fn project_files(root: &Path) -> Vec<String> {
+    let rules = IgnoreRules::new(&[root.to_path_buf()]);
+    let mut files = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            if rules.is_ignored(&path, is_dir) {
+                continue;
+            }
+            if is_dir {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !SKIPPED_DIRS.contains(&name.as_str()) {
+                    stack.push(path);
+                }
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                let parts: Vec<String> = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect();
+                files.push(parts.join("/"));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// 快速打开列出的文件：显示的路径和对应的完整路径，按显示的路径排序
+#[derive(Debug, Default)]
+pub struct WorkspaceFiles {
+    pub labels: Vec<String>,
+    paths: Vec<PathBuf>,
+}
+
+impl WorkspaceFiles {
+    pub fn path_for(&self, label: &str) -> Option<&PathBuf> {
+        let index = self.labels.binary_search_by(|l| l.as_str().cmp(label)).ok()?;
+        self.paths.get(index)
+    }
+}
+
+/// 各根目录下的文件；有多个根目录时显示的路径以根目录名开头
+pub fn workspace_files(roots: &[PathBuf]) -> WorkspaceFiles {
+    let mut entries: Vec<(String, PathBuf)> = Vec::new();
+    for root in roots {
+        let prefix = (roots.len() > 1).then(|| {
+            root.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| root.display().to_string())
+        });
+        for file in project_files(root) {
+            let path = root.join(&file);
+            let label = match &prefix {
+                Some(prefix) => format!("{}/{}", prefix, file),
+                None => file,
+            };
+            entries.push((label, path));
+        }
+    }
+    entries.sort();
+    let (labels, paths) = entries.into_iter().unzip();
+    WorkspaceFiles { labels, paths }
+}
+
+/// 匹配 `query` 的文件及路径中匹配到的字符位置，得分最高的在前，最多 `limit` 个；
+/// 查询为空时按原顺序取前 `limit` 个
+pub fn rank_files(query: &str, files: &[String], limit: usize) -> Vec<(String, Vec<usize>)> {
+    let mut scored: Vec<(i64, &String, Vec<usize>)> = files
+        .iter()
+        .filter_map(|file| fuzzy_match(query, file).map(|m| (m.score, file, m.positions)))
+        .collect();
+    // 得分相同时保持字母顺序
+    scored.sort_by_key(|entry| std::cmp::Reverse(entry.0));
+    scored.truncate(limit);
+    scored.into_iter().map(|(_, file, positions)| (file.clone(), positions)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Repository;
+
+    #[test]
+    fn test_lists_files_and_ranks_best_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        Repository::init(&root).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\nbuild/\n").unwrap();
+        for dir in ["src/ui", "build", "target", "node_modules/x"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["src/ui/main_window.t", "src/main.t", "src/debug.log", "build/out.t", "target/a.t", "node_modules/x/b.t"] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        let files = project_files(&root);
+        assert_eq!(files, vec![".gitignore", "src/main.t", "src/ui/main_window.t"]);
+
+        let ranked = rank_files("main", &files, MAX_RESULTS);
+        assert_eq!(ranked[0], ("src/main.t".to_string(), vec![4, 5, 6, 7]));
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(rank_files("mw", &files, MAX_RESULTS)[0].0, "src/ui/main_window.t");
+        assert_eq!(rank_files("", &files, 1), vec![(".gitignore".to_string(), Vec::new())]);
+    }
+
+    #[test]
+    fn test_lists_files_from_every_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("app");
+        let lib = dir.path().join("lib");
+        for root in [&app, &lib] {
+            std::fs::create_dir_all(root.join("src")).unwrap();
+            std::fs::write(root.join("src/main.t"), "").unwrap();
+        }
+        std::fs::write(lib.join("util.t"), "").unwrap();
+
+        let files = workspace_files(&[app.clone(), lib.clone()]);
+        assert_eq!(files.labels, vec!["app/src/main.t", "lib/src/main.t", "lib/util.t"]);
+        assert_eq!(files.path_for("lib/src/main.t"), Some(&lib.join("src/main.t")));
+        assert_eq!(files.path_for("src/main.t"), None);
+        assert_eq!(rank_files("libmain", &files.labels, MAX_RESULTS)[0].0, "lib/src/main.t");
+
+        // 只有一个根目录时不加根目录名
+        let files = workspace_files(std::slice::from_ref(&lib));
+        assert_eq!(files.labels, vec!["src/main.t", "util.t"]);
+        assert_eq!(files.path_for("util.t"), Some(&lib.join("util.t")));
+    }
+}
@@ -0,0 +1,133 @@
+//! 重命名结绳源文件后更新项目中对它的引用：结绳文件导入、包含语句中的文件名（不含扩展名），
+//! 以及各类文件字符串中的文件名，例如 `"源代码/工具类.t"`。规则保守，只改动明确指向该文件的文本
+
+use std::ops::Range;
+use std::path::Path;
+
+/// 结绳文件中引用其它源文件的语句开头
+const IMPORT_KEYWORDS: &[&str] = &["@导入", "导入", "#包含", "包含", "import", "#include"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenamedSource {
+    old_name: String,
+    new_name: String,
+    old_stem: String,
+    new_stem: String,
+}
+
+impl RenamedSource {
+    /// 只处理文件名变化的 `.t` 文件
+    pub fn new(from: &Path, to: &Path) -> Option<Self> {
+        let is_source = |path: &Path| path.extension().is_some_and(|ext| ext == "t");
+        if !is_source(from) || !is_source(to) {
+            return None;
+        }
+        let name = |path: &Path| path.file_name().map(|n| n.to_string_lossy().to_string());
+        let stem = |path: &Path| path.file_stem().map(|n| n.to_string_lossy().to_string());
+        let renamed = Self { old_name: name(from)?, new_name: name(to)?, old_stem: stem(from)?, new_stem: stem(to)? };
+        (renamed.old_name != renamed.new_name).then_some(renamed)
+    }
+
+    /// 在项目中预先筛选候选文件时搜索的文本
+    pub fn search_text(&self) -> &str {
+        &self.old_stem
+    }
+
+    /// `path` 的内容中需要修改的引用，范围为字节偏移，互不重叠，按位置排列
+    pub fn reference_edits(&self, path: &Path, content: &str) -> Vec<(Range<usize>, String)> {
+        let structured = path.extension().is_some_and(|ext| ext == "t");
+        let mut edits = Vec::new();
+        let mut line_start = 0;
+        for line in content.split_inclusive('\n') {
+            let mut line_edits: Vec<(Range<usize>, String)> = quoted_occurrences(line, &self.old_name)
+                .into_iter()
+                .map(|range| (range, self.new_name.clone()))
+                .collect();
+            if structured && is_import_line(line) {
+                for range in word_occurrences(line, &self.old_stem) {
+                    if !line_edits.iter().any(|(r, _)| r.start < range.end && range.start < r.end) {
+                        line_edits.push((range, self.new_stem.clone()));
+                    }
+                }
+            }
+            line_edits.sort_by_key(|(range, _)| range.start);
+            edits.extend(
+                line_edits.into_iter().map(|(range, text)| (range.start + line_start..range.end + line_start, text)),
+            );
+            line_start += line.len();
+        }
+        edits
+    }
+}
+
+fn is_import_line(line: &str) -> bool {
+    let line = line.trim_start();
+    IMPORT_KEYWORDS.iter().any(|keyword| line.starts_with(keyword))
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 字符串中作为路径最后一段出现的文件名：前面是引号或路径分隔符，后面是引号
+fn quoted_occurrences(line: &str, name: &str) -> Vec<Range<usize>> {
+    line.match_indices(name)
+        .filter(|(start, _)| {
+            let before = line[..*start].chars().next_back();
+            let after = line[start + name.len()..].chars().next();
+            matches!(before, Some('"' | '\'' | '/' | '\\')) && matches!(after, Some('"' | '\''))
+        })
+        .map(|(start, _)| start..start + name.len())
+        .collect()
+}
+
+/// 作为完整标识符出现的位置，前后都不是字母、数字或下划线
+fn word_occurrences(line: &str, word: &str) -> Vec<Range<usize>> {
+    line.match_indices(word)
+        .filter(|(start, _)| {
+            let before = line[..*start].chars().next_back();
+            let after = line[start + word.len()..].chars().next();
+            !before.is_some_and(is_identifier_char) && !after.is_some_and(is_identifier_char)
+        })
+        .map(|(start, _)| start..start + word.len())
+        .collect()
+}
+
+/// 按 [`RenamedSource::reference_edits`] 的结果得到修改后的内容
+pub fn apply_edits(content: &str, edits: &[(Range<usize>, String)]) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for (range, text) in edits {
+        result.push_str(&content[last..range.start]);
+        result.push_str(text);
+        last = range.end;
+    }
+    result.push_str(&content[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_imports_and_path_strings() {
+        assert_eq!(RenamedSource::new(Path::new("/p/a.txt"), Path::new("/p/b.txt")), None);
+        assert_eq!(RenamedSource::new(Path::new("/p/a.t"), Path::new("/q/a.t")), None);
+        let renamed = RenamedSource::new(Path::new("/p/源代码/工具.t"), Path::new("/p/源代码/常用工具.t")).unwrap();
+
+        let source = "@导入 工具\n包含 \"源代码/工具.t\"\n变量 工具类 : 文本 = \"工具\"\n// 工具.t 已弃用\n";
+        let edits = renamed.reference_edits(Path::new("/p/源代码/主窗口.t"), source);
+        assert_eq!(edits.len(), 2);
+        assert_eq!(
+            apply_edits(source, &edits),
+            "@导入 常用工具\n包含 \"源代码/常用工具.t\"\n变量 工具类 : 文本 = \"工具\"\n// 工具.t 已弃用\n"
+        );
+
+        // 其它文件只修改字符串中的文件名
+        let config = "{ \"main\": \"工具.t\", \"name\": \"导入 工具\" }";
+        let edits = renamed.reference_edits(Path::new("/p/project.json"), config);
+        assert_eq!(apply_edits(config, &edits), "{ \"main\": \"常用工具.t\", \"name\": \"导入 工具\" }");
+        assert!(renamed.reference_edits(Path::new("/p/x.t"), "导入 工具箱\n\"新工具.t\"\n").is_empty());
+    }
+}
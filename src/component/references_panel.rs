@@ -0,0 +1,388 @@
+use gpui::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::component::panel_list::ListSelection;
+use crate::component::search_panel::{MAX_FILE_SIZE, SKIPPED_DIRS};
+
+/// 单次查找最多返回的引用数
+const MAX_REFERENCES: usize = 2000;
+const ROW_HEIGHT: f32 = 22.0;
+
+/// 一处引用：文件、0 开始的行号和行内的字节范围
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReferenceHit {
+    pub path: PathBuf,
+    pub line: usize,
+    pub range: Range<usize>,
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 行内与 `word` 完全相同、前后不接标识符字符的位置
+fn word_ranges(line: &str, word: &str) -> Vec<Range<usize>> {
+    line.match_indices(word)
+        .filter(|(start, _)| {
+            let end = start + word.len();
+            !line[..*start].chars().next_back().is_some_and(is_identifier)
+                && !line[end..].chars().next().is_some_and(is_identifier)
+        })
+        .map(|(start, _)| start..start + word.len())
+        .collect()
+}
+
+/// 在各个根目录下按整词、区分大小写查找 `word`。tiec 库没有查找引用的接口，
+/// 结绳文件与其它文件一样按文本查找
+pub(crate) fn find_word_references(roots: &[PathBuf], word: &str, limit: usize) -> Vec<ReferenceHit> {
+    let mut results = Vec::new();
+    if word.is_empty() {
+        return results;
+    }
+    let mut stack = roots.to_vec();
+    stack.reverse();
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        // 先按字母顺序查找本目录的文件，子目录倒序入栈，之后也按字母顺序遍历
+        let (dirs, files): (Vec<PathBuf>, Vec<PathBuf>) = paths.into_iter().partition(|path| path.is_dir());
+        for dir in dirs.into_iter().rev() {
+            let name = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if !SKIPPED_DIRS.contains(&name.as_str()) {
+                stack.push(dir);
+            }
+        }
+        for path in files {
+            if std::fs::metadata(&path).map(|m| m.len() > MAX_FILE_SIZE).unwrap_or(true) {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            for (line, text) in content.lines().enumerate() {
+                for range in word_ranges(text, word) {
+                    results.push(ReferenceHit { path: path.clone(), line, range });
+                    if results.len() >= limit {
+                        return results;
+                    }
+                }
+            }
+        }
+    }
+    results
+}
+
+/// 结果行的预览文本，在行第一次显示时才读取所在文件，之后从缓存取
+#[derive(Default)]
+pub struct LinePreviews {
+    files: HashMap<PathBuf, Option<Vec<String>>>,
+}
+
+impl LinePreviews {
+    pub fn line(&mut self, path: &Path, line: usize) -> Option<&str> {
+        let lines = self.files.entry(path.to_path_buf()).or_insert_with(|| {
+            let content = std::fs::read_to_string(path).ok()?;
+            Some(content.lines().map(str::to_string).collect())
+        });
+        lines.as_ref()?.get(line).map(String::as_str)
+    }
+
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+}
+
+/// 列表中的一行：文件标题或其下的一处引用
+#[derive(Clone, Debug, PartialEq)]
+enum Row {
+    File { path: PathBuf, count: usize },
+    Hit(usize),
+}
+
+/// 按文件分组：每个文件一行标题，后面跟着它的引用
+fn group_rows(hits: &[ReferenceHit]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for (index, hit) in hits.iter().enumerate() {
+        if index == 0 || hits[index - 1].path != hit.path {
+            let count = hits[index..].iter().take_while(|h| h.path == hit.path).count();
+            rows.push(Row::File { path: hit.path.clone(), count });
+        }
+        rows.push(Row::Hit(index));
+    }
+    rows
+}
+
+pub enum ReferencesPanelEvent {
+    /// 打开引用所在的文件（列为行内字节偏移）
+    Open { path: PathBuf, line: usize, column: usize },
+    /// 按 Escape 或点击关闭按钮
+    Dismiss,
+}
+
+impl EventEmitter<ReferencesPanelEvent> for ReferencesPanel {}
+
+/// 查找引用的结果面板：底部停靠，按文件分组列出引用，行内高亮匹配的文本
+pub struct ReferencesPanel {
+    focus_handle: FocusHandle,
+    word: String,
+    root: Option<PathBuf>,
+    hits: Vec<ReferenceHit>,
+    rows: Vec<Row>,
+    /// 在引用中的选中项，文件标题不参与
+    selection: ListSelection,
+    list_state: ListState,
+    previews: Rc<RefCell<LinePreviews>>,
+    searching: bool,
+    generation: u64,
+}
+
+impl ReferencesPanel {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self {
+            focus_handle: cx.focus_handle(),
+            word: String::new(),
+            root: None,
+            hits: Vec::new(),
+            rows: Vec::new(),
+            selection: ListSelection::default(),
+            list_state: ListState::new(0, ListAlignment::Top, px(ROW_HEIGHT)),
+            previews: Rc::new(RefCell::new(LinePreviews::default())),
+            searching: false,
+            generation: 0,
+        }
+    }
+
+    pub fn focus(&self, window: &mut Window) {
+        self.focus_handle.focus(window);
+    }
+
+    /// 在 `roots` 下查找 `word` 的引用；文件标签显示为相对第一个根目录的路径
+    pub fn find(&mut self, word: String, roots: Vec<PathBuf>, cx: &mut Context<Self>) {
+        self.generation += 1;
+        let generation = self.generation;
+        self.word = word.clone();
+        self.root = roots.first().cloned();
+        self.hits.clear();
+        self.rows.clear();
+        self.selection.reset();
+        self.list_state.reset(0);
+        self.previews.borrow_mut().clear();
+        self.searching = true;
+        let executor = cx.background_executor().clone();
+        cx.spawn(move |view: WeakEntity<ReferencesPanel>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let hits = executor.spawn(async move { find_word_references(&roots, &word, MAX_REFERENCES) }).await;
+                view.update(&mut cx, |this, cx| {
+                    if this.generation != generation {
+                        return;
+                    }
+                    this.searching = false;
+                    this.rows = group_rows(&hits);
+                    this.hits = hits;
+                    this.list_state.reset(this.rows.len());
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+        cx.notify();
+    }
+
+    fn open(&mut self, index: usize, cx: &mut Context<Self>) {
+        if let Some(hit) = self.hits.get(index) {
+            self.selection.index = index;
+            cx.emit(ReferencesPanelEvent::Open { path: hit.path.clone(), line: hit.line, column: hit.range.start });
+            cx.notify();
+        }
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let key = event.keystroke.key.as_str();
+        match key {
+            "enter" => self.open(self.selection.index, cx),
+            "escape" => cx.emit(ReferencesPanelEvent::Dismiss),
+            "up" | "down" | "pageup" | "pagedown" if self.selection.handle_key(key, self.hits.len()) => {
+                let selected = self.selection.index;
+                if let Some(row) = self.rows.iter().position(|row| *row == Row::Hit(selected)) {
+                    self.list_state.scroll_to_reveal_item(row);
+                }
+                cx.notify();
+            }
+            _ => {}
+        }
+    }
+
+    fn file_label(&self, path: &Path) -> String {
+        self.root
+            .as_ref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+impl Render for ReferencesPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        let theme_text = theme.text;
+        let theme_muted = theme.muted_text;
+        let panel = cx.entity();
+
+        let status = if self.searching {
+            format!("正在查找“{}”的引用…", self.word)
+        } else {
+            let files = self.rows.len() - self.hits.len();
+            format!("“{}”的引用：{} 个文件中 {} 处", self.word, files, self.hits.len())
+        };
+        let header = div()
+            .h(px(28.0))
+            .px(px(8.0))
+            .flex()
+            .items_center()
+            .justify_between()
+            .text_size(px(12.0))
+            .border_b_1()
+            .border_color(theme.border)
+            .child(div().text_color(theme_text).child(status))
+            .child(
+                div()
+                    .id("references-close")
+                    .px(px(6.0))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .text_color(theme_muted)
+                    .hover(|s| s.bg(theme.list_hover).text_color(theme.text))
+                    .child("×")
+                    .on_click({
+                        let panel = panel.clone();
+                        move |_, _window, cx| {
+                            panel.update(cx, |_, cx| cx.emit(ReferencesPanelEvent::Dismiss));
+                        }
+                    }),
+            );
+
+        let rows = self.rows.clone();
+        let hits = self.hits.clone();
+        let labels: HashMap<PathBuf, String> = rows
+            .iter()
+            .filter_map(|row| match row {
+                Row::File { path, .. } => Some((path.clone(), self.file_label(path))),
+                Row::Hit(_) => None,
+            })
+            .collect();
+        let previews = self.previews.clone();
+        let selected = self.selection.index;
+        let highlight = HighlightStyle {
+            color: Some(theme.text),
+            background_color: Some(theme.match_bg),
+            ..Default::default()
+        };
+        let body = list(self.list_state.clone(), move |index, _window, _cx| {
+            let row = div().w_full().h(px(ROW_HEIGHT)).flex().items_center().text_size(px(12.0));
+            match rows.get(index) {
+                Some(Row::File { path, count }) => row
+                    .px(px(8.0))
+                    .gap(px(6.0))
+                    .child(div().text_color(theme_text).child(labels.get(path).cloned().unwrap_or_default()))
+                    .child(div().text_color(theme_muted).child(count.to_string()))
+                    .into_any_element(),
+                Some(Row::Hit(hit_index)) => {
+                    let hit = &hits[*hit_index];
+                    let line = previews.borrow_mut().line(&hit.path, hit.line).unwrap_or_default().to_string();
+                    // 去掉行首缩进，高亮范围随之前移
+                    let indent = line.len() - line.trim_start().len();
+                    let text = line[indent..].to_string();
+                    let range = hit.range.start.saturating_sub(indent)..hit.range.end.saturating_sub(indent);
+                    let styled = if range.end <= text.len() && text.is_char_boundary(range.start) && text.is_char_boundary(range.end) {
+                        StyledText::new(text).with_highlights(vec![(range, highlight)])
+                    } else {
+                        StyledText::new(text)
+                    };
+                    let panel = panel.clone();
+                    let hit_index = *hit_index;
+                    row.id(("reference", hit_index))
+                        .pl(px(20.0))
+                        .pr(px(8.0))
+                        .cursor_pointer()
+                        .bg(if hit_index == selected { theme.list_selection } else { transparent_black() })
+                        .hover(|s| s.bg(theme.list_hover))
+                        .child(div().flex_shrink_0().mr(px(6.0)).text_color(theme_muted).child(format!("{}", hit.line + 1)))
+                        .child(div().text_color(theme_text).whitespace_nowrap().overflow_hidden().child(styled))
+                        .on_click(move |_, _window, cx| {
+                            panel.update(cx, |this, cx| this.open(hit_index, cx));
+                        })
+                        .into_any_element()
+                }
+                None => div().into_any_element(),
+            }
+        })
+        .flex_1()
+        .w_full();
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::on_key_down))
+            .child(header)
+            .child(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_word_references, group_rows, LinePreviews, Row};
+    use std::ops::Range;
+
+    #[test]
+    fn test_finds_whole_words_and_reads_previews_lazily() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::create_dir(root.join("target")).unwrap();
+        std::fs::write(root.join("src/a.t"), "变量 计数 = 0\n计数 = 计数 + 1\n计数器 = 2\n").unwrap();
+        std::fs::write(root.join("b.txt"), "count 计数\n").unwrap();
+        std::fs::write(root.join("target/c.t"), "计数\n").unwrap();
+
+        let roots = [root.clone()];
+        let hits = find_word_references(&roots, "计数", 100);
+        let found: Vec<(String, usize, Range<usize>)> = hits
+            .iter()
+            .map(|h| (h.path.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"), h.line, h.range.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("b.txt".to_string(), 0, 6..12),
+                ("src/a.t".to_string(), 0, 7..13),
+                ("src/a.t".to_string(), 1, 0..6),
+                ("src/a.t".to_string(), 1, 9..15),
+            ]
+        );
+        assert_eq!(find_word_references(&roots, "计数", 2).len(), 2);
+
+        let rows = group_rows(&hits);
+        assert_eq!(rows.len(), 6);
+        assert_eq!(rows[2], Row::File { path: root.join("src/a.t"), count: 3 });
+
+        // 预览在第一次取用时读取，之后文件变化也沿用缓存
+        let mut previews = LinePreviews::default();
+        assert_eq!(previews.line(&root.join("src/a.t"), 1), Some("计数 = 计数 + 1"));
+        std::fs::write(root.join("src/a.t"), "").unwrap();
+        assert_eq!(previews.line(&root.join("src/a.t"), 1), Some("计数 = 计数 + 1"));
+        assert_eq!(previews.line(&root.join("missing.t"), 0), None);
+        previews.clear();
+        assert_eq!(previews.line(&root.join("src/a.t"), 1), None);
+    }
+}
@@ -0,0 +1,183 @@
+//! 提交前审阅：列出所有变更文件及其增删行数，逐个标记为已审阅。
+//! 标记保留到下一次提交；文件在审阅后又被修改时重新标记为未审阅
+
+use super::side_by_side::{rows, stats};
+use git2::{Repository, StatusOptions};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// 一个变更文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedFile {
+    pub path: PathBuf,
+    /// 相对仓库根目录的路径
+    pub relative: String,
+    pub added: usize,
+    pub removed: usize,
+    /// 两侧内容的摘要，内容变化时随之变化
+    fingerprint: u64,
+}
+
+/// HEAD 中文件的内容；新文件或还没有提交时为空
+pub fn head_text(repo: &Repository, relative: &str) -> String {
+    let blob = repo
+        .head()
+        .and_then(|head| head.peel_to_tree())
+        .and_then(|tree| tree.get_path(Path::new(relative)))
+        .and_then(|entry| repo.find_blob(entry.id()));
+    blob.map(|blob| String::from_utf8_lossy(blob.content()).to_string()).unwrap_or_default()
+}
+
+/// HEAD 提交的 id，用于判断是否已经提交
+pub fn head_id(repo: &Repository) -> Option<String> {
+    repo.head().ok()?.target().map(|oid| oid.to_string())
+}
+
+/// 工作区和暂存区中所有变更的文件，按路径排序
+pub fn changed_files(repo: &Repository) -> Result<Vec<ChangedFile>, git2::Error> {
+    let workdir = repo.workdir().ok_or_else(|| git2::Error::from_str("仓库没有工作区"))?;
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+    let mut files = Vec::new();
+    for entry in repo.statuses(Some(&mut opts))?.iter() {
+        let Some(relative) = entry.path() else {
+            continue;
+        };
+        let path = workdir.join(relative);
+        let old = head_text(repo, relative);
+        let new = std::fs::read(&path).map(|bytes| String::from_utf8_lossy(&bytes).to_string()).unwrap_or_default();
+        let (added, removed) = stats(&rows(&old, &new));
+        let mut hasher = DefaultHasher::new();
+        (&old, &new, path.exists()).hash(&mut hasher);
+        files.push(ChangedFile { path, relative: relative.to_string(), added, removed, fingerprint: hasher.finish() });
+    }
+    files.sort_by(|a, b| a.relative.cmp(&b.relative));
+    Ok(files)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewItem {
+    pub file: ChangedFile,
+    pub reviewed: bool,
+}
+
+/// 一次审阅的清单和当前打开的文件
+#[derive(Debug, Clone, Default)]
+pub struct ReviewSession {
+    head: Option<String>,
+    items: Vec<ReviewItem>,
+    current: usize,
+}
+
+impl ReviewSession {
+    /// 用最新的变更更新清单。HEAD 变化（已提交）时清空所有标记；
+    /// 内容变化的文件重新标记为未审阅。当前文件仍在清单中时保持选中
+    pub fn update(&mut self, head: Option<String>, files: Vec<ChangedFile>) {
+        let committed = head != self.head;
+        let current = self.current_item().map(|item| item.file.path.clone());
+        let previous = std::mem::take(&mut self.items);
+        self.items = files
+            .into_iter()
+            .map(|file| {
+                let reviewed = !committed
+                    && previous.iter().any(|item| item.reviewed && item.file.path == file.path && item.file.fingerprint == file.fingerprint);
+                ReviewItem { file, reviewed }
+            })
+            .collect();
+        self.head = head;
+        self.current = current
+            .and_then(|path| self.items.iter().position(|item| item.file.path == path))
+            .unwrap_or(0)
+            .min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn items(&self) -> &[ReviewItem] {
+        &self.items
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn current_item(&self) -> Option<&ReviewItem> {
+        self.items.get(self.current)
+    }
+
+    pub fn select(&mut self, index: usize) -> Option<&ReviewItem> {
+        if index < self.items.len() {
+            self.current = index;
+        }
+        self.items.get(index)
+    }
+
+    /// 选中下一个文件；已经是最后一个时返回 None
+    pub fn next(&mut self) -> Option<&ReviewItem> {
+        self.select(self.current + 1)
+    }
+
+    pub fn prev(&mut self) -> Option<&ReviewItem> {
+        self.select(self.current.checked_sub(1)?)
+    }
+
+    pub fn toggle_reviewed(&mut self, index: usize) {
+        if let Some(item) = self.items.get_mut(index) {
+            item.reviewed = !item.reviewed;
+        }
+    }
+
+    /// 已审阅数和总数
+    pub fn progress(&self) -> (usize, usize) {
+        (self.items.iter().filter(|item| item.reviewed).count(), self.items.len())
+    }
+
+    pub fn progress_label(&self) -> String {
+        let (reviewed, total) = self.progress();
+        format!("{}/{} 已审阅", reviewed, total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_marks_reset_on_change_and_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        std::fs::write(root.join("a.t"), "一\n二\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.t")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("t", "t@t").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+
+        std::fs::write(root.join("a.t"), "一\n三\n四\n").unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/b.t"), "新\n").unwrap();
+        let files = changed_files(&repo).unwrap();
+        assert_eq!(files.iter().map(|f| (f.relative.as_str(), f.added, f.removed)).collect::<Vec<_>>(), vec![
+            ("a.t", 2, 1),
+            ("src/b.t", 1, 0),
+        ]);
+
+        let mut session = ReviewSession::default();
+        session.update(head_id(&repo), files);
+        session.toggle_reviewed(0);
+        session.toggle_reviewed(1);
+        assert_eq!(session.next().unwrap().file.relative, "src/b.t");
+        assert!(session.next().is_none());
+        assert_eq!(session.progress_label(), "2/2 已审阅");
+
+        // 审阅后又修改的文件重新变为未审阅，当前文件保持选中
+        std::fs::write(root.join("a.t"), "一\n").unwrap();
+        session.update(head_id(&repo), changed_files(&repo).unwrap());
+        assert_eq!(session.progress(), (1, 2));
+        assert_eq!(session.current(), 1);
+
+        // 提交后清空标记
+        session.update(Some("other".to_string()), changed_files(&repo).unwrap());
+        assert_eq!(session.progress(), (0, 2));
+    }
+}
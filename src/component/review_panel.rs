@@ -0,0 +1,219 @@
+use gpui::*;
+use git2::Repository;
+use std::path::{Path, PathBuf};
+use super::review::{changed_files, head_id, head_text, ReviewSession};
+
+pub enum ReviewPanelEvent {
+    /// 打开文件与 HEAD 的对照
+    OpenDiff { path: PathBuf, relative: String, head: String, current: String },
+    /// 按 Escape 或点击关闭
+    Close,
+}
+
+impl EventEmitter<ReviewPanelEvent> for ReviewPanel {}
+
+/// 审阅清单：n/p 切换文件并打开对照，空格标记当前文件已审阅
+pub struct ReviewPanel {
+    pub focus_handle: FocusHandle,
+    repo_root: Option<PathBuf>,
+    session: ReviewSession,
+    list_state: ListState,
+    error: Option<String>,
+}
+
+impl ReviewPanel {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self {
+            focus_handle: cx.focus_handle(),
+            repo_root: None,
+            session: ReviewSession::default(),
+            list_state: ListState::new(0, ListAlignment::Top, px(24.0)),
+            error: None,
+        }
+    }
+
+    pub fn focus(&self, window: &mut Window) {
+        self.focus_handle.focus(window);
+    }
+
+    pub fn repo_root(&self) -> Option<&PathBuf> {
+        self.repo_root.as_ref()
+    }
+
+    /// 重新读取变更；换了仓库时从头开始审阅
+    pub fn refresh(&mut self, repo_root: &Path, cx: &mut Context<Self>) {
+        if self.repo_root.as_deref() != Some(repo_root) {
+            self.session = ReviewSession::default();
+            self.repo_root = Some(repo_root.to_path_buf());
+        }
+        match Repository::open(repo_root) {
+            Ok(repo) => match changed_files(&repo) {
+                Ok(files) => {
+                    self.session.update(head_id(&repo), files);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e.message().to_string()),
+            },
+            Err(_) => self.error = Some("当前文件夹不是 git 仓库".to_string()),
+        }
+        self.list_state.reset(self.session.items().len());
+        cx.notify();
+    }
+
+    /// 打开当前文件的对照
+    pub fn open_current(&mut self, cx: &mut Context<Self>) {
+        let (Some(root), Some(item)) = (&self.repo_root, self.session.current_item()) else {
+            return;
+        };
+        let Ok(repo) = Repository::open(root) else {
+            return;
+        };
+        let path = item.file.path.clone();
+        let relative = item.file.relative.clone();
+        let head = head_text(&repo, &relative);
+        let current = std::fs::read(&path).map(|bytes| String::from_utf8_lossy(&bytes).to_string()).unwrap_or_default();
+        cx.emit(ReviewPanelEvent::OpenDiff { path, relative, head, current });
+    }
+
+    fn select(&mut self, index: usize, cx: &mut Context<Self>) {
+        if self.session.select(index).is_some() {
+            self.show_current(cx);
+        }
+    }
+
+    fn show_current(&mut self, cx: &mut Context<Self>) {
+        self.list_state.scroll_to_reveal_item(self.session.current());
+        self.open_current(cx);
+        cx.notify();
+    }
+
+    fn toggle_reviewed(&mut self, index: usize, cx: &mut Context<Self>) {
+        self.session.toggle_reviewed(index);
+        cx.notify();
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        match event.keystroke.key.as_str() {
+            "n" | "down" if self.session.next().is_some() => self.show_current(cx),
+            "p" | "up" if self.session.prev().is_some() => self.show_current(cx),
+            "space" => self.toggle_reviewed(self.session.current(), cx),
+            "enter" => self.open_current(cx),
+            "escape" => cx.emit(ReviewPanelEvent::Close),
+            _ => {}
+        }
+    }
+}
+
+impl Render for ReviewPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        let theme_text = theme.text;
+        let theme_muted = theme.muted_text;
+        let panel = cx.entity();
+        let items = self.session.items().to_vec();
+        let current = self.session.current();
+        let status = match &self.error {
+            Some(error) => error.clone(),
+            None if items.is_empty() => "没有变更".to_string(),
+            None => self.session.progress_label(),
+        };
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
+                this.on_key_down(event, window, cx);
+            }))
+            .child(
+                div()
+                    .h(px(30.0))
+                    .px(px(8.0))
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .text_size(px(13.0))
+                    .text_color(theme_text)
+                    .child("审阅变更")
+                    .child(
+                        div()
+                            .cursor_pointer()
+                            .text_color(theme_muted)
+                            .hover(|s| s.text_color(theme.text))
+                            .child("×")
+                            .on_mouse_down(MouseButton::Left, {
+                                let panel = panel.clone();
+                                move |_, _window, cx| {
+                                    cx.stop_propagation();
+                                    panel.update(cx, |_, cx| cx.emit(ReviewPanelEvent::Close));
+                                }
+                            }),
+                    ),
+            )
+            .child(
+                div()
+                    .px(px(8.0))
+                    .pb(px(4.0))
+                    .text_size(px(11.0))
+                    .text_color(theme_muted)
+                    .child(format!("{}　n/p 切换，空格标记", status)),
+            )
+            .child(
+                list(self.list_state.clone(), move |index, _window, _cx| {
+                    let Some(item) = items.get(index) else {
+                        return div().into_any_element();
+                    };
+                    let panel_for_check = panel.clone();
+                    let panel_for_click = panel.clone();
+                    div()
+                        .w_full()
+                        .h(px(24.0))
+                        .px(px(8.0))
+                        .flex()
+                        .items_center()
+                        .gap(px(6.0))
+                        .cursor_pointer()
+                        .text_size(px(12.0))
+                        .bg(if index == current { theme.accent.opacity(0.25) } else { transparent_black() })
+                        .hover(|s| s.bg(theme.list_hover))
+                        .child(
+                            div()
+                                .w(px(14.0))
+                                .h(px(14.0))
+                                .flex_none()
+                                .flex()
+                                .items_center()
+                                .justify_center()
+                                .rounded_sm()
+                                .border_1()
+                                .border_color(theme_muted)
+                                .text_size(px(10.0))
+                                .text_color(theme.success)
+                                .child(if item.reviewed { "✓" } else { "" })
+                                .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
+                                    cx.stop_propagation();
+                                    panel_for_check.update(cx, |this, cx| this.toggle_reviewed(index, cx));
+                                }),
+                        )
+                        .child(
+                            div()
+                                .flex_1()
+                                .min_w(px(0.0))
+                                .overflow_hidden()
+                                .whitespace_nowrap()
+                                .text_color(if item.reviewed { theme_muted } else { theme_text })
+                                .child(item.file.relative.clone()),
+                        )
+                        .child(div().flex_none().text_color(theme.success).child(format!("+{}", item.file.added)))
+                        .child(div().flex_none().text_color(theme.error).child(format!("-{}", item.file.removed)))
+                        .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
+                            panel_for_click.update(cx, |this, cx| this.select(index, cx));
+                        })
+                        .into_any_element()
+                })
+                .flex_1()
+                .w_full(),
+            )
+    }
+}
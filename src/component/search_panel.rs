@@ -0,0 +1,701 @@
+use gpui::*;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::component::panel_list::{ListSelection, PanelEscape};
+use crate::component::mod_rs_helpers::{byte_index_to_utf16, byte_range_to_utf16_range, utf16_index_to_byte};
+
+/// 单次搜索最多返回的结果数，避免大项目里列表失控
+const MAX_RESULTS: usize = 2000;
+/// 超过该大小的文件不参与搜索
+pub(crate) const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+pub(crate) const SKIPPED_DIRS: &[&str] = &[".git", "target", "node_modules"];
+/// 用方向键停留在某个结果上超过该时长才预览
+pub(crate) const PREVIEW_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// 搜索范围：包含或排除某个文件夹，在输入框下方显示为可移除的标签
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchScope {
+    pub path: PathBuf,
+    pub exclude: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchMatch {
+    pub path: PathBuf,
+    /// 0 开始的行号
+    pub line: usize,
+    /// 行内字节偏移
+    pub column: usize,
+    pub line_text: String,
+}
+
+pub enum SearchPanelEvent {
+    OpenMatch { path: PathBuf, line: usize, column: usize },
+    /// 只读预览选中的结果，不打开标签
+    PreviewMatch { path: PathBuf, line: usize, column: usize },
+    /// 放弃预览，回到之前的标签
+    CancelPreview,
+    /// 查询为空时按 Escape，焦点回到编辑器
+    LeavePanel,
+}
+
+impl EventEmitter<SearchPanelEvent> for SearchPanel {}
+
+pub struct SearchPanel {
+    pub focus_handle: FocusHandle,
+    root_path: Option<PathBuf>,
+    /// 工作区中后来加入的文件夹，与根目录一起搜索
+    extra_roots: Vec<PathBuf>,
+    query: String,
+    query_cursor: usize,
+    query_marked_range: Option<Range<usize>>,
+    input_bounds: Option<Bounds<Pixels>>,
+    scopes: Vec<SearchScope>,
+    results: Vec<SearchMatch>,
+    selection: ListSelection,
+    list_state: ListState,
+    searching: bool,
+    search_generation: u64,
+    /// 每次移动选中项加一，用于丢弃过期的延迟预览
+    preview_generation: u64,
+    /// 已发出预览且尚未打开或取消
+    previewing: bool,
+}
+
+impl SearchPanel {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        Self {
+            focus_handle: cx.focus_handle(),
+            root_path: None,
+            extra_roots: Vec::new(),
+            query: String::new(),
+            query_cursor: 0,
+            query_marked_range: None,
+            input_bounds: None,
+            scopes: Vec::new(),
+            results: Vec::new(),
+            selection: ListSelection::default(),
+            list_state: ListState::new(0, ListAlignment::Top, px(22.0)),
+            searching: false,
+            search_generation: 0,
+            preview_generation: 0,
+            previewing: false,
+        }
+    }
+
+    pub fn set_root_path(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        self.root_path = Some(path);
+        self.extra_roots.clear();
+        self.scopes.clear();
+        self.run_search(cx);
+    }
+
+    pub fn set_extra_roots(&mut self, roots: Vec<PathBuf>, cx: &mut Context<Self>) {
+        if self.extra_roots == roots {
+            return;
+        }
+        self.scopes.retain(|scope| {
+            !self.extra_roots.iter().any(|root| scope.path.starts_with(root) && !roots.contains(root))
+        });
+        self.extra_roots = roots;
+        self.run_search(cx);
+    }
+
+    pub fn focus(&self, window: &mut Window) {
+        self.focus_handle.focus(window);
+    }
+
+    /// 添加包含/排除范围。同一文件夹只保留一个标签，再次添加时以新的类型为准。
+    pub fn add_scope(&mut self, path: PathBuf, exclude: bool, cx: &mut Context<Self>) {
+        if self.root_path.is_none() {
+            self.root_path = path.parent().map(Path::to_path_buf);
+        }
+        if let Some(existing) = self.scopes.iter_mut().find(|s| s.path == path) {
+            existing.exclude = exclude;
+        } else {
+            self.scopes.push(SearchScope { path, exclude });
+        }
+        self.run_search(cx);
+    }
+
+    pub fn remove_scope(&mut self, index: usize, cx: &mut Context<Self>) {
+        if index < self.scopes.len() {
+            self.scopes.remove(index);
+            self.run_search(cx);
+        }
+    }
+
+    fn run_search(&mut self, cx: &mut Context<Self>) {
+        self.search_generation += 1;
+        let generation = self.search_generation;
+        self.selection.reset();
+        self.preview_generation += 1;
+
+        let roots: Vec<PathBuf> = match self.root_path.clone() {
+            Some(root) if !self.query.is_empty() => std::iter::once(root).chain(self.extra_roots.clone()).collect(),
+            _ => {
+                self.results.clear();
+                self.list_state.reset(0);
+                self.searching = false;
+                cx.notify();
+                return;
+            }
+        };
+
+        self.searching = true;
+        let query = self.query.clone();
+        let scopes = self.scopes.clone();
+        let executor = cx.background_executor().clone();
+        cx.spawn(move |view: WeakEntity<SearchPanel>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let results = executor
+                    .spawn(async move { search_files(&roots, &query, &scopes, MAX_RESULTS) })
+                    .await;
+                view.update(&mut cx, |this, cx| {
+                    // 输入过程中会连续触发搜索，只接受最后一次的结果
+                    if this.search_generation != generation {
+                        return;
+                    }
+                    this.searching = false;
+                    this.list_state.reset(results.len());
+                    this.results = results;
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+        cx.notify();
+    }
+
+    fn open_selected(&mut self, cx: &mut Context<Self>) {
+        self.preview_generation += 1;
+        self.previewing = false;
+        if let Some(m) = self.results.get(self.selection.index) {
+            cx.emit(SearchPanelEvent::OpenMatch {
+                path: m.path.clone(),
+                line: m.line,
+                column: m.column,
+            });
+        }
+    }
+
+    /// 选中项停留 [`PREVIEW_DELAY`] 后发出预览；期间选中项变化则作废
+    fn schedule_preview(&mut self, cx: &mut Context<Self>) {
+        self.preview_generation += 1;
+        let generation = self.preview_generation;
+        cx.spawn(move |view: WeakEntity<SearchPanel>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(PREVIEW_DELAY).await;
+                view.update(&mut cx, |this, cx| {
+                    if this.preview_generation != generation {
+                        return;
+                    }
+                    if let Some(m) = this.results.get(this.selection.index) {
+                        this.previewing = true;
+                        cx.emit(SearchPanelEvent::PreviewMatch {
+                            path: m.path.clone(),
+                            line: m.line,
+                            column: m.column,
+                        });
+                    }
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    fn scope_label(&self, scope: &SearchScope) -> String {
+        let relative = self
+            .root_path
+            .as_ref()
+            .and_then(|root| scope.path.strip_prefix(root).ok())
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_else(|| scope.path.to_string_lossy().to_string());
+        if relative.is_empty() {
+            "**".to_string()
+        } else {
+            format!("{}/**", relative)
+        }
+    }
+
+    fn on_key_down(&mut self, event: &KeyDownEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let key = event.keystroke.key.as_str();
+        match key {
+            "enter" => self.open_selected(cx),
+            "up" | "down" | "pageup" | "pagedown" if self.selection.handle_key(key, self.results.len()) => {
+                self.list_state.scroll_to_reveal_item(self.selection.index);
+                self.schedule_preview(cx);
+                cx.notify();
+            }
+            "escape" if self.previewing => {
+                self.preview_generation += 1;
+                self.previewing = false;
+                cx.emit(SearchPanelEvent::CancelPreview);
+            }
+            "escape" => match PanelEscape::for_filter(&self.query) {
+                PanelEscape::ClearFilter => {
+                    self.preview_generation += 1;
+                    self.query.clear();
+                    self.query_cursor = 0;
+                    self.query_marked_range = None;
+                    self.run_search(cx);
+                }
+                PanelEscape::LeavePanel => cx.emit(SearchPanelEvent::LeavePanel),
+            },
+            "backspace" if self.query_cursor > 0 => {
+                let mut prev = self.query_cursor - 1;
+                while prev > 0 && !self.query.is_char_boundary(prev) {
+                    prev -= 1;
+                }
+                self.query.replace_range(prev..self.query_cursor, "");
+                self.query_cursor = prev;
+                self.query_marked_range = None;
+                self.run_search(cx);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn in_scope(path: &Path, scopes: &[SearchScope]) -> bool {
+    if scopes.iter().any(|s| s.exclude && path.starts_with(&s.path)) {
+        return false;
+    }
+    let mut includes = scopes.iter().filter(|s| !s.exclude).peekable();
+    includes.peek().is_none() || includes.any(|s| path.starts_with(&s.path))
+}
+
+/// 在各个根目录下按范围搜索包含 `query` 的行（忽略大小写）
+pub(crate) fn search_files(roots: &[PathBuf], query: &str, scopes: &[SearchScope], limit: usize) -> Vec<SearchMatch> {
+    let mut results = Vec::new();
+    let needle = query.to_lowercase();
+    let includes: Vec<&PathBuf> = scopes.iter().filter(|s| !s.exclude).map(|s| &s.path).collect();
+    let starts: Vec<PathBuf> = if includes.is_empty() {
+        roots.to_vec()
+    } else {
+        // 嵌套的包含范围只从最外层开始遍历，避免结果重复
+        includes
+            .iter()
+            .filter(|p| !includes.iter().any(|other| other != *p && p.starts_with(other)))
+            .map(|p| p.to_path_buf())
+            .collect()
+    };
+
+    let mut stack = starts;
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        // 倒序入栈，保证按字母顺序遍历
+        for path in paths.into_iter().rev() {
+            if !in_scope(&path, scopes) {
+                continue;
+            }
+            if path.is_dir() {
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                if !SKIPPED_DIRS.contains(&name.as_str()) {
+                    stack.push(path);
+                }
+                continue;
+            }
+            if std::fs::metadata(&path).map(|m| m.len() > MAX_FILE_SIZE).unwrap_or(true) {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            for (line_index, line) in content.lines().enumerate() {
+                if let Some(column) = line.to_lowercase().find(&needle) {
+                    // 小写化可能改变字节长度，列号只在长度一致时可信
+                    let column = if line.to_lowercase().len() == line.len() { column } else { 0 };
+                    results.push(SearchMatch {
+                        path: path.clone(),
+                        line: line_index,
+                        column,
+                        line_text: line.trim().to_string(),
+                    });
+                    if results.len() >= limit {
+                        return results;
+                    }
+                }
+            }
+        }
+    }
+    results
+}
+
+impl EntityInputHandler for SearchPanel {
+    fn marked_text_range(&self, _window: &mut Window, _cx: &mut Context<Self>) -> Option<Range<usize>> {
+        self.query_marked_range
+            .as_ref()
+            .map(|range| byte_range_to_utf16_range(&self.query, range.clone()))
+    }
+
+    fn unmark_text(&mut self, _window: &mut Window, _cx: &mut Context<Self>) {
+        self.query_marked_range = None;
+    }
+
+    fn text_for_range(
+        &mut self,
+        range_utf16: Range<usize>,
+        adjusted_range: &mut Option<Range<usize>>,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) -> Option<String> {
+        let start = utf16_index_to_byte(&self.query, range_utf16.start);
+        let end = utf16_index_to_byte(&self.query, range_utf16.end).max(start);
+        adjusted_range.replace(byte_range_to_utf16_range(&self.query, start..end));
+        Some(self.query[start..end].to_string())
+    }
+
+    fn selected_text_range(
+        &mut self,
+        _ignore_disabled_input: bool,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) -> Option<UTF16Selection> {
+        let cursor = byte_index_to_utf16(&self.query, self.query_cursor);
+        Some(UTF16Selection {
+            range: cursor..cursor,
+            reversed: false,
+        })
+    }
+
+    fn replace_text_in_range(
+        &mut self,
+        range_utf16: Option<Range<usize>>,
+        new_text: &str,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let range = range_utf16
+            .map(|r| utf16_index_to_byte(&self.query, r.start)..utf16_index_to_byte(&self.query, r.end))
+            .or(self.query_marked_range.clone())
+            .unwrap_or(self.query_cursor..self.query_cursor);
+        let start = range.start.min(self.query.len());
+        let end = range.end.min(self.query.len()).max(start);
+        self.query.replace_range(start..end, new_text);
+        self.query_cursor = start + new_text.len();
+        self.query_marked_range = None;
+        self.run_search(cx);
+    }
+
+    fn replace_and_mark_text_in_range(
+        &mut self,
+        range_utf16: Option<Range<usize>>,
+        new_text: &str,
+        _new_selected_range_utf16: Option<Range<usize>>,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let range = range_utf16
+            .map(|r| utf16_index_to_byte(&self.query, r.start)..utf16_index_to_byte(&self.query, r.end))
+            .or(self.query_marked_range.clone())
+            .unwrap_or(self.query_cursor..self.query_cursor);
+        let start = range.start.min(self.query.len());
+        let end = range.end.min(self.query.len()).max(start);
+        self.query.replace_range(start..end, new_text);
+        self.query_cursor = start + new_text.len();
+        self.query_marked_range = if new_text.is_empty() {
+            None
+        } else {
+            Some(start..self.query_cursor)
+        };
+        cx.notify();
+    }
+
+    fn bounds_for_range(
+        &mut self,
+        _range_utf16: Range<usize>,
+        bounds: Bounds<Pixels>,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) -> Option<Bounds<Pixels>> {
+        Some(self.input_bounds.unwrap_or(bounds))
+    }
+
+    fn character_index_for_point(
+        &mut self,
+        _point: Point<Pixels>,
+        _window: &mut Window,
+        _cx: &mut Context<Self>,
+    ) -> Option<usize> {
+        None
+    }
+}
+
+impl Render for SearchPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        let theme_text = theme.text;
+        let theme_muted = theme.muted_text;
+        let panel = cx.entity();
+        let focus = self.focus_handle.clone();
+        let results = self.results.clone();
+        let selected_index = self.selection.index;
+        let root_path = self.root_path.clone();
+        let extra_roots = self.extra_roots.clone();
+
+        let mut chips = div().flex().flex_wrap().gap(px(4.0)).px(px(8.0)).pb(px(6.0));
+        for (index, scope) in self.scopes.iter().enumerate() {
+            let panel_for_remove = panel.clone();
+            let (prefix, bg) = if scope.exclude {
+                ("排除", theme.error.opacity(0.25))
+            } else {
+                ("包含", theme.accent.opacity(0.25))
+            };
+            chips = chips.child(
+                div()
+                    .flex()
+                    .items_center()
+                    .px(px(6.0))
+                    .py(px(2.0))
+                    .rounded_md()
+                    .bg(bg)
+                    .text_size(px(11.0))
+                    .text_color(theme_text)
+                    .child(format!("{}: {}", prefix, self.scope_label(scope)))
+                    .child(
+                        div()
+                            .ml(px(6.0))
+                            .cursor_pointer()
+                            .text_color(theme_muted)
+                            .hover(|s| s.text_color(theme.text))
+                            .child("×")
+                            .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
+                                cx.stop_propagation();
+                                panel_for_remove.update(cx, |this, cx| this.remove_scope(index, cx));
+                            }),
+                    ),
+            );
+        }
+
+        let status = if self.root_path.is_none() {
+            "请先打开一个文件夹".to_string()
+        } else if self.searching {
+            "搜索中…".to_string()
+        } else if self.query.is_empty() {
+            String::new()
+        } else if self.results.len() >= MAX_RESULTS {
+            format!("结果过多，仅显示前 {} 条", MAX_RESULTS)
+        } else {
+            format!("{} 个结果", self.results.len())
+        };
+
+        let input_text = if self.query.is_empty() {
+            div().text_color(theme_muted).child("搜索")
+        } else {
+            div().text_color(theme_text).child(self.query.clone())
+        };
+
+        div()
+            .flex_1()
+            .flex()
+            .flex_col()
+            .size_full()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
+                this.on_key_down(event, window, cx);
+            }))
+            .child(
+                div()
+                    .p(px(8.0))
+                    .relative()
+                    .child(
+                        div()
+                            .w_full()
+                            .h(px(24.0))
+                            .flex()
+                            .items_center()
+                            .bg(theme.input_bg)
+                            .rounded_md()
+                            .border_1()
+                            .border_color(theme.accent)
+                            .px(px(8.0))
+                            .text_size(px(13.0))
+                            .whitespace_nowrap()
+                            .overflow_hidden()
+                            .child(input_text)
+                            .on_mouse_down(MouseButton::Left, {
+                                let focus = focus.clone();
+                                move |_, window, _cx| focus.focus(window)
+                            }),
+                    )
+                    .child(
+                        canvas(|bounds, _window, _cx| bounds, {
+                            let panel = panel.clone();
+                            let focus = focus.clone();
+                            move |bounds, _layout, window, cx| {
+                                panel.update(cx, |this, _| this.input_bounds = Some(bounds));
+                                window.handle_input(&focus, ElementInputHandler::new(bounds, panel.clone()), cx);
+                                if focus.is_focused(window) {
+                                    let (query, cursor) = panel.read_with(cx, |this, _| (this.query.clone(), this.query_cursor));
+                                    let run = TextRun {
+                                        len: query.len(),
+                                        font: window.text_style().font(),
+                                        color: theme_text,
+                                        background_color: None,
+                                        underline: None,
+                                        strikethrough: None,
+                                    };
+                                    let line = window.text_system().shape_line(SharedString::from(query), px(13.0), &[run], None);
+                                    let x = bounds.left() + px(8.0) + line.x_for_index(cursor.min(line.len()));
+                                    window.paint_quad(fill(
+                                        Bounds::new(point(x, bounds.top() + px(4.0)), size(px(1.5), px(16.0))),
+                                        theme.accent,
+                                    ));
+                                }
+                            }
+                        })
+                        .absolute()
+                        .top(px(8.0))
+                        .left(px(8.0))
+                        .right(px(8.0))
+                        .h(px(24.0)),
+                    ),
+            )
+            .child(chips)
+            .child(
+                div()
+                    .px(px(8.0))
+                    .pb(px(4.0))
+                    .text_size(px(11.0))
+                    .text_color(theme_muted)
+                    .child(status),
+            )
+            .child(
+                list(self.list_state.clone(), move |index, _window, _cx| {
+                    let Some(m) = results.get(index) else {
+                        return div().into_any_element();
+                    };
+                    // 其它根目录下的结果以该文件夹的名称开头
+                    let file_label = root_path
+                        .as_ref()
+                        .and_then(|root| m.path.strip_prefix(root).ok())
+                        .map(|relative| relative.to_string_lossy().to_string())
+                        .or_else(|| {
+                            extra_roots.iter().find_map(|root| {
+                                let relative = m.path.strip_prefix(root.parent()?).ok()?;
+                                Some(relative.to_string_lossy().to_string())
+                            })
+                        })
+                        .unwrap_or_else(|| m.path.to_string_lossy().to_string());
+                    let panel_for_click = panel.clone();
+                    div()
+                        .w_full()
+                        .h(px(22.0))
+                        .px(px(8.0))
+                        .flex()
+                        .items_center()
+                        .cursor_pointer()
+                        .text_size(px(12.0))
+                        .bg(if index == selected_index { theme.list_selection } else { transparent_black() })
+                        .hover(|s| s.bg(theme.list_hover))
+                        .child(
+                            div()
+                                .flex_shrink_0()
+                                .mr(px(6.0))
+                                .text_color(theme_muted)
+                                .child(format!("{}:{}", file_label, m.line + 1)),
+                        )
+                        .child(
+                            div()
+                                .text_color(theme_text)
+                                .whitespace_nowrap()
+                                .overflow_hidden()
+                                .child(m.line_text.clone()),
+                        )
+                        .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
+                            panel_for_click.update(cx, |this, cx| {
+                                this.selection.index = index;
+                                this.open_selected(cx);
+                                cx.notify();
+                            });
+                        })
+                        .into_any_element()
+                })
+                .flex_1(),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{in_scope, search_files, SearchMatch, SearchScope, MAX_RESULTS};
+    use std::path::{Path, PathBuf};
+
+    fn scope(path: PathBuf, exclude: bool) -> SearchScope {
+        SearchScope { path, exclude }
+    }
+
+    fn found(results: &[SearchMatch], root: &Path) -> Vec<String> {
+        let mut paths: Vec<String> = results
+            .iter()
+            .map(|m| m.path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_search_never_leaves_scope() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        let project = base.join("project");
+        let extra = base.join("extra");
+        for sub in ["src/ui", "src/gen", "docs", "target"] {
+            std::fs::create_dir_all(project.join(sub)).unwrap();
+        }
+        std::fs::create_dir_all(&extra).unwrap();
+        std::fs::write(project.join("main.t"), "变量 甲 = 目标\n").unwrap();
+        std::fs::write(project.join("src/a.t"), "目标\n其它\n目标").unwrap();
+        std::fs::write(project.join("src/ui/b.t"), "目标").unwrap();
+        std::fs::write(project.join("src/gen/c.t"), "目标").unwrap();
+        std::fs::write(project.join("docs/d.md"), "目标").unwrap();
+        std::fs::write(project.join("target/e.t"), "目标").unwrap();
+        std::fs::write(extra.join("f.t"), "目标").unwrap();
+        std::fs::write(base.join("outside.t"), "目标").unwrap();
+        let roots = vec![project.clone(), extra.clone()];
+
+        // 不限范围时搜索所有根目录，跳过 target 和根目录以外的文件
+        let all = search_files(&roots, "目标", &[], MAX_RESULTS);
+        assert_eq!(
+            found(&all, base),
+            [
+                "extra/f.t",
+                "project/docs/d.md",
+                "project/main.t",
+                "project/src/a.t",
+                "project/src/a.t",
+                "project/src/gen/c.t",
+                "project/src/ui/b.t",
+            ]
+        );
+
+        // 包含 src、排除其中的 gen
+        let scopes = [scope(project.join("src"), false), scope(project.join("src/gen"), true)];
+        let results = search_files(&roots, "目标", &scopes, MAX_RESULTS);
+        assert_eq!(found(&results, base), ["project/src/a.t", "project/src/a.t", "project/src/ui/b.t"]);
+        assert!(results.iter().all(|m| in_scope(&m.path, &scopes)));
+
+        // 嵌套的包含范围不产生重复结果；额外根目录中的范围同样生效
+        let scopes = [scope(project.join("src"), false), scope(project.join("src/ui"), false), scope(extra.clone(), false)];
+        let results = search_files(&roots, "目标", &scopes, MAX_RESULTS);
+        assert_eq!(
+            found(&results, base),
+            ["extra/f.t", "project/src/a.t", "project/src/a.t", "project/src/gen/c.t", "project/src/ui/b.t"]
+        );
+
+        // 只排除时其余文件照常搜索
+        let scopes = [scope(project.join("src"), true), scope(extra, true)];
+        let results = search_files(&roots, "目标", &scopes, MAX_RESULTS);
+        assert_eq!(found(&results, base), ["project/docs/d.md", "project/main.t"]);
+    }
+}
@@ -0,0 +1,91 @@
+//! 左右对照的差异视图的行对齐：相同的行左右并排，修改的行按顺序配对，多出的行对面留空
+
+use similar::{DiffTag, TextDiff};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowKind {
+    Equal,
+    Changed,
+    Removed,
+    Added,
+}
+
+/// 一行对照；`left`、`right` 为两侧的行号（从 0 开始），该侧留空时为 None
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiffRow {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    pub kind: RowKind,
+}
+
+pub fn rows(left: &str, right: &str) -> Vec<DiffRow> {
+    let diff = TextDiff::from_lines(left, right);
+    let mut rows = Vec::new();
+    for op in diff.ops() {
+        let (old, new) = (op.old_range(), op.new_range());
+        match op.tag() {
+            DiffTag::Equal => rows.extend(old.zip(new).map(|(l, r)| DiffRow {
+                left: Some(l),
+                right: Some(r),
+                kind: RowKind::Equal,
+            })),
+            DiffTag::Delete => rows.extend(old.map(|l| DiffRow {
+                left: Some(l),
+                right: None,
+                kind: RowKind::Removed,
+            })),
+            DiffTag::Insert => rows.extend(new.map(|r| DiffRow {
+                left: None,
+                right: Some(r),
+                kind: RowKind::Added,
+            })),
+            DiffTag::Replace => {
+                for i in 0..old.len().max(new.len()) {
+                    let l = (i < old.len()).then(|| old.start + i);
+                    let r = (i < new.len()).then(|| new.start + i);
+                    let kind = match (l, r) {
+                        (Some(_), Some(_)) => RowKind::Changed,
+                        (Some(_), None) => RowKind::Removed,
+                        _ => RowKind::Added,
+                    };
+                    rows.push(DiffRow { left: l, right: r, kind });
+                }
+            }
+        }
+    }
+    rows
+}
+
+/// 增加和删除的行数，修改的行两边各算一次
+pub fn stats(rows: &[DiffRow]) -> (usize, usize) {
+    rows.iter().fold((0, 0), |(added, removed), row| match row.kind {
+        RowKind::Equal => (added, removed),
+        RowKind::Changed => (added + 1, removed + 1),
+        RowKind::Removed => (added, removed + 1),
+        RowKind::Added => (added + 1, removed),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_pair_changes_and_pad_missing_side() {
+        let rows = rows("a\nb\nc\nd\n", "a\nB\nx\nd\ne\n");
+        let pairs: Vec<(Option<usize>, Option<usize>, RowKind)> =
+            rows.iter().map(|r| (r.left, r.right, r.kind)).collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (Some(0), Some(0), RowKind::Equal),
+                (Some(1), Some(1), RowKind::Changed),
+                (Some(2), Some(2), RowKind::Changed),
+                (Some(3), Some(3), RowKind::Equal),
+                (None, Some(4), RowKind::Added),
+            ]
+        );
+        assert_eq!(stats(&rows), (3, 2));
+        assert_eq!(stats(&super::rows("a\n", "a\n")), (0, 0));
+    }
+}
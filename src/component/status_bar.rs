@@ -1,27 +1,162 @@
 use gpui::*;
+use crate::component::FocusRing;
+use crate::editor::core::LineEnding;
 use crate::editor::CodeEditor;
+use crate::plugin::manager::PluginManager;
+use crate::plugin::status_items::StatusItem;
 use std::path::{Path, PathBuf};
+use tiecode_plugin_api::StatusAlignment;
 use std::process::Command;
 use std::time::Duration;
 
+pub enum StatusBarEvent {
+    /// 点击了行列号
+    GoToLine,
+    /// 点击了关联命令的状态栏项
+    RunCommand(String),
+}
+
+/// 当前分支，点击时审阅变更
+pub const GIT_BRANCH_ITEM: &str = "core.git_branch";
+/// 保存前查错得到的错误数，没有错误时隐藏
+pub const DIAGNOSTICS_ITEM: &str = "core.diagnostics";
+/// 显示或隐藏输出面板
+pub const OUTPUT_ITEM: &str = "core.output";
+
+/// [`StatusBar::flash`] 的提示显示多久
+const FLASH_DURATION: Duration = Duration::from_secs(3);
+/// 忙碌指示的动画帧及切换间隔
+const SPINNER_FRAMES: [&str; 4] = ["◐", "◓", "◑", "◒"];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(120);
+
 pub struct StatusBar {
+    /// 状态栏作为一个焦点区域，其中可点击的项可用 Tab 切换
+    pub focus_handle: FocusHandle,
     editor: Entity<CodeEditor>,
-    git_branch: String,
+    /// 状态栏项登记在插件管理器中
+    plugin_manager: Entity<PluginManager>,
+    /// 左侧显示的警告，例如自动保存了有错误的文件
+    warning: Option<String>,
+    /// 后台任务的进度，例如启动后加载插件、编译语法
+    progress: Option<String>,
+    /// 短暂显示的提示，例如找不到定义
+    flash: Option<String>,
+    flash_task: Option<Task<()>>,
+    /// 正在进行的较长任务，例如编译项目，显示为转动的指示和说明
+    busy: Option<String>,
+    spinner_frame: usize,
+    spinner_task: Option<Task<()>>,
+    /// 当前文件保存时是否带 UTF-8 BOM
+    has_bom: bool,
+    /// 是否开启了自动保存
+    autosave: bool,
     #[allow(dead_code)]
     git_check_task: Option<Task<()>>,
 }
 
+impl EventEmitter<StatusBarEvent> for StatusBar {}
+
 impl StatusBar {
-    pub fn new(editor: Entity<CodeEditor>, cx: &mut Context<Self>) -> Self {
+    pub fn new(editor: Entity<CodeEditor>, plugin_manager: Entity<PluginManager>, cx: &mut Context<Self>) -> Self {
+        plugin_manager.update(cx, |manager, _| {
+            manager.register_status_item(GIT_BRANCH_ITEM, StatusAlignment::Left, 100);
+            manager.set_status_item_text(GIT_BRANCH_ITEM, "Git: Checking...", None);
+            manager.set_status_item_command(GIT_BRANCH_ITEM, Some("git.review_changes".to_string()));
+            manager.register_status_item(DIAGNOSTICS_ITEM, StatusAlignment::Left, 90);
+            manager.register_status_item(OUTPUT_ITEM, StatusAlignment::Right, 0);
+            manager.set_status_item_text(OUTPUT_ITEM, "输出", Some("显示或隐藏输出面板".to_string()));
+            manager.set_status_item_command(OUTPUT_ITEM, Some("view.toggle_output".to_string()));
+        });
+        cx.observe(&plugin_manager, |_, _, cx| cx.notify()).detach();
         let mut this = Self { 
+            focus_handle: cx.focus_handle(),
             editor, 
-            git_branch: "Checking...".to_string(),
+            plugin_manager,
+            warning: None,
+            progress: None,
+            flash: None,
+            flash_task: None,
+            busy: None,
+            spinner_frame: 0,
+            spinner_task: None,
+            has_bom: false,
+            autosave: false,
             git_check_task: None,
         };
         this.start_git_check(cx);
         this
     }
 
+    pub fn set_warning(&mut self, warning: Option<String>, cx: &mut Context<Self>) {
+        if self.warning != warning {
+            self.warning = warning;
+            cx.notify();
+        }
+    }
+
+    pub fn set_progress(&mut self, progress: Option<String>, cx: &mut Context<Self>) {
+        if self.progress != progress {
+            self.progress = progress;
+            cx.notify();
+        }
+    }
+
+    /// 显示一条提示，[`FLASH_DURATION`] 后自动消失；新的提示替换之前的
+    pub fn flash(&mut self, message: String, cx: &mut Context<Self>) {
+        self.flash = Some(message);
+        self.flash_task = Some(cx.spawn(|view: WeakEntity<StatusBar>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(FLASH_DURATION).await;
+                view.update(&mut cx, |this, cx| {
+                    this.flash = None;
+                    cx.notify();
+                })
+                .ok();
+            }
+        }));
+        cx.notify();
+    }
+
+    /// 显示或清除忙碌指示
+    pub fn set_busy(&mut self, busy: Option<String>, cx: &mut Context<Self>) {
+        if busy.is_none() {
+            self.spinner_task = None;
+        } else if self.spinner_task.is_none() {
+            self.spinner_task = Some(cx.spawn(|view: WeakEntity<StatusBar>, cx: &mut AsyncApp| {
+                let mut cx = cx.clone();
+                async move {
+                    loop {
+                        cx.background_executor().timer(SPINNER_INTERVAL).await;
+                        let ticked = view.update(&mut cx, |this, cx| {
+                            this.spinner_frame = (this.spinner_frame + 1) % SPINNER_FRAMES.len();
+                            cx.notify();
+                        });
+                        if ticked.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }));
+        }
+        self.busy = busy;
+        cx.notify();
+    }
+
+    pub fn set_bom(&mut self, has_bom: bool, cx: &mut Context<Self>) {
+        if self.has_bom != has_bom {
+            self.has_bom = has_bom;
+            cx.notify();
+        }
+    }
+
+    pub fn set_autosave(&mut self, autosave: bool, cx: &mut Context<Self>) {
+        if self.autosave != autosave {
+            self.autosave = autosave;
+            cx.notify();
+        }
+    }
+
     fn start_git_check(&mut self, cx: &mut Context<Self>) {
         self.git_check_task = Some(cx.spawn(|view: WeakEntity<StatusBar>, cx: &mut AsyncApp| {
             let mut cx = cx.clone();
@@ -50,10 +185,11 @@ impl StatusBar {
 
                     // 4. Update UI
                     view.update(&mut cx, |this, cx: &mut Context<StatusBar>| {
-                        if this.git_branch != branch {
-                            this.git_branch = branch;
-                            cx.notify();
-                        }
+                        this.plugin_manager.update(cx, |manager, cx| {
+                            if manager.set_status_item_text(GIT_BRANCH_ITEM, format!("Git: {}", branch), None) {
+                                cx.notify();
+                            }
+                        });
                     }).ok();
                 }
 
@@ -136,6 +272,48 @@ impl StatusBar {
     }
 }
 
+/// 状态栏项的提示
+struct StatusTooltip(SharedString);
+
+impl Render for StatusTooltip {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = crate::component::theme(cx);
+        div()
+            .px(px(8.0))
+            .py(px(4.0))
+            .bg(theme.panel)
+            .border_1()
+            .border_color(theme.border)
+            .rounded_md()
+            .text_size(px(12.0))
+            .text_color(theme.text)
+            .child(self.0.clone())
+    }
+}
+
+impl StatusBar {
+    fn render_item(item: &StatusItem, cx: &mut Context<Self>) -> Stateful<Div> {
+        let mut element = div()
+            .id(SharedString::from(format!("status-item-{}", item.id)))
+            .rounded_sm()
+            .mr(px(10.0))
+            .child(item.text.clone());
+        if let Some(tooltip) = item.tooltip.clone() {
+            let tooltip = SharedString::from(tooltip);
+            element = element.tooltip(move |_window, cx| cx.new(|_| StatusTooltip(tooltip.clone())).into());
+        }
+        if let Some(command) = item.command.clone() {
+            let hover = crate::component::theme(cx).list_hover;
+            element = element
+                .focus_ring(cx)
+                .cursor_pointer()
+                .hover(|s| s.bg(hover))
+                .on_click(cx.listener(move |_this, _, _window, cx| cx.emit(StatusBarEvent::RunCommand(command.clone()))));
+        }
+        element
+    }
+}
+
 impl Render for StatusBar {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let editor = self.editor.read(cx);
@@ -155,17 +333,27 @@ impl Render for StatusBar {
 
         let uri = &editor.lsp_manager.doc_uri;
         let language = Self::get_language(uri);
+        let line_ending = editor.line_ending();
         
-        let git_branch = &self.git_branch;
+        let manager = self.plugin_manager.read(cx);
+        let left_items: Vec<StatusItem> = manager.status_items().aligned(StatusAlignment::Left).into_iter().cloned().collect();
+        let right_items: Vec<StatusItem> =
+            manager.status_items().aligned(StatusAlignment::Right).into_iter().cloned().collect();
+        let warning = self.warning.clone();
+        let progress = self.progress.clone();
+        let busy = self.busy.as_ref().map(|busy| format!("{} {}", SPINNER_FRAMES[self.spinner_frame], busy));
+        let flash = self.flash.clone();
         
-        // Encoding placeholder (Ropey is UTF-8)
-        let encoding = "UTF-8";
+        // Ropey is UTF-8; the BOM is stripped on open and restored on save
+        let encoding = if self.has_bom { "UTF-8 with BOM" } else { "UTF-8" };
 
-        let theme_bg = rgb(0xff1f2428); // Matches other dark backgrounds like titlebar/tabs
-        let theme_text = rgb(0xffd1d5da);
-        let theme_border = rgb(0xff3c474d);
+        let theme = crate::component::theme(cx);
+        let theme_bg = theme.surface; // Matches other dark backgrounds like titlebar/tabs
+        let theme_text = theme.text;
+        let theme_border = theme.border;
 
         div()
+            .focus_region(&self.focus_handle)
             .w_full()
             .h(px(24.0)) // Slightly smaller than 30px for a status bar feel
             .bg(theme_bg)
@@ -177,19 +365,78 @@ impl Render for StatusBar {
             .px(px(10.0))
             .text_size(px(12.0))
             .text_color(theme_text)
-            // Left side: Git status
+            // 左侧：状态栏项（当前分支、错误数和插件提供的项）、警告和进度
             .child(
-                div().flex().items_center().child(
-                    div().flex().items_center().mr(px(10.0))
-                        //.child(tie_svg::tie_svg().path("assets/icons/git_branch.svg").size(px(12.0)).color(theme_text).into_any_element())
-                        .child(div().ml(px(4.0)).child(format!("Git: {}", git_branch)))
+                div().flex().items_center().children(
+                    left_items.iter().map(|item| Self::render_item(item, cx))
+                ).child(
+                    if let Some(warning) = warning {
+                        // 点击警告将其清除
+                        div()
+                            .id("status-warning")
+                            .focus_ring(cx)
+                            .rounded_sm()
+                            .cursor_pointer()
+                            .text_color(theme.warning)
+                            .child(format!("⚠ {}", warning))
+                            .on_click(cx.listener(|this, _, _window, cx| this.set_warning(None, cx)))
+                    } else {
+                        div().id("status-warning")
+                    }
+                ).child(
+                    div().ml(px(10.0)).text_color(theme_text).child(flash.unwrap_or_default())
+                ).child(
+                    div().ml(px(10.0)).text_color(theme.muted_text).child(progress.unwrap_or_default())
+                ).child(
+                    div().ml(px(10.0)).text_color(theme.muted_text).child(busy.unwrap_or_default())
                 )
             )
-            // Right side: Info
+            // 右侧：插件提供的项和当前文件的信息
             .child(
                 div().flex().items_center()
-                    .child(div().mr(px(15.0)).child(format!("Ln {}, Col {}", line_display, col_display)))
+                    .children(right_items.iter().map(|item| Self::render_item(item, cx).mr(px(15.0))))
+                    .child(
+                        div()
+                            .id("status-position")
+                            .focus_ring(cx)
+                            .rounded_sm()
+                            .cursor_pointer()
+                            .mr(px(15.0))
+                            .child(format!("Ln {}, Col {}", line_display, col_display))
+                            .on_click(cx.listener(|_this, _, _window, cx| cx.emit(StatusBarEvent::GoToLine))),
+                    )
+                    .child(
+                        // 点击开启或关闭自动保存
+                        div()
+                            .id("status-autosave")
+                            .focus_ring(cx)
+                            .rounded_sm()
+                            .cursor_pointer()
+                            .mr(px(15.0))
+                            .text_color(if self.autosave { theme_text } else { theme.muted_text })
+                            .child(if self.autosave { "自动保存：开" } else { "自动保存：关" })
+                            .on_click(cx.listener(|_this, _, _window, cx| {
+                                cx.emit(StatusBarEvent::RunCommand("files.autosave.toggle".to_string()));
+                            })),
+                    )
                     .child(div().mr(px(15.0)).child(encoding))
+                    .child(
+                        // 点击转换为另一种换行符
+                        div()
+                            .id("status-line-ending")
+                            .focus_ring(cx)
+                            .rounded_sm()
+                            .cursor_pointer()
+                            .mr(px(15.0))
+                            .child(line_ending.label())
+                            .on_click(cx.listener(move |_this, _, _window, cx| {
+                                let command = match line_ending {
+                                    LineEnding::Lf => "editor.convert_to_crlf",
+                                    LineEnding::Crlf => "editor.convert_to_lf",
+                                };
+                                cx.emit(StatusBarEvent::RunCommand(command.to_string()));
+                            })),
+                    )
                     .child(div().mr(px(15.0)).child(language))
                     .child(div().child("LSP: Ready"))
             )
@@ -2,6 +2,12 @@ use gpui::*;
 use std::path::PathBuf;
 use crate::component::file_tree::FileTree;
 use crate::component::tie_svg::tie_svg;
+use crate::component::FocusRing;
+
+pub enum ToolPanelEvent {
+    /// 点击了文件页标题栏上的定位按钮
+    RevealActiveFile,
+}
 
 #[derive(Clone)]
 pub struct ToolEntry {
@@ -12,14 +18,23 @@ pub struct ToolEntry {
 }
 
 pub struct ToolPanel {
+    focus_handle: FocusHandle,
+    /// 标题栏上的页面图标所在的焦点区域
+    header_focus: FocusHandle,
     entries: Vec<ToolEntry>,
     selected: usize,
     file_tree: Entity<FileTree>,
     git_panel: Option<Entity<crate::component::git_panel::GitPanel>>,
+    search_panel: Option<Entity<crate::component::search_panel::SearchPanel>>,
+    plugin_panel: Option<Entity<crate::component::plugin_panel::PluginPanel>>,
+    problems_panel: Option<Entity<crate::component::problems_panel::ProblemsPanel>>,
+    settings_panel: Option<Entity<crate::component::project_settings_panel::ProjectSettingsPanel>>,
 }
 
+impl EventEmitter<ToolPanelEvent> for ToolPanel {}
+
 impl ToolPanel {
-    pub fn new(file_tree: Entity<FileTree>, _cx: &mut Context<Self>) -> Self {
+    pub fn new(file_tree: Entity<FileTree>, cx: &mut Context<Self>) -> Self {
         let mut entries = Vec::new();
         entries.push(ToolEntry {
             id: "explorer".to_string(),
@@ -28,10 +43,16 @@ impl ToolPanel {
             builtin_explorer: true,
         });
         Self {
+            focus_handle: cx.focus_handle(),
+            header_focus: cx.focus_handle(),
             entries,
             selected: 0,
             file_tree,
             git_panel: None,
+            search_panel: None,
+            plugin_panel: None,
+            problems_panel: None,
+            settings_panel: None,
         }
     }
 
@@ -51,6 +72,81 @@ impl ToolPanel {
     pub fn git_panel(&self) -> Option<Entity<crate::component::git_panel::GitPanel>> {
         self.git_panel.clone()
     }
+
+    pub fn attach_search_panel(&mut self, search_panel: Entity<crate::component::search_panel::SearchPanel>) {
+        self.search_panel = Some(search_panel);
+    }
+
+    pub fn search_panel(&self) -> Option<Entity<crate::component::search_panel::SearchPanel>> {
+        self.search_panel.clone()
+    }
+
+    pub fn attach_plugin_panel(&mut self, plugin_panel: Entity<crate::component::plugin_panel::PluginPanel>) {
+        self.plugin_panel = Some(plugin_panel);
+    }
+
+    pub fn attach_problems_panel(&mut self, problems_panel: Entity<crate::component::problems_panel::ProblemsPanel>) {
+        self.problems_panel = Some(problems_panel);
+    }
+
+    pub fn attach_settings_panel(
+        &mut self,
+        settings_panel: Entity<crate::component::project_settings_panel::ProjectSettingsPanel>,
+    ) {
+        self.settings_panel = Some(settings_panel);
+    }
+
+    /// 切换到指定 id 的工具页，找不到时保持不变
+    pub fn select_page(&mut self, id: &str, cx: &mut Context<Self>) {
+        if let Some(index) = self.entries.iter().position(|e| e.id == id) {
+            self.selected = index;
+            cx.notify();
+        }
+    }
+}
+
+impl ToolPanel {
+    /// 焦点是否在工具面板（含其中的文件树、Git、搜索页）内
+    pub fn contains_focus(&self, window: &Window, cx: &App) -> bool {
+        self.focus_handle.contains_focused(window, cx)
+    }
+
+    pub fn header_focus(&self) -> FocusHandle {
+        self.header_focus.clone()
+    }
+
+    /// 把焦点交给当前工具页的主要控件
+    pub fn focus_active_page(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        match self.entries.get(self.selected).map(|e| e.id.as_str()) {
+            Some("explorer") => self.file_tree.read(cx).focus(window),
+            Some("git") if self.git_panel.is_some() => {
+                if let Some(panel) = &self.git_panel {
+                    panel.update(cx, |panel, cx| panel.focus_commit_input(window, cx));
+                }
+            }
+            Some("search") if self.search_panel.is_some() => {
+                if let Some(panel) = &self.search_panel {
+                    panel.read(cx).focus(window);
+                }
+            }
+            Some("plugins") if self.plugin_panel.is_some() => {
+                if let Some(panel) = &self.plugin_panel {
+                    panel.read(cx).focus(window);
+                }
+            }
+            Some("problems") if self.problems_panel.is_some() => {
+                if let Some(panel) = &self.problems_panel {
+                    panel.read(cx).focus(window);
+                }
+            }
+            Some("settings") if self.settings_panel.is_some() => {
+                if let Some(panel) = &self.settings_panel {
+                    panel.read(cx).focus(window);
+                }
+            }
+            _ => self.focus_handle.focus(window),
+        }
+    }
 }
 
 impl Render for ToolPanel {
@@ -58,16 +154,18 @@ impl Render for ToolPanel {
         let entries = self.entries.clone();
         let selected = self.selected;
         let panel = cx.entity();
+        let theme = crate::component::theme(cx);
         let header = div()
             .w_full()
             .h(px(32.0))
-            .bg(rgb(0xff232a2e))
+            .bg(theme.header)
             .border_b_1()
-            .border_color(rgb(0xff3c474d))
+            .border_color(theme.border)
             .px(px(8.0))
             .flex()
             .items_center()
-            .gap(px(8.0));
+            .gap(px(8.0))
+            .focus_region(&self.header_focus);
         let mut header = header;
         for (i, e) in entries.iter().enumerate() {
             let icon_elem = if e.id == "git" {
@@ -75,7 +173,7 @@ impl Render for ToolPanel {
                     .path(SharedString::from("assets/git.svg"))
                     .size(px(18.0))
                     .original_colors(false)
-                    .text_color(rgb(0xfff14e32))
+                    .text_color(theme.text)
             } else if let Some(path) = &e.icon {
                 tie_svg()
                     .path(path.to_string_lossy().to_string())
@@ -89,19 +187,49 @@ impl Render for ToolPanel {
             };
             let idx = i;
             let panel_for_click = panel.clone();
+            let panel_for_key = panel.clone();
             header = header.child(
                 div()
+                    .id(("tool-page", idx))
+                    .focus_ring(cx)
                     .p(px(6.0))
                     .rounded_md()
                     .cursor_pointer()
-                    .bg(if selected == idx { rgb(0xff2d353b) } else { rgba(0x00000000) })
-                    .hover(|s| s.bg(rgba(0xffffff12)))
+                    .bg(if selected == idx { theme.panel } else { transparent_black() })
+                    .hover(|s| s.bg(theme.list_hover))
                     .child(icon_elem)
                     .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
                         panel_for_click.update(cx, |this, cx_inner| {
                             this.selected = idx;
                             cx_inner.notify();
                         });
+                    })
+                    .on_click(move |event, _window, cx| {
+                        if event.is_keyboard() {
+                            panel_for_key.update(cx, |this, cx| {
+                                this.selected = idx;
+                                cx.notify();
+                            });
+                        }
+                    }),
+            );
+        }
+        if entries.get(selected).map(|e| e.builtin_explorer).unwrap_or(false) {
+            let panel_for_reveal = panel.clone();
+            header = header.child(
+                div()
+                    .id("reveal-active-file")
+                    .focus_ring(cx)
+                    .ml_auto()
+                    .px(px(6.0))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .text_size(px(16.0))
+                    .text_color(theme.muted_text)
+                    .hover(|s| s.bg(theme.list_hover).text_color(theme.text))
+                    .child("⌖")
+                    .on_click(move |_, _window, cx| {
+                        panel_for_reveal.update(cx, |_this, cx| cx.emit(ToolPanelEvent::RevealActiveFile));
                     }),
             );
         }
@@ -119,10 +247,30 @@ impl Render for ToolPanel {
                             .flex()
                             .items_center()
                             .justify_center()
-                            .text_color(rgb(0xffa9b1b6))
+                            .text_color(theme.muted_text)
                             .child("Git 工具未初始化")
                             .into_any_element()
                     }
+                } else if let (Some(panel), true) = (
+                    &self.search_panel,
+                    entries.get(selected).map(|e| e.id.as_str() == "search").unwrap_or(false),
+                ) {
+                    panel.clone().into_any_element()
+                } else if let (Some(panel), true) = (
+                    &self.plugin_panel,
+                    entries.get(selected).map(|e| e.id.as_str() == "plugins").unwrap_or(false),
+                ) {
+                    panel.clone().into_any_element()
+                } else if let (Some(panel), true) = (
+                    &self.problems_panel,
+                    entries.get(selected).map(|e| e.id.as_str() == "problems").unwrap_or(false),
+                ) {
+                    panel.clone().into_any_element()
+                } else if let (Some(panel), true) = (
+                    &self.settings_panel,
+                    entries.get(selected).map(|e| e.id.as_str() == "settings").unwrap_or(false),
+                ) {
+                    panel.clone().into_any_element()
                 } else {
                     div()
                     .flex_1()
@@ -130,16 +278,16 @@ impl Render for ToolPanel {
                     .flex_col()
                     .p(px(12.0))
                     .text_size(px(13.0))
-                    .text_color(rgb(0xffe6e0d9))
+                    .text_color(theme.text)
                     .child(
                         div()
-                            .text_color(rgb(0xffa9b1b6))
+                            .text_color(theme.muted_text)
                             .child("资源管理器"),
                     )
                     .child(
                         div()
                             .mt(px(8.0))
-                            .text_color(rgb(0xffe6e0d9))
+                            .text_color(theme.text)
                             .child(format!("工具页面：{}", label)),
                     )
                         .into_any_element()
@@ -147,11 +295,12 @@ impl Render for ToolPanel {
             }
         };
         div()
+            .track_focus(&self.focus_handle)
             .w_full()
             .h_full()
             .flex()
             .flex_col()
-            .bg(rgb(0xff252526))
+            .bg(theme.sidebar)
             .child(header)
             .child(body)
     }
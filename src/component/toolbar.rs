@@ -0,0 +1,104 @@
+//! 快捷工具栏：标题栏下方一行固定的命令按钮。在命令面板中右键命令固定或取消固定，拖动按钮调整顺序；
+//! 固定的命令和是否显示记入会话
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Toolbar {
+    /// 按显示顺序排列的命令 id
+    #[serde(default = "default_commands")]
+    pub commands: Vec<String>,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+}
+
+fn default_commands() -> Vec<String> {
+    ["core.new_file", "core.open_file", "core.save", "core.undo", "core.redo", "file.open_terminal"]
+        .iter()
+        .map(|command| command.to_string())
+        .collect()
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+impl Default for Toolbar {
+    fn default() -> Self {
+        Self { commands: default_commands(), visible: default_visible() }
+    }
+}
+
+impl Toolbar {
+    pub fn is_pinned(&self, command: &str) -> bool {
+        self.commands.iter().any(|c| c == command)
+    }
+
+    /// 固定或取消固定命令，新固定的放在最后
+    pub fn toggle_pin(&mut self, command: &str) {
+        if self.is_pinned(command) {
+            self.commands.retain(|c| c != command);
+        } else {
+            self.commands.push(command.to_string());
+        }
+    }
+
+    /// 拖动按钮：把 `command` 移到 `target` 所在的位置，返回顺序是否变化
+    pub fn move_to(&mut self, command: &str, target: &str) -> bool {
+        let (Some(from), Some(to)) = (
+            self.commands.iter().position(|c| c == command),
+            self.commands.iter().position(|c| c == target),
+        ) else {
+            return false;
+        };
+        if from == to {
+            return false;
+        }
+        let command = self.commands.remove(from);
+        self.commands.insert(to, command);
+        true
+    }
+}
+
+/// 内置命令的图标；插件命令使用清单中的图标，都没有时显示标题的首字
+pub fn builtin_icon(command: &str) -> Option<&'static str> {
+    let icon = match command {
+        "core.new_file" => "assets/toolbar/new_file.svg",
+        "core.open_file" => "assets/toolbar/open_file.svg",
+        "core.open_folder" | "workspace.add_folder" => "assets/toolbar/folder.svg",
+        "core.save" | "core.save_as" | "workspace.prepare_commit" => "assets/toolbar/save.svg",
+        "core.undo" => "assets/toolbar/undo.svg",
+        "core.redo" => "assets/toolbar/redo.svg",
+        "file.open_terminal" => "assets/toolbar/terminal.svg",
+        "file_tree.toggle" => "assets/toolbar/sidebar.svg",
+        "command.history" | "editor.recover_discarded" => "assets/toolbar/history.svg",
+        "git.review_changes" => "assets/git.svg",
+        _ => return None,
+    };
+    Some(icon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_and_reorder() {
+        let mut toolbar: Toolbar = serde_json::from_str("{}").unwrap();
+        assert_eq!(toolbar, Toolbar::default());
+        assert!(toolbar.is_pinned("core.save"));
+
+        toolbar.toggle_pin("core.save");
+        toolbar.toggle_pin("file_tree.toggle");
+        assert!(!toolbar.is_pinned("core.save"));
+        assert_eq!(toolbar.commands.last().map(String::as_str), Some("file_tree.toggle"));
+
+        assert!(toolbar.move_to("file_tree.toggle", "core.new_file"));
+        assert_eq!(toolbar.commands[..2], ["file_tree.toggle", "core.new_file"]);
+        assert!(toolbar.move_to("file_tree.toggle", "core.open_file"));
+        // 向右拖动时越过目标按钮
+        assert_eq!(toolbar.commands[..3], ["core.new_file", "core.open_file", "file_tree.toggle"]);
+        assert!(!toolbar.move_to("core.save", "core.undo"));
+    }
+}
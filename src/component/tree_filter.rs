@@ -0,0 +1,123 @@
+//! 文件树筛选：按相对根目录的完整路径模糊匹配项目中的所有文件和文件夹，
+//! 得到要显示的项，以及为显示匹配项需要展开的文件夹
+
+use super::search_panel::SKIPPED_DIRS;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// 查询中的字符（忽略空白、不区分大小写）按顺序出现在路径中即为匹配
+pub fn fuzzy_match(query: &str, path: &str) -> bool {
+    let mut chars = path.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .all(|q| chars.any(|c| c == q))
+}
+
+/// 根目录下的所有文件和文件夹，跳过与全局搜索相同的构建和版本库目录
+pub fn project_paths(root: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if SKIPPED_DIRS.contains(&name.as_str()) {
+                    continue;
+                }
+                stack.push(path.clone());
+            }
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TreeFilter {
+    matched: HashSet<PathBuf>,
+    /// 包含匹配项的文件夹（含根目录），筛选时自动展开
+    ancestors: HashSet<PathBuf>,
+}
+
+impl TreeFilter {
+    pub fn new(root: &Path, query: &str, paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut filter = Self::default();
+        for path in paths {
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let relative: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            if !fuzzy_match(query, &relative.join("/")) {
+                continue;
+            }
+            let mut dir = path.parent();
+            while let Some(parent) = dir.filter(|d| d.starts_with(root)) {
+                if !filter.ancestors.insert(parent.to_path_buf()) {
+                    break;
+                }
+                dir = parent.parent();
+            }
+            filter.matched.insert(path);
+        }
+        filter
+    }
+
+    /// 合并另一个根目录的筛选结果
+    pub fn extend(&mut self, other: TreeFilter) {
+        self.matched.extend(other.matched);
+        self.ancestors.extend(other.ancestors);
+    }
+
+    pub fn ancestors(&self) -> &HashSet<PathBuf> {
+        &self.ancestors
+    }
+
+    pub fn is_match(&self, path: &Path) -> bool {
+        self.matched.contains(path)
+    }
+
+    /// 匹配项本身和包含匹配项的文件夹显示，其余隐藏
+    pub fn shows(&self, path: &Path) -> bool {
+        self.matched.contains(path) || self.ancestors.contains(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_matches_full_relative_path() {
+        assert!(fuzzy_match("SrcMain", "src/main.rs"));
+        assert!(fuzzy_match("ui btn", "src/ui/button.rs"));
+        assert!(!fuzzy_match("rsmain", "src/main.rs"));
+
+        let root = Path::new("/p");
+        let paths = ["src", "src/ui", "src/ui/button.rs", "src/main.rs", "docs", "docs/readme.md"]
+            .iter()
+            .map(|p| root.join(p));
+        let filter = TreeFilter::new(root, "uibtn", paths);
+        assert!(filter.is_match(Path::new("/p/src/ui/button.rs")));
+        assert!(filter.shows(Path::new("/p/src/ui")));
+        assert!(!filter.is_match(Path::new("/p/src/ui")));
+        assert!(filter.ancestors().contains(root));
+        assert!(!filter.shows(Path::new("/p/src/main.rs")));
+        assert!(!filter.shows(Path::new("/p/docs")));
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a/.git")).unwrap();
+        std::fs::write(dir.path().join("a/x.t"), "").unwrap();
+        let mut found = project_paths(dir.path());
+        found.sort();
+        assert_eq!(found, vec![dir.path().join("a"), dir.path().join("a/x.t")]);
+    }
+}
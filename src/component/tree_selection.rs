@@ -0,0 +1,123 @@
+//! 文件树的多选：Ctrl 点击切换单项，Shift 点击从锚点选到该项，Ctrl+A 选中同级的所有项。
+//! 批量删除和移动只处理最上层的项，已选文件夹内部的项随文件夹一起处理
+
+use super::tree_watch::rename_path;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TreeSelection {
+    /// 按选中的先后排列
+    paths: Vec<PathBuf>,
+    /// Shift 点击时范围的起点
+    anchor: Option<PathBuf>,
+}
+
+impl TreeSelection {
+    pub fn contains(&self, path: &Path) -> bool {
+        self.paths.iter().any(|p| p == path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+        self.anchor = None;
+    }
+
+    /// 普通点击：只选中这一项
+    pub fn select_only(&mut self, path: &Path) {
+        self.paths = vec![path.to_path_buf()];
+        self.anchor = Some(path.to_path_buf());
+    }
+
+    /// Ctrl 点击：切换这一项，并作为之后 Shift 点击的起点
+    pub fn toggle(&mut self, path: &Path) {
+        if self.contains(path) {
+            self.paths.retain(|p| p != path);
+        } else {
+            self.paths.push(path.to_path_buf());
+        }
+        self.anchor = Some(path.to_path_buf());
+    }
+
+    /// Shift 点击：选中 `visible` 中从锚点到 `path` 的所有行；锚点不可见时只选中该项
+    pub fn extend_to(&mut self, visible: &[PathBuf], path: &Path) {
+        let anchor = self.anchor.as_ref().and_then(|anchor| visible.iter().position(|p| p == anchor));
+        let (Some(anchor), Some(target)) = (anchor, visible.iter().position(|p| p == path)) else {
+            self.select_only(path);
+            return;
+        };
+        let range = anchor.min(target)..=anchor.max(target);
+        self.paths = visible[range].to_vec();
+    }
+
+    /// 选中 `visible` 中与 `path` 同一父目录的所有行
+    pub fn select_siblings(&mut self, visible: &[PathBuf], path: &Path) {
+        let parent = path.parent();
+        self.paths = visible.iter().filter(|p| p.parent() == parent).cloned().collect();
+        self.anchor = Some(path.to_path_buf());
+    }
+
+    /// 最上层的选中项：去掉位于其它选中文件夹内的项
+    pub fn top_level(&self) -> Vec<PathBuf> {
+        self.paths
+            .iter()
+            .filter(|path| !self.paths.iter().any(|other| other != *path && path.starts_with(other)))
+            .cloned()
+            .collect()
+    }
+
+    /// 重命名或移动后更新选中项
+    pub fn remap(&mut self, from: &Path, to: &Path) {
+        for path in self.paths.iter_mut().chain(self.anchor.as_mut()) {
+            if let Some(renamed) = rename_path(path, from, to) {
+                *path = renamed;
+            }
+        }
+    }
+
+    /// 去掉不再满足条件（如已删除、不在工作区中）的项
+    pub fn retain(&mut self, mut keep: impl FnMut(&Path) -> bool) {
+        self.paths.retain(|path| keep(path));
+        if self.anchor.as_deref().is_some_and(|anchor| !keep(anchor)) {
+            self.anchor = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_range_siblings_and_top_level() {
+        let visible: Vec<PathBuf> = ["/p/a", "/p/a/x.t", "/p/a/y.t", "/p/b.t", "/p/c.t"].iter().map(PathBuf::from).collect();
+        let mut selection = TreeSelection::default();
+        selection.select_only(Path::new("/p/a/x.t"));
+        selection.extend_to(&visible, Path::new("/p/b.t"));
+        assert_eq!(selection.paths, &visible[1..4]);
+
+        selection.toggle(Path::new("/p/a/y.t"));
+        assert_eq!(selection.len(), 2);
+        // 锚点移到最后一次 Ctrl 点击的项
+        selection.extend_to(&visible, Path::new("/p/c.t"));
+        assert_eq!(selection.paths, &visible[2..5]);
+
+        selection.select_siblings(&visible, Path::new("/p/b.t"));
+        assert_eq!(selection.paths, &[visible[0].clone(), visible[3].clone(), visible[4].clone()]);
+
+        selection.toggle(Path::new("/p/a/x.t"));
+        assert_eq!(selection.top_level(), vec![visible[0].clone(), visible[3].clone(), visible[4].clone()]);
+
+        selection.remap(Path::new("/p/a"), Path::new("/p/d"));
+        assert!(selection.contains(Path::new("/p/d/x.t")));
+        selection.retain(|path| !path.starts_with("/p/d"));
+        assert_eq!(selection.len(), 2);
+    }
+}
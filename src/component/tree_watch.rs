@@ -0,0 +1,120 @@
+//! 文件树的文件系统监视：只监视根目录和已展开的目录，忽略 target/ 等大目录内部的变化，
+//! 并把一段时间内的事件合并成一批再刷新
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 内部变化不触发刷新、展开后也不监视的目录
+pub const IGNORED_DIRS: &[&str] = &["target", "node_modules", ".git"];
+
+/// 事件停止多久后刷新
+const QUIET: Duration = Duration::from_millis(200);
+/// 持续有事件时最多等待多久刷新一次
+const MAX_WAIT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeChange {
+    Changed(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+fn is_ignored_name(component: Component) -> bool {
+    match component {
+        Component::Normal(name) => name.to_str().is_some_and(|name| IGNORED_DIRS.contains(&name)),
+        _ => false,
+    }
+}
+
+/// 变化是否发生在被忽略的目录内部；被忽略的目录本身出现或消失仍会显示
+pub fn is_ignored(root: &Path, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return false;
+    };
+    let components: Vec<Component> = relative.components().collect();
+    components.len() > 1 && components[..components.len() - 1].iter().any(|c| is_ignored_name(*c))
+}
+
+/// 需要监视的目录：根目录和已展开的目录，不含被忽略的目录及其子目录
+pub fn watched_dirs(root: &Path, expanded: &HashSet<PathBuf>) -> HashSet<PathBuf> {
+    let mut dirs = HashSet::from([root.to_path_buf()]);
+    for dir in expanded {
+        let Ok(relative) = dir.strip_prefix(root) else {
+            continue;
+        };
+        if !relative.components().any(is_ignored_name) && dir.is_dir() {
+            dirs.insert(dir.clone());
+        }
+    }
+    dirs
+}
+
+/// `path` 是 `from` 或位于其下时，换成 `to` 下的对应路径
+pub fn rename_path(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    path.strip_prefix(from).ok().map(|rest| to.join(rest))
+}
+
+/// 合并短时间内的连续事件
+#[derive(Debug, Default)]
+pub struct ChangeBatch {
+    changes: Vec<TreeChange>,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl ChangeBatch {
+    pub fn push(&mut self, change: TreeChange, now: Instant) {
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+        self.changes.push(change);
+    }
+
+    /// 事件停止 [`QUIET`] 后、或距第一条事件超过 [`MAX_WAIT`] 时取出整批
+    pub fn take_ready(&mut self, now: Instant) -> Option<Vec<TreeChange>> {
+        let (first, last) = (self.first?, self.last?);
+        if now.duration_since(last) < QUIET && now.duration_since(first) < MAX_WAIT {
+            return None;
+        }
+        self.first = None;
+        self.last = None;
+        Some(std::mem::take(&mut self.changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignored_dirs_batching_and_renames() {
+        let root = Path::new("/p");
+        assert!(!is_ignored(root, Path::new("/p/target")));
+        assert!(is_ignored(root, Path::new("/p/target/debug/app")));
+        assert!(is_ignored(root, Path::new("/p/web/node_modules/x/index.js")));
+        assert!(!is_ignored(root, Path::new("/p/src/main.rs")));
+
+        let expanded = HashSet::from([PathBuf::from("/p/target"), PathBuf::from("/p/target/debug")]);
+        assert_eq!(watched_dirs(root, &expanded), HashSet::from([root.to_path_buf()]));
+
+        assert_eq!(
+            rename_path(Path::new("/p/a/b.rs"), Path::new("/p/a"), Path::new("/p/c")),
+            Some(PathBuf::from("/p/c/b.rs"))
+        );
+        assert_eq!(rename_path(Path::new("/p/ab"), Path::new("/p/a"), Path::new("/p/c")), None);
+
+        let mut batch = ChangeBatch::default();
+        let start = Instant::now();
+        assert_eq!(batch.take_ready(start), None);
+        batch.push(TreeChange::Changed(PathBuf::from("/p/a")), start);
+        batch.push(TreeChange::Changed(PathBuf::from("/p/b")), start + Duration::from_millis(100));
+        assert_eq!(batch.take_ready(start + Duration::from_millis(150)), None);
+        assert_eq!(batch.take_ready(start + Duration::from_millis(300)).map(|b| b.len()), Some(2));
+        assert_eq!(batch.take_ready(start + Duration::from_millis(400)), None);
+
+        // 持续不断的事件也会定期刷新
+        for ms in (0..1100).step_by(100) {
+            batch.push(TreeChange::Changed(PathBuf::from("/p/a")), start + Duration::from_millis(ms));
+        }
+        assert!(batch.take_ready(start + Duration::from_millis(1050)).is_some());
+    }
+}
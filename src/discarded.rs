@@ -0,0 +1,63 @@
+//! 关闭时丢弃的未保存修改：关闭有修改的标签后保留其编辑状态（内容和撤销历史），
+//! 本次运行期间可以用“恢复丢弃的修改”找回。只保存在内存中，退出程序即丢失
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// 最多保留的条数，超出时丢弃最早关闭的
+const CAPACITY: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscardedTab<T> {
+    pub path: PathBuf,
+    pub state: T,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscardedTabs<T> {
+    /// 最近关闭的在前
+    entries: VecDeque<DiscardedTab<T>>,
+}
+
+impl<T> Default for DiscardedTabs<T> {
+    fn default() -> Self {
+        Self { entries: VecDeque::new() }
+    }
+}
+
+impl<T> DiscardedTabs<T> {
+    /// 同一文件再次关闭时只保留最新的状态
+    pub fn push(&mut self, path: PathBuf, state: T) {
+        self.entries.retain(|entry| entry.path != path);
+        self.entries.push_front(DiscardedTab { path, state });
+        self.entries.truncate(CAPACITY);
+    }
+
+    /// 取出最近关闭的一项
+    pub fn pop(&mut self) -> Option<DiscardedTab<T>> {
+        self.entries.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_latest_entries_per_file() {
+        let mut discarded = DiscardedTabs::default();
+        for i in 0..CAPACITY + 2 {
+            discarded.push(PathBuf::from(format!("/p/{}.t", i)), i);
+        }
+        discarded.push(PathBuf::from("/p/5.t"), 99);
+        assert_eq!(discarded.entries.len(), CAPACITY);
+        assert_eq!(discarded.pop(), Some(DiscardedTab { path: PathBuf::from("/p/5.t"), state: 99 }));
+        assert_eq!(discarded.pop().map(|entry| entry.state), Some(CAPACITY + 1));
+        // 最早关闭的两项已被丢弃
+        assert!(!discarded.entries.iter().any(|entry| entry.state < 2));
+    }
+}
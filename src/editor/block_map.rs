@@ -40,6 +40,12 @@ impl BlockMap {
         }
     }
 
+    /// 该行（去掉首尾空白后）是否以块起始关键字开头，例如 "如果"
+    pub fn opens_block(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        self.pairs.iter().any(|(start, _)| trimmed.starts_with(start.as_str()))
+    }
+
     pub fn update(&mut self, text: &Rope, grammar_json: &str) {
         // Update pairs if grammar changed
         if self.last_grammar_ptr != grammar_json.as_ptr() {
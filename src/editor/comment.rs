@@ -0,0 +1,177 @@
+//! 行注释 / 块注释的切换。只计算编辑，不依赖视图，返回的区间均基于原始内容
+
+use ropey::Rope;
+use std::ops::{Range, RangeInclusive};
+
+use crate::editor::grammar::grammar_name_for_path;
+
+/// 一种语言的注释记号
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommentTokens {
+    pub line: Option<&'static str>,
+    pub block: Option<(&'static str, &'static str)>,
+}
+
+impl CommentTokens {
+    const C_LIKE: Self = Self { line: Some("//"), block: Some(("/*", "*/")) };
+    const HASH: Self = Self { line: Some("#"), block: None };
+    const MARKUP: Self = Self { line: None, block: Some(("<!--", "-->")) };
+
+    /// 按语法名称（grammar.rs 中各语法的 `name`）查找注释记号
+    pub fn for_grammar(name: &str) -> Option<Self> {
+        match name {
+            "CPP" | "Rust" | "JavaScript" | "Java" | "TypeScript" | "JSON" | "tiecode" => {
+                Some(Self::C_LIKE)
+            }
+            "CSS" => Some(Self { line: None, block: Some(("/*", "*/")) }),
+            "Python" | "Shell" | "TOML" | "YAML" | "CMake" => Some(Self::HASH),
+            "HTML" | "Markdown" => Some(Self::MARKUP),
+            _ => None,
+        }
+    }
+
+    /// 使用与语法高亮相同的扩展名匹配规则
+    pub fn for_path(path: &str) -> Option<Self> {
+        grammar_name_for_path(path).and_then(|name| Self::for_grammar(&name))
+    }
+}
+
+/// 选区覆盖的行；选区恰好止于某行行首时不包含该行
+pub fn selection_lines(content: &Rope, range: Range<usize>) -> RangeInclusive<usize> {
+    let first = content.byte_to_line(range.start);
+    let mut last = content.byte_to_line(range.end);
+    if last > first && content.line_to_byte(last) == range.end {
+        last -= 1;
+    }
+    first..=last
+}
+
+/// 切换 `lines` 的行注释：全部非空行都已注释时取消注释，否则在最小公共缩进处加注释。
+/// 语言没有行注释时逐行包上块注释
+pub fn toggle_line_comment_edits(
+    content: &Rope,
+    lines: RangeInclusive<usize>,
+    tokens: CommentTokens,
+) -> Vec<(Range<usize>, String)> {
+    let (open, close) = match (tokens.line, tokens.block) {
+        (Some(prefix), _) => (prefix, None),
+        (None, Some((open, close))) => (open, Some(close)),
+        (None, None) => return Vec::new(),
+    };
+
+    let rows: Vec<(usize, String)> = lines
+        .filter(|&line| line < content.len_lines())
+        .map(|line| {
+            let text = content.line(line).to_string();
+            (content.line_to_byte(line), text.trim_end_matches(['\n', '\r']).to_string())
+        })
+        .filter(|(_, text)| !text.trim().is_empty())
+        .collect();
+    if rows.is_empty() {
+        return Vec::new();
+    }
+
+    let is_commented = |text: &str| {
+        let text = text.trim();
+        text.starts_with(open)
+            && close.is_none_or(|close| {
+                text.len() >= open.len() + close.len() && text.ends_with(close)
+            })
+    };
+    let indent_of = |text: &str| text.len() - text.trim_start().len();
+
+    let mut edits = Vec::new();
+    if rows.iter().all(|(_, text)| is_commented(text)) {
+        for (start, text) in &rows {
+            let indent = indent_of(text);
+            let mut open_end = indent + open.len();
+            if text[open_end..].starts_with(' ') {
+                open_end += 1;
+            }
+            edits.push((start + indent..start + open_end, String::new()));
+            if let Some(close) = close {
+                let end = text.trim_end().len();
+                let mut close_start = end - close.len();
+                if close_start > open_end && text[..close_start].ends_with(' ') {
+                    close_start -= 1;
+                }
+                edits.push((start + close_start..start + end, String::new()));
+            }
+        }
+    } else {
+        let indent = rows.iter().map(|(_, text)| indent_of(text)).min().unwrap_or(0);
+        for (start, text) in &rows {
+            edits.push((start + indent..start + indent, format!("{} ", open)));
+            if let Some(close) = close {
+                let end = start + text.trim_end().len();
+                edits.push((end..end, format!(" {}", close)));
+            }
+        }
+    }
+    edits
+}
+
+/// 切换 `range` 的块注释；空选区作用于光标所在行去掉首尾空白后的内容。
+/// 语言没有块注释时退回到行注释
+pub fn toggle_block_comment_edits(
+    content: &Rope,
+    range: Range<usize>,
+    tokens: CommentTokens,
+) -> Vec<(Range<usize>, String)> {
+    let Some((open, close)) = tokens.block else {
+        return toggle_line_comment_edits(content, selection_lines(content, range), tokens);
+    };
+
+    let range = if range.is_empty() {
+        let line = content.byte_to_line(range.start);
+        let start = content.line_to_byte(line);
+        let text = content.line(line).to_string();
+        start..start + text.trim_end_matches(['\n', '\r']).len()
+    } else {
+        range
+    };
+    let text = content.byte_slice(range.clone()).to_string();
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Vec::new();
+    }
+
+    let start = range.start + (text.len() - text.trim_start().len());
+    let end = start + trimmed.len();
+    if trimmed.len() >= open.len() + close.len()
+        && trimmed.starts_with(open)
+        && trimmed.ends_with(close)
+    {
+        let mut open_end = open.len();
+        if trimmed[open_end..].starts_with(' ') {
+            open_end += 1;
+        }
+        let mut close_start = trimmed.len() - close.len();
+        if close_start > open_end && trimmed[..close_start].ends_with(' ') {
+            close_start -= 1;
+        }
+        vec![
+            (start..start + open_end, String::new()),
+            (start + close_start..end, String::new()),
+        ]
+    } else {
+        vec![
+            (start..start, format!("{} ", open)),
+            (end..end, format!(" {}", close)),
+        ]
+    }
+}
+
+/// 把原始内容中的偏移映射到应用 `edits` 之后的位置；
+/// 插入点恰在偏移处时偏移随之后移，落在被删除区间内时移到区间起点
+pub fn map_offset(offset: usize, edits: &[(Range<usize>, String)]) -> usize {
+    let mut mapped = offset as isize;
+    for (range, text) in edits {
+        if range.end <= offset {
+            mapped += text.len() as isize - range.len() as isize;
+        } else if range.start < offset {
+            mapped -= (offset - range.start) as isize;
+        }
+    }
+    mapped.max(0) as usize
+}
@@ -40,3 +40,158 @@ impl CompletionKind {
         }
     }
 }
+
+/// 输入后立即请求成员补全的字符；较长的在前，`：：` 不会只认出一个 `：`
+pub const TRIGGER_CHARACTERS: &[&str] = &["::", "：：", "."];
+
+/// 补全前缀中可以出现的字符
+pub fn is_prefix_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '#'
+}
+
+/// 光标所在行光标前的文本 `before` 中补全前缀的起点（字节），以及紧挨在前缀之前的触发字符
+pub fn completion_context(before: &str) -> (usize, Option<&'static str>) {
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|&(_, ch)| is_prefix_char(ch))
+        .last()
+        .map_or(before.len(), |(index, _)| index);
+    let trigger = TRIGGER_CHARACTERS.iter().copied().find(|trigger| before[..start].ends_with(trigger));
+    (start, trigger)
+}
+
+/// 由触发字符打开的补全：记下触发字符之后的位置和语言服务给出的全部项，之后的输入只在本地筛选
+pub struct TriggeredCompletion {
+    pub uri: String,
+    /// 触发字符之后的字节偏移，即成员名的起点
+    pub position: usize,
+    /// 语言服务尚未回复时为 None
+    pub items: Option<Vec<CompletionItem>>,
+}
+
+/// `items` 中以 `prefix` 开头的项
+pub fn narrow(items: &[CompletionItem], prefix: &str) -> Vec<CompletionItem> {
+    items.iter().filter(|item| item.label.starts_with(prefix)).cloned().collect()
+}
+
+/// 补全菜单同时显示的项数，更多时在菜单内滚动
+pub const MAX_VISIBLE_ITEMS: usize = 10;
+/// 补全菜单滚动条的宽度；按下的判定区域更宽一些，方便拖动
+const SCROLLBAR_WIDTH: Pixels = px(4.0);
+const SCROLLBAR_HIT_WIDTH: Pixels = px(10.0);
+
+/// 上次绘制的补全菜单的位置，鼠标的点击、悬停和滚轮据此命中
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompletionMenuLayout {
+    pub bounds: Bounds<Pixels>,
+    pub item_height: Pixels,
+    /// 显示在第一行的项
+    pub scroll: usize,
+    pub total: usize,
+}
+
+impl CompletionMenuLayout {
+    pub fn visible_count(&self) -> usize {
+        self.total.min(MAX_VISIBLE_ITEMS)
+    }
+
+    pub fn max_scroll(&self) -> usize {
+        self.total - self.visible_count()
+    }
+
+    /// `position` 处的项
+    pub fn item_at(&self, position: Point<Pixels>) -> Option<usize> {
+        if !self.bounds.contains(&position) || self.item_height <= px(0.0) {
+            return None;
+        }
+        let row = ((position.y - self.bounds.top()) / self.item_height) as usize;
+        (row < self.visible_count()).then_some(self.scroll + row).filter(|&index| index < self.total)
+    }
+
+    /// 滚动条滑块；项数不超过一屏时没有滚动条
+    pub fn thumb_bounds(&self) -> Option<Bounds<Pixels>> {
+        if self.total <= MAX_VISIBLE_ITEMS {
+            return None;
+        }
+        let track = self.bounds.size.height;
+        let height = track * (self.visible_count() as f32 / self.total as f32);
+        let y = self.bounds.top() + (track - height) * (self.scroll.min(self.max_scroll()) as f32 / self.max_scroll() as f32);
+        Some(Bounds::new(point(self.bounds.right() - SCROLLBAR_WIDTH, y), size(SCROLLBAR_WIDTH, height)))
+    }
+
+    /// `position` 落在可以开始拖动的滑块上
+    pub fn on_thumb(&self, position: Point<Pixels>) -> bool {
+        self.thumb_bounds().is_some_and(|thumb| {
+            self.bounds.contains(&position)
+                && position.x >= self.bounds.right() - SCROLLBAR_HIT_WIDTH
+                && position.y >= thumb.top()
+                && position.y <= thumb.bottom()
+        })
+    }
+
+    /// 从滚动位置 `start` 开始把滑块拖动 `delta_y` 后的滚动位置
+    pub fn scroll_for_drag(&self, start: usize, delta_y: Pixels) -> usize {
+        let Some(thumb) = self.thumb_bounds() else {
+            return 0;
+        };
+        let travel = self.bounds.size.height - thumb.size.height;
+        if travel <= px(0.0) {
+            return start;
+        }
+        let scroll = start as f32 + delta_y / travel * self.max_scroll() as f32;
+        scroll.round().clamp(0.0, self.max_scroll() as f32) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{completion_context, narrow, CompletionItem, CompletionKind, CompletionMenuLayout};
+    use gpui::{point, px, size, Bounds};
+
+    #[test]
+    fn test_completion_menu_hit_testing() {
+        let menu = CompletionMenuLayout {
+            bounds: Bounds::new(point(px(100.0), px(50.0)), size(px(250.0), px(200.0))),
+            item_height: px(20.0),
+            scroll: 5,
+            total: 25,
+        };
+        assert_eq!(menu.item_at(point(px(120.0), px(51.0))), Some(5));
+        assert_eq!(menu.item_at(point(px(120.0), px(249.0))), Some(14));
+        assert_eq!(menu.item_at(point(px(90.0), px(60.0))), None);
+
+        // 滑块高度按一屏占比，位置按滚动占比；拖到底时停在最后一屏
+        let thumb = menu.thumb_bounds().unwrap();
+        assert_eq!((thumb.top(), thumb.size.height), (px(90.0), px(80.0)));
+        assert!(menu.on_thumb(point(px(342.0), px(100.0))));
+        assert!(!menu.on_thumb(point(px(342.0), px(60.0))));
+        assert!(!menu.on_thumb(point(px(200.0), px(100.0))));
+        assert_eq!(menu.scroll_for_drag(5, px(80.0)), 15);
+        assert_eq!(menu.scroll_for_drag(5, px(-24.0)), 2);
+        assert_eq!(menu.scroll_for_drag(5, px(-500.0)), 0);
+        assert_eq!(menu.scroll_for_drag(5, px(500.0)), 15);
+
+        let short = CompletionMenuLayout { total: 3, scroll: 0, ..menu };
+        assert_eq!(short.thumb_bounds(), None);
+        assert_eq!(short.item_at(point(px(120.0), px(119.0))), None);
+        assert_eq!(short.item_at(point(px(120.0), px(99.0))), Some(2));
+    }
+
+    #[test]
+    fn test_completion_context_stops_at_trigger_characters() {
+        assert_eq!(completion_context("    变量 列表 = 列表"), (20, None));
+        assert_eq!(completion_context("列表."), (7, Some(".")));
+        assert_eq!(completion_context("列表.添"), (7, Some(".")));
+        assert_eq!(completion_context("类名::"), (8, Some("::")));
+        assert_eq!(completion_context("类名：：成"), (12, Some("：：")));
+        assert_eq!(completion_context("a = b"), (4, None));
+        assert_eq!(completion_context(""), (0, None));
+
+        let item = |label: &str| CompletionItem { label: label.to_string(), kind: CompletionKind::Function, detail: String::new() };
+        let items = vec![item("添加项目"), item("删除项目"), item("添加全部")];
+        let labels: Vec<_> = narrow(&items, "添加").into_iter().map(|item| item.label).collect();
+        assert_eq!(labels, vec!["添加项目", "添加全部"]);
+        assert_eq!(narrow(&items, "").len(), 3);
+    }
+}
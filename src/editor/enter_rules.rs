@@ -0,0 +1,157 @@
+//! 回车补全块结构：光标前的行匹配规则时，回车后插入规则给出的结构，例如结绳中
+//! `方法 启动方法()` 回车后补上 `结束 方法`。规则来自语法文件的 `enterRules` 和插件清单，
+//! 可在项目设置中关闭：
+//!
+//! ```json
+//! { "editor": { "autoCloseBlocks": false } }
+//! ```
+
+use log::warn;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+use crate::lsp::tiec::settings::PROJECT_SETTINGS_FILE;
+
+/// 插入内容中光标所在的位置
+pub const CURSOR_MARKER: &str = "$0";
+
+/// 语法文件中的一条规则，例如 `{ "pattern": "^\\s*方法\\s", "insert": "\n\t$0\n结束 方法" }`。
+/// `insert` 从光标处插入，换行后的每行都加上当前行的缩进，行首的 `\t` 换成编辑器的缩进
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EnterRuleSpec {
+    pub pattern: String,
+    pub insert: String,
+    /// 下一行已含有它时不插入；默认为 `insert` 的最后一行
+    #[serde(default)]
+    pub closer: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnterRule {
+    pattern: Regex,
+    insert: String,
+    closer: String,
+}
+
+impl EnterRule {
+    pub fn compile(spec: &EnterRuleSpec) -> Result<Self, regex::Error> {
+        let closer = spec
+            .closer
+            .clone()
+            .or_else(|| spec.insert.lines().rev().map(str::trim).find(|line| !line.is_empty()).map(str::to_string))
+            .unwrap_or_default();
+        Ok(Self { pattern: Regex::new(&spec.pattern)?, insert: spec.insert.clone(), closer })
+    }
+}
+
+/// 编译规则，跳过写错的正则表达式
+pub fn compile_rules<'a>(specs: impl IntoIterator<Item = &'a EnterRuleSpec>) -> Vec<EnterRule> {
+    specs
+        .into_iter()
+        .filter_map(|spec| {
+            EnterRule::compile(spec).map_err(|err| warn!("Invalid enter rule {:?}: {}", spec.pattern, err)).ok()
+        })
+        .collect()
+}
+
+/// 语法文件中的 `enterRules`
+pub fn grammar_rules(grammar_json: &str) -> Vec<EnterRuleSpec> {
+    serde_json::from_str::<Value>(grammar_json)
+        .ok()
+        .and_then(|mut value| value.get_mut("enterRules").map(Value::take))
+        .and_then(|rules| serde_json::from_value(rules).ok())
+        .unwrap_or_default()
+}
+
+/// 回车后插入的文本，`cursor` 为插入后光标在其中的字节位置
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expansion {
+    pub text: String,
+    pub cursor: usize,
+}
+
+/// 在行中 `before` 与 `after` 之间回车时按第一条匹配的规则展开；光标后还有内容，
+/// 或下一行已含有结束部分时不展开
+pub fn expand(rules: &[EnterRule], before: &str, after: &str, next_line: Option<&str>) -> Option<Expansion> {
+    if !after.trim().is_empty() {
+        return None;
+    }
+    let rule = rules.iter().find(|rule| rule.pattern.is_match(before))?;
+    if next_line.is_some_and(|line| !rule.closer.is_empty() && line.contains(&rule.closer)) {
+        return None;
+    }
+    let indent: String = before.chars().take_while(|ch| *ch == ' ' || *ch == '\t').collect();
+    let unit = if indent.contains('\t') { "\t" } else { super::INDENT_UNIT };
+
+    let mut text = String::new();
+    for (i, line) in rule.insert.split('\n').enumerate() {
+        if i > 0 {
+            text.push('\n');
+            text.push_str(&indent);
+            let tabs = line.len() - line.trim_start_matches('\t').len();
+            text.push_str(&unit.repeat(tabs));
+            text.push_str(&line[tabs..]);
+        } else {
+            text.push_str(line);
+        }
+    }
+    let cursor = text.find(CURSOR_MARKER).unwrap_or(text.len());
+    text = text.replacen(CURSOR_MARKER, "", 1);
+    Some(Expansion { text, cursor })
+}
+
+/// 项目设置中是否打开了回车补全块结构，默认打开
+pub fn load_enabled(project_root: &Path) -> bool {
+    let Ok(content) = std::fs::read_to_string(project_root.join(PROJECT_SETTINGS_FILE)) else {
+        return true;
+    };
+    let (enabled, warnings) = parse_enabled(&content);
+    for warning in warnings {
+        warn!("{}", warning);
+    }
+    enabled
+}
+
+/// 解析设置文件中的 `editor.autoCloseBlocks`，同时返回面向用户的警告
+pub fn parse_enabled(content: &str) -> (bool, Vec<String>) {
+    let value = serde_json::from_str::<Value>(content).ok();
+    match value.as_ref().and_then(|value| value.get("editor")?.get("autoCloseBlocks")) {
+        None => (true, Vec::new()),
+        Some(Value::Bool(enabled)) => (*enabled, Vec::new()),
+        Some(_) => (true, vec!["editor.autoCloseBlocks 应为 true 或 false".to_string()]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expands_block_skeleton() {
+        let grammar = r#"{ "name": "结绳", "enterRules": [
+            { "pattern": "^\\s*方法\\s+\\S", "insert": "\n\t$0\n结束 方法" },
+            { "pattern": "^\\s*如果\\s", "insert": "\n\t$0\n结束 如果", "closer": "结束 如果" },
+            { "pattern": "(", "insert": "" }
+        ] }"#;
+        let rules = compile_rules(&grammar_rules(grammar));
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].closer, "结束 方法");
+
+        let expansion = expand(&rules, "    方法 启动方法()", "", Some("")).unwrap();
+        assert_eq!(expansion.text, "\n        \n    结束 方法");
+        assert_eq!(expansion.cursor, "\n        ".len());
+        let expansion = expand(&rules, "\t如果 真 则", "  ", None).unwrap();
+        assert_eq!(expansion.text, "\n\t\t\n\t结束 如果");
+
+        // 下一行已有结束部分、光标后还有内容或不匹配时照常换行
+        assert_eq!(expand(&rules, "方法 启动方法()", "", Some("结束 方法")), None);
+        assert_eq!(expand(&rules, "方法 启动", "方法()", None), None);
+        assert_eq!(expand(&rules, "变量 方法数 : 整数", "", None), None);
+
+        assert_eq!(parse_enabled(r#"{ "editor": { "autoCloseBlocks": false } }"#), (false, Vec::new()));
+        assert_eq!(parse_enabled("{}"), (true, Vec::new()));
+        assert!(!parse_enabled(r#"{ "editor": { "autoCloseBlocks": 1 } }"#).1.is_empty());
+    }
+}
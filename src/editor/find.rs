@@ -0,0 +1,145 @@
+//! 编辑器内查找：查找栏的输入、全部匹配及当前匹配。
+//! 匹配在输入停顿后于后台重新计算；其间文档被编辑时先平移已有的匹配，避免标记错位
+
+use std::ops::Range;
+
+/// 匹配数量上限，超过时不再继续查找，提示细化搜索内容
+pub const MAX_MATCHES: usize = 10_000;
+
+/// 查找文本中所有不重叠的匹配，ASCII 字母不区分大小写；第二项表示是否因达到上限而截断
+pub fn find_all(text: &str, query: &str) -> (Vec<Range<usize>>, bool) {
+    let mut matches = Vec::new();
+    if query.is_empty() {
+        return (matches, false);
+    }
+    let (haystack, needle) = (text.as_bytes(), query.as_bytes());
+    let first = needle[0];
+    let mut index = 0;
+    while index + needle.len() <= haystack.len() {
+        // 查询以完整字符开头，首字节相同时匹配必然从字符边界开始
+        if haystack[index].eq_ignore_ascii_case(&first)
+            && haystack[index..index + needle.len()].eq_ignore_ascii_case(needle)
+        {
+            if matches.len() == MAX_MATCHES {
+                return (matches, true);
+            }
+            matches.push(index..index + needle.len());
+            index += needle.len();
+        } else {
+            index += 1;
+        }
+    }
+    (matches, false)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FindState {
+    pub query: String,
+    /// 输入焦点在查找栏上；此时编辑区变暗以突出匹配
+    pub focused: bool,
+    pub matches: Vec<Range<usize>>,
+    pub capped: bool,
+    pub current: Option<usize>,
+}
+
+impl FindState {
+    /// 正在输入查询时编辑区变暗；清空查询后立即恢复
+    pub fn dims_editor(&self) -> bool {
+        self.focused && !self.query.is_empty()
+    }
+
+    pub fn set_matches(&mut self, matches: Vec<Range<usize>>, capped: bool, cursor: usize) {
+        self.matches = matches;
+        self.capped = capped;
+        self.current = self.index_at_or_after(cursor);
+    }
+
+    fn index_at_or_after(&self, offset: usize) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let index = self.matches.partition_point(|m| m.start < offset);
+        Some(if index == self.matches.len() { 0 } else { index })
+    }
+
+    /// 移到下一个（`forward`）或上一个匹配，首尾循环
+    pub fn step(&mut self, forward: bool) -> Option<Range<usize>> {
+        let len = self.matches.len();
+        if len == 0 {
+            return None;
+        }
+        let next = match self.current {
+            Some(current) if forward => (current + 1) % len,
+            Some(current) => (current + len - 1) % len,
+            None => 0,
+        };
+        self.current = Some(next);
+        Some(self.matches[next].clone())
+    }
+
+    /// 文档中 `edit` 被替换为 `new_len` 字节的文本：与之相交的匹配移除，其后的匹配平移
+    pub fn shift_for_edit(&mut self, edit: Range<usize>, new_len: usize) {
+        let removed = edit.end - edit.start;
+        self.matches.retain(|m| m.end <= edit.start || m.start >= edit.end);
+        for m in self.matches.iter_mut().filter(|m| m.start >= edit.end) {
+            m.start = m.start - removed + new_len;
+            m.end = m.end - removed + new_len;
+        }
+        self.current = self.current.filter(|&current| current < self.matches.len());
+    }
+
+    /// 查找栏上的计数，如 `3/120`
+    pub fn label(&self) -> String {
+        if self.query.is_empty() {
+            String::new()
+        } else if self.capped {
+            format!("{}+", group_thousands(MAX_MATCHES))
+        } else if self.matches.is_empty() {
+            "无结果".to_string()
+        } else {
+            let current = self.current.map(|c| c + 1).unwrap_or(0);
+            format!("{}/{}", current, self.matches.len())
+        }
+    }
+}
+
+fn group_thousands(value: usize) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::new();
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(ch);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_step_and_shift_for_edit() {
+        assert_eq!(find_all("Foo foo 甲foo", "foo").0, vec![0..3, 4..7, 11..14]);
+        assert_eq!(find_all("aaaa", "aa").0, vec![0..2, 2..4]);
+        assert_eq!(find_all("甲乙甲", "甲").0, vec![0..3, 6..9]);
+        let (matches, capped) = find_all(&"x".repeat(MAX_MATCHES + 1), "x");
+        assert!(capped);
+        assert_eq!(matches.len(), MAX_MATCHES);
+
+        let mut state = FindState { query: "foo".to_string(), ..Default::default() };
+        assert_eq!(state.label(), "无结果");
+        let (matches, _) = find_all("foo foo foo", "foo");
+        state.set_matches(matches, false, 5);
+        assert_eq!(state.label(), "3/3");
+        assert_eq!(state.step(true), Some(0..3));
+        assert_eq!(state.step(false), Some(8..11));
+
+        // 在第一个匹配之后插入两个字节，并改动第二个匹配
+        state.shift_for_edit(4..5, 2);
+        assert_eq!(state.matches, vec![0..3, 9..12]);
+        state.capped = true;
+        assert_eq!(state.label(), "10,000+");
+    }
+}
@@ -0,0 +1,443 @@
+//! 代码折叠：按花括号、缩进或结绳的块关键字算出可折叠的区域，记下折起的区域，
+//! 并在文档行与去掉折叠行之后的显示行之间换算
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use ropey::Rope;
+
+/// 可折叠的区域。首行 `start` 始终显示，折起时隐藏 `start + 1..end`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FoldRegion {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl FoldRegion {
+    pub fn hidden(&self) -> Range<usize> {
+        self.start + 1..self.end
+    }
+}
+
+/// 按缩进折叠的语言
+pub fn uses_indentation(uri: &str) -> bool {
+    let extension = uri.rsplit('.').next().unwrap_or_default().to_ascii_lowercase();
+    matches!(extension.as_str(), "py" | "pyw" | "yaml" | "yml")
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScanState {
+    Code,
+    Quoted(char),
+    LineComment,
+    BlockComment,
+}
+
+/// 成对的 `{}` 构成的区域，跳过字符串和注释中的括号；右括号所在行保持显示。
+/// `single_quoted_strings` 为假时单引号不当作字符串（例如 Rust 的生命周期）
+pub fn brace_regions(content: &Rope, single_quoted_strings: bool) -> Vec<FoldRegion> {
+    let mut regions = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    let mut state = ScanState::Code;
+    for (line, text) in content.lines().enumerate() {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match state {
+                ScanState::Code => match c {
+                    '"' => state = ScanState::Quoted(c),
+                    '\'' if single_quoted_strings => state = ScanState::Quoted(c),
+                    '/' if chars.peek() == Some(&'/') => state = ScanState::LineComment,
+                    '/' if chars.peek() == Some(&'*') => {
+                        chars.next();
+                        state = ScanState::BlockComment;
+                    }
+                    '{' => open.push(line),
+                    '}' => {
+                        if let Some(start) = open.pop() {
+                            if line >= start + 2 {
+                                regions.push(FoldRegion { start, end: line });
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                ScanState::Quoted(quote) => {
+                    if c == '\\' {
+                        chars.next();
+                    } else if c == quote {
+                        state = ScanState::Code;
+                    }
+                }
+                ScanState::LineComment => {}
+                ScanState::BlockComment => {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        state = ScanState::Code;
+                    }
+                }
+            }
+        }
+        // 未闭合的字符串和行注释都止于行尾
+        if state != ScanState::BlockComment {
+            state = ScanState::Code;
+        }
+    }
+    normalize(regions)
+}
+
+/// 按缩进划分的区域：缩进更深的后续行属于上一个缩进较浅的行，区域止于最后一个非空行
+pub fn indent_regions(content: &Rope) -> Vec<FoldRegion> {
+    let mut regions = Vec::new();
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut last_nonblank = 0;
+    for (line, text) in content.lines().enumerate() {
+        let Some(indent) = indent_width(text) else {
+            continue;
+        };
+        while let Some(&(open_indent, start)) = open.last() {
+            if open_indent < indent {
+                break;
+            }
+            open.pop();
+            if last_nonblank > start {
+                regions.push(FoldRegion { start, end: last_nonblank + 1 });
+            }
+        }
+        open.push((indent, line));
+        last_nonblank = line;
+    }
+    for (_, start) in open {
+        if last_nonblank > start {
+            regions.push(FoldRegion { start, end: last_nonblank + 1 });
+        }
+    }
+    normalize(regions)
+}
+
+/// 结绳的块关键字区域（起始行到结束行），由 `BlockMap` 算出
+pub fn scope_regions(scopes: &HashMap<usize, usize>) -> Vec<FoldRegion> {
+    normalize(
+        scopes
+            .iter()
+            .filter(|(&start, &end)| end >= start + 2)
+            .map(|(&start, &end)| FoldRegion { start, end })
+            .collect(),
+    )
+}
+
+/// 行首空白的宽度，制表符算 4 列；空白行为 None
+fn indent_width(text: ropey::RopeSlice) -> Option<usize> {
+    let mut width = 0;
+    for c in text.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += 4,
+            '\r' | '\n' => return None,
+            _ => return Some(width),
+        }
+    }
+    None
+}
+
+/// 按首行排序，同一行起始的多个区域只保留最大的
+fn normalize(mut regions: Vec<FoldRegion>) -> Vec<FoldRegion> {
+    regions.sort_by_key(|region| (region.start, std::cmp::Reverse(region.end)));
+    regions.dedup_by_key(|region| region.start);
+    regions
+}
+
+/// 文档的可折叠区域和其中折起的部分
+#[derive(Clone, Debug, Default)]
+pub struct Folds {
+    regions: Vec<FoldRegion>,
+    /// 折起的区域，首行到结束行
+    folded: BTreeMap<usize, usize>,
+}
+
+impl Folds {
+    /// 换上重新计算的区域；首行不再是区域起点的折叠随之展开
+    pub fn set_regions(&mut self, regions: Vec<FoldRegion>) {
+        self.folded = std::mem::take(&mut self.folded)
+            .into_keys()
+            .filter_map(|start| {
+                let index = regions.binary_search_by_key(&start, |region| region.start).ok()?;
+                Some((start, regions[index].end))
+            })
+            .collect();
+        self.regions = regions;
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+        self.folded.clear();
+    }
+
+    pub fn region_at(&self, line: usize) -> Option<FoldRegion> {
+        let index = self.regions.binary_search_by_key(&line, |region| region.start).ok()?;
+        Some(self.regions[index])
+    }
+
+    pub fn is_folded(&self, line: usize) -> bool {
+        self.folded.contains_key(&line)
+    }
+
+    /// 折起或展开从 `line` 开始的区域；该行不是区域起点时返回假
+    pub fn toggle(&mut self, line: usize) -> bool {
+        let Some(region) = self.region_at(line) else {
+            return false;
+        };
+        if self.folded.remove(&line).is_none() {
+            self.folded.insert(region.start, region.end);
+        }
+        true
+    }
+
+    pub fn fold_all(&mut self) {
+        self.folded = self.regions.iter().map(|region| (region.start, region.end)).collect();
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.folded.clear();
+    }
+
+    /// 折起第 `level` 层（最外层为 1）的全部区域，其他层保持不变
+    pub fn fold_level(&mut self, level: usize) {
+        let mut enclosing: Vec<usize> = Vec::new();
+        for region in &self.regions {
+            while enclosing.last().is_some_and(|&end| end <= region.start) {
+                enclosing.pop();
+            }
+            enclosing.push(region.end);
+            if enclosing.len() == level {
+                self.folded.insert(region.start, region.end);
+            }
+        }
+    }
+
+    /// 展开隐藏了 `line` 的所有区域，有展开时返回真
+    pub fn unfold_containing(&mut self, line: usize) -> bool {
+        let before = self.folded.len();
+        self.folded.retain(|&start, &mut end| !(start < line && line < end));
+        self.folded.len() != before
+    }
+
+    /// 文档第 `start_line` 到 `end_line` 行被替换为含 `inserted_breaks` 个换行的文本。
+    /// 编辑之后的区域随之平移；编辑落在折起的行内或跨过其边界时展开，有展开时返回真
+    pub fn edit(&mut self, start_line: usize, end_line: usize, inserted_breaks: usize) -> bool {
+        let delta = inserted_breaks as isize - (end_line - start_line) as isize;
+        let shift = |line: usize| line.saturating_add_signed(delta);
+        // 只改首行本身不影响折叠
+        let on_header = |start: usize| start_line == start && end_line == start && inserted_breaks == 0;
+        let before = self.folded.len();
+        self.folded = std::mem::take(&mut self.folded)
+            .into_iter()
+            .filter_map(|(start, end)| {
+                if end_line < start {
+                    Some((shift(start), shift(end)))
+                } else if start_line >= end || on_header(start) {
+                    Some((start, end))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        // 区域在下次重新计算前保持与文档对齐
+        self.regions.retain_mut(|region| {
+            if end_line < region.start {
+                *region = FoldRegion { start: shift(region.start), end: shift(region.end) };
+            } else if region.start <= start_line && end_line < region.end {
+                region.end = shift(region.end);
+            } else if start_line < region.end {
+                return false;
+            }
+            region.end >= region.start + 2
+        });
+        self.folded.len() != before
+    }
+
+    pub fn hidden_lines(&self) -> HiddenLines {
+        HiddenLines::new(self.folded.iter().map(|(&start, &end)| FoldRegion { start, end }.hidden()))
+    }
+}
+
+/// 折起后隐藏的行，以及文档行与显示行之间的换算
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HiddenLines {
+    /// 排好序、互不相邻的隐藏范围
+    ranges: Vec<Range<usize>>,
+    /// 到第 i 个范围为止（含）隐藏的行数
+    hidden_through: Vec<usize>,
+    /// 第 i 个范围之后第一行所在的显示行
+    row_after: Vec<usize>,
+}
+
+impl HiddenLines {
+    pub fn new(ranges: impl IntoIterator<Item = Range<usize>>) -> Self {
+        let mut sorted: Vec<Range<usize>> = ranges.into_iter().filter(|range| !range.is_empty()).collect();
+        sorted.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(sorted.len());
+        for range in sorted {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        let mut hidden_through = Vec::with_capacity(merged.len());
+        let mut row_after = Vec::with_capacity(merged.len());
+        let mut total = 0;
+        for range in &merged {
+            total += range.len();
+            hidden_through.push(total);
+            row_after.push(range.end - total);
+        }
+        Self { ranges: merged, hidden_through, row_after }
+    }
+
+    /// 起点不大于 `line` 的范围个数
+    fn ranges_before(&self, line: usize) -> usize {
+        self.ranges.partition_point(|range| range.start <= line)
+    }
+
+    pub fn is_hidden(&self, line: usize) -> bool {
+        let index = self.ranges_before(line);
+        index > 0 && line < self.ranges[index - 1].end
+    }
+
+    /// 第 `line` 行所在的显示行；隐藏的行算作其折叠首行
+    pub fn row_of(&self, line: usize) -> usize {
+        let index = self.ranges_before(line);
+        if index == 0 {
+            return line;
+        }
+        let range = &self.ranges[index - 1];
+        if line < range.end {
+            range.start - 1 - (self.hidden_through[index - 1] - range.len())
+        } else {
+            line - self.hidden_through[index - 1]
+        }
+    }
+
+    /// 第 `row` 个显示行对应的文档行
+    pub fn line_of_row(&self, row: usize) -> usize {
+        match self.row_after.partition_point(|&start| start <= row) {
+            0 => row,
+            index => row + self.hidden_through[index - 1],
+        }
+    }
+
+    /// 共 `line_count` 行的文档去掉隐藏行后的行数
+    pub fn visible_count(&self, line_count: usize) -> usize {
+        let hidden: usize = self.ranges.iter().map(|range| range.start.min(line_count)..range.end.min(line_count)).map(|range| range.len()).sum();
+        line_count - hidden
+    }
+
+    /// 不小于 `line` 的第一个未隐藏的行
+    fn skip_hidden(&self, line: usize) -> usize {
+        let index = self.ranges_before(line);
+        if index > 0 && line < self.ranges[index - 1].end {
+            self.ranges[index - 1].end
+        } else {
+            line
+        }
+    }
+
+    /// `lines` 中未隐藏的行，整段跳过折起的部分
+    pub fn visible_in(&self, lines: Range<usize>) -> impl Iterator<Item = usize> + '_ {
+        let mut line = lines.start;
+        std::iter::from_fn(move || {
+            line = self.skip_hidden(line);
+            if line >= lines.end {
+                return None;
+            }
+            line += 1;
+            Some(line - 1)
+        })
+    }
+
+    /// `line` 之后下一个显示的行
+    pub fn next_visible(&self, line: usize) -> usize {
+        self.skip_hidden(line + 1)
+    }
+
+    /// `line` 之前上一个显示的行；已在首行时为 None
+    pub fn prev_visible(&self, line: usize) -> Option<usize> {
+        let previous = line.checked_sub(1)?;
+        let index = self.ranges_before(previous);
+        if index > 0 && previous < self.ranges[index - 1].end {
+            Some(self.ranges[index - 1].start - 1)
+        } else {
+            Some(previous)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions_from_braces_indentation_and_scopes() {
+        let source = Rope::from(
+            "fn main() {\n    let s = \"{\";\n    // }\n    if x {\n        y();\n    }\n    /* {\n    */\n}\nfn f() { }\n",
+        );
+        assert_eq!(
+            brace_regions(&source, false),
+            vec![FoldRegion { start: 0, end: 8 }, FoldRegion { start: 3, end: 5 }]
+        );
+        // Rust 生命周期的单引号不当作字符串
+        let lifetimes = Rope::from("fn f<'a>(x: &'a str) -> &'a str {\n    x\n    \n}\n");
+        assert_eq!(brace_regions(&lifetimes, false), vec![FoldRegion { start: 0, end: 3 }]);
+
+        let python = Rope::from("def f():\n    if x:\n        y()\n\n    z()\n\nclass A:\n    pass\n");
+        assert_eq!(
+            indent_regions(&python),
+            vec![FoldRegion { start: 0, end: 5 }, FoldRegion { start: 1, end: 3 }, FoldRegion { start: 6, end: 8 }]
+        );
+        assert!(uses_indentation("file:///a/b.yml") && !uses_indentation("file:///a/b.rs"));
+
+        let scopes = HashMap::from([(0, 4), (1, 2), (5, 9)]);
+        assert_eq!(scope_regions(&scopes), vec![FoldRegion { start: 0, end: 4 }, FoldRegion { start: 5, end: 9 }]);
+    }
+
+    #[test]
+    fn test_hidden_lines_map_between_lines_and_rows() {
+        // 第 2..5 行和第 8..10 行折起
+        let hidden = HiddenLines::new([8..10, 2..5, 3..4]);
+        assert_eq!(hidden.visible_count(12), 7);
+        assert_eq!((hidden.row_of(1), hidden.row_of(3), hidden.row_of(5), hidden.row_of(9), hidden.row_of(10)), (1, 1, 2, 4, 5));
+        let rows: Vec<usize> = (0..7).map(|row| hidden.line_of_row(row)).collect();
+        assert_eq!(rows, vec![0, 1, 5, 6, 7, 10, 11]);
+        assert_eq!(hidden.visible_in(0..12).collect::<Vec<_>>(), rows);
+        assert_eq!((hidden.next_visible(1), hidden.next_visible(7)), (5, 10));
+        assert_eq!((hidden.prev_visible(5), hidden.prev_visible(10), hidden.prev_visible(0)), (Some(1), Some(7), None));
+        assert!(hidden.is_hidden(4) && !hidden.is_hidden(5));
+    }
+
+    #[test]
+    fn test_folds_follow_edits_and_unfold_when_touched() {
+        let mut folds = Folds::default();
+        folds.set_regions(vec![FoldRegion { start: 2, end: 6 }, FoldRegion { start: 3, end: 5 }, FoldRegion { start: 10, end: 14 }]);
+        folds.fold_level(1);
+        assert!(folds.is_folded(2) && !folds.is_folded(3) && folds.is_folded(10));
+
+        // 在前面插入两行，折叠随之下移
+        assert!(!folds.edit(0, 0, 2));
+        assert!(folds.is_folded(4) && folds.is_folded(12));
+        assert_eq!(folds.hidden_lines(), HiddenLines::new([5..8, 13..16]));
+
+        // 只改首行不展开，改到折起的行则展开
+        assert!(!folds.edit(4, 4, 0));
+        assert!(folds.edit(11, 13, 0));
+        assert!(folds.is_folded(4) && !folds.is_folded(12));
+
+        // 跳转到折起的行时展开所在的区域
+        assert!(folds.unfold_containing(6));
+        assert_eq!(folds.hidden_lines(), HiddenLines::default());
+
+        folds.fold_all();
+        assert!(folds.toggle(4) && !folds.is_folded(4) && !folds.toggle(0));
+        // 重新计算后不再存在的区域不保留折叠
+        folds.set_regions(vec![FoldRegion { start: 4, end: 8 }]);
+        assert!(!folds.is_folded(5) && folds.hidden_lines() == HiddenLines::default());
+    }
+}
@@ -0,0 +1,79 @@
+//! 格式化文档：把格式化前后的内容比较成尽量小的编辑，只替换有变化的部分，光标和选区随编辑移动
+
+use serde_json::Value;
+use similar::{DiffTag, TextDiff};
+use std::ops::Range;
+
+/// tiec 语义格式化的结果中格式化后的文本：结果本身是文本，或是带 `text` 字段的对象
+pub fn formatted_text(result: &Value) -> Option<String> {
+    result
+        .as_str()
+        .or_else(|| result.get("text").and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+/// 每行起点的字节偏移，末尾多记一个内容长度
+fn line_offsets(text: &str) -> Vec<usize> {
+    let mut offsets = vec![0];
+    offsets.extend(text.split_inclusive('\n').scan(0, |end, line| {
+        *end += line.len();
+        Some(*end)
+    }));
+    if offsets.len() == 1 {
+        offsets.push(text.len());
+    }
+    offsets
+}
+
+/// 把 `old` 变成 `new` 的编辑，范围为 `old` 中的字节偏移，按位置升序。
+/// 先按行比较，有变化的行再按字符比较，只改缩进时编辑只落在行首
+pub fn format_edits(old: &str, new: &str) -> Vec<(Range<usize>, String)> {
+    let old_lines = line_offsets(old);
+    let new_lines = line_offsets(new);
+    let mut edits = Vec::new();
+    for op in TextDiff::from_lines(old, new).ops() {
+        if op.tag() == DiffTag::Equal {
+            continue;
+        }
+        let (old_range, new_range) = (op.old_range(), op.new_range());
+        let old_start = old_lines[old_range.start];
+        let old_hunk = &old[old_start..old_lines[old_range.end]];
+        let new_hunk = &new[new_lines[new_range.start]..new_lines[new_range.end]];
+
+        let old_chars: Vec<usize> = old_hunk.char_indices().map(|(i, _)| i).chain([old_hunk.len()]).collect();
+        let new_chars: Vec<usize> = new_hunk.char_indices().map(|(i, _)| i).chain([new_hunk.len()]).collect();
+        for op in TextDiff::from_chars(old_hunk, new_hunk).ops() {
+            if op.tag() == DiffTag::Equal {
+                continue;
+            }
+            let (old_range, new_range) = (op.old_range(), op.new_range());
+            let range = old_start + old_chars[old_range.start]..old_start + old_chars[old_range.end];
+            let text = new_hunk[new_chars[new_range.start]..new_chars[new_range.end]].to_string();
+            edits.push((range, text));
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::reference_update::apply_edits;
+
+    #[test]
+    fn test_format_edits_touch_only_changed_text() {
+        let old = "如果 真 则\n输出(\"你好\")\n  结束 如果\n";
+        let new = "如果 真 则\n\t输出(\"你好\")\n结束 如果\n";
+        let edits = format_edits(old, new);
+        assert_eq!(edits, vec![(15..15, "\t".to_string()), (32..34, String::new())]);
+        assert_eq!(apply_edits(old, &edits), new);
+
+        assert!(format_edits(new, new).is_empty());
+        assert_eq!(apply_edits("a", &format_edits("a", "b\n")), "b\n");
+        assert_eq!(apply_edits("", &format_edits("", "变量")), "变量");
+
+        assert_eq!(formatted_text(&serde_json::json!("x")), Some("x".to_string()));
+        assert_eq!(formatted_text(&serde_json::json!({ "text": "y" })), Some("y".to_string()));
+        assert_eq!(formatted_text(&serde_json::json!({ "edits": [] })), None);
+    }
+}
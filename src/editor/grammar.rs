@@ -1,608 +1,290 @@
-pub const CPP_GRAMMAR: &str = r##"{
-  "name": "CPP",
-  "fileExtensions": [".cpp", ".h", ".hpp", ".cc", ".c", ".hh"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "type", "foreground": "#4ec9b0" },
-    { "name": "preprocessor", "foreground": "#c586c0" },
-    { "name": "function", "foreground": "#dcdcaa" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "//.*", "style": "comment" },
-      { "pattern": "/\\*", "state": "block_comment", "style": "comment" },
-      { "pattern": "\"(?:[^\"\\\\]|\\\\.)*\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "#\\s*\\w+", "style": "preprocessor" },
-      { "pattern": "\\b(?:if|else|while|for|return|class|struct|public|private|protected|virtual|override|namespace|using|template|typename|void|int|float|double|bool|const|static|auto|friend|explicit|constexpr|nullptr|true|false|switch|case|default|break|continue|do|try|catch|throw|sizeof|alignof|alignas|decltype|noexcept|static_assert|static_cast|dynamic_cast|const_cast|reinterpret_cast|new|delete|operator|this|enum|union|typedef|char|short|long|unsigned|signed)\\b", "style": "keyword" },
-      { "pattern": "\\b[0-9]+(?:\\.[0-9]*)?f?\\b", "style": "number" },
-      { "pattern": "\\b0x[0-9a-fA-F]+\\b", "style": "number" },
-      { "pattern": "\\b[A-Z][a-zA-Z0-9_]*\\b", "style": "type" },
-      { "pattern": "\\b[a-zA-Z_][a-zA-Z0-9_]*(?=\\()", "style": "function" }
-    ],
-    "block_comment": [
-      { "pattern": "\\*/", "state": "default", "style": "comment" },
-      { "pattern": ".", "style": "comment" }
-    ]
-  }
-}"##;
+//! 语法高亮规则。语法文件放在资源目录的 `grammars` 下，启动时通过资源加载读取，缺失时使用编译进程序的副本；
+//! 调试构建中修改语法文件后会重新编译，方便在编辑器里开发语法。
+//!
+//! 插件可以在清单中提供更多语法，注册在内置语法之后：
+//!
+//! ```json
+//! { "contributes": { "grammars": [{ "path": "grammars/lua.json", "extensions": [".lua"] }] } }
+//! ```
 
-pub const RUST_GRAMMAR: &str = r##"{
-  "name": "Rust",
-  "fileExtensions": [".rs"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "type", "foreground": "#4ec9b0" },
-    { "name": "macro", "foreground": "#dcdcaa" },
-    { "name": "attribute", "foreground": "#c586c0" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "//.*", "style": "comment" },
-      { "pattern": "/\\*", "state": "block_comment", "style": "comment" },
-      { "pattern": "r#*\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"#*", "style": "string" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)'", "style": "string" },
-      { "pattern": "#\\!?\\[[^\\]]+\\]", "style": "attribute" },
-      { "pattern": "\\b[a-zA-Z_][a-zA-Z0-9_]*!\\b", "style": "macro" },
-      { "pattern": "\\b0x[0-9a-fA-F_]+\\b", "style": "number" },
-      { "pattern": "\\b[0-9][0-9_]*(?:\\.[0-9_]+)?(?:[eE][+-]?[0-9_]+)?\\b", "style": "number" },
-      { "pattern": "\\b(?:let|fn|struct|enum|impl|trait|pub|use|mod|crate|super|self|Self|mut|ref|const|static|unsafe|async|await|move|match|if|else|while|for|loop|break|continue|return|where|type|as|in|dyn|union|extern|yield|macro_rules)\\b", "style": "keyword" },
-      { "pattern": "\\b[A-Z][a-zA-Z0-9_]*\\b", "style": "type" }
-    ],
-    "block_comment": [
-      { "pattern": "\\*/", "state": "default", "style": "comment" },
-      { "pattern": ".", "style": "comment" }
-    ]
-  }
-}"##;
+use gpui::AssetSource;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
-pub const JSON_GRAMMAR: &str = r##"{
-  "name": "JSON",
-  "fileExtensions": [".json", ".jsonc"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "punctuation", "foreground": "#d4d4d4" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "\\b(?:true|false|null)\\b", "style": "keyword" },
-      { "pattern": "-?(?:0|[1-9][0-9]*)(?:\\.[0-9]+)?(?:[eE][+-]?[0-9]+)?", "style": "number" },
-      { "pattern": "[\\{\\}\\[\\]\\:,]", "style": "punctuation" }
-    ]
-  }
-}"##;
+pub const CPP_GRAMMAR: &str = include_str!("../../assets/grammars/cpp.json");
+pub const RUST_GRAMMAR: &str = include_str!("../../assets/grammars/rust.json");
+pub const JSON_GRAMMAR: &str = include_str!("../../assets/grammars/json.json");
+pub const CMAKE_GRAMMAR: &str = include_str!("../../assets/grammars/cmake.json");
+pub const TOML_GRAMMAR: &str = include_str!("../../assets/grammars/toml.json");
+pub const YAML_GRAMMAR: &str = include_str!("../../assets/grammars/yaml.json");
+pub const PYTHON_GRAMMAR: &str = include_str!("../../assets/grammars/python.json");
+pub const JAVASCRIPT_GRAMMAR: &str = include_str!("../../assets/grammars/javascript.json");
+pub const JAVA_GRAMMAR: &str = include_str!("../../assets/grammars/java.json");
+pub const TYPESCRIPT_GRAMMAR: &str = include_str!("../../assets/grammars/typescript.json");
+pub const HTML_GRAMMAR: &str = include_str!("../../assets/grammars/html.json");
+pub const CSS_GRAMMAR: &str = include_str!("../../assets/grammars/css.json");
+pub const MARKDOWN_GRAMMAR: &str = include_str!("../../assets/grammars/markdown.json");
+pub const SHELL_GRAMMAR: &str = include_str!("../../assets/grammars/shell.json");
+pub const JIESHENG_GRAMMAR: &str = include_str!("../../assets/grammars/jiesheng.json");
 
-pub const CMAKE_GRAMMAR: &str = r##"{
-  "name": "CMake",
-  "fileExtensions": [".cmake", "CMakeLists.txt", "cmakelists.txt"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "variable", "foreground": "#9cdcfe" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "#.*", "style": "comment" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "\\$\\{[A-Za-z0-9_]+\\}", "style": "variable" },
-      { "pattern": "\\b(?:cmake_minimum_required|project|add_executable|add_library|add_subdirectory|include|find_package|set|unset|option|message|file|list|string|if|elseif|else|endif|foreach|endforeach|while|endwhile|function|endfunction|macro|endmacro|return|break|continue|install|configure_file|target_link_libraries|target_include_directories|target_compile_definitions|target_compile_options|add_custom_command|add_custom_target)\\b", "style": "keyword" },
-      { "pattern": "\\b[0-9]+\\b", "style": "number" }
-    ]
-  }
-}"##;
+/// 编辑器加载的全部语法的内置副本，顺序与注册到 sweetline 引擎时一致
+pub const ALL_GRAMMARS: [&str; 15] = [
+    CPP_GRAMMAR,
+    RUST_GRAMMAR,
+    JSON_GRAMMAR,
+    CMAKE_GRAMMAR,
+    TOML_GRAMMAR,
+    YAML_GRAMMAR,
+    PYTHON_GRAMMAR,
+    JAVASCRIPT_GRAMMAR,
+    JAVA_GRAMMAR,
+    TYPESCRIPT_GRAMMAR,
+    HTML_GRAMMAR,
+    CSS_GRAMMAR,
+    MARKDOWN_GRAMMAR,
+    SHELL_GRAMMAR,
+    JIESHENG_GRAMMAR,
+];
 
-pub const TOML_GRAMMAR: &str = r##"{
-  "name": "TOML",
-  "fileExtensions": [".toml"],
-  "styles": [
-    { "name": "key", "foreground": "#9cdcfe" },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "punctuation", "foreground": "#d4d4d4" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "#.*", "style": "comment" },
-      { "pattern": "\\b[A-Za-z0-9_-]+(?=\\s*=)", "style": "key" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "\\b(?:true|false)\\b", "style": "keyword" },
-      { "pattern": "\\b[0-9]+(?:\\.[0-9]+)?\\b", "style": "number" },
-      { "pattern": "[\\[\\]\\{\\}=,\\.]", "style": "punctuation" }
-    ]
-  }
-}"##;
+/// [`ALL_GRAMMARS`] 各项在资源目录中的文件
+pub const GRAMMAR_ASSETS: [&str; 15] = [
+    "grammars/cpp.json",
+    "grammars/rust.json",
+    "grammars/json.json",
+    "grammars/cmake.json",
+    "grammars/toml.json",
+    "grammars/yaml.json",
+    "grammars/python.json",
+    "grammars/javascript.json",
+    "grammars/java.json",
+    "grammars/typescript.json",
+    "grammars/html.json",
+    "grammars/css.json",
+    "grammars/markdown.json",
+    "grammars/shell.json",
+    "grammars/jiesheng.json",
+];
 
-pub const YAML_GRAMMAR: &str = r##"{
-  "name": "YAML",
-  "fileExtensions": [".yml", ".yaml"],
-  "styles": [
-    { "name": "key", "foreground": "#9cdcfe" },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "punctuation", "foreground": "#d4d4d4" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "#.*", "style": "comment" },
-      { "pattern": "\\b[A-Za-z0-9_-]+(?=\\s*:)", "style": "key" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "\\b(?:true|false|null)\\b", "style": "keyword" },
-      { "pattern": "\\b[0-9]+(?:\\.[0-9]+)?\\b", "style": "number" },
-      { "pattern": "[-:\\[\\]\\{\\},]", "style": "punctuation" }
-    ]
-  }
-}"##;
+/// 结绳语法在 [`ALL_GRAMMARS`] 中的位置，作用域划线也按它计算
+pub const JIESHENG_INDEX: usize = 14;
 
-pub const PYTHON_GRAMMAR: &str = r##"{
-  "name": "Python",
-  "fileExtensions": [".py"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "number", "foreground": "#b5cea8" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "#.*", "style": "comment" },
-      { "pattern": "\\\"\\\"\\\"", "style": "string", "state": "triple_double" },
-      { "pattern": "'''", "style": "string", "state": "triple_single" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "\\b(?:def|class|import|from|as|if|elif|else|for|while|break|continue|return|yield|try|except|finally|with|lambda|pass|raise|global|nonlocal|assert|True|False|None)\\b", "style": "keyword" },
-      { "pattern": "\\b[0-9]+(?:\\.[0-9]+)?\\b", "style": "number" }
-    ],
-    "triple_double": [
-      { "pattern": "\\\"\\\"\\\"", "style": "string", "state": "default" },
-      { "pattern": ".", "style": "string" }
-    ],
-    "triple_single": [
-      { "pattern": "'''", "style": "string", "state": "default" },
-      { "pattern": ".", "style": "string" }
-    ]
-  }
-}"##;
+/// 已注册的一个语法
+struct GrammarEntry {
+    /// 同一份内容只分配一次，作用域划线按地址判断语法是否变化
+    source: Arc<str>,
+    /// 语法中的 `fileExtensions`，打开文件时按它选择语法
+    extensions: Vec<String>,
+    /// 提供语法的插件 id，内置语法为 None
+    plugin: Option<String>,
+    /// 编译失败或插件已停用，不再用于打开的文件
+    disabled: bool,
+}
 
-pub const JAVASCRIPT_GRAMMAR: &str = r##"{
-  "name": "JavaScript",
-  "fileExtensions": [".js", ".mjs", ".cjs", ".jsx"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "function", "foreground": "#dcdcaa" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "//.*", "style": "comment" },
-      { "pattern": "/\\*", "state": "block_comment", "style": "comment" },
-      { "pattern": "`(?:[^`\\\\]|\\\\.)*`", "style": "string" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "\\b(?:function|class|const|let|var|if|else|for|while|do|return|break|continue|switch|case|default|try|catch|finally|throw|new|this|super|extends|import|from|export|async|await|yield|typeof|instanceof|in|of|true|false|null|undefined)\\b", "style": "keyword" },
-      { "pattern": "\\b[0-9]+(?:\\.[0-9]+)?\\b", "style": "number" },
-      { "pattern": "\\b[a-zA-Z_][a-zA-Z0-9_]*(?=\\()", "style": "function" }
-    ],
-    "block_comment": [
-      { "pattern": "\\*/", "state": "default", "style": "comment" },
-      { "pattern": ".", "style": "comment" }
-    ]
-  }
-}"##;
+impl GrammarEntry {
+    fn new(source: Arc<str>, plugin: Option<String>) -> Self {
+        let extensions = file_extensions(&source);
+        Self { source, extensions, plugin, disabled: false }
+    }
+}
+
+fn file_extensions(grammar: &str) -> Vec<String> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(grammar) else {
+        return Vec::new();
+    };
+    value["fileExtensions"]
+        .as_array()
+        .map(|exts| exts.iter().filter_map(|ext| ext.as_str()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// 扩展名到语法的注册表：前面是与 [`ALL_GRAMMARS`] 一一对应的内置语法，第一次使用时填入内置副本，
+/// 之后是插件提供的语法。位置在程序运行期间不变，停用的语法只做标记
+static REGISTRY: RwLock<Vec<GrammarEntry>> = RwLock::new(Vec::new());
 
-pub const JAVA_GRAMMAR: &str = r##"{
-  "name": "Java",
-  "fileExtensions": [".java", ".jav"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "type", "foreground": "#4ec9b0" },
-    { "name": "annotation", "foreground": "#dcdcaa" },
-    { "name": "function", "foreground": "#dcdcaa" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "//.*", "style": "comment" },
-      { "pattern": "/\\*", "state": "block_comment", "style": "comment" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)'", "style": "string" },
-      { "pattern": "@[a-zA-Z_][a-zA-Z0-9_]*", "style": "annotation" },
-      { "pattern": "\\b(?:abstract|assert|boolean|break|byte|case|catch|char|class|const|continue|default|do|double|else|enum|extends|final|finally|float|for|goto|if|implements|import|instanceof|int|interface|long|native|new|package|private|protected|public|return|short|static|strictfp|super|switch|synchronized|this|throw|throws|transient|try|void|volatile|while|true|false|null|var)\\b", "style": "keyword" },
-      { "pattern": "\\b[0-9]+(?:\\.[0-9]+)?f?\\b", "style": "number" },
-      { "pattern": "\\b0x[0-9a-fA-F]+\\b", "style": "number" },
-      { "pattern": "\\b[A-Z][a-zA-Z0-9_]*\\b", "style": "type" },
-      { "pattern": "\\b[a-zA-Z_][a-zA-Z0-9_]*(?=\\()", "style": "function" }
-    ],
-    "block_comment": [
-      { "pattern": "\\*/", "state": "default", "style": "comment" },
-      { "pattern": ".", "style": "comment" }
-    ]
-  }
-}"##;
+fn with_registry<T>(f: impl FnOnce(&mut Vec<GrammarEntry>) -> T) -> T {
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    if registry.is_empty() {
+        registry.extend(ALL_GRAMMARS.iter().map(|grammar| GrammarEntry::new(Arc::from(*grammar), None)));
+    }
+    f(&mut registry)
+}
 
-pub const TYPESCRIPT_GRAMMAR: &str = r##"{
-  "name": "TypeScript",
-  "fileExtensions": [".ts", ".tsx"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "function", "foreground": "#dcdcaa" },
-    { "name": "type", "foreground": "#4ec9b0" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "//.*", "style": "comment" },
-      { "pattern": "/\\*", "state": "block_comment", "style": "comment" },
-      { "pattern": "`(?:[^`\\\\]|\\\\.)*`", "style": "string" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "\\b(?:interface|type|enum|implements|extends|class|const|let|var|if|else|for|while|do|return|break|continue|switch|case|default|try|catch|finally|throw|new|this|super|import|from|export|async|await|yield|typeof|instanceof|in|of|true|false|null|undefined|public|private|protected|readonly|keyof)\\b", "style": "keyword" },
-      { "pattern": "\\b[0-9]+(?:\\.[0-9]+)?\\b", "style": "number" },
-      { "pattern": "\\b[A-Z][a-zA-Z0-9_]*\\b", "style": "type" },
-      { "pattern": "\\b[a-zA-Z_][a-zA-Z0-9_]*(?=\\()", "style": "function" }
-    ],
-    "block_comment": [
-      { "pattern": "\\*/", "state": "default", "style": "comment" },
-      { "pattern": ".", "style": "comment" }
-    ]
-  }
-}"##;
+/// 已注册的语法数，含停用的
+pub fn grammar_count() -> usize {
+    let count = REGISTRY.read().unwrap_or_else(|e| e.into_inner()).len();
+    if count > 0 {
+        return count;
+    }
+    with_registry(|registry| registry.len())
+}
 
-pub const HTML_GRAMMAR: &str = r##"{
-  "name": "HTML",
-  "fileExtensions": [".html", ".htm"],
-  "styles": [
-    { "name": "tag", "foreground": "#569cd6" },
-    { "name": "attr", "foreground": "#9cdcfe" },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "<!--", "style": "comment", "state": "comment_block" },
-      { "pattern": "<\\/?[a-zA-Z][a-zA-Z0-9-]*", "style": "tag" },
-      { "pattern": "\\b[a-zA-Z_:][a-zA-Z0-9_:-]*(?=\\=)", "style": "attr" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" }
-    ],
-    "comment_block": [
-      { "pattern": "-->", "style": "comment", "state": "default" },
-      { "pattern": ".", "style": "comment" }
-    ]
-  }
-}"##;
+/// 当前使用的语法内容
+pub fn grammar_source(index: usize) -> Arc<str> {
+    if let Some(entry) = REGISTRY.read().unwrap_or_else(|e| e.into_inner()).get(index) {
+        return entry.source.clone();
+    }
+    with_registry(|registry| registry[index].source.clone())
+}
 
-pub const CSS_GRAMMAR: &str = r##"{
-  "name": "CSS",
-  "fileExtensions": [".css", ".scss", ".less"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6" },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "number", "foreground": "#b5cea8" },
-    { "name": "selector", "foreground": "#dcdcaa" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "/\\*", "state": "block_comment", "style": "comment" },
-      { "pattern": "@[a-zA-Z_-]+", "style": "keyword" },
-      { "pattern": "\\.[a-zA-Z0-9_-]+", "style": "selector" },
-      { "pattern": "#[a-zA-Z0-9_-]+", "style": "selector" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "\\b[0-9]+(?:\\.[0-9]+)?(?:px|em|rem|%|vh|vw)?\\b", "style": "number" }
-    ],
-    "block_comment": [
-      { "pattern": "\\*/", "state": "default", "style": "comment" },
-      { "pattern": ".", "style": "comment" }
-    ]
-  }
-}"##;
+/// 内置语法编译进程序的副本，插件提供的语法为 None
+pub fn embedded_grammar(index: usize) -> Option<&'static str> {
+    ALL_GRAMMARS.get(index).copied()
+}
 
-pub const MARKDOWN_GRAMMAR: &str = r##"{
-  "name": "Markdown",
-  "fileExtensions": [".md", ".markdown"],
-  "styles": [
-    { "name": "heading", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "code", "foreground": "#ce9178" },
-    { "name": "emphasis", "foreground": "#dcdcaa" },
-    { "name": "link", "foreground": "#4ec9b0" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "^#{1,6}.*", "style": "heading" },
-      { "pattern": "`[^`]+`", "style": "code" },
-      { "pattern": "\\*\\*[^*]+\\*\\*", "style": "emphasis" },
-      { "pattern": "_[^_]+_", "style": "emphasis" },
-      { "pattern": "\\[[^\\]]+\\]\\([^\\)]+\\)", "style": "link" },
-      { "pattern": "```", "style": "code", "state": "code_block" }
-    ],
-    "code_block": [
-      { "pattern": "```", "style": "code", "state": "default" },
-      { "pattern": ".", "style": "code" }
-    ]
-  }
-}"##;
+/// 替换语法内容，None 表示改回内置副本
+pub fn set_grammar_source(index: usize, source: Option<Arc<str>>) {
+    with_registry(|registry| {
+        let Some(source) = source.or_else(|| embedded_grammar(index).map(Arc::from)) else {
+            return;
+        };
+        let plugin = registry[index].plugin.take();
+        registry[index] = GrammarEntry::new(source, plugin);
+    });
+}
 
-pub const SHELL_GRAMMAR: &str = r##"{
-  "name": "Shell",
-  "fileExtensions": [".sh", ".bash", ".zsh"],
-  "styles": [
-    { "name": "keyword", "foreground": "#569cd6", "tags": ["bold"] },
-    { "name": "string", "foreground": "#ce9178" },
-    { "name": "comment", "foreground": "#6a9955" },
-    { "name": "variable", "foreground": "#9cdcfe" }
-  ],
-  "states": {
-    "default": [
-      { "pattern": "#.*", "style": "comment" },
-      { "pattern": "\\\"(?:[^\\\"\\\\]|\\\\.)*\\\"", "style": "string" },
-      { "pattern": "'(?:[^'\\\\]|\\\\.)*'", "style": "string" },
-      { "pattern": "\\$[A-Za-z0-9_]+", "style": "variable" },
-      { "pattern": "\\b(?:if|then|else|elif|fi|for|while|do|done|case|esac|function|return|break|continue|export|unset|local|in)\\b", "style": "keyword" }
-    ]
-  }
-}"##;
+pub fn grammar_disabled(index: usize) -> bool {
+    with_registry(|registry| registry.get(index).is_none_or(|entry| entry.disabled))
+}
+
+/// 停用编译失败的语法，之后打开的文件不再使用它
+pub fn disable_grammar(index: usize) {
+    with_registry(|registry| {
+        if let Some(entry) = registry.get_mut(index) {
+            entry.disabled = true;
+        }
+    });
+}
+
+/// 插件清单中的一个语法，`path` 已换成完整路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginGrammar {
+    pub plugin_id: String,
+    pub path: PathBuf,
+    /// 除语法自身的 `fileExtensions` 外还使用它的文件结尾，例如 `.lua`
+    pub extensions: Vec<String>,
+}
+
+/// 注册插件提供的语法：清单中的扩展名并入语法的 `fileExtensions`，sweetline 按它为文档选择语法。
+/// 同一插件的同名语法替换原来的注册，返回它的位置
+pub fn register_plugin_grammar(plugin_id: &str, source: &str, extensions: &[String]) -> Result<usize, String> {
+    let mut value: serde_json::Value = serde_json::from_str(source).map_err(|err| format!("语法不是有效的 JSON：{}", err))?;
+    let name = grammar_name(source).ok_or_else(|| "语法没有 name".to_string())?;
+    let mut merged = file_extensions(source);
+    for extension in extensions {
+        if !merged.contains(extension) {
+            merged.push(extension.clone());
+        }
+    }
+    value["fileExtensions"] = serde_json::json!(merged);
+    let source: Arc<str> = Arc::from(value.to_string());
+    Ok(with_registry(|registry| {
+        let existing = registry.iter().position(|entry| {
+            entry.plugin.as_deref() == Some(plugin_id) && grammar_name(&entry.source).as_deref() == Some(name.as_str())
+        });
+        let entry = GrammarEntry::new(source, Some(plugin_id.to_string()));
+        match existing {
+            Some(index) => {
+                registry[index] = entry;
+                index
+            }
+            None => {
+                registry.push(entry);
+                registry.len() - 1
+            }
+        }
+    }))
+}
 
-pub const JIESHENG_GRAMMAR: &str = r##"{
-  "name": "tiecode",
-  "fileExtensions": [".t"],
-  "variables": {
-    "identifierStart": "[\\p{Han}\\w_$]+",
-    "identifierPart": "[\\p{Han}\\w_$0-9]*",
-    "identifier": "${identifierStart}${identifierPart}",
-    "whiteSpace": "[ \\t\\f]",
-    "any": "[\\S\\s]",
-    "classExtends": "(?:${whiteSpace}+(:)${whiteSpace}+${type})?"
-  },
-  "styles": [
-    {
-      "name": "keyword",
-      "foreground": "#FF569CD6",
-      "tags": ["bold", "italic"]
-    },
-    {
-      "name": "string",
-      "foreground": "#FFBD63C5"
-    },
-    {
-      "name": "number",
-      "foreground": "#FFE4FAD5"
-    },
-    {
-      "name": "comment",
-      "foreground": "#FF60AE6F"
-    },
-    {
-      "name": "class",
-      "foreground": "#FF4EC9B0"
-    },
-    {
-      "name": "method",
-      "foreground": "#FF9CDCFE"
-    },
-    {
-      "name": "variable",
-      "foreground": "#FF9B9BC8"
-    },
-    {
-      "name": "punctuation",
-      "foreground": "#FFD69D85"
-    },
-    {
-      "name": "annotation",
-      "foreground": "#FFFFFD9B"
+/// 让插件语法与已启用插件的清单一致：读取并注册 `grammars`，停用其余插件的语法。
+/// 读不到或无效的语法只跳过这一项，返回面向用户的说明
+pub fn sync_plugin_grammars(grammars: &[PluginGrammar]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut active = Vec::new();
+    for grammar in grammars {
+        let registered = std::fs::read_to_string(&grammar.path)
+            .map_err(|err| format!("无法读取：{}", err))
+            .and_then(|source| register_plugin_grammar(&grammar.plugin_id, &source, &grammar.extensions));
+        match registered {
+            Ok(index) => active.push(index),
+            Err(err) => {
+                let problem = format!("插件 {} 的语法 {} 未加载：{}", grammar.plugin_id, grammar.path.display(), err);
+                warn!("{}", problem);
+                problems.push(problem);
+            }
+        }
     }
-  ],
-  "variables": {
-    "identifierStart": "[\\p{Han}\\w_$]+",
-    "identifierPart": "[\\p{Han}\\w_$0-9]*",
-    "identifier": "${identifierStart}${identifierPart}",
-    "whiteSpace": "[ \\t\\f]",
-    "any": "[\\S\\s]",
-    "classExtends": "(?:${whiteSpace}+(:)${whiteSpace}+${type})?",
-    "embedCodeRefThis": "(#)(this)",
-    "embedCodeRefClass": "(#)(cls|ncls)(<)(${identifier})(>)"
-  },
-  "blockPairs": [
-    { "start": "类", "end": "结束 类" },
-    { "start": "方法", "end": "结束 方法" },
-    { "start": "循环", "end": "结束 循环" },
-    { "start": "如果", "end": "结束 如果" }
-  ],
-  "states": {
-    "default": [
-      {
-        "pattern": "\\b(类)\\b${whiteSpace}+(${identifier})",
-        "styles": [1, "keyword", 2, "class"],
-        "state": "typeDeclare"
-      },
-      {
-        "pattern": "\\b(创建)\\b${whiteSpace}+(${identifier})",
-        "styles": [1, "keyword", 2, "class"]
-      },
-      {
-        "pattern": "(变量)${whiteSpace}+(${identifier})(?:${whiteSpace}*(:)${whiteSpace}*)?",
-        "styles": [1, "keyword", 2, "variable", 3, "punctuation"],
-        "state": "typeDeclare"
-      },
-      {
-        "pattern": "(事件)${whiteSpace}+(${identifier})${whiteSpace}*(:)${whiteSpace}*(${identifier})${whiteSpace}*(\\()",
-        "styles": [1, "keyword", 2, "variable", 3, "punctuation", 4, "method", 5, "punctuation"]
-      },
-      {
-        "pattern": "\\b(包名|类|继承|变量|常量|方法|属性写|属性读|属性|定义事件|事件|结束|为|真|假|空|本对象|父对象|变体型)\\b",
-        "styles": [1, "keyword"]
-      },
-      {
-        "pattern": "\\b(如果|且|或|则|否则|假如|是|循环|跳过循环|退出循环|订阅事件|属于|返回|创建|等待)\\b",
-        "styles": [1, "keyword"]
-      },
-      {
-        "pattern": "\\b(code)\\b",
-        "styles": [1, "keyword"],
-        "state": "singleLineEmbedCode"
-      },
-      {
-        "pattern": "(@)${whiteSpace}*(code)",
-        "styles": [1, "punctuation", 2, "keyword"],
-        "state": "multiLineEmbedCode"
-      },
-      {
-        "pattern": "(@)${whiteSpace}*(${identifier})",
-        "styles": [1, "punctuation", 2, "annotation"]
-      },
-      {
-        "pattern": "(${identifier})(<)(${identifier})(>)",
-        "styles": [1, "class", 2, "punctuation", 3, "class", 4, "punctuation"]
-      },
-      {
-        "pattern": "(${identifier})(<)(${identifier})(,)(${identifier})(>)",
-        "styles": [1, "class", 2, "punctuation", 3, "class", 4, "punctuation", 5, "class", 6, "punctuation"]
-      },
-      {
-        "pattern": "(${identifier})(<)(${identifier})(>)(,)${whiteSpace}*(<)(${identifier})(>)",
-        "styles": [1, "class", 2, "punctuation", 3, "class", 4, "punctuation", 5, "punctuation", 6, "class", 7, "punctuation"]
-      },
-      {
-        "pattern": "(${identifier})(<)(${identifier})(<)(${identifier})(>)(>)",
-        "styles": [1, "class", 2, "punctuation", 3, "class", 4, "punctuation", 5, "class", 6, "punctuation", 7, "punctuation"]
-      },
-      {
-        "pattern": "(${identifier})(<)(${identifier})(>)",
-        "styles": [1, "class", 2, "punctuation", 3, "class", 4, "punctuation"]
-      },
-      {
-        "pattern": "(${identifier})${whiteSpace}*(:)${whiteSpace}*(${identifier})${whiteSpace}*([,)])",
-        "styles": [1, "variable", 2, "punctuation", 3, "class", 4, "punctuation"]
-      },
-      {
-        "pattern": "(${identifier})${whiteSpace}*(\\()",
-        "styles": [1, "method", 2, "punctuation"]
-      },
-      {
-        "pattern": "\"(?:[^\"\\\\]|\\\\.)*\"|'(?:[^'\\\\]|\\\\.)*'",
-        "style": "string"
-      },
-      {
-        "pattern": "\\b(?:[0-9]*\\.?[0-9]+(?:[eE][+-]?[0-9]+)?[fFdD]?)\\b",
-        "style": "number"
-      },
-      {
-        "pattern": "\\[\\[",
-        "style": "string",
-        "state": "longString"
-      },
-      {
-        "pattern": "/\\*",
-        "style": "comment",
-        "state": "longComment"
-      },
-      {
-        "pattern": "//${any}*",
-        "style": "comment"
-      },
-      {
-        "pattern": "[.()\\[\\]?@%+\\-*/<>=,{}:]",
-        "style": "punctuation"
-      }
-    ],
-    "typeDeclare": [
-      {
-        "pattern": "[<>,\\[\\]:]",
-        "style": "punctuation"
-      },
-      {
-        "pattern": "=",
-        "style": "punctuation",
-        "state": "default"
-      },
-      {
-        "pattern": "${identifier}",
-        "style": "class"
-      },
-      {
-        "onLineEndState": "default"
-      }
-    ],
-    "longString": [
-      {
-        "pattern": "\\]\\]",
-        "style": "string",
-        "state": "default"
-      },
-      {
-        "pattern": "${any}",
-        "style": "string"
-      }
-    ],
-    "longComment": [
-      {
-        "pattern": "\\*/",
-        "style": "comment",
-        "state": "default"
-      },
-      {
-        "pattern": "${any}",
-        "style": "comment"
-      }
-    ],
-    "singleLineEmbedCode": {
-      "reference": "CPP",
-      "rules": [
-        {
-          "pattern": "${embedCodeRefThis}",
-          "styles": [1, "punctuation", 2, "keyword"]
-        },
-        {
-          "pattern": "${embedCodeRefClass}",
-          "styles": [1, "punctuation", 2, "keyword", 3, "punctuation", 4, "class", 5, "punctuation"]
+    with_registry(|registry| {
+        for (index, entry) in registry.iter_mut().enumerate() {
+            if entry.plugin.is_some() && !active.contains(&index) {
+                entry.disabled = true;
+            }
         }
-      ],
-      "onLineEndState": "default"
-    },
-    "multiLineEmbedCode": {
-      "reference": "CPP",
-      "rules": [
-        {
-          "pattern": "${embedCodeRefThis}",
-          "styles": [1, "punctuation", 2, "keyword"]
-        },
-        {
-          "pattern": "${embedCodeRefClass}",
-          "styles": [1, "punctuation", 2, "keyword", 3, "punctuation", 4, "class", 5, "punctuation"]
-        },
-        {
-          "pattern": "(@)${whiteSpace}*(end)",
-          "styles": [1, "punctuation", 2, "keyword"],
-          "state": "default"
+    });
+    problems
+}
+
+/// 启动时从资源中读取全部语法，缺失或不是 UTF-8 的使用内置副本
+pub fn load_grammar_assets(assets: &dyn AssetSource) {
+    for (index, path) in GRAMMAR_ASSETS.iter().enumerate() {
+        let source = match assets.load(path) {
+            Ok(Some(data)) => String::from_utf8(data.into_owned()).ok(),
+            _ => None,
+        };
+        if source.is_none() {
+            info!("Grammar asset {} unavailable, using embedded copy", path);
+        }
+        set_grammar_source(index, source.map(Arc::from));
+    }
+}
+
+/// 资源目录中的语法文件对应的语法
+pub fn grammar_index_for_asset(path: &Path) -> Option<usize> {
+    let name = path.file_name()?.to_str()?;
+    GRAMMAR_ASSETS.iter().position(|asset| asset.rsplit('/').next() == Some(name))
+}
+
+/// 按语法中的 `fileExtensions` 匹配路径结尾，返回语法在注册表中的位置，
+/// 与 sweetline 为文档选择语法的规则相同；多个语法匹配时内置语法优先
+pub fn grammar_index_for_path(path: &str) -> Option<usize> {
+    with_registry(|registry| {
+        registry
+            .iter()
+            .position(|entry| !entry.disabled && entry.extensions.iter().any(|ext| path.ends_with(ext.as_str())))
+    })
+}
+
+/// 与 [`grammar_index_for_path`] 相同，返回语法的 `name`
+pub fn grammar_name_for_path(path: &str) -> Option<String> {
+    grammar_index_for_path(path).and_then(|index| grammar_name(&grammar_source(index)))
+}
+
+pub fn grammar_name(grammar: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(grammar).ok()?;
+    value["name"].as_str().map(str::to_string)
+}
+
+/// 语法通过 `reference` 嵌入的其他语法在注册表中的位置，这些语法需要先编译
+pub fn grammar_dependencies(index: usize) -> Vec<usize> {
+    fn collect(value: &serde_json::Value, names: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                if let Some(name) = map.get("reference").and_then(|r| r.as_str()) {
+                    names.push(name.to_string());
+                }
+                map.values().for_each(|v| collect(v, names));
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect(v, names)),
+            _ => {}
         }
-      ],
-      "onLineEndState": "multiLineEmbedCode"
     }
-  }
-}"##;
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(&grammar_source(index)) else {
+        return Vec::new();
+    };
+    let mut names = Vec::new();
+    collect(&value, &mut names);
+    (0..grammar_count())
+        .filter(|i| *i != index && !grammar_disabled(*i))
+        .filter(|i| grammar_name(&grammar_source(*i)).is_some_and(|name| names.contains(&name)))
+        .collect()
+}
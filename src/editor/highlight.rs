@@ -0,0 +1,62 @@
+//! 整篇文档的语法高亮在后台分析，界面线程先显示无高亮的文本，结果按版本号分块送回
+
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ropey::Rope;
+use tiecode::sweetline::{Document, DocumentAnalyzer, Engine, HighlightSpan};
+
+/// 每块的高亮数量，界面线程每次合并一块
+pub const CHUNK_SPANS: usize = 20_000;
+/// 相邻两块送达的间隔，两块之间至少能画一帧
+pub const CHUNK_INTERVAL: Duration = Duration::from_millis(8);
+
+/// 一块按结束位置排好序的高亮，`lines` 为其覆盖的行
+pub struct HighlightChunk {
+    pub spans: Vec<HighlightSpan>,
+    pub lines: Range<usize>,
+}
+
+/// 后台分析完的文档；分析器交回界面线程，之后的编辑在它上面增量分析
+pub struct LoadedHighlights {
+    pub revision: u64,
+    pub document: Document,
+    pub analyzer: DocumentAnalyzer,
+    pub chunks: Vec<HighlightChunk>,
+}
+
+/// 一次整篇分析。创建时只复制 Rope 的引用，界面线程上没有与文档大小相关的工作
+pub struct HighlightJob {
+    revision: u64,
+    engine: Arc<Engine>,
+    uri: String,
+    content: Rope,
+}
+
+impl HighlightJob {
+    pub fn new(revision: u64, engine: Arc<Engine>, uri: &str, content: &Rope) -> Self {
+        Self { revision, engine, uri: uri.to_string(), content: content.clone() }
+    }
+
+    /// 在后台执行：重新载入文档、分析、排序并分块
+    pub fn run(self) -> LoadedHighlights {
+        let document = Document::new(&self.uri, &self.content.to_string());
+        let analyzer = self.engine.reload_document(&self.uri, &document);
+        let mut spans = DocumentAnalyzer::parse_result(&analyzer.analyze(), false);
+        spans.sort_by_key(|span| (span.end_index, span.start_index));
+        LoadedHighlights { revision: self.revision, document, analyzer, chunks: chunk_spans(spans, CHUNK_SPANS) }
+    }
+}
+
+fn chunk_spans(spans: Vec<HighlightSpan>, size: usize) -> Vec<HighlightChunk> {
+    let mut chunks = Vec::new();
+    let mut spans = spans.into_iter().peekable();
+    while spans.peek().is_some() {
+        let spans: Vec<HighlightSpan> = spans.by_ref().take(size).collect();
+        let first = spans.iter().map(|span| span.start_line as usize).min().unwrap_or(0);
+        let last = spans.iter().map(|span| span.end_line as usize).max().unwrap_or(0);
+        chunks.push(HighlightChunk { spans, lines: first..last + 1 });
+    }
+    chunks
+}
@@ -0,0 +1,234 @@
+//! 悬停提示的延迟：指针停下一段时间后才显示提示（并向语言服务请求），移出提示对应的范围后
+//! 再等一小段时间才隐藏，来回移动时提示不会闪烁。两个延迟来自项目设置的 `editor` 一节，单位为毫秒
+//!
+//! ```json
+//! { "editor": { "hoverDelay": 300, "hoverHideDelay": 100 } }
+//! ```
+//!
+//! 语言服务返回的悬停文档按简单的 Markdown 解析成段落和代码块
+
+use log::warn;
+use serde_json::Value;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::lsp::tiec::settings::PROJECT_SETTINGS_FILE;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HoverDelays {
+    /// 指针停下后多久显示提示
+    pub show: Duration,
+    /// 指针离开提示的范围后多久隐藏
+    pub hide: Duration,
+}
+
+impl Default for HoverDelays {
+    fn default() -> Self {
+        Self { show: Duration::from_millis(300), hide: Duration::from_millis(100) }
+    }
+}
+
+impl HoverDelays {
+    /// 读取项目设置；文件缺失或无法解析时使用默认值
+    pub fn load(project_root: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(project_root.join(PROJECT_SETTINGS_FILE)) else {
+            return Self::default();
+        };
+        let (delays, warnings) = Self::parse(&content);
+        for warning in warnings {
+            warn!("{}", warning);
+        }
+        delays
+    }
+
+    /// 解析设置文件内容，同时返回面向用户的警告；写错的项使用默认值
+    pub fn parse(content: &str) -> (Self, Vec<String>) {
+        let mut delays = Self::default();
+        let mut warnings = Vec::new();
+        let Ok(value) = serde_json::from_str::<Value>(content) else {
+            return (delays, warnings);
+        };
+        let Some(section) = value.get("editor") else {
+            return (delays, warnings);
+        };
+        for (key, target) in [("hoverDelay", &mut delays.show), ("hoverHideDelay", &mut delays.hide)] {
+            let Some(value) = section.get(key) else {
+                continue;
+            };
+            match value.as_u64() {
+                Some(ms) => *target = Duration::from_millis(ms),
+                None => warnings.push(format!("editor.{} 应为不小于 0 的毫秒数", key)),
+            }
+        }
+        (delays, warnings)
+    }
+
+    /// 指针移动到 `pointer`（不在文本上时为 None）之后要做的事；
+    /// `showing` 为正在显示的提示所对应的范围，显示的提示不对应文本时为 `Some(None)`
+    pub fn plan(&self, showing: Option<Option<&Range<usize>>>, pointer: Option<usize>) -> HoverPlan {
+        match (showing, pointer) {
+            // 仍在提示的范围内，保持显示并取消等待中的隐藏
+            (Some(Some(range)), Some(index)) if range.contains(&index) => HoverPlan::Keep,
+            (Some(_), pointer) => HoverPlan::Schedule {
+                hide_after: Some(self.hide),
+                show_after: pointer.map(|_| self.show.max(self.hide)),
+            },
+            (None, Some(_)) => HoverPlan::Schedule { hide_after: None, show_after: Some(self.show) },
+            (None, None) => HoverPlan::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HoverPlan {
+    Keep,
+    /// 从现在起分别经过多久隐藏当前提示、显示新位置的提示；新的移动会重新安排
+    Schedule { hide_after: Option<Duration>, show_after: Option<Duration> },
+}
+
+/// `byte` 所在标识符的字节范围，不在标识符上时为空范围
+pub fn identifier_bounds(text: &str, byte: usize) -> Range<usize> {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_';
+    let start = text[..byte]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_identifier(*c))
+        .last()
+        .map_or(byte, |(i, _)| i);
+    let end = text[byte..].char_indices().find(|(_, c)| !is_identifier(*c)).map_or(text.len(), |(i, _)| byte + i);
+    if start == end || !text[byte..].starts_with(is_identifier) {
+        return byte..byte;
+    }
+    start..end
+}
+
+/// 段落中的行内样式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InlineStyle {
+    Bold,
+    Code,
+}
+
+/// 悬停文档中的一块
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoverBlock {
+    /// 段落：去掉标记后的文本，以及加粗和行内代码的字节范围
+    Text { text: String, styles: Vec<(Range<usize>, InlineStyle)> },
+    /// 围栏代码块及其语言
+    Code { language: Option<String>, code: String },
+}
+
+/// 按简单的 Markdown 解析悬停文档：空行分段，支持加粗、行内代码和围栏代码块，其余原样显示
+pub fn parse_hover_markdown(markdown: &str) -> Vec<HoverBlock> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut lines = markdown.lines();
+    while let Some(line) = lines.next() {
+        if let Some(language) = line.trim_start().strip_prefix("```") {
+            push_paragraph(&mut blocks, &mut paragraph);
+            let code: Vec<&str> = lines.by_ref().take_while(|line| !line.trim_start().starts_with("```")).collect();
+            let language = Some(language.trim().to_string()).filter(|language| !language.is_empty());
+            blocks.push(HoverBlock::Code { language, code: code.join("\n") });
+        } else if line.trim().is_empty() {
+            push_paragraph(&mut blocks, &mut paragraph);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    push_paragraph(&mut blocks, &mut paragraph);
+    blocks
+}
+
+fn push_paragraph(blocks: &mut Vec<HoverBlock>, paragraph: &mut Vec<&str>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    let (text, styles) = parse_inline(&paragraph.join("\n"));
+    blocks.push(HoverBlock::Text { text, styles });
+    paragraph.clear();
+}
+
+/// 去掉 `**加粗**` 和 `` `代码` `` 的标记，记下样式的范围；没有配对的标记原样保留
+fn parse_inline(source: &str) -> (String, Vec<(Range<usize>, InlineStyle)>) {
+    let mut text = String::with_capacity(source.len());
+    let mut styles = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let (marker, style) = if rest.starts_with("**") {
+            ("**", InlineStyle::Bold)
+        } else if c == '`' {
+            ("`", InlineStyle::Code)
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        match rest[marker.len()..].find(marker).filter(|&end| end > 0) {
+            Some(end) => {
+                let start = text.len();
+                text.push_str(&rest[marker.len()..marker.len() + end]);
+                styles.push((start..text.len(), style));
+                rest = &rest[marker.len() * 2 + end..];
+            }
+            None => {
+                text.push_str(marker);
+                rest = &rest[marker.len()..];
+            }
+        }
+    }
+    (text, styles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_delays_and_plans_hover() {
+        let (delays, warnings) = HoverDelays::parse(r#"{ "editor": { "hoverDelay": 500, "hoverHideDelay": -1 } }"#);
+        assert_eq!(delays, HoverDelays { show: Duration::from_millis(500), hide: Duration::from_millis(100) });
+        assert_eq!(warnings, vec!["editor.hoverHideDelay 应为不小于 0 的毫秒数"]);
+        assert_eq!(HoverDelays::parse(r#"{ "tiec": {} }"#), (HoverDelays::default(), Vec::new()));
+
+        let delays = HoverDelays::default();
+        let range = 4..8;
+        assert_eq!(delays.plan(Some(Some(&range)), Some(6)), HoverPlan::Keep);
+        assert_eq!(
+            delays.plan(Some(Some(&range)), Some(9)),
+            HoverPlan::Schedule { hide_after: Some(delays.hide), show_after: Some(delays.show) }
+        );
+        assert_eq!(delays.plan(Some(None), None), HoverPlan::Schedule { hide_after: Some(delays.hide), show_after: None });
+        assert_eq!(delays.plan(None, Some(2)), HoverPlan::Schedule { hide_after: None, show_after: Some(delays.show) });
+        assert_eq!(delays.plan(None, None), HoverPlan::Keep);
+
+        let line = "变量 工具类 : 文本";
+        assert_eq!(&line[identifier_bounds(line, 10)], "工具类");
+        assert_eq!(identifier_bounds(line, 6), 6..6);
+        assert_eq!(&line[identifier_bounds(line, 0)], "变量");
+    }
+
+    #[test]
+    fn test_parses_hover_markdown() {
+        let blocks = parse_hover_markdown("**方法** `取长度`\n返回 `文本` 的长度\n\n```结绳\n变量 a = 1\n```\n未闭合的 ` 和 **");
+        assert_eq!(
+            blocks,
+            vec![
+                HoverBlock::Text {
+                    text: "方法 取长度\n返回 文本 的长度".to_string(),
+                    styles: vec![
+                        (0..6, InlineStyle::Bold),
+                        (7..16, InlineStyle::Code),
+                        (24..30, InlineStyle::Code),
+                    ],
+                },
+                HoverBlock::Code { language: Some("结绳".to_string()), code: "变量 a = 1".to_string() },
+                HoverBlock::Text { text: "未闭合的 ` 和 **".to_string(), styles: Vec::new() },
+            ]
+        );
+        assert_eq!(
+            parse_hover_markdown("```\nx"),
+            vec![HoverBlock::Code { language: None, code: "x".to_string() }]
+        );
+    }
+}
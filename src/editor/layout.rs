@@ -1,10 +1,28 @@
 use gpui::*;
+use std::ops::Range;
+use std::sync::Arc;
 
-#[derive(Clone, Copy)]
+use super::folding::HiddenLines;
+
+/// 插入在某一行下方、占据文档空间的块（如查看定义），其下的行随之下移
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutBlock {
+    pub after_line: usize,
+    pub height: Pixels,
+}
+
+#[derive(Clone)]
 pub struct EditorLayout {
     pub font_size: Pixels,
     pub scroll_offset: Point<Pixels>,
     pub last_bounds: Option<Bounds<Pixels>>,
+    pub block: Option<LayoutBlock>,
+    /// 画过的行中排版后最宽的宽度，用于水平滚动范围
+    pub content_width: Pixels,
+    /// `content_width` 所在的行
+    pub widest_line: usize,
+    /// 折起而不显示的行
+    pub hidden: Arc<HiddenLines>,
 }
 
 impl EditorLayout {
@@ -13,6 +31,10 @@ impl EditorLayout {
             font_size: px(14.0),
             scroll_offset: point(px(0.0), px(0.0)),
             last_bounds: None,
+            block: None,
+            content_width: px(0.0),
+            widest_line: 0,
+            hidden: Arc::new(HiddenLines::default()),
         }
     }
 
@@ -23,24 +45,65 @@ impl EditorLayout {
     pub fn gutter_width(&self, max_digits: usize) -> Pixels {
         let digit_width = self.font_size * 0.75; // Approximation for digit width
         let padding = px(16.0); // 8px left + 8px right
-        digit_width * (max_digits as f32) + padding
+        digit_width * (max_digits as f32) + padding + self.fold_column_width()
+    }
+
+    /// 行号右侧放折叠按钮的一列
+    pub fn fold_column_width(&self) -> Pixels {
+        self.font_size
+    }
+
+    /// 折叠按钮所在列的横向范围
+    pub fn fold_column(&self, bounds: Bounds<Pixels>, max_digits: usize) -> Range<Pixels> {
+        let right = bounds.left() + self.gutter_width(max_digits) - px(4.0);
+        right - self.fold_column_width()..right
+    }
+
+    /// 文本区起点到编辑区左边的距离
+    fn text_left(&self, max_digits: usize) -> Pixels {
+        self.gutter_width(max_digits) + px(8.0)
     }
 
     pub fn text_x(&self, bounds: Bounds<Pixels>, max_digits: usize) -> Pixels {
-        bounds.left() + self.gutter_width(max_digits) + px(8.0) + self.scroll_offset.x
+        bounds.left() + self.text_left(max_digits) + self.scroll_offset.x
+    }
+
+    /// 行顶部在文档中的位置，计入插入块的高度并跳过折起的行；折起的行位于其折叠首行处
+    pub fn line_top(&self, line_index: usize) -> Pixels {
+        let shift = match self.block {
+            Some(block) if line_index > block.after_line => block.height,
+            _ => px(0.0),
+        };
+        self.line_height() * self.hidden.row_of(line_index) as f32 + shift
     }
 
     pub fn line_y(&self, bounds: Bounds<Pixels>, line_index: usize) -> Pixels {
-        bounds.top() + self.line_height() * line_index as f32 + self.scroll_offset.y
+        bounds.top() + self.line_top(line_index) + self.scroll_offset.y
     }
 
+    /// 插入块顶部在文档中的位置
+    pub fn block_top(&self) -> Option<Pixels> {
+        let block = self.block?;
+        Some(self.line_top(block.after_line) + self.line_height())
+    }
+
+
+    /// 落在插入块内的位置算作块上方的那一行
     pub fn line_index_for_y(&self, bounds: Bounds<Pixels>, y: Pixels) -> usize {
-        let local_y = y - bounds.top() - self.scroll_offset.y;
+        let mut local_y = y - bounds.top() - self.scroll_offset.y;
         let line_height = self.line_height();
         if line_height <= px(0.0) {
             return 0;
         }
-        (local_y / line_height).floor().max(0.0) as usize
+        if let Some(block) = self.block {
+            let block_top = line_height * (self.hidden.row_of(block.after_line) + 1) as f32;
+            if local_y >= block_top + block.height {
+                local_y -= block.height;
+            } else if local_y >= block_top {
+                return block.after_line;
+            }
+        }
+        self.hidden.line_of_row((local_y / line_height).floor().max(0.0) as usize)
     }
 
     pub fn scroll(&mut self, delta: Point<Pixels>, max_scroll: Point<Pixels>) {
@@ -49,6 +112,49 @@ impl EditorLayout {
         self.scroll_offset.x = self.scroll_offset.x.clamp(-max_scroll.x, px(0.0));
     }
 
+    /// 记下本帧画出的行 `visible` 排版后的宽度。之前最宽的行再次画出时按新的宽度重新取最大，
+    /// 这样行变短后内容宽度也会跟着变小
+    pub fn observe_line_widths(&mut self, visible: Range<usize>, widths: &[(usize, Pixels)]) {
+        let widest = widths
+            .iter()
+            .copied()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap_or((visible.start, px(0.0)));
+        if visible.contains(&self.widest_line) || widest.1 >= self.content_width {
+            self.widest_line = widest.0;
+            self.content_width = widest.1;
+        }
+    }
+
+    /// 换了文档或字号后重新测量内容宽度
+    pub fn reset_content_width(&mut self) {
+        self.content_width = px(0.0);
+        self.widest_line = 0;
+    }
+
+    /// 水平方向可滚动的距离，右侧留出垂直滚动条和一点空白
+    pub fn max_scroll_x(&self, bounds: Bounds<Pixels>, max_digits: usize) -> Pixels {
+        let view_width = bounds.size.width - self.text_left(max_digits) - self.scrollbar_width();
+        (self.content_width + px(40.0) - view_width).max(px(0.0))
+    }
+
+    /// 水平滚动使文本区中相对行首 `x` 处可见，两侧各留 `margin`
+    pub fn reveal_x(&mut self, bounds: Bounds<Pixels>, max_digits: usize, x: Pixels, margin: Pixels) {
+        let view_width = (bounds.size.width - self.text_left(max_digits) - self.scrollbar_width()).max(px(0.0));
+        let margin = margin.min(view_width / 2.0);
+        let scroll = -self.scroll_offset.x;
+        let scroll = if x < scroll + margin {
+            (x - margin).max(px(0.0))
+        } else if x > scroll + view_width - margin {
+            x - view_width + margin
+        } else {
+            return;
+        };
+        // 光标所在处一定在内容之内，即使那一行还没有画过
+        self.content_width = self.content_width.max(x);
+        self.scroll_offset.x = -scroll;
+    }
+
     pub fn zoom(&mut self, delta: Pixels) {
         self.font_size = (self.font_size + delta).clamp(px(6.0), px(100.0));
     }
@@ -58,8 +164,14 @@ impl EditorLayout {
         px(14.0)
     }
 
+    /// 右侧垂直滚动条的轨道
+    pub fn track_bounds(&self, bounds: Bounds<Pixels>) -> Bounds<Pixels> {
+        let scrollbar_width = self.scrollbar_width();
+        Bounds::new(point(bounds.right() - scrollbar_width, bounds.top()), size(scrollbar_width, bounds.size.height))
+    }
+
     pub fn content_height(&self, line_count: usize) -> Pixels {
-        self.line_height() * line_count as f32
+        self.line_height() * self.hidden.visible_count(line_count) as f32 + self.block.map(|block| block.height).unwrap_or(px(0.0))
     }
 
     pub fn thumb_bounds(
@@ -95,4 +207,112 @@ impl EditorLayout {
             size(scrollbar_width, thumb_height),
         )
     }
+
+    /// 底部水平滚动条的轨道，从行号栏右侧到垂直滚动条左侧
+    pub fn h_track_bounds(&self, bounds: Bounds<Pixels>, max_digits: usize) -> Bounds<Pixels> {
+        let scrollbar_width = self.scrollbar_width();
+        Bounds::from_corners(
+            point(bounds.left() + self.gutter_width(max_digits), bounds.bottom() - scrollbar_width),
+            point(bounds.right() - scrollbar_width, bounds.bottom()),
+        )
+    }
+
+    /// 水平滚动条的滑块；内容没有超出编辑区时为空
+    pub fn h_thumb_bounds(&self, bounds: Bounds<Pixels>, max_digits: usize) -> Bounds<Pixels> {
+        let max_scroll_x = self.max_scroll_x(bounds, max_digits);
+        if max_scroll_x <= px(0.0) {
+            return Bounds::default();
+        }
+        let track = self.h_track_bounds(bounds, max_digits);
+        let view_width = track.size.width;
+        let thumb_width = (view_width / (view_width + max_scroll_x) * view_width).max(px(20.0)).min(view_width);
+        let ratio = (-self.scroll_offset.x / max_scroll_x).clamp(0.0, 1.0);
+        Bounds::new(
+            point(track.left() + (view_width - thumb_width) * ratio, track.top()),
+            size(thumb_width, track.size.height),
+        )
+    }
+
+    /// 从 `start`（开始拖动时的 scroll_offset.x）拖动水平滑块 `delta_x` 后的滚动位置
+    pub fn h_scroll_for_drag(&self, bounds: Bounds<Pixels>, max_digits: usize, start: Pixels, delta_x: Pixels) -> Pixels {
+        let max_scroll_x = self.max_scroll_x(bounds, max_digits);
+        let travel = self.h_track_bounds(bounds, max_digits).size.width - self.h_thumb_bounds(bounds, max_digits).size.width;
+        if travel <= px(0.0) {
+            return start;
+        }
+        (start - max_scroll_x * (delta_x / travel)).clamp(-max_scroll_x, px(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EditorLayout;
+    use crate::editor::folding::HiddenLines;
+    use gpui::{point, px, size, Bounds};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_horizontal_scroll_follows_measured_line_widths() {
+        let bounds = Bounds::new(point(px(0.0), px(0.0)), size(px(600.0), px(400.0)));
+        let mut layout = EditorLayout::new();
+        assert_eq!(layout.h_thumb_bounds(bounds, 2), Bounds::default());
+
+        // 第 30 行最宽；滚到别处后它不在画面内，宽度保留
+        layout.observe_line_widths(0..40, &[(3, px(200.0)), (30, px(1500.0))]);
+        layout.observe_line_widths(100..140, &[(120, px(300.0))]);
+        assert_eq!(layout.content_width, px(1500.0));
+        let max_scroll_x = layout.max_scroll_x(bounds, 2);
+        assert!(max_scroll_x > px(0.0));
+
+        // 拖到轨道末端正好滚到最右
+        let track = layout.h_track_bounds(bounds, 2);
+        let thumb = layout.h_thumb_bounds(bounds, 2);
+        assert_eq!(thumb.left(), track.left());
+        assert!(thumb.size.width < track.size.width);
+        let end = layout.h_scroll_for_drag(bounds, 2, px(0.0), track.size.width);
+        assert_eq!(end, -max_scroll_x);
+        layout.scroll_offset.x = end;
+        assert!((layout.h_thumb_bounds(bounds, 2).right() - track.right()).abs() < px(0.01));
+
+        // 最宽的行变短后再画出时，内容宽度随之变小
+        layout.observe_line_widths(20..60, &[(30, px(100.0)), (45, px(700.0))]);
+        assert_eq!((layout.widest_line, layout.content_width), (45, px(700.0)));
+    }
+
+    #[test]
+    fn test_reveal_x_scrolls_only_when_cursor_leaves_view() {
+        let bounds = Bounds::new(point(px(0.0), px(0.0)), size(px(600.0), px(400.0)));
+        let mut layout = EditorLayout::new();
+        let margin = px(20.0);
+
+        layout.reveal_x(bounds, 2, px(100.0), margin);
+        assert_eq!(layout.scroll_offset.x, px(0.0));
+
+        // 光标越过右边，滚到它刚好在右侧留白之内
+        layout.reveal_x(bounds, 2, px(2000.0), margin);
+        let scroll = -layout.scroll_offset.x;
+        assert!(scroll > px(0.0));
+        assert!(layout.content_width >= px(2000.0));
+        layout.reveal_x(bounds, 2, px(1990.0), margin);
+        assert_eq!(-layout.scroll_offset.x, scroll);
+
+        // 回到行首
+        layout.reveal_x(bounds, 2, px(0.0), margin);
+        assert_eq!(layout.scroll_offset.x, px(0.0));
+    }
+
+    #[test]
+    fn test_folded_lines_take_no_space() {
+        let bounds = Bounds::new(point(px(0.0), px(0.0)), size(px(600.0), px(400.0)));
+        let mut layout = EditorLayout::new();
+        let line_height = layout.line_height();
+        // 第 3..8 行折起在第 2 行之下
+        layout.hidden = Arc::new(HiddenLines::new(std::iter::once(3..8)));
+
+        assert_eq!(layout.line_y(bounds, 8), line_height * 3.0);
+        assert_eq!(layout.line_y(bounds, 5), layout.line_y(bounds, 2));
+        assert_eq!(layout.line_index_for_y(bounds, line_height * 2.5), 2);
+        assert_eq!(layout.line_index_for_y(bounds, line_height * 3.5), 8);
+        assert_eq!(layout.content_height(20), line_height * 15.0);
+    }
 }
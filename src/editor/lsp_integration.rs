@@ -1,11 +1,26 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use anyhow::anyhow;
 use gpui::*;
 use log::{info, warn};
+use ropey::Rope;
 use url::Url;
 
-use crate::plugin::lsp::LspPlugin;
+use crate::lsp::stdio_client::{self, LanguageServer, PublishedDiagnostics};
+use crate::lsp::tiec::guard::HUNG_AFTER;
+use crate::lsp::tiec::types::{
+    CodeActionItem, CursorParams, DefinitionResult, LintResult, Location, Position, Range, RenameResult, RenameSymbolInfo,
+    SmartEnterResult, TextChange, TextEdit,
+};
+use crate::lsp::tiec::worker::{Reply, TiecWorker};
+use crate::lsp::tiec::wrapper::TiecIdeService;
+use crate::plugin::lsp::{missing_library_message, LspPlugin};
 use crate::editor::completion::{CompletionItem, CompletionKind};
+use crate::editor::core::ContentChange;
+use crate::editor::format::formatted_text;
+use crate::editor::outline::{flatten_elements, workspace_symbols, OutlineSymbol, WorkspaceSymbol};
+use crate::editor::peek::index_for_char_position;
 
 pub fn default_doc_uri(path: &Path) -> String {
     if let Ok(url) = Url::from_file_path(path) {
@@ -20,6 +35,282 @@ pub fn default_doc_uri(path: &Path) -> String {
     }
 }
 
+/// tiec 诊断等级：0 DEBUG, 1 INFO, 2 WARNING, 3 ERROR
+pub const DIAGNOSTIC_LEVEL_WARNING: i32 = 2;
+pub const DIAGNOSTIC_LEVEL_ERROR: i32 = 3;
+
+/// 符号定义所在的位置（行列均从 0 开始）
+#[derive(Clone, Debug, PartialEq)]
+pub struct DefinitionLocation {
+    pub path: PathBuf,
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+/// 一条查错结果中的错误（行列均从 0 开始）
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// 查找定义的结果所在的文件和范围；不是本地文件时为 None
+pub fn definition_location(result: DefinitionResult) -> Option<DefinitionLocation> {
+    local_location(result.location)
+}
+
+fn local_location(location: Location) -> Option<DefinitionLocation> {
+    let path = Url::parse(&location.uri).ok()?.to_file_path().ok()?;
+    let range = location.range;
+    Some(DefinitionLocation {
+        path,
+        start: (range.start.line, range.start.column),
+        end: (range.end.line, range.end.column),
+    })
+}
+
+/// 重命名结果中各本地文件的编辑，按路径排序；不是本地文件的 URI 被忽略
+pub fn rename_file_edits(result: RenameResult) -> Vec<(PathBuf, Vec<TextEdit>)> {
+    let mut files: Vec<(PathBuf, Vec<TextEdit>)> = result
+        .project_edit
+        .into_iter()
+        .filter(|(_, edits)| !edits.is_empty())
+        .filter_map(|(uri, edits)| Some((Url::parse(&uri).ok()?.to_file_path().ok()?, edits)))
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files
+}
+
+/// 回车处要问 tiec 的内容：光标前的行内容，以及需要补全结束语句时光标的位置（行和 UTF-16 列）
+#[derive(Clone, Debug)]
+pub struct NewlineQuery {
+    pub before: String,
+    pub closer_at: Option<(usize, usize)>,
+}
+
+/// tiec 对一处回车的建议：缩进增量和需要补全的结束语句，没有时为 None
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NewlineHint {
+    pub advance: Option<i32>,
+    pub closer: Option<String>,
+}
+
+/// 在结绳文件中回车时 tiec 给出的智能键入
+#[derive(Clone, Debug, PartialEq)]
+pub enum SmartEnter {
+    /// 选择文件，用 `format` 中的 `%s` 代入路径后替换 `range`
+    PickFile { range: Range, format: String },
+    /// 从枚举常量中选择一个替换
+    Choose(Vec<CodeActionItem>),
+    /// 直接替换，如切换真/假
+    Replace(TextEdit),
+}
+
+/// 把选中的值代入替换格式中的 `%s`；格式为空时直接用这个值
+pub fn fill_replace_format(format: &str, value: &str) -> String {
+    if format.contains("%s") {
+        format.replacen("%s", value, 1)
+    } else {
+        value.to_string()
+    }
+}
+
+/// 把智能键入结果换成回车时要做的操作；未知类型或没有可选值时返回 None，回车照常换行
+pub fn smart_enter_action(result: SmartEnterResult) -> Option<SmartEnter> {
+    let fill = |value: &str| fill_replace_format(&result.replace_format, value);
+    match result.kind {
+        1 => Some(SmartEnter::PickFile { range: result.range, format: result.replace_format }),
+        2 if !result.enums.is_empty() => Some(SmartEnter::Choose(
+            result
+                .enums
+                .iter()
+                .map(|value| CodeActionItem {
+                    title: value.clone(),
+                    edits: vec![TextEdit { range: result.range.clone(), new_text: fill(value) }],
+                })
+                .collect(),
+        )),
+        3 => Some(SmartEnter::Replace(TextEdit {
+            range: result.range.clone(),
+            new_text: fill(if result.is_true { "假" } else { "真" }),
+        })),
+        _ => None,
+    }
+}
+
+/// 把 tiec 的编辑（行列从 0 开始，列按字符计）换成 `content` 中的字节范围
+pub fn text_edit_ranges(content: &Rope, edits: &[TextEdit]) -> Vec<(std::ops::Range<usize>, String)> {
+    let index = |position: &Position| index_for_char_position(content, position.line, position.column);
+    edits
+        .iter()
+        .map(|edit| (index(&edit.range.start)..index(&edit.range.end), edit.new_text.clone()))
+        .collect()
+}
+
+/// 补全结果中的补全项：结果本身是补全项数组，或是带 `items` 的对象
+pub fn completion_items(result: &serde_json::Value) -> Option<Vec<CompletionItem>> {
+    let items = result.as_array().or_else(|| result.get("items")?.as_array())?;
+    Some(
+        items
+            .iter()
+            .filter_map(|item| {
+                let label = item.get("label")?.as_str()?.to_string();
+                let kind = match item.get("kind").and_then(|kind| kind.as_i64()).unwrap_or(1) {
+                    2 | 3 => CompletionKind::Function,
+                    6 => CompletionKind::Variable,
+                    7 => CompletionKind::Class,
+                    14 => CompletionKind::Keyword,
+                    _ => CompletionKind::Text,
+                };
+                let detail = item.get("detail").and_then(|detail| detail.as_str()).unwrap_or("").to_string();
+                Some(CompletionItem { label, kind, detail })
+            })
+            .collect(),
+    )
+}
+
+/// tiec 插件及其工作线程。插件只在工作线程上使用，界面线程只投递请求，可以复制后交给后台任务
+#[derive(Clone)]
+pub struct TiecHandle {
+    plugin: Arc<Mutex<LspPlugin>>,
+    worker: TiecWorker,
+}
+
+impl TiecHandle {
+    /// 排队在工作线程上使用插件，不关心结果
+    fn post(&self, f: impl FnOnce(&mut LspPlugin) + Send + 'static) {
+        let plugin = self.plugin.clone();
+        self.worker.post(move || f(&mut plugin.lock().unwrap_or_else(PoisonError::into_inner)));
+    }
+
+    fn request_plugin<R: Send + 'static>(
+        &self,
+        slot: &'static str,
+        f: impl FnOnce(&mut LspPlugin) -> anyhow::Result<R> + Send + 'static,
+    ) -> Reply<anyhow::Result<R>> {
+        let plugin = self.plugin.clone();
+        self.worker.request(slot, move || f(&mut plugin.lock().unwrap_or_else(PoisonError::into_inner)))
+    }
+
+    /// 在工作线程上调用 IDE 服务；`slot` 中之后又有请求时，尚未开始的这次请求作废
+    pub fn request<R: Send + 'static>(
+        &self,
+        slot: &'static str,
+        f: impl FnOnce(&TiecIdeService) -> anyhow::Result<R> + Send + 'static,
+    ) -> Reply<anyhow::Result<R>> {
+        self.request_plugin(slot, move |plugin| {
+            let service = plugin.service().ok_or_else(|| anyhow!("tiec service is not initialized"))?;
+            f(&service)
+        })
+    }
+
+    /// 作废 `slot` 中还在排队的请求
+    pub fn cancel(&self, slot: &'static str) {
+        self.worker.cancel(slot);
+    }
+
+    /// 在后台线程上等待 IDE 服务的结果；请求被更新的请求取代或 tiec 卡住时返回错误
+    fn call<R: Send + 'static>(
+        &self,
+        slot: &'static str,
+        f: impl FnOnce(&TiecIdeService) -> anyhow::Result<R> + Send + 'static,
+    ) -> anyhow::Result<R> {
+        self.request(slot, f).wait_timeout(HUNG_AFTER).unwrap_or_else(|| Err(no_reply(slot)))
+    }
+}
+
+fn no_reply(slot: &str) -> anyhow::Error {
+    anyhow!("{} request was superseded or tiec did not reply within {}s", slot, HUNG_AFTER.as_secs())
+}
+
+/// 项目编译的结果：编译的文件和整个项目的诊断
+pub type CompileReply = Reply<anyhow::Result<(Vec<PathBuf>, LintResult)>>;
+
+/// 交给后台任务调用的语言服务：结绳文件为 tiec，配置了外部语言服务器的文件为对应的服务器
+#[derive(Clone)]
+pub enum LanguageBackend {
+    Tiec(TiecHandle),
+    Stdio(Arc<LanguageServer>),
+}
+
+impl LanguageBackend {
+    /// 光标处以 `prefix` 开头的补全项；`trigger` 为刚输入的触发字符，不是由它触发时为空
+    pub fn completion(&self, uri: &str, line: usize, column: usize, prefix: &str, trigger: &str) -> anyhow::Result<Vec<CompletionItem>> {
+        let result = match self {
+            Self::Tiec(tiec) => {
+                let (uri, prefix, trigger) = (uri.to_string(), prefix.to_string(), trigger.to_string());
+                tiec.request_plugin("completion", move |plugin| plugin.completion(&uri, line, column, 0, &prefix, &trigger))
+                    .wait_timeout(HUNG_AFTER)
+                    .unwrap_or_else(|| Err(no_reply("completion")))?
+            }
+            Self::Stdio(server) => server.completion(uri, line, column)?,
+        };
+        let mut items = completion_items(&result).unwrap_or_default();
+        items.retain(|item| item.label.starts_with(prefix));
+        Ok(items)
+    }
+
+    /// 光标处的悬停文档（Markdown）；没有文档时为空
+    pub fn hover(&self, uri: &str, line: usize, column: usize) -> anyhow::Result<String> {
+        match self {
+            Self::Tiec(tiec) => {
+                let params = cursor_params(uri, line, column);
+                tiec.call("hover", move |service| Ok(service.hover(&params)?.text))
+            }
+            Self::Stdio(server) => server.hover(uri, line, column),
+        }
+    }
+
+    /// 光标处符号的定义；找不到或不在本地文件中时为 None
+    pub fn definition(&self, uri: &str, line: usize, column: usize) -> anyhow::Result<Option<DefinitionLocation>> {
+        match self {
+            Self::Tiec(tiec) => {
+                let params = cursor_params(uri, line, column);
+                tiec.call("definition", move |service| Ok(definition_location(service.find_definition(&params)?)))
+            }
+            Self::Stdio(server) => Ok(server.definition(uri, line, column)?.into_iter().find_map(local_location)),
+        }
+    }
+}
+
+fn text_change(change: &ContentChange) -> TextChange {
+    let position = |(line, column): (usize, usize)| Position { line, column };
+    TextChange { range: Range { start: position(change.start), end: position(change.end) }, new_text: change.text.clone() }
+}
+
+fn cursor_params(uri: &str, line: usize, column: usize) -> CursorParams {
+    CursorParams { uri: uri.to_string(), position: Position { line, column }, line_text: None }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProblemSeverity {
+    Error,
+    Warning,
+}
+
+impl ProblemSeverity {
+    /// tiec 诊断等级对应的严重程度；低于 WARNING 的不显示
+    pub fn from_level(level: i32) -> Option<Self> {
+        if level >= DIAGNOSTIC_LEVEL_ERROR {
+            Some(Self::Error)
+        } else if level == DIAGNOSTIC_LEVEL_WARNING {
+            Some(Self::Warning)
+        } else {
+            None
+        }
+    }
+}
+
+/// 问题列表中的一条诊断（行列均从 0 开始，列按字符计）
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub severity: ProblemSeverity,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LspRequestKind {
     #[allow(dead_code)]
@@ -36,8 +327,14 @@ pub struct LspManager {
     pub version: i32,
     pub doc_uri: String,
     pub root_uri: String,
-    plugin: Option<LspPlugin>,
+    tiec: Option<TiecHandle>,
     plugin_load_attempted: bool,
+    /// 当前文档使用的外部语言服务器；结绳文件或没有配置服务器时为 None
+    server: Option<Arc<LanguageServer>>,
+    /// 选定服务器后订阅的诊断推送，等编辑器取走
+    diagnostics: Option<flume::Receiver<PublishedDiagnostics>>,
+    /// 当前文档不交给语言服务（大文件模式），同步和请求都跳过
+    detached: bool,
 }
 
 impl LspManager {
@@ -47,8 +344,11 @@ impl LspManager {
             version: 1,
             doc_uri,
             root_uri: String::new(),
-            plugin: None,
+            tiec: None,
             plugin_load_attempted: false,
+            server: None,
+            diagnostics: None,
+            detached: false,
         }
     }
 
@@ -72,9 +372,10 @@ impl LspManager {
         }
     }
 
-    fn ensure_plugin(&mut self) -> Option<&mut LspPlugin> {
-        if self.plugin.is_some() || self.plugin_load_attempted {
-            return self.plugin.as_mut();
+    /// 第一次使用时加载 tiec 插件并启动它的工作线程
+    fn ensure_tiec(&mut self) -> Option<&TiecHandle> {
+        if self.tiec.is_some() || self.plugin_load_attempted {
+            return self.tiec.as_ref();
         }
 
         self.plugin_load_attempted = true;
@@ -83,7 +384,7 @@ impl LspManager {
         match loaded {
             Ok(Some(plugin)) => {
                 info!("LSP plugin loaded: {}", plugin.name());
-                self.plugin = Some(plugin);
+                self.tiec = Some(TiecHandle { plugin: Arc::new(Mutex::new(plugin)), worker: TiecWorker::spawn() });
             }
             Ok(None) => {}
             Err(err) => {
@@ -91,21 +392,103 @@ impl LspManager {
             }
         }
 
-        self.plugin.as_mut()
+        self.tiec.as_ref()
+    }
+
+    /// 在工作线程上初始化 IDE 服务并打开当前文档；首次打开项目时的编译也在那里进行
+    fn initialize_tiec(&mut self, content: &str) {
+        let root_uri = self.root_uri.clone();
+        let doc_uri = self.doc_uri.clone();
+        let content = content.to_string();
+        if let Some(tiec) = self.ensure_tiec() {
+            tiec.post(move |plugin| {
+                if let Err(err) = plugin.initialize(&root_uri, &doc_uri, &content) {
+                    warn!("LSP plugin initialize failed: {err}");
+                }
+            });
+        }
+    }
+
+    /// 按当前文档的扩展名和项目设置选择外部语言服务器并打开文档
+    fn open_in_server(&mut self, content: &str) {
+        self.close_in_server();
+        let extension = self.doc_uri.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+        if extension.is_empty() || extension == "t" || extension.contains('/') {
+            return;
+        }
+        let Some(root) = Url::parse(&self.root_uri).ok().and_then(|url| url.to_file_path().ok()) else {
+            return;
+        };
+        let Some(command) = stdio_client::load_servers(&root).remove(&extension) else {
+            return;
+        };
+        let server = stdio_client::shared_server(&self.root_uri, &command);
+        server.did_open(&self.doc_uri, stdio_client::language_id(&extension), content);
+        self.diagnostics = Some(server.subscribe_diagnostics());
+        self.server = Some(server);
+    }
+
+    fn close_in_server(&mut self) {
+        if let Some(server) = self.server.take() {
+            server.did_close(&self.doc_uri);
+        }
+        self.diagnostics = None;
+    }
+
+    /// 换用外部语言服务器后新订阅的诊断推送；每次订阅只能取走一次
+    pub fn take_diagnostics(&mut self) -> Option<flume::Receiver<PublishedDiagnostics>> {
+        self.diagnostics.take()
+    }
+
+    /// 交给后台任务调用的语言服务：有外部语言服务器时用它，否则为结绳文件的 tiec 服务
+    pub fn backend(&mut self) -> Option<LanguageBackend> {
+        if self.detached {
+            return None;
+        }
+        if let Some(server) = &self.server {
+            return Some(LanguageBackend::Stdio(server.clone()));
+        }
+        self.service().map(LanguageBackend::Tiec)
     }
 
     pub fn restart(&mut self, root_path: PathBuf, content: &str) {
         self.root_uri = default_doc_uri(&root_path);
+        self.open_in_server(content);
+        self.initialize_tiec(content);
+    }
+
+    /// 在工作线程上编译 `root` 下的全部结绳文件并对整个项目查错；之前还在排队的编译作废。
+    /// 项目设置在服务创建后改过时先按新设置重建服务，并重新登记当前文档的内容 `content`。
+    /// tiec 没有中途取消的接口，已经开始的编译会执行完，由调用方丢弃它的结果。找不到 tiec 库时返回提示
+    pub fn compile_project(&mut self, root: PathBuf, content: &str) -> Result<CompileReply, String> {
         let root_uri = self.root_uri.clone();
         let doc_uri = self.doc_uri.clone();
-        if let Some(plugin) = self.ensure_plugin() {
-            if let Err(err) = plugin.initialize(&root_uri, &doc_uri, content) {
-                warn!("LSP plugin initialize failed: {err}");
+        let content = content.to_string();
+        let tiec = self.ensure_tiec().ok_or_else(missing_library_message)?;
+        Ok(tiec.request_plugin("compile", move |plugin| {
+            if plugin.settings_changed() {
+                info!("Project settings changed, recreating the tiec service before compiling");
+                plugin.reset();
+                if !root_uri.is_empty() && !doc_uri.is_empty() {
+                    plugin.initialize(&root_uri, &doc_uri, &content)?;
+                }
             }
+            plugin.compile_project(&root)
+        }))
+    }
+
+    /// 项目设置变化后重新创建 IDE 服务，正在进行的分析会被丢弃
+    pub fn reload_project(&mut self, content: &str) {
+        if let Some(tiec) = &self.tiec {
+            tiec.post(LspPlugin::reset);
         }
+        self.initialize(content);
     }
 
     pub fn initialize(&mut self, content: &str) {
+        if self.detached {
+            return;
+        }
         if self.root_uri.is_empty() {
             if let Ok(url) = Url::parse(&self.doc_uri) {
                 if let Ok(path) = url.to_file_path() {
@@ -115,116 +498,240 @@ impl LspManager {
             }
         }
 
-        let root_uri = self.root_uri.clone();
+        self.open_in_server(content);
+        self.initialize_tiec(content);
+    }
+
+    pub fn notify_change(&mut self, content: &str) {
+        if self.detached {
+            return;
+        }
+        self.version += 1;
+        if let Some(server) = &self.server {
+            server.did_change(&self.doc_uri, content);
+        }
         let doc_uri = self.doc_uri.clone();
-        if let Some(plugin) = self.ensure_plugin() {
-            if let Err(err) = plugin.initialize(&root_uri, &doc_uri, content) {
-                warn!("LSP plugin initialize failed: {err}");
-            }
+        let version = self.version;
+        let content = content.to_string();
+        if let Some(tiec) = self.ensure_tiec() {
+            tiec.post(move |plugin| {
+                if let Err(err) = plugin.did_change(&doc_uri, version, &content) {
+                    warn!("LSP plugin didChange failed: {err}");
+                }
+            });
         }
     }
 
-    pub fn notify_change(&mut self, content: &str) {
+    /// 把一批编辑同步给语言服务：tiec 逐条增量修改，增量失败时在工作线程上改为整篇同步；
+    /// 外部语言服务器按全量文本同步，只在连接了服务器时才取出全文
+    pub fn notify_changes(&mut self, changes: &[ContentChange], content: &Rope) {
+        if self.detached {
+            return;
+        }
         self.version += 1;
+        if let Some(server) = &self.server {
+            server.did_change(&self.doc_uri, &content.to_string());
+        }
         let doc_uri = self.doc_uri.clone();
         let version = self.version;
-        if let Some(plugin) = self.ensure_plugin() {
-            if let Err(err) = plugin.did_change(&doc_uri, version, content) {
-                warn!("LSP plugin didChange failed: {err}");
-            }
+        let changes: Vec<TextChange> = changes.iter().map(text_change).collect();
+        // Rope 的复制只共享节点，不复制文本
+        let content = content.clone();
+        if let Some(tiec) = self.ensure_tiec() {
+            tiec.post(move |plugin| {
+                if let Err(err) = plugin.did_change_incremental(&doc_uri, &changes) {
+                    warn!("LSP plugin incremental change failed, resyncing {doc_uri}: {err}");
+                    if let Err(err) = plugin.did_change(&doc_uri, version, &content.to_string()) {
+                        warn!("LSP plugin didChange failed: {err}");
+                    }
+                }
+            });
         }
     }
 
     pub fn update_doc_uri(&mut self, new_uri: String, content: &str) {
+        self.close_in_server();
         self.doc_uri = new_uri;
         self.version = 1;
-        let root_uri = self.root_uri.clone();
+        self.detached = false;
+        self.open_in_server(content);
+        self.initialize_tiec(content);
+    }
+
+    /// 换到不交给语言服务的文档，只关闭之前的文档；之后的编辑和请求都不再发给服务
+    pub fn detach_doc_uri(&mut self, new_uri: String) {
+        self.close_in_server();
+        self.doc_uri = new_uri;
+        self.version = 1;
+        self.detached = true;
+    }
+
+    /// 在工作线程上查找光标处符号的定义；没有服务（插件未加载或非结绳文件）时返回 None
+    pub fn definitions(&mut self, line: usize, character: usize) -> Option<Reply<anyhow::Result<Vec<DefinitionLocation>>>> {
+        let tiec = self.service()?;
+        let params = cursor_params(&self.doc_uri, line, character);
+        Some(tiec.request("peek_definition", move |service| {
+            Ok(definition_location(service.find_definition(&params)?).into_iter().collect())
+        }))
+    }
+
+    /// 对已同步的内容取光标处可以重命名的符号；没有服务（插件未加载或非结绳文件）时返回 None
+    pub fn prepare_rename(&mut self, line: usize, column: usize) -> Option<Reply<anyhow::Result<RenameSymbolInfo>>> {
+        let tiec = self.service()?;
+        let params = cursor_params(&self.doc_uri, line, column);
+        Some(tiec.request("rename", move |service| service.prepare_rename(&params)))
+    }
+
+    /// 把光标处的符号重命名为 `new_name`，结果为各文件中的编辑；没有服务时返回 None
+    pub fn rename(&mut self, line: usize, column: usize, new_name: &str) -> Option<Reply<anyhow::Result<Vec<(PathBuf, Vec<TextEdit>)>>>> {
+        let tiec = self.service()?;
+        let params = cursor_params(&self.doc_uri, line, column);
+        let new_name = new_name.to_string();
+        Some(tiec.request("rename", move |service| Ok(rename_file_edits(service.rename(&params, &new_name)?))))
+    }
+
+    /// 对已同步的内容重新查错，只留下错误；没有查错服务（插件未加载或非结绳文件）时返回 None
+    pub fn lint_errors(&mut self) -> Option<Reply<anyhow::Result<Vec<LintError>>>> {
+        let tiec = self.service()?;
         let doc_uri = self.doc_uri.clone();
-        if let Some(plugin) = self.ensure_plugin() {
-            if let Err(err) = plugin.initialize(&root_uri, &doc_uri, content) {
-                warn!("LSP plugin initialize failed: {err}");
-            }
-        }
+        Some(tiec.request("save_lint", move |service| {
+            Ok(service
+                .lint_file(&doc_uri)?
+                .diagnostics
+                .into_iter()
+                .filter(|d| d.level >= DIAGNOSTIC_LEVEL_ERROR)
+                .map(|d| LintError {
+                    line: d.range.start.line,
+                    column: d.range.start.column,
+                    message: d.message,
+                })
+                .collect())
+        }))
     }
 
-    pub fn completion(&mut self, line: usize, character: usize, index: usize, prefix: &str, trigger_char: &str) -> Option<Vec<CompletionItem>> {
+    /// 格式化已同步的整个文档：优先用语义格式化，结果中没有文本时按 `content` 只调整缩进。
+    /// 没有服务（插件未加载或非结绳文件）时返回 None
+    pub fn format_document(&mut self, content: Rope) -> Option<Reply<anyhow::Result<String>>> {
+        let tiec = self.service()?;
         let doc_uri = self.doc_uri.clone();
-        if let Some(plugin) = self.ensure_plugin() {
-            match plugin.completion(&doc_uri, line, character, index, prefix, trigger_char) {
-                Ok(value) => {
-                    if let Some(items) = value.get("items").and_then(|i: &serde_json::Value| i.as_array()) {
-                        let result = items.iter().filter_map(|item: &serde_json::Value| {
-                             let label = item.get("label")?.as_str()?.to_string();
-                             let kind_int = item.get("kind").and_then(|k: &serde_json::Value| k.as_i64()).unwrap_or(1);
-                             let kind = match kind_int {
-                                 2 | 3 => CompletionKind::Function,
-                                 6 => CompletionKind::Variable,
-                                 7 => CompletionKind::Class,
-                                 14 => CompletionKind::Keyword,
-                  _ => CompletionKind::Text,
-                             };
-                             let detail = item.get("detail").and_then(|s: &serde_json::Value| s.as_str()).unwrap_or("").to_string();
-                             
-                             Some(CompletionItem {
-                                 label,
-                                 kind,
-                                 detail,
-                             })
-                        }).collect();
-                        return Some(result);
-                    }
-                }
-                Err(err) => {
-                    warn!("LSP plugin completion failed: {err}");
-                }
-            }
+        Some(tiec.request("format", move |service| {
+            service
+                .format(&doc_uri)
+                .ok()
+                .and_then(|result| formatted_text(&result))
+                .map(Ok)
+                .unwrap_or_else(|| service.format_text(&content.to_string()))
+        }))
+    }
+
+    /// 只按缩进格式化 `content`，用于不在编辑器中的结绳文件；tiec 未加载时返回 None
+    pub fn format_text(&mut self, content: String) -> Option<Reply<anyhow::Result<String>>> {
+        let tiec = self.ensure_tiec()?;
+        Some(tiec.request("format_text", move |service| service.format_text(&content)))
+    }
+
+    /// 交给后台任务调用的 tiec 服务，所有调用都在工作线程上排在已同步的编辑之后；
+    /// 没有服务（插件未加载或非结绳文件）时返回 None
+    pub fn service(&mut self) -> Option<TiecHandle> {
+        if self.detached || !self.doc_uri.ends_with(".t") {
+            return None;
         }
-        None
+        self.ensure_tiec().cloned()
     }
 
-    pub fn hover(&mut self, line: usize, character: usize, index: usize) -> Option<String> {
+    /// 已同步文档的大纲；没有服务（插件未加载或非结绳文件）时返回 None，tiec 没有大纲时结果为 None
+    pub fn outline(&mut self) -> Option<Reply<anyhow::Result<Option<Vec<OutlineSymbol>>>>> {
+        let tiec = self.service()?;
         let doc_uri = self.doc_uri.clone();
-        if let Some(plugin) = self.ensure_plugin() {
-            match plugin.hover(&doc_uri, line, character, index) {
-                Ok(value) => {
-                    // Expecting HoverResult { kind, text }
-                    if let Some(text) = value.get("text").and_then(|s| s.as_str()) {
-                        return Some(text.to_string());
-                    }
-                }
-                Err(err) => {
-                    warn!("LSP plugin hover failed: {err}");
-                }
-            }
-        }
-        None
+        Some(tiec.request_plugin("outline", move |plugin| {
+            Ok(plugin.source_elements(&doc_uri)?.map(|result| flatten_elements(&result.elements)))
+        }))
+    }
+
+    /// 在整个项目中搜索名称包含关键词的符号；没有编译器服务时返回 None
+    pub fn workspace_symbols(&mut self, keyword: &str) -> Option<Reply<anyhow::Result<Vec<WorkspaceSymbol>>>> {
+        let tiec = self.service()?;
+        let keyword = keyword.to_string();
+        Some(tiec.request_plugin("workspace_symbols", move |plugin| {
+            Ok(plugin.workspace_elements(&keyword)?.map(|result| workspace_symbols(&result.elements)).unwrap_or_default())
+        }))
+    }
+
+    /// 对已同步的内容取光标处的智能键入，没有可做的操作时结果为 None；没有服务时返回 None
+    pub fn smart_enter(&mut self, line: usize, column: usize) -> Option<Reply<anyhow::Result<Option<SmartEnter>>>> {
+        let tiec = self.service()?;
+        let params = cursor_params(&self.doc_uri, line, column);
+        Some(tiec.request("smart_enter", move |service| Ok(smart_enter_action(service.smart_enter(&params)?))))
+    }
+
+    /// 结绳文件换行时由编译器给出各处的缩进增量和需要补全的结束语句（如 `结束 如果`），
+    /// 按 `queries` 的顺序返回；所有光标放在同一个请求中，互不取代。没有服务时返回 None
+    pub fn newline_hints(&mut self, content: Rope, queries: Vec<NewlineQuery>) -> Option<Reply<anyhow::Result<Vec<NewlineHint>>>> {
+        let tiec = self.service()?;
+        Some(tiec.request_plugin("newline", move |plugin| {
+            let service = plugin.service();
+            // 只有需要补全结束语句时才取出全文
+            let text = queries.iter().any(|query| query.closer_at.is_some()).then(|| content.to_string());
+            Ok(queries
+                .iter()
+                .map(|query| {
+                    let advance = plugin.indent_advance(&query.before, query.before.chars().count()).unwrap_or_else(|err| {
+                        warn!("LSP plugin indentAdvance failed: {err}");
+                        None
+                    });
+                    let closer = query.closer_at.zip(service.as_ref()).zip(text.as_deref()).and_then(|(((line, column), service), text)| {
+                        match service.newline(text, line, column) {
+                            Ok(closer) => Some(closer.trim().to_string()).filter(|closer| !closer.is_empty()),
+                            Err(err) => {
+                                warn!("LSP plugin newline failed: {err}");
+                                None
+                            }
+                        }
+                    });
+                    NewlineHint { advance, closer }
+                })
+                .collect())
+        }))
     }
 
     pub fn notify_create_file(&mut self, path: &Path, content: &str) {
         let uri = default_doc_uri(path);
-        if let Some(plugin) = self.ensure_plugin() {
-            if let Err(err) = plugin.did_create_file(&uri, content) {
-                warn!("LSP plugin didCreateFile failed: {err}");
-            }
+        let content = content.to_string();
+        if let Some(tiec) = self.ensure_tiec() {
+            tiec.post(move |plugin| {
+                if let Err(err) = plugin.did_create_file(&uri, &content) {
+                    warn!("LSP plugin didCreateFile failed: {err}");
+                }
+            });
         }
     }
 
     pub fn notify_delete_file(&mut self, path: &Path) {
         let uri = default_doc_uri(path);
-        if let Some(plugin) = self.ensure_plugin() {
-            if let Err(err) = plugin.did_delete_file(&uri) {
-                warn!("LSP plugin didDeleteFile failed: {err}");
-            }
+        if let Some(tiec) = self.ensure_tiec() {
+            tiec.post(move |plugin| {
+                if let Err(err) = plugin.did_delete_file(&uri) {
+                    warn!("LSP plugin didDeleteFile failed: {err}");
+                }
+            });
         }
     }
 
     pub fn notify_rename_file(&mut self, old_path: &Path, new_path: &Path) {
         let old_uri = default_doc_uri(old_path);
         let new_uri = default_doc_uri(new_path);
-        if let Some(plugin) = self.ensure_plugin() {
-            if let Err(err) = plugin.did_rename_file(&old_uri, &new_uri) {
-                warn!("LSP plugin didRenameFile failed: {err}");
-            }
+        if let Some(tiec) = self.ensure_tiec() {
+            tiec.post(move |plugin| {
+                if let Err(err) = plugin.did_rename_file(&old_uri, &new_uri) {
+                    warn!("LSP plugin didRenameFile failed: {err}");
+                }
+            });
         }
     }
 }
+
+impl Drop for LspManager {
+    fn drop(&mut self) {
+        self.close_in_server();
+    }
+}
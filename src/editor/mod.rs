@@ -8,43 +8,80 @@ use std::sync::{Arc, Mutex};
 use std::path::PathBuf;
 use similar::TextDiff;
 use std::process::Command;
+use tiecode_plugin_api::EnterRuleContribution;
 use url::Url;
 
 // Value and Url removed
 
 pub mod block_map;
+pub mod comment;
 pub mod completion;
 pub mod core;
+pub mod enter_rules;
+pub mod find;
+pub mod folding;
+pub mod format;
 pub mod grammar;
+pub mod highlight;
+pub mod hover;
 pub mod layout;
 pub mod lsp_integration;
-pub mod undo;
+pub mod outline;
+pub mod overrides;
+pub mod peek;
+pub mod redraw;
+pub mod render_cache;
+pub mod scrollbar_marks;
+pub mod semantic;
+pub mod signature;
 
 #[cfg(test)]
 mod tests;
 
+use crate::component::name_input::NameInput;
 use crate::editor::block_map::BlockMap;
 use crate::editor::grammar::{
-    CPP_GRAMMAR,
-    CMAKE_GRAMMAR,
-    CSS_GRAMMAR,
-    HTML_GRAMMAR,
-    JAVA_GRAMMAR,
-    JAVASCRIPT_GRAMMAR,
-    JIESHENG_GRAMMAR,
-    JSON_GRAMMAR,
-    MARKDOWN_GRAMMAR,
-    PYTHON_GRAMMAR,
-    RUST_GRAMMAR,
-    SHELL_GRAMMAR,
-    TOML_GRAMMAR,
-    TYPESCRIPT_GRAMMAR,
-    YAML_GRAMMAR,
+    disable_grammar,
+    embedded_grammar,
+    grammar_count,
+    grammar_dependencies,
+    grammar_disabled,
+    grammar_index_for_path,
+    grammar_name,
+    grammar_source,
+    set_grammar_source,
+    JIESHENG_INDEX,
 };
-use crate::editor::lsp_integration::{LspManager, default_doc_uri};
-
-use self::core::{EditorCore, Selection};
-use self::layout::EditorLayout;
+use crate::editor::lsp_integration::{
+    DefinitionLocation, LanguageBackend, LintError, LspManager, NewlineHint, NewlineQuery, Problem, ProblemSeverity, SmartEnter,
+    default_doc_uri, fill_replace_format, text_edit_ranges,
+};
+use crate::lsp::stdio_client::PublishedDiagnostics;
+use crate::lsp::tiec::worker::Reply;
+use crate::lsp::tiec::types::{
+    CodeActionItem, CursorParams, Diagnostic, HighlightResult, LintResult, Position, RenameSymbolInfo, SignatureHelpParams, TextEdit,
+};
+use crate::output::{log_channel, OutputChannel};
+use crate::open_documents::minimal_edit;
+
+use self::comment::CommentTokens;
+use self::highlight::{HighlightChunk, HighlightJob, LoadedHighlights};
+use self::render_cache::LineKey;
+use self::scrollbar_marks::{MarkLane, ScrollbarMark, ScrollbarMarks};
+use self::completion::{completion_context, narrow, CompletionItem, CompletionMenuLayout, TriggeredCompletion, MAX_VISIBLE_ITEMS};
+use self::core::{ContentChange, EditorCore, LineEnding, Selection};
+use self::enter_rules::{compile_rules, grammar_rules, EnterRule, EnterRuleSpec};
+use self::find::{find_all, FindState};
+use self::folding::{Folds, HiddenLines};
+use self::format::format_edits;
+use self::hover::{identifier_bounds, parse_hover_markdown, HoverBlock, HoverDelays, HoverPlan, InlineStyle};
+use self::layout::{EditorLayout, LayoutBlock};
+use self::outline::{fallback_outline, OutlineSymbol, WorkspaceSymbol};
+use self::overrides::{EditorOverrides, OverrideRules};
+use self::peek::{block_height, index_for_char_position, location_label, PeekState, PEEK_CONTEXT_LINES, PEEK_HEADER_HEIGHT, PEEK_LIST_WIDTH};
+use self::redraw::RedrawBatch;
+use self::semantic::{overlay, semantic_style, SemanticHighlights, SemanticSpan};
+use self::signature::{active_parameter_range, enclosing_paren};
 use tiecode::sweetline::{Document, DocumentAnalyzer, Engine, HighlightSpan};
 
 actions!(
@@ -56,6 +93,18 @@ actions!(
         Enter,
         Left,
         Right,
+        WordLeft,
+        WordRight,
+        SelectWordLeft,
+        SelectWordRight,
+        LineStart,
+        LineEnd,
+        DocumentStart,
+        DocumentEnd,
+        SelectLineStart,
+        SelectLineEnd,
+        SelectDocumentStart,
+        SelectDocumentEnd,
         Up,
         Down,
         Tab,
@@ -73,17 +122,42 @@ actions!(
         CancelFind,
         Escape,
         GoToDefinition,
+        PeekDefinition,
+        FindReferences,
+        RenameSymbol,
         SignatureHelp,
-        FormatDocument
+        CodeAction,
+        FormatDocument,
+        ToggleLineComment,
+        ToggleBlockComment,
+        FoldAll,
+        UnfoldAll,
+        FoldLevel1,
+        ConvertToLF,
+        ConvertToCRLF,
+        AddCursorAbove,
+        AddCursorBelow
     ]
 );
 
+/// 自动缩进使用的一级缩进，与 Tab 键插入的内容一致
+const INDENT_UNIT: &str = "    ";
+
+/// 折起区域的首行末尾与占位符之间的距离
+const FOLD_PLACEHOLDER_GAP: Pixels = px(6.0);
+
+/// 单词移动时的字符分类
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DecorationColor {
     Gray,
-    #[allow(dead_code)]
     Yellow,
-    #[allow(dead_code)]
     Red,
     #[allow(dead_code)]
     Custom(u32),
@@ -123,15 +197,363 @@ pub struct Decoration {
     pub message: Option<String>,
 }
 
+/// 把针对文档旧版本 `since` 算出的装饰移到当前文本上；被之后的编辑改动过的装饰丢弃
+pub fn map_decorations(core: &EditorCore, since: u64, decorations: Vec<Decoration>) -> Vec<Decoration> {
+    decorations
+        .into_iter()
+        .filter_map(|d| {
+            let range = core.map_range(since, d.range.clone())?;
+            Some(Decoration { range, ..d })
+        })
+        .collect()
+}
+
+/// LSP 位置（行，UTF-16 列）对应的字节偏移
+fn lsp_point_to_offset(content: &Rope, line: usize, char_utf16: usize) -> usize {
+    if line >= content.len_lines() {
+         return content.len_bytes();
+    }
+    let line_start_byte = content.line_to_byte(line);
+    let line_slice = content.line(line);
+    
+    // Fast path: if char_utf16 is 0, return start of line
+    if char_utf16 == 0 {
+        return line_start_byte;
+    }
+
+    // Iterate chars to find byte offset matching utf16 length
+    let mut current_utf16 = 0;
+    let mut byte_offset_in_line = 0;
+    
+    for char in line_slice.chars() {
+         if current_utf16 >= char_utf16 {
+             break;
+         }
+         let len = char.len_utf16();
+         if current_utf16 + len > char_utf16 {
+             // pointing into middle of a char? return start of this char
+             break;
+         }
+         current_utf16 += len;
+         byte_offset_in_line += char.len_utf8();
+    }
+    
+    line_start_byte + byte_offset_in_line
+}
+
+/// 把查错结果转为波浪线装饰和问题列表，错误为红色、警告为黄色；空范围扩展到一个字符
+pub fn lint_decorations(content: &Rope, diagnostics: Vec<Diagnostic>) -> (Vec<Decoration>, Vec<Problem>) {
+    let mut decorations = Vec::new();
+    let mut problems = Vec::new();
+    for diagnostic in diagnostics {
+        let Some(severity) = ProblemSeverity::from_level(diagnostic.level) else {
+            continue;
+        };
+        let start = lsp_point_to_offset(content, diagnostic.range.start.line, diagnostic.range.start.column);
+        let mut end = lsp_point_to_offset(content, diagnostic.range.end.line, diagnostic.range.end.column).max(start);
+        if end == start {
+            let next = content.byte_to_char(start) + 1;
+            end = content.char_to_byte(next.min(content.len_chars()));
+        }
+        let line = content.byte_to_line(start);
+        let column = content.byte_to_char(start) - content.line_to_char(line);
+        decorations.push(Decoration {
+            range: start..end,
+            color: match severity {
+                ProblemSeverity::Error => DecorationColor::Red,
+                ProblemSeverity::Warning => DecorationColor::Yellow,
+            },
+            message: Some(diagnostic.message.clone()),
+        });
+        problems.push(Problem { line, column, message: diagnostic.message, severity });
+    }
+    (decorations, problems)
+}
+
+/// 整个项目的查错结果按文件转为问题列表，`files` 中没有诊断的文件对应空列表，用来清除之前的问题。
+/// 列按 `read` 读到的文件内容换算为字符；读不到的文件直接用诊断中的行列
+pub fn project_problems(
+    files: &[PathBuf],
+    diagnostics: Vec<Diagnostic>,
+    read: impl Fn(&std::path::Path) -> Option<String>,
+) -> Vec<(PathBuf, Vec<Problem>)> {
+    let mut by_file: std::collections::BTreeMap<PathBuf, Vec<Diagnostic>> =
+        files.iter().map(|path| (path.clone(), Vec::new())).collect();
+    for diagnostic in diagnostics {
+        let path = Url::parse(&diagnostic.uri)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .unwrap_or_else(|| PathBuf::from(&diagnostic.uri));
+        by_file.entry(path).or_default().push(diagnostic);
+    }
+    by_file
+        .into_iter()
+        .map(|(path, diagnostics)| {
+            let problems = match read(&path) {
+                Some(text) => lint_decorations(&Rope::from(text), diagnostics).1,
+                None => diagnostics
+                    .into_iter()
+                    .filter_map(|diagnostic| {
+                        let severity = ProblemSeverity::from_level(diagnostic.level)?;
+                        let start = diagnostic.range.start;
+                        Some(Problem { line: start.line, column: start.column, message: diagnostic.message, severity })
+                    })
+                    .collect(),
+            };
+            (path, problems)
+        })
+        .collect()
+}
+
+/// 重命名符号时在标识符下方显示的输入框
+struct RenameInput {
+    input: NameInput,
+    /// 原名及其在文档中的字节范围
+    name: String,
+    range: Range<usize>,
+    /// 打开时的文档版本，之后有编辑则不再重命名
+    version: u64,
+}
+
+/// 光标处可用的代码操作列表，显示在光标所在行下方
+struct CodeActionMenu {
+    actions: Vec<CodeActionItem>,
+    selected: usize,
+    /// 请求时的光标位置与文档版本，之后有编辑则不再应用
+    anchor: usize,
+    version: u64,
+}
+
+/// 回车时 tiec 给出的智能键入，没有可做的操作时为 None
+type SmartEnterReply = Reply<anyhow::Result<Option<SmartEnter>>>;
+
+/// 回车时各光标处要插入的内容，按选区起点索引
+#[derive(Clone, Default, PartialEq)]
+struct NewlineTexts {
+    texts: HashMap<usize, String>,
+    /// 插入后光标从插入内容的末尾往回移动的字节数
+    cursor_back: HashMap<usize, usize>,
+}
+
+/// 参数提示：签名、当前参数在签名中的字节范围，以及所在调用的左括号位置
+#[derive(Clone, Debug)]
+struct SignaturePopup {
+    signature: String,
+    active: Option<Range<usize>>,
+    open: usize,
+    /// 左括号在窗口中的位置，绘制前更新
+    position: Point<Pixels>,
+}
+
 #[derive(Clone, Debug)]
 struct HoverPopup {
-    text: String,
+    blocks: Vec<HoverBlock>,
+    /// 每块代码块的语法颜色，与 `blocks` 一一对应，段落为空
+    code_colors: Vec<Vec<(Range<usize>, Hsla)>>,
     position: Point<Pixels>,
     color: DecorationColor,
+    /// 弹出时的文档版本，之后有编辑则不再显示
+    version: u64,
+    /// 提示对应的文本范围（UTF-16），指针在其中移动时保持显示
+    range: Option<Range<usize>>,
+}
+
+impl HoverPopup {
+    /// 只有一段纯文本的提示，如诊断信息
+    fn plain(text: String, position: Point<Pixels>, color: DecorationColor, version: u64, range: Option<Range<usize>>) -> Self {
+        Self {
+            blocks: vec![HoverBlock::Text { text, styles: Vec::new() }],
+            code_colors: vec![Vec::new()],
+            position,
+            color,
+            version,
+            range,
+        }
+    }
+}
+
+/// 标签切走时保存的编辑状态
+pub struct EditorSnapshot {
+    core: EditorCore,
+    scroll_offset: Point<Pixels>,
+    overrides: EditorOverrides,
+    saved_content: Option<String>,
+    folds: Folds,
+    line_ending: LineEnding,
+    large_file: bool,
+}
+
+impl EditorSnapshot {
+    pub fn content(&self) -> String {
+        self.core.content.to_string()
+    }
+
+    /// 光标所在行列和纵向滚动位置，用于保存会话
+    pub fn view_position(&self) -> (usize, usize, f32) {
+        let (line, column) = self.core.line_col_for_offset(self.core.primary_selection().head);
+        (line, column, f32::from(self.scroll_offset.y))
+    }
+
+    /// 标签在后台被保存后更新差异基准
+    pub fn mark_saved(&mut self, content: &str) {
+        self.saved_content = Some(content.to_string());
+    }
+
+    /// 把后台标签的内容换成格式化后的 `formatted`，只替换有变化的部分，可以撤销
+    pub fn apply_formatted(&mut self, formatted: &str) {
+        let edits = format_edits(&self.content(), formatted);
+        self.apply_edits(edits);
+    }
+
+    /// 修改后台标签的内容，可以撤销；范围为编辑前内容中的字节偏移
+    pub fn apply_edits(&mut self, edits: Vec<(Range<usize>, String)>) {
+        for selection in &mut self.core.selections {
+            selection.anchor = comment::map_offset(selection.anchor, &edits);
+            selection.head = comment::map_offset(selection.head, &edits);
+        }
+        self.core.apply_edits(edits);
+    }
+}
+
+/// 未命名标签的占位路径前缀，这类路径不对应磁盘上的文件
+const UNTITLED_PREFIX: &str = "untitled:";
+
+/// 第 `index` 个未命名标签的占位路径
+pub fn untitled_path(index: usize) -> PathBuf {
+    PathBuf::from(format!("{}Untitled-{}", UNTITLED_PREFIX, index))
 }
 
+/// 未命名标签的显示名称，例如 `Untitled-1`；普通文件返回 None
+pub fn untitled_name(path: &std::path::Path) -> Option<&str> {
+    path.to_str()?.strip_prefix(UNTITLED_PREFIX)
+}
+
+/// 停止输入多久后重新计算 git 差异
+const GIT_DIFF_DELAY: Duration = Duration::from_millis(150);
+/// 查找栏输入或文档编辑停顿这么久之后重新查找匹配
+const FIND_DELAY: Duration = Duration::from_millis(100);
+/// 编辑停顿多久后在后台查错
+const LINT_DELAY: Duration = Duration::from_millis(500);
+/// 编辑停顿多久后向 tiec 请求语义高亮
+const SEMANTIC_DELAY: Duration = Duration::from_millis(300);
+/// 悬停提示的最大尺寸，内容更多时换行并在提示内滚动
+const HOVER_MAX_WIDTH: Pixels = px(480.0);
+const HOVER_MAX_HEIGHT: Pixels = px(320.0);
+
+/// 按行比较基准内容与当前内容，得到每行（当前内容中的行号）的差异标记。
+/// 比较前两边的换行都统一为 LF，只有换行符不同的行不算修改
+fn compute_git_diff(base: &str, content: &Rope) -> HashMap<usize, GitDiffStatus> {
+    let mut map = HashMap::new();
+    let base = LineEnding::Lf.normalize(base);
+    let current = content.to_string();
+    let current = LineEnding::Lf.normalize(&current);
+    let diff = TextDiff::from_lines(base.as_ref(), current.as_ref());
+    for op in diff.ops() {
+        match op.tag() {
+            similar::DiffTag::Delete => {
+                if op.new_range().start <= content.len_lines() {
+                    map.insert(op.new_range().start, GitDiffStatus::Deleted);
+                }
+            }
+            similar::DiffTag::Insert => {
+                for i in op.new_range() {
+                    map.insert(i, GitDiffStatus::Added);
+                }
+            }
+            similar::DiffTag::Replace => {
+                for i in op.new_range() {
+                    map.insert(i, GitDiffStatus::Modified);
+                }
+            }
+            similar::DiffTag::Equal => {}
+        }
+    }
+    map
+}
+
+/// 差异标记的颜色；未保存的修改用紫色系，与未提交的修改区分
+fn diff_color(status: GitDiffStatus, against_saved: bool) -> u32 {
+    match (status, against_saved) {
+        (GitDiffStatus::Added, false) => 0x2ea043,
+        (GitDiffStatus::Modified, false) => 0x005cc5,
+        (GitDiffStatus::Deleted, false) => 0xd73a49,
+        (GitDiffStatus::Added, true) => 0xa371f7,
+        (GitDiffStatus::Modified, true) => 0x8250df,
+        (GitDiffStatus::Deleted, true) => 0xd2a8ff,
+    }
+}
+
+/// 基准内容中与第 `line` 行所在差异块对应的文本：返回当前内容中该块的字节范围和基准中的原文，
+/// 原文的换行改为 `ending`
+fn diff_hunk_at(base: &str, content: &Rope, line: usize, ending: LineEnding) -> Option<(Range<usize>, String)> {
+    let base = LineEnding::Lf.normalize(base);
+    let current = content.to_string();
+    let current = LineEnding::Lf.normalize(&current);
+    let diff = TextDiff::from_lines(base.as_ref(), current.as_ref());
+    let op = diff.ops().iter().find(|op| {
+        let new_range = op.new_range();
+        match op.tag() {
+            similar::DiffTag::Equal => false,
+            similar::DiffTag::Delete => new_range.start == line,
+            _ => new_range.contains(&line),
+        }
+    })?;
+    let (old_range, new_range) = (op.old_range(), op.new_range());
+    let end_line = new_range.end.min(content.len_lines());
+    let range = content.line_to_byte(new_range.start)..content.line_to_byte(end_line);
+    Some((range, ending.normalize(&diff.old_slices()[old_range].concat()).into_owned()))
+}
+
+/// 复制的文本：各选区的内容按顺序以换行连接；`keep_empty` 时空选区也占一行，
+/// 列选择复制后各行仍然对齐。没有可复制的内容时为 None
+fn selections_text(content: &Rope, selections: &[Selection], keep_empty: bool) -> Option<String> {
+    let texts: Vec<String> = selections
+        .iter()
+        .filter(|selection| keep_empty || !selection.is_empty())
+        .map(|selection| content.byte_slice(selection.range()).to_string())
+        .collect();
+    if texts.iter().all(String::is_empty) {
+        return None;
+    }
+    Some(texts.join("\n"))
+}
+
+/// 列选择的一端：行号和相对文本起点的横坐标，不随滚动变化
+#[derive(Clone, Copy, Debug)]
+struct ColumnPoint {
+    line: usize,
+    x: Pixels,
+}
+
+/// 最近一次列选择：产生的选区和两端的横坐标。选区被别的操作改变后不再视为列选择
+#[derive(Clone, Debug)]
+struct ColumnSelection {
+    selections: Vec<Selection>,
+    anchor_x: Pixels,
+    head_x: Pixels,
+}
+
+/// Alt 拖动进行中的列选择
+#[derive(Clone, Debug)]
+struct ColumnDrag {
+    anchor: ColumnPoint,
+    /// 按下之前已有的选区，列选择加在它们之后
+    base: Vec<Selection>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum CodeEditorEvent {
     OpenFile(PathBuf),
+    /// 打开文件并跳到指定行列（均从 0 开始，列按字符计）
+    OpenLocation { path: PathBuf, line: usize, column: usize },
+    /// 当前文档的查错结果已更新
+    Problems(Vec<Problem>),
+    /// 在状态栏短暂显示的提示
+    StatusMessage(String),
+    /// 查找符号的引用
+    FindReferences(String),
+    /// 在工作区中把符号重命名，内容为 tiec 给出的各文件中的编辑
+    RenameSymbol { old_name: String, edits: Vec<(PathBuf, Vec<crate::lsp::tiec::types::TextEdit>)> },
 }
 
 impl EventEmitter<CodeEditorEvent> for CodeEditor {}
@@ -162,6 +584,11 @@ impl CodeLine {
         self.shaped.paint(origin, line_height, window, cx)
     }
 
+    /// 排版后的宽度
+    pub fn width(&self) -> Pixels {
+        self.shaped.width
+    }
+
     pub fn index_for_x(&self, x: Pixels) -> Option<usize> {
         let expanded_idx = self.shaped.index_for_x(x)?;
         match self.map_orig_to_expanded.binary_search(&expanded_idx) {
@@ -263,111 +690,336 @@ pub struct CodeEditor {
     pub focus_handle: FocusHandle,
     pub core: EditorCore,
     pub layout: EditorLayout,
-    render_cache: Arc<Mutex<LruCache<String, CodeLine>>>,
+    render_cache: Arc<Mutex<LruCache<LineKey, CodeLine>>>,
+    /// 渲染缓存键中的文档代数，整篇重新载入时更换
+    render_generation: u64,
     dragging_scrollbar: bool,
+    drag_selecting: bool,
+    drag_pointer: Option<Point<Pixels>>,
+    /// Alt 拖动时按矩形区域选择
+    column_drag: Option<ColumnDrag>,
+    column_selection: Option<ColumnSelection>,
+    drag_autoscroll_active: bool,
     drag_start_y: Option<Pixels>,
     scroll_start_y: Option<Pixels>,
+    /// 上一帧画在滚动条上的标记，点击标记时据此跳转
+    scrollbar_marks: ScrollbarMarks,
+    dragging_h_scrollbar: bool,
+    drag_start_x: Option<Pixels>,
+    scroll_start_x: Option<Pixels>,
+    /// 下一帧绘制前水平滚动到主光标处；光标所在行要排版后才知道位置
+    reveal_cursor_x: bool,
     sweetline_engine: Arc<Engine>,
+    /// 已注册的语法中已编译进引擎的；插件之后注册的语法在用到时补上
+    compiled_grammars: Vec<bool>,
     sweetline_document: Option<Document>,
     sweetline_analyzer: Option<DocumentAnalyzer>,
     cached_highlights: Vec<HighlightSpan>,
+    /// 高亮的版本号，整篇重新分析或整篇替换高亮时递增；旧版本的分块送达时丢弃
+    highlight_revision: u64,
+    /// 整篇分析进行中时为 Some，记下期间的编辑，分析器送回后补上
+    highlight_backlog: Option<Vec<ContentChange>>,
+    highlight_task: Option<Task<()>>,
     style_cache: HashMap<u32, Hsla>,
     decorations: Vec<Decoration>,
+    /// `decorations` 中的范围所对应的文档版本
+    decorations_version: u64,
     hover_popup: Option<HoverPopup>,
+    hover_delays: HoverDelays,
+    /// 等待中的悬停提示显示或隐藏；替换即取消上一次
+    hover_task: Option<Task<()>>,
+    /// 后台向语言服务请求的悬停文档；新的请求或指针移动会取消它
+    hover_request: Option<Task<()>>,
+    /// 回车时是否按规则补全块结构，来自项目设置
+    auto_close_blocks: bool,
+    /// 插件提供的回车规则
+    plugin_enter_rules: Vec<EnterRuleContribution>,
+    /// 当前文档适用的回车规则，按文档 uri 缓存
+    enter_rules: Option<(String, Vec<EnterRule>)>,
     pub lsp_manager: LspManager,
+    completion_active: bool,
+    completion_items: Vec<CompletionItem>,
+    completion_index: usize,
+    /// 给出补全项时的文档版本
+    completion_version: u64,
     completion_scroll_offset: f32,
+    /// 上次绘制的补全菜单，没有显示时为 None
+    completion_menu: Option<CompletionMenuLayout>,
+    /// 鼠标悬停的补全项
+    completion_hover: Option<usize>,
+    /// 拖动补全菜单滚动条时按下的位置和当时的滚动位置
+    completion_thumb_drag: Option<(Pixels, usize)>,
+    /// 由触发字符打开的补全，之后输入的成员名在本地筛选
+    completion_trigger: Option<TriggeredCompletion>,
     pub git_diff_map: HashMap<usize, GitDiffStatus>,
+    /// 输入期间延迟计算的 git 差异；替换即取消上一次
+    git_diff_task: Option<Task<()>>,
+    /// 编辑后延迟进行的后台查错；替换即取消上一次
+    lint_task: Option<Task<()>>,
+    /// 结绳文件的语义高亮，覆盖在语法高亮之上
+    semantic: SemanticHighlights,
+    /// 编辑后延迟请求的语义高亮；替换即取消上一次
+    semantic_task: Option<Task<()>>,
+    /// 后台进行的转到定义；替换即取消上一次
+    definition_task: Option<Task<()>>,
+    /// 后台进行的补全；替换即取消上一次
+    completion_task: Option<Task<()>>,
+    /// 接收外部语言服务器推送的诊断
+    diagnostics_task: Option<Task<()>>,
+    /// 重命名符号的输入框；打开时键盘输入交给它
+    rename: Option<RenameInput>,
+    /// 光标所在调用的参数提示
+    signature_popup: Option<SignaturePopup>,
+    signature_task: Option<Task<()>>,
+    /// 代码操作列表；打开时上下键和回车交给它
+    code_actions: Option<CodeActionMenu>,
+    code_action_task: Option<Task<()>>,
     pub git_base_content: Option<String>,
+    /// 打开或最后一次保存时的内容；不在 git 仓库中的文件用它作为差异基准
+    saved_content: Option<String>,
+    /// 查找栏；关闭时为 None
+    find: Option<FindState>,
+    find_task: Option<Task<()>>,
     pub block_map: BlockMap,
     pub block_highlight: Option<BlockHighlightState>,
     pub indent_guides: IndentGuideConfig,
     indent_guides_rng: u64,
+    /// 可折叠区域和折起的部分
+    folds: Folds,
+    /// 编辑增删了行，下次绘制前重新计算可折叠区域
+    folds_dirty: bool,
+    /// 回车和粘贴插入的换行符，打开文件时取文件中占多数的一种
+    line_ending: LineEnding,
+    /// 大文件模式：只读，不做语法高亮、差异标记，也不交给语言服务
+    large_file: bool,
+    /// 只读预览的文档 uri。预览只做语法高亮，不通知 LSP，也不读取 git 基准
+    preview_uri: Option<String>,
+    /// 当前缓冲区生效的按文件类型覆盖项；会话中的缩放、只读切换也记在这里，随标签保存
+    overrides: EditorOverrides,
+    /// 没有 fontSize 覆盖的缓冲区共用的字号
+    base_font_size: Pixels,
+    redraw: RedrawBatch,
+    /// 编辑后无法增量同步、整篇重新交给 sweetline 和语言服务的次数
+    full_syncs: u64,
+    /// 查看定义；打开时在锚点行下方插入嵌入编辑器
+    peek: Option<PeekState>,
+    peek_editor: Option<Entity<CodeEditor>>,
+    /// 自身是查看定义中的嵌入编辑器
+    is_peek_view: bool,
 }
 
 impl CodeEditor {
     pub fn new(cx: &mut Context<Self>, file_path: Option<PathBuf>) -> Self {
+        // 只编译当前文件需要的语法，其余由 `compile_next_grammar` 在启动后逐个编译
         let engine = Arc::new(Engine::new(true));
-        engine
-            .compile_json(CPP_GRAMMAR)
-            .expect("Failed to compile CPP grammar");
-        engine
-            .compile_json(RUST_GRAMMAR)
-            .expect("Failed to compile Rust grammar");
-        engine
-            .compile_json(JSON_GRAMMAR)
-            .expect("Failed to compile JSON grammar");
-        engine
-            .compile_json(CMAKE_GRAMMAR)
-            .expect("Failed to compile CMake grammar");
-        engine
-            .compile_json(TOML_GRAMMAR)
-            .expect("Failed to compile TOML grammar");
-        engine
-            .compile_json(YAML_GRAMMAR)
-            .expect("Failed to compile YAML grammar");
-        engine
-            .compile_json(PYTHON_GRAMMAR)
-            .expect("Failed to compile Python grammar");
-        engine
-            .compile_json(JAVASCRIPT_GRAMMAR)
-            .expect("Failed to compile JavaScript grammar");
-        engine
-            .compile_json(JAVA_GRAMMAR)
-            .expect("Failed to compile Java grammar");
-        engine
-            .compile_json(TYPESCRIPT_GRAMMAR)
-            .expect("Failed to compile TypeScript grammar");
-        engine
-            .compile_json(HTML_GRAMMAR)
-            .expect("Failed to compile HTML grammar");
-        engine
-            .compile_json(CSS_GRAMMAR)
-            .expect("Failed to compile CSS grammar");
-        engine
-            .compile_json(MARKDOWN_GRAMMAR)
-            .expect("Failed to compile Markdown grammar");
-        engine
-            .compile_json(SHELL_GRAMMAR)
-            .expect("Failed to compile Shell grammar");
-        engine
-            .compile_json(JIESHENG_GRAMMAR)
-            .expect("Failed to compile 结绳 grammar");
-
+        let has_file = file_path.is_some();
         let default_path = file_path.unwrap_or_else(|| std::env::temp_dir().join("untitled.t"));
         let doc_uri = default_doc_uri(&default_path);
-        let doc = Document::new(&doc_uri, "");
-        let analyzer = engine.load_document(&doc);
 
-        let mut editor = Self {
+        let mut editor = Self::with_engine(cx, engine, doc_uri);
+        editor.init_lsp_and_spawn_loop(cx);
+        if has_file {
+            editor.fetch_git_base_content(cx);
+        }
+        editor.sync_sweetline_document(cx); // Trigger block map update
+
+        editor
+    }
+
+    /// 查看定义中嵌入的只读编辑器：与宿主共用语法引擎，不连接 LSP
+    fn new_peek_view(engine: Arc<Engine>, cx: &mut Context<Self>) -> Self {
+        let doc_uri = default_doc_uri(&std::env::temp_dir().join("peek.t"));
+        let mut editor = Self::with_engine(cx, engine, doc_uri);
+        editor.is_peek_view = true;
+        editor
+    }
+
+    fn with_engine(cx: &mut Context<Self>, engine: Arc<Engine>, doc_uri: String) -> Self {
+        Self {
             focus_handle: cx.focus_handle(),
             core: EditorCore::new(),
             layout: EditorLayout::new(),
             render_cache: Arc::new(Mutex::new(LruCache::new(NonZeroUsize::new(1000).unwrap()))),
+            render_generation: render_cache::next_generation(),
             dragging_scrollbar: false,
+            drag_selecting: false,
+            drag_pointer: None,
+            column_drag: None,
+            column_selection: None,
+            drag_autoscroll_active: false,
             drag_start_y: None,
             scroll_start_y: None,
+            scrollbar_marks: ScrollbarMarks::default(),
+            dragging_h_scrollbar: false,
+            drag_start_x: None,
+            scroll_start_x: None,
+            reveal_cursor_x: false,
             sweetline_engine: engine,
-            sweetline_document: Some(doc),
-            sweetline_analyzer: Some(analyzer),
+            compiled_grammars: vec![false; grammar_count()],
+            sweetline_document: None,
+            sweetline_analyzer: None,
             cached_highlights: Vec::new(),
+            highlight_revision: 0,
+            highlight_backlog: None,
+            highlight_task: None,
             style_cache: HashMap::new(),
             decorations: Vec::new(),
+            decorations_version: 0,
             hover_popup: None,
+            hover_delays: HoverDelays::default(),
+            hover_task: None,
+            hover_request: None,
+            auto_close_blocks: true,
+            plugin_enter_rules: Vec::new(),
+            enter_rules: None,
             lsp_manager: LspManager::new(doc_uri),
+            completion_active: false,
+            completion_items: Vec::new(),
+            completion_index: 0,
+            completion_version: 0,
             completion_scroll_offset: 0.0,
+            completion_menu: None,
+            completion_hover: None,
+            completion_thumb_drag: None,
+            completion_trigger: None,
             git_diff_map: HashMap::new(),
+            git_diff_task: None,
+            lint_task: None,
+            semantic: SemanticHighlights::default(),
+            semantic_task: None,
+            definition_task: None,
+            completion_task: None,
+            diagnostics_task: None,
+            rename: None,
+            signature_popup: None,
+            signature_task: None,
+            code_actions: None,
+            code_action_task: None,
             git_base_content: None,
+            saved_content: None,
+            find: None,
+            find_task: None,
             block_map: BlockMap::new(),
             block_highlight: None,
             indent_guides: IndentGuideConfig::default(),
             indent_guides_rng: Self::seed_indent_guides_rng(),
-        };
+            folds: Folds::default(),
+            folds_dirty: true,
+            line_ending: LineEnding::default(),
+            large_file: false,
+            preview_uri: None,
+            overrides: EditorOverrides::default(),
+            base_font_size: EditorLayout::new().font_size,
+            redraw: RedrawBatch::default(),
+            full_syncs: 0,
+            peek: None,
+            peek_editor: None,
+            is_peek_view: false,
+        }
+    }
 
-        editor.init_lsp_and_spawn_loop(cx);
-        editor.fetch_git_base_content(cx);
-        editor.sync_sweetline_document(cx); // Trigger block map update
+    /// 编译语法及其嵌入的语法；已编译和已停用的跳过。资源目录中的语法编译失败时改用内置副本，
+    /// 插件提供的语法编译失败时停用这个语法
+    fn compile_grammar(&mut self, index: usize) {
+        self.compiled_grammars.resize(grammar_count(), false);
+        if self.compiled_grammars[index] || grammar_disabled(index) {
+            return;
+        }
+        self.compiled_grammars[index] = true;
+        for dependency in grammar_dependencies(index) {
+            self.compile_grammar(dependency);
+        }
+        let grammar = grammar_source(index);
+        if let Err(err) = self.sweetline_engine.compile_json(&grammar) {
+            let name = grammar_name(&grammar).unwrap_or_default();
+            match embedded_grammar(index) {
+                Some(embedded) if *grammar == *embedded => panic!("Failed to compile {} grammar: {:?}", name, err),
+                Some(_) => {
+                    eprintln!("Failed to compile {} grammar from assets, using embedded copy: {:?}", name, err);
+                    set_grammar_source(index, None);
+                    self.compiled_grammars[index] = false;
+                    self.compile_grammar(index);
+                }
+                None => {
+                    eprintln!("Failed to compile plugin grammar {}, disabling it: {:?}", name, err);
+                    disable_grammar(index);
+                }
+            }
+        }
+    }
 
-        editor
+    /// 语法文件改动后重新编译：先在单独的引擎中检查，出错时保留原来的语法并返回错误。
+    /// 通过后换用新引擎（sweetline 不能替换已编译的语法），语法按需重新编译，当前文档重新高亮
+    pub fn reload_grammar(&mut self, index: usize, source: String, cx: &mut Context<Self>) -> Result<(), String> {
+        fn check(engine: &Engine, index: usize, compiled: &mut [bool]) -> Result<(), String> {
+            if std::mem::replace(&mut compiled[index], true) {
+                return Ok(());
+            }
+            for dependency in grammar_dependencies(index) {
+                check(engine, dependency, compiled)?;
+            }
+            let grammar = grammar_source(index);
+            engine.compile_json(&grammar).map_err(|err| {
+                format!("{} 语法编译失败：{:?}", grammar_name(&grammar).unwrap_or_default(), err)
+            })
+        }
+
+        let previous = grammar_source(index);
+        set_grammar_source(index, Some(Arc::from(source)));
+        if let Err(err) = check(&Engine::new(true), index, &mut vec![false; grammar_count()]) {
+            set_grammar_source(index, Some(previous));
+            return Err(err);
+        }
+
+        self.sweetline_analyzer = None;
+        self.sweetline_document = None;
+        self.sweetline_engine = Arc::new(Engine::new(true));
+        self.compiled_grammars = vec![false; grammar_count()];
+        self.enter_rules = None;
+        self.style_cache.clear();
+        if let Ok(mut cache) = self.render_cache.lock() {
+            cache.clear();
+        }
+        // 查看定义的嵌入编辑器还在用旧引擎，下次打开时重新创建
+        self.close_peek(cx);
+        self.peek_editor = None;
+
+        let uri = self.preview_uri.clone().unwrap_or_else(|| self.lsp_manager.doc_uri.clone());
+        if uri.ends_with(".t") {
+            self.block_map.update(&self.core.content, &grammar_source(JIESHENG_INDEX));
+        }
+        self.load_highlights(uri, cx);
+        cx.notify();
+        Ok(())
+    }
+
+    /// 载入文档前确保其语言的语法已编译
+    fn ensure_grammar_for(&mut self, uri: &str) {
+        if let Some(index) = grammar_index_for_path(uri) {
+            self.compile_grammar(index);
+        }
+    }
+
+    /// 插件的语法有变化后，当前文档的语法还没有编译时重新载入文档，让会话中先打开的文件也能高亮
+    pub fn plugin_grammars_changed(&mut self, cx: &mut Context<Self>) {
+        if self.preview_uri.is_some() {
+            return;
+        }
+        let uri = self.lsp_manager.doc_uri.clone();
+        if grammar_index_for_path(&uri).is_some_and(|index| !self.compiled_grammars.get(index).copied().unwrap_or(false)) {
+            self.sync_sweetline_document(cx);
+            cx.notify();
+        }
+    }
+
+    /// 编译下一个尚未编译的语法，返回已编译数和总数（不含停用的）；全部编译完成时返回 None
+    pub fn compile_next_grammar(&mut self) -> Option<(usize, usize)> {
+        self.compiled_grammars.resize(grammar_count(), false);
+        let index = (0..self.compiled_grammars.len()).find(|&i| !self.compiled_grammars[i] && !grammar_disabled(i))?;
+        self.compile_grammar(index);
+        let enabled: Vec<usize> = (0..self.compiled_grammars.len()).filter(|&i| !grammar_disabled(i)).collect();
+        let done = enabled.iter().filter(|&&i| self.compiled_grammars[i]).count();
+        Some((done, enabled.len()))
     }
 
     fn seed_indent_guides_rng() -> u64 {
@@ -427,6 +1079,8 @@ impl CodeEditor {
                         if let Ok(output) = output {
                             if output.status.success() {
                                 if let Ok(content) = String::from_utf8(output.stdout) {
+                                    // 与缓冲区一致，按去掉 BOM 后的文本比较
+                                    let content = tiecode_buffer::strip_bom(&content).0.to_string();
                                     self.git_base_content = Some(content);
                                     self.update_git_diff(cx);
                                     return;
@@ -441,165 +1095,666 @@ impl CodeEditor {
         self.update_git_diff(cx);
     }
 
-    pub fn update_git_diff(&mut self, cx: &mut Context<Self>) {
-        self.git_diff_map.clear();
-        
-        if let Some(base) = &self.git_base_content {
-            let current = self.core.content.to_string();
-            let diff = TextDiff::from_lines(base, &current);
-            
-            for op in diff.ops() {
-                match op.tag() {
-                    similar::DiffTag::Delete => {
-                        // println!("Diff: Deleted at {}", op.new_range().start);
-                        if op.new_range().start <= self.core.content.len_lines() {
-                             self.git_diff_map.insert(op.new_range().start, GitDiffStatus::Deleted);
-                        }
-                    }
-                    similar::DiffTag::Insert => {
-                        // println!("Diff: Inserted at {:?}", op.new_range());
-                        for i in op.new_range() {
-                            self.git_diff_map.insert(i, GitDiffStatus::Added);
-                        }
-                    }
-                    similar::DiffTag::Replace => {
-                        // println!("Diff: Modified at {:?}", op.new_range());
-                        for i in op.new_range() {
-                            self.git_diff_map.insert(i, GitDiffStatus::Modified);
-                        }
-                    }
-                    similar::DiffTag::Equal => {}
-                }
-            }
+    /// 差异基准：有 git 基准时与提交的内容比较，否则与打开或最后一次保存时的内容比较
+    fn diff_base(&self) -> Option<&String> {
+        if self.large_file {
+            return None;
         }
-        cx.notify();
+        self.git_base_content.as_ref().or(self.saved_content.as_ref())
     }
 
-    pub fn perform_undo(&mut self, cx: &mut Context<Self>) {
-        self.core.undo();
-        self.sync_sweetline_document(cx);
-        cx.notify();
+    /// 差异标记表示未保存的修改而不是未提交的修改
+    pub fn diff_against_saved(&self) -> bool {
+        self.git_base_content.is_none() && self.saved_content.is_some()
     }
 
-    pub fn perform_redo(&mut self, cx: &mut Context<Self>) {
-        self.core.redo();
-        self.sync_sweetline_document(cx);
-        cx.notify();
+    /// 保存成功后以写入的内容作为新的基准
+    pub fn mark_saved(&mut self, content: &str, cx: &mut Context<Self>) {
+        self.saved_content = Some(content.to_string());
+        self.update_git_diff(cx);
+        self.schedule_lint(Duration::ZERO, cx);
     }
 
-    pub fn perform_select_all(&mut self, cx: &mut Context<Self>) {
-        self.core.select_all();
-        self.core.completion_active = false;
-        cx.notify();
+    pub fn update_git_diff(&mut self, cx: &mut Context<Self>) {
+        self.git_diff_task = None;
+        self.git_diff_map = match self.diff_base() {
+            Some(base) => compute_git_diff(base, &self.core.content),
+            None => HashMap::new(),
+        };
+        self.request_redraw(cx);
     }
 
-    pub fn perform_copy(&mut self, cx: &mut Context<Self>) {
-        let mut texts = Vec::new();
-        for selection in &self.core.selections {
-            if !selection.is_empty() {
-                texts.push(self.core.content.byte_slice(selection.range()).to_string());
-            }
-        }
-        if texts.is_empty() {
+    /// 把光标所在的差异块还原为基准中的内容（提交的或已保存的），可以撤销
+    pub fn revert_hunk(&mut self, cx: &mut Context<Self>) {
+        if self.is_read_only() {
             return;
         }
-        let text = texts.join("\n");
-        cx.write_to_clipboard(ClipboardItem::new_string(text));
+        let Some(base) = self.diff_base() else {
+            return;
+        };
+        let head = self.core.primary_selection().head;
+        let (line, _) = self.core.line_col_for_offset(head);
+        let Some((range, text)) = diff_hunk_at(base, &self.core.content, line, self.line_ending) else {
+            return;
+        };
+        self.core.replace_range(range, &text);
+        self.sync_edits(cx);
+        self.update_git_diff(cx);
     }
 
-    pub fn perform_cut(&mut self, cx: &mut Context<Self>) {
-        self.perform_copy(cx);
-        self.core.delete_selection();
-        self.sync_sweetline_document(cx);
-        cx.notify();
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
     }
 
-    pub fn perform_paste(&mut self, cx: &mut Context<Self>) {
-        if let Some(item) = cx.read_from_clipboard() {
-            if let Some(text) = item.text() {
-                self.insert_text(&text, cx);
-            }
+    /// 把整个文档的换行统一为 `ending`，之后插入的换行也用它；作为一步撤销
+    pub fn set_line_ending(&mut self, ending: LineEnding, cx: &mut Context<Self>) {
+        if self.is_read_only() {
+            return;
         }
+        self.line_ending = ending;
+        let edits = ending.conversion_edits(&self.core.content);
+        self.apply_edits(edits, cx);
+        cx.notify();
     }
 
-
-    fn paint_soft_shadow(window: &mut Window, bounds: Bounds<Pixels>, corner_radius: Pixels) {
-        let steps = 10;
-        let max_blur = px(24.0);
-        let max_alpha = 0.12;
-        let offset_y = px(4.0);
-
-        for i in 1..=steps {
-            let t = i as f32 / steps as f32;
-            let spread = max_blur * t;
-            let current_offset_y = offset_y * t;
-            
-            let shadow_bounds = Bounds::from_corners(
-                point(bounds.left() - spread, bounds.top() - spread + current_offset_y),
-                point(bounds.right() + spread, bounds.bottom() + spread + current_offset_y),
-            );
-            
-            let decay_factor = (1.0 - t).powi(2);
-            let current_alpha = max_alpha * decay_factor;
-            
-            if current_alpha > 0.0 {
-                let color = gpui::hsla(0.0, 0.0, 0.0, current_alpha);
-                let radius = corner_radius + spread;
-                
-                let mut quad = fill(shadow_bounds, color);
-                quad.corner_radii = Corners::all(radius);
-                window.paint_quad(quad);
-            }
-        }
+    fn convert_to_lf(&mut self, _: &ConvertToLF, _: &mut Window, cx: &mut Context<Self>) {
+        self.set_line_ending(LineEnding::Lf, cx);
     }
 
-    #[allow(dead_code)]
-    pub fn set_decorations(&mut self, decorations: Vec<Decoration>, cx: &mut Context<Self>) {
-        self.decorations = decorations;
-        cx.notify();
+    fn convert_to_crlf(&mut self, _: &ConvertToCRLF, _: &mut Window, cx: &mut Context<Self>) {
+        self.set_line_ending(LineEnding::Crlf, cx);
     }
 
-    #[allow(dead_code)]
-    pub fn clear_decorations(&mut self, cx: &mut Context<Self>) {
-        self.decorations.clear();
-        self.hover_popup = None;
-        cx.notify();
+    /// 编辑后在 [`GIT_DIFF_DELAY`] 内没有新的编辑时，在后台重新计算 git 差异
+    fn schedule_git_diff(&mut self, cx: &mut Context<Self>) {
+        let Some(base) = self.diff_base().cloned() else {
+            self.git_diff_task = None;
+            if !self.git_diff_map.is_empty() {
+                self.git_diff_map.clear();
+                self.request_redraw(cx);
+            }
+            return;
+        };
+        let content = self.core.content.clone();
+        let version = self.core.version();
+        let executor = cx.background_executor().clone();
+        self.git_diff_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(GIT_DIFF_DELAY).await;
+                let map = executor.spawn(async move { compute_git_diff(&base, &content) }).await;
+                view.update(&mut cx, |this, cx| {
+                    // 之后的编辑会另行安排计算，过期的结果直接丢弃
+                    if this.core.version() != version {
+                        return;
+                    }
+                    this.git_diff_map = map;
+                    cx.notify();
+                })
+                .ok();
+            }
+        }));
     }
 
-    pub fn open_file(&mut self, path: PathBuf, content: String, cx: &mut Context<Self>) {
-        let new_uri = default_doc_uri(&path);
-        
-        if new_uri == self.lsp_manager.doc_uri {
-            self.set_content(content, cx);
+    /// `delay` 内没有新的编辑时在后台查错，结果显示为波浪线并通过 [`CodeEditorEvent::Problems`] 发出
+    fn schedule_lint(&mut self, delay: Duration, cx: &mut Context<Self>) {
+        if self.preview_uri.is_some() || self.large_file {
+            self.lint_task = None;
             return;
         }
-
-        // Detect project root and restart LSP if needed
-        let new_root_path = LspManager::detect_project_root(&path);
-        let new_root_uri = default_doc_uri(&new_root_path);
-        
-        if new_root_uri != self.lsp_manager.root_uri {
-            self.lsp_manager.restart(new_root_path, &content);
+        // 外部语言服务器自行推送诊断，不需要定时查错
+        if let Some(diagnostics) = self.lsp_manager.take_diagnostics() {
+            self.listen_diagnostics(diagnostics, cx);
         }
-
-        // Clean up old document
-        let _ = self.sweetline_engine.remove_document(&self.lsp_manager.doc_uri);
-        
-        // Register new file with LSP
-        self.lsp_manager.update_doc_uri(new_uri, &content);
+        let Some(service) = self.lsp_manager.service() else {
+            self.lint_task = None;
+            return;
+        };
+        // 新的编辑让还在排队的查错作废
+        service.cancel("lint");
+        let uri = self.lsp_manager.doc_uri.clone();
+        let version = self.core.version();
+        self.lint_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(delay).await;
+                let lint_uri = uri.clone();
+                let Some(result) = service.request("lint", move |service| service.lint_file(&lint_uri)).recv().await else {
+                    return;
+                };
+                view.update(&mut cx, |this, cx| {
+                    // 之后的编辑或切换文档会另行安排查错，过期的结果直接丢弃
+                    if this.core.version() != version || this.lsp_manager.doc_uri != uri {
+                        return;
+                    }
+                    match result {
+                        Ok(result) => this.apply_lint(result, cx),
+                        Err(err) => log_channel(OutputChannel::Lsp, format!("Failed to lint {}: {}", uri, err)),
+                    }
+                })
+                .ok();
+            }
+        }));
+    }
+
+    /// 把外部语言服务器推送的当前文档的诊断交回界面线程显示
+    fn listen_diagnostics(&mut self, diagnostics: flume::Receiver<PublishedDiagnostics>, cx: &mut Context<Self>) {
+        self.diagnostics_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                while let Ok(published) = diagnostics.recv_async().await {
+                    let updated = view.update(&mut cx, |this, cx| {
+                        if published.uri == this.lsp_manager.doc_uri {
+                            this.apply_lint(LintResult { diagnostics: published.diagnostics }, cx);
+                        }
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+            }
+        }));
+    }
+
+    fn apply_lint(&mut self, result: LintResult, cx: &mut Context<Self>) {
+        let (decorations, problems) = lint_decorations(&self.core.content, result.diagnostics);
+        self.set_decorations(decorations, self.core.version(), cx);
+        cx.emit(CodeEditorEvent::Problems(problems));
+    }
+
+    /// [`SEMANTIC_DELAY`] 内没有新的编辑时向 tiec 请求结绳文件的语义高亮
+    fn schedule_semantic_highlights(&mut self, cx: &mut Context<Self>) {
+        let service = self.lsp_manager.service().filter(|_| self.preview_uri.is_none());
+        let Some(service) = service else {
+            self.semantic_task = None;
+            return;
+        };
+        service.cancel("highlight");
+        let uri = self.lsp_manager.doc_uri.clone();
+        let version = self.core.version();
+        self.semantic_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(SEMANTIC_DELAY).await;
+                let highlight_uri = uri.clone();
+                let Some(result) = service.request("highlight", move |service| service.highlight(&highlight_uri)).recv().await else {
+                    return;
+                };
+                view.update(&mut cx, |this, cx| {
+                    // 请求之后文档又有变化时结果已经过期，等下一次请求
+                    if this.core.version() != version || this.lsp_manager.doc_uri != uri {
+                        return;
+                    }
+                    match result {
+                        Ok(result) => this.apply_semantic_highlights(result, cx),
+                        Err(err) => log_channel(OutputChannel::Lsp, format!("Failed to highlight {}: {}", uri, err)),
+                    }
+                })
+                .ok();
+            }
+        }));
+    }
+
+    fn apply_semantic_highlights(&mut self, result: HighlightResult, cx: &mut Context<Self>) {
+        let content = &self.core.content;
+        let spans = result
+            .highlights
+            .into_iter()
+            .filter_map(|item| {
+                let style = semantic_style(item.kind)?;
+                let start = lsp_point_to_offset(content, item.range.start.line, item.range.start.column);
+                let end = lsp_point_to_offset(content, item.range.end.line, item.range.end.column);
+                Some(SemanticSpan { range: start..end, style })
+            })
+            .collect();
+        let changed = self.semantic.replace(spans);
+        if changed.is_empty() {
+            return;
+        }
+        self.invalidate_render_lines(&changed);
+        self.request_redraw(cx);
+    }
+
+    /// 从渲染缓存中移除 `ranges` 所在的行，其它行的排版保留
+    fn invalidate_render_lines(&self, ranges: &[Range<usize>]) {
+        let content = &self.core.content;
+        let Ok(mut cache) = self.render_cache.lock() else {
+            return;
+        };
+        let mut lines: Vec<usize> = ranges
+            .iter()
+            .flat_map(|range| {
+                let end = range.end.min(content.len_bytes());
+                content.byte_to_line(range.start.min(end))..=content.byte_to_line(end)
+            })
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+        for line in lines {
+            cache.pop(&LineKey::for_line(self.render_generation, content, line).0);
+        }
+    }
+
+    /// 从渲染缓存中移除 `lines` 中各行的排版
+    fn invalidate_render_line_range(&self, lines: Range<usize>) {
+        let Ok(mut cache) = self.render_cache.lock() else {
+            return;
+        };
+        let stale: Vec<LineKey> = cache.iter().map(|(key, _)| *key).filter(|key| lines.contains(&key.line())).collect();
+        for key in stale {
+            cache.pop(&key);
+        }
+    }
+
+    /// 查询或文档变化后在 [`FIND_DELAY`] 内没有新的变化时，在后台重新查找匹配
+    fn schedule_find(&mut self, cx: &mut Context<Self>) {
+        let Some(query) = self.find.as_ref().map(|find| find.query.clone()) else {
+            return;
+        };
+        if query.is_empty() {
+            self.find_task = None;
+            return;
+        }
+        let content = self.core.content.clone();
+        let executor = cx.background_executor().clone();
+        self.find_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(FIND_DELAY).await;
+                let (matches, capped) =
+                    executor.spawn(async move { find_all(&content.to_string(), &query) }).await;
+                view.update(&mut cx, |this, cx| {
+                    let cursor = this.core.primary_selection().range().start;
+                    let Some(find) = this.find.as_mut() else {
+                        return;
+                    };
+                    find.set_matches(matches, capped, cursor);
+                    // 输入查询时跟随到光标处或其后的第一个匹配
+                    if find.focused {
+                        if let Some(current) = find.current {
+                            let range = find.matches[current].clone();
+                            this.select_find_match(range, cx);
+                        }
+                    }
+                    cx.notify();
+                })
+                .ok();
+            }
+        }));
+    }
+
+    fn select_find_match(&mut self, range: Range<usize>, cx: &mut Context<Self>) {
+        self.core.selections = vec![Selection::new(range.start, range.end)];
+        self.scroll_to_cursor(cx);
+    }
+
+    /// 查找栏获得焦点时键入的文本追加到查询
+    fn edit_find_query(&mut self, edit: impl FnOnce(&mut String), cx: &mut Context<Self>) {
+        let Some(find) = self.find.as_mut() else {
+            return;
+        };
+        edit(&mut find.query);
+        if find.query.is_empty() {
+            find.matches.clear();
+            find.capped = false;
+            find.current = None;
+        }
+        self.schedule_find(cx);
+        cx.notify();
+    }
+
+    fn find_focused(&self) -> bool {
+        self.find.as_ref().is_some_and(|find| find.focused)
+    }
+
+    fn request_redraw(&mut self, cx: &mut Context<Self>) {
+        if self.redraw.request() {
+            cx.notify();
+        }
+    }
+
+    /// 在一个重绘批次内执行 `f`，期间的重绘请求合并为一次 notify
+    fn batch_redraw<R>(&mut self, cx: &mut Context<Self>, f: impl FnOnce(&mut Self, &mut Context<Self>) -> R) -> R {
+        self.redraw.begin();
+        let result = f(self, cx);
+        if self.redraw.end() {
+            cx.notify();
+        }
+        result
+    }
+
+    /// 已发出的 notify 次数，显示在性能面板中
+    pub fn notify_count(&self) -> u64 {
+        self.redraw.notify_count()
+    }
+
+    /// 编辑后整篇同步的次数，显示在性能面板中
+    pub fn full_sync_count(&self) -> u64 {
+        self.full_syncs
+    }
+
+    pub fn perform_undo(&mut self, cx: &mut Context<Self>) {
+        if self.is_read_only() {
+            return;
+        }
+        self.core.undo();
+        self.sync_edits(cx);
+        cx.notify();
+    }
+
+    pub fn perform_redo(&mut self, cx: &mut Context<Self>) {
+        if self.is_read_only() {
+            return;
+        }
+        self.core.redo();
+        self.sync_edits(cx);
+        cx.notify();
+    }
+
+    pub fn perform_select_all(&mut self, cx: &mut Context<Self>) {
+        self.core.select_all();
+        self.completion_active = false;
+        cx.notify();
+    }
+
+    pub fn perform_copy(&mut self, cx: &mut Context<Self>) {
+        let mut texts = Vec::new();
+        for selection in &self.core.selections {
+            if !selection.is_empty() {
+                texts.push(self.core.content.byte_slice(selection.range()).to_string());
+            }
+        }
+        if texts.is_empty() {
+            return;
+        }
+        let text = texts.join("\n");
+        cx.write_to_clipboard(ClipboardItem::new_string(text));
+    }
+
+    pub fn perform_cut(&mut self, cx: &mut Context<Self>) {
+        if self.is_read_only() {
+            return;
+        }
+        self.perform_copy(cx);
+        self.core.break_undo_group();
+        self.core.delete_selection();
+        self.core.break_undo_group();
+        self.sync_edits(cx);
+        cx.notify();
+    }
+
+    pub fn perform_paste(&mut self, cx: &mut Context<Self>) {
+        if let Some(item) = cx.read_from_clipboard() {
+            if let Some(text) = item.text() {
+                self.insert_text(&self.line_ending.normalize(&text), cx);
+            }
+        }
+    }
+
+
+    fn paint_soft_shadow(window: &mut Window, bounds: Bounds<Pixels>, corner_radius: Pixels) {
+        let steps = 10;
+        let max_blur = px(24.0);
+        let max_alpha = 0.12;
+        let offset_y = px(4.0);
+
+        for i in 1..=steps {
+            let t = i as f32 / steps as f32;
+            let spread = max_blur * t;
+            let current_offset_y = offset_y * t;
+            
+            let shadow_bounds = Bounds::from_corners(
+                point(bounds.left() - spread, bounds.top() - spread + current_offset_y),
+                point(bounds.right() + spread, bounds.bottom() + spread + current_offset_y),
+            );
+            
+            let decay_factor = (1.0 - t).powi(2);
+            let current_alpha = max_alpha * decay_factor;
+            
+            if current_alpha > 0.0 {
+                let color = gpui::hsla(0.0, 0.0, 0.0, current_alpha);
+                let radius = corner_radius + spread;
+                
+                let mut quad = fill(shadow_bounds, color);
+                quad.corner_radii = Corners::all(radius);
+                window.paint_quad(quad);
+            }
+        }
+    }
+
+    /// 设置装饰；`version` 为计算装饰时的文档版本，后台算出的结果到达前文档已被编辑时，
+    /// 范围先移到当前文本上
+    pub fn set_decorations(&mut self, decorations: Vec<Decoration>, version: u64, cx: &mut Context<Self>) {
+        self.decorations = map_decorations(&self.core, version, decorations);
+        self.decorations_version = self.core.version();
+        cx.notify();
+    }
+
+    /// 文档在上次读取装饰后被编辑过时，把装饰移到当前文本上
+    fn refresh_decorations(&mut self) {
+        let version = self.core.version();
+        if self.decorations_version != version {
+            let decorations = std::mem::take(&mut self.decorations);
+            self.decorations = map_decorations(&self.core, self.decorations_version, decorations);
+            self.decorations_version = version;
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn clear_decorations(&mut self, cx: &mut Context<Self>) {
+        self.decorations.clear();
+        self.hover_popup = None;
+        cx.notify();
+    }
+
+    pub fn open_file(&mut self, path: PathBuf, content: String, cx: &mut Context<Self>) {
+        self.open_document(path, content, false, cx);
+    }
+
+    /// 以大文件模式打开：只读，不做语法高亮和差异标记，也不通知语言服务
+    pub fn open_large_file(&mut self, path: PathBuf, content: String, cx: &mut Context<Self>) {
+        self.open_document(path, content, true, cx);
+    }
+
+    fn open_document(&mut self, path: PathBuf, content: String, large_file: bool, cx: &mut Context<Self>) {
+        self.end_preview();
+        // 未命名缓冲区与启动时的 untitled.t 一样使用临时目录下的文档 URI，并沿用当前项目
+        let untitled = untitled_name(&path).map(|name| std::env::temp_dir().join(format!("{}.t", name)));
+        let new_uri = default_doc_uri(untitled.as_deref().unwrap_or(&path));
+        
+        if new_uri == self.lsp_manager.doc_uri && large_file == self.large_file {
+            self.set_content(content, cx);
+            return;
+        }
+        self.large_file = large_file;
+
+        // Detect project root and restart LSP if needed
+        self.overrides = EditorOverrides::default();
+        self.hover_delays = HoverDelays::default();
+        self.auto_close_blocks = true;
+        if untitled.is_none() {
+            let new_root_path = LspManager::detect_project_root(&path);
+            let new_root_uri = default_doc_uri(&new_root_path);
+            self.overrides = OverrideRules::load(&new_root_path).for_path(&path, &new_root_path);
+            self.hover_delays = HoverDelays::load(&new_root_path);
+            self.auto_close_blocks = enter_rules::load_enabled(&new_root_path);
+
+            if new_root_uri != self.lsp_manager.root_uri && !large_file {
+                self.lsp_manager.restart(new_root_path, &content);
+            }
+        }
+        self.apply_overrides();
+
+        // Clean up old document
+        let _ = self.sweetline_engine.remove_document(&self.lsp_manager.doc_uri);
         
-        self.core.content = Rope::from(content.clone());
+        // Register new file with LSP
+        if large_file {
+            self.lsp_manager.detach_doc_uri(new_uri);
+        } else {
+            self.lsp_manager.update_doc_uri(new_uri, &content);
+        }
+        
+        self.line_ending = LineEnding::detect(&content);
+        self.core.set_text(&content);
         self.core.set_cursor(0);
         self.decorations.clear();
         self.hover_popup = None;
-        self.fetch_git_base_content(cx);
+        self.saved_content = untitled.is_none().then(|| content.clone());
+        self.folds.clear();
+        self.update_hidden_lines();
+        if untitled.is_some() || large_file {
+            self.git_base_content = None;
+            self.update_git_diff(cx);
+        } else {
+            self.fetch_git_base_content(cx);
+        }
         self.sync_sweetline_document(cx);
 
         cx.notify();
     }
 
+    /// 用磁盘上的新内容替换缓冲区，尽量保留光标所在行列和滚动位置。
+    /// 替换只改动不同的部分，作为一个撤销步骤，撤销后回到重新载入之前的内容
+    pub fn reload_content(&mut self, content: String, cx: &mut Context<Self>) {
+        let head = self.core.primary_selection().head;
+        let (line, col) = self.core.line_col_for_offset(head);
+        self.line_ending = LineEnding::detect(&content);
+        if let Some(edit) = minimal_edit(&self.core.content.to_string(), &content) {
+            self.core.break_undo_group();
+            self.apply_edits(vec![edit], cx);
+            self.core.break_undo_group();
+        }
+        let index = self.core.offset_for_line_col(line, col);
+        self.core.set_cursor(index);
+        self.saved_content = Some(content);
+        self.update_git_diff(cx);
+        cx.notify();
+    }
+
+    /// 光标所在行列和纵向滚动位置，用于保存会话
+    pub fn view_position(&self) -> (usize, usize, f32) {
+        let (line, column) = self.core.line_col_for_offset(self.core.primary_selection().head);
+        (line, column, f32::from(self.layout.scroll_offset.y))
+    }
+
+    /// 恢复会话中记录的光标和滚动位置；文件变短时限制在内容范围内
+    pub fn set_view_position(&mut self, line: usize, column: usize, scroll_y: f32, cx: &mut Context<Self>) {
+        let index = self.core.offset_for_line_col(line, column);
+        self.core.set_cursor(index);
+        let line_count = self.core.content.len_lines().max(1);
+        let max_scroll = self.layout.line_height() * line_count as f32;
+        self.layout.scroll_offset.y = px(scroll_y).clamp(-max_scroll, px(0.0));
+        cx.notify();
+    }
+
+    /// 项目设置变化后按新设置重建结绳 IDE 服务
+    pub fn reload_language_project(&mut self) {
+        self.lsp_manager.reload_project(&self.core.content.to_string());
+    }
+
+    /// 以只读方式显示文件内容，供搜索结果等快速预览
+    pub fn show_preview(&mut self, path: &std::path::Path, content: &str, cx: &mut Context<Self>) {
+        let uri = default_doc_uri(path);
+        if let Some(old) = self.preview_uri.replace(uri.clone()) {
+            let _ = self.sweetline_engine.remove_document(&old);
+        }
+        self.core = EditorCore::from_text(content);
+        self.line_ending = LineEnding::detect(content);
+        self.layout.scroll_offset = point(px(0.0), px(0.0));
+        self.layout.reset_content_width();
+        self.folds.clear();
+        self.folds_dirty = true;
+        self.update_hidden_lines();
+        self.decorations.clear();
+        self.git_diff_map.clear();
+        self.hover_popup = None;
+        self.completion_active = false;
+
+        if uri.ends_with(".t") {
+            self.block_map.update(&self.core.content, &grammar_source(JIESHENG_INDEX));
+        } else {
+            self.block_map.update(&self.core.content, "{}");
+        }
+        self.load_highlights(uri, cx);
+        cx.notify();
+    }
+
+    /// 结束预览；之后需要重新载入真正的文件内容
+    pub fn end_preview(&mut self) {
+        if let Some(uri) = self.preview_uri.take() {
+            let _ = self.sweetline_engine.remove_document(&uri);
+            self.sweetline_analyzer = None;
+            self.sweetline_document = None;
+            self.highlight_backlog = None;
+            self.highlight_task = None;
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.preview_uri.is_some() || self.large_file || self.overrides.read_only == Some(true)
+    }
+
+    /// 切换当前缓冲区的只读状态，优先于设置中的 readOnly，仅对该标签有效
+    pub fn toggle_read_only(&mut self, cx: &mut Context<Self>) {
+        if self.preview_uri.is_some() || self.large_file {
+            return;
+        }
+        self.overrides.read_only = Some(!self.is_read_only());
+        self.completion_active = false;
+        cx.notify();
+    }
+
+    fn apply_overrides(&mut self) {
+        self.layout.font_size = self.overrides.font_size.map(px).unwrap_or(self.base_font_size);
+        self.layout.reset_content_width();
+        if let Ok(mut cache) = self.render_cache.lock() {
+            cache.clear();
+        }
+    }
+
+    /// 取出当前文件的编辑状态（内容、选区、撤销历史、滚动位置），编辑器留下空缓冲区
+    pub fn take_snapshot(&mut self) -> EditorSnapshot {
+        let snapshot = EditorSnapshot {
+            core: std::mem::replace(&mut self.core, EditorCore::new()),
+            scroll_offset: self.layout.scroll_offset,
+            overrides: std::mem::take(&mut self.overrides),
+            saved_content: self.saved_content.take(),
+            folds: std::mem::take(&mut self.folds),
+            line_ending: std::mem::take(&mut self.line_ending),
+            large_file: std::mem::take(&mut self.large_file),
+        };
+        self.update_hidden_lines();
+        self.apply_overrides();
+        self.layout.scroll_offset = point(px(0.0), px(0.0));
+        self.completion_active = false;
+        self.hover_popup = None;
+        snapshot
+    }
+
+    /// 打开文件并恢复之前用 [`take_snapshot`](Self::take_snapshot) 保存的状态
+    pub fn restore_snapshot(&mut self, path: PathBuf, snapshot: EditorSnapshot, cx: &mut Context<Self>) {
+        self.open_document(path, snapshot.core.content.to_string(), snapshot.large_file, cx);
+        self.core = snapshot.core;
+        // 文档已按最终内容整篇载入，后台标签上积累的增量不能再应用一次
+        self.core.take_changes();
+        self.overrides = snapshot.overrides;
+        self.apply_overrides();
+        self.layout.scroll_offset = snapshot.scroll_offset;
+        // open_file 把未保存的内容当成了基准
+        self.saved_content = snapshot.saved_content;
+        self.folds = snapshot.folds;
+        self.folds_dirty = true;
+        self.line_ending = snapshot.line_ending;
+        self.update_hidden_lines();
+        self.update_git_diff(cx);
+        self.schedule_lint(Duration::ZERO, cx);
+        cx.notify();
+    }
+
     pub fn set_content(&mut self, content: String, cx: &mut Context<Self>) {
-        self.core.content = Rope::from(content.clone());
+        self.line_ending = LineEnding::detect(&content);
+        self.core.set_text(&content);
         self.sync_sweetline_document(cx);
         self.core.set_cursor(0);
         
@@ -610,35 +1765,194 @@ impl CodeEditor {
 
     pub fn set_cursor(&mut self, index: usize, cx: &mut Context<Self>) {
         self.core.set_cursor(index);
-        self.core.completion_active = false;
+        self.completion_active = false;
         self.hover_popup = None;
         cx.notify();
     }
 
+    /// 光标移动到指定行列（均从 0 开始，列为字节偏移）并滚动到可见区域
+    pub fn go_to_line_col(&mut self, line: usize, col: usize, cx: &mut Context<Self>) {
+        let index = Self::index_for_line_col(&self.core.content, line, col);
+        self.set_cursor(index, cx);
+        self.scroll_to_cursor(cx);
+    }
+
+    /// 对当前缓冲区内容重新查错，供保存前检查；没有查错服务或查错失败时结果为 None
+    pub fn lint_errors_for_save(&mut self, cx: &mut Context<Self>) -> Task<Option<Vec<LintError>>> {
+        let Some(reply) = self.lsp_manager.lint_errors() else {
+            return Task::ready(None);
+        };
+        cx.background_executor().spawn(async move {
+            match reply.recv().await? {
+                Ok(errors) => Some(errors),
+                Err(err) => {
+                    log_channel(OutputChannel::Lsp, format!("Failed to lint before saving: {}", err));
+                    None
+                }
+            }
+        })
+    }
+
+    /// 当前文档的大纲：结绳文件在后台取编译器的结果，没有服务或取不到时按行匹配定义
+    pub fn outline(&mut self, cx: &mut Context<Self>) -> Task<Vec<OutlineSymbol>> {
+        let Some(reply) = self.lsp_manager.outline() else {
+            return Task::ready(fallback_outline(&self.core.content.to_string()));
+        };
+        cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let symbols = match reply.recv().await {
+                    Some(Ok(symbols)) => symbols,
+                    Some(Err(err)) => {
+                        log_channel(OutputChannel::Lsp, format!("Failed to get outline: {}", err));
+                        None
+                    }
+                    None => None,
+                };
+                match symbols {
+                    Some(symbols) => symbols,
+                    None => view.update(&mut cx, |this, _| fallback_outline(&this.core.content.to_string())).unwrap_or_default(),
+                }
+            }
+        })
+    }
+
+    /// 在后台搜索项目中名称包含关键词的符号；没有编译器服务时为空
+    pub fn workspace_symbols(&mut self, keyword: &str, cx: &mut Context<Self>) -> Task<Vec<WorkspaceSymbol>> {
+        let Some(reply) = self.lsp_manager.workspace_symbols(keyword) else {
+            return Task::ready(Vec::new());
+        };
+        cx.background_executor().spawn(async move {
+            match reply.recv().await {
+                Some(Ok(symbols)) => symbols,
+                Some(Err(err)) => {
+                    log_channel(OutputChannel::Lsp, format!("Failed to search workspace symbols: {}", err));
+                    Vec::new()
+                }
+                None => Vec::new(),
+            }
+        })
+    }
+
     pub fn select_to(&mut self, index: usize, cx: &mut Context<Self>) {
         self.core.select_to(index);
-        self.core.completion_active = false;
+        self.completion_active = false;
         cx.notify();
     }
 
     pub fn insert_text(&mut self, text: &str, cx: &mut Context<Self>) {
-        self.core.insert_text(text);
-        self.sync_sweetline_document(cx);
-        self.notify_lsp_change(text);
-        self.update_completion(cx);
+        if self.is_read_only() {
+            return;
+        }
+        // 粘贴和命令插入的文本单独作为一步撤销，不与前后的输入合并
+        self.batch_redraw(cx, |this, cx| {
+            this.core.break_undo_group();
+            this.core.insert_text(text);
+            this.core.break_undo_group();
+            this.sync_edits(cx);
+            this.update_completion(cx);
+            this.request_redraw(cx);
+        });
+    }
+
+    /// 插件通过编辑器接口替换一段文本，作为一步撤销；范围不合法或只读时返回原因
+    pub fn replace_range_checked(&mut self, range: Range<usize>, text: &str, cx: &mut Context<Self>) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err("当前文件为只读".to_string());
+        }
+        self.check_offset(range.start)?;
+        self.check_offset(range.end)?;
+        if range.start > range.end {
+            return Err(format!("范围 {}..{} 的起点在终点之后", range.start, range.end));
+        }
+        self.batch_redraw(cx, |this, cx| {
+            this.core.break_undo_group();
+            this.core.replace_range(range, text);
+            this.core.break_undo_group();
+            this.completion_active = false;
+            this.sync_edits(cx);
+            this.request_redraw(cx);
+        });
+        Ok(())
+    }
+
+    /// 插件通过编辑器接口设置选区，每项为 (锚点, 光标)
+    pub fn set_selections(&mut self, selections: &[(usize, usize)], cx: &mut Context<Self>) -> Result<(), String> {
+        if selections.is_empty() {
+            return Err("至少需要一个选区".to_string());
+        }
+        for &(anchor, head) in selections {
+            self.check_offset(anchor)?;
+            self.check_offset(head)?;
+        }
+        self.core.selections = selections.iter().map(|&(anchor, head)| Selection::new(anchor, head)).collect();
+        self.core.merge_selections();
+        self.core.marked_range = None;
+        self.completion_active = false;
         cx.notify();
+        Ok(())
     }
 
-    fn notify_lsp_change(&mut self, _text: &str) {
-        let content = self.core.content.to_string();
-        self.lsp_manager.notify_change(&content);
+    fn check_offset(&self, offset: usize) -> Result<(), String> {
+        let len = self.core.content.len_bytes();
+        if offset > len {
+            return Err(format!("偏移 {} 超出文本长度 {}", offset, len));
+        }
+        let char_index = self.core.content.byte_to_char(offset);
+        if self.core.content.char_to_byte(char_index) != offset {
+            return Err(format!("偏移 {} 不在字符边界上", offset));
+        }
+        Ok(())
+    }
+
+    /// 把上次同步之后的编辑逐条交给 sweetline、语言服务和文档上的标记，不复制全文；
+    /// 缓冲区给不出增量（整篇替换或积压太多）时整篇重新载入
+    fn sync_edits(&mut self, cx: &mut Context<Self>) {
+        let Some(changes) = self.core.take_changes() else {
+            self.full_syncs += 1;
+            self.sync_sweetline_document(cx);
+            self.lsp_manager.notify_change(&self.core.content.to_string());
+            return;
+        };
+        if changes.is_empty() {
+            return;
+        }
+        for change in &changes {
+            if let Some(find) = self.find.as_mut() {
+                find.shift_for_edit(change.range.clone(), change.text.len());
+            }
+            self.semantic.shift_for_edit(change.range.clone(), change.text.len());
+            let inserted_breaks = ropey::str_utils::byte_to_line_idx(&change.text, change.text.len());
+            self.folds.edit(change.start.0, change.end.0, inserted_breaks);
+        }
+        self.update_hidden_lines();
+        self.lsp_manager.notify_changes(&changes, &self.core.content);
+
+        if let Some(analyzer) = &self.sweetline_analyzer {
+            let result = Self::analyze_changes(analyzer, &changes);
+            self.update_highlights_from_result(result);
+        } else if let Some(backlog) = self.highlight_backlog.as_mut() {
+            // 整篇分析还没完成，分析器送回后再补上这些编辑
+            backlog.extend(changes.iter().cloned());
+        } else {
+            self.sync_sweetline_document(cx);
+            return;
+        }
+        // 块结构和可折叠区域只在增删了行时重新计算
+        if changes.iter().any(ContentChange::changes_lines) {
+            self.update_block_map();
+            self.folds_dirty = true;
+        }
+        self.schedule_git_diff(cx);
+        self.schedule_lint(LINT_DELAY, cx);
+        self.schedule_semantic_highlights(cx);
+        self.schedule_find(cx);
     }
 
     #[allow(dead_code)]
     pub fn delete_range(&mut self, range: Range<usize>, cx: &mut Context<Self>) {
         self.core.delete_range(range);
-        self.sync_sweetline_document(cx);
-        self.notify_lsp_change("");
+        self.sync_edits(cx);
         self.update_completion(cx);
         cx.notify();
     }
@@ -648,12 +1962,17 @@ impl CodeEditor {
     }
 
     fn scroll_to_cursor(&mut self, cx: &mut Context<Self>) {
+        self.reveal_cursor_x = true;
+        let index = self.core.primary_selection().head;
+        let (line, _, _) = Self::line_col_for_index(&self.core.content, index);
+        // 光标跳进折起的区域时（查找、跳转到行或定义）展开它
+        if self.folds.unfold_containing(line) {
+            self.update_hidden_lines();
+        }
         if let Some(bounds) = self.layout.last_bounds {
-            let index = self.core.primary_selection().head;
-            let (line, _, _) = Self::line_col_for_index(&self.core.content, index);
             
             let line_height = self.layout.line_height();
-            let line_top = line_height * line as f32;
+            let line_top = self.layout.line_top(line);
             let line_bottom = line_top + line_height;
             
             let scroll_top = -self.layout.scroll_offset.y;
@@ -664,26 +1983,81 @@ impl CodeEditor {
             } else if line_bottom > scroll_bottom {
                 self.layout.scroll_offset.y = -(line_bottom - bounds.size.height);
             }
-            cx.notify();
+            self.request_redraw(cx);
+        }
+    }
+
+    /// 滚动使第 `line` 行位于编辑区中间
+    fn center_line(&mut self, line: usize) {
+        let Some(bounds) = self.layout.last_bounds else {
+            return;
+        };
+        let line_height = self.layout.line_height();
+        let content_height = self.layout.content_height(self.core.content.len_lines().max(1));
+        let max_scroll_y = (content_height - bounds.size.height + line_height).max(px(0.0));
+        let top = self.layout.line_top(line) - (bounds.size.height - line_height) / 2.0;
+        self.layout.scroll_offset.y = (-top).clamp(-max_scroll_y, px(0.0));
+    }
+
+    /// 滚动条上的标记：诊断按严重程度着色，查找匹配和 git 改动各占一列，光标行横跨整个轨道。
+    /// 标记按显示行放置，折起的行算作其折叠首行
+    fn collect_scrollbar_marks(
+        content: &Rope,
+        decorations: &[Decoration],
+        find_matches: &[Range<usize>],
+        git_diff_map: &HashMap<usize, GitDiffStatus>,
+        diff_against_saved: bool,
+        cursor_line: usize,
+        hidden: &HiddenLines,
+    ) -> ScrollbarMarks {
+        let line_of = |offset: usize| hidden.row_of(content.byte_to_line(offset.min(content.len_bytes())));
+        let mut marks = ScrollbarMarks::new(hidden.visible_count(content.len_lines()));
+        for decoration in decorations {
+            marks.push(ScrollbarMark {
+                line: line_of(decoration.range.start),
+                lane: Some(MarkLane::Diagnostic),
+                color: decoration.color.rgba(),
+                priority: decoration.color.priority(),
+            });
+        }
+        for m in find_matches {
+            marks.push(ScrollbarMark { line: line_of(m.start), lane: Some(MarkLane::Find), color: rgba(0xe2c08dcc), priority: 0 });
+        }
+        for (&line, &status) in git_diff_map {
+            marks.push(ScrollbarMark {
+                line: hidden.row_of(line),
+                lane: Some(MarkLane::Git),
+                color: rgb(diff_color(status, diff_against_saved)),
+                priority: 0,
+            });
+        }
+        marks.push(ScrollbarMark { line: hidden.row_of(cursor_line), lane: None, color: rgba(0xffffffb3), priority: 0 });
+        marks
+    }
+
+    /// 需要时水平滚动到主光标处；在绘制前排版光标所在行得到其位置
+    fn reveal_cursor_x(&mut self, bounds: Bounds<Pixels>, window: &Window) {
+        if !std::mem::take(&mut self.reveal_cursor_x) {
+            return;
         }
+        let head = self.core.primary_selection().head;
+        let (line, _, line_start) = Self::line_col_for_index(&self.core.content, head);
+        let line_len = render_cache::line_text_len(&self.core.content, line);
+        let line_text = self.core.content.byte_slice(line_start..line_start + line_len).to_string();
+        let shape = self.get_cached_shape_line(window, &line_text, self.layout.font_size, line, line_start);
+        let x = shape.x_for_index(head.saturating_sub(line_start).min(line_len));
+        let max_digits = self.core.content.len_lines().max(1).to_string().len();
+        let margin = self.layout.font_size * 2.0;
+        self.layout.reveal_x(bounds, max_digits, x, margin);
     }
 
     fn point_for_index(&mut self, index: usize) -> Point<Pixels> {
         if let Some(bounds) = self.layout.last_bounds {
             let (line, _, line_start) = Self::line_col_for_index(&self.core.content, index);
-            let line_slice = self.core.content.line(line);
-            let mut line_text_string = line_slice.to_string();
-            if line_text_string.ends_with('\n') {
-                line_text_string.pop();
-                if line_text_string.ends_with('\r') {
-                    line_text_string.pop();
-                }
-            }
-            
-            let key = format!("{}:{}", line, line_text_string);
+            let (key, line_len) = LineKey::for_line(self.render_generation, &self.core.content, line);
             let x_offset = if let Ok(mut cache) = self.render_cache.lock() {
                 if let Some(line) = cache.get(&key) {
-                     let local_index = index.saturating_sub(line_start).min(line_text_string.len());
+                     let local_index = index.saturating_sub(line_start).min(line_len);
                      line.x_for_index(local_index)
                 } else {
                     px(0.0)
@@ -708,83 +2082,136 @@ impl CodeEditor {
         self.process_lsp_messages(cx);
 
         let primary = self.core.primary_selection();
-        if primary.is_empty() {
-            let cursor = primary.head;
-            
-            // --- C++ Keyword Completion (Fixed) ---
-            let content = &self.core.content;
-            let len_bytes = content.len_bytes();
-            
-            if cursor > len_bytes {
+        if !primary.is_empty() {
+            return;
+        }
+        let cursor = primary.head;
+        let content = &self.core.content;
+        if cursor > content.len_bytes() {
+            return;
+        }
+        let line_start = content.line_to_byte(content.byte_to_line(cursor));
+        let before = content.byte_slice(line_start..cursor).to_string();
+        let (start, trigger) = completion_context(&before);
+        let prefix_start = line_start + start;
+
+        if let Some(session) = self.completion_trigger.as_ref() {
+            if session.uri == self.lsp_manager.doc_uri && session.position == prefix_start && trigger.is_some() {
+                self.narrow_triggered_completion(cx);
                 return;
             }
+            // 删到了触发字符之前，或已离开这次成员访问
+            let deleted_trigger = cursor < session.position;
+            self.completion_trigger = None;
+            if deleted_trigger {
+                self.close_completion(cx);
+                return;
+            }
+        }
 
-            let mut word_start = cursor;
-            let mut current_idx = cursor;
-            let len_chars = content.len_chars();
-
-            while current_idx > 0 {
-                if current_idx > len_bytes {
-                    break;
-                }
-
-                let char_idx = content.byte_to_char(current_idx);
-                if char_idx == 0 && current_idx > 0 {
-                     break;
-                }
-                
-                let prev_char_idx = char_idx.saturating_sub(1);
-                if prev_char_idx >= len_chars {
-                    break;
-                }
+        // 补全在后台请求（tiec 在其工作线程上），回来后再显示
+        let Some(backend) = self.lsp_manager.backend() else {
+            return;
+        };
+        if let Some(trigger) = trigger {
+            // 成员补全按触发字符之后的位置请求全部成员，已输入的成员名在本地筛选
+            self.completion_trigger =
+                Some(TriggeredCompletion { uri: self.lsp_manager.doc_uri.clone(), position: prefix_start, items: None });
+            self.close_completion(cx);
+            self.request_completion(backend, prefix_start, String::new(), trigger, cx);
+        } else if prefix_start < cursor {
+            self.request_completion(backend, cursor, before[start..].to_string(), "", cx);
+        }
+    }
 
-                let ch = content.char(prev_char_idx);
-                let char_len = ch.len_utf8();
-                
-                if current_idx < char_len {
-                    break;
-                }
-                let prev_byte_idx = current_idx - char_len;
+    /// 按触发字符之后已输入的成员名筛选补全项，不再请求语言服务；语言服务尚未回复时不做处理
+    fn narrow_triggered_completion(&mut self, cx: &mut Context<Self>) {
+        let Some(session) = self.completion_trigger.as_ref() else {
+            return;
+        };
+        let Some(items) = session.items.as_ref() else {
+            return;
+        };
+        let cursor = self.core.primary_selection().head.max(session.position);
+        let prefix = self.core.content.byte_slice(session.position..cursor).to_string();
+        self.completion_items = narrow(items, &prefix);
+        self.completion_version = self.core.version();
+        self.completion_active = !self.completion_items.is_empty();
+        self.completion_index = 0;
+        self.completion_scroll_offset = 0.0;
+        self.completion_hover = None;
+        self.request_redraw(cx);
+    }
 
-                if !ch.is_alphanumeric() && ch != '_' && ch != '#' {
-                    word_start = current_idx;
-                    break;
-                }
-                current_idx = prev_byte_idx;
-                word_start = current_idx;
-            }
-            
-            let word_start_char = content.byte_to_char(word_start);
-            let cursor_char = content.byte_to_char(cursor);
-            let prefix = content.slice(word_start_char..cursor_char).to_string();
-            
-            if !prefix.is_empty() {
-                // Use LSP for completion
-                let line_idx = content.byte_to_line(cursor);
-                let line_start_byte = content.line_to_byte(line_idx);
-                let char_idx = content.byte_to_char(cursor).saturating_sub(content.byte_to_char(line_start_byte));
-
-                if let Some(mut items) = self.lsp_manager.completion(line_idx, char_idx, cursor, &prefix, "") {
-                    items.retain(|item| item.label.starts_with(&prefix));
-                    
-                    if !items.is_empty() {
-                        self.core.completion_items = items;
-                        self.core.completion_active = true;
-                        self.core.completion_index = 0;
-                        self.completion_scroll_offset = 0.0;
-                        cx.notify();
+    fn close_completion(&mut self, cx: &mut Context<Self>) {
+        self.completion_task = None;
+        self.completion_active = false;
+        self.completion_items.clear();
+        self.request_redraw(cx);
+    }
+
+    /// 在后台请求补全，回到界面线程后显示；请求期间又有输入时由新的请求取代
+    fn request_completion(
+        &mut self,
+        backend: LanguageBackend,
+        cursor: usize,
+        prefix: String,
+        trigger: &'static str,
+        cx: &mut Context<Self>,
+    ) {
+        let (line, column) = self.lsp_position_for_index(cursor);
+        let uri = self.lsp_manager.doc_uri.clone();
+        let request_uri = uri.clone();
+        let version = self.core.version();
+        let executor = cx.background_executor().clone();
+        self.completion_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let result = executor.spawn(async move { backend.completion(&request_uri, line, column, &prefix, trigger) }).await;
+                view.update(&mut cx, |this, cx| {
+                    if this.lsp_manager.doc_uri != uri {
                         return;
                     }
-                }
+                    let items = match result {
+                        Ok(items) => items,
+                        Err(err) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to get completion: {}", err));
+                            return;
+                        }
+                    };
+                    // 成员补全的结果在这期间输入的成员名上筛选，不因文档版本变化而作废
+                    if !trigger.is_empty() {
+                        if let Some(session) = this.completion_trigger.as_mut().filter(|session| session.position == cursor) {
+                            session.items = Some(items);
+                            this.narrow_triggered_completion(cx);
+                        }
+                        return;
+                    }
+                    if this.core.version() != version || items.is_empty() {
+                        return;
+                    }
+                    this.completion_items = items;
+                    this.completion_version = version;
+                    this.completion_active = true;
+                    this.completion_index = 0;
+                    this.completion_scroll_offset = 0.0;
+                    this.completion_hover = None;
+                    this.request_redraw(cx);
+                })
+                .ok();
             }
-            // --- End C++ Keyword Completion ---
-
-// JFLSP integration removed
-        }
+        }));
     }
 
     fn confirm_completion(&mut self, cx: &mut Context<Self>) {
-        if let Some(item) = self.core.completion_items.get(self.core.completion_index) {
+        // 补全项是针对更早的文本给出的，前缀可能已经不同，不再应用
+        if self.completion_version != self.core.version() {
+            self.completion_active = false;
+            self.completion_items.clear();
+            self.request_redraw(cx);
+            return;
+        }
+        if let Some(item) = self.completion_items.get(self.completion_index) {
             let label = item.label.clone();
 
             let primary = self.core.primary_selection();
@@ -827,194 +2254,1119 @@ impl CodeEditor {
                 word_start = current_idx;
             }
 
+            self.core.break_undo_group();
             self.core.replace_range(word_start..cursor, &label);
-            self.sync_sweetline_document(cx);
+            self.core.break_undo_group();
+            self.sync_edits(cx);
             self.update_completion(cx);
 
-            self.core.completion_active = false;
-            self.core.completion_items.clear();
-            self.core.completion_index = 0;
-            cx.notify();
+            self.completion_active = false;
+            self.completion_items.clear();
+            self.completion_index = 0;
+            cx.notify();
+        }
+    }
+
+    fn backspace(&mut self, _: &Backspace, window: &mut Window, cx: &mut Context<Self>) {
+        if self.rename.is_some() {
+            self.edit_rename(NameInput::backspace, cx);
+            return;
+        }
+        self.code_actions = None;
+        if self.find_focused() {
+            self.edit_find_query(
+                |query| {
+                    query.pop();
+                },
+                cx,
+            );
+            return;
+        }
+        // ctrl/cmd + backspace deletes the previous word instead of a single char
+        let by_word = window.modifiers().secondary();
+        self.batch_redraw(cx, |this, cx| {
+            this.core.delete_at_cursors(|content, cursor| {
+                if by_word {
+                    Self::prev_word_index(content, cursor)
+                } else {
+                    Self::prev_char_index(content, cursor)
+                }
+            });
+            this.sync_edits(cx);
+            this.update_completion(cx);
+            this.request_redraw(cx);
+        });
+    }
+
+    fn delete(&mut self, _: &Delete, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.rename.is_some() {
+            self.edit_rename(NameInput::delete, cx);
+            return;
+        }
+        self.code_actions = None;
+        self.batch_redraw(cx, |this, cx| {
+            this.core.delete_at_cursors(Self::next_char_index);
+            this.sync_edits(cx);
+            this.update_completion(cx);
+            this.request_redraw(cx);
+        });
+    }
+
+    fn delete_line(&mut self, _: &DeleteLine, _window: &mut Window, cx: &mut Context<Self>) {
+        let mut ranges_to_delete = Vec::new();
+
+        for selection in &self.core.selections {
+            let cursor = selection.range().start;
+            let line_idx = self.core.content.byte_to_line(cursor);
+            let line_start = self.core.content.line_to_byte(line_idx);
+
+            let next_line_idx = line_idx + 1;
+            let range_end = if next_line_idx < self.core.content.len_lines() {
+                self.core.content.line_to_byte(next_line_idx)
+            } else {
+                self.core.content.len_bytes()
+            };
+
+            let mut range_start = line_start;
+
+            // If last line, try to delete previous newline
+            if next_line_idx >= self.core.content.len_lines() && range_start > 0 {
+                range_start -= 1;
+                if range_start > 0 && self.core.content.byte(range_start - 1) == b'\r' {
+                    range_start -= 1;
+                }
+            }
+            ranges_to_delete.push(range_start..range_end);
+        }
+
+        // We need to merge ranges if they overlap to avoid double deletion issues if we did it sequentially?
+        // Actually replace_selections expects selections.
+        // We can just construct selections from these ranges and call replace_selections("").
+        // But replace_selections expects US to update selections after.
+        // It's easier to use replace_selections.
+
+        self.core.selections = ranges_to_delete
+            .into_iter()
+            .map(|r| Selection::new(r.start, r.end))
+            .collect();
+        self.core.merge_selections(); // Merge overlapping lines
+
+        self.core.delete_selection();
+        self.sync_edits(cx);
+        cx.notify();
+    }
+
+    fn enter(&mut self, _: &Enter, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.rename.is_some() {
+            self.confirm_rename(cx);
+            return;
+        }
+        if self.code_actions.is_some() {
+            self.apply_code_action(cx);
+            return;
+        }
+        if self.find_focused() {
+            self.step_find(true, cx);
+            return;
+        }
+        if self.core.marked_range.is_some() {
+            return;
+        }
+        if self.completion_active {
+            self.confirm_completion(cx);
+            return;
+        }
+        let smart_enter = self.request_smart_enter();
+        self.insert_newline_with_indent(smart_enter, cx);
+    }
+
+    /// 结绳文件中单个光标回车时，在换行之前向 tiec 请求光标处的智能键入（选择文件、枚举常量或切换真/假）
+    fn request_smart_enter(&mut self) -> Option<SmartEnterReply> {
+        if self.is_peek_view || self.core.selections.len() != 1 || !self.core.primary_selection().range().is_empty() {
+            return None;
+        }
+        let head = self.core.primary_selection().head;
+        let (line, column) = self.char_position(head);
+        self.lsp_manager.smart_enter(line, column)
+    }
+
+    /// 执行光标在 `head` 处时得到的智能键入
+    fn apply_smart_enter(&mut self, action: SmartEnter, head: usize, cx: &mut Context<Self>) {
+        match action {
+            SmartEnter::Replace(edit) => {
+                let edits = text_edit_ranges(&self.core.content, &[edit]);
+                self.apply_edits(edits, cx);
+                self.core.break_undo_group();
+            }
+            SmartEnter::Choose(actions) => {
+                self.hover_popup = None;
+                self.code_actions = Some(CodeActionMenu { actions, selected: 0, anchor: head, version: self.core.version() });
+                cx.notify();
+            }
+            SmartEnter::PickFile { range, format } => {
+                let version = self.core.version();
+                cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+                    let mut cx = cx.clone();
+                    async move {
+                        let Some(handle) = rfd::AsyncFileDialog::new().pick_file().await else {
+                            return;
+                        };
+                        let path = handle.path().to_string_lossy().replace('\\', "/");
+                        let new_text = fill_replace_format(&format, &path);
+                        view.update(&mut cx, |this, cx| {
+                            if this.core.version() != version {
+                                return;
+                            }
+                            let edits = text_edit_ranges(&this.core.content, &[TextEdit { range, new_text }]);
+                            this.apply_edits(edits, cx);
+                            this.core.break_undo_group();
+                        })
+                        .ok();
+                    }
+                })
+                .detach();
+            }
+        }
+    }
+
+    /// 每个光标各自沿用所在行的缩进；行匹配回车规则时补全块结构，光标放在规则标出的位置。
+    /// 整体作为一步撤销。结绳文件先按本地推断换行，同时在后台问 tiec 各处的缩进和结束语句，
+    /// 文档没有再变化且结果不同时撤销这次换行，改用 tiec 的结果重新插入；
+    /// `smart_enter` 给出了智能键入时撤销这次换行，改为执行智能键入
+    fn insert_newline_with_indent(&mut self, smart_enter: Option<SmartEnterReply>, cx: &mut Context<Self>) {
+        let head = self.core.primary_selection().head;
+        self.core.merge_selections();
+        let content = self.core.content.clone();
+        let selections = self.core.selections.clone();
+        let queries = selections
+            .iter()
+            .map(|selection| self.newline_query(&content, selection.range()))
+            .collect();
+        let reply = self.lsp_manager.newline_hints(content.clone(), queries);
+        if reply.is_some() || smart_enter.is_some() {
+            // 之后可能撤销这次换行，不能与之前的输入合成一步
+            self.core.break_undo_group();
+        }
+        let local = self.newline_texts(&content, &selections, &[]);
+        self.apply_newline_texts(&local, &selections, cx);
+        if reply.is_none() && smart_enter.is_none() {
+            return;
+        }
+        let version = self.core.version();
+        cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let action = match smart_enter {
+                    Some(smart_enter) => match smart_enter.recv().await {
+                        Some(Ok(action)) => action,
+                        Some(Err(err)) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to get smart enter: {}", err));
+                            None
+                        }
+                        None => None,
+                    },
+                    None => None,
+                };
+                if let Some(action) = action {
+                    view.update(&mut cx, |this, cx| {
+                        if this.core.version() != version {
+                            return;
+                        }
+                        this.core.undo();
+                        this.sync_edits(cx);
+                        this.apply_smart_enter(action, head, cx);
+                    })
+                    .ok();
+                    return;
+                }
+                let Some(reply) = reply else {
+                    return;
+                };
+                let hints = match reply.recv().await {
+                    Some(Ok(hints)) => hints,
+                    Some(Err(err)) => {
+                        log_channel(OutputChannel::Lsp, format!("Failed to get newline hints: {}", err));
+                        return;
+                    }
+                    None => return,
+                };
+                view.update(&mut cx, |this, cx| {
+                    if this.core.version() != version {
+                        return;
+                    }
+                    let hinted = this.newline_texts(&content, &selections, &hints);
+                    if hinted == local {
+                        return;
+                    }
+                    this.core.undo();
+                    this.sync_edits(cx);
+                    this.apply_newline_texts(&hinted, &selections, cx);
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    /// 在 `range` 处回车时要问 tiec 的内容；光标后还有内容时不补全结束语句
+    fn newline_query(&self, content: &Rope, range: Range<usize>) -> NewlineQuery {
+        let line_start = content.line_to_byte(content.byte_to_line(range.start));
+        let before = content.byte_slice(line_start..range.start).to_string();
+        let end_line = content.byte_to_line(range.end);
+        let after = content.line(end_line).byte_slice(range.end - content.line_to_byte(end_line)..).to_string();
+        let closer_at = (self.auto_close_blocks && after.trim().is_empty()).then(|| self.lsp_position_for_index(range.start));
+        NewlineQuery { before, closer_at }
+    }
+
+    /// 各光标处回车要插入的内容；`hints` 为 tiec 按光标顺序给出的建议，为空时只按本地规则推断
+    fn newline_texts(&mut self, content: &Rope, selections: &[Selection], hints: &[NewlineHint]) -> NewlineTexts {
+        let rules = if self.auto_close_blocks { self.current_enter_rules().to_vec() } else { Vec::new() };
+        let mut texts = NewlineTexts::default();
+        // 插入的换行按文件的换行符写入
+        let ending = self.line_ending;
+        for (i, selection) in selections.iter().enumerate() {
+            let hint = hints.get(i).cloned().unwrap_or_default();
+            let range = selection.range();
+            let start = range.start;
+            let line_start = content.line_to_byte(content.byte_to_line(start));
+            let before = content.byte_slice(line_start..start).to_string();
+            let end_line = content.byte_to_line(range.end);
+            let line_end = if end_line + 1 < content.len_lines() {
+                content.line_to_byte(end_line + 1)
+            } else {
+                content.len_bytes()
+            };
+            let after = content.byte_slice(range.end..line_end).to_string();
+            let next_line = (end_line + 1 < content.len_lines()).then(|| content.line(end_line + 1).to_string());
+            let levels = hint.advance.unwrap_or_else(|| self.indent_levels_after(&before));
+            // 下一行已含有 tiec 给出的结束语句时不再补全
+            let closer = hint.closer.filter(|closer| {
+                self.auto_close_blocks && after.trim().is_empty() && !next_line.as_deref().is_some_and(|line| line.contains(closer.as_str()))
+            });
+            if let Some(closer) = closer {
+                let inner = Self::newline_with_indent(&before, levels.max(1));
+                let outer = Self::newline_with_indent(&before, 0);
+                let after_cursor = ending.normalize(&format!("{outer}{closer}")).into_owned();
+                texts.cursor_back.insert(start, after_cursor.len());
+                texts.texts.insert(start, format!("{}{after_cursor}", ending.normalize(&inner)));
+                continue;
+            }
+            if let Some(expansion) = enter_rules::expand(&rules, &before, &after, next_line.as_deref()) {
+                texts.cursor_back.insert(start, ending.normalize(&expansion.text[expansion.cursor..]).len());
+                texts.texts.insert(start, ending.normalize(&expansion.text).into_owned());
+                continue;
+            }
+            texts.texts.insert(start, ending.normalize(&Self::newline_with_indent(&before, levels)).into_owned());
+        }
+        texts
+    }
+
+    /// 在 `selections` 处插入 `texts`，作为一步撤销
+    fn apply_newline_texts(&mut self, texts: &NewlineTexts, selections: &[Selection], cx: &mut Context<Self>) {
+        let ending = self.line_ending;
+        self.core.replace_selections_with(|_, range| {
+            texts.texts.get(&range.start).cloned().unwrap_or_else(|| ending.as_str().to_string())
+        });
+        for (selection, original) in self.core.selections.iter_mut().zip(selections) {
+            if let Some(back) = texts.cursor_back.get(&original.range().start) {
+                let position = selection.head - back;
+                *selection = Selection::new(position, position);
+            }
+        }
+        self.core.break_undo_group();
+        self.sync_edits(cx);
+        self.update_completion(cx);
+        cx.notify();
+    }
+
+    /// 当前文档适用的回车规则：语法文件中的在前，插件提供的在后
+    fn current_enter_rules(&mut self) -> &[EnterRule] {
+        let uri = self.lsp_manager.doc_uri.clone();
+        if self.enter_rules.as_ref().map(|(cached, _)| cached) != Some(&uri) {
+            let mut specs = grammar_index_for_path(&uri)
+                .map(|index| grammar_rules(&grammar_source(index)))
+                .unwrap_or_default();
+            let extension = std::path::Path::new(&uri).extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+            specs.extend(
+                self.plugin_enter_rules
+                    .iter()
+                    .filter(|rule| rule.language.eq_ignore_ascii_case(extension))
+                    .map(|rule| EnterRuleSpec {
+                        pattern: rule.pattern.clone(),
+                        insert: rule.insert.clone(),
+                        closer: rule.closer.clone(),
+                    }),
+            );
+            self.enter_rules = Some((uri, compile_rules(&specs)));
+        }
+        self.enter_rules.as_ref().map(|(_, rules)| rules.as_slice()).unwrap_or_default()
+    }
+
+    /// 设置插件提供的回车规则，在发现插件后调用
+    pub fn set_plugin_enter_rules(&mut self, rules: Vec<EnterRuleContribution>) {
+        self.plugin_enter_rules = rules;
+        self.enter_rules = None;
+    }
+
+    /// 根据光标前的行内容推断下一行多缩进几级：行尾为 `{` 或以块起始关键字开头时缩进一级
+    fn indent_levels_after(&self, before_cursor: &str) -> i32 {
+        let trimmed = before_cursor.trim_end();
+        if trimmed.ends_with('{') || self.block_map.opens_block(trimmed) {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn tab(&mut self, _: &Tab, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.completion_active {
+            self.confirm_completion(cx);
+            return;
+        }
+        self.insert_text(INDENT_UNIT, cx);
+    }
+
+    fn shift_tab(&mut self, _: &ShiftTab, _window: &mut Window, _cx: &mut Context<Self>) {
+        println!("special: shift-tab");
+    }
+
+    #[allow(dead_code)]
+    fn ctrl_shift_tab(&mut self, _: &CtrlShiftTab, _window: &mut Window, _cx: &mut Context<Self>) {
+        println!("special: ctrl-shift-tab");
+    }
+
+    fn undo(&mut self, _: &Undo, _window: &mut Window, cx: &mut Context<Self>) {
+        self.core.undo();
+        self.sync_edits(cx);
+        self.update_completion(cx);
+        cx.notify();
+    }
+
+    fn redo(&mut self, _: &Redo, _window: &mut Window, cx: &mut Context<Self>) {
+        self.core.redo();
+        self.sync_edits(cx);
+        self.update_completion(cx);
+        cx.notify();
+    }
+
+    fn select_all(&mut self, _: &SelectAll, _window: &mut Window, cx: &mut Context<Self>) {
+        self.core.select_all();
+        self.completion_active = false;
+        cx.notify();
+    }
+
+    fn copy(&mut self, _: &Copy, _window: &mut Window, cx: &mut Context<Self>) {
+        let column = self.is_column_selection();
+        if let Some(text) = selections_text(&self.core.content, &self.core.selections, column) {
+            cx.write_to_clipboard(ClipboardItem::new_string(text));
+        }
+    }
+
+    fn cut(&mut self, _: &Cut, _window: &mut Window, cx: &mut Context<Self>) {
+        self.copy(&Copy, _window, cx);
+        self.core.break_undo_group();
+        self.core.delete_selection();
+        self.core.break_undo_group();
+        self.sync_edits(cx);
+        cx.notify();
+    }
+
+    fn paste(&mut self, _: &Paste, _window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(item) = cx.read_from_clipboard() {
+            if let Some(text) = item.text() {
+                if self.rename.is_some() {
+                    let line = text.lines().next().unwrap_or_default().to_string();
+                    self.edit_rename(|input| input.insert(&line), cx);
+                    return;
+                }
+                if self.find_focused() {
+                    let line = text.lines().next().unwrap_or_default().to_string();
+                    self.edit_find_query(|query| query.push_str(&line), cx);
+                    return;
+                }
+                // 粘贴的换行统一为文件的换行符
+                self.insert_text(&self.line_ending.normalize(&text), cx);
+            }
+        }
+    }
+
+    /// 打开查找栏并把输入焦点交给它；选中了单行文本时用作查询
+    fn toggle_find(&mut self, _: &ToggleFind, _: &mut Window, cx: &mut Context<Self>) {
+        let selected = self.selected_text();
+        let find = self.find.get_or_insert_with(FindState::default);
+        find.focused = true;
+        if !selected.is_empty() && !selected.contains('\n') {
+            find.query = selected;
+        }
+        self.schedule_find(cx);
+        cx.notify();
+    }
+
+    fn find_next(&mut self, _: &FindNext, _: &mut Window, cx: &mut Context<Self>) {
+        self.step_find(true, cx);
+    }
+
+    fn find_prev(&mut self, _: &FindPrev, _: &mut Window, cx: &mut Context<Self>) {
+        self.step_find(false, cx);
+    }
+
+    fn cancel_find(&mut self, _: &CancelFind, _: &mut Window, cx: &mut Context<Self>) {
+        self.close_find(cx);
+    }
+
+    fn step_find(&mut self, forward: bool, cx: &mut Context<Self>) {
+        if let Some(range) = self.find.as_mut().and_then(|find| find.step(forward)) {
+            self.select_find_match(range, cx);
+            cx.notify();
+        }
+    }
+
+    fn close_find(&mut self, cx: &mut Context<Self>) {
+        self.find = None;
+        self.find_task = None;
+        cx.notify();
+    }
+
+    fn selected_text(&self) -> String {
+        let range = self.core.primary_selection().range();
+        self.core.content.byte_slice(range).to_string()
+    }
+
+    fn go_to_definition(&mut self, _: &GoToDefinition, _: &mut Window, cx: &mut Context<Self>) {
+        let head = self.core.primary_selection().head;
+        self.go_to_definition_at(head, cx);
+    }
+
+    /// 在后台查找 `index` 处符号的定义：在当前文件中时移动光标，否则打开所在文件并跳到定义处
+    fn go_to_definition_at(&mut self, index: usize, cx: &mut Context<Self>) {
+        if self.is_peek_view || self.preview_uri.is_some() {
+            return;
+        }
+        let Some(backend) = self.lsp_manager.backend() else {
+            cx.emit(CodeEditorEvent::StatusMessage("找不到定义".to_string()));
+            return;
+        };
+        let (line, column) = self.lsp_position_for_index(index);
+        let uri = self.lsp_manager.doc_uri.clone();
+        let request_uri = uri.clone();
+        let version = self.core.version();
+        let executor = cx.background_executor().clone();
+        self.definition_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let result = executor.spawn(async move { backend.definition(&request_uri, line, column) }).await;
+                view.update(&mut cx, |this, cx| {
+                    // 查找期间文档已被编辑或切换，结果不再对应光标处的符号
+                    if this.core.version() != version || this.lsp_manager.doc_uri != uri {
+                        return;
+                    }
+                    let location = match result {
+                        Ok(location) => location,
+                        Err(err) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to find definition: {}", err));
+                            None
+                        }
+                    };
+                    let Some(location) = location else {
+                        cx.emit(CodeEditorEvent::StatusMessage("找不到定义".to_string()));
+                        return;
+                    };
+                    let current_path = Url::parse(&uri).ok().and_then(|url| url.to_file_path().ok());
+                    if current_path.as_ref() == Some(&location.path) {
+                        this.go_to_char_position(location.start.0, location.start.1, cx);
+                    } else {
+                        cx.emit(CodeEditorEvent::OpenLocation {
+                            path: location.path,
+                            line: location.start.0,
+                            column: location.start.1,
+                        });
+                    }
+                })
+                .ok();
+            }
+        }));
+    }
+
+    /// 光标处标识符在文档中的字节范围；光标在标识符末尾时也算在其中
+    fn identifier_at_cursor(&self) -> Option<Range<usize>> {
+        let head = self.core.primary_selection().head;
+        let line = self.core.content.byte_to_line(head);
+        let line_start = self.core.content.line_to_byte(line);
+        let line_text = self.core.content.line(line).to_string();
+        let byte = head - line_start;
+        let mut word = identifier_bounds(&line_text, byte);
+        if word.is_empty() {
+            let prev = line_text[..byte].chars().next_back()?;
+            word = identifier_bounds(&line_text, byte - prev.len_utf8());
         }
+        (!word.is_empty()).then(|| word.start + line_start..word.end + line_start)
     }
 
-    fn backspace(&mut self, _: &Backspace, _window: &mut Window, cx: &mut Context<Self>) {
-        // Expand empty selections to include previous char
-        for selection in self.core.selections.iter_mut() {
-            if selection.is_empty() {
-                let cursor = selection.head;
-                let prev = Self::prev_char_index(&self.core.content, cursor);
-                // Modify selection to cover the character to be deleted
-                // Note: we set anchor=prev, head=cursor (forward selection) or vice versa.
-                // replace_selections uses .range(), so order doesn't matter for deletion content,
-                // but we want to ensure we delete the range prev..cursor.
-                *selection = Selection::new(prev, cursor);
+    /// 查找选中文本或光标处标识符的引用
+    fn find_references(&mut self, _: &FindReferences, _: &mut Window, cx: &mut Context<Self>) {
+        if self.is_peek_view {
+            cx.propagate();
+            return;
+        }
+        let selected = self.selected_text();
+        let word = if !selected.trim().is_empty() && !selected.contains('\n') {
+            selected.trim().to_string()
+        } else {
+            match self.identifier_at_cursor() {
+                Some(range) => self.core.content.byte_slice(range).to_string(),
+                None => String::new(),
             }
+        };
+        if word.is_empty() {
+            cx.emit(CodeEditorEvent::StatusMessage("光标处没有可以查找引用的符号".to_string()));
+            return;
         }
-        self.core.delete_selection();
-        self.sync_sweetline_document(cx);
-        self.update_completion(cx);
-        cx.notify();
+        cx.emit(CodeEditorEvent::FindReferences(word));
     }
 
-    fn delete(&mut self, _: &Delete, _window: &mut Window, cx: &mut Context<Self>) {
-        // Expand empty selections to include next char
-        for selection in self.core.selections.iter_mut() {
-            if selection.is_empty() {
-                let cursor = selection.head;
-                let next = Self::next_char_index(&self.core.content, cursor);
-                *selection = Selection::new(cursor, next);
+    /// 在后台由 tiec 确认光标处的符号可以重命名，文档没有变化时在它上面打开重命名输入框，预填并选中原名
+    fn rename_symbol(&mut self, _: &RenameSymbol, _: &mut Window, cx: &mut Context<Self>) {
+        if self.is_peek_view || self.is_read_only() {
+            return;
+        }
+        let head = self.core.primary_selection().head;
+        let (line, column) = self.char_position(head);
+        let Some(reply) = self.lsp_manager.prepare_rename(line, column) else {
+            cx.emit(CodeEditorEvent::StatusMessage("只有结绳文件可以重命名符号".to_string()));
+            return;
+        };
+        let version = self.core.version();
+        let uri = self.lsp_manager.doc_uri.clone();
+        cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let Some(result) = reply.recv().await else {
+                    return;
+                };
+                view.update(&mut cx, |this, cx| {
+                    if this.lsp_manager.doc_uri != uri || this.core.version() != version {
+                        return;
+                    }
+                    let info = match result {
+                        Ok(info) if !info.name.is_empty() => info,
+                        Ok(_) => {
+                            cx.emit(CodeEditorEvent::StatusMessage("光标处没有可以重命名的符号".to_string()));
+                            return;
+                        }
+                        Err(err) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to prepare rename: {}", err));
+                            cx.emit(CodeEditorEvent::StatusMessage("光标处没有可以重命名的符号".to_string()));
+                            return;
+                        }
+                    };
+                    this.open_rename(info, cx);
+                })
+                .ok();
             }
+        })
+        .detach();
+    }
+
+    fn open_rename(&mut self, info: RenameSymbolInfo, cx: &mut Context<Self>) {
+        let start = index_for_char_position(&self.core.content, info.range.start.line, info.range.start.column);
+        let end = index_for_char_position(&self.core.content, info.range.end.line, info.range.end.column);
+        let range = start..end;
+        let name = info.name;
+        if let Some(find) = self.find.as_mut() {
+            find.focused = false;
         }
-        self.core.delete_selection();
-        self.sync_sweetline_document(cx);
-        self.update_completion(cx);
+        self.completion_active = false;
+        self.hover_popup = None;
+        self.rename = Some(RenameInput { input: NameInput::for_rename(&name), name, range, version: self.core.version() });
         cx.notify();
     }
 
-    fn delete_line(&mut self, _: &DeleteLine, _window: &mut Window, cx: &mut Context<Self>) {
-        let mut ranges_to_delete = Vec::new();
-
-        for selection in &self.core.selections {
-            let cursor = selection.range().start;
-            let line_idx = self.core.content.byte_to_line(cursor);
-            let line_start = self.core.content.line_to_byte(line_idx);
+    fn edit_rename(&mut self, edit: impl FnOnce(&mut NameInput), cx: &mut Context<Self>) {
+        if let Some(rename) = self.rename.as_mut() {
+            edit(&mut rename.input);
+            cx.notify();
+        }
+    }
 
-            let next_line_idx = line_idx + 1;
-            let range_end = if next_line_idx < self.core.content.len_lines() {
-                self.core.content.line_to_byte(next_line_idx)
-            } else {
-                self.core.content.len_bytes()
-            };
+    /// 确认重命名：新名称有效且与原名不同时向 tiec 请求各文件中的编辑，交给窗口修改
+    fn confirm_rename(&mut self, cx: &mut Context<Self>) {
+        let Some(rename) = self.rename.take() else {
+            return;
+        };
+        cx.notify();
+        let new_name = rename.input.text().trim().to_string();
+        if new_name == rename.name || rename.version != self.core.version() {
+            return;
+        }
+        let valid = new_name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+            && new_name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !valid {
+            cx.emit(CodeEditorEvent::StatusMessage(format!("“{}”不是有效的名称", new_name)));
+            return;
+        }
+        let (line, column) = self.char_position(rename.range.start);
+        let Some(reply) = self.lsp_manager.rename(line, column, &new_name) else {
+            return;
+        };
+        let uri = self.lsp_manager.doc_uri.clone();
+        cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let Some(result) = reply.recv().await else {
+                    return;
+                };
+                view.update(&mut cx, |this, cx| {
+                    // 等待期间文档有编辑时，编辑的位置已经过期
+                    if this.lsp_manager.doc_uri != uri || this.core.version() != rename.version {
+                        return;
+                    }
+                    match result {
+                        Ok(edits) => cx.emit(CodeEditorEvent::RenameSymbol { old_name: rename.name, edits }),
+                        Err(err) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to rename: {}", err));
+                            cx.emit(CodeEditorEvent::StatusMessage(format!("无法重命名“{}”", rename.name)));
+                        }
+                    }
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
 
-            let mut range_start = line_start;
+    /// 字节偏移所在的行和行内的字符列，均从 0 开始
+    fn char_position(&self, index: usize) -> (usize, usize) {
+        let content = &self.core.content;
+        let line = content.byte_to_line(index);
+        (line, content.byte_to_char(index) - content.line_to_char(line))
+    }
 
-            // If last line, try to delete previous newline
-            if next_line_idx >= self.core.content.len_lines() && range_start > 0 {
-                range_start -= 1;
-                if range_start > 0 && self.core.content.byte(range_start - 1) == b'\r' {
-                    range_start -= 1;
-                }
-            }
-            ranges_to_delete.push(range_start..range_end);
+    /// 在后台向 tiec 请求光标处可生成的事件等代码操作，有结果时在光标下方列出
+    fn code_action(&mut self, _: &CodeAction, _: &mut Window, cx: &mut Context<Self>) {
+        if self.is_peek_view || self.is_read_only() {
+            return;
         }
+        let Some(service) = self.lsp_manager.service() else {
+            return;
+        };
+        let head = self.core.primary_selection().head;
+        let version = self.core.version();
+        let (line, column) = self.lsp_position_for_index(head);
+        let uri = self.lsp_manager.doc_uri.clone();
+        let params = CursorParams { uri: uri.clone(), position: Position { line, column }, line_text: None };
+        self.code_action_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let Some(result) = service.request("code_action", move |service| service.generate_event(&params)).recv().await else {
+                    return;
+                };
+                view.update(&mut cx, |this, cx| {
+                    if this.lsp_manager.doc_uri != uri || this.core.version() != version {
+                        return;
+                    }
+                    match result {
+                        Ok(result) if !result.actions.is_empty() => {
+                            this.completion_active = false;
+                            this.hover_popup = None;
+                            this.code_actions = Some(CodeActionMenu { actions: result.actions, selected: 0, anchor: head, version });
+                        }
+                        Ok(_) => cx.emit(CodeEditorEvent::StatusMessage("光标处没有可用的操作".to_string())),
+                        Err(err) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to get code actions: {}", err));
+                            cx.emit(CodeEditorEvent::StatusMessage("获取代码操作失败".to_string()));
+                        }
+                    }
+                    cx.notify();
+                })
+                .ok();
+            }
+        }));
+    }
 
-        // We need to merge ranges if they overlap to avoid double deletion issues if we did it sequentially?
-        // Actually replace_selections expects selections.
-        // We can just construct selections from these ranges and call replace_selections("").
-        // But replace_selections expects US to update selections after.
-        // It's easier to use replace_selections.
-
-        self.core.selections = ranges_to_delete
+    /// 应用选中的代码操作，所有编辑作为一次撤销
+    fn apply_code_action(&mut self, cx: &mut Context<Self>) {
+        let Some(menu) = self.code_actions.take() else {
+            return;
+        };
+        cx.notify();
+        if menu.version != self.core.version() {
+            return;
+        }
+        let Some(action) = menu.actions.into_iter().nth(menu.selected) else {
+            return;
+        };
+        let content = &self.core.content;
+        let mut edits: Vec<(Range<usize>, String)> = action
+            .edits
             .into_iter()
-            .map(|r| Selection::new(r.start, r.end))
+            .map(|edit| {
+                let start = lsp_point_to_offset(content, edit.range.start.line, edit.range.start.column);
+                let end = lsp_point_to_offset(content, edit.range.end.line, edit.range.end.column);
+                (start..end.max(start), edit.new_text)
+            })
             .collect();
-        self.core.merge_selections(); // Merge overlapping lines
-
-        self.core.delete_selection();
-        self.sync_sweetline_document(cx);
-        cx.notify();
+        edits.sort_by_key(|(range, _)| range.start);
+        self.apply_edits(edits, cx);
     }
 
-    fn enter(&mut self, _: &Enter, _window: &mut Window, cx: &mut Context<Self>) {
-        if self.core.marked_range.is_some() {
+    /// 在后台查找光标处符号的定义，在当前行下方显示；找不到时在光标处提示
+    fn peek_definition(&mut self, _: &PeekDefinition, _: &mut Window, cx: &mut Context<Self>) {
+        if self.is_peek_view {
+            cx.propagate();
             return;
         }
-        if self.core.completion_active {
-            self.confirm_completion(cx);
+        let head = self.core.primary_selection().head;
+        let content = &self.core.content;
+        let line = content.byte_to_line(head);
+        let line_start = content.line_to_byte(line);
+        let column = content.byte_to_char(head) - content.byte_to_char(line_start);
+        let version = self.core.version();
+        let Some(reply) = self.lsp_manager.definitions(line, column) else {
+            self.show_peek_results(head, line_start, Vec::new(), cx);
             return;
-        }
-        self.insert_text("\n", cx);
+        };
+        cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let Some(result) = reply.recv().await else {
+                    return;
+                };
+                view.update(&mut cx, |this, cx| {
+                    if this.core.version() != version {
+                        return;
+                    }
+                    let results = result.unwrap_or_else(|err| {
+                        log_channel(OutputChannel::Lsp, format!("Failed to find definition: {}", err));
+                        Vec::new()
+                    });
+                    this.show_peek_results(head, line_start, results, cx);
+                })
+                .ok();
+            }
+        })
+        .detach();
     }
 
-    fn tab(&mut self, _: &Tab, _window: &mut Window, cx: &mut Context<Self>) {
-        if self.core.completion_active {
-            self.confirm_completion(cx);
+    fn show_peek_results(&mut self, head: usize, line_start: usize, results: Vec<DefinitionLocation>, cx: &mut Context<Self>) {
+        if results.is_empty() {
+            let position = self.point_for_index(head);
+            self.hover_popup =
+                Some(HoverPopup::plain("找不到定义".to_string(), position, DecorationColor::Gray, self.core.version(), None));
+            cx.notify();
             return;
         }
-        self.insert_text("    ", cx);
+        self.peek = Some(PeekState::new(line_start, self.core.version(), results));
+        self.show_peek_result(0, cx);
     }
 
-    fn shift_tab(&mut self, _: &ShiftTab, _window: &mut Window, _cx: &mut Context<Self>) {
-        println!("special: shift-tab");
+    /// 在嵌入编辑器中显示第 `index` 个结果
+    fn show_peek_result(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(peek) = self.peek.as_mut() else {
+            return;
+        };
+        peek.selected = index.min(peek.results.len().saturating_sub(1));
+        let Some(location) = peek.current().cloned() else {
+            return;
+        };
+        // 定义在当前文件中时显示未保存的内容
+        let current_path = Url::parse(&self.lsp_manager.doc_uri).ok().and_then(|url| url.to_file_path().ok());
+        let content = if current_path.as_ref() == Some(&location.path) {
+            self.core.content.to_string()
+        } else {
+            std::fs::read_to_string(&location.path).unwrap_or_default()
+        };
+        self.ensure_grammar_for(&default_doc_uri(&location.path));
+        let engine = self.sweetline_engine.clone();
+        let editor = self
+            .peek_editor
+            .get_or_insert_with(|| cx.new(|cx| CodeEditor::new_peek_view(engine, cx)))
+            .clone();
+        let compiled_grammars = self.compiled_grammars.clone();
+        let font_size = self.layout.font_size;
+        editor.update(cx, |peek, cx| {
+            // 宿主已编译所需的语法，嵌入编辑器不再重复编译
+            peek.compiled_grammars = compiled_grammars;
+            peek.layout.font_size = font_size;
+            peek.show_location(&location, &content, cx);
+        });
+        cx.notify();
     }
 
-    #[allow(dead_code)]
-    fn ctrl_shift_tab(&mut self, _: &CtrlShiftTab, _window: &mut Window, _cx: &mut Context<Self>) {
-        println!("special: ctrl-shift-tab");
+    /// 只读显示定义所在的文件，选中定义并在其上方保留几行上下文
+    fn show_location(&mut self, location: &DefinitionLocation, content: &str, cx: &mut Context<Self>) {
+        self.show_preview(&location.path, content, cx);
+        let start = index_for_char_position(&self.core.content, location.start.0, location.start.1);
+        let end = index_for_char_position(&self.core.content, location.end.0, location.end.1);
+        self.core.set_cursor(start);
+        self.core.select_to(end);
+        let top_line = location.start.0.saturating_sub(PEEK_CONTEXT_LINES);
+        self.layout.scroll_offset.y = -(self.layout.line_height() * top_line as f32);
+        cx.notify();
     }
 
-    fn undo(&mut self, _: &Undo, _window: &mut Window, cx: &mut Context<Self>) {
-        self.core.undo();
-        self.sync_sweetline_document(cx);
-        self.update_completion(cx);
+    pub fn close_peek(&mut self, cx: &mut Context<Self>) {
+        self.peek = None;
+        self.layout.block = None;
         cx.notify();
     }
 
-    fn redo(&mut self, _: &Redo, _window: &mut Window, cx: &mut Context<Self>) {
-        self.core.redo();
-        self.sync_sweetline_document(cx);
-        self.update_completion(cx);
-        cx.notify();
+    /// 真正跳转到查看中的定义
+    fn open_peek_location(&mut self, cx: &mut Context<Self>) {
+        let Some(location) = self.peek.as_ref().and_then(|peek| peek.current()).cloned() else {
+            return;
+        };
+        self.close_peek(cx);
+        cx.emit(CodeEditorEvent::OpenLocation {
+            path: location.path,
+            line: location.start.0,
+            column: location.start.1,
+        });
     }
 
-    fn select_all(&mut self, _: &SelectAll, _window: &mut Window, cx: &mut Context<Self>) {
-        self.core.select_all();
-        self.core.completion_active = false;
-        cx.notify();
+    /// 光标移动到指定行列（列按字符计）并滚动到可见区域
+    pub fn go_to_char_position(&mut self, line: usize, column: usize, cx: &mut Context<Self>) {
+        let index = index_for_char_position(&self.core.content, line, column);
+        self.set_cursor(index, cx);
+        self.scroll_to_cursor(cx);
     }
 
-    fn copy(&mut self, _: &Copy, _window: &mut Window, cx: &mut Context<Self>) {
-        let mut texts = Vec::new();
-        for selection in &self.core.selections {
-            if !selection.is_empty() {
-                texts.push(self.core.content.byte_slice(selection.range()).to_string());
+    /// 按文档编辑平移查看定义的锚点并更新插入块；锚点所在处被编辑时关闭
+    fn sync_peek_block(&mut self) {
+        let Some(peek) = self.peek.as_mut() else {
+            self.layout.block = None;
+            return;
+        };
+        let version = self.core.version();
+        if peek.anchor_version != version {
+            match self.core.map_range(peek.anchor_version, peek.anchor..peek.anchor) {
+                Some(range) => {
+                    peek.anchor = range.start;
+                    peek.anchor_version = version;
+                }
+                None => {
+                    self.peek = None;
+                    self.layout.block = None;
+                    return;
+                }
             }
         }
-        if texts.is_empty() {
+        let after_line = self.core.content.byte_to_line(peek.anchor.min(self.core.content.len_bytes()));
+        self.layout.block = Some(LayoutBlock { after_line, height: block_height(self.layout.line_height()) });
+    }
+
+    fn signature_help(&mut self, _: &SignatureHelp, _: &mut Window, cx: &mut Context<Self>) {
+        self.request_signature_help("", cx);
+    }
+
+    /// 光标所在调用的左括号位置，只在光标所在行中查找
+    fn enclosing_call(&self, index: usize) -> Option<usize> {
+        let line_start = self.core.content.line_to_byte(self.core.content.byte_to_line(index));
+        let before = self.core.content.byte_slice(line_start..index).to_string();
+        enclosing_paren(&before, before.len()).map(|open| line_start + open)
+    }
+
+    /// 在后台向 tiec 请求光标所在调用的签名；`trigger` 为触发提示的字符，快捷键触发时为空
+    fn request_signature_help(&mut self, trigger: &str, cx: &mut Context<Self>) {
+        if self.is_peek_view || self.preview_uri.is_some() {
             return;
         }
-        // Join with newlines for now
-        let text = texts.join("\n");
-        cx.write_to_clipboard(ClipboardItem::new_string(text));
+        let Some(service) = self.lsp_manager.service() else {
+            return;
+        };
+        let head = self.core.primary_selection().head;
+        let Some(open) = self.enclosing_call(head) else {
+            self.signature_popup = None;
+            cx.notify();
+            return;
+        };
+        let (line, column) = self.lsp_position_for_index(head);
+        let uri = self.lsp_manager.doc_uri.clone();
+        let params = SignatureHelpParams { uri: uri.clone(), position: Position { line, column }, trigger_char: trigger.to_string() };
+        self.signature_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let Some(result) = service.request("signature_help", move |service| service.signature_help(&params)).recv().await else {
+                    return;
+                };
+                view.update(&mut cx, |this, cx| {
+                    // 请求期间光标已离开这次调用或切换了文档
+                    let head = this.core.primary_selection().head;
+                    if this.lsp_manager.doc_uri != uri || this.enclosing_call(head) != Some(open) {
+                        return;
+                    }
+                    this.signature_popup = match result {
+                        Ok(result) if !result.signature.is_empty() => Some(SignaturePopup {
+                            active: active_parameter_range(&result.signature, &result.active_parameter),
+                            signature: result.signature,
+                            open,
+                            position: point(px(0.0), px(0.0)),
+                        }),
+                        Ok(_) => None,
+                        Err(err) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to get signature help: {}", err));
+                            None
+                        }
+                    };
+                    cx.notify();
+                })
+                .ok();
+            }
+        }));
     }
 
-    fn cut(&mut self, _: &Cut, _window: &mut Window, cx: &mut Context<Self>) {
-        self.copy(&Copy, _window, cx);
-        self.core.delete_selection();
-        self.sync_sweetline_document(cx);
-        cx.notify();
+    /// 光标移出提示所在的调用时关闭参数提示，否则更新提示的位置
+    fn sync_signature_popup(&mut self) {
+        let Some(open) = self.signature_popup.as_ref().map(|popup| popup.open) else {
+            return;
+        };
+        let head = self.core.primary_selection().head;
+        if head > self.core.content.len_bytes() || self.enclosing_call(head) != Some(open) {
+            self.signature_popup = None;
+            self.signature_task = None;
+            return;
+        }
+        let position = self.point_for_index(open);
+        if let Some(popup) = self.signature_popup.as_mut() {
+            popup.position = position;
+        }
     }
 
-    fn paste(&mut self, _: &Paste, _window: &mut Window, cx: &mut Context<Self>) {
-        if let Some(item) = cx.read_from_clipboard() {
-            if let Some(text) = item.text() {
-                self.insert_text(&text, cx);
-            }
+    fn format_document(&mut self, _: &FormatDocument, _: &mut Window, cx: &mut Context<Self>) {
+        self.format(cx).detach();
+    }
+
+    /// 在后台用 tiec 格式化文档，文档没有变化时只替换有变化的部分，作为一次撤销；失败时在状态栏提示。
+    /// 返回的任务在格式化结果应用后完成，格式化失败时为错误
+    pub fn format(&mut self, cx: &mut Context<Self>) -> Task<anyhow::Result<()>> {
+        if self.is_read_only() || self.is_peek_view || self.preview_uri.is_some() {
+            return Task::ready(Ok(()));
         }
+        let Some(reply) = self.lsp_manager.format_document(self.core.content.clone()) else {
+            return Task::ready(Ok(()));
+        };
+        let version = self.core.version();
+        cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let Some(result) = reply.recv().await else {
+                    return Ok(());
+                };
+                view.update(&mut cx, |this, cx| {
+                    if this.core.version() != version {
+                        return Ok(());
+                    }
+                    match result {
+                        Ok(formatted) => {
+                            let content = this.core.content.to_string();
+                            this.apply_edits(format_edits(&content, &formatted), cx);
+                            Ok(())
+                        }
+                        Err(err) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to format document: {}", err));
+                            cx.emit(CodeEditorEvent::StatusMessage(format!("格式化失败：{}", err)));
+                            Err(err)
+                        }
+                    }
+                })?
+            }
+        })
     }
 
-    // Find actions stubs to prevent crash if keybindings exist
-    fn toggle_find(&mut self, _: &ToggleFind, _: &mut Window, _: &mut Context<Self>) {}
-    fn find_next(&mut self, _: &FindNext, _: &mut Window, _: &mut Context<Self>) {}
-    fn find_prev(&mut self, _: &FindPrev, _: &mut Window, _: &mut Context<Self>) {}
-    fn cancel_find(&mut self, _: &CancelFind, _: &mut Window, _: &mut Context<Self>) {}
+    /// 在后台只按缩进格式化不在编辑器中的结绳文件内容；tiec 未加载时结果为 None
+    pub fn format_text(&mut self, content: String, cx: &mut Context<Self>) -> Task<anyhow::Result<Option<String>>> {
+        let Some(reply) = self.lsp_manager.format_text(content) else {
+            return Task::ready(Ok(None));
+        };
+        cx.background_executor().spawn(async move {
+            reply.recv().await.unwrap_or_else(|| Err(anyhow::anyhow!("format request was superseded"))).map(Some)
+        })
+    }
 
-    fn go_to_definition(&mut self, _: &GoToDefinition, _: &mut Window, _cx: &mut Context<Self>) {
-        // LSP functionality removed
+    fn toggle_line_comment(&mut self, _: &ToggleLineComment, _: &mut Window, cx: &mut Context<Self>) {
+        let Some(tokens) = CommentTokens::for_path(&self.lsp_manager.doc_uri) else {
+            return;
+        };
+        self.core.merge_selections();
+        let content = self.core.content.clone();
+        let mut edits = Vec::new();
+        // 每个选区各自决定注释还是取消注释；同一行只处理一次
+        let mut next_line = 0;
+        for selection in &self.core.selections {
+            let lines = comment::selection_lines(&content, selection.range());
+            let first = (*lines.start()).max(next_line);
+            if first > *lines.end() {
+                continue;
+            }
+            next_line = lines.end() + 1;
+            edits.extend(comment::toggle_line_comment_edits(&content, first..=*lines.end(), tokens));
+        }
+        self.apply_edits(edits, cx);
     }
 
-    fn signature_help(&mut self, _: &SignatureHelp, _: &mut Window, _cx: &mut Context<Self>) {
-        // LSP functionality removed
+    fn toggle_block_comment(&mut self, _: &ToggleBlockComment, _: &mut Window, cx: &mut Context<Self>) {
+        let Some(tokens) = CommentTokens::for_path(&self.lsp_manager.doc_uri) else {
+            return;
+        };
+        self.core.merge_selections();
+        let content = self.core.content.clone();
+        let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+        for selection in &self.core.selections {
+            let group = comment::toggle_block_comment_edits(&content, selection.range(), tokens);
+            // 同一行上的多个光标会得到相同的编辑，只应用一次
+            if group.first().is_some_and(|first| edits.iter().any(|(range, _)| *range == first.0)) {
+                continue;
+            }
+            edits.extend(group);
+        }
+        self.apply_edits(edits, cx);
     }
 
-    fn format_document(&mut self, _: &FormatDocument, _: &mut Window, _cx: &mut Context<Self>) {
-        // LSP functionality removed
+    /// 以一次撤销应用注释、更新引用等编辑，并把光标映射到编辑后的位置；范围为编辑前内容中的字节偏移
+    pub fn apply_edits(&mut self, edits: Vec<(Range<usize>, String)>, cx: &mut Context<Self>) {
+        if edits.is_empty() {
+            return;
+        }
+        for selection in &mut self.core.selections {
+            selection.anchor = comment::map_offset(selection.anchor, &edits);
+            selection.head = comment::map_offset(selection.head, &edits);
+            selection.preferred_column = None;
+        }
+        self.core.apply_edits(edits);
+        self.completion_active = false;
+        self.sync_edits(cx);
+        cx.notify();
     }
 
     fn escape(&mut self, _: &Escape, _: &mut Window, cx: &mut Context<Self>) {
+        // 嵌入编辑器中按 Escape 由宿主关闭查看定义
+        if self.is_peek_view {
+            cx.propagate();
+            return;
+        }
+        if self.rename.take().is_some() {
+            cx.notify();
+            return;
+        }
+        if self.code_actions.take().is_some() {
+            cx.notify();
+            return;
+        }
+        if self.signature_popup.take().is_some() {
+            self.signature_task = None;
+            cx.notify();
+            return;
+        }
+        if self.find.is_some() {
+            self.close_find(cx);
+            return;
+        }
+        if self.peek.is_some() {
+            self.close_peek(cx);
+            return;
+        }
         self.core.selections = vec![self.core.selections[0].clone()];
-        self.core.completion_active = false;
+        self.completion_active = false;
+        self.completion_trigger = None;
         self.hover_popup = None;
         cx.notify();
     }
@@ -1033,6 +3385,10 @@ impl CodeEditor {
     }
 
     fn move_left(&mut self, _: &Left, window: &mut Window, cx: &mut Context<Self>) {
+        if self.rename.is_some() {
+            self.edit_rename(NameInput::move_left, cx);
+            return;
+        }
         let content = &self.core.content;
         let shift = window.modifiers().shift;
 
@@ -1058,6 +3414,10 @@ impl CodeEditor {
     }
 
     fn move_right(&mut self, _: &Right, window: &mut Window, cx: &mut Context<Self>) {
+        if self.rename.is_some() {
+            self.edit_rename(NameInput::move_right, cx);
+            return;
+        }
         let content = &self.core.content;
         let shift = window.modifiers().shift;
 
@@ -1082,10 +3442,98 @@ impl CodeEditor {
         cx.notify();
     }
 
+    /// 把每个选区的 head 移到 `target(content, head)`，`extend` 时保留 anchor。
+    fn move_selections_with(
+        &mut self,
+        extend: bool,
+        cx: &mut Context<Self>,
+        target: impl Fn(&Rope, usize) -> usize,
+    ) {
+        let content = &self.core.content;
+
+        for selection in self.core.selections.iter_mut() {
+            let new_head = target(content, selection.head);
+
+            if extend {
+                selection.head = new_head;
+            } else {
+                *selection = Selection::new(new_head, new_head);
+            }
+            selection.preferred_column = None;
+        }
+        self.core.merge_selections();
+        self.scroll_to_cursor(cx);
+        cx.notify();
+    }
+
+    fn move_word(&mut self, forward: bool, extend: bool, cx: &mut Context<Self>) {
+        if forward {
+            self.move_selections_with(extend, cx, Self::next_word_index);
+        } else {
+            self.move_selections_with(extend, cx, Self::prev_word_index);
+        }
+    }
+
+    fn word_left(&mut self, _: &WordLeft, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_word(false, false, cx);
+    }
+
+    fn word_right(&mut self, _: &WordRight, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_word(true, false, cx);
+    }
+
+    fn select_word_left(&mut self, _: &SelectWordLeft, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_word(false, true, cx);
+    }
+
+    fn select_word_right(&mut self, _: &SelectWordRight, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_word(true, true, cx);
+    }
+
+    fn line_start(&mut self, _: &LineStart, _: &mut Window, cx: &mut Context<Self>) {
+        if self.rename.is_some() {
+            self.edit_rename(NameInput::move_home, cx);
+            return;
+        }
+        self.move_selections_with(false, cx, Self::smart_home_index);
+    }
+
+    fn line_end(&mut self, _: &LineEnd, _: &mut Window, cx: &mut Context<Self>) {
+        if self.rename.is_some() {
+            self.edit_rename(NameInput::move_end, cx);
+            return;
+        }
+        self.move_selections_with(false, cx, Self::line_end_index);
+    }
+
+    fn document_start(&mut self, _: &DocumentStart, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selections_with(false, cx, |_, _| 0);
+    }
+
+    fn document_end(&mut self, _: &DocumentEnd, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selections_with(false, cx, |content, _| content.len_bytes());
+    }
+
+    fn select_line_start(&mut self, _: &SelectLineStart, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selections_with(true, cx, Self::smart_home_index);
+    }
+
+    fn select_line_end(&mut self, _: &SelectLineEnd, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selections_with(true, cx, Self::line_end_index);
+    }
+
+    fn select_document_start(&mut self, _: &SelectDocumentStart, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selections_with(true, cx, |_, _| 0);
+    }
+
+    fn select_document_end(&mut self, _: &SelectDocumentEnd, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selections_with(true, cx, |content, _| content.len_bytes());
+    }
+
     fn ensure_completion_visible(&mut self) {
-        let max_visible_items = 10;
+        let max_visible_items = MAX_VISIBLE_ITEMS;
         let current_scroll = self.completion_scroll_offset as usize;
-        let index = self.core.completion_index;
+        let index = self.completion_index;
         
         if index < current_scroll {
             self.completion_scroll_offset = index as f32;
@@ -1095,9 +3543,14 @@ impl CodeEditor {
     }
 
     fn move_up(&mut self, _: &Up, window: &mut Window, cx: &mut Context<Self>) {
-        if self.core.completion_active {
-            if self.core.completion_index > 0 {
-                self.core.completion_index -= 1;
+        if let Some(menu) = self.code_actions.as_mut() {
+            menu.selected = menu.selected.saturating_sub(1);
+            cx.notify();
+            return;
+        }
+        if self.completion_active {
+            if self.completion_index > 0 {
+                self.completion_index -= 1;
                 self.ensure_completion_visible();
                 cx.notify();
             }
@@ -1125,7 +3578,8 @@ impl CodeEditor {
             let (line, col, _) = Self::line_col_for_index(content, cursor);
             let preferred = selection.preferred_column.unwrap_or(col);
 
-            let target_line = line.saturating_sub(1);
+            // 跳过折起的行
+            let target_line = self.layout.hidden.prev_visible(line).unwrap_or(0);
             let new_index = Self::index_for_line_col(content, target_line, preferred);
 
             let mut new_sel = if shift {
@@ -1144,9 +3598,14 @@ impl CodeEditor {
     }
 
     fn move_down(&mut self, _: &Down, window: &mut Window, cx: &mut Context<Self>) {
-        if self.core.completion_active {
-            if self.core.completion_index < self.core.completion_items.len().saturating_sub(1) {
-                self.core.completion_index += 1;
+        if let Some(menu) = self.code_actions.as_mut() {
+            menu.selected = (menu.selected + 1).min(menu.actions.len().saturating_sub(1));
+            cx.notify();
+            return;
+        }
+        if self.completion_active {
+            if self.completion_index < self.completion_items.len().saturating_sub(1) {
+                self.completion_index += 1;
                 cx.notify();
             }
             return;
@@ -1172,7 +3631,8 @@ impl CodeEditor {
             let (line, col, _) = Self::line_col_for_index(content, cursor);
             let preferred = selection.preferred_column.unwrap_or(col);
             let max_line = content.len_lines().saturating_sub(1);
-            let target_line = (line + 1).min(max_line);
+            let next_line = self.layout.hidden.next_visible(line);
+            let target_line = if next_line <= max_line { next_line } else { line };
             let new_index = Self::index_for_line_col(content, target_line, preferred);
 
             let mut new_sel = if shift {
@@ -1196,6 +3656,14 @@ impl CodeEditor {
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        // 指针在补全菜单上时滚动菜单而不是文档
+        if let Some(menu) = self.completion_menu.filter(|menu| self.completion_active && menu.bounds.contains(&event.position)) {
+            let lines = event.delta.pixel_delta(menu.item_height).y / menu.item_height;
+            self.completion_scroll_offset = (self.completion_scroll_offset - lines).clamp(0.0, menu.max_scroll() as f32);
+            self.completion_hover = None;
+            cx.notify();
+            return;
+        }
         if _window.modifiers().control {
             let old_font_size = self.layout.font_size;
             let old_line_height = self.layout.line_height();
@@ -1203,6 +3671,12 @@ impl CodeEditor {
             let delta = event.delta.pixel_delta(px(10.0)).y;
             self.layout.zoom(delta);
             let new_font_size = self.layout.font_size;
+            // 有字号覆盖的标签单独缩放，其余标签共用字号
+            if self.overrides.font_size.is_some() {
+                self.overrides.font_size = Some(f32::from(new_font_size));
+            } else {
+                self.base_font_size = new_font_size;
+            }
             let new_line_height = self.layout.line_height();
 
             let x_ratio = if old_font_size > px(0.0) {
@@ -1218,6 +3692,8 @@ impl CodeEditor {
 
             self.layout.scroll_offset.x = old_scroll_offset.x * x_ratio;
             self.layout.scroll_offset.y = old_scroll_offset.y * y_ratio;
+            // 行宽与字号成正比，下一帧重新测量前先按比例缩放
+            self.layout.content_width *= x_ratio;
 
             if let Ok(mut cache) = self.render_cache.lock() {
                 cache.clear();
@@ -1227,48 +3703,32 @@ impl CodeEditor {
             let view_size = bounds.size;
 
             let line_count = self.core.content.len_lines().max(1);
-            let total_height = self.layout.line_height() * line_count as f32;
+            let total_height = self.layout.content_height(line_count);
             let max_scroll_y =
                 (total_height - view_size.height + self.layout.line_height()).max(px(0.0));
 
-            let max_line_len = self
-                .core
-                .content
-                .lines()
-                .map(|l| l.len_bytes())
-                .max()
-                .unwrap_or(0);
             let max_digits = line_count.to_string().len();
-            let gutter_width = self.layout.gutter_width(max_digits);
-            let char_width = self.layout.font_size * 0.75;
-            let content_width = gutter_width + px(40.0) + (max_line_len as f32 * char_width);
-            let max_scroll_x = (content_width - view_size.width).max(px(0.0));
+            let max_scroll_x = self.layout.max_scroll_x(bounds, max_digits);
 
             self.layout.scroll_offset.y = self.layout.scroll_offset.y.clamp(-max_scroll_y, px(0.0));
             self.layout.scroll_offset.x = self.layout.scroll_offset.x.clamp(-max_scroll_x, px(0.0));
         } else {
-            let delta = event.delta.pixel_delta(px(20.0));
+            let mut delta = event.delta.pixel_delta(px(20.0));
+            // Shift+滚轮水平滚动
+            if _window.modifiers().shift && delta.x == px(0.0) {
+                delta = point(delta.y, px(0.0));
+            }
 
             let bounds = self.layout.last_bounds.unwrap_or_default();
             let view_size = bounds.size;
 
             let line_count = self.core.content.len_lines().max(1);
-            let total_height = self.layout.line_height() * line_count as f32;
+            let total_height = self.layout.content_height(line_count);
             let max_scroll_y =
                 (total_height - view_size.height + self.layout.line_height()).max(px(0.0));
 
-            let max_line_len = self
-                .core
-                .content
-                .lines()
-                .map(|l| l.len_bytes())
-                .max()
-                .unwrap_or(0);
             let max_digits = line_count.to_string().len();
-            let gutter_width = self.layout.gutter_width(max_digits);
-            let char_width = self.layout.font_size * 0.75;
-            let content_width = gutter_width + px(40.0) + (max_line_len as f32 * char_width);
-            let max_scroll_x = (content_width - view_size.width).max(px(0.0));
+            let max_scroll_x = self.layout.max_scroll_x(bounds, max_digits);
 
             self.layout.scroll(delta, point(max_scroll_x, max_scroll_y));
         }
@@ -1353,7 +3813,7 @@ impl CodeEditor {
         line_index: usize,
         line_start_byte: usize,
     ) -> CodeLine {
-        let key = format!("{}:{}", line_index, text);
+        let key = LineKey::for_text(self.render_generation, line_index, text);
 
         if let Ok(mut cache) = self.render_cache.lock() {
             if let Some(line) = cache.get(&key) {
@@ -1385,60 +3845,207 @@ impl CodeEditor {
     }
 
     fn sync_sweetline_document(&mut self, cx: &mut Context<Self>) {
-        let text = self.core.content.to_string();
+        // 整篇重新载入，之前积累的增量不再需要
+        self.core.take_changes();
+        self.render_generation = render_cache::next_generation();
+        self.layout.reset_content_width();
+        self.update_block_map();
+        self.folds_dirty = true;
+        self.load_highlights(self.lsp_manager.doc_uri.clone(), cx);
+        // 整篇重新载入时无法平移旧的语义高亮，等新的结果
+        self.semantic.clear();
+        self.schedule_git_diff(cx);
+        self.schedule_lint(LINT_DELAY, cx);
+        self.schedule_semantic_highlights(cx);
+        self.schedule_find(cx);
+    }
 
-        // Update Block Map
-        if self.lsp_manager.doc_uri.ends_with(".t") {
-            self.block_map.update(&self.core.content, JIESHENG_GRAMMAR);
+    /// 按当前文档重新计算可折叠区域：结绳按块关键字，Python 和 YAML 按缩进，其他按花括号
+    fn refresh_folds(&mut self) {
+        if !self.folds_dirty {
+            return;
+        }
+        self.folds_dirty = false;
+        if self.large_file {
+            return;
+        }
+        let uri = self.preview_uri.as_deref().unwrap_or(&self.lsp_manager.doc_uri);
+        let regions = if uri.ends_with(".t") {
+            folding::scope_regions(&self.block_map.scopes)
+        } else if folding::uses_indentation(uri) {
+            folding::indent_regions(&self.core.content)
         } else {
-            self.block_map.update(&self.core.content, "{}");
+            folding::brace_regions(&self.core.content, !uri.ends_with(".rs"))
+        };
+        self.folds.set_regions(regions);
+        self.update_hidden_lines();
+    }
+
+    /// 折叠变化后更新排版中隐藏的行
+    fn update_hidden_lines(&mut self) {
+        self.layout.hidden = Arc::new(self.folds.hidden_lines());
+    }
+
+    /// 修改折叠；光标所在行被折起时移到折叠首行的末尾
+    fn change_folds(&mut self, change: impl FnOnce(&mut Folds), cx: &mut Context<Self>) {
+        self.refresh_folds();
+        change(&mut self.folds);
+        self.update_hidden_lines();
+        let content = &self.core.content;
+        let line = content.byte_to_line(self.core.primary_selection().head.min(content.len_bytes()));
+        if self.layout.hidden.is_hidden(line) {
+            let header = self.layout.hidden.line_of_row(self.layout.hidden.row_of(line));
+            let end = content.line_to_byte(header) + render_cache::line_text_len(content, header);
+            self.set_cursor(end, cx);
         }
+        cx.notify();
+    }
 
-        let _ = self.sweetline_engine.remove_document(&self.lsp_manager.doc_uri);
-        self.sweetline_analyzer = None;
-        self.sweetline_document = None;
+    /// 折起或展开从第 `line` 行开始的区域
+    pub fn toggle_fold(&mut self, line: usize, cx: &mut Context<Self>) {
+        self.change_folds(|folds| {
+            folds.toggle(line);
+        }, cx);
+    }
 
-        let doc = Document::new(&self.lsp_manager.doc_uri, &text);
-        let analyzer = self.sweetline_engine.load_document(&doc);
+    pub fn fold_all(&mut self, cx: &mut Context<Self>) {
+        self.change_folds(Folds::fold_all, cx);
+        self.scroll_to_cursor(cx);
+    }
 
-        self.sweetline_document = Some(doc);
-        self.sweetline_analyzer = Some(analyzer);
+    pub fn unfold_all(&mut self, cx: &mut Context<Self>) {
+        self.change_folds(Folds::unfold_all, cx);
+        self.scroll_to_cursor(cx);
+    }
 
-        self.update_highlights();
-        self.update_git_diff(cx);
+    /// 只折起最外层的区域
+    pub fn fold_top_level(&mut self, cx: &mut Context<Self>) {
+        self.change_folds(|folds| folds.fold_level(1), cx);
+        self.scroll_to_cursor(cx);
     }
 
-    fn update_highlights(&mut self) {
-        if let Some(analyzer) = &self.sweetline_analyzer {
-            let result = analyzer.analyze();
-            self.cached_highlights = DocumentAnalyzer::parse_result(&result, false);
-            self.cached_highlights
-                .sort_by(|a, b| (a.end_index, a.start_index).cmp(&(b.end_index, b.start_index)));
-
-            // Update style cache
-            for span in &self.cached_highlights {
-                if !self.style_cache.contains_key(&span.style_id) {
-                    if let Some(name) = self.sweetline_engine.get_style_name(span.style_id) {
-                        // println!("Caching style: {} -> {}", span.style_id, name);
-                        if let Some(color) = self.color_for_style(&name) {
-                            self.style_cache.insert(span.style_id, color);
-                        }
+    fn fold_all_blocks(&mut self, _: &FoldAll, _: &mut Window, cx: &mut Context<Self>) {
+        self.fold_all(cx);
+    }
+
+    fn unfold_all_blocks(&mut self, _: &UnfoldAll, _: &mut Window, cx: &mut Context<Self>) {
+        self.unfold_all(cx);
+    }
+
+    fn fold_level_1(&mut self, _: &FoldLevel1, _: &mut Window, cx: &mut Context<Self>) {
+        self.fold_top_level(cx);
+    }
+
+    /// 点中的折叠按钮或折叠占位符所在的行
+    fn fold_toggle_at(&self, position: Point<Pixels>, window: &Window) -> Option<usize> {
+        let bounds = self.layout.last_bounds?;
+        if !bounds.contains(&position) {
+            return None;
+        }
+        let content = &self.core.content;
+        let max_digits = content.len_lines().max(1).to_string().len();
+        let line = self.layout.line_index_for_y(bounds, position.y);
+        if line >= content.len_lines() {
+            return None;
+        }
+        self.folds.region_at(line)?;
+        if self.layout.fold_column(bounds, max_digits).contains(&position.x) {
+            return Some(line);
+        }
+        if !self.folds.is_folded(line) {
+            return None;
+        }
+        let line_start = content.line_to_byte(line);
+        let text = content.byte_slice(line_start..line_start + render_cache::line_text_len(content, line)).to_string();
+        let width = self.get_cached_shape_line(window, &text, self.layout.font_size, line, line_start).width();
+        let placeholder_left = self.layout.text_x(bounds, max_digits) + width + FOLD_PLACEHOLDER_GAP;
+        (position.x >= placeholder_left && position.x <= placeholder_left + self.layout.font_size * 3.0).then_some(line)
+    }
+
+    fn update_block_map(&mut self) {
+        if self.lsp_manager.doc_uri.ends_with(".t") {
+            self.block_map.update(&self.core.content, &grammar_source(JIESHENG_INDEX));
+        } else {
+            self.block_map.update(&self.core.content, "{}");
+        }
+    }
+
+    /// 在后台整篇分析 `uri` 的高亮；分析完之前显示无高亮的文本，结果送回后逐块合并
+    fn load_highlights(&mut self, uri: String, cx: &mut Context<Self>) {
+        self.highlight_revision += 1;
+        self.sweetline_analyzer = None;
+        self.sweetline_document = None;
+        self.highlight_backlog = Some(Vec::new());
+        self.cached_highlights.clear();
+        if let Ok(mut cache) = self.render_cache.lock() {
+            cache.clear();
+        }
+        if self.large_file {
+            self.highlight_backlog = None;
+            self.highlight_task = None;
+            return;
+        }
+        self.ensure_grammar_for(&uri);
+        let job = HighlightJob::new(self.highlight_revision, self.sweetline_engine.clone(), &uri, &self.core.content);
+        self.highlight_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let loaded = cx.background_executor().spawn(async move { job.run() }).await;
+                let Ok(Some((revision, chunks))) = view.update(&mut cx, |this, cx| this.finish_highlight_load(loaded, cx)) else {
+                    return;
+                };
+                for chunk in chunks {
+                    let merged = view.update(&mut cx, |this, cx| this.merge_highlight_chunk(revision, chunk, cx));
+                    if !matches!(merged, Ok(true)) {
+                        return;
                     }
+                    cx.background_executor().timer(highlight::CHUNK_INTERVAL).await;
                 }
             }
+        }));
+    }
 
-            // Invalidate render cache
-            if let Ok(mut cache) = self.render_cache.lock() {
-                cache.clear();
-            }
+    /// 收下后台分析的结果；返回还需逐块合并的高亮，结果已过期或已在分析器上补上期间的编辑时返回 None
+    fn finish_highlight_load(&mut self, loaded: LoadedHighlights, cx: &mut Context<Self>) -> Option<(u64, Vec<HighlightChunk>)> {
+        if loaded.revision != self.highlight_revision {
+            return None;
+        }
+        let backlog = self.highlight_backlog.take().unwrap_or_default();
+        self.sweetline_document = Some(loaded.document);
+        let analyzer = self.sweetline_analyzer.insert(loaded.analyzer);
+        if backlog.is_empty() {
+            return Some((loaded.revision, loaded.chunks));
         }
+        // 分析的是编辑之前的文本，补上期间的编辑后直接得到整篇结果
+        let result = Self::analyze_changes(analyzer, &backlog);
+        self.update_highlights_from_result(result);
+        self.request_redraw(cx);
+        None
     }
 
-    fn update_highlights_from_result(&mut self, result: Vec<i32>) {
-        self.cached_highlights = DocumentAnalyzer::parse_result(&result, false);
-        self.cached_highlights
-            .sort_by(|a, b| (a.end_index, a.start_index).cmp(&(b.end_index, b.start_index)));
-        for span in &self.cached_highlights {
+    /// 合并一块高亮，只重绘它覆盖的行；版本已过期时返回 false，其余的块不再合并
+    fn merge_highlight_chunk(&mut self, revision: u64, chunk: HighlightChunk, cx: &mut Context<Self>) -> bool {
+        if revision != self.highlight_revision {
+            return false;
+        }
+        self.cache_styles(&chunk.spans);
+        self.cached_highlights.extend(chunk.spans);
+        self.invalidate_render_line_range(chunk.lines);
+        self.request_redraw(cx);
+        true
+    }
+
+    /// 依次在分析器上增量分析 `changes`，返回最后一次的整篇结果
+    fn analyze_changes(analyzer: &DocumentAnalyzer, changes: &[ContentChange]) -> Vec<i32> {
+        let mut result = Vec::new();
+        for change in changes {
+            result = analyzer.analyze_incremental(change.start.0, change.start.1, change.end.0, change.end.1, &change.text);
+        }
+        result
+    }
+
+    fn cache_styles(&mut self, spans: &[HighlightSpan]) {
+        for span in spans {
             if !self.style_cache.contains_key(&span.style_id) {
                 if let Some(name) = self.sweetline_engine.get_style_name(span.style_id) {
                     if let Some(color) = self.color_for_style(&name) {
@@ -1447,6 +4054,15 @@ impl CodeEditor {
                 }
             }
         }
+    }
+
+    fn update_highlights_from_result(&mut self, result: Vec<i32>) {
+        let mut spans = DocumentAnalyzer::parse_result(&result, false);
+        spans.sort_by_key(|span| (span.end_index, span.start_index));
+        self.cache_styles(&spans);
+        self.cached_highlights = spans;
+        // 整篇替换后，还没合并的分块已经过期
+        self.highlight_revision += 1;
         if let Ok(mut cache) = self.render_cache.lock() {
             cache.clear();
         }
@@ -1539,7 +4155,19 @@ impl CodeEditor {
                 last_end = end;
             }
         }
-        normalized
+
+        let line_range = line_start_byte..line_start_byte + line_len;
+        let semantic = self
+            .semantic
+            .in_range(line_range.clone())
+            .filter_map(|span| {
+                let color = self.color_for_style(span.style)?;
+                let start = span.range.start.max(line_range.start) - line_start_byte;
+                let end = span.range.end.min(line_range.end) - line_start_byte;
+                Some((start..end, color))
+            })
+            .collect();
+        overlay(normalized, semantic)
     }
 
 
@@ -1606,39 +4234,6 @@ impl CodeEditor {
         (line_index, col_utf16)
     }
 
-    fn lsp_point_to_offset(&self, line: usize, char_utf16: usize) -> usize {
-        let content = &self.core.content;
-        if line >= content.len_lines() {
-             return content.len_bytes();
-        }
-        let line_start_byte = content.line_to_byte(line);
-        let line_slice = content.line(line);
-        
-        // Fast path: if char_utf16 is 0, return start of line
-        if char_utf16 == 0 {
-            return line_start_byte;
-        }
-
-        // Iterate chars to find byte offset matching utf16 length
-        let mut current_utf16 = 0;
-        let mut byte_offset_in_line = 0;
-        
-        for char in line_slice.chars() {
-             if current_utf16 >= char_utf16 {
-                 break;
-             }
-             let len = char.len_utf16();
-             if current_utf16 + len > char_utf16 {
-                 // pointing into middle of a char? return start of this char
-                 break;
-             }
-             current_utf16 += len;
-             byte_offset_in_line += char.len_utf8();
-        }
-        
-        line_start_byte + byte_offset_in_line
-    }
-
     fn index_for_line_col(content: &Rope, line: usize, col: usize) -> usize {
         if line >= content.len_lines() {
             return content.len_bytes();
@@ -1701,6 +4296,120 @@ impl CodeEditor {
         content.char_to_byte(char_idx + 1)
     }
 
+    /// Home 键目标：先跳到行首第一个非空白字符，已经在那里时再跳到第 0 列。
+    fn smart_home_index(content: &Rope, index: usize) -> usize {
+        let index = index.min(content.len_bytes());
+        let line = content.byte_to_line(index);
+        let line_start = content.line_to_byte(line);
+        let indent: usize = content
+            .line(line)
+            .chars()
+            .take_while(|ch| *ch == ' ' || *ch == '\t')
+            .map(|ch| ch.len_utf8())
+            .sum();
+        let first_non_ws = line_start + indent;
+
+        if index == first_non_ws {
+            line_start
+        } else {
+            first_non_ws
+        }
+    }
+
+    /// 行尾（换行符之前）。CRLF 整体跳过，光标不会落在 \r 和 \n 之间。
+    fn line_end_index(content: &Rope, index: usize) -> usize {
+        let index = index.min(content.len_bytes());
+        let line = content.byte_to_line(index);
+        let line_start = content.line_to_byte(line);
+        let mut end = line_start + content.line(line).len_bytes();
+        if end > line_start && content.byte(end - 1) == b'\n' {
+            end -= 1;
+            if end > line_start && content.byte(end - 1) == b'\r' {
+                end -= 1;
+            }
+        }
+        end
+    }
+
+    /// 换行时插入的文本：沿用 `line_before_cursor` 的缩进，再增减 `levels` 级。
+    /// 以制表符缩进的行按 \t 增加，否则按四个空格。
+    fn newline_with_indent(line_before_cursor: &str, levels: i32) -> String {
+        let mut indent: String = line_before_cursor
+            .chars()
+            .take_while(|ch| *ch == ' ' || *ch == '\t')
+            .collect();
+        if levels >= 0 {
+            let unit = if indent.contains('\t') { "\t" } else { INDENT_UNIT };
+            for _ in 0..levels {
+                indent.push_str(unit);
+            }
+        } else {
+            for _ in 0..levels.unsigned_abs() {
+                let len = Self::dedent_once(&indent).len();
+                indent.truncate(len);
+            }
+        }
+        format!("\n{}", indent)
+    }
+
+    /// 去掉一级缩进：末尾的一个 \t，或回退到上一个四空格对齐位置。
+    fn dedent_once(indent: &str) -> &str {
+        if let Some(stripped) = indent.strip_suffix('\t') {
+            return stripped;
+        }
+        let spaces = indent.len() - indent.trim_end_matches(' ').len();
+        let remove = match spaces % INDENT_UNIT.len() {
+            0 => spaces.min(INDENT_UNIT.len()),
+            partial => partial,
+        };
+        &indent[..indent.len() - remove]
+    }
+
+    fn char_class(ch: char) -> CharClass {
+        if ch.is_whitespace() {
+            CharClass::Whitespace
+        } else if ch.is_alphanumeric() || ch == '_' {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        }
+    }
+
+    /// 向前跳过空白，再跳过一段同类字符（单词或标点），返回新的字节偏移。
+    fn prev_word_index(content: &Rope, index: usize) -> usize {
+        let index = index.min(content.len_bytes());
+        let mut char_idx = content.byte_to_char(index);
+
+        while char_idx > 0 && Self::char_class(content.char(char_idx - 1)) == CharClass::Whitespace {
+            char_idx -= 1;
+        }
+        if char_idx > 0 {
+            let class = Self::char_class(content.char(char_idx - 1));
+            while char_idx > 0 && Self::char_class(content.char(char_idx - 1)) == class {
+                char_idx -= 1;
+            }
+        }
+        content.char_to_byte(char_idx)
+    }
+
+    /// 向后跳过空白，再跳过一段同类字符（单词或标点），返回新的字节偏移。
+    fn next_word_index(content: &Rope, index: usize) -> usize {
+        let index = index.min(content.len_bytes());
+        let len = content.len_chars();
+        let mut char_idx = content.byte_to_char(index);
+
+        while char_idx < len && Self::char_class(content.char(char_idx)) == CharClass::Whitespace {
+            char_idx += 1;
+        }
+        if char_idx < len {
+            let class = Self::char_class(content.char(char_idx));
+            while char_idx < len && Self::char_class(content.char(char_idx)) == class {
+                char_idx += 1;
+            }
+        }
+        content.char_to_byte(char_idx)
+    }
+
     fn utf16_index_to_byte_in_str(text: &str, utf16_index: usize) -> usize {
         let mut count = 0;
         for (byte_index, ch) in text.char_indices() {
@@ -1846,6 +4555,20 @@ impl EntityInputHandler for CodeEditor {
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        if self.rename.is_some() {
+            let line = new_text.lines().next().unwrap_or_default().to_string();
+            self.edit_rename(|input| input.insert(&line), cx);
+            return;
+        }
+        // 输入时关闭代码操作列表
+        self.code_actions = None;
+        if self.find_focused() {
+            self.edit_find_query(|query| query.push_str(new_text), cx);
+            return;
+        }
+        if self.is_read_only() {
+            return;
+        }
         let mut range = range_utf16
             .as_ref()
             .map(|range_utf16| self.core.range_from_utf16(range_utf16))
@@ -1858,28 +4581,28 @@ impl EntityInputHandler for CodeEditor {
             range.end = range.end.min(content_len);
         }
 
-        // Compute incremental range BEFORE applying edit
-        let (start_line, start_col) = self.lsp_position_for_index(range.start);
-        let (end_line, end_col) = self.lsp_position_for_index(range.end);
+        // 在只有缩进的行上输入 `}` 时先回退一级缩进，与插入合并为一步撤销
+        if new_text == "}" && range.is_empty() && self.core.marked_range.is_none() {
+            let content = &self.core.content;
+            let line_start = content.line_to_byte(content.byte_to_line(range.start));
+            let before = content.byte_slice(line_start..range.start).to_string();
+            if !before.is_empty() && before.chars().all(|ch| ch == ' ' || ch == '\t') {
+                range.start = line_start + Self::dedent_once(&before).len();
+            }
+        }
 
         self.core.replace_range(range.clone(), new_text);
+        self.sync_edits(cx);
 
-        // Incremental analyze to avoid full-document reload
-        if let Some(analyzer) = &self.sweetline_analyzer {
-            let result = analyzer.analyze_incremental(
-                start_line,
-                start_col,
-                end_line,
-                end_col,
-                new_text,
-            );
-            self.update_highlights_from_result(result);
-        } else {
-            self.sync_sweetline_document(cx);
+        self.batch_redraw(cx, |this, cx| {
+            this.update_completion(cx);
+            this.request_redraw(cx);
+        });
+        match new_text {
+            "(" | "," => self.request_signature_help(new_text, cx),
+            ")" => self.signature_popup = None,
+            _ => {}
         }
-
-        self.update_completion(cx);
-        cx.notify();
     }
 
     fn replace_and_mark_text_in_range(
@@ -1890,6 +4613,10 @@ impl EntityInputHandler for CodeEditor {
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        // 查找栏和重命名输入框不显示输入法的组字，只接收最终提交的文本
+        if self.is_read_only() || self.find_focused() || self.rename.is_some() {
+            return;
+        }
         let mut range = range_utf16
             .as_ref()
             .map(|range_utf16| self.core.range_from_utf16(range_utf16))
@@ -1904,24 +4631,8 @@ impl EntityInputHandler for CodeEditor {
             range.end = range.end.min(content_len);
         }
 
-        // Compute incremental range BEFORE applying edit
-        let (start_line, start_col) = self.lsp_position_for_index(range.start);
-        let (end_line, end_col) = self.lsp_position_for_index(range.end);
-
         self.core.replace_range(range.clone(), new_text);
-
-        if let Some(analyzer) = &self.sweetline_analyzer {
-            let result = analyzer.analyze_incremental(
-                start_line,
-                start_col,
-                end_line,
-                end_col,
-                new_text,
-            );
-            self.update_highlights_from_result(result);
-        } else {
-            self.sync_sweetline_document(cx);
-        }
+        self.sync_edits(cx);
 
         if !new_text.is_empty() {
             let new_end = range.start + new_text.len();
@@ -2040,7 +4751,7 @@ impl EntityInputHandler for CodeEditor {
 }
 
 impl CodeEditor {
-    fn hover_info_at(&self, index: usize) -> Option<(String, DecorationColor)> {
+    fn hover_info_at(&self, index: usize) -> Option<(String, DecorationColor, Range<usize>)> {
         let mut best: Option<(&Decoration, usize)> = None;
         for d in &self.decorations {
             if d.message.is_none() {
@@ -2061,56 +4772,161 @@ impl CodeEditor {
                 }
             }
         }
-        best.map(|(d, _)| (d.message.clone().unwrap_or_default(), d.color))
-            .filter(|(t, _)| !t.is_empty())
+        best.map(|(d, _)| (d.message.clone().unwrap_or_default(), d.color, d.range.clone()))
+            .filter(|(t, _, _)| !t.is_empty())
     }
 
+    /// 指针移动后按 [`HoverDelays`] 安排悬停提示：停下一段时间才显示，离开提示的范围后稍等再隐藏
     fn update_hover_popup(&mut self, pos: Point<Pixels>, window: &Window, cx: &mut Context<Self>) {
-        self.process_lsp_messages(cx);
-
         let index = self.index_for_point(pos, window);
-        
-        // Check local decorations first
-        let next = index
-            .and_then(|i| self.hover_info_at(i))
-            .map(|(text, color)| HoverPopup {
-                text,
-                position: pos,
-                color,
-            });
+        let showing = self.hover_popup.as_ref().map(|popup| popup.range.as_ref());
+        let HoverPlan::Schedule { hide_after, show_after } = self.hover_delays.plan(showing, index) else {
+            self.hover_task = None;
+            return;
+        };
+        self.hover_request = None;
+        let version = self.core.version();
+        self.hover_task = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let mut waited = Duration::ZERO;
+                if let Some(hide_after) = hide_after {
+                    cx.background_executor().timer(hide_after).await;
+                    waited = hide_after;
+                    let hidden = view.update(&mut cx, |this, cx| {
+                        this.hover_popup = None;
+                        cx.notify();
+                    });
+                    if hidden.is_err() {
+                        return;
+                    }
+                }
+                let (Some(show_after), Some(index)) = (show_after, index) else {
+                    return;
+                };
+                cx.background_executor().timer(show_after.saturating_sub(waited)).await;
+                view.update(&mut cx, |this, cx| this.show_hover(index, pos, version, cx)).ok();
+            }
+        }));
+    }
 
-        if let Some(next_popup) = next {
-             self.hover_popup = Some(next_popup);
-             cx.notify();
-             return;
+    /// 显示 `index` 处的悬停提示：先取诊断等装饰的说明，没有时向语言服务请求
+    fn show_hover(&mut self, index: usize, pos: Point<Pixels>, version: u64, cx: &mut Context<Self>) {
+        if self.core.version() != version {
+            return;
         }
-        
-        // If no local decoration, request from LSP
-        if let Some(_idx) = index {
-            /*
-            let (line, col) = self.lsp_position_for_index(idx);
-            
-            // Debounce/Check duplicate: If we already requested hover for this position recently, skip
-            // We use a simplified check here. In a robust impl, we'd want true debouncing.
-            let already_pending = self.lsp_manager.pending_requests.values().any(|k| {
-                 if let LspRequestKind::Hover { index, .. } = k {
-                     index == &idx
-                 } else {
-                     false
-                 }
-            });
-            */
+        self.process_lsp_messages(cx);
+        self.refresh_decorations();
+
+        if let Some((text, color, range)) = self.hover_info_at(index) {
+            self.hover_popup = Some(HoverPopup::plain(text, pos, color, version, Some(range)));
+            cx.notify();
+            return;
+        }
+        let byte = self.core.range_from_utf16(&(index..index)).start;
+        let line = self.core.content.byte_to_line(byte);
+        let line_start = self.core.content.line_to_byte(line);
+        let line_text = self.core.content.line(line).to_string();
+        let word = identifier_bounds(&line_text, byte - line_start);
+        if word.is_empty() {
+            return;
+        }
+        let Some(backend) = self.lsp_manager.backend() else {
+            return;
+        };
+        let (line, column) = self.lsp_position_for_index(byte);
+        let uri = self.lsp_manager.doc_uri.clone();
+        let request_uri = uri.clone();
+        let range = self.core.offset_to_utf16(line_start + word.start)..self.core.offset_to_utf16(line_start + word.end);
+        let executor = cx.background_executor().clone();
+        self.hover_request = Some(cx.spawn(move |view: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let result = executor.spawn(async move { backend.hover(&request_uri, line, column) }).await;
+                view.update(&mut cx, |this, cx| {
+                    if this.core.version() != version || this.lsp_manager.doc_uri != uri {
+                        return;
+                    }
+                    let text = match result {
+                        Ok(text) => text,
+                        Err(err) => {
+                            log_channel(OutputChannel::Lsp, format!("Failed to get hover: {}", err));
+                            return;
+                        }
+                    };
+                    if text.trim().is_empty() {
+                        return;
+                    }
+                    let blocks = parse_hover_markdown(&text);
+                    let code_colors = blocks
+                        .iter()
+                        .map(|block| match block {
+                            HoverBlock::Code { language, code } => this.hover_code_colors(language.as_deref(), code),
+                            HoverBlock::Text { .. } => Vec::new(),
+                        })
+                        .collect();
+                    this.hover_popup = Some(HoverPopup {
+                        blocks,
+                        code_colors,
+                        position: pos,
+                        color: DecorationColor::Gray,
+                        version,
+                        range: Some(range),
+                    });
+                    cx.notify();
+                })
+                .ok();
+            }
+        }));
+    }
 
-// LSP hover request removed
+    /// 悬停文档中代码块的语法颜色；没有注明语言或注明结绳时按当前文档的语言高亮
+    fn hover_code_colors(&mut self, language: Option<&str>, code: &str) -> Vec<(Range<usize>, Hsla)> {
+        let extension = match language {
+            None | Some("结绳") => self.lsp_manager.doc_uri.rsplit_once('.').map_or("t", |(_, ext)| ext).to_string(),
+            Some(language) => language.to_ascii_lowercase(),
+        };
+        let uri = format!("file:///hover-block.{}", extension);
+        self.ensure_grammar_for(&uri);
+        let analyzer = self.sweetline_engine.load_document(&Document::new(&uri, code));
+        let spans = DocumentAnalyzer::parse_result(&analyzer.analyze(), false);
+        let _ = self.sweetline_engine.remove_document(&uri);
+
+        // 高亮按字符计，转为字节范围；重叠的部分只保留先出现的
+        let offsets: Vec<usize> = code.char_indices().map(|(i, _)| i).chain([code.len()]).collect();
+        let mut colors: Vec<(Range<usize>, Hsla)> = Vec::new();
+        for span in spans {
+            let color = self
+                .sweetline_engine
+                .get_style_name(span.style_id)
+                .and_then(|name| self.color_for_style(&name));
+            let (Some(color), Some(&start), Some(&end)) =
+                (color, offsets.get(span.start_index as usize), offsets.get(span.end_index as usize))
+            else {
+                continue;
+            };
+            if start < end {
+                colors.push((start..end, color));
+            }
         }
-        
-        // Don't clear immediately if waiting for LSP, but maybe we should?
-        // If we moved, the old popup might be stale.
-        // Let's keep it until new one arrives or explicitly cleared?
-        // Actually, if we moved to a new place without local info, we should probably clear the old one until LSP replies.
-        if self.hover_popup.is_some() {
-             self.hover_popup = None;
-             cx.notify();
+        colors.sort_by_key(|(range, _)| range.start);
+        let mut last_end = 0;
+        colors.retain(|(range, _)| {
+            let keep = range.start >= last_end;
+            if keep {
+                last_end = range.end;
+            }
+            keep
+        });
+        colors
+    }
+
+    /// 按下鼠标或拖动时不显示悬停提示
+    fn suppress_hover(&mut self, cx: &mut Context<Self>) {
+        self.hover_task = None;
+        self.hover_request = None;
+        if self.hover_popup.take().is_some() {
+            cx.notify();
         }
     }
 
@@ -2129,37 +4945,134 @@ impl CodeEditor {
             line_index = line_count.saturating_sub(1);
         }
 
+        Some(self.index_for_line_x(line_index, point.x - text_x, window))
+    }
+
+    /// 第 `line_index` 行上横坐标 `x`（相对文本起点）处的字节偏移，超出行尾时为行尾
+    fn index_for_line_x(&self, line_index: usize, x: Pixels, window: &Window) -> usize {
+        let (line_start, line_text, line) = self.shaped_line_at(line_index, window);
+        let utf8_index = Self::clamp_to_char_boundary(
+            &line_text,
+            line.index_for_x(x.max(px(0.0))).unwrap_or(line_text.len()),
+        );
+        (line_start + utf8_index).min(self.core.content.len_bytes())
+    }
+
+    /// 第 `line_index` 行的起点、不含换行符的文本和排版结果
+    fn shaped_line_at(&self, line_index: usize, window: &Window) -> (usize, String, CodeLine) {
+        let content = &self.core.content;
         let line_start = content.line_to_byte(line_index);
-        let line_slice = content.line(line_index);
-        let mut line_text_string = line_slice.to_string();
-        if line_text_string.ends_with('\n') {
-            line_text_string.pop();
-            if line_text_string.ends_with('\r') {
-                line_text_string.pop();
+        let mut line_text = content.line(line_index).to_string();
+        if line_text.ends_with('\n') {
+            line_text.pop();
+            if line_text.ends_with('\r') {
+                line_text.pop();
             }
         }
-        let line_text = &line_text_string;
+        let line = self.get_cached_shape_line(window, &line_text, self.layout.font_size, line_index, line_start);
+        (line_start, line_text, line)
+    }
 
-        let line = self.get_cached_shape_line(
-            window,
-            line_text,
-            self.layout.font_size,
-            line_index,
-            line_start,
-        );
-        let local_x = (point.x - text_x).max(px(0.0));
-        let utf8_index = Self::clamp_to_char_boundary(
-            line_text,
-            line.index_for_x(local_x).unwrap_or(line_text.len()),
-        );
-        let index = line_start + utf8_index;
-        if index > content.len_bytes() {
-            Some(content.len_bytes())
-        } else {
-            Some(index)
+    /// 指针所在的行和相对文本起点的横坐标，用于列选择
+    fn column_point(&self, point: Point<Pixels>) -> Option<ColumnPoint> {
+        let bounds = self.layout.last_bounds?;
+        let line_count = self.core.content.len_lines().max(1);
+        let text_x = self.layout.text_x(bounds, line_count.to_string().len());
+        let line = self.layout.line_index_for_y(bounds, point.y).min(line_count - 1);
+        Some(ColumnPoint { line, x: point.x - text_x })
+    }
+
+    /// 矩形区域内每个显示的行各一个选区，两端按像素列定位，比例字体和全角字符都能对齐；
+    /// 短于起点列的行在行尾放一个光标
+    fn column_selections(&self, anchor: ColumnPoint, head: ColumnPoint, window: &Window) -> Vec<Selection> {
+        let lines = anchor.line.min(head.line)..anchor.line.max(head.line) + 1;
+        self.layout
+            .hidden
+            .visible_in(lines)
+            .map(|line| {
+                Selection::new(
+                    self.index_for_line_x(line, anchor.x, window),
+                    self.index_for_line_x(line, head.x, window),
+                )
+            })
+            .collect()
+    }
+
+    /// 当前选区仍是最近一次列选择的结果
+    fn is_column_selection(&self) -> bool {
+        self.column_selection
+            .as_ref()
+            .is_some_and(|column| column.selections == self.core.selections)
+    }
+
+    /// 用 `base` 加上列选区替换当前选区，并记下这次列选择
+    fn set_column_selection(&mut self, base: Vec<Selection>, column: Vec<Selection>, anchor_x: Pixels, head_x: Pixels, cx: &mut Context<Self>) {
+        self.core.selections = base;
+        self.core.selections.extend(column);
+        self.core.merge_selections();
+        self.core.marked_range = None;
+        self.completion_active = false;
+        self.column_selection = Some(ColumnSelection { selections: self.core.selections.clone(), anchor_x, head_x });
+        cx.notify();
+    }
+
+    /// 拖选时把选区延伸到指针处；Alt 拖动时改为矩形区域
+    fn drag_select_to(&mut self, point: Point<Pixels>, window: &Window, cx: &mut Context<Self>) {
+        if let Some(drag) = self.column_drag.clone() {
+            if let Some(head) = self.column_point(point) {
+                let column = self.column_selections(drag.anchor, head, window);
+                self.set_column_selection(drag.base, column, drag.anchor.x, head.x, cx);
+            }
+        } else if let Some(index) = self.index_for_point(point, window) {
+            self.select_to(index, cx);
         }
     }
 
+    /// 在最上方（`up`）或最下方的选区的相邻显示行上按相同的像素列再加一个选区
+    fn grow_column(&mut self, up: bool, window: &mut Window, cx: &mut Context<Self>) {
+        let edge = if up { self.core.selections.first() } else { self.core.selections.last() };
+        let Some(edge) = edge.cloned() else {
+            return;
+        };
+        let (line, _) = self.core.line_col_for_offset(edge.head);
+        let target = if up {
+            self.layout.hidden.prev_visible(line)
+        } else {
+            Some(self.layout.hidden.next_visible(line)).filter(|next| *next < self.core.content.len_lines())
+        };
+        let Some(target) = target else {
+            return;
+        };
+        // 延续之前的列选择，否则从主光标所在的列开始
+        let (anchor_x, head_x) = match self.column_selection.as_ref().filter(|_| self.is_column_selection()) {
+            Some(column) => (column.anchor_x, column.head_x),
+            None => {
+                let primary = self.core.primary_selection();
+                let x_of = |index: usize| {
+                    let (line, _) = self.core.line_col_for_offset(index);
+                    let (line_start, _, shaped) = self.shaped_line_at(line, window);
+                    shaped.x_for_index(index - line_start)
+                };
+                (x_of(primary.anchor), x_of(primary.head))
+            }
+        };
+        let added = Selection::new(
+            self.index_for_line_x(target, anchor_x, window),
+            self.index_for_line_x(target, head_x, window),
+        );
+        let base = self.core.selections.clone();
+        self.set_column_selection(base, vec![added], anchor_x, head_x, cx);
+        self.scroll_to_cursor(cx);
+    }
+
+    fn add_cursor_above(&mut self, _: &AddCursorAbove, window: &mut Window, cx: &mut Context<Self>) {
+        self.grow_column(true, window, cx);
+    }
+
+    fn add_cursor_below(&mut self, _: &AddCursorBelow, window: &mut Window, cx: &mut Context<Self>) {
+        self.grow_column(false, window, cx);
+    }
+
     fn spawn_block_highlight_animation(
         &mut self,
         start_time: Instant,
@@ -2216,6 +5129,25 @@ impl CodeEditor {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        if self.completion_mouse_down(event.position, cx) {
+            return;
+        }
+        self.suppress_hover(cx);
+        // 点击编辑区放弃重命名和代码操作
+        self.rename = None;
+        self.code_actions = None;
+        // 点击编辑区把输入交回编辑器，查找栏保持打开，编辑区恢复正常亮度
+        if let Some(find) = self.find.as_mut() {
+            find.focused = false;
+        }
+
+        if event.button == MouseButton::Left && !event.modifiers.shift {
+            if let Some(line) = self.fold_toggle_at(event.position, window) {
+                self.toggle_fold(line, cx);
+                return;
+            }
+        }
+
         // Check Block Indent Line Click / Line Highlight
         if !event.modifiers.shift
             && !event.modifiers.alt
@@ -2374,17 +5306,15 @@ impl CodeEditor {
             let content_height = self.layout.content_height(self.core.content.len_lines());
             let thumb_bounds = self.layout.thumb_bounds(bounds, content_height);
 
-            let scrollbar_width = self.layout.scrollbar_width();
-            let track_bounds = Bounds::new(
-                point(bounds.right() - scrollbar_width, bounds.top()),
-                size(scrollbar_width, bounds.size.height),
-            );
+            let track_bounds = self.layout.track_bounds(bounds);
 
             if track_bounds.contains(&event.position) {
                 if thumb_bounds.contains(&event.position) {
                     self.dragging_scrollbar = true;
                     self.drag_start_y = Some(event.position.y);
                     self.scroll_start_y = Some(self.layout.scroll_offset.y);
+                } else if let Some(row) = self.scrollbar_marks.line_at(track_bounds, event.position) {
+                    self.center_line(self.layout.hidden.line_of_row(row));
                 } else {
                     let percent = (event.position.y - bounds.top()) / bounds.size.height;
                     let max_scroll = (content_height - bounds.size.height).max(px(0.0));
@@ -2393,18 +5323,45 @@ impl CodeEditor {
                 cx.notify();
                 return;
             }
+
+            let max_digits = self.core.content.len_lines().max(1).to_string().len();
+            let h_thumb_bounds = self.layout.h_thumb_bounds(bounds, max_digits);
+            let h_track_bounds = self.layout.h_track_bounds(bounds, max_digits);
+            if !h_thumb_bounds.is_empty() && h_track_bounds.contains(&event.position) {
+                if h_thumb_bounds.contains(&event.position) {
+                    self.dragging_h_scrollbar = true;
+                    self.drag_start_x = Some(event.position.x);
+                    self.scroll_start_x = Some(self.layout.scroll_offset.x);
+                } else {
+                    let percent = (event.position.x - h_track_bounds.left()) / h_track_bounds.size.width;
+                    self.layout.scroll_offset.x = -self.layout.max_scroll_x(bounds, max_digits) * percent;
+                }
+                cx.notify();
+                return;
+            }
         }
 
         self.hover_popup = None;
 
         if let Some(index) = self.index_for_point(event.position, window) {
+            self.drag_selecting = true;
+            self.drag_pointer = Some(event.position);
             if event.modifiers.alt {
-                // Add cursor
+                // Alt 单击加一个光标，拖动则按矩形区域选择
+                self.column_drag = self.column_point(event.position).map(|anchor| ColumnDrag {
+                    anchor,
+                    base: self.core.selections.clone(),
+                });
                 self.core.add_cursor(index);
                 cx.notify();
             } else if event.modifiers.shift {
                 // Extend last selection
                 self.select_to(index, cx);
+            } else if event.modifiers.secondary() {
+                // Ctrl/Cmd 点击转到定义
+                self.stop_drag_select();
+                self.set_cursor(index, cx);
+                self.go_to_definition_at(index, cx);
             } else {
                 // Reset to single cursor
                 self.set_cursor(index, cx);
@@ -2421,6 +5378,135 @@ impl CodeEditor {
         self.dragging_scrollbar = false;
         self.drag_start_y = None;
         self.scroll_start_y = None;
+        self.dragging_h_scrollbar = false;
+        self.drag_start_x = None;
+        self.scroll_start_x = None;
+        self.completion_thumb_drag = None;
+        self.stop_drag_select();
+    }
+
+    /// 补全菜单上的点击：点击项确认补全，按下滚动条滑块开始拖动，返回 true；
+    /// 点在菜单外时关闭菜单并返回 false，交给编辑区继续处理
+    fn completion_mouse_down(&mut self, position: Point<Pixels>, cx: &mut Context<Self>) -> bool {
+        let Some(menu) = self.completion_menu.filter(|_| self.completion_active) else {
+            return false;
+        };
+        if !menu.bounds.contains(&position) {
+            self.completion_active = false;
+            self.completion_hover = None;
+            cx.notify();
+            return false;
+        }
+        if menu.on_thumb(position) {
+            self.completion_thumb_drag = Some((position.y, menu.scroll));
+        } else if let Some(index) = menu.item_at(position) {
+            self.completion_index = index;
+            self.confirm_completion(cx);
+        }
+        true
+    }
+
+    /// 拖动补全菜单的滚动条或更新悬停的补全项；正在拖动时返回 true
+    fn completion_mouse_move(&mut self, event: &MouseMoveEvent, cx: &mut Context<Self>) -> bool {
+        if let Some((start_y, start_scroll)) = self.completion_thumb_drag {
+            if event.pressed_button != Some(MouseButton::Left) {
+                self.completion_thumb_drag = None;
+                return false;
+            }
+            if let Some(menu) = self.completion_menu {
+                self.completion_scroll_offset = menu.scroll_for_drag(start_scroll, event.position.y - start_y) as f32;
+                cx.notify();
+            }
+            return true;
+        }
+        let hover = self
+            .completion_menu
+            .filter(|_| self.completion_active)
+            .and_then(|menu| menu.item_at(event.position));
+        if hover != self.completion_hover {
+            self.completion_hover = hover;
+            cx.notify();
+        }
+        false
+    }
+
+    fn stop_drag_select(&mut self) {
+        self.drag_selecting = false;
+        self.drag_pointer = None;
+        self.column_drag = None;
+    }
+
+    /// 拖选期间窗口级的鼠标移动。指针移出编辑区上下边缘时启动自动滚动。
+    fn on_drag_select_move(&mut self, position: Point<Pixels>, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.drag_selecting {
+            return;
+        }
+        self.drag_pointer = Some(position);
+        let Some(bounds) = self.layout.last_bounds else {
+            return;
+        };
+        if position.y < bounds.top() || position.y > bounds.bottom() {
+            self.ensure_drag_autoscroll(window, cx);
+        }
+    }
+
+    fn ensure_drag_autoscroll(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.drag_autoscroll_active {
+            return;
+        }
+        self.drag_autoscroll_active = true;
+        let window_handle = window.window_handle();
+        cx.spawn(move |editor: WeakEntity<CodeEditor>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    cx.background_executor()
+                        .timer(Duration::from_millis(16))
+                        .await;
+                    let keep_running = cx
+                        .update_window(window_handle, |_, window, cx| {
+                            editor
+                                .update(cx, |editor, cx| editor.tick_drag_autoscroll(window, cx))
+                                .unwrap_or(false)
+                        })
+                        .unwrap_or(false);
+                    if !keep_running {
+                        break;
+                    }
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn tick_drag_autoscroll(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let (Some(bounds), Some(pointer)) = (self.layout.last_bounds, self.drag_pointer) else {
+            self.drag_autoscroll_active = false;
+            return false;
+        };
+        let overshoot = if pointer.y < bounds.top() {
+            pointer.y - bounds.top()
+        } else if pointer.y > bounds.bottom() {
+            pointer.y - bounds.bottom()
+        } else {
+            px(0.0)
+        };
+        if !self.drag_selecting || overshoot == px(0.0) {
+            self.drag_autoscroll_active = false;
+            return false;
+        }
+
+        // 离边缘越远滚动越快，单帧最多三行
+        let line_height = self.layout.line_height();
+        let step = (overshoot * 0.25).clamp(-line_height * 3.0, line_height * 3.0);
+        let content_height = self.layout.content_height(self.core.content.len_lines().max(1));
+        let max_scroll_y = (content_height - bounds.size.height + line_height).max(px(0.0));
+        self.layout.scroll_offset.y = (self.layout.scroll_offset.y - step).clamp(-max_scroll_y, px(0.0));
+
+        let clamped = point(pointer.x, pointer.y.clamp(bounds.top(), bounds.bottom() - px(1.0)));
+        self.drag_select_to(clamped, window, cx);
+        cx.notify();
+        true
     }
 
     fn on_mouse_move(
@@ -2429,6 +5515,9 @@ impl CodeEditor {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
+        if self.completion_mouse_move(event, cx) {
+            return;
+        }
         if self.dragging_scrollbar {
             if event.pressed_button != Some(MouseButton::Left) {
                 self.dragging_scrollbar = false;
@@ -2461,13 +5550,32 @@ impl CodeEditor {
             return;
         }
 
+        if self.dragging_h_scrollbar {
+            if event.pressed_button != Some(MouseButton::Left) {
+                self.dragging_h_scrollbar = false;
+                return;
+            }
+            if let (Some(start_x), Some(scroll_start), Some(bounds)) =
+                (self.drag_start_x, self.scroll_start_x, self.layout.last_bounds)
+            {
+                let max_digits = self.core.content.len_lines().max(1).to_string().len();
+                self.layout.scroll_offset.x =
+                    self.layout.h_scroll_for_drag(bounds, max_digits, scroll_start, event.position.x - start_x);
+                cx.notify();
+            }
+            return;
+        }
+
+        if cx.has_active_drag() {
+            self.suppress_hover(cx);
+            return;
+        }
         if event.pressed_button.is_none() {
             self.update_hover_popup(event.position, window, cx);
             return;
         }
-        if let Some(index) = self.index_for_point(event.position, window) {
-            self.select_to(index, cx);
-        }
+        self.suppress_hover(cx);
+        self.drag_select_to(event.position, window, cx);
     }
 }
 
@@ -2476,7 +5584,7 @@ impl Render for CodeEditor {
         let editor = cx.entity();
         let focus_handle = self.focus_handle.clone();
 
-        div()
+        let mut root = div()
             .size_full()
             .key_context("CodeEditor")
             .track_focus(&focus_handle)
@@ -2486,31 +5594,454 @@ impl Render for CodeEditor {
             .on_mouse_move(cx.listener(Self::on_mouse_move))
             .on_scroll_wheel(cx.listener(Self::on_scroll_wheel))
             .on_modifiers_changed(cx.listener(Self::on_modifiers_changed))
-            .on_action(cx.listener(Self::backspace))
-            .on_action(cx.listener(Self::delete))
-            .on_action(cx.listener(Self::delete_line))
-            .on_action(cx.listener(Self::enter))
-            .on_action(cx.listener(Self::tab))
             .on_action(cx.listener(Self::shift_tab))
             .on_action(cx.listener(Self::move_left))
+            .on_action(cx.listener(Self::add_cursor_above))
+            .on_action(cx.listener(Self::add_cursor_below))
             .on_action(cx.listener(Self::move_right))
+            .on_action(cx.listener(Self::word_left))
+            .on_action(cx.listener(Self::word_right))
+            .on_action(cx.listener(Self::select_word_left))
+            .on_action(cx.listener(Self::select_word_right))
+            .on_action(cx.listener(Self::line_start))
+            .on_action(cx.listener(Self::line_end))
+            .on_action(cx.listener(Self::document_start))
+            .on_action(cx.listener(Self::document_end))
+            .on_action(cx.listener(Self::select_line_start))
+            .on_action(cx.listener(Self::select_line_end))
+            .on_action(cx.listener(Self::select_document_start))
+            .on_action(cx.listener(Self::select_document_end))
             .on_action(cx.listener(Self::move_up))
             .on_action(cx.listener(Self::move_down))
             .on_action(cx.listener(Self::select_all))
             .on_action(cx.listener(Self::copy))
-            .on_action(cx.listener(Self::cut))
-            .on_action(cx.listener(Self::paste))
-            .on_action(cx.listener(Self::undo))
-            .on_action(cx.listener(Self::redo))
             .on_action(cx.listener(Self::toggle_find))
             .on_action(cx.listener(Self::find_next))
             .on_action(cx.listener(Self::find_prev))
             .on_action(cx.listener(Self::cancel_find))
             .on_action(cx.listener(Self::escape))
             .on_action(cx.listener(Self::go_to_definition))
-            .on_action(cx.listener(Self::signature_help))
-            .on_action(cx.listener(Self::format_document))
+            .on_action(cx.listener(Self::peek_definition))
+            .on_action(cx.listener(Self::find_references))
+            .on_action(cx.listener(Self::rename_symbol))
+            .on_action(cx.listener(Self::code_action))
+            .on_action(cx.listener(Self::signature_help));
+
+        // 只读预览时不注册修改内容的动作
+        if !self.is_read_only() {
+            root = root
+                .on_action(cx.listener(Self::backspace))
+                .on_action(cx.listener(Self::delete))
+                .on_action(cx.listener(Self::delete_line))
+                .on_action(cx.listener(Self::enter))
+                .on_action(cx.listener(Self::tab))
+                .on_action(cx.listener(Self::cut))
+                .on_action(cx.listener(Self::paste))
+                .on_action(cx.listener(Self::undo))
+                .on_action(cx.listener(Self::redo))
+                .on_action(cx.listener(Self::format_document))
+                .on_action(cx.listener(Self::toggle_line_comment))
+                .on_action(cx.listener(Self::toggle_block_comment))
+                .on_action(cx.listener(Self::fold_all_blocks))
+                .on_action(cx.listener(Self::unfold_all_blocks))
+                .on_action(cx.listener(Self::fold_level_1))
+                .on_action(cx.listener(Self::convert_to_lf))
+                .on_action(cx.listener(Self::convert_to_crlf));
+        }
+
+        self.sync_peek_block();
+        self.sync_signature_popup();
+        let peek = self.render_peek(cx);
+        let find_bar = self.find.as_ref().map(|find| self.render_find_bar(find, cx));
+        let rename = self.render_rename();
+        let code_actions = self.render_code_actions(cx);
+        let hover = self.render_hover(cx);
+        root.relative()
             .child(code_editor_canvas(editor, focus_handle))
+            .children(peek)
+            .children(find_bar)
+            .children(rename)
+            .children(code_actions)
+            .children(hover)
+    }
+}
+
+impl CodeEditor {
+    /// 悬停提示：在指针下方，放不下时在上方；内容过长时自动换行，超过最大高度后在提示内滚动
+    fn render_hover(&self, cx: &mut Context<Self>) -> Option<Stateful<Div>> {
+        let hover = self.hover_popup.as_ref().filter(|hover| hover.version == self.core.version())?;
+        let bounds = self.layout.last_bounds?;
+        let position = hover.position - bounds.origin;
+        let width = bounds.size.width;
+        let height = bounds.size.height;
+
+        let mut popup = div()
+            .id("hover-popup")
+            .absolute()
+            .left((position.x + px(12.0)).min(width - HOVER_MAX_WIDTH - px(4.0)).max(px(4.0)))
+            .max_w(HOVER_MAX_WIDTH)
+            .max_h(HOVER_MAX_HEIGHT)
+            .overflow_y_scroll()
+            .flex()
+            .flex_col()
+            .gap(px(6.0))
+            .px(px(10.0))
+            .py(px(6.0))
+            .bg(rgba(0x1e1e1ef0))
+            .border_1()
+            .border_color(hover.color.rgba())
+            .text_size(self.layout.font_size.min(px(14.0)))
+            .text_color(rgb(0xffffffff))
+            // 指针移到提示上时保持显示，滚轮只滚动提示
+            .on_mouse_move(cx.listener(|this, _, _window, cx| {
+                this.hover_task = None;
+                cx.stop_propagation();
+            }))
+            .on_scroll_wheel(|_, _window, cx| cx.stop_propagation())
+            .on_mouse_down(MouseButton::Left, |_, _window, cx| cx.stop_propagation());
+        popup = if position.y + px(18.0) + HOVER_MAX_HEIGHT > height && position.y > height / 2.0 {
+            popup.bottom(height - position.y + px(4.0))
+        } else {
+            popup.top(position.y + px(18.0))
+        };
+
+        for (block, colors) in hover.blocks.iter().zip(&hover.code_colors) {
+            popup = popup.child(match block {
+                HoverBlock::Text { text, styles } => {
+                    let highlights = styles.iter().map(|(range, style)| {
+                        let style = match style {
+                            InlineStyle::Bold => HighlightStyle { font_weight: Some(FontWeight::BOLD), ..Default::default() },
+                            InlineStyle::Code => HighlightStyle {
+                                color: Some(rgb(0xffce9178).into()),
+                                background_color: Some(rgba(0xffffff14).into()),
+                                ..Default::default()
+                            },
+                        };
+                        (range.clone(), style)
+                    });
+                    div().child(StyledText::new(text.clone()).with_highlights(highlights))
+                }
+                HoverBlock::Code { code, .. } => {
+                    let highlights = colors
+                        .iter()
+                        .map(|(range, color)| (range.clone(), HighlightStyle { color: Some(*color), ..Default::default() }));
+                    div()
+                        .px(px(6.0))
+                        .py(px(4.0))
+                        .bg(rgb(0xff232a2e))
+                        .text_color(rgb(0xffcccccc))
+                        .child(StyledText::new(code.clone()).with_highlights(highlights))
+                }
+            });
+        }
+        Some(popup)
+    }
+
+    /// 重命名输入框：贴在原名下方，选区和光标的画法与文件树的内联输入一致
+    fn render_rename(&mut self) -> Option<Div> {
+        let start = self.rename.as_ref()?.range.start;
+        let bounds = self.layout.last_bounds?;
+        let position = self.point_for_index(start) - bounds.origin;
+        let line_height = self.layout.line_height();
+        let input = &self.rename.as_ref()?.input;
+        let caret = || div().w(px(1.5)).h(px(14.0)).bg(rgb(0xff007fd4));
+
+        let name = input.text();
+        let cursor = input.cursor();
+        let selection = input.selection().unwrap_or(cursor..cursor);
+        let mut text = div()
+            .flex()
+            .items_center()
+            .child(name[..selection.start].to_string());
+        if cursor == selection.start {
+            text = text.child(caret());
+        }
+        if !selection.is_empty() {
+            text = text.child(div().bg(rgb(0xff264f78)).child(name[selection.clone()].to_string()));
+        }
+        if cursor != selection.start {
+            text = text.child(caret());
+        }
+        text = text.child(name[selection.end..].to_string());
+
+        Some(
+            div()
+                .absolute()
+                .left(position.x.max(px(0.0)))
+                .top(position.y + line_height)
+                .min_w(px(160.0))
+                .h(px(24.0))
+                .px(px(6.0))
+                .flex()
+                .items_center()
+                .whitespace_nowrap()
+                .bg(rgb(0xff1e1e1e))
+                .border_1()
+                .border_color(rgb(0xff007fd4))
+                .text_size(px(13.0))
+                .text_color(rgb(0xffe6e0d9))
+                .on_mouse_down(MouseButton::Left, |_, _window, cx| cx.stop_propagation())
+                .child(text),
+        )
+    }
+
+    /// 代码操作列表：贴在光标所在行下方，选中项高亮，点击即应用
+    fn render_code_actions(&mut self, cx: &mut Context<Self>) -> Option<Div> {
+        // 打开后有编辑时不再显示
+        let anchor = self.code_actions.as_ref().filter(|menu| menu.version == self.core.version())?.anchor;
+        let bounds = self.layout.last_bounds?;
+        let position = self.point_for_index(anchor) - bounds.origin;
+        let line_height = self.layout.line_height();
+        let menu = self.code_actions.as_ref()?;
+        let items = menu.actions.iter().enumerate().map(|(index, action)| {
+            let background = if index == menu.selected { rgb(0xff04395e) } else { rgb(0xff252526) };
+            div()
+                .h(px(22.0))
+                .px(px(8.0))
+                .flex()
+                .items_center()
+                .bg(background)
+                .hover(|style| style.bg(rgb(0xff2a2d2e)))
+                .child(action.title.clone())
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _window, cx| {
+                        cx.stop_propagation();
+                        if let Some(menu) = this.code_actions.as_mut() {
+                            menu.selected = index;
+                        }
+                        this.apply_code_action(cx);
+                    }),
+                )
+        });
+        Some(
+            div()
+                .absolute()
+                .left(position.x.max(px(0.0)))
+                .top(position.y + line_height)
+                .min_w(px(200.0))
+                .py(px(2.0))
+                .flex()
+                .flex_col()
+                .whitespace_nowrap()
+                .bg(rgb(0xff252526))
+                .border_1()
+                .border_color(rgb(0xff454545))
+                .text_size(px(13.0))
+                .text_color(rgb(0xffcccccc))
+                .children(items),
+        )
+    }
+
+    /// 查看定义的插入块：标题栏（点击路径跳转）、嵌入编辑器，多个结果时右侧为结果列表
+    fn render_peek(&self, cx: &mut Context<Self>) -> Option<Div> {
+        let peek = self.peek.as_ref()?;
+        let peek_editor = self.peek_editor.clone()?;
+        let location = peek.current()?.clone();
+        let top = self.layout.block_top()? + self.layout.scroll_offset.y;
+        let host = cx.entity();
+        let muted = rgb(0xff8b949e);
+
+        let header = div()
+            .h(px(PEEK_HEADER_HEIGHT))
+            .flex_none()
+            .flex()
+            .items_center()
+            .gap(px(8.0))
+            .px(px(8.0))
+            .bg(rgb(0xff232a2e))
+            .text_size(px(12.0))
+            .child(
+                div()
+                    .cursor_pointer()
+                    .text_color(rgb(0xff7fbbb3))
+                    .hover(|s| s.text_color(rgb(0xffa7c080)))
+                    .child(format!("{}:{}", location.path.display(), location.start.0 + 1))
+                    .on_mouse_down(MouseButton::Left, {
+                        let host = host.clone();
+                        move |_, _window, cx| {
+                            cx.stop_propagation();
+                            host.update(cx, |this, cx| this.open_peek_location(cx));
+                        }
+                    }),
+            )
+            .child(div().flex_1())
+            .child(
+                div()
+                    .cursor_pointer()
+                    .text_color(muted)
+                    .hover(|s| s.text_color(rgb(0xffe6e0d9)))
+                    .child("×")
+                    .on_mouse_down(MouseButton::Left, {
+                        let host = host.clone();
+                        move |_, _window, cx| {
+                            cx.stop_propagation();
+                            host.update(cx, |this, cx| this.close_peek(cx));
+                        }
+                    }),
+            );
+
+        let list = (peek.results.len() > 1).then(|| {
+            div()
+                .w(px(PEEK_LIST_WIDTH))
+                .h_full()
+                .flex_none()
+                .flex()
+                .flex_col()
+                .border_l_1()
+                .border_color(rgb(0xff3c474d))
+                .text_size(px(12.0))
+                .children(peek.results.iter().enumerate().map(|(index, result)| {
+                    let host = host.clone();
+                    div()
+                        .h(px(22.0))
+                        .px(px(8.0))
+                        .flex()
+                        .items_center()
+                        .overflow_hidden()
+                        .whitespace_nowrap()
+                        .cursor_pointer()
+                        .text_color(rgb(0xffd3c6aa))
+                        .bg(if index == peek.selected { rgba(0x2d6cdf40) } else { rgba(0x00000000) })
+                        .hover(|s| s.bg(rgba(0xffffff12)))
+                        .child(location_label(result))
+                        .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
+                            cx.stop_propagation();
+                            host.update(cx, |this, cx| this.show_peek_result(index, cx));
+                        })
+                }))
+        });
+
+        // 块内的鼠标和滚动只作用于嵌入编辑器；只读编辑器不处理的修改动作不能落到宿主上
+        Some(
+            div()
+                .absolute()
+                .top(top)
+                .left(px(0.0))
+                .right(self.layout.scrollbar_width())
+                .h(block_height(self.layout.line_height()))
+                .flex()
+                .flex_col()
+                .bg(rgb(0xff1e2326))
+                .border_y_1()
+                .border_color(rgb(0xff4f585e))
+                .on_mouse_down(MouseButton::Left, |_, _window, cx| cx.stop_propagation())
+                .on_mouse_up(MouseButton::Left, |_, _window, cx| cx.stop_propagation())
+                .on_mouse_move(|_, _window, cx| cx.stop_propagation())
+                .on_scroll_wheel(|_, _window, cx| cx.stop_propagation())
+                .on_action(|_: &Backspace, _window, _cx| {})
+                .on_action(|_: &Delete, _window, _cx| {})
+                .on_action(|_: &DeleteLine, _window, _cx| {})
+                .on_action(|_: &Enter, _window, _cx| {})
+                .on_action(|_: &Tab, _window, _cx| {})
+                .on_action(|_: &Cut, _window, _cx| {})
+                .on_action(|_: &Paste, _window, _cx| {})
+                .on_action(|_: &Undo, _window, _cx| {})
+                .on_action(|_: &Redo, _window, _cx| {})
+                .on_action(|_: &FormatDocument, _window, _cx| {})
+                .on_action(|_: &ToggleLineComment, _window, _cx| {})
+                .on_action(|_: &ToggleBlockComment, _window, _cx| {})
+                .child(header)
+                .child(
+                    div()
+                        .flex_1()
+                        .min_h(px(0.0))
+                        .flex()
+                        .child(div().flex_1().min_w(px(0.0)).h_full().child(peek_editor))
+                        .children(list),
+                ),
+        )
+    }
+
+    fn render_find_bar(&self, find: &FindState, cx: &mut Context<Self>) -> Div {
+        let muted = rgb(0xff8b949e);
+        let caret = || div().w(px(1.5)).h(px(14.0)).bg(rgb(0xff007fd4));
+
+        let mut input = div()
+            .flex_1()
+            .min_w(px(0.0))
+            .h(px(22.0))
+            .px(px(6.0))
+            .flex()
+            .items_center()
+            .overflow_hidden()
+            .whitespace_nowrap()
+            .bg(rgb(0xff1e1e1e))
+            .border_1()
+            .border_color(if find.focused { rgb(0xff007fd4) } else { rgb(0xff3c3c3c) });
+        if find.query.is_empty() {
+            input = input.child(div().text_color(muted).child("查找"));
+        } else {
+            input = input.child(find.query.clone());
+        }
+        if find.focused {
+            input = input.child(caret());
+        }
+
+        let button = |label: &'static str, handler: fn(&mut CodeEditor, &mut Context<CodeEditor>)| {
+            div()
+                .px(px(4.0))
+                .cursor_pointer()
+                .text_color(muted)
+                .hover(|s| s.text_color(rgb(0xffe6e0d9)))
+                .child(label)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _window, cx| {
+                        cx.stop_propagation();
+                        handler(this, cx);
+                    }),
+                )
+        };
+
+        let mut bar = div()
+            .absolute()
+            .top(px(4.0))
+            .right(px(16.0))
+            .w(px(320.0))
+            .p(px(4.0))
+            .flex()
+            .flex_col()
+            .gap(px(2.0))
+            .bg(rgb(0xff252526))
+            .border_1()
+            .border_color(rgb(0xff3c3c3c))
+            .rounded(px(4.0))
+            .text_size(px(13.0))
+            .text_color(rgb(0xffcccccc))
+            .cursor(CursorStyle::Arrow)
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| {
+                    cx.stop_propagation();
+                    this.focus_handle.focus(window);
+                    if let Some(find) = this.find.as_mut() {
+                        find.focused = true;
+                    }
+                    cx.notify();
+                }),
+            )
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap(px(4.0))
+                    .child(input)
+                    .child(div().min_w(px(56.0)).text_color(muted).child(find.label()))
+                    .child(button("↑", |this, cx| this.step_find(false, cx)))
+                    .child(button("↓", |this, cx| this.step_find(true, cx)))
+                    .child(button("×", |this, cx| this.close_find(cx))),
+            );
+        if find.capped {
+            bar = bar.child(
+                div()
+                    .px(px(2.0))
+                    .text_size(px(12.0))
+                    .text_color(rgb(0xffe2c08d))
+                    .child("匹配过多，只标记了一部分，请输入更具体的内容"),
+            );
+        }
+        bar
     }
 }
 
@@ -2518,8 +6049,12 @@ pub fn code_editor_canvas(
     editor: Entity<CodeEditor>,
     focus_handle: FocusHandle,
 ) -> impl IntoElement {
+    let editor_for_prepaint = editor.clone();
     canvas(
-        |bounds, _window, _cx| bounds,
+        move |bounds, window, cx| {
+            editor_for_prepaint.update(cx, |editor, _cx| editor.reveal_cursor_x(bounds, window));
+            bounds
+        },
         move |bounds, _layout, window, cx| {
             window.handle_input(
                 &focus_handle,
@@ -2528,38 +6063,70 @@ pub fn code_editor_canvas(
             );
             editor.update(cx, |editor, _cx| {
                 editor.layout.last_bounds = Some(bounds);
+                // 绘制补全菜单时重新记下
+                editor.completion_menu = None;
+                editor.refresh_decorations();
+                editor.refresh_folds();
             });
 
+            // 拖选时指针可能离开编辑区，元素自身的鼠标事件收不到，改在窗口级别监听
+            if editor.read(cx).drag_selecting {
+                let editor_for_move = editor.clone();
+                window.on_mouse_event(move |event: &MouseMoveEvent, phase, window, cx| {
+                    if phase == DispatchPhase::Bubble {
+                        editor_for_move.update(cx, |editor, cx| {
+                            editor.on_drag_select_move(event.position, window, cx);
+                        });
+                    }
+                });
+                let editor_for_up = editor.clone();
+                window.on_mouse_event(move |event: &MouseUpEvent, phase, _window, cx| {
+                    if phase == DispatchPhase::Bubble && event.button == MouseButton::Left {
+                        editor_for_up.update(cx, |editor, _cx| editor.stop_drag_select());
+                    }
+                });
+            }
+
             let (
-                layout,
+                mut layout,
                 content,
                 selections,
                 completion_active,
                 completion_items,
                 completion_index,
+                completion_hover,
                 decorations,
-                hover_popup,
+                signature_popup,
                 git_diff_map,
+                diff_against_saved,
                 block_map,
                 block_highlight,
                 indent_guides,
+                find,
             ) = {
                 let state = editor.read(cx);
                 (
-                    state.layout,
+                    state.layout.clone(),
                     state.core.content.clone(),
                     state.core.selections.clone(),
-                    state.core.completion_active,
-                    state.core.completion_items.clone(),
-                    state.core.completion_index,
+                    state.completion_active,
+                    state.completion_items.clone(),
+                    state.completion_index,
+                    state.completion_hover,
                     state.decorations.clone(),
-                    state.hover_popup.clone(),
+                    state.signature_popup.clone(),
                     state.git_diff_map.clone(),
+                    state.diff_against_saved(),
                     state.block_map.clone(),
                     state.block_highlight.clone(),
                     state.indent_guides.clone(),
+                    state.find.clone(),
                 )
             };
+            let dim_editor = find.as_ref().is_some_and(|find| find.dims_editor());
+            let find_matches = find.map(|find| (find.matches, find.current)).unwrap_or_default();
+            // 编辑区变暗时匹配画在变暗层之上，否则像选区一样画在文字之下
+            let mut dimmed_match_quads = Vec::new();
 
             let font_size = layout.font_size;
             let line_height = layout.line_height();
@@ -2584,9 +6151,19 @@ pub fn code_editor_canvas(
                 let start_line = layout.line_index_for_y(bounds, bounds.top());
                 let end_line =
                     (layout.line_index_for_y(bounds, bounds.bottom()) + 1).min(line_count);
+                let hidden = layout.hidden.clone();
+                // 可见的折叠首行及其是否折起
+                let fold_heads: HashMap<usize, bool> = {
+                    let folds = &editor.read(cx).folds;
+                    hidden
+                        .visible_in(start_line..end_line)
+                        .filter(|&i| folds.region_at(i).is_some())
+                        .map(|i| (i, folds.is_folded(i)))
+                        .collect()
+                };
 
                 // 1. Draw Global Backgrounds (Current Line Highlight and Git Diff Backgrounds)
-                for i in start_line..end_line {
+                for i in hidden.visible_in(start_line..end_line) {
                     let y = layout.line_y(bounds, i);
                     
                     // Current Line Highlight
@@ -2600,10 +6177,10 @@ pub fn code_editor_canvas(
 
                     // Git Diff Background Highlight
                     if let Some(status) = git_diff_map.get(&i) {
+                         // ~20% opacity; do not highlight background for deletions (since text is gone)
                          let bg_color = match status {
-                             GitDiffStatus::Added => Some(rgba(0x2ea04333)), // Green with ~20% opacity
-                             GitDiffStatus::Modified => Some(rgba(0x005cc533)), // Blue with ~20% opacity
-                             GitDiffStatus::Deleted => None, // Do not highlight background for deletions (since text is gone)
+                             GitDiffStatus::Deleted => None,
+                             _ => Some(rgba(diff_color(*status, diff_against_saved) << 8 | 0x33)),
                          };
                          
                          if let Some(color) = bg_color {
@@ -2617,15 +6194,11 @@ pub fn code_editor_canvas(
                 }
 
                 // 2. Draw Gutter
-                for i in start_line..end_line {
+                for i in hidden.visible_in(start_line..end_line) {
                     let y = layout.line_y(bounds, i);
 
                     if let Some(status) = git_diff_map.get(&i) {
-                         let color = match status {
-                             GitDiffStatus::Added => rgb(0x2ea043),
-                             GitDiffStatus::Modified => rgb(0x005cc5),
-                             GitDiffStatus::Deleted => rgb(0xd73a49),
-                         };
+                         let color = rgb(diff_color(*status, diff_against_saved));
                          let indicator_bounds = Bounds::from_corners(
                              point(bounds.left() + px(2.0), y),
                              point(bounds.left() + px(6.0), y + line_height),
@@ -2642,19 +6215,34 @@ pub fn code_editor_canvas(
                     );
 
                     let number_width = number_line.width;
-                    let number_x = bounds.left() + gutter_width - px(8.0) - number_width;
+                    let number_x = bounds.left() + gutter_width - px(8.0) - layout.fold_column_width() - number_width;
 
                     number_line
                         .paint(point(number_x, y), line_height, window, cx)
                         .ok();
+
+                    if let Some(&folded) = fold_heads.get(&i) {
+                        let toggle = CodeEditor::shape_line(
+                            window,
+                            if folded { "▸" } else { "▾" },
+                            rgb(0x8b949e).into(),
+                            font_size * 0.8,
+                        );
+                        let column = layout.fold_column(bounds, max_digits);
+                        let toggle_x = column.start + (column.end - column.start - toggle.width) / 2.0;
+                        toggle
+                            .paint(point(toggle_x, y + px(1.0)), line_height, window, cx)
+                            .ok();
+                    }
                     
                     // Draw Diff Symbols (+/~)
                     if let Some(status) = git_diff_map.get(&i) {
-                         let (symbol, color) = match status {
-                             GitDiffStatus::Added => (Some("+"), rgb(0x2ea043)),
-                             GitDiffStatus::Modified => (Some("~"), rgb(0x005cc5)),
-                             GitDiffStatus::Deleted => (None, rgb(0xd73a49)),
+                         let symbol = match status {
+                             GitDiffStatus::Added => Some("+"),
+                             GitDiffStatus::Modified => Some("~"),
+                             GitDiffStatus::Deleted => None,
                          };
+                         let color = rgb(diff_color(*status, diff_against_saved));
                          
                          if let Some(sym) = symbol {
                              let symbol_line = CodeEditor::shape_line(
@@ -2678,11 +6266,7 @@ pub fn code_editor_canvas(
                 let last_line_idx = content.len_lines();
                 if end_line == last_line_idx {
                     if let Some(status) = git_diff_map.get(&last_line_idx) {
-                         let color = match status {
-                             GitDiffStatus::Added => rgb(0x2ea043),
-                             GitDiffStatus::Modified => rgb(0x005cc5),
-                             GitDiffStatus::Deleted => rgb(0xd73a49),
-                         };
+                         let color = rgb(diff_color(*status, diff_against_saved));
                          let y = layout.line_y(bounds, last_line_idx);
                          let indicator_bounds = Bounds::from_corners(
                              point(bounds.left() + px(2.0), y),
@@ -2754,7 +6338,7 @@ pub fn code_editor_canvas(
                             (false, 0.0, 0, 0, 0, 0, rgb(0x4ec9b0))
                         };
 
-                        for i in start_line..end_line {
+                        for i in hidden.visible_in(start_line..end_line) {
                             if let Some(depth) = block_map.depths.get(i) {
                                 for level in 1..=*depth {
                                     let x = text_x + indent_width * (level as f32 - 1.0);
@@ -2811,12 +6395,13 @@ pub fn code_editor_canvas(
                 );
 
                 // 3. Draw Text Area
+                let mut line_widths = Vec::with_capacity(end_line.saturating_sub(start_line));
                 window.with_content_mask(
                     Some(ContentMask {
                         bounds: text_area_bounds,
                     }),
                     |window| {
-                        for i in start_line..end_line {
+                        for i in hidden.visible_in(start_line..end_line) {
                             let line_start = content.line_to_byte(i);
                             let line_slice = content.line(i);
                             let mut line_text_string = line_slice.to_string();
@@ -2873,6 +6458,42 @@ pub fn code_editor_canvas(
                                 }
                             }
 
+                            // Draw Find Matches
+                            let line_end = line_start + line_text.len();
+                            let (matches, current) = &find_matches;
+                            let first = matches.partition_point(|m| m.end <= line_start);
+                            let mut match_shape = None;
+                            for (index, m) in matches.iter().enumerate().skip(first) {
+                                if m.start > line_end {
+                                    break;
+                                }
+                                let shape = match_shape.get_or_insert_with(|| {
+                                    editor
+                                        .read(cx)
+                                        .get_cached_shape_line(window, line_text, font_size, i, line_start)
+                                });
+                                let start = CodeEditor::clamp_to_char_boundary(line_text, m.start.max(line_start) - line_start);
+                                let end = CodeEditor::clamp_to_char_boundary(line_text, m.end.min(line_end) - line_start);
+                                let mut end_x = shape.x_for_index(end);
+                                if m.end > line_end {
+                                    end_x += px(6.0);
+                                }
+                                let match_bounds = Bounds::from_corners(
+                                    point(text_x + shape.x_for_index(start), y),
+                                    point(text_x + end_x, y + line_height),
+                                );
+                                let color = if Some(index) == *current {
+                                    rgba(0xf2a23c99)
+                                } else {
+                                    rgba(0xe2c08d4d)
+                                };
+                                if dim_editor {
+                                    dimmed_match_quads.push(fill(match_bounds, color));
+                                } else {
+                                    window.paint_quad(fill(match_bounds, color));
+                                }
+                            }
+
                             // Draw Text
                             let text_line = editor
                                 .read(cx)
@@ -2880,6 +6501,27 @@ pub fn code_editor_canvas(
                             text_line
                                 .paint(point(text_x, y), line_height, window, cx)
                                 .ok();
+                            line_widths.push((i, text_line.width()));
+
+                            // 折起的区域在首行末尾画占位符
+                            if fold_heads.get(&i) == Some(&true) {
+                                let placeholder = CodeEditor::shape_line(
+                                    window,
+                                    "… ⟩",
+                                    rgb(0x8b949e).into(),
+                                    font_size,
+                                );
+                                let placeholder_bounds = Bounds::new(
+                                    point(text_x + text_line.width() + FOLD_PLACEHOLDER_GAP, y + px(2.0)),
+                                    size(placeholder.width + px(8.0), line_height - px(4.0)),
+                                );
+                                let mut placeholder_quad = fill(placeholder_bounds, rgba(0x8b949e33));
+                                placeholder_quad.corner_radii = Corners::all(px(3.0));
+                                window.paint_quad(placeholder_quad);
+                                placeholder
+                                    .paint(point(placeholder_bounds.left() + px(4.0), y), line_height, window, cx)
+                                    .ok();
+                            }
 
                             for d in &decorations {
                                 let line_end_incl_newline = line_start + line_slice.len_bytes();
@@ -2914,6 +6556,19 @@ pub fn code_editor_canvas(
                             }
                         }
 
+                        // 按画出的行排版后的宽度更新水平滚动范围
+                        layout.observe_line_widths(start_line..end_line, &line_widths);
+                        editor.update(cx, |editor, _cx| {
+                            editor.layout.observe_line_widths(start_line..end_line, &line_widths);
+                        });
+
+                        if dim_editor {
+                            window.paint_quad(fill(text_area_bounds, rgba(0x1e1e1e80)));
+                            for quad in dimmed_match_quads.drain(..) {
+                                window.paint_quad(quad);
+                            }
+                        }
+
                         // Draw Cursors
                         for selection in &selections {
                             let head = selection.head;
@@ -2921,7 +6576,7 @@ pub fn code_editor_canvas(
                             let (line, _, line_start) =
                                 CodeEditor::line_col_for_index(&content, head);
 
-                            if line >= start_line && line < end_line {
+                            if line >= start_line && line < end_line && !hidden.is_hidden(line) {
                                 let line_slice = content.line(line);
                                 let mut line_text_string = line_slice.to_string();
                                 if line_text_string.ends_with('\n') {
@@ -2985,16 +6640,22 @@ pub fn code_editor_canvas(
                             let menu_width = px(250.0);
                             
                             // Calculate visible range
-                            let max_visible_items = 10;
                             let total_items = completion_items.len();
-                            let visible_count = total_items.min(max_visible_items);
+                            let visible_count = total_items.min(MAX_VISIBLE_ITEMS);
                             let menu_height = item_height * visible_count as f32;
                             
-                            let scroll_index = editor.read(cx).completion_scroll_offset as usize;
+                            let scroll_index = (editor.read(cx).completion_scroll_offset as usize).min(total_items - visible_count);
                             let visible_items = &completion_items[scroll_index..(scroll_index + visible_count).min(total_items)];
 
                             let menu_bounds =
                                 Bounds::new(point(menu_x, menu_y), size(menu_width, menu_height));
+                            let menu_layout = CompletionMenuLayout {
+                                bounds: menu_bounds,
+                                item_height,
+                                scroll: scroll_index,
+                                total: total_items,
+                            };
+                            editor.update(cx, |editor, _cx| editor.completion_menu = Some(menu_layout));
 
                             // Paint shadow
                             CodeEditor::paint_soft_shadow(window, menu_bounds, px(4.0));
@@ -3017,6 +6678,8 @@ pub fn code_editor_canvas(
 
                                     if global_index == completion_index {
                                         window.paint_quad(fill(item_bounds, rgb(0x04395e)));
+                                    } else if completion_hover == Some(global_index) {
+                                        window.paint_quad(fill(item_bounds, rgb(0x2a2d2e)));
                                     }
 
                                     // Icon
@@ -3073,22 +6736,7 @@ pub fn code_editor_canvas(
                             });
                             
                             // Scrollbar for completion menu
-                            if total_items > max_visible_items {
-                                let scrollbar_width = px(4.0);
-                                let track_bounds = Bounds::new(
-                                    point(menu_bounds.right() - scrollbar_width, menu_bounds.top()),
-                                    size(scrollbar_width, menu_bounds.size.height),
-                                );
-                                
-                                let thumb_height = menu_bounds.size.height * (visible_count as f32 / total_items as f32);
-                                let thumb_y = menu_bounds.top() + (menu_bounds.size.height - thumb_height) * (scroll_index as f32 / (total_items - visible_count) as f32);
-                                
-                                let thumb_bounds = Bounds::new(
-                                    point(menu_bounds.right() - scrollbar_width, thumb_y),
-                                    size(scrollbar_width, thumb_height)
-                                );
-                                
-                                window.paint_quad(fill(track_bounds, rgba(0x00000000)));
+                            if let Some(thumb_bounds) = menu_layout.thumb_bounds() {
                                 window.paint_quad(fill(thumb_bounds, rgba(0x80808080)));
                             }
                         }
@@ -3097,58 +6745,72 @@ pub fn code_editor_canvas(
                         let content_height = layout.content_height(line_count);
                         let thumb_bounds = layout.thumb_bounds(bounds, content_height);
 
+                        let mut marks = ScrollbarMarks::default();
                         if !thumb_bounds.is_empty() {
-                            let scrollbar_width = layout.scrollbar_width();
-                            let track_bounds = Bounds::new(
-                                point(bounds.right() - scrollbar_width, bounds.top()),
-                                size(scrollbar_width, bounds.size.height),
-                            );
-
+                            let track_bounds = layout.track_bounds(bounds);
                             window.paint_quad(fill(track_bounds, rgba(0x00000000)));
 
+                            // 诊断、查找匹配、git 改动和光标行的位置标记，每帧只计算一次
+                            marks = CodeEditor::collect_scrollbar_marks(
+                                &content,
+                                &decorations,
+                                &find_matches.0,
+                                &git_diff_map,
+                                diff_against_saved,
+                                current_line,
+                                &hidden,
+                            );
+                            for (mark_bounds, mark) in marks.layout(track_bounds) {
+                                window.paint_quad(fill(mark_bounds, mark.color));
+                            }
+
                             let mut thumb_quad = fill(thumb_bounds, rgba(0x42424280));
                             thumb_quad.corner_radii = Corners::all(px(4.0));
                             window.paint_quad(thumb_quad);
                         }
+                        editor.update(cx, |editor, _cx| editor.scrollbar_marks = marks);
+
+                        // 水平滚动条，只在有行超出编辑区宽度时显示
+                        let h_thumb_bounds = layout.h_thumb_bounds(bounds, max_digits);
+                        if !h_thumb_bounds.is_empty() {
+                            let mut thumb_quad = fill(h_thumb_bounds, rgba(0x42424280));
+                            thumb_quad.corner_radii = Corners::all(px(4.0));
+                            window.paint_quad(thumb_quad);
+                        }
                     },
                 );
 
-                if let Some(hover) = &hover_popup {
+                // 参数提示画在调用所在行的上方，上方放不下时画在下方
+                if let Some(popup) = &signature_popup {
                     let popup_font = font_size.min(px(14.0));
                     let popup_line_height = popup_font * 1.4;
-                    let text_line =
-                        CodeEditor::shape_line(window, &hover.text, rgb(0xffffffff).into(), popup_font);
+                    let highlights: Vec<(Range<usize>, Hsla)> =
+                        popup.active.clone().map(|range| (range, rgb(0xff4fc1ff).into())).into_iter().collect();
+                    let text_line = CodeEditor::shape_code_line(window, &popup.signature, popup_font, &highlights);
 
                     let padding_x = px(10.0);
-                    let padding_y = px(6.0);
-                    let popup_w = (text_line.width + padding_x * 2.0).max(px(60.0));
+                    let padding_y = px(4.0);
+                    let popup_w = text_line.width + padding_x * 2.0;
                     let popup_h = popup_line_height + padding_y * 2.0;
 
-                    let mut x = hover.position.x + px(12.0);
-                    let mut y = hover.position.y + px(18.0);
-                    x = x
+                    let x = popup
+                        .position
+                        .x
                         .min(bounds.right() - popup_w - px(4.0))
                         .max(bounds.left() + px(4.0));
-                    y = y
-                        .min(bounds.bottom() - popup_h - px(4.0))
-                        .max(bounds.top() + px(4.0));
+                    let mut y = popup.position.y - popup_h - px(2.0);
+                    if y < bounds.top() + px(4.0) {
+                        y = popup.position.y + line_height + px(2.0);
+                    }
 
                     let popup_bounds = Bounds::new(point(x, y), size(popup_w, popup_h));
-                    
-                    // Paint shadow
                     CodeEditor::paint_soft_shadow(window, popup_bounds, px(4.0));
-                    
                     let mut popup_quad = fill(popup_bounds, rgba(0x1e1e1ef0));
                     popup_quad.border_widths = Edges::all(px(1.0));
-                    popup_quad.border_color = hover.color.rgba().into();
+                    popup_quad.border_color = rgb(0xff454545).into();
                     window.paint_quad(popup_quad);
                     text_line
-                        .paint(
-                            point(x + padding_x, y + padding_y),
-                            popup_line_height,
-                            window,
-                            cx,
-                        )
+                        .paint(point(x + padding_x, y + padding_y), popup_line_height, window, cx)
                         .ok();
                 }
             });
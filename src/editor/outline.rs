@@ -0,0 +1,179 @@
+//! 文档大纲：命令面板中输入 `@` 时列出当前文件的类、方法和变量。
+//! 结绳文件取编译器的 source_elements；没有服务时按行匹配函数、类等定义，其它语言也能使用。
+//! 输入 `#` 时用编译器的 workspace_elements 搜索整个项目中的符号
+
+use regex::Regex;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use url::Url;
+
+use crate::lsp::tiec::types::{SourceElement, SourceElementNode, WorkspaceElement};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Class,
+    Method,
+    Variable,
+    Other,
+}
+
+impl SymbolKind {
+    /// 与补全项使用相同的类型编号
+    fn from_tiec(kind: i32) -> Self {
+        match kind {
+            2 | 3 => SymbolKind::Method,
+            6 => SymbolKind::Variable,
+            7 => SymbolKind::Class,
+            _ => SymbolKind::Other,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SymbolKind::Class => "类",
+            SymbolKind::Method => "方法",
+            SymbolKind::Variable => "变量",
+            SymbolKind::Other => "符号",
+        }
+    }
+}
+
+/// 大纲中的一项；行列从 0 开始，列按字符计
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// 项目中的符号及其所在的文件
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceSymbol {
+    pub path: PathBuf,
+    pub symbol: OutlineSymbol,
+}
+
+/// 位置取符号名所在处
+fn symbol_of(element: &SourceElement) -> OutlineSymbol {
+    OutlineSymbol {
+        name: element.name.clone(),
+        kind: SymbolKind::from_tiec(element.kind),
+        line: element.identifier_range.start.line,
+        column: element.identifier_range.start.column,
+    }
+}
+
+/// 按文档顺序展开编译器返回的符号树
+pub fn flatten_elements(nodes: &[SourceElementNode]) -> Vec<OutlineSymbol> {
+    let mut symbols = Vec::new();
+    let mut stack: Vec<&SourceElementNode> = nodes.iter().rev().collect();
+    while let Some(node) = stack.pop() {
+        symbols.push(symbol_of(&node.element));
+        stack.extend(node.children.iter().rev());
+    }
+    symbols
+}
+
+/// 编译器在整个项目中搜索到的符号，跳过不是本地文件的项
+pub fn workspace_symbols(elements: &[WorkspaceElement]) -> Vec<WorkspaceSymbol> {
+    elements
+        .iter()
+        .filter_map(|element| {
+            let path = Url::parse(&element.uri).ok()?.to_file_path().ok()?;
+            Some(WorkspaceSymbol { path, symbol: symbol_of(&element.element) })
+        })
+        .collect()
+}
+
+fn definition_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"^\s*(?:(?:pub(?:\([^)]*\))?|export|default|async|unsafe|const|static|public|private|protected)\s+)*(fn|def|function|class|struct|enum|trait|interface|impl|mod)(?:<[^>]*>)?\s+([\p{L}_][\p{L}\p{N}_]*)",
+        )
+        .unwrap()
+    })
+}
+
+/// 没有编译器服务时的大纲：逐行匹配 Rust、Python、JavaScript 等语言中函数和类的定义
+pub fn fallback_outline(text: &str) -> Vec<OutlineSymbol> {
+    let pattern = definition_pattern();
+    text.lines()
+        .enumerate()
+        .filter_map(|(line, line_text)| {
+            let captures = pattern.captures(line_text)?;
+            let name = captures.get(2)?;
+            let kind = match &captures[1] {
+                "fn" | "def" | "function" => SymbolKind::Method,
+                _ => SymbolKind::Class,
+            };
+            Some(OutlineSymbol {
+                name: name.as_str().to_string(),
+                kind,
+                line,
+                column: line_text[..name.start()].chars().count(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp::tiec::types::{Position, Range, SourceElement};
+
+    fn node(name: &str, kind: i32, line: usize, children: Vec<SourceElementNode>) -> SourceElementNode {
+        let range = Range { start: Position { line, column: 2 }, end: Position { line, column: 6 } };
+        SourceElementNode {
+            element: SourceElement {
+                kind,
+                tags: Vec::new(),
+                name: name.to_string(),
+                detail: None,
+                range: range.clone(),
+                identifier_range: range,
+            },
+            children,
+        }
+    }
+
+    #[test]
+    fn test_outline_from_elements_and_fallback() {
+        let tree = vec![
+            node("启动窗口", 7, 0, vec![node("标题", 6, 2, Vec::new()), node("按钮1_被单击", 2, 4, Vec::new())]),
+            node("工具类", 7, 10, Vec::new()),
+        ];
+        let names: Vec<(String, SymbolKind, usize)> =
+            flatten_elements(&tree).into_iter().map(|s| (s.name, s.kind, s.line)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("启动窗口".to_string(), SymbolKind::Class, 0),
+                ("标题".to_string(), SymbolKind::Variable, 2),
+                ("按钮1_被单击".to_string(), SymbolKind::Method, 4),
+                ("工具类".to_string(), SymbolKind::Class, 10),
+            ]
+        );
+
+        let rust = "pub(crate) struct Editor {\n}\nimpl<T> Buffer<T> {\n    pub async fn 保存(&self) {}\n    let x = fn_call();\n}\n";
+        let symbols = fallback_outline(rust);
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols[0], OutlineSymbol { name: "Editor".to_string(), kind: SymbolKind::Class, line: 0, column: 18 });
+        assert_eq!(symbols[1].name, "Buffer");
+        assert_eq!(symbols[2], OutlineSymbol { name: "保存".to_string(), kind: SymbolKind::Method, line: 3, column: 17 });
+
+        let workspace = vec![
+            WorkspaceElement { uri: "file:///p/源代码/工具类.t".to_string(), element: node("工具类", 7, 3, Vec::new()).element },
+            WorkspaceElement { uri: "lib://基本库/文本.t".to_string(), element: node("文本", 7, 0, Vec::new()).element },
+        ];
+        let symbols = workspace_symbols(&workspace);
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].path, PathBuf::from("/p/源代码/工具类.t"));
+        assert_eq!((symbols[0].symbol.line, symbols[0].symbol.column), (3, 2));
+
+        let python = "class Foo:\n    def bar(self):\n        pass\n";
+        let names: Vec<String> = fallback_outline(python).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["Foo", "bar"]);
+    }
+}
@@ -0,0 +1,180 @@
+//! 按文件类型覆盖编辑器默认值，来自项目设置的 `editorOverrides` 一节
+//!
+//! ```json
+//! { "editorOverrides": { "*.log": { "readOnly": true }, "docs/*.t": { "fontSize": 16 } } }
+//! ```
+//!
+//! 不含 `/` 的模式匹配文件名，否则匹配相对项目根目录的路径；`*` 匹配任意字符，`?` 匹配单个字符。
+//! 多条规则都匹配时逐项合并，模式越长越优先
+
+use log::warn;
+use serde_json::Value;
+use std::path::Path;
+
+use crate::lsp::tiec::settings::PROJECT_SETTINGS_FILE;
+
+/// `editorOverrides` 中支持的键
+pub const SUPPORTED_KEYS: &[&str] = &["readOnly", "fontSize"];
+
+/// 一个缓冲区生效的覆盖项，None 表示使用编辑器默认值
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EditorOverrides {
+    pub read_only: Option<bool>,
+    pub font_size: Option<f32>,
+}
+
+impl EditorOverrides {
+    fn merge(&mut self, other: &EditorOverrides) {
+        self.read_only = other.read_only.or(self.read_only);
+        self.font_size = other.font_size.or(self.font_size);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct OverrideRules {
+    rules: Vec<(String, EditorOverrides)>,
+}
+
+impl OverrideRules {
+    /// 读取项目设置中的规则；文件缺失或无法解析时没有任何覆盖
+    pub fn load(project_root: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(project_root.join(PROJECT_SETTINGS_FILE)) else {
+            return Self::default();
+        };
+        let (rules, warnings) = Self::parse(&content);
+        for warning in warnings {
+            warn!("{}", warning);
+        }
+        rules
+    }
+
+    /// 解析设置文件内容，同时返回面向用户的警告（未知的键、类型不对的值）
+    pub fn parse(content: &str) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let section = match serde_json::from_str::<Value>(content) {
+            Ok(mut value) => value.get_mut("editorOverrides").map(Value::take),
+            // 设置文件整体的语法错误由 tiec 一节报告
+            Err(_) => None,
+        };
+        let Some(section) = section else {
+            return (Self::default(), warnings);
+        };
+        let Value::Object(section) = section else {
+            warnings.push("editorOverrides 应为对象".to_string());
+            return (Self::default(), warnings);
+        };
+
+        let mut rules = Vec::new();
+        for (pattern, keys) in section {
+            let Value::Object(keys) = keys else {
+                warnings.push(format!("editorOverrides 中 {} 的值应为对象", pattern));
+                continue;
+            };
+            let mut overrides = EditorOverrides::default();
+            for (key, value) in keys {
+                match key.as_str() {
+                    "readOnly" => match value.as_bool() {
+                        Some(v) => overrides.read_only = Some(v),
+                        None => warnings.push(format!("{} 的 readOnly 应为 true 或 false", pattern)),
+                    },
+                    "fontSize" => match value.as_f64().filter(|v| *v > 0.0) {
+                        Some(v) => overrides.font_size = Some(v as f32),
+                        None => warnings.push(format!("{} 的 fontSize 应为正数", pattern)),
+                    },
+                    _ => warnings.push(format!(
+                        "{} 中不支持的键 {}（支持：{}）",
+                        pattern,
+                        key,
+                        SUPPORTED_KEYS.join("、")
+                    )),
+                }
+            }
+            rules.push((pattern, overrides));
+        }
+        // JSON 对象不保证键的顺序，按模式长度排序让更具体的规则后应用
+        rules.sort_by_key(|(pattern, _)| pattern.len());
+        (Self { rules }, warnings)
+    }
+
+    pub fn for_path(&self, path: &Path, project_root: &Path) -> EditorOverrides {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let relative = path
+            .strip_prefix(project_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let mut result = EditorOverrides::default();
+        for (pattern, overrides) in &self.rules {
+            let subject = if pattern.contains('/') { &relative } else { &file_name };
+            if glob_match(pattern, subject) {
+                result.merge(overrides);
+            }
+        }
+        result
+    }
+}
+
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 `*` 的位置，以及它当前吞到的文本位置
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_files_get_overrides_and_other_files_do_not() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join(".tiecode")).unwrap();
+        std::fs::write(
+            root.path().join(PROJECT_SETTINGS_FILE),
+            r#"{ "editorOverrides": { "*.log": { "readOnly": true, "fontSize": 12 }, "logs/*": { "fontSize": 11 } } }"#,
+        )
+        .unwrap();
+        let rules = OverrideRules::load(root.path());
+
+        let log = rules.for_path(&root.path().join("build.log"), root.path());
+        assert_eq!(log, EditorOverrides { read_only: Some(true), font_size: Some(12.0) });
+        // 更长的模式逐项覆盖较短的
+        let nested = rules.for_path(&root.path().join("logs/run.log"), root.path());
+        assert_eq!(nested, EditorOverrides { read_only: Some(true), font_size: Some(11.0) });
+        let source = rules.for_path(&root.path().join("src/main.rs"), root.path());
+        assert_eq!(source, EditorOverrides::default());
+    }
+
+    #[test]
+    fn test_unknown_keys_and_bad_values_are_reported() {
+        let (rules, warnings) = OverrideRules::parse(
+            r#"{ "editorOverrides": { "*.log": { "wordWrap": true, "readOnly": "yes" } } }"#,
+        );
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.iter().any(|w| w.contains("wordWrap")));
+        assert!(warnings.iter().any(|w| w.contains("readOnly")));
+        assert_eq!(rules.for_path(Path::new("/p/a.log"), Path::new("/p")), EditorOverrides::default());
+        assert!(glob_match("*.min.*", "app.min.json"));
+        assert!(!glob_match("*.log", "log.txt"));
+    }
+}
@@ -0,0 +1,88 @@
+//! 查看定义：在当前行下方插入只读的小编辑器显示定义所在文件，不离开当前文件。
+//! 插入块占据文档空间，其下的行随之下移；文档被编辑时锚点随之平移
+
+use gpui::{px, Pixels};
+use ropey::Rope;
+
+use super::lsp_integration::DefinitionLocation;
+
+/// 嵌入编辑器可见的行数
+pub const PEEK_VISIBLE_LINES: usize = 12;
+/// 定义上方保留的上下文行数
+pub const PEEK_CONTEXT_LINES: usize = 3;
+pub const PEEK_HEADER_HEIGHT: f32 = 26.0;
+/// 有多个结果时右侧列表的宽度
+pub const PEEK_LIST_WIDTH: f32 = 220.0;
+
+/// 行列（列按字符计）对应的字节偏移，超出范围时限制在文档内
+pub fn index_for_char_position(content: &Rope, line: usize, column: usize) -> usize {
+    let line = line.min(content.len_lines().saturating_sub(1));
+    let line_start = content.line_to_char(line);
+    let line_len = content.line(line).len_chars();
+    content.char_to_byte(line_start + column.min(line_len))
+}
+
+/// 插入块的高度：标题栏加上可见行
+pub fn block_height(line_height: Pixels) -> Pixels {
+    px(PEEK_HEADER_HEIGHT) + line_height * PEEK_VISIBLE_LINES as f32 + px(2.0)
+}
+
+/// 结果列表和标题中显示的位置：文件名和从 1 开始的行号
+pub fn location_label(location: &DefinitionLocation) -> String {
+    let name = location
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| location.path.display().to_string());
+    format!("{}:{}", name, location.start.0 + 1)
+}
+
+pub struct PeekState {
+    /// 锚点所在行行首的字节偏移，及其对应的文档版本
+    pub anchor: usize,
+    pub anchor_version: u64,
+    pub results: Vec<DefinitionLocation>,
+    pub selected: usize,
+}
+
+impl PeekState {
+    pub fn new(anchor: usize, anchor_version: u64, results: Vec<DefinitionLocation>) -> Self {
+        Self { anchor, anchor_version, results, selected: 0 }
+    }
+
+    pub fn current(&self) -> Option<&DefinitionLocation> {
+        self.results.get(self.selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::layout::{EditorLayout, LayoutBlock};
+    use gpui::{point, size, Bounds};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_block_shifts_lines_below_anchor() {
+        let content = Rope::from("甲乙\nab\n");
+        assert_eq!(index_for_char_position(&content, 0, 1), 3);
+        assert_eq!(index_for_char_position(&content, 1, 9), 10);
+        assert_eq!(index_for_char_position(&content, 9, 0), content.len_bytes());
+
+        let location = DefinitionLocation { path: PathBuf::from("/p/源代码/主窗口.t"), start: (41, 4), end: (41, 8) };
+        assert_eq!(location_label(&location), "主窗口.t:42");
+
+        let mut layout = EditorLayout::new();
+        let bounds = Bounds::new(point(px(0.0), px(0.0)), size(px(400.0), px(400.0)));
+        let line_height = layout.line_height();
+        let height = block_height(line_height);
+        layout.block = Some(LayoutBlock { after_line: 2, height });
+        assert_eq!(layout.line_y(bounds, 2), line_height * 2.0);
+        assert_eq!(layout.line_y(bounds, 3), line_height * 3.0 + height);
+        assert_eq!(layout.block_top(), Some(line_height * 3.0));
+        // 块内的位置算作锚点行，块下方的位置扣除块的高度
+        assert_eq!(layout.line_index_for_y(bounds, line_height * 3.0 + px(5.0)), 2);
+        assert_eq!(layout.line_index_for_y(bounds, line_height * 4.5 + height), 4);
+        assert_eq!(layout.content_height(10), line_height * 10.0 + height);
+    }
+}
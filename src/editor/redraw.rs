@@ -0,0 +1,72 @@
+//! 合并一次用户操作中的重绘请求。插入文本会依次更新高亮、补全、git 差异等，
+//! 它们各自请求重绘；在批次内只记下请求，批次结束时统一 notify 一次
+
+#[derive(Debug, Default)]
+pub struct RedrawBatch {
+    depth: u32,
+    pending: bool,
+    /// 实际发出的 notify 次数，用于检查每次按键最多重绘一次
+    notify_count: u64,
+}
+
+impl RedrawBatch {
+    pub fn begin(&mut self) {
+        self.depth += 1;
+    }
+
+    /// 请求重绘；返回 true 表示调用方应立即 notify（不在批次内）
+    pub fn request(&mut self) -> bool {
+        if self.depth > 0 {
+            self.pending = true;
+            false
+        } else {
+            self.notify_count += 1;
+            true
+        }
+    }
+
+    /// 结束批次；最外层批次期间有过请求时返回 true，调用方 notify 一次
+    pub fn end(&mut self) -> bool {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 && std::mem::take(&mut self.pending) {
+            self.notify_count += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn notify_count(&self) -> u64 {
+        self.notify_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_batches_notify_once() {
+        let mut batch = RedrawBatch::default();
+        batch.begin();
+        assert!(!batch.request());
+        batch.begin();
+        assert!(!batch.request());
+        // 内层批次结束时还不重绘
+        assert!(!batch.end());
+        assert!(!batch.request());
+        assert!(batch.end());
+        assert_eq!(batch.notify_count(), 1);
+
+        // 没有任何请求的批次不重绘
+        batch.begin();
+        assert!(!batch.end());
+        assert_eq!(batch.notify_count(), 1);
+
+        // 批次外的请求直接重绘；多余的 end 不会让之后的请求被吞掉
+        assert!(batch.request());
+        assert!(!batch.end());
+        assert!(batch.request());
+        assert_eq!(batch.notify_count(), 3);
+    }
+}
@@ -0,0 +1,86 @@
+//! 排版缓存的键：记文档代数、行号和行文本的哈希，不把行文本复制进键里
+
+use ropey::Rope;
+use std::hash::{DefaultHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// 新的文档代数；载入另一个文档或整篇重新载入时更换，之前缓存的排版不会再被命中
+pub fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LineKey {
+    generation: u64,
+    line: usize,
+    text_hash: u64,
+}
+
+impl LineKey {
+    /// 已经取出的一行文本（不含换行符）的键
+    pub fn for_text(generation: u64, line: usize, text: &str) -> Self {
+        Self { generation, line, text_hash: hash_chunks([text]) }
+    }
+
+    /// 直接读取文档中的一行，不分配字符串；同时返回这一行不含换行符的字节数
+    pub fn for_line(generation: u64, content: &Rope, line: usize) -> (Self, usize) {
+        let start = content.line_to_byte(line);
+        let len = line_text_len(content, line);
+        let text_hash = hash_chunks(content.byte_slice(start..start + len).chunks());
+        (Self { generation, line, text_hash }, len)
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+/// 第 `line` 行不含换行符（`\n` 或 `\r\n`）的字节数
+pub fn line_text_len(content: &Rope, line: usize) -> usize {
+    let slice = content.line(line);
+    let mut len = slice.len_bytes();
+    if len > 0 && slice.byte(len - 1) == b'\n' {
+        len -= 1;
+        if len > 0 && slice.byte(len - 1) == b'\r' {
+            len -= 1;
+        }
+    }
+    len
+}
+
+/// 按字节逐段写入，分段方式不同的同一段文本得到相同的哈希
+fn hash_chunks<'a>(chunks: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for chunk in chunks {
+        hasher.write(chunk.as_bytes());
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_keys_match_without_copying_lines() {
+        // 足够长的行会跨越 Rope 的多个分块
+        let long = "变量 甲 = 1 ".repeat(400);
+        let content = Rope::from(format!("首行\r\n{}\n\n末行", long));
+        assert!(content.line(1).chunks().count() > 1);
+
+        let generation = next_generation();
+        let (key, len) = LineKey::for_line(generation, &content, 1);
+        assert_eq!(len, long.len());
+        assert_eq!(key, LineKey::for_text(generation, 1, &long));
+        assert_eq!(LineKey::for_line(generation, &content, 0), (LineKey::for_text(generation, 0, "首行"), 6));
+        assert_eq!(LineKey::for_line(generation, &content, 2).1, 0);
+        assert_eq!(LineKey::for_line(generation, &content, 3).1, 6);
+
+        // 同一行号、同样的文本，换了文档代数或行号就不再命中
+        assert_ne!(key, LineKey::for_text(next_generation(), 1, &long));
+        assert_ne!(LineKey::for_text(generation, 1, "甲"), LineKey::for_text(generation, 2, "甲"));
+        assert_ne!(LineKey::for_text(generation, 1, "甲"), LineKey::for_text(generation, 1, "乙"));
+    }
+}
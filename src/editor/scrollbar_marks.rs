@@ -0,0 +1,161 @@
+//! 垂直滚动条轨道上的标记：诊断、查找匹配、git 改动和光标所在行。
+//! 位置只按行号占总行数的比例计算，缩放字号后仍然对应同一行
+
+use gpui::{point, px, size, Bounds, Pixels, Point, Rgba};
+
+/// 标记最小的高度
+const MARK_HEIGHT: Pixels = px(2.0);
+/// 点击位置与标记相差不超过这个距离时算作点中
+const HIT_SLOP: Pixels = px(3.0);
+
+/// 标记在轨道中所占的列，同一行的不同类别从左到右并排
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MarkLane {
+    Git,
+    Find,
+    Diagnostic,
+}
+
+impl MarkLane {
+    const COUNT: usize = 3;
+
+    fn index(self) -> usize {
+        match self {
+            Self::Git => 0,
+            Self::Find => 1,
+            Self::Diagnostic => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollbarMark {
+    /// 显示行，有折叠时不计折起的行
+    pub line: usize,
+    /// None 为光标所在行，占满轨道宽度
+    pub lane: Option<MarkLane>,
+    pub color: Rgba,
+    /// 同一列落在同一像素行时只画优先级最高的
+    pub priority: u8,
+}
+
+/// 一帧中算好的全部标记，绘制和点击共用
+#[derive(Clone, Debug, Default)]
+pub struct ScrollbarMarks {
+    marks: Vec<ScrollbarMark>,
+    line_count: usize,
+}
+
+impl ScrollbarMarks {
+    pub fn new(line_count: usize) -> Self {
+        Self { marks: Vec::new(), line_count: line_count.max(1) }
+    }
+
+    pub fn push(&mut self, mark: ScrollbarMark) {
+        self.marks.push(mark);
+    }
+
+    /// 各标记在轨道 `track` 上的位置，光标行排在最后以画在最上层；
+    /// 同一列落在同一像素行的标记只保留优先级最高的一个
+    pub fn layout(&self, track: Bounds<Pixels>) -> Vec<(Bounds<Pixels>, ScrollbarMark)> {
+        let row_height = (track.size.height / self.line_count as f32).max(MARK_HEIGHT);
+        let lane_width = track.size.width / MarkLane::COUNT as f32;
+        let mut placed: Vec<(Pixels, ScrollbarMark)> = self
+            .marks
+            .iter()
+            .map(|mark| {
+                let y = (track.top() + track.size.height * (mark.line.min(self.line_count) as f32 / self.line_count as f32)).floor();
+                (y, *mark)
+            })
+            .collect();
+        placed.sort_by(|(a_y, a), (b_y, b)| {
+            let a_key = (a.lane.is_none(), a.lane);
+            let b_key = (b.lane.is_none(), b.lane);
+            a_key.cmp(&b_key).then(a_y.partial_cmp(b_y).unwrap_or(std::cmp::Ordering::Equal)).then(b.priority.cmp(&a.priority))
+        });
+        placed.dedup_by(|(y, mark), (kept_y, kept)| mark.lane == kept.lane && y == kept_y);
+        placed
+            .into_iter()
+            .map(|(y, mark)| {
+                let bounds = match mark.lane {
+                    Some(lane) => Bounds::new(
+                        point(track.left() + lane_width * lane.index() as f32, y),
+                        size(lane_width, row_height),
+                    ),
+                    None => Bounds::new(point(track.left(), y), size(track.size.width, MARK_HEIGHT)),
+                };
+                (bounds, mark)
+            })
+            .collect()
+    }
+
+    /// 点中的标记所在的行；同一高度上并排的标记不分列，取离点击位置最近的
+    pub fn line_at(&self, track: Bounds<Pixels>, position: Point<Pixels>) -> Option<usize> {
+        if position.x < track.left() || position.x > track.right() {
+            return None;
+        }
+        self.layout(track)
+            .into_iter()
+            .filter(|(bounds, _)| position.y >= bounds.top() - HIT_SLOP && position.y <= bounds.bottom() + HIT_SLOP)
+            .min_by(|(a, _), (b, _)| {
+                let a_distance = (a.center().y - position.y).abs();
+                let b_distance = (b.center().y - position.y).abs();
+                a_distance.partial_cmp(&b_distance).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(_, mark)| mark.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MarkLane, ScrollbarMark, ScrollbarMarks};
+    use gpui::{point, px, rgba, size, Bounds};
+
+    fn mark(line: usize, lane: Option<MarkLane>, priority: u8) -> ScrollbarMark {
+        ScrollbarMark { line, lane, color: rgba(priority as u32), priority }
+    }
+
+    #[test]
+    fn test_marks_follow_line_ratio_and_stack_by_lane() {
+        let track = Bounds::new(point(px(100.0), px(0.0)), size(px(12.0), px(1000.0)));
+        let mut marks = ScrollbarMarks::new(10_000);
+        marks.push(mark(5_000, None, 0));
+        marks.push(mark(5_000, Some(MarkLane::Diagnostic), 2));
+        // 与上一个落在同一像素行，优先级更高的保留
+        marks.push(mark(5_001, Some(MarkLane::Diagnostic), 3));
+        marks.push(mark(5_000, Some(MarkLane::Git), 1));
+        marks.push(mark(2_500, Some(MarkLane::Find), 1));
+
+        let laid_out = marks.layout(track);
+        assert_eq!(laid_out.len(), 4);
+        let (git, _) = laid_out.iter().find(|(_, mark)| mark.lane == Some(MarkLane::Git)).unwrap();
+        let (diagnostic, kept) = laid_out.iter().find(|(_, mark)| mark.lane == Some(MarkLane::Diagnostic)).unwrap();
+        let (find, _) = laid_out.iter().find(|(_, mark)| mark.lane == Some(MarkLane::Find)).unwrap();
+        assert_eq!(kept.priority, 3);
+        assert_eq!((git.top(), diagnostic.top(), find.top()), (px(500.0), px(500.0), px(250.0)));
+        // 同一行的不同类别并排，互不覆盖
+        assert_eq!(git.left(), px(100.0));
+        assert_eq!(diagnostic.left(), px(108.0));
+        assert!(git.right() <= find.left() && find.right() <= diagnostic.left());
+        // 光标行画在最后，占满轨道宽度
+        let (cursor, last) = laid_out.last().unwrap();
+        assert_eq!((last.lane, cursor.size.width), (None, px(12.0)));
+
+        // 轨道变高（例如缩放后窗口变化）时位置仍按比例
+        let taller = Bounds::new(point(px(100.0), px(0.0)), size(px(12.0), px(2000.0)));
+        assert!(marks.layout(taller).iter().any(|(bounds, mark)| mark.lane == Some(MarkLane::Find) && bounds.top() == px(500.0)));
+    }
+
+    #[test]
+    fn test_clicking_a_mark_returns_its_line() {
+        let track = Bounds::new(point(px(100.0), px(0.0)), size(px(12.0), px(1000.0)));
+        let mut marks = ScrollbarMarks::new(1_000);
+        marks.push(mark(200, Some(MarkLane::Find), 1));
+        marks.push(mark(700, Some(MarkLane::Git), 1));
+
+        assert_eq!(marks.line_at(track, point(px(111.0), px(201.0))), Some(200));
+        assert_eq!(marks.line_at(track, point(px(101.0), px(698.0))), Some(700));
+        assert_eq!(marks.line_at(track, point(px(105.0), px(450.0))), None);
+        assert_eq!(marks.line_at(track, point(px(90.0), px(200.0))), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! 结绳文件的语义高亮：tiec highlight 给出的符号按类型着色，覆盖在 sweetline 的语法高亮之上
+
+use std::ops::Range;
+
+/// 一段语义高亮，范围为文档中的字节偏移，`style` 为 `color_for_style` 中的样式名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticSpan {
+    pub range: Range<usize>,
+    pub style: &'static str,
+}
+
+/// tiec 的符号类型对应的样式名，编号与补全项相同；没有对应样式的类型保留语法高亮
+pub fn semantic_style(kind: i32) -> Option<&'static str> {
+    match kind {
+        2 | 3 => Some("function"),
+        6 => Some("variable"),
+        7 => Some("class"),
+        14 => Some("keyword"),
+        _ => None,
+    }
+}
+
+/// 当前文档的语义高亮，按位置升序且互不重叠
+#[derive(Default)]
+pub struct SemanticHighlights {
+    spans: Vec<SemanticSpan>,
+}
+
+impl SemanticHighlights {
+    /// 换成新的结果，返回着色有变化的范围，只需重绘这些范围所在的行
+    pub fn replace(&mut self, mut spans: Vec<SemanticSpan>) -> Vec<Range<usize>> {
+        spans.sort_by_key(|span| (span.range.start, span.range.end));
+        let mut end = 0;
+        spans.retain(|span| {
+            let keep = span.range.start >= end && span.range.start < span.range.end;
+            if keep {
+                end = span.range.end;
+            }
+            keep
+        });
+        let mut changed: Vec<Range<usize>> = self
+            .spans
+            .iter()
+            .filter(|span| !spans.contains(span))
+            .chain(spans.iter().filter(|span| !self.spans.contains(span)))
+            .map(|span| span.range.clone())
+            .collect();
+        changed.sort_by_key(|range| range.start);
+        self.spans = spans;
+        changed
+    }
+
+    /// 移除全部语义高亮，返回原先着色的范围
+    pub fn clear(&mut self) -> Vec<Range<usize>> {
+        self.replace(Vec::new())
+    }
+
+    /// 文档中 `edit` 被替换为 `new_len` 字节的文本：与之相交的高亮移除，其后的高亮平移
+    pub fn shift_for_edit(&mut self, edit: Range<usize>, new_len: usize) {
+        let removed = edit.end - edit.start;
+        self.spans.retain(|span| span.range.end <= edit.start || span.range.start >= edit.end);
+        for span in self.spans.iter_mut().filter(|span| span.range.start >= edit.end) {
+            span.range.start = span.range.start - removed + new_len;
+            span.range.end = span.range.end - removed + new_len;
+        }
+    }
+
+    /// 与 `range` 相交的高亮
+    pub fn in_range(&self, range: Range<usize>) -> impl Iterator<Item = &SemanticSpan> {
+        let first = self.spans.partition_point(|span| span.range.end <= range.start);
+        self.spans[first..].iter().take_while(move |span| span.range.start < range.end)
+    }
+}
+
+/// 把语义高亮覆盖到一行的语法高亮上，重叠处以语义高亮为准；两者都按位置升序且各自互不重叠
+pub fn overlay<C: Copy>(lexical: Vec<(Range<usize>, C)>, semantic: Vec<(Range<usize>, C)>) -> Vec<(Range<usize>, C)> {
+    if semantic.is_empty() {
+        return lexical;
+    }
+    let mut merged = Vec::with_capacity(lexical.len() + semantic.len());
+    for (range, color) in lexical {
+        let mut start = range.start;
+        for covered in semantic.iter().map(|(covered, _)| covered) {
+            if covered.end <= start || covered.start >= range.end {
+                continue;
+            }
+            if covered.start > start {
+                merged.push((start..covered.start, color));
+            }
+            start = start.max(covered.end);
+        }
+        if start < range.end {
+            merged.push((start..range.end, color));
+        }
+    }
+    merged.extend(semantic);
+    merged.sort_by_key(|(range, _)| range.start);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(range: Range<usize>, style: &'static str) -> SemanticSpan {
+        SemanticSpan { range, style }
+    }
+
+    #[test]
+    fn test_semantic_spans_override_lexical_and_follow_edits() {
+        let lexical = vec![(0..4, 'k'), (5..12, 'v'), (13..15, 'p')];
+        let semantic = vec![(7..10, 'c'), (13..15, 'f')];
+        assert_eq!(
+            overlay(lexical.clone(), semantic),
+            vec![(0..4, 'k'), (5..7, 'v'), (7..10, 'c'), (10..12, 'v'), (13..15, 'f')]
+        );
+        assert_eq!(overlay(lexical.clone(), Vec::new()), lexical);
+
+        let mut highlights = SemanticHighlights::default();
+        let changed = highlights.replace(vec![span(20..24, "class"), span(0..6, "function"), span(2..4, "variable")]);
+        assert_eq!(changed, vec![0..6, 20..24]);
+        assert_eq!(highlights.replace(vec![span(0..6, "function"), span(20..24, "variable")]), vec![20..24, 20..24]);
+
+        // 编辑落在前一段高亮里：这段高亮移除，之后的高亮平移
+        highlights.shift_for_edit(3..4, 3);
+        let spans: Vec<_> = highlights.in_range(0..100).cloned().collect();
+        assert_eq!(spans, vec![span(22..26, "variable")]);
+        assert_eq!(highlights.in_range(0..22).count(), 0);
+        assert_eq!(highlights.clear(), vec![22..26]);
+
+        assert_eq!(semantic_style(7), Some("class"));
+        assert_eq!(semantic_style(0), None);
+    }
+}
@@ -0,0 +1,61 @@
+//! 参数提示：光标所在调用的左括号，以及签名中当前参数的位置
+
+use std::ops::Range;
+
+/// `text` 中 `index` 之前尚未闭合的最后一个左括号的位置；字符串里的括号不计
+pub fn enclosing_paren(text: &str, index: usize) -> Option<usize> {
+    let mut open = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text[..index].char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' => open.push(i),
+            ')' => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    open.pop()
+}
+
+/// 签名中当前参数的字节范围，只在参数列表（第一个左括号之后）中查找
+pub fn active_parameter_range(signature: &str, active: &str) -> Option<Range<usize>> {
+    if active.is_empty() {
+        return None;
+    }
+    let params = signature.find('(')? + 1;
+    let start = params + signature[params..].find(active)?;
+    Some(start..start + active.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_enclosing_call_and_active_parameter() {
+        let line = "取参数信息(1, 求和(2, 3), \"(\"";
+        assert_eq!(enclosing_paren(line, line.len()), Some(15));
+        assert_eq!(enclosing_paren(line, 28), Some(25));
+        assert_eq!(enclosing_paren(line, 15), None);
+        assert_eq!(enclosing_paren("求和(2, 3)", 12), None);
+
+        let signature = "取参数信息(参数1: 文本, 参数2: 整数): 文本";
+        let range = active_parameter_range(signature, "参数2: 整数").unwrap();
+        assert_eq!(&signature[range], "参数2: 整数");
+        assert_eq!(active_parameter_range(signature, ""), None);
+        assert_eq!(active_parameter_range(signature, "取参数信息"), None);
+    }
+}
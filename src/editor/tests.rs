@@ -191,6 +191,194 @@ mod tests {
         assert!(core.marked_range.is_none(), "marked_range should be cleared after replace_range");
     }
 
+    #[test]
+    fn test_undo_coalesces_typing_and_deleting_within_limits() {
+        use crate::editor::core::EditorCore;
+        use std::time::Duration;
+
+        fn type_text(core: &mut EditorCore, text: &str) {
+            for ch in text.chars() {
+                let head = core.primary_selection().head;
+                core.replace_range(head..head, ch.encode_utf8(&mut [0; 4]));
+            }
+        }
+        fn backspace(core: &mut EditorCore) {
+            core.delete_at_cursors(CodeEditor::prev_char_index);
+        }
+
+        let mut core = EditorCore::new();
+        type_text(&mut core, "变量 a");
+        assert_eq!(core.history.undo_depth(), 1);
+        backspace(&mut core);
+        backspace(&mut core);
+        assert_eq!(core.history.undo_depth(), 2);
+        type_text(&mut core, "b");
+        assert_eq!(core.history.undo_depth(), 3);
+
+        // 回车、粘贴等边界之后另起一步
+        core.insert_text("\n");
+        core.break_undo_group();
+        type_text(&mut core, "cd");
+        core.break_undo_group();
+        type_text(&mut core, "e");
+        assert_eq!(core.history.undo_depth(), 6);
+
+        // 光标移动过或超过时间窗口的输入不合并
+        core.set_cursor(0);
+        type_text(&mut core, "f");
+        core.history.set_coalesce_window(Duration::ZERO);
+        type_text(&mut core, "gh");
+        assert_eq!(core.history.undo_depth(), 9);
+        assert_eq!(core.content.to_string(), "fgh变量b\ncde");
+
+        let mut steps = Vec::new();
+        while core.history.can_undo() {
+            core.undo();
+            steps.push((core.content.to_string(), core.primary_selection().head));
+        }
+        let expected = [
+            ("fg变量b\ncde", 2),
+            ("f变量b\ncde", 1),
+            ("变量b\ncde", 0),
+            ("变量b\ncd", 10),
+            ("变量b\n", 8),
+            ("变量b", 7),
+            ("变量", 6),
+            ("变量 a", 8),
+            ("", 0),
+        ];
+        let expected: Vec<_> = expected.iter().map(|&(text, head)| (text.to_string(), head)).collect();
+        assert_eq!(steps, expected);
+
+        core.redo();
+        assert_eq!((core.content.to_string(), core.primary_selection().head), ("变量 a".to_string(), 8));
+    }
+
+    #[test]
+    fn test_undo_redo_restore_multi_cursor_selections() {
+        use crate::editor::core::{EditorCore, Selection};
+
+        let cursors = |core: &EditorCore| -> Vec<(usize, usize)> {
+            core.selections.iter().map(|selection| (selection.anchor, selection.head)).collect()
+        };
+        let mut core = EditorCore::from_text("ab\ncd\n");
+        core.selections = vec![Selection::new(1, 1), Selection::new(4, 4)];
+        core.insert_text("x");
+        core.insert_text("y");
+        core.delete_at_cursors(CodeEditor::prev_char_index);
+        assert_eq!(core.content.to_string(), "axb\ncxd\n");
+        assert_eq!(core.history.undo_depth(), 2);
+
+        core.undo();
+        assert_eq!(core.content.to_string(), "axyb\ncxyd\n");
+        assert_eq!(cursors(&core), vec![(3, 3), (8, 8)]);
+        core.undo();
+        assert_eq!(core.content.to_string(), "ab\ncd\n");
+        assert_eq!(cursors(&core), vec![(1, 1), (4, 4)]);
+        core.redo();
+        assert_eq!(cursors(&core), vec![(3, 3), (8, 8)]);
+
+        // 删除选中的文本后撤销，选区连同方向一起恢复
+        core.selections = vec![Selection::new(2, 0), Selection::new(5, 7)];
+        core.delete_selection();
+        assert_eq!(core.content.to_string(), "yb\nyd\n");
+        core.undo();
+        assert_eq!(core.content.to_string(), "axyb\ncxyd\n");
+        assert_eq!(cursors(&core), vec![(2, 0), (5, 7)]);
+        core.redo();
+        assert_eq!(cursors(&core), vec![(0, 0), (3, 3)]);
+    }
+
+    #[test]
+    fn test_opening_large_file_analyzes_highlights_in_background() {
+        use crate::editor::grammar::JIESHENG_GRAMMAR;
+        use crate::editor::highlight::HighlightJob;
+        use ropey::Rope;
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        let engine = Arc::new(Engine::new(true));
+        engine.compile_json(CPP_GRAMMAR).expect("Failed to compile CPP");
+        engine.compile_json(JIESHENG_GRAMMAR).expect("Failed to compile JIESHENG");
+        let line = "变量 计数 为 整数 = 0 // 计数器\n";
+        let text = line.repeat(5 * 1024 * 1024 / line.len() + 1);
+        let content = Rope::from_str(&text);
+
+        // 打开文件时界面线程只创建分析任务，不超过一帧
+        let started = Instant::now();
+        let job = HighlightJob::new(1, engine.clone(), "file:///large.t", &content);
+        let elapsed = started.elapsed();
+        assert!(elapsed < Duration::from_millis(16), "open blocked for {:?}", elapsed);
+
+        let loaded = std::thread::spawn(move || job.run()).join().unwrap();
+        assert_eq!(loaded.revision, 1);
+        assert!(loaded.chunks.len() > 1);
+
+        // 分块依次相接，合并起来与同步分析的结果相同
+        let mut expected = DocumentAnalyzer::parse_result(&engine.load_document(&Document::new("file:///copy.t", &text)).analyze(), false);
+        expected.sort_by_key(|span| (span.end_index, span.start_index));
+        let mut merged = Vec::new();
+        for chunk in loaded.chunks {
+            assert!(chunk.spans.iter().all(|span| chunk.lines.contains(&(span.start_line as usize)) && chunk.lines.contains(&(span.end_line as usize))));
+            merged.extend(chunk.spans);
+        }
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn test_word_boundaries_respect_char_classes() {
+        use ropey::Rope;
+
+        let content = Rope::from("foo_bar  += 变量名.值\r\nx");
+        let text = content.to_string();
+        let at = |needle: &str| text.find(needle).unwrap();
+
+        // word -> whitespace -> punctuation run
+        assert_eq!(CodeEditor::next_word_index(&content, 0), at("  +="));
+        assert_eq!(CodeEditor::next_word_index(&content, at("  +=")), at(" 变量"));
+        // multi-byte identifiers are one word and never split
+        assert_eq!(CodeEditor::next_word_index(&content, at(" 变量")), at(".值"));
+        assert_eq!(CodeEditor::prev_word_index(&content, at(".值")), at("变量"));
+        // CRLF is skipped as a whole
+        assert_eq!(CodeEditor::next_word_index(&content, at("值") + "值".len()), text.len());
+        assert_eq!(CodeEditor::prev_word_index(&content, text.len() - 1), at("值"));
+        // clamps at document edges
+        assert_eq!(CodeEditor::prev_word_index(&content, 0), 0);
+        assert_eq!(CodeEditor::next_word_index(&content, text.len()), text.len());
+    }
+
+    #[test]
+    fn test_smart_home_and_line_end() {
+        use ropey::Rope;
+
+        let content = Rope::from("    foo\r\n\tbar\nbaz");
+        // first press: first non-whitespace, second press: column 0
+        assert_eq!(CodeEditor::smart_home_index(&content, 6), 4);
+        assert_eq!(CodeEditor::smart_home_index(&content, 4), 0);
+        assert_eq!(CodeEditor::smart_home_index(&content, 0), 4);
+        assert_eq!(CodeEditor::smart_home_index(&content, 11), 10);
+
+        // end stops before CRLF / LF
+        assert_eq!(CodeEditor::line_end_index(&content, 0), 7);
+        assert_eq!(CodeEditor::line_end_index(&content, 9), 13);
+        assert_eq!(CodeEditor::line_end_index(&content, 14), content.len_bytes());
+    }
+
+    #[test]
+    fn test_newline_indent_copies_and_adjusts_whitespace() {
+        assert_eq!(CodeEditor::newline_with_indent("    foo", 0), "\n    ");
+        assert_eq!(CodeEditor::newline_with_indent("    if (x) {", 1), "\n        ");
+        assert_eq!(CodeEditor::newline_with_indent("\tfoo {", 1), "\n\t\t");
+        assert_eq!(CodeEditor::newline_with_indent("        x", -1), "\n    ");
+        assert_eq!(CodeEditor::newline_with_indent("foo", -1), "\n");
+
+        // dedent goes back to the previous 4-space stop
+        assert_eq!(CodeEditor::dedent_once("      "), "    ");
+        assert_eq!(CodeEditor::dedent_once("        "), "    ");
+        assert_eq!(CodeEditor::dedent_once("  \t"), "  ");
+        assert_eq!(CodeEditor::dedent_once(""), "");
+    }
+
     #[test]
     fn test_jiesheng_incremental_edit_crash() {
         use crate::editor::grammar::JIESHENG_GRAMMAR;
@@ -250,4 +438,313 @@ mod tests {
         assert_eq!(map.scopes.as_ref().get(&1).copied(), Some(4));
         assert_eq!(map.scopes.as_ref().get(&2).copied(), Some(3));
     }
+
+    #[test]
+    fn test_toggle_line_comment_uses_common_indent_and_round_trips() {
+        use crate::editor::comment::{toggle_line_comment_edits, CommentTokens};
+        use ropey::Rope;
+
+        fn apply(text: &str, mut edits: Vec<(std::ops::Range<usize>, String)>) -> String {
+            let mut text = text.to_string();
+            edits.sort_by_key(|edit| std::cmp::Reverse(edit.0.start));
+            for (range, new_text) in edits {
+                text.replace_range(range, &new_text);
+            }
+            text
+        }
+
+        let rust = CommentTokens::for_path("file:///src/main.rs").unwrap();
+        let code = "    if x {\n\n        y();\n    }\n";
+        let commented = apply(code, toggle_line_comment_edits(&Rope::from(code), 0..=3, rust));
+        assert_eq!(commented, "    // if x {\n\n    //     y();\n    // }\n");
+        let restored = apply(&commented, toggle_line_comment_edits(&Rope::from(commented.as_str()), 0..=3, rust));
+        assert_eq!(restored, code);
+
+        // 部分行已注释时整体加注释
+        let mixed = "# a\nb\n";
+        let python = CommentTokens::for_path("script.py").unwrap();
+        assert_eq!(apply(mixed, toggle_line_comment_edits(&Rope::from(mixed), 0..=1, python)), "# # a\n# b\n");
+
+        let html = CommentTokens::for_path("index.html").unwrap();
+        let tag = "  <p>hi</p>";
+        let wrapped = apply(tag, toggle_line_comment_edits(&Rope::from(tag), 0..=0, html));
+        assert_eq!(wrapped, "  <!-- <p>hi</p> -->");
+        assert_eq!(apply(&wrapped, toggle_line_comment_edits(&Rope::from(wrapped.as_str()), 0..=0, html)), tag);
+    }
+
+    #[test]
+    fn test_untitled_paths_are_not_files() {
+        use crate::editor::{untitled_name, untitled_path};
+        let path = untitled_path(2);
+        assert_eq!(untitled_name(&path), Some("Untitled-2"));
+        assert!(!path.is_file());
+        assert_eq!(untitled_name(std::path::Path::new("/tmp/Untitled-2")), None);
+    }
+
+    #[test]
+    fn test_revert_hunk_restores_base_lines() {
+        use crate::editor::diff_hunk_at;
+        use crate::editor::core::LineEnding;
+        use ropey::Rope;
+        let base = "a\nb\nc\n";
+        let content = Rope::from("a\nB\nX\nc\n");
+        let (range, text) = diff_hunk_at(base, &content, 2, LineEnding::Lf).unwrap();
+        assert_eq!(&content.to_string()[range], "B\nX\n");
+        assert_eq!(text, "b\n");
+        assert!(diff_hunk_at(base, &content, 0, LineEnding::Lf).is_none());
+
+        // 删除的行标记在其后一行，还原时插回原处
+        let deleted = Rope::from("a\nc\n");
+        let (range, text) = diff_hunk_at(base, &deleted, 1, LineEnding::Lf).unwrap();
+        assert_eq!(range, 2..2);
+        assert_eq!(text, "b\n");
+    }
+
+    #[test]
+    fn test_git_diff_ignores_line_ending_differences() {
+        use crate::editor::core::LineEnding;
+        use crate::editor::{compute_git_diff, diff_hunk_at, GitDiffStatus};
+        use ropey::Rope;
+        // 基准为 LF，工作区里被转换成了 CRLF，只有真正改过的行有标记
+        let base = "a\nb\nc\n";
+        let content = Rope::from("a\r\nB\r\nc\r\n");
+        let map = compute_git_diff(base, &content);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&1), Some(&GitDiffStatus::Modified));
+        assert!(compute_git_diff(base, &Rope::from("a\r\nb\r\nc\r\n")).is_empty());
+
+        // 还原时按文件的换行符写回
+        let (range, text) = diff_hunk_at(base, &content, 1, LineEnding::Crlf).unwrap();
+        assert_eq!(&content.to_string()[range], "B\r\n");
+        assert_eq!(text, "b\r\n");
+    }
+
+    #[test]
+    fn test_stale_decorations_follow_concurrent_edits() {
+        use crate::editor::core::EditorCore;
+        use crate::editor::{map_decorations, Decoration, DecorationColor};
+        let mut core = EditorCore::from_text("变量 a = 1\n变量 b = 未定义\n");
+        // 后台查错针对这一版本开始计算
+        let version = core.version();
+        let undefined = core.content.to_string().find("未定义").unwrap();
+        let decoration = |range| Decoration { range, color: DecorationColor::Gray, message: None };
+        let stale = vec![decoration(7..8), decoration(undefined..undefined + 9)];
+
+        // 结果到达前用户在开头插入一行，并改掉了 `a`
+        core.replace_range(0..0, "// 注释\n");
+        core.replace_range(17..18, "甲");
+
+        let mapped = map_decorations(&core, version, stale);
+        assert_eq!(mapped.len(), 1);
+        let range = mapped[0].range.clone();
+        assert_eq!(&core.content.to_string()[range], "未定义");
+
+        // 整个文档被替换后旧结果全部丢弃
+        core.set_text("变量 b = 未定义\n");
+        assert!(map_decorations(&core, version, vec![decoration(0..1)]).is_empty());
+    }
+
+    #[test]
+    fn test_lint_diagnostics_become_squiggles_and_problems() {
+        use crate::editor::lint_decorations;
+        use crate::editor::lsp_integration::{Problem, ProblemSeverity};
+        use crate::editor::DecorationColor;
+        use crate::lsp::tiec::types::{Diagnostic, Position, Range};
+        use ropey::Rope;
+        let content = Rope::from("变量 a = 1\n变量 b = 未定义\n");
+        let diagnostic = |start: (usize, usize), end: (usize, usize), level| Diagnostic {
+            uri: String::new(),
+            range: Range {
+                start: Position { line: start.0, column: start.1 },
+                end: Position { line: end.0, column: end.1 },
+            },
+            key: String::new(),
+            message: format!("level {}", level),
+            level,
+        };
+        let (decorations, problems) = lint_decorations(
+            &content,
+            vec![diagnostic((1, 7), (1, 10), 3), diagnostic((0, 3), (0, 3), 2), diagnostic((0, 0), (0, 2), 1)],
+        );
+
+        // 低于警告的诊断不显示，空范围扩展到一个字符
+        let text = content.to_string();
+        assert_eq!(decorations.len(), 2);
+        assert_eq!(&text[decorations[0].range.clone()], "未定义");
+        assert_eq!(decorations[0].color, DecorationColor::Red);
+        assert_eq!(&text[decorations[1].range.clone()], "a");
+        assert_eq!(decorations[1].color, DecorationColor::Yellow);
+        assert_eq!(decorations[1].message.as_deref(), Some("level 2"));
+        assert_eq!(
+            problems,
+            vec![
+                Problem { line: 1, column: 7, message: "level 3".to_string(), severity: ProblemSeverity::Error },
+                Problem { line: 0, column: 3, message: "level 2".to_string(), severity: ProblemSeverity::Warning },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_project_diagnostics_group_by_file() {
+        use crate::editor::lsp_integration::{Problem, ProblemSeverity};
+        use crate::editor::project_problems;
+        use crate::lsp::tiec::types::{Diagnostic, Position, Range};
+        use std::path::PathBuf;
+        let root = std::env::temp_dir();
+        let (main, util, clean) = (root.join("主程序.t"), root.join("工具.t"), root.join("干净.t"));
+        let diagnostic = |path: &PathBuf, line, column, level| Diagnostic {
+            uri: url::Url::from_file_path(path).unwrap().to_string(),
+            range: Range { start: Position { line, column }, end: Position { line, column: column + 1 } },
+            key: String::new(),
+            message: format!("level {}", level),
+            level,
+        };
+        let problems = project_problems(
+            &[main.clone(), util.clone(), clean.clone()],
+            vec![diagnostic(&main, 1, 3, 3), diagnostic(&util, 4, 2, 2), diagnostic(&main, 0, 0, 1)],
+            |path| (path == main).then(|| "变量 a\n变量 b = 未定义\n".to_string()),
+        );
+
+        // 列按读到的内容换算；读不到内容时用诊断中的行列；没有诊断的文件清空
+        let problem = |line, column, level, severity| Problem { line, column, message: format!("level {}", level), severity };
+        let mut expected = vec![
+            (main.clone(), vec![problem(1, 3, 3, ProblemSeverity::Error)]),
+            (util.clone(), vec![problem(4, 2, 2, ProblemSeverity::Warning)]),
+            (clean.clone(), Vec::new()),
+        ];
+        expected.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(problems, expected);
+    }
+
+    #[test]
+    fn test_grammar_dependencies_compile_embedded_languages_first() {
+        use crate::editor::grammar::{grammar_dependencies, grammar_index_for_path, grammar_name, ALL_GRAMMARS};
+        let jiesheng = grammar_index_for_path("/p/main.t").unwrap();
+        let cpp = grammar_index_for_path("/p/a.cpp").unwrap();
+        assert_eq!(grammar_dependencies(jiesheng), vec![cpp]);
+        assert!(grammar_dependencies(cpp).is_empty());
+        assert_eq!(grammar_name(ALL_GRAMMARS[cpp]).as_deref(), Some("CPP"));
+        assert_eq!(grammar_index_for_path("/p/readme.unknown"), None);
+    }
+
+    #[test]
+    fn test_grammar_assets_override_embedded_copies() {
+        use crate::editor::grammar::{
+            grammar_index_for_asset, grammar_name, grammar_source, set_grammar_source, ALL_GRAMMARS, GRAMMAR_ASSETS,
+        };
+        use std::path::Path;
+        use std::sync::Arc;
+        let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+        for (index, asset) in GRAMMAR_ASSETS.iter().enumerate() {
+            assert_eq!(std::fs::read_to_string(assets.join(asset)).unwrap(), ALL_GRAMMARS[index]);
+            assert_eq!(grammar_index_for_asset(&assets.join(asset)), Some(index));
+        }
+        assert_eq!(grammar_index_for_asset(Path::new("/assets/grammars/unknown.json")), None);
+
+        let shell = grammar_index_for_asset(Path::new("shell.json")).unwrap();
+        set_grammar_source(shell, Some(Arc::from(r#"{ "name": "Shell Dev", "fileExtensions": [".sh"] }"#)));
+        assert_eq!(grammar_name(&grammar_source(shell)).as_deref(), Some("Shell Dev"));
+        set_grammar_source(shell, None);
+        assert_eq!(&*grammar_source(shell), ALL_GRAMMARS[shell]);
+    }
+
+    #[test]
+    fn test_plugin_grammars_register_by_extension() {
+        use crate::editor::grammar::{
+            grammar_disabled, grammar_index_for_path, grammar_source, register_plugin_grammar, sync_plugin_grammars,
+            PluginGrammar,
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lua.json");
+        std::fs::write(&path, r#"{ "name": "Plugin Lua", "fileExtensions": [".pluginlua"] }"#).unwrap();
+        let grammar = PluginGrammar {
+            plugin_id: "demo.lua".to_string(),
+            path: path.clone(),
+            extensions: vec![".pluginluau".to_string()],
+        };
+        assert!(sync_plugin_grammars(std::slice::from_ref(&grammar)).is_empty());
+        let index = grammar_index_for_path("/p/main.pluginluau").unwrap();
+        assert_eq!(grammar_index_for_path("/p/main.pluginlua"), Some(index));
+        assert!(grammar_source(index).contains(".pluginluau"));
+
+        // 再次同步时保留原来的位置
+        assert!(sync_plugin_grammars(std::slice::from_ref(&grammar)).is_empty());
+        assert_eq!(grammar_index_for_path("/p/main.pluginlua"), Some(index));
+
+        // 读不到的语法只跳过这一项，插件不再提供的语法停用
+        let missing = PluginGrammar { path: dir.path().join("missing.json"), ..grammar };
+        assert_eq!(sync_plugin_grammars(&[missing]).len(), 1);
+        assert!(grammar_disabled(index));
+        assert_eq!(grammar_index_for_path("/p/main.pluginlua"), None);
+        assert!(register_plugin_grammar("demo.lua", "{", &[]).is_err());
+    }
+
+    #[test]
+    fn test_column_copy_keeps_short_lines() {
+        use crate::editor::core::{EditorCore, Selection};
+        use crate::editor::selections_text;
+        let mut core = EditorCore::from_text("甲乙丙\n甲\n甲乙丙丁\n");
+        // 第二行短于起点列，只有行尾的光标
+        core.selections = vec![Selection::new(3, 9), Selection::new(13, 13), Selection::new(17, 23)];
+        core.insert_text("X");
+        assert_eq!(core.content.to_string(), "甲X\n甲X\n甲X丁\n");
+
+        let selections = vec![Selection::new(3, 6), Selection::new(13, 13), Selection::new(17, 20)];
+        let content = ropey::Rope::from("甲乙丙\n甲\n甲乙丙丁\n");
+        assert_eq!(selections_text(&content, &selections, true).as_deref(), Some("乙\n\n乙"));
+        assert_eq!(selections_text(&content, &selections, false).as_deref(), Some("乙\n乙"));
+        assert_eq!(selections_text(&content, &[Selection::new(0, 0)], true), None);
+    }
+
+    #[test]
+    fn test_rename_result_maps_to_byte_ranges() {
+        use crate::editor::lsp_integration::{rename_file_edits, text_edit_ranges};
+        use crate::lsp::tiec::types::RenameResult;
+        let result: RenameResult = serde_json::from_str(
+            r#"{ "projectEdit": {
+                "file:///p/b.t": [{ "range": { "start": { "line": 1, "column": 3 }, "end": { "line": 1, "column": 5 } }, "newText": "总数" }],
+                "file:///p/a.t": [],
+                "untitled:1": [{ "range": { "start": { "line": 0, "column": 0 }, "end": { "line": 0, "column": 1 } }, "newText": "x" }]
+            } }"#,
+        )
+        .unwrap();
+        let files = rename_file_edits(result);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, std::path::PathBuf::from("/p/b.t"));
+
+        // 列按字符计，换成字节范围
+        let content = ropey::Rope::from("变量 计数\n返回 计数\n");
+        let edits = text_edit_ranges(&content, &files[0].1);
+        assert_eq!(edits, vec![(21..27, "总数".to_string())]);
+    }
+
+    #[test]
+    fn test_smart_enter_result_maps_to_action() {
+        use crate::editor::lsp_integration::{smart_enter_action, SmartEnter};
+        use crate::lsp::tiec::types::SmartEnterResult;
+        let parse = |json: &str| smart_enter_action(serde_json::from_str::<SmartEnterResult>(json).unwrap());
+        let range = r#""range": { "start": { "line": 2, "column": 4 }, "end": { "line": 2, "column": 5 } }"#;
+
+        let Some(SmartEnter::Replace(edit)) = parse(&format!(r#"{{ "kind": 3, {range}, "replaceFormat": "%s", "isTrue": true }}"#)) else {
+            panic!("真/假开关应直接替换");
+        };
+        assert_eq!(edit.new_text, "假");
+        assert_eq!(edit.range.start.column, 4);
+
+        let Some(SmartEnter::Choose(actions)) =
+            parse(&format!(r#"{{ "kind": 2, {range}, "replaceFormat": "颜色.%s", "enums": ["红", "绿"] }}"#))
+        else {
+            panic!("枚举常量应列出可选值");
+        };
+        let texts: Vec<_> = actions.iter().map(|action| (action.title.as_str(), action.edits[0].new_text.as_str())).collect();
+        assert_eq!(texts, [("红", "颜色.红"), ("绿", "颜色.绿")]);
+
+        assert!(matches!(
+            parse(&format!(r#"{{ "kind": 1, {range}, "replaceFormat": ""%s"" }}"#)),
+            Some(SmartEnter::PickFile { format, .. }) if format == "\"%s\""
+        ));
+        // 未知类型和没有枚举值时照常换行
+        assert_eq!(parse(&format!(r#"{{ "kind": 0, {range} }}"#)), None);
+        assert_eq!(parse(&format!(r#"{{ "kind": 2, {range}, "enums": [] }}"#)), None);
+    }
 }
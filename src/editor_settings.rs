@@ -0,0 +1,152 @@
+//! 不属于某个项目的编辑器设置，保存在配置目录的 editor.json 中：
+//!
+//! ```json
+//! { "largeFileThresholdMb": 20, "autosaveDelayMs": 1000, "saveErrorCheck": "warn" }
+//! ```
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const EDITOR_SETTINGS_FILE: &str = "editor.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorSettings {
+    /// 超过这个大小（MB）的文件打开前先确认，以大文件模式打开
+    #[serde(default = "default_threshold_mb")]
+    pub large_file_threshold_mb: u64,
+    /// 自动保存时缓冲区停止编辑多久（毫秒）后写入
+    #[serde(default = "default_autosave_delay_ms")]
+    pub autosave_delay_ms: u64,
+    #[serde(default)]
+    pub save_error_check: SaveErrorCheck,
+}
+
+/// 保存带有查错错误的文件时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SaveErrorCheck {
+    #[default]
+    Off,
+    /// 提示错误，可直接“仍然保存”
+    Warn,
+    /// 提示错误，需要额外点击一次才会出现“仍然保存”
+    Block,
+}
+
+impl SaveErrorCheck {
+    pub fn next(self) -> Self {
+        match self {
+            SaveErrorCheck::Off => SaveErrorCheck::Warn,
+            SaveErrorCheck::Warn => SaveErrorCheck::Block,
+            SaveErrorCheck::Block => SaveErrorCheck::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SaveErrorCheck::Off => "关闭",
+            SaveErrorCheck::Warn => "提示",
+            SaveErrorCheck::Block => "阻止",
+        }
+    }
+}
+
+fn default_threshold_mb() -> u64 {
+    20
+}
+
+fn default_autosave_delay_ms() -> u64 {
+    1000
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            large_file_threshold_mb: default_threshold_mb(),
+            autosave_delay_ms: default_autosave_delay_ms(),
+            save_error_check: SaveErrorCheck::default(),
+        }
+    }
+}
+
+impl EditorSettings {
+    /// 没有设置文件或无法解析时使用默认值
+    pub fn load() -> Self {
+        settings_path().map(|path| Self::load_from(&path)).unwrap_or_default()
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|err| {
+            warn!("Ignoring unreadable editor settings {:?}: {}", path, err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = settings_path() {
+            if let Err(err) = self.save_to(&path) {
+                warn!("Failed to save editor settings to {:?}: {}", path, err);
+            }
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, content)
+    }
+
+    pub fn large_file_threshold(&self) -> u64 {
+        self.large_file_threshold_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn autosave_delay(&self) -> Duration {
+        Duration::from_millis(self.autosave_delay_ms)
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    crate::paths::config_dir().map(|dir| dir.join(EDITOR_SETTINGS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_keys_use_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(EDITOR_SETTINGS_FILE);
+        let defaults = EditorSettings::load_from(&path);
+        assert_eq!(defaults.large_file_threshold(), 20 * 1024 * 1024);
+        assert_eq!(defaults.autosave_delay(), Duration::from_millis(1000));
+
+        std::fs::write(&path, "{ \"largeFileThresholdMb\": 5 }").unwrap();
+        let settings = EditorSettings::load_from(&path);
+        assert_eq!(settings.large_file_threshold(), 5 * 1024 * 1024);
+        assert_eq!(settings.autosave_delay(), defaults.autosave_delay());
+
+        std::fs::write(&path, "{ \"autosaveDelayMs\": \"soon\" }").unwrap();
+        assert_eq!(EditorSettings::load_from(&path), defaults);
+    }
+
+    #[test]
+    fn test_save_error_check_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config").join(EDITOR_SETTINGS_FILE);
+        assert_eq!(EditorSettings::load_from(&path).save_error_check, SaveErrorCheck::Off);
+
+        let settings = EditorSettings { save_error_check: SaveErrorCheck::Block, ..EditorSettings::default() };
+        settings.save_to(&path).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains("\"saveErrorCheck\": \"block\""));
+        assert_eq!(EditorSettings::load_from(&path), settings);
+    }
+}
@@ -0,0 +1,74 @@
+//! 打开文件前的检查：开头含 NUL 字节的文件按二进制处理，不载入编辑器；超过阈值的文件
+//! 先确认，再以大文件模式打开。阈值见 [`EditorSettings`](crate::editor_settings::EditorSettings)
+
+use std::io::Read;
+use std::path::Path;
+
+/// 检查是否为二进制时读取的文件开头长度
+const SNIFF_LEN: u64 = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileKind {
+    Text,
+    /// 超过阈值的文本文件
+    Large { size: u64 },
+    /// 开头含有 NUL 字节
+    Binary { size: u64 },
+}
+
+/// 按文件大小和开头的内容判断怎样打开；二进制优先于大文件
+pub fn inspect(path: &Path, threshold: u64) -> std::io::Result<FileKind> {
+    let file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut head = Vec::new();
+    file.take(SNIFF_LEN).read_to_end(&mut head)?;
+    Ok(if head.contains(&0) {
+        FileKind::Binary { size }
+    } else if size > threshold {
+        FileKind::Large { size }
+    } else {
+        FileKind::Text
+    })
+}
+
+/// 显示给用户的文件大小，如 `512 B`、`3.4 KB`、`21.0 MB`
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_detects_binary_and_large_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("a.t");
+        std::fs::write(&text, "变量 甲 = 1\n").unwrap();
+        assert_eq!(inspect(&text, 1024).unwrap(), FileKind::Text);
+        assert_eq!(inspect(&text, 4).unwrap(), FileKind::Large { size: 15 });
+
+        let binary = dir.path().join("a.bin");
+        std::fs::write(&binary, b"\x7fELF\x00\x01").unwrap();
+        assert_eq!(inspect(&binary, 1).unwrap(), FileKind::Binary { size: 6 });
+
+        assert!(inspect(&dir.path().join("missing"), 1024).is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(3 * 1024 + 400), "3.4 KB");
+        assert_eq!(format_size(21 * 1024 * 1024), "21.0 MB");
+    }
+}
@@ -0,0 +1,211 @@
+//! 监视已打开的文件在外部被修改或删除
+//!
+//! 监视的是文件所在的目录而不是文件本身，这样“写临时文件再改名”的保存方式也能被发现。
+//! 同一文件的事件在安静一段时间后才交给调用方，VS Code 等编辑器保存时会连续写两次
+
+use log::warn;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// 文件最后一次变化后等待多久再处理
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 按文件合并短时间内的连续事件
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl Debouncer {
+    pub fn push(&mut self, path: PathBuf, now: Instant) {
+        self.pending.insert(path, now);
+    }
+
+    /// 取出最后一次事件距今已超过 [`DEBOUNCE`] 的文件
+    pub fn take_settled(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut settled = Vec::new();
+        self.pending.retain(|path, last| {
+            if now.duration_since(*last) >= DEBOUNCE {
+                settled.push(path.clone());
+                false
+            } else {
+                true
+            }
+        });
+        settled
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub struct OpenFileWatcher {
+    watcher: Option<RecommendedWatcher>,
+    dirs: HashSet<PathBuf>,
+    rx: mpsc::Receiver<PathBuf>,
+    debouncer: Debouncer,
+    /// 每个文件最后一次从磁盘读取或写回时的内容，用于判断缓冲区是否有未保存的修改
+    saved: HashMap<PathBuf, u64>,
+}
+
+/// 把变化的路径发到 `tx`
+fn forward_events(tx: mpsc::Sender<PathBuf>) -> impl FnMut(notify::Result<notify::Event>) + Send + 'static {
+    move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        // 读取文件本身也会产生访问事件
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in event.paths {
+            let _ = tx.send(path);
+        }
+    }
+}
+
+impl OpenFileWatcher {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel::<PathBuf>();
+        let watcher = notify::recommended_watcher(forward_events(tx));
+        let watcher = match watcher {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                warn!("Open file watcher init failed: {:?}", err);
+                None
+            }
+        };
+        Self {
+            watcher,
+            dirs: HashSet::new(),
+            rx,
+            debouncer: Debouncer::default(),
+            saved: HashMap::new(),
+        }
+    }
+
+    /// 让监视的目录与已打开的文件保持一致
+    pub fn sync<'a>(&mut self, paths: impl IntoIterator<Item = &'a PathBuf>) {
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+        let dirs: HashSet<PathBuf> = paths
+            .into_iter()
+            .filter_map(|path| path.parent().map(Path::to_path_buf))
+            .collect();
+        for dir in self.dirs.difference(&dirs) {
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&self.dirs) {
+            if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                warn!("Failed to watch {:?}: {:?}", dir, err);
+            }
+        }
+        self.dirs = dirs;
+    }
+
+    /// 收集新事件，返回已经稳定下来的文件；其中可能有未打开的文件，由调用方过滤
+    pub fn poll(&mut self, now: Instant) -> Vec<PathBuf> {
+        while let Ok(path) = self.rx.try_recv() {
+            self.debouncer.push(path, now);
+        }
+        self.debouncer.take_settled(now)
+    }
+
+    /// 稍后再处理该文件，例如当前已有其它确认框
+    pub fn retry(&mut self, path: PathBuf) {
+        self.debouncer.push(path, Instant::now());
+    }
+
+    /// 记录文件在磁盘上的内容，在读取或保存之后调用
+    pub fn mark_saved(&mut self, path: &Path, content: &str) {
+        self.saved.insert(path.to_path_buf(), content_hash(content));
+    }
+
+    /// 缓冲区与最后一次读取或保存的内容相同；没有记录时视为有修改
+    pub fn is_clean(&self, path: &Path, buffer: &str) -> bool {
+        self.saved.get(path) == Some(&content_hash(buffer))
+    }
+
+    /// 磁盘上的内容与最后一次读取或保存时不同，例如另一个窗口在这之后保存了它；没有记录或读取失败时视为没有变化
+    pub fn changed_on_disk(&self, path: &Path) -> bool {
+        let Some(saved) = self.saved.get(path) else {
+            return false;
+        };
+        std::fs::read_to_string(path).is_ok_and(|raw| content_hash(tiecode_buffer::strip_bom(&raw).0) != *saved)
+    }
+
+    pub fn rename(&mut self, src: &Path, dst: &Path) {
+        if let Some(hash) = self.saved.remove(src) {
+            self.saved.insert(dst.to_path_buf(), hash);
+        }
+    }
+
+    pub fn forget(&mut self, path: &Path) {
+        self.saved.remove(path);
+    }
+}
+
+/// 调试构建中监视语法目录，改动稳定后由调用方重新编译语法
+pub struct GrammarWatcher {
+    _watcher: RecommendedWatcher,
+    rx: mpsc::Receiver<PathBuf>,
+    debouncer: Debouncer,
+}
+
+impl GrammarWatcher {
+    pub fn new(dir: &Path) -> Option<Self> {
+        let (tx, rx) = mpsc::channel::<PathBuf>();
+        let watcher = notify::recommended_watcher(forward_events(tx)).and_then(|mut watcher| {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+        match watcher {
+            Ok(watcher) => Some(Self { _watcher: watcher, rx, debouncer: Debouncer::default() }),
+            Err(err) => {
+                warn!("Failed to watch grammars in {:?}: {:?}", dir, err);
+                None
+            }
+        }
+    }
+
+    /// 已经稳定下来的语法文件
+    pub fn poll(&mut self, now: Instant) -> Vec<PathBuf> {
+        while let Ok(path) = self.rx.try_recv() {
+            self.debouncer.push(path, now);
+        }
+        self.debouncer.take_settled(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_double_write_is_reported_once_after_quiet_period() {
+        let mut debouncer = Debouncer::default();
+        let start = Instant::now();
+        let path = PathBuf::from("/p/a.t");
+        debouncer.push(path.clone(), start);
+        debouncer.push(path.clone(), start + Duration::from_millis(50));
+        assert!(debouncer.take_settled(start + Duration::from_millis(200)).is_empty());
+        assert_eq!(debouncer.take_settled(start + Duration::from_millis(350)), vec![path]);
+        assert!(debouncer.take_settled(start + Duration::from_millis(1000)).is_empty());
+
+        let mut watcher = OpenFileWatcher::new();
+        let file = Path::new("/p/a.t");
+        assert!(!watcher.is_clean(file, "a"));
+        watcher.mark_saved(file, "a");
+        assert!(watcher.is_clean(file, "a"));
+        assert!(!watcher.is_clean(file, "ab"));
+    }
+}
@@ -1 +1,2 @@
+pub mod stdio_client;
 pub mod tiec;
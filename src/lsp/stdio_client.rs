@@ -0,0 +1,761 @@
+//! 通过子进程的标准输入输出与外部语言服务器（rust-analyzer、pyright 等）通信，
+//! 实现 Language Server Protocol 中编辑器用到的部分：初始化、文档同步、补全、悬停、转到定义和诊断推送。
+//!
+//! 每种扩展名使用哪个服务器来自项目设置的 `lsp.servers` 一节，值为命令行（字符串或参数数组），
+//! 空数组表示不为这种文件启动服务器：
+//!
+//! ```json
+//! { "lsp": { "servers": { "rs": "rust-analyzer", "py": ["pyright-langserver", "--stdio"] } } }
+//! ```
+//!
+//! 同一项目中相同命令的服务器由所有编辑器共享。服务器退出后在下次使用时按退避时间重新启动，
+//! 并重新打开仍在编辑的文档。
+
+use anyhow::{anyhow, Context as _, Result};
+use log::warn;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::lsp::tiec::settings::PROJECT_SETTINGS_FILE;
+use crate::lsp::tiec::types::{Diagnostic, Location, Position, Range};
+use crate::output::{log_channel, OutputChannel};
+use crate::paths::expand_path;
+
+/// 等待初始化握手的最长时间；rust-analyzer 在大项目中启动较慢
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(30);
+/// 等待普通请求的最长时间，超时后取消请求
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// 服务器连续运行这么久之后退出，不计入连续失败的次数
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+/// 重启退避的上限
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// 启动语言服务器的命令行
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl ServerCommand {
+    fn new(program: &str, args: &[&str]) -> Self {
+        Self { program: program.to_string(), args: args.iter().map(|arg| arg.to_string()).collect() }
+    }
+}
+
+/// 没有项目设置时使用的服务器：扩展名 → 命令行
+pub fn default_servers() -> HashMap<String, ServerCommand> {
+    HashMap::from([
+        ("rs".to_string(), ServerCommand::new("rust-analyzer", &[])),
+        ("py".to_string(), ServerCommand::new("pyright-langserver", &["--stdio"])),
+    ])
+}
+
+/// 读取项目设置中的服务器配置，与默认配置合并；文件缺失或无法解析时使用默认配置
+pub fn load_servers(project_root: &Path) -> HashMap<String, ServerCommand> {
+    let Ok(content) = std::fs::read_to_string(project_root.join(PROJECT_SETTINGS_FILE)) else {
+        return default_servers();
+    };
+    let (servers, warnings) = parse_servers(&content);
+    for warning in warnings {
+        warn!("{}", warning);
+    }
+    servers
+}
+
+/// 解析设置文件中的 `lsp.servers`，同时返回面向用户的警告；写错的项保留默认配置
+pub fn parse_servers(content: &str) -> (HashMap<String, ServerCommand>, Vec<String>) {
+    let mut servers = default_servers();
+    let mut warnings = Vec::new();
+    let value = serde_json::from_str::<Value>(content).ok();
+    let Some(section) = value.as_ref().and_then(|value| value.get("lsp")?.get("servers")) else {
+        return (servers, warnings);
+    };
+    let Some(section) = section.as_object() else {
+        warnings.push("lsp.servers 应为扩展名到命令行的映射".to_string());
+        return (servers, warnings);
+    };
+    for (extension, command) in section {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        let parts: Option<Vec<String>> = match command {
+            Value::String(line) => Some(line.split_whitespace().map(str::to_string).collect()),
+            Value::Array(parts) => parts.iter().map(|part| part.as_str().map(str::to_string)).collect(),
+            _ => None,
+        };
+        match parts {
+            Some(parts) if parts.is_empty() => {
+                servers.remove(&extension);
+            }
+            Some(mut parts) => {
+                // 程序可以写成 `~/bin/clangd` 或 `$VAR/bin/clangd` 等形式
+                let program = expand_path(&parts.remove(0)).to_string_lossy().into_owned();
+                servers.insert(extension, ServerCommand { program, args: parts });
+            }
+            None => warnings.push(format!("lsp.servers.{} 应为命令行字符串或字符串数组", extension)),
+        }
+    }
+    (servers, warnings)
+}
+
+/// 文档的语言标识，用于 `textDocument/didOpen`
+pub fn language_id(extension: &str) -> &str {
+    match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        other => other,
+    }
+}
+
+/// 按 JSON-RPC 的基础协议给消息加上 `Content-Length` 头
+pub fn encode_message(message: &Value) -> Vec<u8> {
+    let body = message.to_string();
+    let mut bytes = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    bytes.extend_from_slice(body.as_bytes());
+    bytes
+}
+
+/// 读取一条消息；流在两条消息之间结束时返回 None
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    let mut line = String::new();
+    let mut first = true;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            if first {
+                return Ok(None);
+            }
+            return Err(anyhow!("stream ended inside message header"));
+        }
+        first = false;
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(anyhow!("malformed header line: {:?}", header));
+        };
+        if name.trim().eq_ignore_ascii_case("Content-Length") {
+            length = Some(value.trim().parse::<usize>().with_context(|| format!("invalid Content-Length: {:?}", value))?);
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("message without Content-Length header"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// 服务器推送的一份文档的全部诊断
+#[derive(Clone, Debug)]
+pub struct PublishedDiagnostics {
+    pub uri: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// LSP 的位置（`character`）转成 tiec 的位置（`column`），两者都按 UTF-16 计
+fn position(value: &Value) -> Option<Position> {
+    Some(Position {
+        line: value.get("line")?.as_u64()? as usize,
+        column: value.get("character")?.as_u64()? as usize,
+    })
+}
+
+fn range(value: &Value) -> Option<Range> {
+    Some(Range { start: position(value.get("start")?)?, end: position(value.get("end")?)? })
+}
+
+/// `textDocument/publishDiagnostics` 的参数；严重程度换算成 tiec 的诊断等级
+pub fn parse_diagnostics(params: &Value) -> Option<PublishedDiagnostics> {
+    let uri = params.get("uri")?.as_str()?.to_string();
+    let diagnostics = params
+        .get("diagnostics")?
+        .as_array()?
+        .iter()
+        .filter_map(|diagnostic| {
+            let level = match diagnostic.get("severity").and_then(Value::as_u64).unwrap_or(1) {
+                1 => 3,
+                2 => 2,
+                3 => 1,
+                _ => 0,
+            };
+            let key = match diagnostic.get("code") {
+                Some(Value::String(code)) => code.clone(),
+                Some(Value::Number(code)) => code.to_string(),
+                _ => String::new(),
+            };
+            Some(Diagnostic {
+                uri: uri.clone(),
+                range: range(diagnostic.get("range")?)?,
+                key,
+                message: diagnostic.get("message")?.as_str()?.to_string(),
+                level,
+            })
+        })
+        .collect();
+    Some(PublishedDiagnostics { uri, diagnostics })
+}
+
+/// `textDocument/definition` 的结果：单个位置、位置数组或 `LocationLink` 数组
+pub fn parse_locations(result: &Value) -> Vec<Location> {
+    let items = match result {
+        Value::Array(items) => items.iter().collect(),
+        Value::Null => Vec::new(),
+        item => vec![item],
+    };
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let uri = item.get("uri").or_else(|| item.get("targetUri"))?.as_str()?.to_string();
+            let target = item.get("range").or_else(|| item.get("targetSelectionRange"))?;
+            Some(Location { uri, range: range(target)? })
+        })
+        .collect()
+}
+
+/// `textDocument/hover` 结果中的文档，统一成 Markdown
+pub fn parse_hover(result: &Value) -> String {
+    fn marked(value: &Value) -> String {
+        match value {
+            Value::String(text) => text.clone(),
+            Value::Array(items) => items.iter().map(marked).filter(|text| !text.is_empty()).collect::<Vec<_>>().join("\n\n"),
+            Value::Object(object) => {
+                let text = object.get("value").and_then(Value::as_str).unwrap_or_default();
+                match object.get("language").and_then(Value::as_str) {
+                    Some(language) => format!("```{}\n{}\n```", language, text),
+                    None => text.to_string(),
+                }
+            }
+            _ => String::new(),
+        }
+    }
+    result.get("contents").map(marked).unwrap_or_default()
+}
+
+/// 服务器发来的请求的回复；编辑器不支持的功能一律回复空结果
+fn reply_to_server(method: &str, params: &Value) -> Value {
+    match method {
+        "workspace/configuration" => {
+            let count = params.get("items").and_then(Value::as_array).map_or(0, Vec::len);
+            Value::Array(vec![Value::Null; count])
+        }
+        _ => Value::Null,
+    }
+}
+
+type PendingRequests = Arc<Mutex<HashMap<i64, flume::Sender<Result<Value>>>>>;
+type DiagnosticSubscribers = Arc<Mutex<Vec<flume::Sender<PublishedDiagnostics>>>>;
+
+/// 一个正在运行的服务器进程。消息由写线程发出，读线程把回复交给等待中的请求、把诊断推送给订阅者
+pub struct StdioClient {
+    name: String,
+    child: Mutex<Child>,
+    outgoing: flume::Sender<Vec<u8>>,
+    pending: PendingRequests,
+    next_id: AtomicI64,
+    alive: Arc<AtomicBool>,
+    started: Instant,
+}
+
+impl StdioClient {
+    /// 启动服务器并完成初始化握手
+    fn start(command: &ServerCommand, root_uri: &str, diagnostics: DiagnosticSubscribers) -> Result<Self> {
+        let mut child = Command::new(&command.program)
+            .args(&command.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to spawn {}", command.program))?;
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("missing stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("missing stdout"))?;
+        let stderr = child.stderr.take().ok_or_else(|| anyhow!("missing stderr"))?;
+
+        let name = command.program.clone();
+        let (outgoing, queue) = flume::unbounded::<Vec<u8>>();
+        let pending: PendingRequests = Arc::default();
+        let alive = Arc::new(AtomicBool::new(true));
+
+        std::thread::spawn(move || write_loop(stdin, queue));
+        {
+            let name = name.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    log_channel(OutputChannel::Lsp, format!("[{}] {}", name, line));
+                }
+            });
+        }
+        {
+            let (name, pending, alive, outgoing) = (name.clone(), pending.clone(), alive.clone(), outgoing.clone());
+            std::thread::spawn(move || read_loop(&name, stdout, &pending, &diagnostics, &outgoing, &alive));
+        }
+
+        let client = Self { name, child: Mutex::new(child), outgoing, pending, next_id: AtomicI64::new(1), alive, started: Instant::now() };
+        client.request_with_timeout("initialize", initialize_params(root_uri), INITIALIZE_TIMEOUT)?;
+        client.notify("initialized", json!({}))?;
+        Ok(client)
+    }
+
+    /// 进程仍在运行且输出没有关闭
+    pub fn is_alive(&self) -> bool {
+        if !self.alive.load(Ordering::SeqCst) {
+            return false;
+        }
+        let exited = self.child.lock().map(|mut child| child.try_wait().ok().flatten().is_some()).unwrap_or(true);
+        if exited {
+            self.alive.store(false, Ordering::SeqCst);
+        }
+        !exited
+    }
+
+    fn send(&self, message: Value) -> Result<()> {
+        self.outgoing.send(encode_message(&message)).map_err(|_| anyhow!("{} is not running", self.name))
+    }
+
+    pub fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// 发出请求并阻塞等待回复，应在后台线程调用
+    pub fn request(&self, method: &str, params: Value) -> Result<Value> {
+        self.request_with_timeout(method, params, REQUEST_TIMEOUT)
+    }
+
+    fn request_with_timeout(&self, method: &str, params: Value, timeout: Duration) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = flume::bounded(1);
+        self.pending.lock().map_err(|_| anyhow!("pending requests poisoned"))?.insert(id, sender);
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(flume::RecvTimeoutError::Disconnected) => Err(anyhow!("{} exited during {}", self.name, method)),
+            Err(flume::RecvTimeoutError::Timeout) => {
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.remove(&id);
+                }
+                self.notify("$/cancelRequest", json!({ "id": id })).ok();
+                Err(anyhow!("{} timed out on {}", self.name, method))
+            }
+        }
+    }
+
+    /// 请求服务器正常退出，不等待回复
+    fn shutdown(&self) {
+        self.notify("exit", Value::Null).ok();
+    }
+}
+
+impl Drop for StdioClient {
+    fn drop(&mut self) {
+        self.shutdown();
+        if let Ok(mut child) = self.child.lock() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+}
+
+fn initialize_params(root_uri: &str) -> Value {
+    json!({
+        "processId": std::process::id(),
+        "rootUri": root_uri,
+        "workspaceFolders": [{ "uri": root_uri, "name": root_uri.rsplit('/').next().unwrap_or(root_uri) }],
+        "capabilities": {
+            "textDocument": {
+                "synchronization": { "dynamicRegistration": false, "didSave": false },
+                "completion": { "completionItem": { "snippetSupport": false } },
+                "hover": { "contentFormat": ["markdown", "plaintext"] },
+                "definition": { "linkSupport": true },
+                "publishDiagnostics": { "relatedInformation": false }
+            },
+            "workspace": { "configuration": true, "workspaceFolders": true }
+        }
+    })
+}
+
+fn write_loop(mut stdin: ChildStdin, queue: flume::Receiver<Vec<u8>>) {
+    for bytes in queue.iter() {
+        if stdin.write_all(&bytes).and_then(|_| stdin.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+fn read_loop(
+    name: &str,
+    stdout: impl Read,
+    pending: &PendingRequests,
+    diagnostics: &DiagnosticSubscribers,
+    outgoing: &flume::Sender<Vec<u8>>,
+    alive: &AtomicBool,
+) {
+    let mut reader = BufReader::new(stdout);
+    loop {
+        let message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(err) => {
+                log_channel(OutputChannel::Lsp, format!("Failed to read from {}: {}", name, err));
+                break;
+            }
+        };
+        let method = message.get("method").and_then(Value::as_str);
+        let id = message.get("id");
+        match (method, id) {
+            // 服务器发来的请求
+            (Some(method), Some(id)) => {
+                let result = reply_to_server(method, message.get("params").unwrap_or(&Value::Null));
+                outgoing.send(encode_message(&json!({ "jsonrpc": "2.0", "id": id, "result": result }))).ok();
+            }
+            (Some("textDocument/publishDiagnostics"), None) => {
+                let Some(published) = message.get("params").and_then(parse_diagnostics) else {
+                    continue;
+                };
+                if let Ok(mut subscribers) = diagnostics.lock() {
+                    subscribers.retain(|subscriber| subscriber.send(published.clone()).is_ok());
+                }
+            }
+            (Some(_), None) => {}
+            (None, Some(id)) => {
+                let Some(id) = id.as_i64() else {
+                    continue;
+                };
+                let Some(sender) = pending.lock().ok().and_then(|mut pending| pending.remove(&id)) else {
+                    continue;
+                };
+                let result = match message.get("error") {
+                    Some(error) => Err(anyhow!(
+                        "{}: {}",
+                        name,
+                        error.get("message").and_then(Value::as_str).unwrap_or("request failed")
+                    )),
+                    None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                };
+                sender.send(result).ok();
+            }
+            (None, None) => {}
+        }
+    }
+    alive.store(false, Ordering::SeqCst);
+    // 等待中的请求随发送端一起丢弃，立即以错误返回
+    if let Ok(mut pending) = pending.lock() {
+        pending.clear();
+    }
+}
+
+/// 第 `failures` 次连续启动失败或崩溃后等待多久再重启：1 秒起每次翻倍，最多 [`MAX_RESTART_DELAY`]
+pub fn restart_delay(failures: u32) -> Duration {
+    Duration::from_secs(1u64 << failures.saturating_sub(1).min(5)).min(MAX_RESTART_DELAY)
+}
+
+/// 编辑器中打开的文档，重启服务器后按最新内容重新打开
+struct OpenDocument {
+    language_id: String,
+    version: i32,
+    text: String,
+    /// 打开这份文档的编辑器数
+    editors: usize,
+}
+
+#[derive(Default)]
+struct ServerState {
+    client: Option<Arc<StdioClient>>,
+    starting: bool,
+    failures: u32,
+    retry_at: Option<Instant>,
+    documents: HashMap<String, OpenDocument>,
+}
+
+/// 一个项目中的一个语言服务器：按需在后台启动，退出后按退避时间重启
+pub struct LanguageServer {
+    command: ServerCommand,
+    root_uri: String,
+    state: Mutex<ServerState>,
+    diagnostics: DiagnosticSubscribers,
+    /// 每份文档最近一次推送的诊断，新的订阅者先收到这些
+    latest: Arc<Mutex<HashMap<String, PublishedDiagnostics>>>,
+}
+
+static SERVERS: Mutex<Vec<Arc<LanguageServer>>> = Mutex::new(Vec::new());
+
+/// 项目 `root_uri` 中用 `command` 启动的服务器，所有编辑器共享同一个
+pub fn shared_server(root_uri: &str, command: &ServerCommand) -> Arc<LanguageServer> {
+    let mut servers = SERVERS.lock().unwrap_or_else(|err| err.into_inner());
+    if let Some(server) = servers.iter().find(|server| server.root_uri == root_uri && &server.command == command) {
+        return server.clone();
+    }
+    let latest: Arc<Mutex<HashMap<String, PublishedDiagnostics>>> = Arc::default();
+    let diagnostics: DiagnosticSubscribers = Arc::default();
+    // 第一个订阅者记录最近的诊断
+    let (sender, receiver) = flume::unbounded::<PublishedDiagnostics>();
+    if let Ok(mut subscribers) = diagnostics.lock() {
+        subscribers.push(sender);
+    }
+    {
+        let latest = latest.clone();
+        std::thread::spawn(move || {
+            for published in receiver.iter() {
+                if let Ok(mut latest) = latest.lock() {
+                    latest.insert(published.uri.clone(), published);
+                }
+            }
+        });
+    }
+    let server = Arc::new(LanguageServer {
+        command: command.clone(),
+        root_uri: root_uri.to_string(),
+        state: Mutex::default(),
+        diagnostics,
+        latest,
+    });
+    servers.push(server.clone());
+    server
+}
+
+impl LanguageServer {
+    fn state(&self) -> std::sync::MutexGuard<'_, ServerState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// 正在运行的客户端。服务器未启动或已退出时在后台（重新）启动，期间返回 None；不会阻塞
+    pub fn client(self: &Arc<Self>) -> Option<Arc<StdioClient>> {
+        let mut state = self.state();
+        if let Some(client) = state.client.clone() {
+            if client.is_alive() {
+                return Some(client);
+            }
+            state.client = None;
+            if client.started.elapsed() >= STABLE_AFTER {
+                state.failures = 0;
+            }
+            state.failures += 1;
+            let delay = restart_delay(state.failures);
+            state.retry_at = Some(Instant::now() + delay);
+            log_channel(
+                OutputChannel::Lsp,
+                format!("Language server {} exited, restarting in {}s", self.command.program, delay.as_secs()),
+            );
+        }
+        if state.starting || state.retry_at.is_some_and(|at| Instant::now() < at) {
+            return None;
+        }
+        state.starting = true;
+        let server = self.clone();
+        std::thread::spawn(move || server.start());
+        None
+    }
+
+    fn start(&self) {
+        let result = StdioClient::start(&self.command, &self.root_uri, self.diagnostics.clone());
+        let mut state = self.state();
+        state.starting = false;
+        match result {
+            Ok(client) => {
+                log_channel(OutputChannel::Lsp, format!("Language server {} started", self.command.program));
+                for (uri, document) in &state.documents {
+                    client.notify("textDocument/didOpen", did_open_params(uri, document)).ok();
+                }
+                state.retry_at = None;
+                state.client = Some(Arc::new(client));
+            }
+            Err(err) => {
+                state.failures += 1;
+                let delay = restart_delay(state.failures);
+                state.retry_at = Some(Instant::now() + delay);
+                log_channel(
+                    OutputChannel::Lsp,
+                    format!("Failed to start language server {}: {:#}, retrying in {}s", self.command.program, err, delay.as_secs()),
+                );
+            }
+        }
+    }
+
+    /// 订阅诊断推送；先收到各文档最近一次的诊断
+    pub fn subscribe_diagnostics(&self) -> flume::Receiver<PublishedDiagnostics> {
+        let (sender, receiver) = flume::unbounded();
+        if let Ok(latest) = self.latest.lock() {
+            for published in latest.values() {
+                sender.send(published.clone()).ok();
+            }
+        }
+        if let Ok(mut subscribers) = self.diagnostics.lock() {
+            subscribers.push(sender);
+        }
+        receiver
+    }
+
+    pub fn did_open(self: &Arc<Self>, uri: &str, language_id: &str, text: &str) {
+        let mut state = self.state();
+        if let Some(document) = state.documents.get_mut(uri) {
+            document.editors += 1;
+            return;
+        }
+        let document = OpenDocument { language_id: language_id.to_string(), version: 1, text: text.to_string(), editors: 1 };
+        if let Some(client) = state.client.clone() {
+            client.notify("textDocument/didOpen", did_open_params(uri, &document)).ok();
+        }
+        state.documents.insert(uri.to_string(), document);
+        drop(state);
+        self.client();
+    }
+
+    /// 以全量文本同步文档
+    pub fn did_change(self: &Arc<Self>, uri: &str, text: &str) {
+        let mut state = self.state();
+        let Some(document) = state.documents.get_mut(uri) else {
+            return;
+        };
+        if document.text == text {
+            return;
+        }
+        document.version += 1;
+        document.text = text.to_string();
+        let params = json!({
+            "textDocument": { "uri": uri, "version": document.version },
+            "contentChanges": [{ "text": text }],
+        });
+        drop(state);
+        if let Some(client) = self.client() {
+            client.notify("textDocument/didChange", params).ok();
+        }
+    }
+
+    /// 最后一个编辑器关闭文档时通知服务器
+    pub fn did_close(&self, uri: &str) {
+        let mut state = self.state();
+        let Some(document) = state.documents.get_mut(uri) else {
+            return;
+        };
+        document.editors -= 1;
+        if document.editors > 0 {
+            return;
+        }
+        state.documents.remove(uri);
+        if let Some(client) = state.client.clone() {
+            client.notify("textDocument/didClose", json!({ "textDocument": { "uri": uri } })).ok();
+        }
+    }
+
+    fn request(self: &Arc<Self>, method: &str, uri: &str, line: usize, character: usize) -> Result<Value> {
+        let client = self.client().ok_or_else(|| anyhow!("{} is not running", self.command.program))?;
+        client.request(
+            method,
+            json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } }),
+        )
+    }
+
+    /// 补全结果：`CompletionItem` 数组或带 `items` 的 `CompletionList`
+    pub fn completion(self: &Arc<Self>, uri: &str, line: usize, character: usize) -> Result<Value> {
+        self.request("textDocument/completion", uri, line, character)
+    }
+
+    /// 悬停文档（Markdown）；没有文档时为空
+    pub fn hover(self: &Arc<Self>, uri: &str, line: usize, character: usize) -> Result<String> {
+        Ok(parse_hover(&self.request("textDocument/hover", uri, line, character)?))
+    }
+
+    pub fn definition(self: &Arc<Self>, uri: &str, line: usize, character: usize) -> Result<Vec<Location>> {
+        Ok(parse_locations(&self.request("textDocument/definition", uri, line, character)?))
+    }
+}
+
+fn did_open_params(uri: &str, document: &OpenDocument) -> Value {
+    json!({
+        "textDocument": {
+            "uri": uri,
+            "languageId": document.language_id,
+            "version": document.version,
+            "text": document.text,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 每次最多读出一个字节，模拟管道中分段到达的数据
+    struct Trickle(Cursor<Vec<u8>>);
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_frames_messages_with_content_length() {
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "result": { "contents": "变量 甲 : 整数" } });
+        let encoded = encode_message(&message);
+        let header = format!("Content-Length: {}\r\n\r\n", message.to_string().len());
+        assert!(encoded.starts_with(header.as_bytes()));
+
+        // 连续的多条消息、多字节字符、额外的 Content-Type 头和分段到达的数据
+        let second = json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} });
+        let mut stream = encoded.clone();
+        let body = second.to_string();
+        stream.extend_from_slice(
+            format!("content-length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{}", body.len(), body)
+                .as_bytes(),
+        );
+        let mut reader = BufReader::new(Trickle(Cursor::new(stream)));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(second));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+
+        assert!(read_message(&mut Cursor::new(b"Content-Type: x\r\n\r\n{}".to_vec())).is_err());
+        assert!(read_message(&mut Cursor::new(b"Content-Length: 10\r\n\r\n{}".to_vec())).is_err());
+        assert!(read_message(&mut Cursor::new(b"Content-Length: 2\r\n".to_vec())).is_err());
+        assert!(read_message(&mut Cursor::new(b"Content-Length: abc\r\n\r\n{}".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_parses_server_results_and_settings() {
+        let published = parse_diagnostics(&json!({
+            "uri": "file:///p/main.rs",
+            "diagnostics": [
+                { "range": { "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 7 } }, "severity": 1, "code": "E0425", "message": "cannot find value" },
+                { "range": { "start": { "line": 2, "character": 0 }, "end": { "line": 2, "character": 1 } }, "severity": 2, "message": "unused" },
+            ]
+        }))
+        .unwrap();
+        assert_eq!(published.uri, "file:///p/main.rs");
+        let levels: Vec<_> = published.diagnostics.iter().map(|d| (d.level, d.key.as_str(), d.range.start.column)).collect();
+        assert_eq!(levels, vec![(3, "E0425", 4), (2, "", 0)]);
+
+        let location = json!({ "uri": "file:///p/lib.rs", "range": { "start": { "line": 3, "character": 2 }, "end": { "line": 3, "character": 5 } } });
+        let link = json!([{ "targetUri": "file:///p/a.py", "targetRange": {}, "targetSelectionRange": location["range"] }]);
+        assert_eq!(parse_locations(&location)[0].uri, "file:///p/lib.rs");
+        assert_eq!(parse_locations(&link)[0].range.start.line, 3);
+        assert!(parse_locations(&Value::Null).is_empty());
+
+        assert_eq!(parse_hover(&json!({ "contents": { "kind": "markdown", "value": "**x**" } })), "**x**");
+        assert_eq!(parse_hover(&json!({ "contents": [{ "language": "rust", "value": "fn f()" }, "doc"] })), "```rust\nfn f()\n```\n\ndoc");
+        assert_eq!(parse_hover(&Value::Null), "");
+
+        let (servers, warnings) = parse_servers(r#"{ "lsp": { "servers": { ".PY": "pylsp -v", "rs": [], "go": 1 } } }"#);
+        assert_eq!(servers.get("py"), Some(&ServerCommand::new("pylsp", &["-v"])));
+        assert!(!servers.contains_key("rs"));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(parse_servers("{}").0, default_servers());
+        let (servers, _) = parse_servers(r#"{ "lsp": { "servers": { "ts": "${CARGO_MANIFEST_DIR}/bin/tsserver --stdio" } } }"#);
+        let program = format!("{}/bin/tsserver", env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(servers.get("ts"), Some(&ServerCommand::new(&program, &["--stdio"])));
+
+        assert_eq!(restart_delay(1), Duration::from_secs(1));
+        assert_eq!(restart_delay(3), Duration::from_secs(4));
+        assert_eq!(restart_delay(20), MAX_RESTART_DELAY);
+        assert_eq!(reply_to_server("workspace/configuration", &json!({ "items": [{}, {}] })), json!([null, null]));
+    }
+}
@@ -0,0 +1,118 @@
+//! tiec 调用记录：在项目设置中打开 `tiec.logFfiCalls` 后，每次调用编译器库都会记下函数名、参数、
+//! 耗时和返回值，写入日志并保留最近的若干条。调用过慢时给出警告；库内发生访问冲突时，
+//! 把最近的调用写进 crash.log，便于找出是哪次调用出的问题。关闭时每次调用只多一次判断
+
+use log::{info, warn};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 保留的最近调用条数
+pub const RECENT_CALLS: usize = 32;
+/// 超过这个耗时的调用记为慢调用
+pub const SLOW_CALL: Duration = Duration::from_millis(200);
+/// 参数超过这个长度（字节）时截断
+const MAX_ARGS_LEN: usize = 200;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static RECENT: Mutex<VecDeque<CallRecord>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallRecord {
+    pub name: &'static str,
+    pub args: String,
+    pub duration: Duration,
+    /// 返回值或返回码；调用中发生访问冲突时为 None
+    pub result: Option<String>,
+}
+
+impl fmt::Display for CallRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = self.result.as_deref().unwrap_or("access violation");
+        write!(f, "{}({}) -> {} in {:?}", self.name, self.args, result, self.duration)
+    }
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    if ENABLED.swap(enabled, Ordering::Relaxed) != enabled {
+        info!("tiec FFI call logging {}", if enabled { "enabled" } else { "disabled" });
+    }
+}
+
+/// 截断到不超过 `max` 字节，不切断字符
+fn truncate_args(mut args: String, max: usize) -> String {
+    if args.len() > max {
+        let mut end = max;
+        while !args.is_char_boundary(end) {
+            end -= 1;
+        }
+        let total = args.len();
+        args.truncate(end);
+        args.push_str(&format!("…({} bytes)", total));
+    }
+    args
+}
+
+/// 记下一次调用，写入日志；慢调用记为警告
+pub fn record(name: &'static str, args: String, duration: Duration, result: Option<String>) {
+    let record = CallRecord { name, args: truncate_args(args, MAX_ARGS_LEN), duration, result };
+    if duration >= SLOW_CALL {
+        warn!("Slow tiec call: {}", record);
+    } else {
+        info!("tiec call: {}", record);
+    }
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    if recent.len() == RECENT_CALLS {
+        recent.pop_front();
+    }
+    recent.push_back(record);
+}
+
+/// 最近的调用，最早的在前
+pub fn recent_calls() -> Vec<CallRecord> {
+    RECENT.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+}
+
+/// 写入崩溃报告的调用记录，没有记录时提示如何打开
+pub fn crash_report() -> String {
+    let recent = recent_calls();
+    if recent.is_empty() {
+        return "No tiec calls recorded (set tiec.logFfiCalls in .tiecode/settings.json to record them)".to_string();
+    }
+    let mut report = format!("Last {} tiec calls:", recent.len());
+    for call in recent {
+        report.push_str(&format!("\n  {}", call));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_recent_calls_and_truncates_args() {
+        assert_eq!(truncate_args("短参数".to_string(), 10), "短参数");
+        // 截断位置落在多字节字符中间时退到字符边界
+        assert_eq!(truncate_args("结绳编程".to_string(), 7), "结绳…(12 bytes)");
+
+        for i in 0..RECENT_CALLS + 3 {
+            record("hover", format!("{{\"line\":{}}}", i), Duration::from_millis(1), Some("0x1".to_string()));
+        }
+        record("lint_all", "x".repeat(300), SLOW_CALL, None);
+        let recent = recent_calls();
+        assert_eq!(recent.len(), RECENT_CALLS);
+        assert_eq!(recent[0].args, "{\"line\":4}");
+        let last = recent.last().unwrap();
+        assert_eq!(last.args.len(), MAX_ARGS_LEN + "…(300 bytes)".len());
+        assert!(last.to_string().starts_with("lint_all(xxx"));
+        assert!(last.to_string().ends_with("-> access violation in 200ms"));
+        assert!(crash_report().starts_with(&format!("Last {} tiec calls:\n  hover(", RECENT_CALLS)));
+    }
+}
@@ -0,0 +1,154 @@
+//! tiec 调用保护：调用逐个进行，调用中的 panic（Windows 上还有库内的访问冲突）变成调用失败；
+//! 一次调用迟迟不返回时，看门狗记下卡住的调用，之后的调用直接失败，不再排队等待
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// 超过这个时长仍未返回的调用视为卡住
+pub const HUNG_AFTER: Duration = Duration::from_secs(60);
+
+/// 看门狗检查的间隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 一次调用失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// 库内访问冲突，只有 Windows 上能捕获
+    AccessViolation(String),
+    /// 调用中发生 panic
+    Panic(String),
+}
+
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AccessViolation(detail) => write!(f, "access violation: {}", detail),
+            Self::Panic(message) => write!(f, "panicked: {}", message),
+        }
+    }
+}
+
+/// 执行一次 tiec 调用，库内出错时返回 [`Fault`] 而不是让程序崩溃
+#[cfg(windows)]
+pub fn protect<R>(f: impl FnMut() -> R) -> Result<R, Fault> {
+    match catch_unwind(AssertUnwindSafe(|| microseh::try_seh(f))) {
+        Ok(Ok(result)) => Ok(result),
+        Ok(Err(e)) => Err(Fault::AccessViolation(format!("{:?}", e))),
+        Err(payload) => Err(Fault::Panic(panic_message(payload.as_ref()))),
+    }
+}
+
+/// 执行一次 tiec 调用。没有 SEH 的平台上库内的段错误仍会结束进程，这里只能把 panic 变成调用失败
+#[cfg(not(windows))]
+pub fn protect<R>(mut f: impl FnMut() -> R) -> Result<R, Fault> {
+    catch_unwind(AssertUnwindSafe(&mut f)).map_err(|payload| Fault::Panic(panic_message(payload.as_ref())))
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+struct InFlight {
+    name: &'static str,
+    since: Instant,
+    reported: bool,
+}
+
+/// 同一时间只允许一次 tiec 调用，并记下正在进行的调用
+pub struct CallGate {
+    current: Mutex<Option<InFlight>>,
+    idle: Condvar,
+    hung_after: Duration,
+}
+
+/// 占用中的调用权，释放时唤醒等待的调用
+pub struct GateGuard<'a>(&'a CallGate);
+
+impl CallGate {
+    pub const fn new(hung_after: Duration) -> Self {
+        Self { current: Mutex::new(None), idle: Condvar::new(), hung_after }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<InFlight>> {
+        self.current.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// 等前一次调用结束后占用调用权；前一次调用已经卡住时直接返回错误
+    pub fn enter(&self, name: &'static str) -> Result<GateGuard<'_>, String> {
+        let mut current = self.lock();
+        while let Some(call) = current.as_ref() {
+            let elapsed = call.since.elapsed();
+            if elapsed >= self.hung_after {
+                return Err(format!("tiec is unresponsive: {} has been running for {}s", call.name, elapsed.as_secs()));
+            }
+            current = self
+                .idle
+                .wait_timeout(current, self.hung_after - elapsed)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        *current = Some(InFlight { name, since: Instant::now(), reported: false });
+        Ok(GateGuard(self))
+    }
+
+    /// 已经卡住且还没报告过的调用及其运行时长，每次调用只报告一次
+    pub fn take_hung(&self) -> Option<(&'static str, Duration)> {
+        let mut current = self.lock();
+        let call = current.as_mut()?;
+        let elapsed = call.since.elapsed();
+        if call.reported || elapsed < self.hung_after {
+            return None;
+        }
+        call.reported = true;
+        Some((call.name, elapsed))
+    }
+
+    /// 启动看门狗线程，调用卡住时交给 `report`
+    pub fn watch(&'static self, report: fn(&'static str, Duration)) {
+        let spawned = std::thread::Builder::new().name("tiec-watchdog".to_string()).spawn(move || loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            if let Some((name, elapsed)) = self.take_hung() {
+                report(name, elapsed);
+            }
+        });
+        if let Err(e) = spawned {
+            log::warn!("Failed to spawn tiec watchdog: {}", e);
+        }
+    }
+}
+
+impl Drop for GateGuard<'_> {
+    fn drop(&mut self) {
+        *self.0.lock() = None;
+        self.0.idle.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panics_become_faults_and_hung_calls_fail_fast() {
+        assert_eq!(protect(|| 1), Ok(1));
+        let fault = protect(|| -> i32 { panic!("bad pointer") });
+        assert_eq!(fault, Err(Fault::Panic("bad pointer".to_string())));
+
+        let gate = CallGate::new(Duration::from_millis(50));
+        drop(gate.enter("hover").unwrap());
+        let _compile = gate.enter("compile_files").unwrap();
+        assert_eq!(gate.take_hung(), None);
+        std::thread::sleep(Duration::from_millis(60));
+
+        // 卡住的调用只报告一次，之后的调用不再等待
+        assert_eq!(gate.take_hung().map(|(name, _)| name), Some("compile_files"));
+        assert_eq!(gate.take_hung(), None);
+        let error = gate.enter("lint_file").err().unwrap();
+        assert!(error.contains("compile_files"), "{}", error);
+    }
+}
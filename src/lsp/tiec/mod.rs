@@ -1,6 +1,10 @@
 #![allow(dead_code)]
 
+pub mod ffi_log;
+pub mod guard;
+pub mod settings;
 pub mod types;
+pub mod worker;
 pub mod wrapper;
 #[cfg(test)]
 mod test;
@@ -100,6 +104,12 @@ pub struct TiecLib {
     pub tc_ide_service_rename_source: unsafe extern "C" fn(ide_handle: RawHandle, uri: *const c_char, new_uri: *const c_char) -> TcError,
     pub tc_ide_service_complete: unsafe extern "C" fn(ide_handle: RawHandle, params_json: *const c_char) -> *const c_char,
     pub tc_ide_service_hover: unsafe extern "C" fn(ide_handle: RawHandle, params_json: *const c_char) -> *const c_char,
+    pub tc_ide_service_find_definition: unsafe extern "C" fn(ide_handle: RawHandle, params_json: *const c_char) -> *const c_char,
+    pub tc_ide_service_prepare_rename: unsafe extern "C" fn(ide_handle: RawHandle, params_json: *const c_char) -> *const c_char,
+    pub tc_ide_service_rename: unsafe extern "C" fn(ide_handle: RawHandle, params_json: *const c_char, new_name: *const c_char) -> *const c_char,
+    pub tc_ide_service_signature_help: unsafe extern "C" fn(ide_handle: RawHandle, params_json: *const c_char) -> *const c_char,
+    pub tc_ide_service_smart_enter: unsafe extern "C" fn(ide_handle: RawHandle, params_json: *const c_char) -> *const c_char,
+    pub tc_ide_service_generate_event: unsafe extern "C" fn(ide_handle: RawHandle, params_json: *const c_char) -> *const c_char,
     pub tc_ide_service_lint_file: unsafe extern "C" fn(ide_handle: RawHandle, uri: *const c_char) -> *const c_char,
     pub tc_ide_service_lint_all: unsafe extern "C" fn(ide_handle: RawHandle) -> *const c_char,
     pub tc_ide_service_highlight: unsafe extern "C" fn(ide_handle: RawHandle, uri: *const c_char) -> *const c_char,
@@ -141,6 +151,12 @@ impl TiecLib {
             tc_ide_service_rename_source: load_sym!(b"tc_ide_service_rename_source"),
             tc_ide_service_complete: load_sym!(b"tc_ide_service_complete"),
             tc_ide_service_hover: load_sym!(b"tc_ide_service_hover"),
+            tc_ide_service_find_definition: load_sym!(b"tc_ide_service_find_definition"),
+            tc_ide_service_prepare_rename: load_sym!(b"tc_ide_service_prepare_rename"),
+            tc_ide_service_rename: load_sym!(b"tc_ide_service_rename"),
+            tc_ide_service_signature_help: load_sym!(b"tc_ide_service_signature_help"),
+            tc_ide_service_smart_enter: load_sym!(b"tc_ide_service_smart_enter"),
+            tc_ide_service_generate_event: load_sym!(b"tc_ide_service_generate_event"),
             tc_ide_service_lint_file: load_sym!(b"tc_ide_service_lint_file"),
             tc_ide_service_lint_all: load_sym!(b"tc_ide_service_lint_all"),
             tc_ide_service_highlight: load_sym!(b"tc_ide_service_highlight"),
@@ -0,0 +1,310 @@
+//! 项目设置文件中的 `tiec` 一节：包名、目标平台、类库搜索路径和调用记录开关
+//!
+//! ```json
+//! { "tiec": { "packageName": "demo", "platform": "android", "searchPaths": ["~/tiecode/libs"] } }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::lsp::tiec::types::{CompilerOptions, SearchPrefixes};
+
+/// 项目设置文件相对项目根目录的位置
+pub const PROJECT_SETTINGS_FILE: &str = ".tiecode/settings.json";
+
+/// 编译器的目标平台，数值与 tiec 的 `platform` 选项一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetPlatform {
+    Android,
+    Harmony,
+    Linux,
+    Windows,
+    Ios,
+    Apple,
+    Html,
+}
+
+impl TargetPlatform {
+    pub const ALL: [TargetPlatform; 7] = [
+        TargetPlatform::Android,
+        TargetPlatform::Harmony,
+        TargetPlatform::Linux,
+        TargetPlatform::Windows,
+        TargetPlatform::Ios,
+        TargetPlatform::Apple,
+        TargetPlatform::Html,
+    ];
+
+    pub fn code(self) -> i32 {
+        match self {
+            TargetPlatform::Android => 1,
+            TargetPlatform::Harmony => 2,
+            TargetPlatform::Linux => 3,
+            TargetPlatform::Windows => 4,
+            TargetPlatform::Ios => 5,
+            TargetPlatform::Apple => 6,
+            TargetPlatform::Html => 7,
+        }
+    }
+
+    /// 传给编译器 `target` 选项的名称
+    pub fn target_name(self) -> &'static str {
+        match self {
+            TargetPlatform::Android => "android",
+            TargetPlatform::Harmony => "harmony",
+            TargetPlatform::Linux => "linux",
+            TargetPlatform::Windows => "windows",
+            TargetPlatform::Ios => "ios",
+            TargetPlatform::Apple => "apple",
+            TargetPlatform::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TiecSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<TargetPlatform>,
+    /// 类库搜索路径；相对路径基于项目根目录，支持 `~` 和环境变量
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_paths: Vec<String>,
+    /// 记录每次调用编译器库的参数、耗时和返回值，用于排查卡顿和崩溃
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub log_ffi_calls: bool,
+}
+
+/// 设置文件中的一处错误，行列从 0 开始，列按字符计
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct ProjectSettings {
+    #[serde(default)]
+    tiec: Option<TiecSettings>,
+}
+
+impl TiecSettings {
+    /// 读取项目设置；没有设置文件或其中没有 `tiec` 一节时返回 None
+    pub fn load(project_root: &Path) -> Result<Option<Self>> {
+        let path = project_root.join(PROJECT_SETTINGS_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read project settings at {:?}", path))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Option<Self>> {
+        let settings: ProjectSettings =
+            serde_json::from_str(content).with_context(|| "Failed to parse project settings")?;
+        Ok(settings.tiec)
+    }
+
+    pub fn resolved_search_paths(&self, project_root: &Path) -> Vec<PathBuf> {
+        self.search_paths
+            .iter()
+            .map(|raw| project_root.join(crate::paths::expand_path(raw)))
+            .collect()
+    }
+
+    /// 检查设置是否可用，返回面向用户的错误说明
+    pub fn validate(&self, project_root: &Path) -> Vec<String> {
+        self.issues(project_root).into_iter().map(|(_, message)| message).collect()
+    }
+
+    /// 每条错误连同设置文件中出错的文本，用来定位到所在行
+    fn issues(&self, project_root: &Path) -> Vec<(String, String)> {
+        let mut issues = Vec::new();
+        if let Some(name) = &self.package_name {
+            if let Err(err) = validate_package_name(name) {
+                issues.push(("\"packageName\"".to_string(), err));
+            }
+        }
+        for (raw, path) in self.search_paths.iter().zip(self.resolved_search_paths(project_root)) {
+            if !path.is_dir() {
+                let quoted = serde_json::to_string(raw).unwrap_or_default();
+                issues.push((quoted, format!("搜索路径不存在：{}", raw)));
+            }
+        }
+        issues
+    }
+
+    /// 把本节写回设置文件的内容，保留其他键；`content` 为空时新建
+    pub fn merge_into(&self, content: &str) -> Result<String> {
+        let mut root = if content.trim().is_empty() {
+            serde_json::Value::Object(Default::default())
+        } else {
+            serde_json::from_str(content).with_context(|| "Failed to parse project settings")?
+        };
+        let object = root.as_object_mut().context("Project settings must be a JSON object")?;
+        object.insert("tiec".to_string(), serde_json::to_value(self)?);
+        let mut merged = serde_json::to_string_pretty(&root)?;
+        merged.push('\n');
+        Ok(merged)
+    }
+
+    /// 把设置写入编译选项，未设置的项保留默认值
+    pub fn apply_to(&self, options: &mut CompilerOptions, project_root: &Path) {
+        if let Some(name) = &self.package_name {
+            options.package_name = Some(name.clone());
+        }
+        if let Some(platform) = self.platform {
+            options.platform = Some(platform.code());
+            options.target = Some(platform.target_name().to_string());
+        }
+        if !self.search_paths.is_empty() {
+            let lib = self
+                .resolved_search_paths(project_root)
+                .into_iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect();
+            options
+                .search_prefixes
+                .get_or_insert_with(SearchPrefixes::default)
+                .lib = Some(lib);
+        }
+    }
+}
+
+/// 检查设置文件的 tiec 一节，返回每处错误及其在文件中的位置；格式有误时只返回解析错误
+pub fn check_project_settings(content: &str, project_root: &Path) -> Vec<SettingsError> {
+    let settings = match serde_json::from_str::<ProjectSettings>(content) {
+        Ok(settings) => settings.tiec,
+        Err(err) => {
+            return vec![SettingsError {
+                line: err.line().saturating_sub(1),
+                column: err.column().saturating_sub(1),
+                message: format!("项目设置格式有误：{}", err),
+            }]
+        }
+    };
+    let Some(settings) = settings else {
+        return Vec::new();
+    };
+    settings
+        .issues(project_root)
+        .into_iter()
+        .map(|(needle, message)| {
+            let (line, column) = content
+                .find(&needle)
+                .map(|offset| {
+                    let before = &content[..offset];
+                    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+                    (before.matches('\n').count(), before[line_start..].chars().count())
+                })
+                .unwrap_or((0, 0));
+            SettingsError { line, column, message }
+        })
+        .collect()
+}
+
+/// 包名由点分隔的若干段组成，每段以字母或下划线开头，只含字母、数字和下划线
+fn validate_package_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("包名不能为空".to_string());
+    }
+    let valid_segment = |segment: &str| {
+        segment.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+    };
+    if name.split('.').all(valid_segment) {
+        Ok(())
+    } else {
+        Err(format!("包名无效：{}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_apply_tiec_section() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir(root.path().join("libs")).unwrap();
+        let settings = TiecSettings::parse(
+            r#"{ "tiec": { "packageName": "com.demo", "platform": "windows", "searchPaths": ["libs"], "logFfiCalls": true } }"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(settings.validate(root.path()).is_empty());
+        assert!(settings.log_ffi_calls);
+
+        let mut options = CompilerOptions {
+            target: Some("android".to_string()),
+            ..Default::default()
+        };
+        settings.apply_to(&mut options, root.path());
+        assert_eq!(options.package_name.as_deref(), Some("com.demo"));
+        assert_eq!(options.platform, Some(4));
+        assert_eq!(options.target.as_deref(), Some("windows"));
+        let lib = options.search_prefixes.unwrap().lib.unwrap();
+        assert_eq!(lib, vec![root.path().join("libs").to_string_lossy().to_string()]);
+
+        assert_eq!(TiecSettings::parse("{}").unwrap(), None);
+        assert!(TiecSettings::parse(r#"{ "tiec": { "platform": "dos" } }"#).is_err());
+    }
+
+    #[test]
+    fn test_validate_reports_bad_package_and_missing_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let settings = TiecSettings {
+            package_name: Some("1demo.x".to_string()),
+            platform: None,
+            search_paths: vec!["missing".to_string()],
+            log_ffi_calls: false,
+        };
+        assert_eq!(
+            settings.validate(root.path()),
+            vec!["包名无效：1demo.x".to_string(), "搜索路径不存在：missing".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_check_locates_errors_in_file() {
+        let root = tempfile::tempdir().unwrap();
+        let content = "{\n  \"tiec\": {\n    \"packageName\": \"1demo\",\n    \"searchPaths\": [\"libs\", \"missing\"]\n  }\n}\n";
+        std::fs::create_dir(root.path().join("libs")).unwrap();
+        let errors = check_project_settings(content, root.path());
+        assert_eq!(
+            errors,
+            vec![
+                SettingsError { line: 2, column: 4, message: "包名无效：1demo".to_string() },
+                SettingsError { line: 3, column: 28, message: "搜索路径不存在：missing".to_string() },
+            ]
+        );
+
+        let errors = check_project_settings("{\n  \"tiec\": {\n    \"platform\": \"dos\"\n  }\n}", root.path());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 2);
+        assert!(check_project_settings("{ \"editor\": {} }", root.path()).is_empty());
+    }
+
+    #[test]
+    fn test_merge_keeps_other_sections() {
+        let settings = TiecSettings {
+            package_name: Some("demo".to_string()),
+            platform: Some(TargetPlatform::Linux),
+            search_paths: vec!["libs".to_string()],
+            log_ffi_calls: false,
+        };
+        let merged = settings
+            .merge_into(r#"{ "editor": { "tabSize": 2 }, "tiec": { "platform": "html" } }"#)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(value["editor"]["tabSize"], 2);
+        assert_eq!(TiecSettings::parse(&merged).unwrap(), Some(settings.clone()));
+        assert_eq!(TiecSettings::parse(&settings.merge_into("").unwrap()).unwrap(), Some(settings.clone()));
+        assert!(settings.merge_into("[]").is_err());
+    }
+}
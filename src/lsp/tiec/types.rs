@@ -1,12 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Position {
     pub line: usize,
     pub column: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Range {
     pub start: Position,
     pub end: Position,
@@ -170,7 +170,7 @@ pub struct CompletionItem {
     pub extra_edits: Option<Vec<TextEdit>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TextEdit {
     pub range: Range,
@@ -194,6 +194,87 @@ pub struct HoverResult {
     pub text: String,
 }
 
+// --- Definition ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefinitionResult {
+    pub identifier_range: Range,
+    pub location: Location,
+}
+
+// --- Rename ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameSymbolInfo {
+    pub name: String,
+    pub range: Range,
+    pub kind: i32, // ElementKind
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameResult {
+    /// 文件 URI 到该文件中的编辑
+    #[serde(default)]
+    pub project_edit: std::collections::HashMap<String, Vec<TextEdit>>,
+}
+
+// --- Signature Help ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureHelpParams {
+    pub uri: String,
+    pub position: Position,
+    pub trigger_char: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureHelpResult {
+    pub signature: String,
+    #[serde(default)]
+    pub active_parameter: String,
+}
+
+// --- Code Action ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeActionResult {
+    #[serde(default)]
+    pub actions: Vec<CodeActionItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeActionItem {
+    pub title: String,
+    #[serde(default)]
+    pub edits: Vec<TextEdit>,
+}
+
+// --- Smart Enter ---
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartEnterResult {
+    pub kind: i32, // SmartEnterKind: 0 未知, 1 选择文件, 2 选择枚举常量, 3 真/假开关
+    pub range: Range,
+    /// 含 `%s` 的替换文本格式
+    #[serde(default)]
+    pub replace_format: String,
+    #[serde(default)]
+    pub enums: Vec<String>,
+    #[serde(default)]
+    pub is_true: bool,
+}
+
 // --- Highlight ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -221,6 +302,17 @@ pub struct SourceElementNode {
     pub children: Vec<SourceElementNode>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceElementsResult {
+    pub elements: Vec<WorkspaceElement>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceElement {
+    pub uri: String,
+    pub element: SourceElement,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SourceElement {
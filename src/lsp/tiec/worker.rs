@@ -0,0 +1,170 @@
+//! tiec 工作线程：所有 tiec 调用都在这一个线程上按投递顺序执行，界面线程只投递请求，
+//! 在之后的 `cx.spawn` 中取回结果，编译或查错再慢也不会卡住窗口。
+//!
+//! 请求带一个槽位名（如 `"completion"`、`"lint"`），同一槽位有更新的请求时，尚未开始执行的旧请求
+//! 直接作废。tiec 没有中途取消调用的接口，已经开始的调用会执行完，结果由调用方按文档版本丢弃
+
+use log::warn;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// 工作线程的句柄，可以复制后交给后台任务；所有句柄都释放后线程退出
+#[derive(Clone)]
+pub struct TiecWorker {
+    jobs: flume::Sender<Job>,
+    /// 每个槽位最新一次请求的序号
+    slots: Arc<Mutex<HashMap<&'static str, u64>>>,
+    /// 排队和正在执行的任务数
+    pending: Arc<AtomicUsize>,
+}
+
+impl TiecWorker {
+    pub fn spawn() -> Self {
+        let (jobs, queue) = flume::unbounded::<Job>();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let counter = pending.clone();
+        std::thread::Builder::new()
+            .name("tiec-worker".to_string())
+            .spawn(move || {
+                for job in queue.iter() {
+                    if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        warn!("tiec worker job panicked");
+                    }
+                    counter.fetch_sub(1, Ordering::SeqCst);
+                }
+            })
+            .expect("failed to spawn tiec worker thread");
+        Self { jobs, slots: Arc::default(), pending }
+    }
+
+    /// 排队执行 `f`，不关心结果
+    pub fn post(&self, f: impl FnOnce() + Send + 'static) {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.jobs.send(Box::new(f)).is_err() {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// 排队执行 `f`，结果从返回的 [`Reply`] 中取回；`slot` 中之后又有请求时，尚未开始的这次请求作废
+    pub fn request<R: Send + 'static>(&self, slot: &'static str, f: impl FnOnce() -> R + Send + 'static) -> Reply<R> {
+        let generation = self.bump(slot);
+        let slots = self.slots.clone();
+        let (sender, receiver) = flume::bounded(1);
+        self.post(move || {
+            let latest = slots.lock().unwrap_or_else(PoisonError::into_inner).get(slot).copied();
+            if latest == Some(generation) {
+                sender.send(f()).ok();
+            }
+        });
+        Reply(receiver)
+    }
+
+    /// 作废 `slot` 中尚未开始的请求
+    pub fn cancel(&self, slot: &'static str) {
+        self.bump(slot);
+    }
+
+    fn bump(&self, slot: &'static str) -> u64 {
+        let mut slots = self.slots.lock().unwrap_or_else(PoisonError::into_inner);
+        let generation = slots.entry(slot).or_default();
+        *generation += 1;
+        *generation
+    }
+
+    /// 还有排队或正在执行的任务；需要立即得到结果的调用方据此改用本地推断
+    pub fn is_busy(&self) -> bool {
+        self.pending.load(Ordering::SeqCst) > 0
+    }
+}
+
+/// 一次请求的结果；请求作废时取回 None
+pub struct Reply<R>(flume::Receiver<R>);
+
+impl<R> Reply<R> {
+    pub async fn recv(self) -> Option<R> {
+        self.0.recv_async().await.ok()
+    }
+
+    /// 阻塞等待结果，只在后台线程调用
+    pub fn wait(self) -> Option<R> {
+        self.0.recv().ok()
+    }
+
+    /// 最多等待 `timeout`，供需要同步结果的界面调用；超时也返回 None
+    pub fn wait_timeout(self, timeout: Duration) -> Option<R> {
+        self.0.recv_timeout(timeout).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_superseded_requests_are_skipped_while_edits_flood_in() {
+        let worker = TiecWorker::spawn();
+        // 文档的版本号和内容，只在工作线程上修改
+        let document = Arc::new(Mutex::new((0u64, String::new())));
+
+        // 一次查错正在执行时连续投递编辑和新的查错
+        let (started, wait_started) = flume::bounded(1);
+        let (release, wait_release) = flume::bounded::<()>(1);
+        let in_flight = {
+            let document = document.clone();
+            worker.request("lint", move || {
+                started.send(()).ok();
+                wait_release.recv().ok();
+                document.lock().unwrap().0
+            })
+        };
+        wait_started.recv().unwrap();
+        assert!(worker.is_busy());
+
+        let mut replies = Vec::new();
+        for version in 1..=500u64 {
+            let edited = document.clone();
+            worker.post(move || {
+                let mut document = edited.lock().unwrap();
+                document.0 = version;
+                document.1.push('变');
+            });
+            let linted = document.clone();
+            replies.push(worker.request("lint", move || {
+                let document = linted.lock().unwrap();
+                (document.0, document.1.chars().count() as u64)
+            }));
+        }
+        release.send(()).unwrap();
+        assert_eq!(in_flight.wait(), Some(0));
+
+        // 界面一侧只应用不早于已应用版本的结果；只有最后一次查错真正执行，且看到了全部编辑
+        let mut applied = 0;
+        let mut delivered = 0;
+        for reply in replies {
+            if let Some((version, length)) = reply.wait() {
+                assert!(version >= applied);
+                assert_eq!(version, length);
+                applied = version;
+                delivered += 1;
+            }
+        }
+        assert_eq!((applied, delivered), (500, 1));
+
+        // 作废后的请求不执行；任务出错不影响之后的任务
+        let (release, wait_release) = flume::bounded::<()>(1);
+        worker.post(move || {
+            wait_release.recv().ok();
+        });
+        let cancelled = worker.request("completion", || 1);
+        worker.cancel("completion");
+        worker.post(|| panic!("job failed"));
+        release.send(()).unwrap();
+        assert_eq!(cancelled.wait(), None);
+        assert_eq!(worker.request("completion", || 2).wait_timeout(Duration::from_secs(5)), Some(2));
+    }
+}
@@ -1,10 +1,73 @@
 use std::ffi::{CStr, CString};
-use std::sync::Arc;
+use std::sync::{Arc, Once};
 use anyhow::{Result, anyhow};
 use libc::c_char;
 use log::{debug, info};
+use std::time::{Duration, Instant};
 use super::{TiecLib, RawHandle, TcError};
+use super::ffi_log;
+use super::guard::{protect, CallGate, Fault, HUNG_AFTER};
 use super::types::*;
+use crate::output::{log_channel, OutputChannel};
+
+/// tiec 库不保证可以在多个线程上同时调用，所有调用逐个进行
+static GATE: CallGate = CallGate::new(HUNG_AFTER);
+static WATCHDOG: Once = Once::new();
+
+/// 调用 tiec 库：库内出错时返回错误；打开调用记录时记下参数、耗时和返回值，`args` 只在记录时求值
+fn call_guarded<R: std::fmt::Debug>(
+    name: &'static str,
+    args: impl FnOnce() -> String,
+    f: impl FnMut() -> R,
+) -> Result<R> {
+    WATCHDOG.call_once(|| GATE.watch(report_hung));
+    let _call = GATE.enter(name).map_err(|e| anyhow!(e))?;
+    if !ffi_log::enabled() {
+        return protect(f).map_err(|fault| call_failed(name, fault));
+    }
+    let args = args();
+    let start = Instant::now();
+    let result = protect(f);
+    ffi_log::record(name, args, start.elapsed(), result.as_ref().ok().map(|r| format!("{:?}", r)));
+    result.map_err(|fault| call_failed(name, fault))
+}
+
+/// 调用失败时把最近的调用写进 crash.log
+fn call_failed(name: &str, fault: Fault) -> anyhow::Error {
+    let message = format!("{} caused {}", name, fault);
+    crate::panic_handler::write_crash_log(&format!("{}\n{}", message, ffi_log::crash_report()));
+    anyhow!(message)
+}
+
+/// 看门狗发现调用卡住时输出到语言服务频道，并把最近的调用写进 crash.log
+fn report_hung(name: &'static str, elapsed: Duration) {
+    let message = format!("tiec call {} has not returned after {}s", name, elapsed.as_secs());
+    crate::panic_handler::write_crash_log(&format!("{}\n{}", message, ffi_log::crash_report()));
+    log_channel(OutputChannel::Lsp, message);
+}
+
+/// 检查返回 [`TcError`] 的调用
+fn check(name: &str, err: TcError) -> Result<()> {
+    if err != TcError::Ok {
+        return Err(anyhow!("{} failed: {:?}", name, err));
+    }
+    Ok(())
+}
+
+/// 读取返回字符串的调用的结果
+fn reply_text<'a>(name: &str, ptr: *const c_char) -> Result<&'a str> {
+    if ptr.is_null() {
+        return Err(anyhow!("{} returned null", name));
+    }
+    Ok(unsafe { CStr::from_ptr(ptr).to_str()? })
+}
+
+/// 读取返回 JSON 的调用的结果
+fn reply_json<R: serde::de::DeserializeOwned>(name: &str, ptr: *const c_char) -> Result<R> {
+    let text = reply_text(name, ptr)?;
+    debug!("{} result: {}", name, text);
+    Ok(serde_json::from_str(text)?)
+}
 
 pub struct TiecLoader {
     lib: Arc<TiecLib>,
@@ -18,11 +81,11 @@ impl TiecLoader {
 
     pub fn create_context(&self, options: &serde_json::Value) -> Result<TiecContext> {
         let json = serde_json::to_string(options)?;
-        let c_json = CString::new(json)?;
+        let c_json = CString::new(json.as_str())?;
         
-        let handle = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_create_context)(c_json.as_ptr()) 
-        }).map_err(|e| anyhow!("create_context caused access violation: {:?}", e))?;
+        let handle = call_guarded("create_context", || json.clone(), || unsafe {
+            (self.lib.tc_create_context)(c_json.as_ptr())
+        })?;
 
         if handle == 0 {
             return Err(anyhow!("Failed to create context"));
@@ -43,7 +106,7 @@ pub struct TiecContext {
 impl Drop for TiecContext {
     fn drop(&mut self) {
         // We can't easily return Result from drop, but we can catch panic to avoid crashing
-        let _ = microseh::try_seh(|| unsafe {
+        let _ = call_guarded("free_context", String::new, || unsafe {
             (self.lib.tc_free_context)(self.handle);
         });
     }
@@ -51,9 +114,9 @@ impl Drop for TiecContext {
 
 impl TiecContext {
     pub fn create_ide_service(self) -> Result<TiecIdeService> {
-        let handle = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_create_ide_service)(self.handle) 
-        }).map_err(|e| anyhow!("create_ide_service caused access violation: {:?}", e))?;
+        let handle = call_guarded("create_ide_service", String::new, || unsafe {
+            (self.lib.tc_create_ide_service)(self.handle)
+        })?;
 
         if handle == 0 {
             return Err(anyhow!("Failed to create IDE service"));
@@ -89,42 +152,44 @@ impl TiecIdeService {
     fn call_json_op<T: serde::Serialize, R: serde::de::DeserializeOwned>(
         &self, 
         op: unsafe extern "C" fn(RawHandle, *const c_char) -> *const c_char, 
-        op_name: &str,
+        op_name: &'static str,
         params: &T
     ) -> Result<R> {
         let json = serde_json::to_string(params)?;
         debug!("Calling {} with handle {:?} and json: {}", op_name, self.handle, json);
         
-        let c_json = CString::new(json)?;
+        let c_json = CString::new(json.as_str())?;
         
-        let result_ptr = unsafe { op(self.handle, c_json.as_ptr()) };
+        let result_ptr = call_guarded(op_name, || json.clone(), || unsafe { op(self.handle, c_json.as_ptr()) })?;
         debug!("{} returned ptr: {:?}", op_name, result_ptr);
-        if result_ptr.is_null() {
-            return Err(anyhow!("{} returned null", op_name));
-        }
-        
-        let result_str = unsafe { CStr::from_ptr(result_ptr).to_str()? };
-        debug!("{} result: {}", op_name, result_str);
-        let result: R = serde_json::from_str(result_str)?;
-        
-        Ok(result)
+        reply_json(op_name, result_ptr)
+    }
+
+    /// 以文档 uri 或搜索关键字为参数、返回 JSON 的调用
+    fn call_str_op<R: serde::de::DeserializeOwned>(
+        &self,
+        op: unsafe extern "C" fn(RawHandle, *const c_char) -> *const c_char,
+        op_name: &'static str,
+        arg: &str,
+    ) -> Result<R> {
+        let c_arg = CString::new(arg)?;
+        let result_ptr = call_guarded(op_name, || arg.to_string(), || unsafe { op(self.handle, c_arg.as_ptr()) })?;
+        reply_json(op_name, result_ptr)
     }
 
     fn call_void_op<T: serde::Serialize>(
         &self, 
         op: unsafe extern "C" fn(RawHandle, *const c_char) -> TcError, 
-        op_name: &str,
+        op_name: &'static str,
         params: &T
     ) -> Result<()> {
         let json = serde_json::to_string(params)?;
         debug!("Calling {} with handle {:?} and json: {}", op_name, self.handle, json);
         
-        let c_json = CString::new(json)?;
+        let c_json = CString::new(json.as_str())?;
         
-        let err = unsafe { op(self.handle, c_json.as_ptr()) };
-        if err != TcError::Ok {
-            return Err(anyhow!("{} failed with error: {:?}", op_name, err));
-        }
+        let err = call_guarded(op_name, || json.clone(), || unsafe { op(self.handle, c_json.as_ptr()) })?;
+        check(op_name, err)?;
         debug!("{} success", op_name);
         Ok(())
     }
@@ -141,17 +206,15 @@ impl TiecIdeService {
             .map(|s| s.as_ptr())
             .collect();
             
-        let err = microseh::try_seh(|| unsafe { 
+        let err = call_guarded("compile_files", || files.join(", "), || unsafe {
             (self.lib.tc_ide_service_compile_files)(
                 self.handle, 
                 c_ptrs.len(), 
                 c_ptrs.as_ptr()
-            ) 
-        }).map_err(|e| anyhow!("compile_files caused access violation: {:?}", e))?;
+            )
+        })?;
         
-        if err != TcError::Ok {
-            return Err(anyhow!("compile_files failed: {:?}", err));
-        }
+        check("compile_files", err)?;
         debug!("compile_files success");
         Ok(())
     }
@@ -162,14 +225,10 @@ impl TiecIdeService {
         
         let c_uri = CString::new(uri)?;
         let c_text = CString::new(new_text)?;
-        let err = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_edit_source)(self.handle, c_uri.as_ptr(), c_text.as_ptr()) 
-        }).map_err(|e| anyhow!("edit_source caused access violation: {:?}", e))?;
-
-        if err != TcError::Ok {
-            return Err(anyhow!("edit_source failed: {:?}", err));
-        }
-        Ok(())
+        let err = call_guarded("edit_source", || format!("{}, {} bytes", uri, new_text.len()), || unsafe {
+            (self.lib.tc_ide_service_edit_source)(self.handle, c_uri.as_ptr(), c_text.as_ptr())
+        })?;
+        check("edit_source", err)
     }
 
     pub fn edit_source_incremental(&self, uri: &str, change: &TextChange) -> Result<()> {
@@ -178,57 +237,41 @@ impl TiecIdeService {
         info!("Debug JSON for edit_source_incremental: uri={}, json={}", uri, json);
         
         let c_uri = CString::new(uri)?;
-        let c_json = CString::new(json)?;
+        let c_json = CString::new(json.as_str())?;
         
-        let err = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_edit_source_incremental)(self.handle, c_uri.as_ptr(), c_json.as_ptr()) 
-        }).map_err(|e| anyhow!("edit_source_incremental caused access violation: {:?}", e))?;
-
-        if err != TcError::Ok {
-            return Err(anyhow!("edit_source_incremental failed: {:?}", err));
-        }
-        Ok(())
+        let err = call_guarded("edit_source_incremental", || format!("{}, {}", uri, json), || unsafe {
+            (self.lib.tc_ide_service_edit_source_incremental)(self.handle, c_uri.as_ptr(), c_json.as_ptr())
+        })?;
+        check("edit_source_incremental", err)
     }
     
     pub fn create_source(&self, uri: &str, initial_text: &str) -> Result<()> {
         debug!("create_source: {}", uri);
         let c_uri = CString::new(uri)?;
         let c_text = CString::new(initial_text)?;
-        let err = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_create_source)(self.handle, c_uri.as_ptr(), c_text.as_ptr()) 
-        }).map_err(|e| anyhow!("create_source caused access violation: {:?}", e))?;
-
-        if err != TcError::Ok {
-            return Err(anyhow!("create_source failed: {:?}", err));
-        }
-        Ok(())
+        let err = call_guarded("create_source", || format!("{}, {} bytes", uri, initial_text.len()), || unsafe {
+            (self.lib.tc_ide_service_create_source)(self.handle, c_uri.as_ptr(), c_text.as_ptr())
+        })?;
+        check("create_source", err)
     }
 
     pub fn delete_source(&self, uri: &str) -> Result<()> {
         debug!("delete_source: {}", uri);
         let c_uri = CString::new(uri)?;
-        let err = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_delete_source)(self.handle, c_uri.as_ptr()) 
-        }).map_err(|e| anyhow!("delete_source caused access violation: {:?}", e))?;
-
-        if err != TcError::Ok {
-            return Err(anyhow!("delete_source failed: {:?}", err));
-        }
-        Ok(())
+        let err = call_guarded("delete_source", || uri.to_string(), || unsafe {
+            (self.lib.tc_ide_service_delete_source)(self.handle, c_uri.as_ptr())
+        })?;
+        check("delete_source", err)
     }
     
     pub fn rename_source(&self, uri: &str, new_uri: &str) -> Result<()> {
         debug!("rename_source: {} -> {}", uri, new_uri);
         let c_uri = CString::new(uri)?;
         let c_new_uri = CString::new(new_uri)?;
-        let err = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_rename_source)(self.handle, c_uri.as_ptr(), c_new_uri.as_ptr()) 
-        }).map_err(|e| anyhow!("rename_source caused access violation: {:?}", e))?;
-
-        if err != TcError::Ok {
-            return Err(anyhow!("rename_source failed: {:?}", err));
-        }
-        Ok(())
+        let err = call_guarded("rename_source", || format!("{} -> {}", uri, new_uri), || unsafe {
+            (self.lib.tc_ide_service_rename_source)(self.handle, c_uri.as_ptr(), c_new_uri.as_ptr())
+        })?;
+        check("rename_source", err)
     }
 
     pub fn complete(&self, params: &serde_json::Value) -> Result<CompletionResult> {
@@ -236,147 +279,106 @@ impl TiecIdeService {
         debug!("Calling complete with handle {:?} and json: {}", self.handle, json);
         info!("Debug JSON for complete: {}", json);
         
-        let c_json = CString::new(json)?;
+        let c_json = CString::new(json.as_str())?;
         
         info!("Invoking FFI complete...");
         
-        let result_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_complete)(self.handle, c_json.as_ptr()) 
-        }).map_err(|e| anyhow!("complete caused access violation: {:?}", e))?;
+        let result_ptr = call_guarded("complete", || json.clone(), || unsafe {
+            (self.lib.tc_ide_service_complete)(self.handle, c_json.as_ptr())
+        })?;
 
         info!("FFI complete returned ptr: {:?}", result_ptr);
-        
-        if result_ptr.is_null() {
-            return Err(anyhow!("complete returned null"));
-        }
-        
-        let result_str = unsafe { CStr::from_ptr(result_ptr).to_str()? };
-        debug!("complete result: {}", result_str);
-        let result: CompletionResult = serde_json::from_str(result_str)?;
-        
-        Ok(result)
+        reply_json("complete", result_ptr)
     }
 
     pub fn hover(&self, params: &CursorParams) -> Result<HoverResult> {
+        self.call_json_op(self.lib.tc_ide_service_hover, "hover", params)
+    }
+    
+    pub fn find_definition(&self, params: &CursorParams) -> Result<DefinitionResult> {
+        self.call_json_op(self.lib.tc_ide_service_find_definition, "find_definition", params)
+    }
+
+    pub fn prepare_rename(&self, params: &CursorParams) -> Result<RenameSymbolInfo> {
+        self.call_json_op(self.lib.tc_ide_service_prepare_rename, "prepare_rename", params)
+    }
+
+    pub fn rename(&self, params: &CursorParams, new_name: &str) -> Result<RenameResult> {
         let json = serde_json::to_string(params)?;
-        let c_json = CString::new(json)?;
-        
-        let res_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_hover)(self.handle, c_json.as_ptr()) 
-        }).map_err(|e| anyhow!("hover caused access violation: {:?}", e))?;
+        let c_json = CString::new(json.as_str())?;
+        let c_name = CString::new(new_name)?;
+        let res_ptr = call_guarded("rename", || format!("{}, {:?}", json, new_name), || unsafe {
+            (self.lib.tc_ide_service_rename)(self.handle, c_json.as_ptr(), c_name.as_ptr())
+        })?;
+        reply_json("rename", res_ptr)
+    }
 
-        if res_ptr.is_null() {
-            return Err(anyhow!("hover returned null"));
-        }
-        let res_str = unsafe { CStr::from_ptr(res_ptr).to_str()? };
-        Ok(serde_json::from_str(res_str)?)
+    pub fn signature_help(&self, params: &SignatureHelpParams) -> Result<SignatureHelpResult> {
+        self.call_json_op(self.lib.tc_ide_service_signature_help, "signature_help", params)
     }
-    
-    pub fn lint_file(&self, uri: &str) -> Result<LintResult> {
-        let c_uri = CString::new(uri)?;
-        
-        let res_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_lint_file)(self.handle, c_uri.as_ptr()) 
-        }).map_err(|e| anyhow!("lint_file caused access violation: {:?}", e))?;
 
-        if res_ptr.is_null() {
-            return Err(anyhow!("lint_file returned null"));
-        }
-        let res_str = unsafe { CStr::from_ptr(res_ptr).to_str()? };
-        Ok(serde_json::from_str(res_str)?)
+    pub fn smart_enter(&self, params: &CursorParams) -> Result<SmartEnterResult> {
+        self.call_json_op(self.lib.tc_ide_service_smart_enter, "smart_enter", params)
+    }
+
+    pub fn generate_event(&self, params: &CursorParams) -> Result<CodeActionResult> {
+        self.call_json_op(self.lib.tc_ide_service_generate_event, "generate_event", params)
+    }
+
+    pub fn lint_file(&self, uri: &str) -> Result<LintResult> {
+        self.call_str_op(self.lib.tc_ide_service_lint_file, "lint_file", uri)
     }
     
     pub fn lint_all(&self) -> Result<LintResult> {
-        let res_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_lint_all)(self.handle) 
-        }).map_err(|e| anyhow!("lint_all caused access violation: {:?}", e))?;
-
-        if res_ptr.is_null() {
-            return Err(anyhow!("lint_all returned null"));
-        }
-        let res_str = unsafe { CStr::from_ptr(res_ptr).to_str()? };
-        Ok(serde_json::from_str(res_str)?)
+        let res_ptr = call_guarded("lint_all", String::new, || unsafe {
+            (self.lib.tc_ide_service_lint_all)(self.handle)
+        })?;
+        reply_json("lint_all", res_ptr)
     }
 
     pub fn highlight(&self, uri: &str) -> Result<HighlightResult> {
-        let c_uri = CString::new(uri)?;
-        
-        let res_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_highlight)(self.handle, c_uri.as_ptr()) 
-        }).map_err(|e| anyhow!("highlight caused access violation: {:?}", e))?;
-
-        if res_ptr.is_null() {
-            return Err(anyhow!("highlight returned null"));
-        }
-        let res_str = unsafe { CStr::from_ptr(res_ptr).to_str()? };
-        Ok(serde_json::from_str(res_str)?)
+        self.call_str_op(self.lib.tc_ide_service_highlight, "highlight", uri)
     }
     
     pub fn format(&self, uri: &str) -> Result<serde_json::Value> {
         // Return Value as specific struct is not fully defined in docs yet
-        let c_uri = CString::new(uri)?;
-        
-        let res_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_format)(self.handle, c_uri.as_ptr()) 
-        }).map_err(|e| anyhow!("format caused access violation: {:?}", e))?;
-
-        if res_ptr.is_null() {
-            return Err(anyhow!("format returned null"));
-        }
-        let res_str = unsafe { CStr::from_ptr(res_ptr).to_str()? };
-        Ok(serde_json::from_str(res_str)?)
+        self.call_str_op(self.lib.tc_ide_service_format, "format", uri)
     }
     
     pub fn source_elements(&self, uri: &str) -> Result<SourceElementsResult> {
-        let c_uri = CString::new(uri)?;
-        
-        let res_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_source_elements)(self.handle, c_uri.as_ptr()) 
-        }).map_err(|e| anyhow!("source_elements caused access violation: {:?}", e))?;
-
-        if res_ptr.is_null() {
-            return Err(anyhow!("source_elements returned null"));
-        }
-        let res_str = unsafe { CStr::from_ptr(res_ptr).to_str()? };
-        Ok(serde_json::from_str(res_str)?)
+        self.call_str_op(self.lib.tc_ide_service_source_elements, "source_elements", uri)
     }
     
+    pub fn workspace_elements(&self, keyword: &str) -> Result<WorkspaceElementsResult> {
+        self.call_str_op(self.lib.tc_ide_service_workspace_elements, "workspace_elements", keyword)
+    }
+
     // Static utility methods that don't need service handle but are part of lib
     
     pub fn format_text(&self, doc_text: &str) -> Result<String> {
         let c_text = CString::new(doc_text)?;
         
-        let res_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_format_text)(c_text.as_ptr()) 
-        }).map_err(|e| anyhow!("format_text caused access violation: {:?}", e))?;
-
-        if res_ptr.is_null() {
-            return Err(anyhow!("format_text returned null"));
-        }
-        let res_str = unsafe { CStr::from_ptr(res_ptr).to_str()? };
-        Ok(res_str.to_string())
+        let res_ptr = call_guarded("format_text", || format!("{} bytes", doc_text.len()), || unsafe {
+            (self.lib.tc_ide_service_format_text)(c_text.as_ptr())
+        })?;
+        Ok(reply_text("format_text", res_ptr)?.to_string())
     }
     
     pub fn newline(&self, doc_text: &str, line: usize, column: usize) -> Result<String> {
         let c_text = CString::new(doc_text)?;
         
-        let res_ptr = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_newline)(c_text.as_ptr(), line, column) 
-        }).map_err(|e| anyhow!("newline caused access violation: {:?}", e))?;
-
-        if res_ptr.is_null() {
-            return Err(anyhow!("newline returned null"));
-        }
-        let res_str = unsafe { CStr::from_ptr(res_ptr).to_str()? };
-        Ok(res_str.to_string())
+        let res_ptr = call_guarded("newline", || format!("{} bytes, {}:{}", doc_text.len(), line, column), || unsafe {
+            (self.lib.tc_ide_service_newline)(c_text.as_ptr(), line, column)
+        })?;
+        Ok(reply_text("newline", res_ptr)?.to_string())
     }
     
     pub fn indent_advance(&self, line_text: &str, column: usize) -> Result<i32> {
         let c_text = CString::new(line_text)?;
         
-        let result = microseh::try_seh(|| unsafe { 
-            (self.lib.tc_ide_service_indent_advance)(c_text.as_ptr(), column) 
-        }).map_err(|e| anyhow!("indent_advance caused access violation: {:?}", e))?;
+        let result = call_guarded("indent_advance", || format!("{:?}, {}", line_text, column), || unsafe {
+            (self.lib.tc_ide_service_indent_advance)(c_text.as_ptr(), column)
+        })?;
 
         Ok(result)
     }
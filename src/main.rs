@@ -1,37 +1,115 @@
 #![cfg_attr(all(not(test), not(debug_assertions)), windows_subsystem = "windows")]
 
+mod autosave;
+mod cli;
+mod command_journal;
 mod component;
+mod discarded;
 mod editor;
+mod editor_settings;
+mod file_guard;
+mod file_watch;
 mod plugin;
 mod lsp;
+mod open_documents;
+mod output;
 mod panic_handler;
+mod paths;
+mod session;
+mod startup;
+mod system_open;
+mod tab_mru;
+#[cfg(test)]
+mod test_harness;
+mod workspace;
 
 //DEMO
 
 use component::{
-    command_palette::{CommandPalette, CommandPaletteEvent},
-    file_tree::{file_icon, FileTree, FileTreeEvent},
+    command_palette::{CommandPalette, CommandPaletteEvent, PaletteItem},
+    diff_viewer::{diff_tab_label, diff_tab_path, DiffViewer},
+    file_tree::{FileTree, FileTreeEvent},
+    icon_theme::{file_icon, folder_icon, set_icon_theme, IconTheme, DEFAULT_ICON_THEME},
     modal::modal,
+    output_panel::OutputPanel,
     popover::popover,
     tie_svg::tie_svg,
-    status_bar::StatusBar,
+    git_panel::GitPanelEvent,
+    git_status::GitStatusMap,
+    reference_update::{apply_edits, RenamedSource},
+    search_panel::{search_files, SearchPanel, SearchPanelEvent},
+    status_bar::{StatusBar, StatusBarEvent, DIAGNOSTICS_ITEM},
+    tool_panel::ToolPanelEvent,
+    FocusRing,
 };
 use editor::{
-    Backspace, CodeEditor, CodeEditorEvent, Copy, CtrlShiftTab, Cut, Delete, DeleteLine, Down, Enter, Escape,
-    FindNext, FindPrev, GoToDefinition, FormatDocument, SignatureHelp, Left, Paste, Redo, Right, SelectAll, ShiftTab, Tab, ToggleFind, Undo, Up,
+    Backspace, CodeEditor, CodeEditorEvent, EditorSnapshot, Copy, CtrlShiftTab, Cut, Delete, DeleteLine, Down, Enter, Escape,
+    FindNext, FindPrev, FindReferences, RenameSymbol, GoToDefinition, PeekDefinition, FormatDocument, SignatureHelp, CodeAction, Left, Paste, Redo, Right, SelectAll, SelectWordLeft, SelectWordRight, ShiftTab, Tab, ToggleBlockComment, ToggleFind, ToggleLineComment, Undo, Up,
+    FoldAll, UnfoldAll, FoldLevel1, AddCursorAbove, AddCursorBelow,
+    WordLeft, WordRight, LineStart, LineEnd, DocumentStart, DocumentEnd, SelectLineStart, SelectLineEnd,
+    SelectDocumentStart, SelectDocumentEnd,
     IndentGuideHighlightColor,
+    untitled_name, untitled_path, project_problems,
 };
-use plugin::manager::PluginManager;
-use tiecode_plugin_api::CommandContribution;
+use editor::core::LineEnding;
+use editor::lsp_integration::{text_edit_ranges, LintError, Problem, ProblemSeverity};
+use output::{log_channel, OutputChannel};
+use editor::grammar::{grammar_index_for_asset, sync_plugin_grammars};
+use editor::enter_rules;
+use editor::hover::HoverDelays;
+use editor::overrides::OverrideRules;
+use component::panel_list::FocusRegion;
+use component::tree_watch::rename_path;
+use component::file_clipboard::{ClipboardOp, FileClipboard, PasteOutcome};
+use component::review_panel::{ReviewPanel, ReviewPanelEvent};
+use component::plugin_panel::{PluginPanel, PluginPanelEvent};
+use component::problems_panel::{ProblemsPanel, ProblemsPanelEvent};
+use component::project_settings_panel::{ProjectSettingsPanel, ProjectSettingsPanelEvent};
+use component::references_panel::{ReferencesPanel, ReferencesPanelEvent};
+use session::{Session, TabState, TabView, TreeState};
+use tab_mru::TabMru;
+use startup::StartupTimeline;
+use command_journal::{elapsed_label, CommandJournal, CommandOutcome, JournalEntry, UndoMark};
+use discarded::{DiscardedTab, DiscardedTabs};
+use component::toolbar::{builtin_icon, Toolbar};
+use autosave::Autosave;
+use editor_settings::{EditorSettings, SaveErrorCheck};
+use file_guard::FileKind;
+use file_watch::{GrammarWatcher, OpenFileWatcher};
+use lsp::tiec::settings::{check_project_settings, PROJECT_SETTINGS_FILE};
+use lsp::tiec::types::TextEdit;
+use open_documents::{minimal_edit, OpenDocuments, SharedSync};
+use plugin::editor_host::{ChannelEditorHost, EditorRequest};
+use plugin::keymap::{binding_context, normalize_keystrokes};
+use plugin::settings::PluginSettings;
+use plugin::manager::{
+    ActivationEvent, NativeCommand, PluginInvocation, PluginManager, PluginState, DISABLE_PLUGIN_COMMAND_PREFIX,
+    ICON_THEME_COMMAND_PREFIX,
+};
+use tiecode_plugin_api::{CommandContribution, EditorHost, KeybindingContribution, PluginEvent, FILE_TREE_CONTEXT_MENU};
 use anyhow::Result;
 use gpui::*;
 use log::*;
 use image::GenericImageView;
+use ropey::Rope;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+actions!(
+    start_window,
+    [ShowCommandPalette, QuickOpen, GoToLine, CompileProject, SwitchTab, NewFile, OpenFile, OpenFolder, FocusNextRegion, FocusPreviousRegion, FocusFileTreeFilter, FocusNextElement, FocusPreviousElement]
+);
 
-actions!(start_window, [ShowCommandPalette]);
+/// 插件快捷键触发的命令，与命令面板走同一条执行路径
+#[derive(Clone, PartialEq, Debug, Action)]
+#[action(namespace = start_window, no_json)]
+struct RunCommand(String);
+
+/// ctrl+tab 按住超过该时长才显示最近使用列表，否则视为轻点，直接切回上一个文件
+const TAB_SWITCH_HOLD_DELAY: Duration = Duration::from_millis(150);
 
 struct Assets {
     base: PathBuf,
@@ -65,7 +143,7 @@ static APP_ID: &str = "d8b8e2b1-0c9b-4b7e-8b8a-0c9b4b7e8b8a";
 
 fn default_assets_base() -> PathBuf {
     if let Ok(base) = std::env::var("TIECODE_ASSETS_BASE") {
-        return PathBuf::from(base);
+        return paths::expand_path(&base);
     }
 
     if let Ok(exe_path) = std::env::current_exe() {
@@ -95,6 +173,7 @@ mod windows_console {
 }
 
 fn main() {
+    let process_started = Instant::now();
     #[cfg(windows)]
     unsafe {
         // Set console output code page to UTF-8 (65001) to fix garbled Chinese logs
@@ -102,269 +181,946 @@ fn main() {
     }
 
     panic_handler::init();
-    env_logger::init();
+    output::OutputLogger::init();
+
+    let args = cli::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(2);
+    });
+    // 比较模式下先读取两侧文件，读不到时不打开窗口
+    let diff = args.diff.map(|request| match request.read() {
+        Ok((left, right)) => (request, left, right),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    });
 
     Application::new()
         .with_assets(Assets {
             base: default_assets_base(),
         })
-        .run(|context: &mut App| {
+        .run(move |context: &mut App| {
         info!("tiecode for desktop start success!");
 
-        // 获取平台来确定ctrl还是cmd
-        let ctrl_cmd = cfg!(target_os = "macos").then(|| "cmd").unwrap_or("ctrl");
-
-        let mut bindings = vec![
-            KeyBinding::new("backspace", Backspace, Some("CodeEditor")),
-            KeyBinding::new("delete", Delete, Some("CodeEditor")),
-            KeyBinding::new("left", Left, Some("CodeEditor")),
-            KeyBinding::new("right", Right, Some("CodeEditor")),
-            KeyBinding::new("up", Up, Some("CodeEditor")),
-            KeyBinding::new("down", Down, Some("CodeEditor")),
-            KeyBinding::new("enter", Enter, Some("CodeEditor")),
-            KeyBinding::new("tab", Tab, Some("CodeEditor")),
-            KeyBinding::new("shift-tab", ShiftTab, Some("CodeEditor")),
-            KeyBinding::new("escape", Escape, Some("CodeEditor")),
-            KeyBinding::new("f3", FindNext, Some("CodeEditor")),
-            KeyBinding::new("shift-f3", FindPrev, Some("CodeEditor")),
-            KeyBinding::new("f12", GoToDefinition, Some("CodeEditor")),
-            KeyBinding::new(&format!("{}-shift-space", ctrl_cmd), SignatureHelp, Some("CodeEditor")),
-            KeyBinding::new("shift-alt-f", FormatDocument, Some("CodeEditor")),
-        ];
-
-        // 3. 动态拼接并添加带修饰键的绑定
-        bindings.extend([
-            KeyBinding::new(
-                &format!("{}-shift-k", ctrl_cmd),
-                DeleteLine,
-                Some("CodeEditor"),
-            ),
-            KeyBinding::new(
-                &format!("{}-shift-tab", ctrl_cmd),
-                CtrlShiftTab,
-                Some("CodeEditor"),
-            ),
-            KeyBinding::new(&format!("{}-c", ctrl_cmd), Copy, Some("CodeEditor")),
-            KeyBinding::new(&format!("{}-x", ctrl_cmd), Cut, Some("CodeEditor")),
-            KeyBinding::new(&format!("{}-v", ctrl_cmd), Paste, Some("CodeEditor")),
-            KeyBinding::new(&format!("{}-z", ctrl_cmd), Undo, Some("CodeEditor")),
-            KeyBinding::new(&format!("{}-shift-z", ctrl_cmd), Redo, Some("CodeEditor")),
-            KeyBinding::new(&format!("{}-f", ctrl_cmd), ToggleFind, Some("CodeEditor")),
-            KeyBinding::new(&format!("{}-a", ctrl_cmd), SelectAll, Some("CodeEditor")),
-            KeyBinding::new(&format!("{}-shift-p", ctrl_cmd), ShowCommandPalette, None),
-        ]);
+        let icon_theme = IconTheme::load(&default_assets_base().join(DEFAULT_ICON_THEME), None)
+            .unwrap_or_else(|err| {
+                warn!("{:#}", err);
+                IconTheme::fallback()
+            });
+        context.set_global(icon_theme);
+        editor::grammar::load_grammar_assets(context.asset_source().as_ref());
 
-        // 4. 注册所有绑定
-        context.bind_keys(bindings);
-
-        let bounds = Bounds::centered(None, size(px(1200.0), px(700.0)), context);
-        let _ = context.open_window(
-            WindowOptions {
-                window_bounds: Some(WindowBounds::Windowed(bounds)),
-                titlebar: Some(TitlebarOptions {
-                    appears_transparent: true,
-                    ..TitlebarOptions::default()
-                }),
-                ..WindowOptions::default()
-            },
-            |_, cx| {
-                let editor = cx.new(|cx| CodeEditor::new(cx, None));
-                editor.update(cx, |editor, _cx| {
-                    // Indent guides: disable animation + bold, enable colorful palette.
-                    editor.indent_guides.highlight.animate = false;
-                    editor.indent_guides.thickness.highlighted = editor.indent_guides.thickness.normal;
-                    editor.indent_guides.highlight.colors = IndentGuideHighlightColor::Palette(vec![
-                        rgb(0x4ec9b0),
-                        rgb(0x569cd6),
-                        rgb(0xc586c0),
-                        rgb(0xdcdcaa),
-                        rgb(0xce9178),
-                    ]);
-                    editor.indent_guides.highlight.randomize_palette = true;
-                });
-                /* editor.update(cx, |editor, cx| {
-                     if editor.core.content.len_bytes() != 0 {
-                         return;
-                     }
+        context.bind_keys(key_bindings());
+        open_start_window(context, diff, process_started, true);
+    });
+}
 
-                    let sample = "类 启动类\n{\n    方法 启动方法()\n    {\n        变量 list: 列表<文本> = 新建 列表<文本>()\n        list.\n    }\n}\n";
-                    editor.set_content(sample.to_string(), cx);
-                }); */
-                let file_tree = cx.new(|cx| FileTree::new(None, cx));
-                let command_palette = cx.new(CommandPalette::new);
-                let image_viewer = cx.new(|cx| crate::component::image_viewer::ImageViewer::new(cx));
-                let markdown_viewer = cx.new(|cx| crate::component::markdown_viewer::MarkdownViewer::new(cx));
-                let tool_panel = {
-                    let ft = file_tree.clone();
-                    cx.new(|cx| crate::component::tool_panel::ToolPanel::new(ft, cx))
-                };
-                let git_panel = cx.new(|cx| crate::component::git_panel::GitPanel::new(cx));
-                let plugin_manager = cx.new(|_| PluginManager::new());
-                let status_bar = cx.new(|cx| StatusBar::new(editor.clone(), cx));
-                
-                plugin_manager.update(cx, |manager: &mut PluginManager, _cx| {
-                    manager.discover_plugins();
-                    manager.command_registry.register(CommandContribution {
-                        command: "file_tree.toggle".to_string(),
-                        title: "Toggle File Tree".to_string(),
-                        category: Some("View".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.undo".to_string(),
-                        title: "Undo".to_string(),
-                        category: Some("Edit".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.redo".to_string(),
-                        title: "Redo".to_string(),
-                        category: Some("Edit".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.cut".to_string(),
-                        title: "Cut".to_string(),
-                        category: Some("Edit".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.copy".to_string(),
-                        title: "Copy".to_string(),
-                        category: Some("Edit".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.paste".to_string(),
-                        title: "Paste".to_string(),
-                        category: Some("Edit".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.select_all".to_string(),
-                        title: "Select All".to_string(),
-                        category: Some("Edit".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.save".to_string(),
-                        title: "Save".to_string(),
-                        category: Some("File".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.close".to_string(),
-                        title: "Close Editor".to_string(),
-                        category: Some("File".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "core.exit".to_string(),
-                        title: "Exit".to_string(),
-                        category: Some("File".to_string()),
-                    });
-                    manager.command_registry.register(CommandContribution {
-                        command: "view.set_background".to_string(),
-                        title: "Set Background Image".to_string(),
-                        category: Some("View".to_string()),
-                    });
-                    manager.register_tool_page("git", "Git", Some(PathBuf::from("assets/git.svg")));
+/// 编辑器和窗口的按键绑定
+fn key_bindings() -> Vec<KeyBinding> {
+    // 获取平台来确定ctrl还是cmd
+    let ctrl_cmd = cfg!(target_os = "macos").then(|| "cmd").unwrap_or("ctrl");
+
+    let mut bindings = vec![
+        KeyBinding::new("backspace", Backspace, Some("CodeEditor")),
+        KeyBinding::new("delete", Delete, Some("CodeEditor")),
+        KeyBinding::new("left", Left, Some("CodeEditor")),
+        KeyBinding::new("right", Right, Some("CodeEditor")),
+        KeyBinding::new("up", Up, Some("CodeEditor")),
+        KeyBinding::new("down", Down, Some("CodeEditor")),
+        KeyBinding::new("shift-alt-up", AddCursorAbove, Some("CodeEditor")),
+        KeyBinding::new("shift-alt-down", AddCursorBelow, Some("CodeEditor")),
+        KeyBinding::new("enter", Enter, Some("CodeEditor")),
+        KeyBinding::new("tab", Tab, Some("CodeEditor")),
+        KeyBinding::new("shift-tab", ShiftTab, Some("CodeEditor")),
+        KeyBinding::new("escape", Escape, Some("CodeEditor")),
+        KeyBinding::new("home", LineStart, Some("CodeEditor")),
+        KeyBinding::new("end", LineEnd, Some("CodeEditor")),
+        KeyBinding::new("shift-home", SelectLineStart, Some("CodeEditor")),
+        KeyBinding::new("shift-end", SelectLineEnd, Some("CodeEditor")),
+        KeyBinding::new("f3", FindNext, Some("CodeEditor")),
+        KeyBinding::new("shift-f3", FindPrev, Some("CodeEditor")),
+        KeyBinding::new("f12", GoToDefinition, Some("CodeEditor")),
+        KeyBinding::new("alt-f12", PeekDefinition, Some("CodeEditor")),
+        KeyBinding::new("shift-f12", FindReferences, Some("CodeEditor")),
+        KeyBinding::new("f2", RenameSymbol, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-shift-space", ctrl_cmd), SignatureHelp, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-.", ctrl_cmd), CodeAction, Some("CodeEditor")),
+        KeyBinding::new("shift-alt-f", FormatDocument, Some("CodeEditor")),
+    ];
+
+    // 3. 动态拼接并添加带修饰键的绑定
+    bindings.extend([
+        KeyBinding::new(
+            &format!("{}-shift-k", ctrl_cmd),
+            DeleteLine,
+            Some("CodeEditor"),
+        ),
+        KeyBinding::new(
+            &format!("{}-shift-tab", ctrl_cmd),
+            CtrlShiftTab,
+            Some("CodeEditor"),
+        ),
+        KeyBinding::new(&format!("{}-c", ctrl_cmd), Copy, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-x", ctrl_cmd), Cut, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-v", ctrl_cmd), Paste, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-z", ctrl_cmd), Undo, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-shift-z", ctrl_cmd), Redo, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-f", ctrl_cmd), ToggleFind, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-a", ctrl_cmd), SelectAll, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-/", ctrl_cmd), ToggleLineComment, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-shift-/", ctrl_cmd), ToggleBlockComment, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-k {}-0", ctrl_cmd, ctrl_cmd), FoldAll, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-k {}-j", ctrl_cmd, ctrl_cmd), UnfoldAll, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-k {}-1", ctrl_cmd, ctrl_cmd), FoldLevel1, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-left", ctrl_cmd), WordLeft, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-right", ctrl_cmd), WordRight, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-shift-left", ctrl_cmd), SelectWordLeft, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-shift-right", ctrl_cmd), SelectWordRight, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-backspace", ctrl_cmd), Backspace, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-home", ctrl_cmd), DocumentStart, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-end", ctrl_cmd), DocumentEnd, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-shift-home", ctrl_cmd), SelectDocumentStart, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-shift-end", ctrl_cmd), SelectDocumentEnd, Some("CodeEditor")),
+        KeyBinding::new(&format!("{}-shift-p", ctrl_cmd), ShowCommandPalette, None),
+        KeyBinding::new(&format!("{}-p", ctrl_cmd), QuickOpen, None),
+        KeyBinding::new("ctrl-g", GoToLine, None),
+        KeyBinding::new(&format!("{}-shift-b", ctrl_cmd), CompileProject, None),
+        KeyBinding::new("ctrl-tab", SwitchTab, None),
+        KeyBinding::new(&format!("{}-n", ctrl_cmd), NewFile, None),
+        KeyBinding::new(&format!("{}-o", ctrl_cmd), OpenFile, None),
+        KeyBinding::new(&format!("{}-k {}-o", ctrl_cmd, ctrl_cmd), OpenFolder, None),
+        KeyBinding::new("f6", FocusNextRegion, None),
+        KeyBinding::new("shift-f6", FocusPreviousRegion, None),
+        KeyBinding::new("tab", FocusNextElement, None),
+        KeyBinding::new("shift-tab", FocusPreviousElement, None),
+        KeyBinding::new(&format!("{}-shift-e", ctrl_cmd), FocusFileTreeFilter, None),
+    ]);
+
+    bindings
+}
+
+/// [`key_bindings`] 中同时是命令的，在命令面板中显示快捷键
+fn core_command_keys() -> Vec<(&'static str, String)> {
+    let ctrl_cmd = cfg!(target_os = "macos").then(|| "cmd").unwrap_or("ctrl");
+    vec![
+        ("core.undo", format!("{}-z", ctrl_cmd)),
+        ("core.redo", format!("{}-shift-z", ctrl_cmd)),
+        ("core.cut", format!("{}-x", ctrl_cmd)),
+        ("core.copy", format!("{}-c", ctrl_cmd)),
+        ("core.paste", format!("{}-v", ctrl_cmd)),
+        ("core.select_all", format!("{}-a", ctrl_cmd)),
+        ("core.new_file", format!("{}-n", ctrl_cmd)),
+        ("core.open_file", format!("{}-o", ctrl_cmd)),
+        ("core.open_folder", format!("{}-k {}-o", ctrl_cmd, ctrl_cmd)),
+        ("workspace.quick_open", format!("{}-p", ctrl_cmd)),
+        ("editor.go_to_line", "ctrl-g".to_string()),
+        ("editor.fold_all", format!("{}-k {}-0", ctrl_cmd, ctrl_cmd)),
+        ("editor.unfold_all", format!("{}-k {}-j", ctrl_cmd, ctrl_cmd)),
+        ("editor.fold_level_1", format!("{}-k {}-1", ctrl_cmd, ctrl_cmd)),
+        ("project.compile", format!("{}-shift-b", ctrl_cmd)),
+    ]
+}
+
+/// 打开主窗口，`diff` 为以 `--diff` 启动时两侧的内容。`primary` 为启动时打开的窗口，只有它读写会话；
+/// 之后用“新建窗口”打开的窗口从空白开始
+fn open_start_window(
+    context: &mut App,
+    diff: Option<(cli::DiffRequest, String, String)>,
+    process_started: Instant,
+    primary: bool,
+) {
+    let bounds = Bounds::centered(None, size(px(1200.0), px(700.0)), context);
+    let _ = context.open_window(
+        WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(bounds)),
+            titlebar: Some(TitlebarOptions {
+                appears_transparent: true,
+                ..TitlebarOptions::default()
+            }),
+            ..WindowOptions::default()
+        },
+        move |window, cx| {
+            let window_handle = window.window_handle();
+            let start_window = build_start_window(window, cx, diff.is_some(), process_started, primary);
+            if let Some((request, left, right)) = diff {
+                start_window.update(cx, |this, cx| this.open_diff(&request, &left, &right, cx));
+            } else if let Some(session) = primary.then(Session::load).flatten() {
+                start_window.update(cx, |this, _| {
+                    this.startup_tasks.push_back(StartupTask::RestoreSession(session))
                 });
+            }
+            start_window.update(cx, |this, _| {
+                this.startup_tasks.push_back(StartupTask::DiscoverPlugins);
+                this.startup_tasks.push_back(StartupTask::CompileGrammars);
+            });
+            let view = start_window.downgrade();
+            window.on_window_should_close(cx, move |_, cx| {
+                view.update(cx, |this, cx| this.save_session(cx)).ok();
+                cx.default_global::<OpenDocuments>().close_window(window_handle);
+                true
+            });
+            start_window
+        },
+    );
+}
 
-                {
-                    let pages = plugin_manager.read(cx).list_tool_pages().to_vec();
-                    tool_panel.update(cx, |panel, cx| {
-                        panel.attach_git_panel(git_panel.clone());
-                        for p in pages {
-                            panel.add_tool_page(p.id, p.label, p.icon_path);
-                        }
-                        cx.notify();
-                    });
+/// 创建主窗口的内容，不读取会话，也不排队启动任务；测试中在 gpui 的测试平台上用它打开窗口
+fn build_start_window(
+    window: &mut Window,
+    cx: &mut App,
+    diff_mode: bool,
+    process_started: Instant,
+    primary: bool,
+) -> Entity<StartWindow> {
+    let command_keys = core_command_keys();
+    // 窗口和空白编辑器先绘制出来，插件、其余语法、会话和 git 仓库在首次绘制后排队读取
+    let window_handle = window.window_handle();
+    let mut startup = StartupTimeline::new(process_started);
+    let editor = startup.measure("编辑器", || cx.new(|cx| CodeEditor::new(cx, None)));
+    editor.update(cx, |editor, _cx| {
+        // Indent guides: disable animation + bold, enable colorful palette.
+        editor.indent_guides.highlight.animate = false;
+        editor.indent_guides.thickness.highlighted = editor.indent_guides.thickness.normal;
+        editor.indent_guides.highlight.colors = IndentGuideHighlightColor::Palette(vec![
+            rgb(0x4ec9b0),
+            rgb(0x569cd6),
+            rgb(0xc586c0),
+            rgb(0xdcdcaa),
+            rgb(0xce9178),
+        ]);
+        editor.indent_guides.highlight.randomize_palette = true;
+    });
+    /* editor.update(cx, |editor, cx| {
+         if editor.core.content.len_bytes() != 0 {
+             return;
+         }
+
+        let sample = "类 启动类\n{\n    方法 启动方法()\n    {\n        变量 list: 列表<文本> = 新建 列表<文本>()\n        list.\n    }\n}\n";
+        editor.set_content(sample.to_string(), cx);
+    }); */
+    let file_tree = startup.measure("文件树", || cx.new(|cx| FileTree::new(None, cx)));
+    let command_palette = cx.new(CommandPalette::new);
+    let image_viewer = cx.new(|cx| crate::component::image_viewer::ImageViewer::new(cx));
+    let markdown_viewer = cx.new(|cx| crate::component::markdown_viewer::MarkdownViewer::new(cx));
+    let diff_viewer = cx.new(DiffViewer::new);
+    let review_panel = cx.new(ReviewPanel::new);
+    let tool_panel = {
+        let ft = file_tree.clone();
+        cx.new(|cx| crate::component::tool_panel::ToolPanel::new(ft, cx))
+    };
+    let git_panel = cx.new(|cx| crate::component::git_panel::GitPanel::new(cx));
+    let search_panel = cx.new(SearchPanel::new);
+    let plugin_panel = cx.new(PluginPanel::new);
+    let problems_panel = cx.new(ProblemsPanel::new);
+    let settings_panel = cx.new(ProjectSettingsPanel::new);
+    let plugin_manager = cx.new(|_| PluginManager::new());
+    let status_bar = cx.new(|cx| StatusBar::new(editor.clone(), plugin_manager.clone(), cx));
+    let output_panel = cx.new(OutputPanel::new);
+    let references_panel = cx.new(ReferencesPanel::new);
+
+    plugin_manager.update(cx, |manager: &mut PluginManager, _cx| {
+        manager.set_disabled_plugins(PluginSettings::load().disabled);
+        if let Some(dir) = paths::config_dir() {
+            manager.add_plugin_dir(dir.join("plugins"));
+        }
+        for (command, key) in command_keys {
+            manager.keymap.register(command, key);
+        }
+        manager.command_registry.register(CommandContribution {
+            command: "file_tree.toggle".to_string(),
+            title: "Toggle File Tree".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "file_tree.toggle_hidden".to_string(),
+            title: "Toggle Hidden Files in File Tree".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "workspace.add_folder".to_string(),
+            title: "Add Folder to Workspace...".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "file_tree.reveal_active".to_string(),
+            title: "Reveal Active File in File Tree".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "workspace.quick_open".to_string(),
+            title: "Go to File".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "file.reveal_in_file_manager".to_string(),
+            title: "Reveal Active File in File Manager".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "file.open_terminal".to_string(),
+            title: "Open Terminal at Active File".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "git.review_changes".to_string(),
+            title: "Review Changes".to_string(),
+            category: Some("Git".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "command.history".to_string(),
+            title: "Command History".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "view.toggle_toolbar".to_string(),
+            title: "Toggle Toolbar".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "view.toggle_output".to_string(),
+            title: "Toggle Output Panel".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "view.toggle_performance".to_string(),
+            title: "Toggle Performance Overlay".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.undo".to_string(),
+            title: "Undo".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.redo".to_string(),
+            title: "Redo".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.cut".to_string(),
+            title: "Cut".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.copy".to_string(),
+            title: "Copy".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.paste".to_string(),
+            title: "Paste".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.select_all".to_string(),
+            title: "Select All".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.toggle_read_only".to_string(),
+            title: "Toggle Read-Only".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.revert_hunk".to_string(),
+            title: "Revert Change at Cursor".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.go_to_line".to_string(),
+            title: "Go to Line".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.fold_all".to_string(),
+            title: "Fold All".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.unfold_all".to_string(),
+            title: "Unfold All".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.fold_level_1".to_string(),
+            title: "Fold Level 1".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.convert_to_lf".to_string(),
+            title: "Convert Line Endings to LF".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.convert_to_crlf".to_string(),
+            title: "Convert Line Endings to CRLF".to_string(),
+            category: Some("Edit".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.recover_discarded".to_string(),
+            title: "Recover Discarded Changes".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.new_file".to_string(),
+            title: "New File".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "window.new".to_string(),
+            title: "New Window".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.open_file".to_string(),
+            title: "Open File...".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.open_folder".to_string(),
+            title: "Open Folder...".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.save".to_string(),
+            title: "Save".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.save_as".to_string(),
+            title: "Save As...".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.cycle_save_error_check".to_string(),
+            title: "Cycle Save Error Check (Off / Warn / Block)".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "editor.toggle_format_on_save".to_string(),
+            title: "Toggle Format on Save".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "files.autosave.toggle".to_string(),
+            title: "Toggle Auto Save".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "file.toggle_bom".to_string(),
+            title: "Toggle UTF-8 BOM".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "project.compile".to_string(),
+            title: "Compile Project".to_string(),
+            category: Some("Workspace".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "workspace.prepare_commit".to_string(),
+            title: "Prepare Commit".to_string(),
+            category: Some("Workspace".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.close".to_string(),
+            title: "Close Editor".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.exit".to_string(),
+            title: "Exit".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "core.clear_session".to_string(),
+            title: "Clear Saved Session".to_string(),
+            category: Some("File".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "view.switch_last_editor".to_string(),
+            title: "Switch to Last Editor".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "view.toggle_theme".to_string(),
+            title: "Toggle Light/Dark Theme".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.command_registry.register(CommandContribution {
+            command: "view.set_background".to_string(),
+            title: "Set Background Image".to_string(),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        manager.register_icon_theme("default", "默认", default_assets_base().join(DEFAULT_ICON_THEME));
+        manager.register_tool_page("git", "Git", Some(PathBuf::from("assets/git.svg")));
+        manager.register_tool_page("search", "搜索", Some(PathBuf::from("assets/icons/search_dark.svg")));
+        manager.register_tool_page("plugins", "插件", Some(PathBuf::from("assets/icons/c3Library_dark.svg")));
+        manager.register_tool_page("problems", "问题", Some(PathBuf::from("assets/icons/check.svg")));
+        manager.register_tool_page("settings", "项目设置", Some(PathBuf::from("assets/icons/config_dark.svg")));
+    });
+
+    {
+        let pages = plugin_manager.read(cx).list_tool_pages().to_vec();
+        tool_panel.update(cx, |panel, cx| {
+            panel.attach_git_panel(git_panel.clone());
+            panel.attach_search_panel(search_panel.clone());
+            panel.attach_plugin_panel(plugin_panel.clone());
+            panel.attach_problems_panel(problems_panel.clone());
+            panel.attach_settings_panel(settings_panel.clone());
+            for p in pages {
+                panel.add_tool_page(p.id, p.label, p.icon_path);
+            }
+            cx.notify();
+        });
+    }
+
+    let start_window = cx.new(|cx| {
+        let subscription = cx.subscribe(&file_tree, |this: &mut StartWindow, _emitter, event: &FileTreeEvent, cx| {
+            match event {
+                FileTreeEvent::OpenFile(path) => {
+                    this.open_file_path(path.clone(), cx);
+                }
+                FileTreeEvent::ContextMenu { position, path, is_dir, selection } => {
+                    this.context_menu_open = true;
+                    this.needs_context_menu_focus = true;
+                    this.context_menu_position = *position;
+                    this.context_menu_path = Some(path.clone());
+                    this.context_menu_is_dir = *is_dir;
+                    this.context_menu_selection = selection.clone();
+                    cx.notify();
+                }
+                FileTreeEvent::RequestMove { moves } => {
+                    this.request_confirm(ConfirmAction::Move { moves: moves.clone() }, cx);
+                }
+                FileTreeEvent::RequestDelete { entries } => {
+                    this.request_confirm(ConfirmAction::Delete { entries: entries.clone() }, cx);
+                }
+                FileTreeEvent::Renamed { from, to } => {
+                    this.path_renamed(from, to, cx);
+                    this.offer_reference_update(from, to, cx);
                 }
+            }
+        });
 
-                cx.new(|cx| {
-                    let subscription = cx.subscribe(&file_tree, |this: &mut StartWindow, _emitter, event: &FileTreeEvent, cx| {
-                        match event {
-                            FileTreeEvent::OpenFile(path) => {
-                                this.open_file_path(path.clone(), cx);
-                            }
-                            FileTreeEvent::ContextMenu { position, path, is_dir } => {
-                                this.context_menu_open = true;
-                                this.context_menu_position = *position;
-                                this.context_menu_path = Some(path.clone());
-                                this.context_menu_is_dir = *is_dir;
-                                cx.notify();
-                            }
-                            FileTreeEvent::RequestMove { src, dst } => {
-                                this.request_confirm(
-                                    ConfirmAction::Move {
-                                        src: src.clone(),
-                                        dst: dst.clone(),
-                                    },
-                                    cx,
-                                );
-                            }
-                            FileTreeEvent::RequestDelete { path, is_dir } => {
-                                this.request_confirm(
-                                    ConfirmAction::Delete {
-                                        path: path.clone(),
-                                        is_dir: *is_dir,
-                                    },
-                                    cx,
-                                );
-                            }
-                        }
-                    });
+        let editor_subscription = cx.subscribe(&editor, |this: &mut StartWindow, _emitter, event: &CodeEditorEvent, cx| {
+            match event {
+                CodeEditorEvent::OpenFile(path) => {
+                    this.open_file_path(path.clone(), cx);
+                }
+                CodeEditorEvent::OpenLocation { path, line, column } => {
+                    this.open_file_path(path.clone(), cx);
+                    if this.active_tab.as_ref() == Some(path) {
+                        let (line, column) = (*line, *column);
+                        this.editor.update(cx, |editor, cx| editor.go_to_char_position(line, column, cx));
+                    }
+                }
+                CodeEditorEvent::StatusMessage(message) => {
+                    let message = message.clone();
+                    this.status_bar.update(cx, |bar, cx| bar.flash(message, cx));
+                }
+                CodeEditorEvent::FindReferences(word) => {
+                    this.find_references(word.clone(), cx);
+                }
+                CodeEditorEvent::RenameSymbol { old_name, edits } => {
+                    this.apply_rename(old_name, edits.clone(), cx);
+                }
+                CodeEditorEvent::Problems(problems) => {
+                    // 未命名缓冲区没有可以跳转的文件
+                    if let Some(path) = this.active_tab.clone().filter(|path| untitled_name(path).is_none()) {
+                        let problems = problems.clone();
+                        this.problems_panel.update(cx, |panel, cx| panel.set_problems(path, problems, cx));
+                    }
+                }
+            }
+        });
 
-                    let editor_subscription = cx.subscribe(&editor, |this: &mut StartWindow, _emitter, event: &CodeEditorEvent, cx| {
-                        match event {
-                            CodeEditorEvent::OpenFile(path) => {
-                                this.open_file_path(path.clone(), cx);
-                            }
-                        }
+        let palette_subscription = cx.subscribe(&command_palette, |this: &mut StartWindow, _emitter, event: &CommandPaletteEvent, cx| {
+            match event {
+                CommandPaletteEvent::Dismiss => {
+                    this.command_palette.update(cx, |palette, cx| {
+                        palette.hide(cx);
                     });
-
-                    let palette_subscription = cx.subscribe(&command_palette, |this: &mut StartWindow, _emitter, event: &CommandPaletteEvent, cx| {
-                        match event {
-                            CommandPaletteEvent::Dismiss => {
-                                this.command_palette.update(cx, |palette, cx| {
-                                    palette.hide(cx);
-                                });
-                                this.needs_focus_restore = true;
-                                cx.notify();
-                            }
-                            CommandPaletteEvent::ExecuteCommand(command_id) => {
-                                this.execute_command(&command_id, cx);
-                            }
+                    this.needs_focus_restore = true;
+                    cx.notify();
+                }
+                CommandPaletteEvent::ExecuteCommand(command_id) => {
+                    this.execute_command(&command_id, None, cx);
+                }
+                CommandPaletteEvent::OpenFile(path) => {
+                    this.open_file_path(path.clone(), cx);
+                    this.needs_focus_restore = true;
+                    cx.notify();
+                }
+                CommandPaletteEvent::PreviewMatch(path) => {
+                    this.preview_file(path.clone(), 0, 0, cx);
+                }
+                CommandPaletteEvent::CancelPreview => {
+                    this.cancel_preview(cx);
+                }
+                CommandPaletteEvent::RequestSymbols => {
+                    // 图片、Markdown 等标签没有大纲
+                    let symbols = if this.active_tab.as_ref().is_some_and(StartWindow::is_text_path) {
+                        this.editor.update(cx, |editor, cx| editor.outline(cx))
+                    } else {
+                        Task::ready(Vec::new())
+                    };
+                    let palette = this.command_palette.downgrade();
+                    cx.spawn(move |_, cx: &mut AsyncApp| {
+                        let mut cx = cx.clone();
+                        async move {
+                            let symbols = symbols.await;
+                            palette.update(&mut cx, |palette, cx| palette.set_symbols(symbols, cx)).ok();
                         }
-                    });
+                    })
+                    .detach();
+                }
+                CommandPaletteEvent::GoToSymbol { line, column } | CommandPaletteEvent::GoToLine { line, column } => {
+                    let (line, column) = (*line, *column);
+                    this.editor.update(cx, |editor, cx| editor.go_to_char_position(line, column, cx));
+                    this.needs_focus_restore = true;
+                    cx.notify();
+                }
+                CommandPaletteEvent::RequestWorkspaceSymbols(keyword) => {
+                    // 项目符号来自结绳编译器，需要当前标签是文本文件
+                    let symbols = if this.active_tab.as_ref().is_some_and(StartWindow::is_text_path) {
+                        this.editor.update(cx, |editor, cx| editor.workspace_symbols(keyword, cx))
+                    } else {
+                        Task::ready(Vec::new())
+                    };
+                    let palette = this.command_palette.downgrade();
+                    let keyword = keyword.clone();
+                    cx.spawn(move |_, cx: &mut AsyncApp| {
+                        let mut cx = cx.clone();
+                        async move {
+                            let symbols = symbols.await;
+                            palette.update(&mut cx, |palette, cx| palette.set_workspace_symbols(&keyword, symbols, cx)).ok();
+                        }
+                    })
+                    .detach();
+                }
+                CommandPaletteEvent::OpenLocation { path, line, column } => {
+                    this.open_file_path(path.clone(), cx);
+                    if this.active_tab.as_ref() == Some(path) {
+                        let (line, column) = (*line, *column);
+                        this.editor.update(cx, |editor, cx| editor.go_to_char_position(line, column, cx));
+                    }
+                    this.needs_focus_restore = true;
+                    cx.notify();
+                }
+                CommandPaletteEvent::TogglePinned(command_id) => {
+                    this.toggle_toolbar_pin(command_id, cx);
+                }
+            }
+        });
+
+        let git_subscription = cx.subscribe(&git_panel, |this: &mut StartWindow, _emitter, event: &GitPanelEvent, cx| {
+            match event {
+                GitPanelEvent::BranchChanged { from, to } => {
+                    log_channel(OutputChannel::Git, format!("Branch switched: {} -> {}", from, to));
+                    this.refresh_workspace_content(cx);
+                }
+                GitPanelEvent::StatusChanged => {
+                    this.sync_git_status(cx);
+                    this.refresh_review(cx);
+                }
+            }
+        });
+
+        let review_subscription = cx.subscribe(&review_panel, |this: &mut StartWindow, _emitter, event: &ReviewPanelEvent, cx| {
+            match event {
+                ReviewPanelEvent::OpenDiff { path, relative, head, current } => {
+                    this.open_review_diff(path, relative, head, current, cx);
+                }
+                ReviewPanelEvent::Close => this.close_review(cx),
+            }
+        });
+
+        let search_subscription = cx.subscribe(&search_panel, |this: &mut StartWindow, _emitter, event: &SearchPanelEvent, cx| {
+            match event {
+                SearchPanelEvent::PreviewMatch { path, line, column } => {
+                    this.preview_file(path.clone(), *line, *column, cx);
+                }
+                SearchPanelEvent::CancelPreview => {
+                    this.cancel_preview(cx);
+                }
+                SearchPanelEvent::LeavePanel => {
+                    this.needs_focus_restore = true;
+                    cx.notify();
+                }
+                SearchPanelEvent::OpenMatch { path, line, column } => {
+                    this.open_file_path(path.clone(), cx);
+                    if this.active_tab.as_ref() == Some(path) {
+                        let (line, column) = (*line, *column);
+                        this.editor.update(cx, |editor, cx| {
+                            editor.go_to_line_col(line, column, cx);
+                        });
+                        this.needs_focus_restore = true;
+                    }
+                }
+            }
+        });
 
-                    StartWindow {
-                        editor,
-                        file_tree,
-                        command_palette,
-                        plugin_manager,
-                        status_bar,
-                        image_viewer,
-                        markdown_viewer,
-                        tool_panel,
-                        file_tree_visible: true,
-                        open_tabs: Vec::new(),
-                        active_tab: None,
-                        external_drag_position: point(px(0.0), px(0.0)),
-                        external_drag_primary: None,
-                        external_drag_is_dir: false,
-                        external_drag_count: 0,
-                        confirm_open: false,
-                        confirm_action: None,
-                        context_menu_open: false,
-                        context_menu_position: point(px(0.0), px(0.0)),
-                        context_menu_path: None,
-                        context_menu_is_dir: false,
-                        _subscriptions: vec![
-                            subscription,
-                            editor_subscription,
-                            palette_subscription,
-                        ],
-                        needs_focus_restore: false,
-                        needs_initial_focus: true,
-                        background_image: None,
+        cx.spawn(|view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    cx.background_executor().timer(Duration::from_millis(250)).await;
+                    if view.update(&mut cx, |this, cx| this.poll_file_changes(cx)).is_err() {
+                        break;
                     }
-                })
+                }
+            }
+        })
+        .detach();
+
+        // 插件线程经 editor_host 发来的编辑器请求在界面线程上逐个处理
+        let (editor_host, editor_requests) = plugin::editor_host::channel();
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                while let Ok(request) = editor_requests.recv_async().await {
+                    // 窗口关闭后丢弃请求，等待中的插件得到“编辑器已关闭”
+                    if view.update(&mut cx, |this, cx| this.handle_editor_request(request, cx)).is_err() {
+                        break;
+                    }
+                }
+            }
+        })
+        .detach();
+
+        let tool_panel_subscription = cx.subscribe(&tool_panel, |this: &mut StartWindow, _emitter, event: &ToolPanelEvent, cx| {
+            match event {
+                ToolPanelEvent::RevealActiveFile => {
+                    this.reveal_active_file(cx);
+                }
+            }
+        });
+
+        let plugin_panel_subscription = cx.subscribe(&plugin_panel, |this: &mut StartWindow, _emitter, event: &PluginPanelEvent, cx| {
+            match event {
+                PluginPanelEvent::SetEnabled { id, enabled } => {
+                    this.set_plugin_enabled(id, *enabled, cx);
+                }
+                PluginPanelEvent::Rescan => this.rescan_plugins(cx),
+            }
+        });
+
+        let references_subscription = cx.subscribe(&references_panel, |this: &mut StartWindow, _emitter, event: &ReferencesPanelEvent, cx| {
+            match event {
+                ReferencesPanelEvent::Open { path, line, column } => {
+                    this.open_file_path(path.clone(), cx);
+                    if this.active_tab.as_ref() == Some(path) {
+                        let (line, column) = (*line, *column);
+                        this.editor.update(cx, |editor, cx| editor.go_to_line_col(line, column, cx));
+                        this.needs_focus_restore = true;
+                    }
+                }
+                ReferencesPanelEvent::Dismiss => {
+                    this.references_visible = false;
+                    this.needs_focus_restore = true;
+                    cx.notify();
+                }
+            }
+        });
+
+        let problems_panel_subscription = cx.subscribe(&problems_panel, |this: &mut StartWindow, _emitter, event: &ProblemsPanelEvent, cx| {
+            match event {
+                ProblemsPanelEvent::Open { path, line, column } => {
+                    this.open_file_path(path.clone(), cx);
+                    if this.active_tab.as_ref() == Some(path) {
+                        let (line, column) = (*line, *column);
+                        this.editor.update(cx, |editor, cx| editor.go_to_char_position(line, column, cx));
+                        this.needs_focus_restore = true;
+                    }
+                }
+            }
+        });
+
+        let settings_panel_subscription = cx.subscribe(&settings_panel, |this: &mut StartWindow, _emitter, event: &ProjectSettingsPanelEvent, cx| {
+            match event {
+                ProjectSettingsPanelEvent::Save { path, content } => this.save_project_settings(path, content, cx),
+            }
+        });
+
+        let status_bar_subscription = cx.subscribe(&status_bar, |this: &mut StartWindow, _emitter, event: &StatusBarEvent, cx| {
+            match event {
+                StatusBarEvent::GoToLine => {
+                    this.go_to_line(cx);
+                }
+                StatusBarEvent::RunCommand(command) => this.execute_command(command, None, cx),
+            }
+        });
+
+        // 编辑器失去焦点或窗口切到后台时立即自动保存，不等延迟
+        let editor_focus = editor.read(cx).focus_handle.clone();
+        let blur_subscription = cx.on_blur(&editor_focus, window, |this: &mut StartWindow, _window, cx| {
+            this.autosave_active(cx);
+        });
+        let activation_subscription = cx.observe_window_activation(window, |this: &mut StartWindow, window, cx| {
+            if !window.is_window_active() {
+                this.autosave_active(cx);
+            }
+        });
+
+        let quit_subscription = cx.on_app_quit(|this: &mut StartWindow, cx| {
+            this.save_session(cx);
+            this.plugin_manager.update(cx, |manager, _| manager.shutdown());
+            async {}
+        });
+
+        StartWindow {
+            editor,
+            file_tree,
+            command_palette,
+            plugin_manager,
+            status_bar,
+            output_panel,
+            references_panel,
+            image_viewer,
+            markdown_viewer,
+            diff_viewer,
+            diff_mode,
+            review_panel,
+            plugin_panel,
+            problems_panel,
+            settings_panel,
+            review_visible: false,
+            review_tab: None,
+            needs_review_focus: false,
+            tool_panel,
+            file_tree_visible: !diff_mode,
+            open_tabs: Vec::new(),
+            active_tab: None,
+            tab_mru: TabMru::default(),
+            tab_switcher: None,
+            file_watcher: OpenFileWatcher::new(),
+            grammar_watcher: if cfg!(debug_assertions) {
+                GrammarWatcher::new(&default_assets_base().join("grammars"))
+            } else {
+                None
             },
-        );
+            binary_tabs: Vec::new(),
+            external_drag_position: point(px(0.0), px(0.0)),
+            external_drag_primary: None,
+            external_drag_is_dir: false,
+            external_drag_count: 0,
+            tree_drop_target: None,
+            confirm_open: false,
+            confirm_action: None,
+            confirm_focus: cx.focus_handle(),
+            confirm_default_focus: cx.focus_handle().tab_stop(true),
+            needs_confirm_focus: false,
+            confirm_return_focus: None,
+            toolbar_focus: cx.focus_handle(),
+            tab_bar_focus: cx.focus_handle(),
+            context_menu_focus: cx.focus_handle(),
+            needs_context_menu_focus: false,
+            context_menu_return_focus: None,
+            save_error_check: EditorSettings::load().save_error_check,
+            format_on_save: false,
+            autosave: Autosave::new(EditorSettings::load().autosave_delay()),
+            pending_save: None,
+            prepare_commit_toast: None,
+            needs_git_focus: false,
+            needs_palette_focus: false,
+            preview: None,
+            untitled_count: 0,
+            context_menu_open: false,
+            context_menu_position: point(px(0.0), px(0.0)),
+            context_menu_path: None,
+            context_menu_is_dir: false,
+            context_menu_selection: Vec::new(),
+            file_clipboard: None,
+            startup,
+            startup_tasks: VecDeque::new(),
+            startup_task: None,
+            compile_task: None,
+            _reference_scan: None,
+            performance_visible: false,
+            output_visible: false,
+            references_visible: false,
+            needs_references_focus: false,
+            _subscriptions: vec![
+                subscription,
+                editor_subscription,
+                palette_subscription,
+                search_subscription,
+                git_subscription,
+                review_subscription,
+                tool_panel_subscription,
+                status_bar_subscription,
+                plugin_panel_subscription,
+                problems_panel_subscription,
+                settings_panel_subscription,
+                references_subscription,
+                quit_subscription,
+                blur_subscription,
+                activation_subscription,
+            ],
+            needs_focus_restore: false,
+            needs_initial_focus: true,
+            background_image: None,
+            session_cleared: false,
+            tree_states: Default::default(),
+            command_journal: CommandJournal::default(),
+            discarded_tabs: DiscardedTabs::default(),
+            toolbar: Toolbar::default(),
+            toolbar_drag: None,
+            recovered_tabs: Vec::new(),
+            window_handle,
+            primary_window: primary,
+            shared_seen: HashMap::new(),
+            buffer_versions: HashMap::new(),
+            editor_host,
+        }
     });
+    start_window
 }
 
 struct StartWindow {
@@ -375,30 +1131,262 @@ struct StartWindow {
     status_bar: Entity<StatusBar>,
     image_viewer: Entity<crate::component::image_viewer::ImageViewer>,
     markdown_viewer: Entity<crate::component::markdown_viewer::MarkdownViewer>,
+    diff_viewer: Entity<DiffViewer>,
+    /// 以 `--diff` 启动：不读写会话，比较标签关闭后退出
+    diff_mode: bool,
+    /// 提交前审阅的清单，显示在编辑区右侧
+    review_panel: Entity<ReviewPanel>,
+    plugin_panel: Entity<PluginPanel>,
+    /// 查错得到的问题，按文件列在“问题”工具页
+    problems_panel: Entity<ProblemsPanel>,
+    /// “项目设置”工具页，编辑设置文件的 tiec 一节
+    settings_panel: Entity<ProjectSettingsPanel>,
+    review_visible: bool,
+    /// 审阅时打开的对照标签，切换文件时关闭
+    review_tab: Option<PathBuf>,
+    needs_review_focus: bool,
     tool_panel: Entity<crate::component::tool_panel::ToolPanel>,
     file_tree_visible: bool,
-    open_tabs: Vec<PathBuf>,
+    open_tabs: Vec<OpenTab>,
     active_tab: Option<PathBuf>,
+    /// 最近激活的标签，最新的在前
+    tab_mru: TabMru,
+    tab_switcher: Option<TabSwitcher>,
+    file_watcher: OpenFileWatcher,
+    /// 调试构建中监视语法文件，改动后重新编译
+    grammar_watcher: Option<GrammarWatcher>,
+    /// 含二进制数据、只显示提示的标签及其文件大小
+    binary_tabs: Vec<(PathBuf, u64)>,
     external_drag_position: Point<Pixels>,
     external_drag_primary: Option<PathBuf>,
     external_drag_is_dir: bool,
     external_drag_count: usize,
+    /// 从文件树拖动时鼠标下方的放置区域
+    tree_drop_target: Option<TreeDropTarget>,
     confirm_open: bool,
     confirm_action: Option<ConfirmAction>,
+    /// 确认框及其默认按钮（确定）的焦点，打开时默认按钮获得焦点
+    confirm_focus: FocusHandle,
+    confirm_default_focus: FocusHandle,
+    needs_confirm_focus: bool,
+    /// 确认框打开前的焦点，关闭后还给它
+    confirm_return_focus: Option<FocusHandle>,
+    /// 工具栏和标签栏作为焦点区域，其中的按钮、标签可用 Tab 切换
+    toolbar_focus: FocusHandle,
+    tab_bar_focus: FocusHandle,
+    /// 文件树右键菜单，打开时获得焦点，关闭后还给打开前的控件
+    context_menu_focus: FocusHandle,
+    needs_context_menu_focus: bool,
+    context_menu_return_focus: Option<FocusHandle>,
+    save_error_check: SaveErrorCheck,
+    /// 保存前先格式化文档
+    format_on_save: bool,
+    autosave: Autosave,
+    /// 因存在错误而等待用户确认的保存
+    pending_save: Option<PendingSave>,
+    /// 最近一次“准备提交”的逐个文件结果
+    prepare_commit_toast: Option<PrepareCommitToast>,
+    /// 下一帧把焦点交给 Git 面板的提交框
+    needs_git_focus: bool,
+    /// 不是由按键打开命令面板时（如从工具栏执行命令），下次绘制时把焦点移到面板
+    needs_palette_focus: bool,
+    /// 搜索结果的只读预览；存在时 `active_tab` 为 None
+    preview: Option<PreviewTab>,
+    /// 已创建的未命名标签数，用于编号 Untitled-N
+    untitled_count: usize,
     context_menu_open: bool,
     context_menu_position: Point<Pixels>,
     context_menu_path: Option<PathBuf>,
     context_menu_is_dir: bool,
+    /// 在文件树的多选上右键时的所有最上层选中项，删除时一并删除
+    context_menu_selection: Vec<(PathBuf, bool)>,
+    /// 文件树中复制或剪切、等待粘贴的项
+    file_clipboard: Option<FileClipboard>,
+    startup: StartupTimeline,
+    /// 首次绘制后依次执行的启动任务
+    startup_tasks: VecDeque<StartupTask>,
+    startup_task: Option<Task<()>>,
+    /// 正在进行的项目编译；替换即丢弃上一次的结果
+    compile_task: Option<Task<()>>,
+    /// 重命名结绳源文件后在后台查找对它的引用
+    _reference_scan: Option<Task<()>>,
+    /// 显示性能面板（启动各阶段耗时）
+    performance_visible: bool,
+    /// 底部的输出面板
+    output_panel: Entity<OutputPanel>,
+    output_visible: bool,
+    /// 查找引用的结果，显示在编辑区下方
+    references_panel: Entity<ReferencesPanel>,
+    references_visible: bool,
+    needs_references_focus: bool,
     _subscriptions: Vec<Subscription>,
     needs_focus_restore: bool,
     needs_initial_focus: bool,
     background_image: Option<PathBuf>,
+    /// 执行过“清除会话”后本次运行不再保存会话
+    session_cleared: bool,
+    /// 各文件夹的文件树状态，包括已经关闭的文件夹
+    tree_states: std::collections::BTreeMap<PathBuf, TreeState>,
+    command_journal: CommandJournal,
+    /// 关闭时丢弃了未保存修改的标签，可以用命令恢复
+    discarded_tabs: DiscardedTabs<EditorSnapshot>,
+    /// 从丢弃的修改恢复、尚未保存的标签，显示说明横幅
+    recovered_tabs: Vec<PathBuf>,
+    toolbar: Toolbar,
+    /// 正在按住的工具栏按钮，及是否已经拖动改变了顺序
+    toolbar_drag: Option<(String, bool)>,
+    window_handle: AnyWindowHandle,
+    /// 启动时打开的窗口，只有它读写会话
+    primary_window: bool,
+    /// 共享编辑的文件最后同步的版本，见 [`open_documents`]
+    shared_seen: HashMap<PathBuf, u64>,
+    /// 各标签最后一次发布给插件的缓冲区版本
+    buffer_versions: HashMap<PathBuf, u64>,
+    /// 交给动态库插件的编辑器接口，请求由 [`StartWindow::handle_editor_request`] 处理
+    editor_host: Arc<ChannelEditorHost>,
+}
+
+/// 一个已打开的标签。不在前台的文本标签把编辑状态存在 `snapshot` 中，
+/// 切回时原样恢复，未保存的修改也随之保留
+struct OpenTab {
+    path: PathBuf,
+    snapshot: Option<EditorSnapshot>,
+    /// 打开时带 UTF-8 BOM，保存时写回 BOM
+    bom: bool,
+    /// 文件在当前分支上不存在
+    missing: bool,
+    /// 文件在外部被删除
+    deleted: bool,
+    /// 从上次会话恢复、尚未激活的标签的光标和滚动位置
+    restore_view: Option<TabView>,
+}
+
+impl OpenTab {
+    fn new(path: PathBuf) -> Self {
+        Self { path, snapshot: None, bom: false, missing: false, deleted: false, restore_view: None }
+    }
+}
+
+/// 工具栏按钮的提示：命令标题和快捷键
+struct ToolbarTooltip {
+    title: String,
+    key: Option<String>,
+}
+
+impl Render for ToolbarTooltip {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = component::theme(cx);
+        div()
+            .px(px(8.0))
+            .py(px(4.0))
+            .flex()
+            .gap(px(8.0))
+            .bg(theme.panel)
+            .border_1()
+            .border_color(theme.border)
+            .rounded_md()
+            .text_size(px(12.0))
+            .text_color(theme.text)
+            .child(self.title.clone())
+            .child(div().text_color(theme.muted_text).child(self.key.clone().unwrap_or_default()))
+    }
+}
+
+/// 首次绘制之后才执行的启动工作，每项之间让出一帧
+enum StartupTask {
+    RestoreSession(Session),
+    /// 读取会话中文件夹所在的 git 仓库，并恢复其提交草稿
+    GitRepo { root: PathBuf, drafts: std::collections::BTreeMap<PathBuf, String> },
+    DiscoverPlugins,
+    /// 逐个编译当前文件用不到的语法，每次一个
+    CompileGrammars,
+}
+
+impl StartupTask {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::RestoreSession(_) => "恢复会话",
+            Self::GitRepo { .. } => "读取 Git 仓库",
+            Self::DiscoverPlugins => "加载插件",
+            Self::CompileGrammars => "编译语法",
+        }
+    }
+}
+
+/// 临时的预览标签，后续预览复用它
+struct PreviewTab {
+    path: PathBuf,
+    /// 开始预览前的标签，取消预览时切回
+    restore: Option<PathBuf>,
+    /// 正在后台读取的文件；替换即取消
+    _load: Option<Task<()>>,
+}
+
+/// ctrl+tab 按下期间的切换状态，松开 ctrl 时切换到 `index` 指向的 MRU 项
+struct TabSwitcher {
+    index: usize,
+    started_at: Instant,
+    overlay_visible: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SaveTrigger {
+    Manual,
+    /// 自动保存不弹出提示，只在状态栏显示警告
+    Auto,
+}
+
+/// 保存前查错发现错误时暂存的状态，由编辑器上方的提示条处理
+struct PendingSave {
+    path: PathBuf,
+    errors: Vec<LintError>,
+    /// 阻止模式下用户已点击“仍要保存…”
+    save_unlocked: bool,
+}
+
+/// 重命名后查找引用时最多检查的匹配行数
+const REFERENCE_SCAN_LIMIT: usize = 5000;
+
+/// 提示条中最多列出的错误数
+const SAVE_BANNER_MAX_ERRORS: usize = 3;
+
+/// “准备提交”的进度：逐个文件的结果，还没处理完的文件为 None
+struct PrepareCommitToast {
+    results: Vec<(String, Option<workspace::PrepareOutcome>)>,
+    shown_at: Instant,
+    /// 所有文件处理完后才开始计时关闭
+    finished: bool,
+}
+
+/// “准备提交”结果提示框的停留时间
+const PREPARE_COMMIT_TOAST_DURATION: Duration = Duration::from_secs(6);
+
+/// 从文件树拖入时可以放置的区域，放下后打开拖动的文件
+#[derive(Clone, Copy, PartialEq)]
+enum TreeDropTarget {
+    TabBar,
+    Editor,
 }
 
 #[derive(Clone)]
 enum ConfirmAction {
-    Move { src: PathBuf, dst: PathBuf },
-    Delete { path: PathBuf, is_dir: bool },
+    /// 拖动文件树中的一项或多选的所有项
+    Move { moves: Vec<(PathBuf, PathBuf)> },
+    Delete { entries: Vec<(PathBuf, bool)> },
+    /// 项目设置变化后重启结绳 IDE 服务
+    RestartLanguageService { settings: PathBuf },
+    /// 关闭有内容的未命名标签
+    CloseUnsaved { path: PathBuf },
+    /// 从工作区移除文件夹，其下有未保存修改的标签一并关闭
+    RemoveRoot { root: PathBuf },
+    /// 有未保存修改的文件在外部被修改，确认后丢弃修改并重新载入
+    ReloadExternal { path: PathBuf },
+    /// 保存时发现文件在载入后已被另一个窗口或其它程序写入，确认后覆盖
+    OverwriteExternal { path: PathBuf },
+    /// 结绳源文件改名后一并更新其它文件中的引用；`files` 为引用所在的文件和处数
+    UpdateReferences { from: PathBuf, to: PathBuf, files: Vec<(PathBuf, usize)> },
+    /// 超过阈值的文件，确认后以大文件模式只读打开
+    OpenLargeFile { path: PathBuf, size: u64 },
 }
 
 impl StartWindow {
@@ -415,83 +1403,2618 @@ impl StartWindow {
         }
     }
 
-    fn open_file_path(&mut self, path: PathBuf, cx: &mut Context<Self>) {
-        if Self::is_image_path(&path) {
-            self.image_viewer.update(cx, |viewer, cx| {
-                viewer.open_image(path.clone(), cx);
-            });
-            if !self.open_tabs.iter().any(|p| p == &path) {
-                self.open_tabs.push(path.clone());
-            }
-            self.active_tab = Some(path);
-            cx.notify();
-        } else if Self::is_markdown_path(&path) {
-            if let Ok(content) = std::fs::read_to_string(&path) {
-                self.markdown_viewer.update(cx, |viewer, cx| {
-                    viewer.set_content(content, cx);
-                });
-                if !self.open_tabs.iter().any(|p| p == &path) {
-                    self.open_tabs.push(path.clone());
-                }
-                self.active_tab = Some(path);
-                cx.notify();
+    fn is_diff_path(path: &PathBuf) -> bool {
+        diff_tab_label(path).is_some()
+    }
+
+    fn is_text_path(path: &PathBuf) -> bool {
+        !Self::is_image_path(path) && !Self::is_markdown_path(path) && !Self::is_diff_path(path)
+    }
+
+    /// 标签上显示的名称：文件名，未命名标签为 Untitled-N，比较标签为两侧的文件名
+    fn tab_label(path: &PathBuf) -> String {
+        if let Some(name) = untitled_name(path) {
+            return name.to_string();
+        }
+        if let Some(label) = diff_tab_label(path) {
+            return label;
+        }
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string())
+    }
+
+    fn is_binary_tab(&self, path: &PathBuf) -> bool {
+        self.binary_tabs.iter().any(|(p, _)| p == path)
+    }
+
+    fn ensure_tab(&mut self, path: &PathBuf) -> &mut OpenTab {
+        let index = match self.open_tabs.iter().position(|t| &t.path == path) {
+            Some(index) => index,
+            None => {
+                self.open_tabs.push(OpenTab::new(path.clone()));
+                self.open_tabs.len() - 1
+            }
+        };
+        &mut self.open_tabs[index]
+    }
+
+    fn find_tab(&self, path: &PathBuf) -> Option<&OpenTab> {
+        self.open_tabs.iter().find(|t| &t.path == path)
+    }
+
+    /// 把编辑器中前台文本标签的状态存回该标签，之后编辑器可以载入别的文件
+    fn stash_active_editor(&mut self, cx: &mut Context<Self>) {
+        // 切换标签前先自动保存
+        self.autosave_active(cx);
+        let Some(active) = self.active_tab.clone().filter(Self::is_text_path) else {
+            return;
+        };
+        if self.is_binary_tab(&active) {
+            return;
+        }
+        let snapshot = self.editor.update(cx, |editor, _| editor.take_snapshot());
+        // 标签已关闭时状态随之丢弃
+        if let Some(tab) = self.open_tabs.iter_mut().find(|t| t.path == active) {
+            tab.snapshot = Some(snapshot);
+        }
+    }
+
+    /// 标签当前的文本内容：前台标签取编辑器，其余取保存的状态；从未激活过的标签返回 None
+    fn tab_buffer(&self, path: &PathBuf, cx: &App) -> Option<String> {
+        if self.active_tab.as_ref() == Some(path) && Self::is_text_path(path) && !self.is_binary_tab(path) {
+            return Some(self.editor.read(cx).core.content.to_string());
+        }
+        self.open_tabs
+            .iter()
+            .find(|t| &t.path == path)
+            .and_then(|t| t.snapshot.as_ref())
+            .map(|snapshot| snapshot.content())
+    }
+
+    /// 把激活事件交给插件管理器，激活失败的插件在状态栏提示
+    fn emit_plugin_event(&mut self, event: ActivationEvent, cx: &mut Context<Self>) {
+        let failures: Vec<String> = self.plugin_manager.update(cx, |manager, _| {
+            manager
+                .emit_activation_event(&event)
+                .into_iter()
+                .filter_map(|id| match manager.plugin_state(&id) {
+                    Some(PluginState::Failed(reason)) => Some(format!("{}：{}", id, reason)),
+                    _ => None,
+                })
+                .collect()
+        });
+        if !failures.is_empty() {
+            let warning = format!("插件激活失败：{}", failures.join("；"));
+            self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(warning), cx));
+        }
+    }
+
+    /// 把事件交给订阅了它的插件，投递在插件各自的线程上进行
+    fn publish_plugin_event(&self, event: PluginEvent, cx: &App) {
+        self.plugin_manager.read(cx).publish(&event);
+    }
+
+    fn open_file_path(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        if self.preview.take().is_some() {
+            self.editor.update(cx, |editor, _| editor.end_preview());
+        }
+        if self.active_tab.as_ref() == Some(&path) && Self::is_text_path(&path) {
+            // 已在编辑器中，不要用磁盘内容覆盖未保存的修改
+            return;
+        }
+        if !self.claim_document(&path, cx) {
+            return;
+        }
+        if Self::is_diff_path(&path) {
+            // 比较视图只有一个，内容在打开比较标签时已经载入
+            self.stash_active_editor(cx);
+            self.set_active_tab(path);
+            cx.notify();
+        } else if Self::is_image_path(&path) {
+            self.stash_active_editor(cx);
+            self.image_viewer.update(cx, |viewer, cx| {
+                viewer.open_image(path.clone(), cx);
+            });
+            self.ensure_tab(&path);
+            self.set_active_tab(path);
+            cx.notify();
+        } else if Self::is_markdown_path(&path) {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                let content = tiecode_buffer::strip_bom(&content).0.to_string();
+                self.stash_active_editor(cx);
+                self.markdown_viewer.update(cx, |viewer, cx| {
+                    viewer.set_content(content, cx);
+                });
+                self.ensure_tab(&path);
+                self.set_active_tab(path);
+                cx.notify();
+            }
+        } else if let Some(snapshot) = self
+            .open_tabs
+            .iter_mut()
+            .find(|t| t.path == path)
+            .and_then(|t| t.snapshot.take())
+        {
+            self.stash_active_editor(cx);
+            self.editor.update(cx, |editor, cx| {
+                editor.restore_snapshot(path.clone(), snapshot, cx);
+            });
+            self.set_active_tab(path);
+            self.sync_bom_indicator(cx);
+            cx.notify();
+        } else if self.is_binary_tab(&path) {
+            self.stash_active_editor(cx);
+            self.set_active_tab(path);
+            cx.notify();
+        } else {
+            self.open_text_file(path, cx);
+        }
+    }
+
+    /// 从磁盘打开文本文件；含二进制数据的只显示提示，超过阈值的先确认
+    fn open_text_file(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        let threshold = EditorSettings::load().large_file_threshold();
+        match file_guard::inspect(&path, threshold) {
+            Ok(FileKind::Binary { size }) => {
+                log_channel(OutputChannel::App, format!("Not opening binary file as text: {:?}", path));
+                self.stash_active_editor(cx);
+                self.binary_tabs.retain(|(p, _)| p != &path);
+                self.binary_tabs.push((path.clone(), size));
+                self.ensure_tab(&path);
+                self.set_active_tab(path);
+                cx.notify();
             }
-        } else if let Ok(content) = std::fs::read_to_string(&path) {
+            Ok(FileKind::Large { size }) => {
+                self.request_confirm(ConfirmAction::OpenLargeFile { path, size }, cx);
+            }
+            // 读不到的文件交给下面照常处理
+            Ok(FileKind::Text) | Err(_) => self.load_text_file(path, false, cx),
+        }
+    }
+
+    /// 读入文本文件交给编辑器；`large_file` 时以大文件模式打开
+    fn load_text_file(&mut self, path: PathBuf, large_file: bool, cx: &mut Context<Self>) {
+        if let Ok(raw) = std::fs::read_to_string(&path) {
+            self.stash_active_editor(cx);
+            // BOM 只记录在标签上，缓冲区里不保留
+            let (content, bom) = tiecode_buffer::strip_bom(&raw);
+            let content = content.to_string();
+            let restore_view = self
+                .open_tabs
+                .iter_mut()
+                .find(|t| t.path == path)
+                .and_then(|t| t.restore_view.take());
+            self.file_watcher.mark_saved(&path, &content);
             self.editor.update(cx, |editor, cx| {
-                editor.open_file(path.clone(), content, cx);
+                if large_file {
+                    editor.open_large_file(path.clone(), content, cx);
+                } else {
+                    editor.open_file(path.clone(), content, cx);
+                }
+                if let Some(view) = restore_view {
+                    editor.set_view_position(view.line, view.column, view.scroll_y, cx);
+                }
+            });
+            self.ensure_tab(&path).bom = bom;
+            let extension = path.extension().map(|ext| ext.to_string_lossy().to_string()).unwrap_or_default();
+            self.set_active_tab(path.clone());
+            self.sync_bom_indicator(cx);
+            self.emit_plugin_event(ActivationEvent::FileOpen(extension), cx);
+            self.publish_plugin_event(PluginEvent::FileOpened(path), cx);
+            cx.notify();
+        }
+    }
+
+    /// 在编辑区只读预览文件，不打开标签；连续预览复用同一个预览标签
+    fn preview_file(&mut self, path: PathBuf, line: usize, column: usize, cx: &mut Context<Self>) {
+        if !Self::is_text_path(&path) {
+            return;
+        }
+        if self.preview.is_none() {
+            let restore = self.active_tab.clone();
+            self.stash_active_editor(cx);
+            self.active_tab = None;
+            self.preview = Some(PreviewTab { path: path.clone(), restore, _load: None });
+        }
+
+        // 已打开的文件用标签里的内容，其中可能有未保存的修改
+        if let Some(content) = self.tab_buffer(&path, cx) {
+            if let Some(preview) = self.preview.as_mut() {
+                preview.path = path.clone();
+                preview._load = None;
+            }
+            self.show_preview(&path, &content, line, column, cx);
+            return;
+        }
+
+        let executor = cx.background_executor().clone();
+        let load_path = path.clone();
+        let task = cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let read_path = load_path.clone();
+                let content = executor
+                    .spawn(async move { std::fs::read_to_string(&read_path) })
+                    .await;
+                view.update(&mut cx, |this, cx| match content {
+                    Ok(raw) => {
+                        let content = tiecode_buffer::strip_bom(&raw).0;
+                        this.show_preview(&load_path, content, line, column, cx);
+                    }
+                    Err(err) => {
+                        warn!("Preview failed: {:?}, {}", load_path, err);
+                        let warning = format!("无法预览 {}：{}", Self::tab_label(&load_path), err);
+                        this.status_bar.update(cx, |bar, cx| bar.set_warning(Some(warning), cx));
+                    }
+                })
+                .ok();
+            }
+        });
+        if let Some(preview) = self.preview.as_mut() {
+            preview.path = path;
+            preview._load = Some(task);
+        }
+        cx.notify();
+    }
+
+    fn show_preview(&mut self, path: &PathBuf, content: &str, line: usize, column: usize, cx: &mut Context<Self>) {
+        // 读取完成前已经预览了别的文件或结束了预览
+        if self.preview.as_ref().map(|p| &p.path) != Some(path) {
+            return;
+        }
+        self.editor.update(cx, |editor, cx| {
+            editor.show_preview(path, content, cx);
+            editor.go_to_line_col(line, column, cx);
+        });
+        cx.notify();
+    }
+
+    /// 放弃预览并切回预览前的标签
+    fn cancel_preview(&mut self, cx: &mut Context<Self>) {
+        let Some(preview) = self.preview.take() else {
+            return;
+        };
+        self.editor.update(cx, |editor, _| editor.end_preview());
+        let restore = preview
+            .restore
+            .filter(|path| self.open_tabs.iter().any(|t| &t.path == path));
+        match restore {
+            Some(path) => self.open_file_path(path, cx),
+            None => {
+                self.editor.update(cx, |editor, cx| {
+                    editor.take_snapshot();
+                    editor.set_content(String::new(), cx);
+                });
+            }
+        }
+        cx.notify();
+    }
+
+    fn new_file(&mut self, _: &NewFile, _window: &mut Window, cx: &mut Context<Self>) {
+        self.open_untitled(cx);
+    }
+
+    /// 打开一个空的未命名标签，保存时需要另存为
+    /// 打开左右对照的比较标签
+    fn open_diff(&mut self, request: &cli::DiffRequest, left: &str, right: &str, cx: &mut Context<Self>) {
+        self.diff_viewer.update(cx, |viewer, cx| {
+            viewer.set_files(&request.left, left, &request.right, right, cx);
+        });
+        let path = diff_tab_path(&request.left, &request.right);
+        self.ensure_tab(&path);
+        self.open_file_path(path, cx);
+    }
+
+    fn open_untitled(&mut self, cx: &mut Context<Self>) {
+        if self.preview.take().is_some() {
+            self.editor.update(cx, |editor, _| editor.end_preview());
+        }
+        self.stash_active_editor(cx);
+        self.untitled_count += 1;
+        let path = untitled_path(self.untitled_count);
+        self.editor.update(cx, |editor, cx| {
+            editor.open_file(path.clone(), String::new(), cx);
+        });
+        self.ensure_tab(&path);
+        self.set_active_tab(path);
+        self.sync_bom_indicator(cx);
+        self.needs_focus_restore = true;
+        cx.notify();
+    }
+
+    /// 选择文件夹加入工作区
+    fn pick_workspace_folder(&mut self, cx: &mut Context<Self>) {
+        let directory = self.file_tree.read(cx).root_path().cloned();
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let mut dialog = rfd::AsyncFileDialog::new();
+                if let Some(directory) = directory {
+                    dialog = dialog.set_directory(directory);
+                }
+                let picked = dialog.pick_folder().await;
+                view.update(&mut cx, |this: &mut StartWindow, cx: &mut Context<StartWindow>| {
+                    if let Some(handle) = picked {
+                        this.add_workspace_folder(handle.path().to_path_buf(), cx);
+                    }
+                    this.needs_focus_restore = true;
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    /// 把文件夹作为另一个顶层节点加入文件树和搜索；还没有打开文件夹时直接打开它
+    fn add_workspace_folder(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        if self.file_tree.read(cx).root_path().is_none() {
+            self.open_folder(path, cx);
+            return;
+        }
+        if !self.file_tree.update(cx, |tree, cx| tree.add_root(path.clone(), cx)) {
+            return;
+        }
+        self.restore_tree_state(&path, cx);
+        self.sync_workspace_roots(cx);
+    }
+
+    /// 移除工作区中的文件夹；其下有未保存的文件时先确认
+    fn request_remove_workspace_folder(&mut self, root: PathBuf, cx: &mut Context<Self>) {
+        if self.unsaved_tabs_under(&root, cx).is_empty() {
+            self.remove_workspace_folder(&root, cx);
+        } else {
+            self.request_confirm(ConfirmAction::RemoveRoot { root }, cx);
+        }
+    }
+
+    fn remove_workspace_folder(&mut self, root: &PathBuf, cx: &mut Context<Self>) {
+        self.remember_tree_states(cx);
+        self.file_tree.update(cx, |tree, cx| tree.remove_root(root, cx));
+        // 仍在其它根目录下的标签保留
+        let closing: Vec<PathBuf> = {
+            let tree = self.file_tree.read(cx);
+            self.open_tabs
+                .iter()
+                .map(|t| t.path.clone())
+                .filter(|path| path.starts_with(root) && tree.root_for(path).is_none())
+                .collect()
+        };
+        for path in closing {
+            self.close_tab(&path, cx);
+        }
+        self.sync_workspace_roots(cx);
+    }
+
+    /// `root` 下有未保存修改的标签
+    fn unsaved_tabs_under(&self, root: &PathBuf, cx: &App) -> Vec<PathBuf> {
+        self.open_tabs
+            .iter()
+            .map(|t| &t.path)
+            .filter(|path| path.starts_with(root))
+            .filter(|path| {
+                self.tab_buffer(path, cx)
+                    .is_some_and(|buffer| !self.file_watcher.is_clean(path, &buffer))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// 查找引用和重命名符号的范围：工作区文件夹；没有打开文件夹时为当前文件所在的目录
+    fn reference_roots(&self, cx: &App) -> Vec<PathBuf> {
+        let tree = self.file_tree.read(cx);
+        let mut roots: Vec<PathBuf> = tree.root_path().cloned().into_iter().chain(tree.extra_roots().to_vec()).collect();
+        if roots.is_empty() {
+            let directory = self
+                .active_tab
+                .as_ref()
+                .filter(|path| untitled_name(path).is_none())
+                .and_then(|path| path.parent())
+                .map(|parent| parent.to_path_buf());
+            roots.extend(directory);
+        }
+        roots
+    }
+
+    /// 在工作区文件夹中查找 `word` 的引用，结果显示在底部面板
+    fn find_references(&mut self, word: String, cx: &mut Context<Self>) {
+        let roots = self.reference_roots(cx);
+        self.references_panel.update(cx, |panel, cx| panel.find(word, roots, cx));
+        self.references_visible = true;
+        self.needs_references_focus = true;
+        cx.notify();
+    }
+
+    /// 工作区文件夹变化后，同步搜索范围和文件树上的 git 状态，并记入会话
+    fn sync_workspace_roots(&mut self, cx: &mut Context<Self>) {
+        let extra_roots = self.file_tree.read(cx).extra_roots().to_vec();
+        if let Some(search_panel) = self.tool_panel.read(cx).search_panel() {
+            search_panel.update(cx, |panel, cx| panel.set_extra_roots(extra_roots, cx));
+        }
+        self.sync_git_status(cx);
+        self.save_session(cx);
+        cx.notify();
+    }
+
+    /// 把文件夹设为文件树、Git 面板和搜索的根目录
+    fn open_folder(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        self.show_folder(path.clone(), cx);
+        self.attach_git_repo(path.clone(), cx);
+        self.publish_plugin_event(PluginEvent::WorkspaceOpened(path), cx);
+        self.save_session(cx);
+        cx.notify();
+    }
+
+    /// 文件树和搜索切换到文件夹，不读取 git 仓库
+    fn show_folder(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        self.remember_tree_states(cx);
+        self.file_tree.update(cx, |tree, cx| {
+            tree.set_root_path(path.clone(), cx);
+        });
+        self.restore_tree_state(&path, cx);
+        self.check_project_settings(&path, cx);
+        self.settings_panel.update(cx, |panel, cx| panel.set_project_root(path.clone(), cx));
+        if let Some(search_panel) = self.tool_panel.read(cx).search_panel() {
+            search_panel.update(cx, |sp, cx| {
+                sp.set_root_path(path, cx);
             });
-            if !self.open_tabs.iter().any(|p| p == &path) {
-                self.open_tabs.push(path.clone());
+        }
+    }
+
+    /// 记下当前各根目录的文件树状态，之后重新打开这些文件夹时恢复
+    fn remember_tree_states(&mut self, cx: &App) {
+        let tree = self.file_tree.read(cx);
+        for root in tree.roots() {
+            let state = tree.tree_state(&root);
+            self.tree_states.insert(root, state);
+        }
+    }
+
+    fn restore_tree_state(&mut self, root: &PathBuf, cx: &mut Context<Self>) {
+        if let Some(state) = self.tree_states.get(root).cloned() {
+            self.file_tree.update(cx, |tree, cx| tree.restore_tree_state(root, &state, cx));
+        }
+    }
+
+    fn attach_git_repo(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+            git_panel.update(cx, |gp, cx| {
+                gp.set_repo_root(path, cx);
+            });
+        }
+    }
+
+    fn set_background_image(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        self.background_image = Some(path);
+        self.file_tree.update(cx, |tree: &mut FileTree, cx: &mut Context<FileTree>| {
+            tree.set_transparent(true, cx);
+        });
+        cx.notify();
+    }
+
+    /// 切换文件图标主题；插件提供的主题在内置主题的基础上扩展
+    fn select_icon_theme(&mut self, id: &str, cx: &mut Context<Self>) {
+        let Some(entry) = self.plugin_manager.read(cx).icon_theme(id).cloned() else {
+            warn!("Unknown icon theme {}", id);
+            return;
+        };
+        let default_path = default_assets_base().join(DEFAULT_ICON_THEME);
+        let base = if entry.path == default_path {
+            None
+        } else {
+            IconTheme::load(&default_path, None).ok()
+        };
+        match IconTheme::load(&entry.path, base.as_ref()) {
+            Ok(theme) => set_icon_theme(theme, cx),
+            Err(err) => {
+                warn!("{:#}", err);
+                self.status_bar.update(cx, |bar, cx| {
+                    bar.set_warning(Some(format!("无法加载图标主题：{}", entry.label)), cx)
+                });
+            }
+        }
+    }
+
+    /// 当前工作区状态；未命名标签不记录
+    fn session_state(&self, cx: &App) -> Session {
+        let tabs = self
+            .open_tabs
+            .iter()
+            .filter(|tab| untitled_name(&tab.path).is_none())
+            .map(|tab| {
+                let view = if self.active_tab.as_ref() == Some(&tab.path) && Self::is_text_path(&tab.path) {
+                    Some(self.editor.read(cx).view_position())
+                } else {
+                    tab.snapshot.as_ref().map(|snapshot| snapshot.view_position())
+                };
+                let view = view
+                    .map(|(line, column, scroll_y)| TabView { line, column, scroll_y })
+                    .or(tab.restore_view);
+                TabState { path: tab.path.clone(), view }
+            })
+            .collect();
+        let mut tree_states = self.tree_states.clone();
+        let tree = self.file_tree.read(cx);
+        for root in tree.roots() {
+            let state = tree.tree_state(&root);
+            tree_states.insert(root, state);
+        }
+        Session {
+            root: self.file_tree.read(cx).root_path().cloned(),
+            extra_roots: self.file_tree.read(cx).extra_roots().to_vec(),
+            tabs,
+            active: self.active_tab.clone().filter(|path| untitled_name(path).is_none()),
+            background_image: self.background_image.clone(),
+            commit_drafts: self
+                .tool_panel
+                .read(cx)
+                .git_panel()
+                .map(|panel| panel.read(cx).commit_drafts())
+                .unwrap_or_default(),
+            tree_states,
+            toolbar: self.toolbar.clone(),
+        }
+    }
+
+    fn save_session(&self, cx: &App) {
+        // 会话还没恢复时不能用空白状态覆盖它
+        let restoring = self.startup_tasks.iter().any(|task| matches!(task, StartupTask::RestoreSession(_)));
+        if self.primary_window && !self.session_cleared && !self.diff_mode && !restoring {
+            self.session_state(cx).save();
+        }
+    }
+
+    /// 启动时恢复上次的会话；已不存在的文件在读取时已被去掉
+    fn restore_session(&mut self, session: Session, cx: &mut Context<Self>) {
+        for tab in session.tabs {
+            if !self.open_tabs.iter().any(|t| t.path == tab.path) {
+                self.open_tabs.push(OpenTab { restore_view: tab.view, ..OpenTab::new(tab.path) });
+            }
+        }
+        let active = session.active.or_else(|| self.open_tabs.last().map(|t| t.path.clone()));
+        if let Some(active) = active {
+            self.open_file_path(active, cx);
+        }
+        if let Some(image) = session.background_image {
+            self.set_background_image(image, cx);
+        }
+        let drafts = session.commit_drafts;
+        self.tree_states = session.tree_states;
+        self.toolbar = session.toolbar;
+        match session.root {
+            Some(root) => {
+                self.show_folder(root.clone(), cx);
+                let tree_states = &self.tree_states;
+                let extra_roots = self.file_tree.update(cx, |tree, cx| {
+                    for extra in session.extra_roots {
+                        if tree.add_root(extra.clone(), cx) {
+                            if let Some(state) = tree_states.get(&extra) {
+                                tree.restore_tree_state(&extra, state, cx);
+                            }
+                        }
+                    }
+                    tree.extra_roots().to_vec()
+                });
+                if let Some(search_panel) = self.tool_panel.read(cx).search_panel() {
+                    search_panel.update(cx, |panel, cx| panel.set_extra_roots(extra_roots, cx));
+                }
+                // 仓库状态紧接着单独读取，文件树先显示出来
+                self.startup_tasks.push_front(StartupTask::GitRepo { root, drafts });
+            }
+            None => self.restore_commit_drafts(drafts, cx),
+        }
+        cx.notify();
+    }
+
+    fn restore_commit_drafts(&mut self, drafts: std::collections::BTreeMap<PathBuf, String>, cx: &mut Context<Self>) {
+        if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+            git_panel.update(cx, |panel, cx| panel.restore_commit_drafts(drafts, cx));
+        }
+    }
+
+    /// 首次绘制后开始依次执行排队的启动任务
+    fn start_deferred_startup(&mut self, cx: &mut Context<Self>) {
+        if !self.startup.mark_first_paint() {
+            return;
+        }
+        info!("First paint after {:?}", self.startup.first_paint().unwrap_or_default());
+        self.startup_task = Some(cx.spawn(|view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                loop {
+                    cx.background_executor().timer(Duration::from_millis(1)).await;
+                    let more = view
+                        .update(&mut cx, |this, cx| this.run_next_startup_task(cx))
+                        .unwrap_or(false);
+                    if !more {
+                        break;
+                    }
+                }
+            }
+        }));
+    }
+
+    /// 执行一项启动任务；队列已空时记下可交互时间并返回 false
+    fn run_next_startup_task(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(task) = self.startup_tasks.pop_front() else {
+            self.startup.mark_interactive();
+            self.status_bar.update(cx, |bar, cx| bar.set_progress(None, cx));
+            info!(
+                "Interactive after {:?}: {}",
+                self.startup.interactive().unwrap_or_default(),
+                self.startup.lines().join(", ")
+            );
+            cx.notify();
+            return false;
+        };
+        let label = task.label();
+        let started = Instant::now();
+        match task {
+            StartupTask::RestoreSession(session) => self.restore_session(session, cx),
+            StartupTask::GitRepo { root, drafts } => {
+                self.attach_git_repo(root, cx);
+                self.sync_git_status(cx);
+                self.restore_commit_drafts(drafts, cx);
+            }
+            StartupTask::DiscoverPlugins => {
+                self.plugin_manager.update(cx, |manager, _| manager.discover_plugins());
+                self.plugins_changed(cx);
+                self.emit_plugin_event(ActivationEvent::Startup, cx);
+            }
+            StartupTask::CompileGrammars => {
+                let progress = self.editor.update(cx, |editor, _| editor.compile_next_grammar());
+                if progress.is_some_and(|(done, total)| done < total) {
+                    self.startup_tasks.push_front(StartupTask::CompileGrammars);
+                }
+            }
+        }
+        self.startup.record(label, started.elapsed(), true);
+        if let Some(next) = self.startup_tasks.front() {
+            let progress = format!("正在{}…", next.label());
+            self.status_bar.update(cx, |bar, cx| bar.set_progress(Some(progress), cx));
+        }
+        if self.performance_visible {
+            cx.notify();
+        }
+        true
+    }
+
+    fn render_performance_overlay(&self, cx: &App) -> AnyElement {
+        let theme = component::theme(cx);
+        if !self.performance_visible {
+            return div().into_any_element();
+        }
+        div()
+            .absolute()
+            .top(px(40.0))
+            .right(px(16.0))
+            .w(px(240.0))
+            .flex()
+            .flex_col()
+            .bg(theme.surface.opacity(0.94))
+            .border_1()
+            .border_color(theme.list_active)
+            .rounded_md()
+            .p(px(10.0))
+            .text_size(px(12.0))
+            .text_color(theme.text)
+            .child(div().mb(px(6.0)).text_color(theme.accent).child("启动耗时"))
+            .children(self.startup.lines().into_iter().map(|line| div().whitespace_nowrap().child(line)))
+            .child(div().mt(px(8.0)).mb(px(6.0)).text_color(theme.accent).child("重绘"))
+            .child(div().whitespace_nowrap().child(format!("编辑器 notify {} 次", self.editor.read(cx).notify_count())))
+            .child(div().whitespace_nowrap().child(format!("编辑后整篇同步 {} 次", self.editor.read(cx).full_sync_count())))
+            .into_any_element()
+    }
+
+    fn focus_next_region(&mut self, _: &FocusNextRegion, window: &mut Window, cx: &mut Context<Self>) {
+        self.cycle_focus(false, window, cx);
+    }
+
+    fn focus_previous_region(&mut self, _: &FocusPreviousRegion, window: &mut Window, cx: &mut Context<Self>) {
+        self.cycle_focus(true, window, cx);
+    }
+
+    /// 显示文件页并把输入交给文件树顶部的筛选框
+    fn focus_file_tree_filter(&mut self, _: &FocusFileTreeFilter, window: &mut Window, cx: &mut Context<Self>) {
+        self.file_tree_visible = true;
+        self.tool_panel.update(cx, |panel, cx| panel.select_page("explorer", cx));
+        self.file_tree.update(cx, |tree, cx| tree.focus_filter(window, cx));
+        cx.notify();
+    }
+
+    /// F6 / Shift+F6：在编辑器、状态栏、工具栏、工具面板和标签栏之间切换焦点，隐藏或为空的区域会被跳过
+    fn cycle_focus(&mut self, backwards: bool, window: &mut Window, cx: &mut Context<Self>) {
+        let editor_focus = self.editor.read(cx).focus_handle.clone();
+        let status_bar_focus = self.status_bar.read(cx).focus_handle.clone();
+        let current = if editor_focus.contains_focused(window, cx) {
+            Some(FocusRegion::Editor)
+        } else if self.tool_panel.read(cx).contains_focus(window, cx) {
+            Some(FocusRegion::ToolPanel)
+        } else if status_bar_focus.contains_focused(window, cx) {
+            Some(FocusRegion::StatusBar)
+        } else if self.toolbar_focus.contains_focused(window, cx) {
+            Some(FocusRegion::Toolbar)
+        } else if self.tab_bar_focus.contains_focused(window, cx) {
+            Some(FocusRegion::TabBar)
+        } else {
+            None
+        };
+        let mut available = vec![FocusRegion::Editor, FocusRegion::StatusBar];
+        if self.file_tree_visible {
+            available.push(FocusRegion::ToolPanel);
+        }
+        if self.toolbar.visible && !self.toolbar.commands.is_empty() {
+            available.push(FocusRegion::Toolbar);
+        }
+        if !self.open_tabs.is_empty() {
+            available.push(FocusRegion::TabBar);
+        }
+        // 界面区域本身不是 Tab 停靠点，聚焦后移到其中的第一个控件
+        let focus_first = |region: &FocusHandle, window: &mut Window| {
+            region.focus(window);
+            window.focus_next();
+        };
+        match FocusRegion::cycle(current, &available, backwards) {
+            Some(FocusRegion::Editor) => editor_focus.focus(window),
+            Some(FocusRegion::ToolPanel) => {
+                self.tool_panel.update(cx, |panel, cx| panel.focus_active_page(window, cx));
+            }
+            Some(FocusRegion::StatusBar) => focus_first(&status_bar_focus, window),
+            Some(FocusRegion::Toolbar) => focus_first(&self.toolbar_focus, window),
+            Some(FocusRegion::TabBar) => focus_first(&self.tab_bar_focus, window),
+            None => {}
+        }
+        cx.notify();
+    }
+
+    fn focus_next_element(&mut self, _: &FocusNextElement, window: &mut Window, cx: &mut Context<Self>) {
+        self.cycle_element_focus(false, window, cx);
+    }
+
+    fn focus_previous_element(&mut self, _: &FocusPreviousElement, window: &mut Window, cx: &mut Context<Self>) {
+        self.cycle_element_focus(true, window, cx);
+    }
+
+    /// 工具栏、标签栏、状态栏等界面区域，以及打开的确认框和右键菜单，按检查顺序排列
+    fn chrome_regions(&self, cx: &App) -> Vec<FocusHandle> {
+        let mut regions = Vec::new();
+        if self.confirm_open {
+            regions.push(self.confirm_focus.clone());
+        }
+        if self.context_menu_open {
+            regions.push(self.context_menu_focus.clone());
+        }
+        regions.push(self.toolbar_focus.clone());
+        regions.push(self.tab_bar_focus.clone());
+        regions.push(self.tool_panel.read(cx).header_focus());
+        regions.push(self.status_bar.read(cx).focus_handle.clone());
+        regions
+    }
+
+    /// Tab / Shift+Tab：在焦点所在的区域内循环切换控件；焦点不在这些区域时交给编辑器、输入框等处理
+    fn cycle_element_focus(&mut self, backwards: bool, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(region) = self
+            .chrome_regions(cx)
+            .into_iter()
+            .find(|region| region.contains_focused(window, cx))
+        else {
+            cx.propagate();
+            return;
+        };
+        if !backwards {
+            window.focus_next();
+            if !region.contains_focused(window, cx) {
+                // 区域本身不是 Tab 停靠点，从它出发到达区域内的第一个控件
+                region.focus(window);
+                window.focus_next();
+            }
+            return;
+        }
+        window.focus_prev();
+        if region.contains_focused(window, cx) && !region.is_focused(window) {
+            return;
+        }
+        // 回到区域内的最后一个控件
+        region.focus(window);
+        let mut last = None;
+        for _ in 0..256 {
+            window.focus_next();
+            let Some(focused) = window.focused(cx) else {
+                break;
+            };
+            if !region.contains_focused(window, cx) || last.as_ref() == Some(&focused) {
+                break;
+            }
+            last = Some(focused);
+        }
+        match last {
+            Some(last) => last.focus(window),
+            None => region.focus(window),
+        }
+    }
+
+    /// 确认框、右键菜单打开时把焦点交给它们，关闭后还给打开前的控件
+    fn restore_overlay_focus(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.confirm_open {
+            if self.needs_confirm_focus {
+                self.needs_confirm_focus = false;
+                if self.confirm_return_focus.is_none() {
+                    self.confirm_return_focus = window.focused(cx);
+                }
+                self.confirm_default_focus.focus(window);
+            }
+        } else if let Some(handle) = self.confirm_return_focus.take() {
+            if self.confirm_focus.contains_focused(window, cx) {
+                handle.focus(window);
+            }
+        }
+        if self.context_menu_open {
+            if self.needs_context_menu_focus {
+                self.needs_context_menu_focus = false;
+                if self.context_menu_return_focus.is_none() {
+                    self.context_menu_return_focus = window.focused(cx);
+                }
+                self.context_menu_focus.focus(window);
+            }
+        } else if let Some(handle) = self.context_menu_return_focus.take() {
+            if self.context_menu_focus.contains_focused(window, cx) {
+                handle.focus(window);
+            }
+        }
+    }
+
+    fn open_file_action(&mut self, _: &OpenFile, _window: &mut Window, cx: &mut Context<Self>) {
+        self.pick_and_open(false, cx);
+    }
+
+    fn open_folder_action(&mut self, _: &OpenFolder, _window: &mut Window, cx: &mut Context<Self>) {
+        self.pick_and_open(true, cx);
+    }
+
+    /// 弹出系统对话框选择文件或文件夹并打开，结束后焦点回到编辑器
+    fn pick_and_open(&mut self, folder: bool, cx: &mut Context<Self>) {
+        let directory = self.file_tree.read(cx).root_path().cloned();
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let mut dialog = rfd::AsyncFileDialog::new();
+                if let Some(directory) = directory {
+                    dialog = dialog.set_directory(directory);
+                }
+                let picked = if folder {
+                    dialog.pick_folder().await
+                } else {
+                    dialog.pick_file().await
+                };
+                view.update(&mut cx, |this: &mut StartWindow, cx: &mut Context<StartWindow>| {
+                    if let Some(handle) = picked {
+                        let path = handle.path().to_path_buf();
+                        if folder {
+                            this.open_folder(path, cx);
+                        } else {
+                            this.open_file_path(path, cx);
+                        }
+                    }
+                    this.needs_focus_restore = true;
+                    cx.notify();
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    fn sync_bom_indicator(&mut self, cx: &mut Context<Self>) {
+        let bom = self
+            .active_tab
+            .as_ref()
+            .and_then(|p| self.find_tab(p))
+            .is_some_and(|t| t.bom);
+        self.status_bar.update(cx, |bar, cx| bar.set_bom(bom, cx));
+    }
+
+    fn toggle_bom(&mut self, cx: &mut Context<Self>) {
+        let Some(path) = self.active_tab.clone() else {
+            return;
+        };
+        if let Some(tab) = self.open_tabs.iter_mut().find(|t| t.path == path) {
+            tab.bom = !tab.bom;
+        }
+        self.sync_bom_indicator(cx);
+    }
+
+    fn set_active_tab(&mut self, path: PathBuf) {
+        self.tab_mru.activate(&path);
+        self.active_tab = Some(path);
+    }
+
+    fn switch_to_last_editor(&mut self, cx: &mut Context<Self>) {
+        if let Some(previous) = self.tab_mru.get(1).cloned() {
+            self.open_file_path(previous, cx);
+        }
+    }
+
+    fn switch_tab(&mut self, _: &SwitchTab, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.tab_mru.len() < 2 {
+            return;
+        }
+        if let Some(switcher) = self.tab_switcher.as_mut() {
+            // 按住 ctrl 连续按 tab：在列表中后移
+            switcher.index = (switcher.index + 1) % self.tab_mru.len();
+            switcher.overlay_visible = true;
+            cx.notify();
+            return;
+        }
+
+        let started_at = Instant::now();
+        self.tab_switcher = Some(TabSwitcher {
+            index: 1,
+            started_at,
+            overlay_visible: false,
+        });
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(TAB_SWITCH_HOLD_DELAY).await;
+                view.update(&mut cx, |this, cx| {
+                    if let Some(switcher) = this.tab_switcher.as_mut() {
+                        if switcher.started_at == started_at {
+                            switcher.overlay_visible = true;
+                            cx.notify();
+                        }
+                    }
+                })
+                .ok();
+            }
+        })
+        .detach();
+    }
+
+    fn on_modifiers_changed(&mut self, event: &ModifiersChangedEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        if event.modifiers.control {
+            return;
+        }
+        if let Some(switcher) = self.tab_switcher.take() {
+            if let Some(path) = self.tab_mru.get(switcher.index).cloned() {
+                self.open_file_path(path, cx);
+            }
+            cx.notify();
+        }
+    }
+
+    /// 打开搜索页，并把文件夹加入搜索范围（包含或排除）
+    fn search_in_folder(&mut self, path: PathBuf, exclude: bool, window: &mut Window, cx: &mut Context<Self>) {
+        self.file_tree_visible = true;
+        let search_panel = self.tool_panel.update(cx, |panel, cx| {
+            panel.select_page("search", cx);
+            panel.search_panel()
+        });
+        if let Some(search_panel) = search_panel {
+            search_panel.update(cx, |panel, cx| {
+                panel.add_scope(path, exclude, cx);
+                if !exclude {
+                    panel.focus(window);
+                }
+            });
+        }
+    }
+
+    /// 从界面关闭标签；有内容的未命名标签先确认，关闭后只能用 Recover Discarded Changes 命令找回
+    fn request_close_tab(&mut self, path: &PathBuf, cx: &mut Context<Self>) {
+        let has_content = untitled_name(path).is_some()
+            && self.tab_buffer(path, cx).is_some_and(|buffer| !buffer.is_empty());
+        if has_content {
+            self.request_confirm(ConfirmAction::CloseUnsaved { path: path.clone() }, cx);
+        } else {
+            self.close_tab(path, cx);
+        }
+    }
+
+    fn close_tab(&mut self, path: &PathBuf, cx: &mut Context<Self>) {
+        if self.diff_mode && Self::is_diff_path(path) {
+            // 作为 difftool 时关闭比较即结束，git 接着打开下一个文件
+            std::process::exit(0);
+        }
+        self.keep_discarded(path, cx);
+        let was_active = self.active_tab.as_ref() == Some(path);
+        self.open_tabs.retain(|t| &t.path != path);
+        self.recovered_tabs.retain(|p| p != path);
+        self.tab_mru.remove(path);
+        self.binary_tabs.retain(|(p, _)| p != path);
+        self.file_watcher.forget(path);
+        self.problems_panel.update(cx, |panel, cx| panel.remove_path(path, cx));
+        if was_active {
+            if let Some(next_path) = self
+                .tab_mru
+                .first()
+                .cloned()
+                .or_else(|| self.open_tabs.last().map(|t| t.path.clone()))
+            {
+                self.open_file_path(next_path, cx);
+            } else {
+                self.active_tab = None;
+                self.editor.update(cx, |editor, cx| {
+                    editor.take_snapshot();
+                    editor.set_content(String::new(), cx);
+                });
+            }
+        }
+        cx.notify();
+    }
+
+    /// 关闭有未保存修改的文本标签前保留其编辑状态，之后可以恢复
+    fn keep_discarded(&mut self, path: &PathBuf, cx: &mut Context<Self>) {
+        let Some(buffer) = self.tab_buffer(path, cx) else {
+            return;
+        };
+        let dirty = match untitled_name(path) {
+            Some(_) => !buffer.is_empty(),
+            None => !self.file_watcher.is_clean(path, &buffer),
+        };
+        if !dirty {
+            return;
+        }
+        let snapshot = if self.active_tab.as_ref() == Some(path) {
+            Some(self.editor.update(cx, |editor, _| editor.take_snapshot()))
+        } else {
+            self.open_tabs.iter_mut().find(|t| &t.path == path).and_then(|t| t.snapshot.take())
+        };
+        if let Some(snapshot) = snapshot {
+            self.discarded_tabs.push(path.clone(), snapshot);
+        }
+    }
+
+    /// 在新标签中恢复最近丢弃的修改，连同撤销历史；保存之前不会写入磁盘。
+    /// 文件已重新打开时放到新的未命名标签中，不覆盖其中的内容
+    fn recover_discarded(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(DiscardedTab { path, state }) = self.discarded_tabs.pop() else {
+            return false;
+        };
+        if self.preview.take().is_some() {
+            self.editor.update(cx, |editor, _| editor.end_preview());
+        }
+        let path = if self.open_tabs.iter().any(|t| t.path == path) {
+            self.untitled_count += 1;
+            untitled_path(self.untitled_count)
+        } else {
+            path
+        };
+        self.stash_active_editor(cx);
+        let mut bom = false;
+        if untitled_name(&path).is_none() {
+            // 以磁盘上的内容为保存基准，恢复的内容显示为未保存
+            if let Ok(raw) = std::fs::read_to_string(&path) {
+                let (content, has_bom) = tiecode_buffer::strip_bom(&raw);
+                self.file_watcher.mark_saved(&path, content);
+                bom = has_bom;
+            }
+        }
+        self.editor.update(cx, |editor, cx| {
+            editor.restore_snapshot(path.clone(), state, cx);
+        });
+        self.ensure_tab(&path).bom = bom;
+        self.set_active_tab(path.clone());
+        self.recovered_tabs.push(path);
+        self.sync_bom_indicator(cx);
+        self.needs_focus_restore = true;
+        cx.notify();
+        true
+    }
+
+    /// 切换分支等操作后统一检查所有已打开的文件：
+    /// 重新载入内容变化的文件，标记当前分支上不存在的文件，并刷新 git 基准内容
+    fn refresh_workspace_content(&mut self, cx: &mut Context<Self>) {
+        let active = self.active_tab.clone();
+        for tab in &mut self.open_tabs {
+            tab.missing = false;
+        }
+        let paths: Vec<PathBuf> = self
+            .open_tabs
+            .iter()
+            .map(|t| t.path.clone())
+            .filter(|path| untitled_name(path).is_none())
+            .collect();
+        for path in paths {
+            let is_active = active.as_ref() == Some(&path);
+            let in_editor = is_active && Self::is_text_path(&path);
+            let buffer = self.tab_buffer(&path, cx);
+            match workspace::check_open_file(&path, buffer.as_deref()) {
+                workspace::OpenFileState::Missing => self.ensure_tab(&path).missing = true,
+                workspace::OpenFileState::Changed(content) => {
+                    self.reload_or_confirm(path, buffer, content, cx);
+                }
+                workspace::OpenFileState::Unchanged => {
+                    if is_active && !in_editor {
+                        self.open_file_path(path, cx);
+                    }
+                }
+            }
+        }
+        if active.as_ref().is_none_or(|path| untitled_name(path).is_none()) {
+            self.editor.update(cx, |editor, cx| editor.fetch_git_base_content(cx));
+        }
+        if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+            git_panel.update(cx, |panel, cx| panel.refresh(cx));
+        }
+        cx.notify();
+    }
+
+    fn save_file(&mut self, cx: &mut Context<Self>) {
+        self.save_file_with(SaveTrigger::Manual, cx);
+    }
+
+    fn save_file_with(&mut self, trigger: SaveTrigger, cx: &mut Context<Self>) {
+        let Some(path) = self.active_tab.clone().filter(Self::is_text_path) else {
+            return;
+        };
+        // 二进制文件的标签上没有编辑器内容，写回会清空文件
+        if self.is_binary_tab(&path) {
+            return;
+        }
+        if untitled_name(&path).is_some() {
+            self.save_as(cx);
+            return;
+        }
+        if self.find_tab(&path).is_some_and(|t| t.missing) {
+            // 写回会在当前分支上凭空创建该文件
+            self.status_bar.update(cx, |bar, cx| {
+                bar.set_warning(Some("文件不在当前分支上，未保存".to_string()), cx)
+            });
+            return;
+        }
+        if self.file_watcher.changed_on_disk(&path) {
+            // 还没来得及提示重新载入，直接写入会覆盖别处的修改
+            if trigger == SaveTrigger::Manual && self.confirm_action.is_none() {
+                self.request_confirm(ConfirmAction::OverwriteExternal { path }, cx);
+            } else {
+                let warning = format!("{} 已在别处被修改，未保存", Self::tab_label(&path));
+                self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(warning), cx));
+            }
+            return;
+        }
+        // 自动保存时格式化会挪动正在输入的内容
+        let format = (self.format_on_save && trigger == SaveTrigger::Manual)
+            .then(|| self.editor.update(cx, |editor, cx| editor.format(cx)));
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                // 格式化失败已在状态栏提示，照常保存
+                if let Some(format) = format {
+                    format.await.ok();
+                }
+                // 每次保存都对当前内容重新查错，避免使用过期的结果
+                let Ok(lint) = view.update(&mut cx, |this, cx| {
+                    let version = this.editor.read(cx).core.version();
+                    let errors = (this.save_error_check != SaveErrorCheck::Off)
+                        .then(|| this.editor.update(cx, |editor, cx| editor.lint_errors_for_save(cx)));
+                    (version, errors)
+                }) else {
+                    return;
+                };
+                let (version, errors) = lint;
+                let errors = match errors {
+                    Some(errors) => Some(errors.await.unwrap_or_default()),
+                    None => None,
+                };
+                view.update(&mut cx, |this, cx| this.finish_save(path, trigger, version, errors, cx)).ok();
+            }
+        })
+        .detach();
+    }
+
+    /// 格式化和查错完成后写入：期间换了标签则放弃；内容又有变化时手动保存重新开始，自动保存留给下一次
+    fn finish_save(&mut self, path: PathBuf, trigger: SaveTrigger, version: u64, errors: Option<Vec<LintError>>, cx: &mut Context<Self>) {
+        if self.active_tab.as_ref() != Some(&path) {
+            return;
+        }
+        if self.editor.read(cx).core.version() != version {
+            if trigger == SaveTrigger::Manual {
+                self.save_file_with(trigger, cx);
+            }
+            return;
+        }
+        let mut warning = None;
+        if let Some(errors) = errors {
+            self.show_error_count(&path, &errors, cx);
+            if !errors.is_empty() {
+                match trigger {
+                    SaveTrigger::Manual => {
+                        self.pending_save = Some(PendingSave {
+                            path,
+                            errors,
+                            save_unlocked: false,
+                        });
+                        cx.notify();
+                        return;
+                    }
+                    SaveTrigger::Auto => {
+                        let name = path
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        warning = Some(format!("{} 已自动保存，但有 {} 个错误", name, errors.len()));
+                    }
+                }
+            }
+        }
+        match self.write_active_file(&path, cx) {
+            Ok(()) if trigger == SaveTrigger::Manual => self.autosave.saved_manually(&path),
+            Ok(()) => {}
+            Err(err) => {
+                self.autosave.save_failed(&path);
+                if trigger == SaveTrigger::Auto {
+                    warning = Some(format!("{} 自动保存失败：{}，手动保存之前不再自动保存", Self::tab_label(&path), err));
+                }
+            }
+        }
+        self.status_bar.update(cx, |bar, cx| bar.set_warning(warning, cx));
+    }
+
+    /// 当前标签停止编辑超过自动保存的延迟后写入磁盘
+    fn poll_autosave(&mut self, cx: &mut Context<Self>) {
+        let Some(path) = self.active_tab.clone().filter(Self::is_text_path) else {
+            return;
+        };
+        let version = self.editor.read(cx).core.version();
+        if self.autosave.poll(&path, version, Instant::now()) {
+            self.autosave_active(cx);
+        }
+    }
+
+    /// 自动保存当前标签：只写有未保存修改、可以自动保存的文件，提示框打开期间不保存
+    fn autosave_active(&mut self, cx: &mut Context<Self>) {
+        if self.confirm_open || self.pending_save.is_some() {
+            return;
+        }
+        let Some(path) = self.active_tab.clone().filter(|path| Self::is_text_path(path) && self.autosave.can_save(path)) else {
+            return;
+        };
+        let dirty = self.tab_buffer(&path, cx).is_some_and(|buffer| !self.file_watcher.is_clean(&path, &buffer));
+        if dirty {
+            self.save_file_with(SaveTrigger::Auto, cx);
+        }
+    }
+
+    /// 在状态栏显示保存前查错得到的错误数，提示第一个错误；没有错误时隐藏
+    fn show_error_count(&mut self, path: &PathBuf, errors: &[LintError], cx: &mut Context<Self>) {
+        let (text, tooltip) = match errors.first() {
+            Some(first) => (
+                format!("错误：{}", errors.len()),
+                Some(format!("{} 第 {} 行：{}", Self::tab_label(path), first.line + 1, first.message)),
+            ),
+            None => (String::new(), None),
+        };
+        self.plugin_manager.update(cx, |manager, cx| {
+            if manager.set_status_item_text(DIAGNOSTICS_ITEM, text, tooltip) {
+                cx.notify();
+            }
+        });
+    }
+
+    /// 选择新位置保存当前标签，之后标签指向新文件
+    fn save_as(&mut self, cx: &mut Context<Self>) {
+        let Some(source) = self.active_tab.clone().filter(Self::is_text_path) else {
+            return;
+        };
+        let file_name = match untitled_name(&source) {
+            Some(name) => format!("{}.t", name),
+            None => Self::tab_label(&source),
+        };
+        let directory = match untitled_name(&source) {
+            Some(_) => self.file_tree.read(cx).root_path().cloned(),
+            None => source.parent().map(|p| p.to_path_buf()),
+        };
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let mut dialog = rfd::AsyncFileDialog::new().set_file_name(&file_name);
+                if let Some(directory) = directory {
+                    dialog = dialog.set_directory(directory);
+                }
+                if let Some(file) = dialog.save_file().await {
+                    let target = file.path().to_path_buf();
+                    view.update(&mut cx, |this, cx| this.finish_save_as(source, target, cx)).ok();
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn finish_save_as(&mut self, source: PathBuf, target: PathBuf, cx: &mut Context<Self>) {
+        // 对话框打开期间标签可能已被关闭
+        let Some(content) = self.tab_buffer(&source, cx) else {
+            return;
+        };
+        if target != source {
+            // 目标文件已在别的标签中打开时，它的内容即将被覆盖
+            self.open_tabs.retain(|t| t.path != target);
+            self.tab_mru.remove(&target);
+            self.rename_tab(&source, &target);
+        }
+        if self.write_file(&target, &content, cx).is_err() {
+            if target != source {
+                self.rename_tab(&target, &source);
+            }
+            self.status_bar.update(cx, |bar, cx| {
+                bar.set_warning(Some("另存为失败".to_string()), cx)
+            });
+            return;
+        }
+        if self.active_tab.as_ref() == Some(&target) {
+            // 换成新文件的文档 URI，保留光标和撤销历史
+            self.editor.update(cx, |editor, cx| {
+                let snapshot = editor.take_snapshot();
+                editor.restore_snapshot(target.clone(), snapshot, cx);
+            });
+            self.sync_bom_indicator(cx);
+        }
+        self.status_bar.update(cx, |bar, cx| bar.set_warning(None, cx));
+        self.file_tree.update(cx, |tree, cx| {
+            tree.refresh();
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    /// 标签改为指向 `dst`，保留其位置和各项标记
+    fn rename_tab(&mut self, src: &PathBuf, dst: &PathBuf) {
+        if let Some(tab) = self.open_tabs.iter_mut().find(|t| &t.path == src) {
+            tab.path = dst.clone();
+        }
+        self.tab_mru.rename(src, dst);
+        if let Some(index) = self.recovered_tabs.iter().position(|p| p == src) {
+            self.recovered_tabs[index] = dst.clone();
+        }
+        if let Some((path, _)) = self.binary_tabs.iter_mut().find(|(p, _)| p == src) {
+            *path = dst.clone();
+        }
+        self.file_watcher.rename(src, dst);
+        if self.active_tab.as_ref() == Some(src) {
+            self.active_tab = Some(dst.clone());
+        }
+    }
+
+    /// 打开审阅清单，从第一个变更文件开始
+    fn start_review(&mut self, cx: &mut Context<Self>) {
+        let root = self
+            .tool_panel
+            .read(cx)
+            .git_panel()
+            .and_then(|git_panel| git_panel.read(cx).repo_root().cloned());
+        let Some(root) = root else {
+            self.status_bar.update(cx, |bar, cx| bar.set_warning(Some("请先打开一个 git 仓库".to_string()), cx));
+            return;
+        };
+        self.review_visible = true;
+        self.needs_review_focus = true;
+        self.review_panel.update(cx, |panel, cx| {
+            panel.refresh(&root, cx);
+            panel.open_current(cx);
+        });
+        cx.notify();
+    }
+
+    /// 文件在审阅期间被修改或提交后重新读取清单
+    fn refresh_review(&mut self, cx: &mut Context<Self>) {
+        if !self.review_visible {
+            return;
+        }
+        if let Some(root) = self.review_panel.read(cx).repo_root().cloned() {
+            self.review_panel.update(cx, |panel, cx| panel.refresh(&root, cx));
+        }
+    }
+
+    fn close_review(&mut self, cx: &mut Context<Self>) {
+        self.review_visible = false;
+        if let Some(tab) = self.review_tab.take() {
+            self.close_tab(&tab, cx);
+        }
+        self.needs_focus_restore = true;
+        cx.notify();
+    }
+
+    /// 在对照标签中打开审阅的文件，替换上一个审阅的对照
+    fn open_review_diff(&mut self, path: &Path, relative: &str, head: &str, current: &str, cx: &mut Context<Self>) {
+        let left = PathBuf::from(format!("HEAD:{}", relative));
+        self.diff_viewer.update(cx, |viewer, cx| viewer.set_files(&left, head, path, current, cx));
+        let tab = diff_tab_path(&left, path);
+        self.ensure_tab(&tab);
+        self.open_file_path(tab.clone(), cx);
+        if let Some(previous) = self.review_tab.replace(tab.clone()).filter(|previous| *previous != tab) {
+            self.close_tab(&previous, cx);
+        }
+        self.needs_review_focus = true;
+    }
+
+    /// 把 git 面板刷新得到的文件状态交给文件树显示
+    fn sync_git_status(&mut self, cx: &mut Context<Self>) {
+        let Some(git_panel) = self.tool_panel.read(cx).git_panel() else {
+            return;
+        };
+        let mut status = git_panel.read(cx).file_status().clone();
+        // 后来加入工作区的文件夹各自读取所在仓库的状态
+        for root in self.file_tree.read(cx).extra_roots().to_vec() {
+            status.extend(GitStatusMap::for_repo(&root));
+        }
+        self.file_tree.update(cx, |tree, cx| tree.set_git_status(status, cx));
+    }
+
+    /// 在文件树中展开并选中当前标签的文件；文件不在已打开的文件夹内时在状态栏说明并返回 false
+    fn reveal_active_file(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(path) = self.active_tab.clone() else {
+            self.status_bar.update(cx, |bar, cx| {
+                bar.set_warning(Some("没有打开的文件".to_string()), cx)
+            });
+            return false;
+        };
+        let revealed = self.file_tree.update(cx, |tree, cx| tree.reveal_path(&path, cx));
+        if !revealed {
+            self.status_bar.update(cx, |bar, cx| {
+                bar.set_warning(Some("当前文件不在已打开的文件夹中".to_string()), cx)
+            });
+            return false;
+        }
+        self.file_tree_visible = true;
+        self.tool_panel.update(cx, |panel, cx| panel.select_page("explorer", cx));
+        cx.notify();
+        true
+    }
+
+    /// 文件树中的拖动经过标签栏或编辑区
+    fn tree_drag_over(&mut self, target: TreeDropTarget, position: Point<Pixels>, cx: &mut Context<Self>) {
+        if !self.file_tree.read(cx).is_dragging() {
+            return;
+        }
+        self.file_tree.update(cx, |tree, cx| tree.drag_outside(position, cx));
+        if self.tree_drop_target != Some(target) {
+            self.tree_drop_target = Some(target);
+            cx.notify();
+        }
+    }
+
+    /// 在标签栏或编辑区放下文件树中拖动的项：打开文件，文件夹则在文件树中展开
+    fn drop_tree_drag(&mut self, cx: &mut Context<Self>) {
+        self.tree_drop_target = None;
+        let paths = self.file_tree.update(cx, |tree, cx| tree.take_drag(cx));
+        for path in paths {
+            if path.is_dir() {
+                self.file_tree.update(cx, |tree, cx| tree.expand_and_reveal(&path, cx));
+                self.file_tree_visible = true;
+                self.tool_panel.update(cx, |panel, cx| panel.select_page("explorer", cx));
+            } else {
+                self.open_file_path(path, cx);
+            }
+        }
+        cx.notify();
+    }
+
+    /// 接受文件树拖动的区域，拖动经过时加上高亮
+    fn tree_drop_zone(&self, target: TreeDropTarget, content: AnyElement, cx: &mut Context<Self>) -> Stateful<Div> {
+        let theme = component::theme(cx);
+        let id = match target {
+            TreeDropTarget::TabBar => "tree-drop-tab-bar",
+            TreeDropTarget::Editor => "tree-drop-editor",
+        };
+        div()
+            .id(id)
+            .relative()
+            .flex()
+            .flex_col()
+            .w_full()
+            .on_mouse_move(cx.listener(move |this, event: &MouseMoveEvent, _window, cx| {
+                this.tree_drag_over(target, event.position, cx);
+            }))
+            .on_hover(cx.listener(move |this, hovered: &bool, _window, cx| {
+                if !*hovered && this.tree_drop_target == Some(target) {
+                    this.tree_drop_target = None;
+                    cx.notify();
+                }
+            }))
+            .on_mouse_up(MouseButton::Left, cx.listener(|this, _: &MouseUpEvent, _window, cx| {
+                if this.file_tree.read(cx).is_dragging() {
+                    this.drop_tree_drag(cx);
+                    cx.stop_propagation();
+                }
+            }))
+            .child(content)
+            .child(if self.tree_drop_target == Some(target) {
+                div()
+                    .absolute()
+                    .top_0()
+                    .left_0()
+                    .size_full()
+                    .border_2()
+                    .border_color(theme.accent)
+                    .bg(theme.accent.opacity(0.12))
+            } else {
+                div()
+            })
+    }
+
+    /// 当前标签对应的磁盘文件；没有时在状态栏提示
+    fn active_file_on_disk(&mut self, cx: &mut Context<Self>) -> Option<PathBuf> {
+        let path = self.active_tab.clone().filter(|path| path.exists());
+        if path.is_none() {
+            self.status_bar.update(cx, |bar, cx| {
+                bar.set_warning(Some("没有打开的文件".to_string()), cx)
+            });
+        }
+        path
+    }
+
+    fn reveal_in_file_manager(&mut self, path: &Path, cx: &mut Context<Self>) {
+        if let Err(err) = system_open::reveal_in_file_manager(path) {
+            warn!("Failed to reveal {:?} in file manager: {}", path, err);
+            self.status_bar.update(cx, |bar, cx| {
+                bar.set_warning(Some(format!("无法打开文件管理器: {}", err)), cx)
+            });
+        }
+    }
+
+    /// 在终端中打开文件夹，或文件所在的文件夹
+    fn open_terminal_at(&mut self, path: &Path, cx: &mut Context<Self>) {
+        if let Err(err) = system_open::open_terminal(path) {
+            warn!("Failed to open terminal at {:?}: {}", path, err);
+            self.status_bar.update(cx, |bar, cx| {
+                bar.set_warning(Some(format!("无法打开终端: {}", err)), cx)
+            });
+        }
+    }
+
+    /// 文件或文件夹在磁盘上改名或移动后，让其下已打开的标签指向新位置；
+    /// 当前文件受影响时编辑器换用新的文档 URI，保留光标和撤销历史
+    fn path_renamed(&mut self, from: &PathBuf, to: &PathBuf, cx: &mut Context<Self>) {
+        let renamed: Vec<(PathBuf, PathBuf)> = self
+            .open_tabs
+            .iter()
+            .filter_map(|t| rename_path(&t.path, from, to).map(|dst| (t.path.clone(), dst)))
+            .collect();
+        let active = self.active_tab.clone();
+        for (src, dst) in &renamed {
+            self.rename_tab(src, dst);
+            if active.as_ref() == Some(src) {
+                self.editor.update(cx, |editor, cx| {
+                    let snapshot = editor.take_snapshot();
+                    editor.restore_snapshot(dst.clone(), snapshot, cx);
+                });
+            }
+        }
+        if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+            git_panel.update(cx, |panel, cx| panel.refresh(cx));
+        }
+        cx.notify();
+    }
+
+    /// 结绳源文件改名后在工作区中查找对它的引用，找到时询问是否一并更新；不更新时保留单纯的改名
+    fn offer_reference_update(&mut self, from: &PathBuf, to: &PathBuf, cx: &mut Context<Self>) {
+        let Some(renamed) = RenamedSource::new(from, to) else {
+            return;
+        };
+        let tree = self.file_tree.read(cx);
+        let Some(root) = tree.root_path().cloned() else {
+            return;
+        };
+        let mut roots = vec![root];
+        roots.extend(tree.extra_roots().iter().cloned());
+        let query = renamed.search_text().to_string();
+        let (from, to) = (from.clone(), to.clone());
+        let executor = cx.background_executor().clone();
+        self._reference_scan = Some(cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                let matches = executor
+                    .spawn(async move { search_files(&roots, &query, &[], REFERENCE_SCAN_LIMIT) })
+                    .await;
+                view.update(&mut cx, |this, cx| {
+                    let mut candidates: Vec<PathBuf> = matches.into_iter().map(|m| m.path).collect();
+                    // 未保存的修改只在编辑器中，磁盘上搜索不到
+                    candidates.extend(
+                        this.open_tabs
+                            .iter()
+                            .map(|tab| tab.path.clone())
+                            .filter(|path| untitled_name(path).is_none() && Self::is_text_path(path)),
+                    );
+                    candidates.sort();
+                    candidates.dedup();
+                    let files: Vec<(PathBuf, usize)> = candidates
+                        .into_iter()
+                        .filter_map(|path| {
+                            let content = this.reference_content(&path, cx)?;
+                            let count = renamed.reference_edits(&path, &content).len();
+                            (count > 0).then_some((path, count))
+                        })
+                        .collect();
+                    if !files.is_empty() && this.confirm_action.is_none() {
+                        this.request_confirm(ConfirmAction::UpdateReferences { from, to, files }, cx);
+                    }
+                })
+                .ok();
+            }
+        }));
+    }
+
+    /// 查找引用时文件的内容：已打开的取编辑器中的内容，否则读取磁盘
+    fn reference_content(&self, path: &PathBuf, cx: &App) -> Option<String> {
+        if let Some(buffer) = self.tab_buffer(path, cx) {
+            return Some(buffer);
+        }
+        let raw = std::fs::read_to_string(path).ok()?;
+        Some(tiecode_buffer::strip_bom(&raw).0.to_string())
+    }
+
+    /// 按 tiec 给出的编辑在各个文件中重命名 `old_name`：当前标签和有未保存修改的标签在编辑器中修改（一次撤销），
+    /// 其余写入磁盘。写入某个文件失败时还原已写入的文件，不做任何修改
+    fn apply_rename(&mut self, old_name: &str, files: Vec<(PathBuf, Vec<TextEdit>)>, cx: &mut Context<Self>) {
+        let mut in_memory = Vec::new();
+        // 文件、原始内容、修改后的内容（不含 BOM）和是否带 BOM
+        let mut on_disk: Vec<(PathBuf, String, String, bool)> = Vec::new();
+        let mut count = 0;
+        for (path, file_edits) in files {
+            if let Some(buffer) = self.tab_buffer(&path, cx) {
+                if self.active_tab.as_ref() == Some(&path) || !self.file_watcher.is_clean(&path, &buffer) {
+                    let edits = text_edit_ranges(&Rope::from_str(&buffer), &file_edits);
+                    count += edits.len();
+                    in_memory.push((path, edits));
+                    continue;
+                }
+            }
+            let Ok(raw) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let (content, bom) = tiecode_buffer::strip_bom(&raw);
+            let edits = text_edit_ranges(&Rope::from_str(content), &file_edits);
+            count += edits.len();
+            let updated = apply_edits(content, &edits);
+            on_disk.push((path, raw.clone(), updated, bom));
+        }
+        if count == 0 {
+            self.status_bar.update(cx, |bar, cx| bar.flash(format!("没有需要修改的“{}”", old_name), cx));
+            return;
+        }
+        let mut written: Vec<(&PathBuf, &String)> = Vec::new();
+        for (path, raw, updated, bom) in &on_disk {
+            if let Err(err) = std::fs::write(path, tiecode_buffer::with_bom(updated, *bom)) {
+                log_channel(OutputChannel::App, format!("Failed to rename symbol in {:?}: {}", path, err));
+                for (path, raw) in written {
+                    let _ = std::fs::write(path, raw);
+                }
+                self.status_bar.update(cx, |bar, cx| {
+                    bar.set_warning(Some(format!("重命名失败，未做任何修改：{}", err)), cx)
+                });
+                return;
+            }
+            written.push((path, raw));
+        }
+
+        let file_count = on_disk.len() + in_memory.len();
+        for (path, _, updated, _) in on_disk {
+            if self.open_tabs.iter().any(|tab| tab.path == path) {
+                self.reload_from_disk(&path, updated, cx);
+            }
+        }
+        for (path, edits) in in_memory {
+            if self.active_tab.as_ref() == Some(&path) {
+                self.editor.update(cx, |editor, cx| editor.apply_edits(edits, cx));
+            } else if let Some(snapshot) = self
+                .open_tabs
+                .iter_mut()
+                .find(|tab| tab.path == path)
+                .and_then(|tab| tab.snapshot.as_mut())
+            {
+                snapshot.apply_edits(edits);
+            }
+        }
+        if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+            git_panel.update(cx, |panel, cx| panel.refresh(cx));
+        }
+        self.status_bar.update(cx, |bar, cx| {
+            bar.flash(format!("已在 {} 个文件中重命名 {} 处", file_count, count), cx)
+        });
+        cx.notify();
+    }
+
+    /// 更新对改名文件的引用：有未保存修改的标签在编辑器中修改（可以撤销），其余写入磁盘。
+    /// 写入某个文件失败时还原已写入的文件，不做任何修改
+    fn update_references(&mut self, from: &PathBuf, to: &PathBuf, files: Vec<(PathBuf, usize)>, cx: &mut Context<Self>) {
+        let Some(renamed) = RenamedSource::new(from, to) else {
+            return;
+        };
+        let mut in_memory = Vec::new();
+        // 文件、原始内容、修改后的内容（不含 BOM）和是否带 BOM
+        let mut on_disk: Vec<(PathBuf, String, String, bool)> = Vec::new();
+        let mut count = 0;
+        for (path, _) in files {
+            if let Some(buffer) = self.tab_buffer(&path, cx) {
+                if !self.file_watcher.is_clean(&path, &buffer) {
+                    let edits = renamed.reference_edits(&path, &buffer);
+                    count += edits.len();
+                    if !edits.is_empty() {
+                        in_memory.push((path, edits));
+                    }
+                    continue;
+                }
+            }
+            let Ok(raw) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let (content, bom) = tiecode_buffer::strip_bom(&raw);
+            let edits = renamed.reference_edits(&path, content);
+            if edits.is_empty() {
+                continue;
+            }
+            count += edits.len();
+            let updated = apply_edits(content, &edits);
+            on_disk.push((path, raw.clone(), updated, bom));
+        }
+
+        let mut written: Vec<(&PathBuf, &String)> = Vec::new();
+        for (path, raw, updated, bom) in &on_disk {
+            if let Err(err) = std::fs::write(path, tiecode_buffer::with_bom(updated, *bom)) {
+                warn!("Failed to update references in {:?}: {}", path, err);
+                for (path, raw) in written {
+                    let _ = std::fs::write(path, raw);
+                }
+                self.status_bar.update(cx, |bar, cx| {
+                    bar.set_warning(Some(format!("更新引用失败，未做任何修改：{}", err)), cx)
+                });
+                return;
+            }
+            written.push((path, raw));
+        }
+
+        let file_count = on_disk.len() + in_memory.len();
+        for (path, _, updated, _) in on_disk {
+            if self.open_tabs.iter().any(|tab| tab.path == path) {
+                self.reload_from_disk(&path, updated, cx);
+            }
+        }
+        for (path, edits) in in_memory {
+            if self.active_tab.as_ref() == Some(&path) {
+                self.editor.update(cx, |editor, cx| editor.apply_edits(edits, cx));
+            } else if let Some(snapshot) = self
+                .open_tabs
+                .iter_mut()
+                .find(|tab| tab.path == path)
+                .and_then(|tab| tab.snapshot.as_mut())
+            {
+                snapshot.apply_edits(edits);
+            }
+        }
+        if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+            git_panel.update(cx, |panel, cx| panel.refresh(cx));
+        }
+        self.status_bar.update(cx, |bar, cx| {
+            bar.set_warning(Some(format!("已更新 {} 个文件中的 {} 处引用", file_count, count)), cx)
+        });
+        cx.notify();
+    }
+
+    /// 把文件树中复制或剪切的项粘贴到文件夹 `dir`；剪切的项移动后已打开的标签随之指向新位置
+    fn paste_into(&mut self, dir: &PathBuf, cx: &mut Context<Self>) {
+        let Some(clipboard) = self.file_clipboard.clone() else {
+            return;
+        };
+        match clipboard.paste_into(dir) {
+            Ok(PasteOutcome::Copied(_)) => {
+                if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+                    git_panel.update(cx, |panel, cx| panel.refresh(cx));
+                }
+            }
+            Ok(PasteOutcome::Moved { from, to }) => {
+                self.file_clipboard = None;
+                self.path_renamed(&from, &to, cx);
+            }
+            Ok(PasteOutcome::Unchanged) => {
+                self.file_clipboard = None;
+                return;
+            }
+            Err(err) => {
+                warn!("Paste failed: {:?} -> {:?}, {}", clipboard.path, dir, err);
+                self.status_bar.update(cx, |bar, cx| {
+                    bar.set_warning(Some(format!("粘贴失败: {}", err)), cx)
+                });
+                return;
+            }
+        }
+        self.file_tree.update(cx, |tree, cx| {
+            tree.refresh();
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    /// 处理外部对已打开文件的修改：没有未保存修改的直接重新载入，有修改的询问用户；
+    /// 文件被删除时在标签上标出
+    fn poll_file_changes(&mut self, cx: &mut Context<Self>) {
+        let paths: Vec<PathBuf> = self
+            .open_tabs
+            .iter()
+            .map(|t| t.path.clone())
+            .filter(|path| untitled_name(path).is_none() && !Self::is_diff_path(path))
+            .collect();
+        self.file_watcher.sync(&paths);
+        self.sync_open_documents(&paths, cx);
+        self.publish_buffer_change(&paths, cx);
+        self.poll_autosave(cx);
+        for path in self.file_watcher.poll(Instant::now()) {
+            if paths.contains(&path) {
+                self.external_file_change(path, cx);
+            }
+        }
+        let grammars = self.grammar_watcher.as_mut().map(|watcher| watcher.poll(Instant::now())).unwrap_or_default();
+        for path in grammars {
+            self.reload_grammar_file(&path, cx);
+        }
+    }
+
+    /// 把本窗口的标签记入所有窗口共用的文档表；共享编辑时与其它窗口同步当前标签的内容
+    fn sync_open_documents(&mut self, paths: &[PathBuf], cx: &mut Context<Self>) {
+        let window = self.window_handle;
+        self.shared_seen.retain(|path, _| paths.contains(path));
+        cx.default_global::<OpenDocuments>().sync_window(window, paths);
+        let Some(path) = self.active_tab.clone().filter(|path| paths.contains(path) && Self::is_text_path(path)) else {
+            return;
+        };
+        let Some(buffer) = self.tab_buffer(&path, cx) else {
+            return;
+        };
+        let dirty = !self.file_watcher.is_clean(&path, &buffer);
+        let documents = cx.default_global::<OpenDocuments>();
+        documents.set_dirty(&path, window, dirty);
+        if documents.open_elsewhere(&path, window).is_none() || !self.share_documents(cx) {
+            return;
+        }
+        let seen = self.shared_seen.get(&path).copied().unwrap_or(0);
+        match cx.default_global::<OpenDocuments>().sync_shared(&path, seen, &buffer) {
+            SharedSync::Pull(shared) => {
+                self.shared_seen.insert(path, shared.version);
+                if let Some(edit) = minimal_edit(&buffer, &shared.content) {
+                    self.editor.update(cx, |editor, cx| editor.apply_edits(vec![edit], cx));
+                }
+            }
+            SharedSync::Published(version) => {
+                self.shared_seen.insert(path, version);
+            }
+            SharedSync::Unchanged => {}
+        }
+    }
+
+    /// 当前标签的缓冲区自上次检查后有编辑时发布 [`PluginEvent::BufferChanged`]；
+    /// 随文件变化检查定时进行，期间的多次编辑合并为一次
+    fn publish_buffer_change(&mut self, paths: &[PathBuf], cx: &mut Context<Self>) {
+        self.buffer_versions.retain(|path, _| paths.contains(path));
+        let Some(path) = self.active_tab.clone().filter(|path| paths.contains(path) && Self::is_text_path(path)) else {
+            return;
+        };
+        let version = self.editor.read(cx).core.version();
+        // 刚打开或切换到的标签只记下版本
+        match self.buffer_versions.insert(path.clone(), version) {
+            Some(seen) if seen != version => self.publish_plugin_event(PluginEvent::BufferChanged { path, version }, cx),
+            _ => {}
+        }
+    }
+
+    /// 当前项目是否允许多个窗口同时编辑同一文件
+    fn share_documents(&self, cx: &App) -> bool {
+        self.file_tree.read(cx).root_path().is_some_and(|root| open_documents::load_share_documents(root))
+    }
+
+    /// 打开文件前检查其它窗口：已在另一个窗口中打开时切换过去并返回 false；
+    /// 允许共享编辑时照常打开，内容以那个窗口中的为准
+    fn claim_document(&mut self, path: &PathBuf, cx: &mut Context<Self>) -> bool {
+        if untitled_name(path).is_some() || Self::is_diff_path(path) || self.open_tabs.iter().any(|t| &t.path == path) {
+            return true;
+        }
+        let window = self.window_handle;
+        let Some((owner, dirty)) = cx.default_global::<OpenDocuments>().open_elsewhere(path, window) else {
+            cx.default_global::<OpenDocuments>().open(path, window);
+            return true;
+        };
+        let Some(handle) = owner.downcast::<StartWindow>() else {
+            return true;
+        };
+        if self.share_documents(cx) {
+            let content = handle.read(cx).ok().and_then(|other| other.tab_buffer(path, cx));
+            let documents = cx.default_global::<OpenDocuments>();
+            if let Some(content) = content {
+                documents.publish(path, &content);
+            }
+            documents.open(path, window);
+            return true;
+        }
+        let target = path.clone();
+        let focused = handle.update(cx, |this, window, cx| {
+            window.activate_window();
+            this.open_file_path(target, cx);
+        });
+        if focused.is_err() {
+            // 那个窗口已经关闭
+            return true;
+        }
+        let note = if dirty { "，有未保存的修改" } else { "" };
+        let warning = format!("{} 已在另一个窗口中打开{}", Self::tab_label(path), note);
+        self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(warning), cx));
+        false
+    }
+
+    /// 语法文件改动后重新编译；出错时保留原来的语法并在状态栏提示
+    fn reload_grammar_file(&mut self, path: &Path, cx: &mut Context<Self>) {
+        let Some(index) = grammar_index_for_asset(path) else {
+            return;
+        };
+        // 保存时可能先删除再写入，文件暂时不存在时等下一次事件
+        let Ok(source) = fs::read_to_string(path) else {
+            return;
+        };
+        match self.editor.update(cx, |editor, cx| editor.reload_grammar(index, source, cx)) {
+            Ok(()) => info!("Reloaded grammar {:?}", path),
+            Err(err) => {
+                warn!("{}", err);
+                self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(err), cx));
+            }
+        }
+    }
+
+    fn external_file_change(&mut self, path: PathBuf, cx: &mut Context<Self>) {
+        let buffer = self.tab_buffer(&path, cx);
+        match workspace::check_open_file(&path, buffer.as_deref()) {
+            workspace::OpenFileState::Missing => {
+                if let Some(tab) = self.open_tabs.iter_mut().find(|t| t.path == path && !t.deleted && !t.missing) {
+                    tab.deleted = true;
+                    cx.notify();
+                }
+            }
+            workspace::OpenFileState::Unchanged => {
+                // 被删除的文件又出现了，例如改名保存
+                if let Some(tab) = self.open_tabs.iter_mut().find(|t| t.path == path && t.deleted) {
+                    tab.deleted = false;
+                    cx.notify();
+                }
+                // 另一个窗口保存了与本窗口相同的内容，例如共享编辑的文件
+                let saved_elsewhere = buffer.filter(|buffer| {
+                    !self.file_watcher.is_clean(&path, buffer)
+                        && fs::read_to_string(&path).is_ok_and(|raw| tiecode_buffer::strip_bom(&raw).0 == buffer)
+                });
+                if let Some(buffer) = saved_elsewhere {
+                    self.mark_tab_saved(&path, &buffer, cx);
+                    cx.notify();
+                }
+            }
+            workspace::OpenFileState::Changed(content) => {
+                if let Some(tab) = self.open_tabs.iter_mut().find(|t| t.path == path) {
+                    tab.deleted = false;
+                }
+                self.reload_or_confirm(path, buffer, content, cx);
+            }
+        }
+    }
+
+    /// 磁盘内容变了：没有未保存修改的标签直接重新载入，否则先询问
+    fn reload_or_confirm(&mut self, path: PathBuf, buffer: Option<String>, content: String, cx: &mut Context<Self>) {
+        let clean = buffer.is_some_and(|buffer| self.file_watcher.is_clean(&path, &buffer));
+        if clean {
+            self.reload_from_disk(&path, content, cx);
+        } else if self.confirm_action.is_none() {
+            self.request_confirm(ConfirmAction::ReloadExternal { path }, cx);
+        } else {
+            self.file_watcher.retry(path);
+        }
+    }
+
+    /// 用磁盘内容替换标签的缓冲区；后台标签在下次激活时重新读取，保留光标位置
+    fn reload_from_disk(&mut self, path: &PathBuf, content: String, cx: &mut Context<Self>) {
+        self.file_watcher.mark_saved(path, &content);
+        if self.active_tab.as_ref() == Some(path) && Self::is_text_path(path) {
+            self.editor.update(cx, |editor, cx| editor.reload_content(content, cx));
+        } else if let Some(tab) = self.open_tabs.iter_mut().find(|t| &t.path == path) {
+            if let Some(snapshot) = tab.snapshot.take() {
+                let (line, column, scroll_y) = snapshot.view_position();
+                tab.restore_view = Some(TabView { line, column, scroll_y });
+            }
+        }
+        cx.notify();
+    }
+
+    fn write_active_file(&mut self, path: &PathBuf, cx: &mut Context<Self>) -> std::io::Result<()> {
+        let content = self.editor.read(cx).core.content.to_string();
+        self.write_file(path, &content, cx)
+    }
+
+    /// 以 `content` 作为标签在磁盘上的内容，标签不再显示为有修改
+    fn mark_tab_saved(&mut self, path: &PathBuf, content: &str, cx: &mut Context<Self>) {
+        self.file_watcher.mark_saved(path, content);
+        if self.active_tab.as_ref() == Some(path) {
+            self.editor.update(cx, |editor, cx| editor.mark_saved(content, cx));
+        } else if let Some(snapshot) = self
+            .open_tabs
+            .iter_mut()
+            .find(|t| &t.path == path)
+            .and_then(|t| t.snapshot.as_mut())
+        {
+            snapshot.mark_saved(content);
+        }
+    }
+
+    fn write_file(&mut self, path: &PathBuf, content: &str, cx: &mut Context<Self>) -> std::io::Result<()> {
+        let bytes = tiecode_buffer::with_bom(content, self.find_tab(path).is_some_and(|t| t.bom));
+        if let Err(e) = std::fs::write(path, bytes) {
+            log_channel(OutputChannel::App, format!("Failed to save {:?}: {}", path, e));
+            return Err(e);
+        }
+        if let Some(tab) = self.open_tabs.iter_mut().find(|t| &t.path == path) {
+            tab.deleted = false;
+        }
+        self.recovered_tabs.retain(|p| p != path);
+        self.mark_tab_saved(path, content, cx);
+        self.publish_plugin_event(PluginEvent::FileSaved(path.clone()), cx);
+        if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+            git_panel.update(cx, |panel, cx| panel.refresh(cx));
+        }
+        self.refresh_review(cx);
+        if path.ends_with(PROJECT_SETTINGS_FILE) {
+            self.project_settings_saved(path, content, cx);
+        }
+        if path.file_name().is_some_and(|name| name == ".gitignore") {
+            self.file_tree.update(cx, |tree, cx| tree.reload_ignore_rules(cx));
+        }
+        Ok(())
+    }
+
+    /// 保存项目设置后先校验 tiec 一节，再询问是否重启 IDE 服务
+    fn project_settings_saved(&mut self, path: &PathBuf, content: &str, cx: &mut Context<Self>) {
+        let Some(root) = path.parent().and_then(Path::parent) else {
+            return;
+        };
+        let valid = self.report_project_settings(path, root, content, cx);
+        if self.file_tree.read(cx).roots().iter().any(|r| r == root) {
+            let root = root.to_path_buf();
+            self.settings_panel.update(cx, |panel, cx| panel.set_project_root(root, cx));
+        }
+        // editorOverrides 和 editor 一节在下次打开文件时生效，这里只提示写错的键
+        let (_, mut override_warnings) = OverrideRules::parse(content);
+        override_warnings.extend(HoverDelays::parse(content).1);
+        override_warnings.extend(enter_rules::parse_enabled(content).1);
+        override_warnings.extend(open_documents::parse_share_documents(content).1);
+        if !override_warnings.is_empty() {
+            let warning = format!("编辑器设置有误：{}", override_warnings.join("；"));
+            self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(warning), cx));
+        }
+        if !valid {
+            // 设置有误时服务会忽略它们，不必重启
+            self.tool_panel.update(cx, |panel, cx| panel.select_page("problems", cx));
+            return;
+        }
+        self.request_confirm(ConfirmAction::RestartLanguageService { settings: path.clone() }, cx);
+    }
+
+    /// 把 tiec 一节的错误列在问题页，没有错误时清除上次的结果；返回设置是否可用
+    fn report_project_settings(&mut self, path: &Path, root: &Path, content: &str, cx: &mut Context<Self>) -> bool {
+        let problems: Vec<Problem> = check_project_settings(content, root)
+            .into_iter()
+            .map(|error| Problem {
+                line: error.line,
+                column: error.column,
+                message: error.message,
+                severity: ProblemSeverity::Error,
+            })
+            .collect();
+        let valid = problems.is_empty();
+        let path = path.to_path_buf();
+        self.problems_panel.update(cx, |panel, cx| panel.set_problems(path, problems, cx));
+        valid
+    }
+
+    /// 打开文件夹和编译前检查项目设置；没有设置文件时视为可用
+    fn check_project_settings(&mut self, root: &Path, cx: &mut Context<Self>) -> bool {
+        let path = root.join(PROJECT_SETTINGS_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => self.report_project_settings(&path, root, &content, cx),
+            Err(_) => {
+                self.problems_panel.update(cx, |panel, cx| panel.remove_path(&path, cx));
+                true
+            }
+        }
+    }
+
+    /// 项目设置页点击保存：写入设置文件，再走与编辑器中保存设置文件相同的流程
+    fn save_project_settings(&mut self, path: &PathBuf, content: &str, cx: &mut Context<Self>) {
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, content));
+        if let Err(err) = written {
+            warn!("Failed to save project settings {:?}: {}", path, err);
+            self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(format!("无法保存项目设置：{}", err)), cx));
+            return;
+        }
+        self.status_bar.update(cx, |bar, cx| bar.flash("项目设置已保存".to_string(), cx));
+        self.project_settings_saved(path, content, cx);
+    }
+
+    fn save_anyway(&mut self, cx: &mut Context<Self>) {
+        if let Some(pending) = self.pending_save.take() {
+            // 提示条显示期间切换了文件时，编辑器里已不是这份内容
+            if self.active_tab.as_ref() == Some(&pending.path) {
+                let _ = self.write_active_file(&pending.path, cx);
+                self.status_bar.update(cx, |bar, cx| bar.set_warning(None, cx));
+            }
+        }
+        self.needs_focus_restore = true;
+        cx.notify();
+    }
+
+    fn cancel_pending_save(&mut self, cx: &mut Context<Self>) {
+        self.pending_save = None;
+        self.needs_focus_restore = true;
+        cx.notify();
+    }
+
+    fn jump_to_save_error(&mut self, line: usize, column: usize, dismiss: bool, cx: &mut Context<Self>) {
+        self.editor.update(cx, |editor, cx| {
+            editor.go_to_line_col(line, column, cx);
+        });
+        if dismiss {
+            self.pending_save = None;
+        }
+        self.needs_focus_restore = true;
+        cx.notify();
+    }
+
+    fn render_save_banner(&self, cx: &mut Context<Self>) -> AnyElement {
+        let theme = component::theme(cx);
+        let Some(pending) = self
+            .pending_save
+            .as_ref()
+            .filter(|p| self.active_tab.as_ref() == Some(&p.path))
+        else {
+            return div().into_any_element();
+        };
+
+        let button = |label: &str, primary: bool| {
+            div()
+                .ml(px(6.0))
+                .px(px(10.0))
+                .py(px(3.0))
+                .rounded_md()
+                .cursor_pointer()
+                .bg(if primary { theme.primary_button } else { theme.border })
+                .hover(|s| s.bg(theme.list_active))
+                .text_color(theme.text)
+                .child(label.to_string())
+        };
+
+        let mut list = div().flex().flex_col();
+        for error in pending.errors.iter().take(SAVE_BANNER_MAX_ERRORS) {
+            let (line, column) = (error.line, error.column);
+            list = list.child(
+                div()
+                    .flex()
+                    .cursor_pointer()
+                    .hover(|s| s.bg(theme.list_hover))
+                    .child(
+                        div()
+                            .mr(px(8.0))
+                            .text_color(theme.accent)
+                            .child(format!("{}:{}", line + 1, column + 1)),
+                    )
+                    .child(div().overflow_hidden().child(error.message.clone()))
+                    .on_mouse_down(MouseButton::Left, cx.listener(move |this, _, _window, cx| {
+                        this.jump_to_save_error(line, column, false, cx);
+                    })),
+            );
+        }
+        let more = pending.errors.len().saturating_sub(SAVE_BANNER_MAX_ERRORS);
+        if more > 0 {
+            list = list.child(div().text_color(theme.muted_text).child(format!("还有 {} 个错误…", more)));
+        }
+
+        let mut actions = div().flex().items_center();
+        if self.save_error_check == SaveErrorCheck::Block && !pending.save_unlocked {
+            let first = pending.errors.first().map(|e| (e.line, e.column)).unwrap_or((0, 0));
+            actions = actions
+                .child(button("修复", true).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |this, _, _window, cx| {
+                        this.jump_to_save_error(first.0, first.1, true, cx);
+                    }),
+                ))
+                .child(button("仍要保存…", false).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _, _window, cx| {
+                        if let Some(pending) = this.pending_save.as_mut() {
+                            pending.save_unlocked = true;
+                        }
+                        cx.notify();
+                    }),
+                ));
+        } else {
+            actions = actions
+                .child(button("仍然保存", false).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _, _window, cx| this.save_anyway(cx)),
+                ))
+                .child(button("取消", true).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, _, _window, cx| this.cancel_pending_save(cx)),
+                ));
+        }
+
+        div()
+            .w_full()
+            .flex()
+            .justify_between()
+            .px(px(10.0))
+            .py(px(6.0))
+            .bg(theme.error_bg)
+            .border_b_1()
+            .border_color(theme.error)
+            .text_size(px(12.0))
+            .text_color(theme.text)
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .flex_1()
+                    .child(
+                        div()
+                            .mb(px(2.0))
+                            .text_color(theme.error)
+                            .child(format!("当前文件有 {} 个错误", pending.errors.len())),
+                    )
+                    .child(list),
+            )
+            .child(actions)
+            .into_any_element()
+    }
+
+    /// 从丢弃的修改恢复的标签上方的说明
+    fn render_recovered_banner(&self, cx: &mut Context<Self>) -> AnyElement {
+        let theme = component::theme(cx);
+        let Some(path) = self.active_tab.clone().filter(|path| self.recovered_tabs.contains(path)) else {
+            return div().into_any_element();
+        };
+        div()
+            .w_full()
+            .flex()
+            .justify_between()
+            .items_center()
+            .px(px(10.0))
+            .py(px(6.0))
+            .bg(theme.success_bg)
+            .border_b_1()
+            .border_color(theme.success)
+            .text_size(px(12.0))
+            .text_color(theme.text)
+            .child("已恢复关闭时丢弃的修改。保存之前不会写入磁盘上的文件。")
+            .child(
+                div()
+                    .ml(px(6.0))
+                    .px(px(10.0))
+                    .py(px(3.0))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .bg(theme.border)
+                    .hover(|s| s.bg(theme.list_active))
+                    .child("知道了")
+                    .on_mouse_down(MouseButton::Left, cx.listener(move |this, _, _window, cx| {
+                        this.recovered_tabs.retain(|p| p != &path);
+                        cx.notify();
+                    })),
+            )
+            .into_any_element()
+    }
+
+    /// 含二进制数据的文件不载入编辑器，只显示大小和建议
+    fn render_binary_notice(&self, path: &PathBuf, size: u64, cx: &mut Context<Self>) -> Div {
+        let theme = component::theme(cx);
+        let reveal = path.clone();
+        div()
+            .flex_1()
+            .flex()
+            .flex_col()
+            .items_center()
+            .justify_center()
+            .text_size(px(13.0))
+            .text_color(theme.muted_text)
+            .child(
+                div()
+                    .text_size(px(15.0))
+                    .text_color(theme.text)
+                    .child(format!("{} 是二进制文件", Self::tab_label(path))),
+            )
+            .child(div().mt(px(6.0)).child(format!("大小 {}，无法作为文本编辑。", file_guard::format_size(size))))
+            .child(div().mt(px(4.0)).child("图片请用图片查看器打开，其它格式请使用外部工具。"))
+            .child(
+                div()
+                    .mt(px(12.0))
+                    .px(px(10.0))
+                    .py(px(3.0))
+                    .rounded_md()
+                    .cursor_pointer()
+                    .bg(theme.border)
+                    .hover(|s| s.bg(theme.list_active))
+                    .text_color(theme.text)
+                    .child("在文件管理器中显示")
+                    .on_mouse_down(MouseButton::Left, cx.listener(move |this, _, _window, cx| {
+                        this.reveal_in_file_manager(&reveal, cx);
+                    })),
+            )
+    }
+
+    /// 提交前的整理：依次保存所有已打开的文件，开启了保存时格式化时先格式化有修改的结绳文件，
+    /// 然后刷新 git 状态并聚焦提交框。单个文件失败只跳过该文件，右下角的提示框逐个显示进度和结果
+    fn prepare_commit(&mut self, cx: &mut Context<Self>) {
+        let paths: Vec<PathBuf> = self
+            .open_tabs
+            .iter()
+            .map(|t| t.path.clone())
+            // 未命名标签只能另存为，不参与提交
+            .filter(|path| Self::is_text_path(path) && untitled_name(path).is_none())
+            .collect();
+        let shown_at = Instant::now();
+        self.prepare_commit_toast = Some(PrepareCommitToast {
+            results: paths.iter().map(|path| (Self::tab_label(path), None)).collect(),
+            shown_at,
+            finished: false,
+        });
+        cx.notify();
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                for (i, path) in paths.into_iter().enumerate() {
+                    let Ok(outcome) = view.update(&mut cx, |this, cx| this.prepare_file_for_commit(&path, cx)) else {
+                        return;
+                    };
+                    let outcome = outcome.await;
+                    view.update(&mut cx, |this, cx| {
+                        if let Some(toast) = this.prepare_commit_toast.as_mut().filter(|t| t.shown_at == shown_at) {
+                            toast.results[i].1 = Some(outcome);
+                            cx.notify();
+                        }
+                    })
+                    .ok();
+                }
+                view.update(&mut cx, |this, cx| this.finish_prepare_commit(shown_at, cx)).ok();
             }
-            self.active_tab = Some(path);
-            cx.notify();
+        })
+        .detach();
+    }
+
+    fn finish_prepare_commit(&mut self, shown_at: Instant, cx: &mut Context<Self>) {
+        if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
+            git_panel.update(cx, |panel, cx| panel.refresh(cx));
         }
+        self.tool_panel.update(cx, |panel, cx| panel.select_page("git", cx));
+        self.file_tree_visible = true;
+        self.needs_git_focus = true;
+
+        if let Some(toast) = self.prepare_commit_toast.as_mut().filter(|t| t.shown_at == shown_at) {
+            toast.finished = true;
+        }
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                cx.background_executor().timer(PREPARE_COMMIT_TOAST_DURATION).await;
+                view.update(&mut cx, |this, cx| {
+                    if this.prepare_commit_toast.as_ref().map(|t| t.shown_at) == Some(shown_at) {
+                        this.prepare_commit_toast = None;
+                        cx.notify();
+                    }
+                })
+                .ok();
+            }
+        })
+        .detach();
+        cx.notify();
     }
 
-    fn close_tab(&mut self, path: &PathBuf, cx: &mut Context<Self>) {
-        let was_active = self.active_tab.as_ref() == Some(path);
-        self.open_tabs.retain(|p| p != path);
-        if was_active {
-            if let Some(next_path) = self.open_tabs.last().cloned() {
-                self.open_file_path(next_path, cx);
-            } else {
-                self.active_tab = None;
-                self.editor.update(cx, |editor, cx| {
-                    editor.set_content(String::new(), cx);
-                });
+    /// 保存一个文件供提交，开启了保存时格式化时先格式化；任务在格式化、查错和写入都完成后结束
+    fn prepare_file_for_commit(&mut self, path: &PathBuf, cx: &mut Context<Self>) -> Task<workspace::PrepareOutcome> {
+        use workspace::PrepareOutcome;
+        if self.find_tab(path).is_some_and(|t| t.missing) {
+            return Task::ready(PrepareOutcome::Skipped("不在当前分支".to_string()));
+        }
+        // 从未激活过的标签没有未保存的修改
+        let Some(buffer) = self.tab_buffer(path, cx) else {
+            return Task::ready(PrepareOutcome::Unchanged);
+        };
+        let is_active = self.active_tab.as_ref() == Some(path);
+        let path = path.clone();
+        match workspace::check_open_file(&path, Some(&buffer)) {
+            workspace::OpenFileState::Unchanged => Task::ready(PrepareOutcome::Unchanged),
+            workspace::OpenFileState::Missing => Task::ready(PrepareOutcome::Failed("文件已不存在".to_string())),
+            workspace::OpenFileState::Changed(_) if !is_active => {
+                if self.save_error_check != SaveErrorCheck::Off {
+                    // 查错只针对编辑器中的文件
+                    return Task::ready(PrepareOutcome::Skipped("切换到该文件后保存".to_string()));
+                }
+                let is_source = path.extension().is_some_and(|ext| ext == "t");
+                if !(self.format_on_save && is_source) {
+                    return Task::ready(match self.write_file(&path, &buffer, cx) {
+                        Ok(()) => PrepareOutcome::Saved,
+                        Err(e) => PrepareOutcome::Failed(e.to_string()),
+                    });
+                }
+                // 后台标签的内容不在 tiec 中，只按缩进格式化
+                let formatted = self.editor.update(cx, |editor, cx| editor.format_text(buffer.clone(), cx));
+                cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+                    let mut cx = cx.clone();
+                    async move {
+                        let formatted = formatted.await;
+                        view.update(&mut cx, |this, cx| this.finish_prepare_background(&path, &buffer, formatted, cx))
+                            .unwrap_or_else(|e| PrepareOutcome::Failed(e.to_string()))
+                    }
+                })
+            }
+            workspace::OpenFileState::Changed(_) => {
+                let format = self.format_on_save.then(|| self.editor.update(cx, |editor, cx| editor.format(cx)));
+                cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+                    let mut cx = cx.clone();
+                    async move {
+                        if let Some(format) = format {
+                            if let Err(err) = format.await {
+                                return PrepareOutcome::Failed(format!("格式化失败：{}", err));
+                            }
+                        }
+                        let Ok((version, errors)) = view.update(&mut cx, |this, cx| {
+                            let version = this.editor.read(cx).core.version();
+                            let errors = (this.save_error_check != SaveErrorCheck::Off)
+                                .then(|| this.editor.update(cx, |editor, cx| editor.lint_errors_for_save(cx)));
+                            (version, errors)
+                        }) else {
+                            return PrepareOutcome::Failed("窗口已关闭".to_string());
+                        };
+                        let errors = match errors {
+                            Some(errors) => errors.await.unwrap_or_default(),
+                            None => Vec::new(),
+                        };
+                        view.update(&mut cx, |this, cx| this.finish_prepare_active(&path, version, errors, cx))
+                            .unwrap_or_else(|e| PrepareOutcome::Failed(e.to_string()))
+                    }
+                })
             }
         }
-        cx.notify();
     }
 
-    fn save_file(&mut self, cx: &mut Context<Self>) {
-        if let Some(path) = &self.active_tab {
-            let content = self.editor.read(cx).core.content.to_string();
-            if let Err(e) = std::fs::write(path, content) {
-                println!("Failed to save file: {}", e);
-            } else {
-                if let Some(git_panel) = self.tool_panel.read(cx).git_panel() {
-                    git_panel.update(cx, |panel, _| panel.refresh());
+    /// 后台标签格式化完成后写入；期间内容有变化或切换到了该标签时跳过
+    fn finish_prepare_background(
+        &mut self,
+        path: &PathBuf,
+        buffer: &str,
+        formatted: anyhow::Result<Option<String>>,
+        cx: &mut Context<Self>,
+    ) -> workspace::PrepareOutcome {
+        use workspace::PrepareOutcome;
+        let formatted = match formatted {
+            Ok(formatted) => formatted,
+            Err(err) => return PrepareOutcome::Failed(format!("格式化失败：{}", err)),
+        };
+        if self.active_tab.as_ref() == Some(path) || self.tab_buffer(path, cx).as_deref() != Some(buffer) {
+            return PrepareOutcome::Skipped("格式化期间有修改".to_string());
+        }
+        let content = match formatted {
+            Some(formatted) => {
+                if let Some(snapshot) = self.open_tabs.iter_mut().find(|t| &t.path == path).and_then(|t| t.snapshot.as_mut()) {
+                    snapshot.apply_formatted(&formatted);
                 }
+                formatted
             }
+            None => buffer.to_string(),
+        };
+        match self.write_file(path, &content, cx) {
+            Ok(()) => PrepareOutcome::Saved,
+            Err(e) => PrepareOutcome::Failed(e.to_string()),
+        }
+    }
+
+    /// 当前文件查错完成后写入；有错误时与普通保存一样交给提示条，由用户决定是否仍然保存
+    fn finish_prepare_active(
+        &mut self,
+        path: &PathBuf,
+        version: u64,
+        errors: Vec<LintError>,
+        cx: &mut Context<Self>,
+    ) -> workspace::PrepareOutcome {
+        use workspace::PrepareOutcome;
+        if self.active_tab.as_ref() != Some(path) || self.editor.read(cx).core.version() != version {
+            return PrepareOutcome::Skipped("查错期间有修改".to_string());
+        }
+        if !errors.is_empty() {
+            let message = format!("有 {} 个错误，等待确认", errors.len());
+            self.pending_save = Some(PendingSave {
+                path: path.clone(),
+                errors,
+                save_unlocked: false,
+            });
+            return PrepareOutcome::Failed(message);
+        }
+        match self.write_active_file(path, cx) {
+            Ok(()) => PrepareOutcome::Saved,
+            Err(e) => PrepareOutcome::Failed(e.to_string()),
+        }
+    }
+
+    fn render_prepare_commit_toast(&self, cx: &mut Context<Self>) -> AnyElement {
+        let theme = component::theme(cx);
+        use workspace::PrepareOutcome;
+        let Some(toast) = self.prepare_commit_toast.as_ref() else {
+            return div().into_any_element();
+        };
+
+        let mut list = div().flex().flex_col();
+        let current = toast.results.iter().position(|(_, outcome)| outcome.is_none());
+        for (i, (name, outcome)) in toast.results.iter().enumerate() {
+            let (status, color) = match outcome {
+                Some(PrepareOutcome::Saved) => ("已保存".to_string(), theme.success),
+                Some(PrepareOutcome::Unchanged) => ("无修改".to_string(), theme.muted_text),
+                Some(PrepareOutcome::Skipped(reason)) => (reason.clone(), theme.warning),
+                Some(PrepareOutcome::Failed(reason)) => (reason.clone(), theme.error),
+                None if current == Some(i) => ("处理中…".to_string(), theme.accent),
+                None => ("等待".to_string(), theme.muted_text),
+            };
+            list = list.child(
+                div()
+                    .flex()
+                    .justify_between()
+                    .py(px(1.0))
+                    .child(div().mr(px(12.0)).overflow_hidden().child(name.clone()))
+                    .child(div().text_color(color).child(status)),
+            );
         }
+        let done: Vec<&PrepareOutcome> = toast.results.iter().filter_map(|(_, o)| o.as_ref()).collect();
+        let summary = if toast.finished {
+            workspace::prepare_summary(done)
+        } else {
+            format!("已处理 {}/{} 个文件", done.len(), toast.results.len())
+        };
+
+        div()
+            .absolute()
+            .bottom(px(36.0))
+            .right(px(16.0))
+            .w(px(300.0))
+            .flex()
+            .flex_col()
+            .bg(theme.surface.opacity(0.94))
+            .border_1()
+            .border_color(theme.list_active)
+            .rounded_md()
+            .p(px(10.0))
+            .text_size(px(12.0))
+            .text_color(theme.text)
+            .cursor_pointer()
+            .child(div().mb(px(6.0)).text_color(theme.accent).child("准备提交"))
+            .child(list)
+            .child(div().mt(px(6.0)).text_color(theme.muted_text).child(summary))
+            .on_mouse_down(MouseButton::Left, cx.listener(|this, _, _window, cx| {
+                this.prepare_commit_toast = None;
+                cx.notify();
+            }))
+            .into_any_element()
     }
 
     fn close_active_tab(&mut self, cx: &mut Context<Self>) {
         if let Some(path) = self.active_tab.clone() {
-            self.close_tab(&path, cx);
+            self.request_close_tab(&path, cx);
         }
     }
 
     fn request_confirm(&mut self, action: ConfirmAction, cx: &mut Context<Self>) {
         self.confirm_action = Some(action);
         self.confirm_open = true;
+        self.needs_confirm_focus = true;
         cx.notify();
     }
 
     fn cancel_confirm(&mut self, cx: &mut Context<Self>) {
         self.confirm_open = false;
-        self.confirm_action = None;
+        if let Some(ConfirmAction::ReloadExternal { path }) = self.confirm_action.take() {
+            // 保留编辑器内容：以磁盘上的新内容为基准，之后保存会覆盖它
+            if let Ok(raw) = std::fs::read_to_string(&path) {
+                self.file_watcher.mark_saved(&path, tiecode_buffer::strip_bom(&raw).0);
+            }
+        }
         cx.notify();
     }
 
@@ -500,55 +4023,83 @@ impl StartWindow {
         self.confirm_open = false;
         if let Some(action) = action {
             match action {
-                ConfirmAction::Move { src, dst } => {
-                    match std::fs::rename(&src, &dst) {
-                        Ok(_) => {
-                            if let Some(index) = self.open_tabs.iter().position(|p| p == &src) {
-                                self.open_tabs[index] = dst.clone();
-                            }
-                            if self.active_tab.as_ref() == Some(&src) {
-                                self.active_tab = Some(dst);
+                ConfirmAction::Move { moves } => {
+                    for (src, dst) in moves {
+                        match std::fs::rename(&src, &dst) {
+                            Ok(_) => self.path_renamed(&src, &dst, cx),
+                            Err(err) => {
+                                log_channel(OutputChannel::App, format!("Move failed: {:?} -> {:?}, {}", src, dst, err));
                             }
-                            let file_tree = self.file_tree.clone();
-                            file_tree.update(cx, |tree, cx| {
-                                tree.refresh();
-                                cx.notify();
-                            });
-                        }
-                        Err(err) => {
-                            println!("Move failed: {:?} -> {:?}, {}", src, dst, err);
                         }
                     }
+                    let file_tree = self.file_tree.clone();
+                    file_tree.update(cx, |tree, cx| {
+                        tree.refresh();
+                        cx.notify();
+                    });
                 }
-                ConfirmAction::Delete { path, is_dir } => {
-                    let result = if is_dir {
-                        std::fs::remove_dir_all(&path)
-                    } else {
-                        std::fs::remove_file(&path)
-                    };
-                    match result {
-                        Ok(_) => {
-                            self.open_tabs.retain(|p| p != &path);
-                            if self.active_tab.as_ref() == Some(&path) {
-                                self.active_tab = self.open_tabs.last().cloned();
-                                if let Some(next) = self.active_tab.clone() {
-                                    self.open_file_path(next, cx);
-                                } else {
-                                    self.editor.update(cx, |editor, cx| {
-                                        editor.set_content(String::new(), cx);
-                                    });
+                ConfirmAction::CloseUnsaved { path } => {
+                    self.close_tab(&path, cx);
+                }
+                ConfirmAction::UpdateReferences { from, to, files } => {
+                    self.update_references(&from, &to, files, cx);
+                }
+                ConfirmAction::RemoveRoot { root } => {
+                    self.remove_workspace_folder(&root, cx);
+                }
+                ConfirmAction::OpenLargeFile { path, .. } => {
+                    log_channel(OutputChannel::App, format!("Opening large file read-only: {:?}", path));
+                    self.load_text_file(path, true, cx);
+                }
+                ConfirmAction::ReloadExternal { path } => {
+                    if let Ok(raw) = std::fs::read_to_string(&path) {
+                        let content = tiecode_buffer::strip_bom(&raw).0.to_string();
+                        self.reload_from_disk(&path, content, cx);
+                    }
+                }
+                ConfirmAction::OverwriteExternal { path } => {
+                    // 确认框打开期间切换了文件时，编辑器里已不是这份内容
+                    if self.active_tab.as_ref() == Some(&path) {
+                        let _ = self.write_active_file(&path, cx);
+                    }
+                }
+                ConfirmAction::RestartLanguageService { settings } => {
+                    log_channel(
+                        OutputChannel::Lsp,
+                        format!("Restarting language service after settings change: {:?}", settings),
+                    );
+                    self.editor.update(cx, |editor, _| editor.reload_language_project());
+                }
+                ConfirmAction::Delete { entries } => {
+                    for (path, is_dir) in entries {
+                        let result = if is_dir {
+                            std::fs::remove_dir_all(&path)
+                        } else {
+                            std::fs::remove_file(&path)
+                        };
+                        match result {
+                            Ok(_) => {
+                                // 删除文件夹时关闭其中所有已打开的文件
+                                let closed: Vec<PathBuf> = self
+                                    .open_tabs
+                                    .iter()
+                                    .filter(|t| t.path.starts_with(&path))
+                                    .map(|t| t.path.clone())
+                                    .collect();
+                                for tab in closed {
+                                    self.close_tab(&tab, cx);
                                 }
                             }
-                            let file_tree = self.file_tree.clone();
-                            file_tree.update(cx, |tree, cx| {
-                                tree.refresh();
-                                cx.notify();
-                            });
-                        }
-                        Err(err) => {
-                            println!("Delete failed: {:?}, {}", path, err);
+                            Err(err) => {
+                                log_channel(OutputChannel::App, format!("Delete failed: {:?}, {}", path, err));
+                            }
                         }
                     }
+                    let file_tree = self.file_tree.clone();
+                    file_tree.update(cx, |tree, cx| {
+                        tree.refresh();
+                        cx.notify();
+                    });
                 }
             }
         }
@@ -557,20 +4108,526 @@ impl StartWindow {
 
     fn show_command_palette(&mut self, _: &ShowCommandPalette, window: &mut Window, cx: &mut Context<Self>) {
         let commands = self.plugin_manager.read(cx).command_registry.list().into_iter().cloned().collect();
+        let keybindings = self.plugin_manager.read(cx).keymap.labels();
+        let recent = self.command_journal.recent_commands();
         let handle = self.command_palette.read(cx).focus_handle.clone();
         handle.focus(window);
+        let pinned = self.toolbar.commands.clone();
         self.command_palette.update(cx, |palette, cx| {
-            palette.set_commands(commands, cx);
+            palette.set_commands(commands, recent, keybindings, cx);
+            palette.set_pinned(pinned, cx);
             palette.show(cx);
         });
     }
 
-    fn execute_command(&mut self, command_id: &str, cx: &mut Context<Self>) {
+    fn run_command_action(&mut self, action: &RunCommand, _window: &mut Window, cx: &mut Context<Self>) {
+        // gpui 不能撤下单个绑定，停用或删除的插件的快捷键在这里忽略
+        if !self.plugin_manager.read(cx).keybindings().iter().any(|binding| binding.command == action.0) {
+            return;
+        }
+        self.execute_command(&action.0, None, cx);
+    }
+
+    /// 插件发现、启用或停用之后，同步编辑器中的回车规则、按键绑定和插件页
+    fn plugins_changed(&mut self, cx: &mut Context<Self>) {
+        let manager = self.plugin_manager.read(cx);
+        let enter_rules = manager.enter_rules();
+        let keybindings = manager.keybindings();
+        let (summaries, load_errors) = (manager.summaries(), manager.load_errors().to_vec());
+        let grammar_problems = sync_plugin_grammars(manager.grammars());
+        self.editor.update(cx, |editor, cx| {
+            editor.set_plugin_enter_rules(enter_rules);
+            editor.plugin_grammars_changed(cx);
+        });
+        if !grammar_problems.is_empty() {
+            let warning = grammar_problems.join("；");
+            self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(warning), cx));
+        }
+        Self::bind_plugin_keys(&keybindings, cx);
+        self.plugin_panel.update(cx, |panel, cx| panel.set_plugins(summaries, load_errors, cx));
+    }
+
+    /// 启用或停用插件并保存到插件设置；返回插件是否存在
+    fn set_plugin_enabled(&mut self, id: &str, enabled: bool, cx: &mut Context<Self>) -> bool {
+        let disabled = self.plugin_manager.update(cx, |manager, _| {
+            manager.set_plugin_enabled(id, enabled).then(|| manager.disabled_plugins().clone())
+        });
+        let Some(disabled) = disabled else {
+            return false;
+        };
+        PluginSettings { disabled }.save();
+        self.plugins_changed(cx);
+        if enabled {
+            self.emit_plugin_event(ActivationEvent::Startup, cx);
+        }
+        true
+    }
+
+    /// 重新扫描插件目录，载入新增的插件、移除已删除的插件
+    fn rescan_plugins(&mut self, cx: &mut Context<Self>) {
+        self.plugin_manager.update(cx, |manager, _| manager.discover_plugins());
+        self.plugins_changed(cx);
+        self.emit_plugin_event(ActivationEvent::Startup, cx);
+    }
+
+    /// 在后台线程执行动态库插件的命令，插件通过 [`ChannelEditorHost`] 读写当前编辑器
+    fn run_native_command(&mut self, command: NativeCommand, cx: &mut Context<Self>) {
+        let host: Arc<dyn EditorHost> = self.editor_host.clone();
+        let task = cx.background_executor().spawn(async move { command.run(host).map_err(|err| (command.command, err)) });
+        cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                if let Err((command, err)) = task.await {
+                    let message = format!("插件命令 {} 失败：{:#}", command, err);
+                    warn!("{}", message);
+                    let _ = view.update(&mut cx, |this, cx| {
+                        this.status_bar.update(cx, |bar, cx| bar.set_warning(Some(message), cx));
+                    });
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// 处理插件经编辑器接口发来的请求，作用于当前的文本标签
+    fn handle_editor_request(&mut self, request: EditorRequest, cx: &mut Context<Self>) {
+        if let EditorRequest::ActiveFilePath(reply) = request {
+            let path = self.active_tab.clone().filter(|path| untitled_name(path).is_none());
+            reply.send(Ok(path));
+            return;
+        }
+        if !self.active_tab.as_ref().is_some_and(Self::is_text_path) {
+            request.fail("没有打开文本文件");
+            return;
+        }
+        self.editor.update(cx, |editor, cx| match request {
+            EditorRequest::GetText(reply) => reply.send(Ok(editor.core.content.to_string())),
+            EditorRequest::ReplaceRange(range, text, reply) => reply.send(editor.replace_range_checked(range, &text, cx)),
+            EditorRequest::CursorOffset(reply) => reply.send(Ok(editor.core.primary_selection().head)),
+            EditorRequest::Selections(reply) => reply.send(Ok(editor
+                .core
+                .selections
+                .iter()
+                .map(|selection| (selection.anchor, selection.head))
+                .collect())),
+            EditorRequest::SetSelections(selections, reply) => reply.send(editor.set_selections(&selections, cx)),
+            EditorRequest::ActiveFilePath(_) => unreachable!(),
+        });
+    }
+
+    /// 把插件清单中的快捷键绑定为 [`RunCommand`]；与已有绑定按键相同的跳过，内置绑定优先
+    fn bind_plugin_keys(contributions: &[KeybindingContribution], cx: &mut App) {
+        let keymap = cx.key_bindings();
+        let keymap = keymap.borrow();
+        let mut taken: Vec<Vec<_>> = keymap.bindings().map(|binding| binding.keystrokes().to_vec()).collect();
+        let mut bindings = Vec::new();
+        for contribution in contributions {
+            let keys = normalize_keystrokes(&contribution.key);
+            if keys.is_empty() || keys.split_whitespace().any(|stroke| Keystroke::parse(stroke).is_err()) {
+                warn!("Ignoring keybinding {:?} for {}: invalid keystroke", contribution.key, contribution.command);
+                continue;
+            }
+            let context = match binding_context(contribution.when.as_deref()) {
+                Ok(context) => context,
+                Err(when) => {
+                    warn!("Ignoring keybinding {} for {}: unknown context {:?}", keys, contribution.command, when);
+                    continue;
+                }
+            };
+            let binding = KeyBinding::new(&keys, RunCommand(contribution.command.clone()), context);
+            let bound = keymap.bindings().any(|existing| {
+                existing.keystrokes() == binding.keystrokes() && existing.action().partial_eq(binding.action())
+            });
+            if bound {
+                // 重新启用插件或重新扫描时已经绑定过
+                continue;
+            }
+            if taken.iter().any(|keystrokes| keystrokes.as_slice() == binding.keystrokes()) {
+                warn!("Keybinding {} for {} conflicts with an existing binding, skipped", keys, contribution.command);
+                continue;
+            }
+            taken.push(binding.keystrokes().to_vec());
+            bindings.push(binding);
+        }
+        drop(keymap);
+        if !bindings.is_empty() {
+            info!("Bound {} plugin keybindings", bindings.len());
+            cx.bind_keys(bindings);
+        }
+    }
+
+    fn quick_open_action(&mut self, _: &QuickOpen, _window: &mut Window, cx: &mut Context<Self>) {
+        self.quick_open(cx);
+    }
+
+    /// 在命令面板中列出各根目录下的文件，选中后打开
+    fn quick_open(&mut self, cx: &mut Context<Self>) -> bool {
+        let roots = self.file_tree.read(cx).roots();
+        if roots.is_empty() {
+            self.status_bar.update(cx, |bar, cx| bar.set_warning(Some("请先打开文件夹".to_string()), cx));
+            return false;
+        }
+        // 输入 `>` 切换到命令时需要命令列表
+        let commands = self.plugin_manager.read(cx).command_registry.list().into_iter().cloned().collect();
+        let keybindings = self.plugin_manager.read(cx).keymap.labels();
+        let recent = self.command_journal.recent_commands();
+        let pinned = self.toolbar.commands.clone();
+        self.command_palette.update(cx, |palette, cx| {
+            palette.set_commands(commands, recent, keybindings, cx);
+            palette.set_pinned(pinned, cx);
+            palette.show_files(roots, cx);
+        });
+        self.needs_palette_focus = true;
+        cx.notify();
+        true
+    }
+
+    fn compile_project_action(&mut self, _: &CompileProject, _window: &mut Window, cx: &mut Context<Self>) {
+        self.execute_command("project.compile", None, cx);
+    }
+
+    /// 在后台编译当前文件夹下的全部结绳文件，进度写到输出面板的编译频道，诊断列在问题页中。
+    /// 再次编译时丢弃上一次的结果
+    fn compile_project(&mut self, cx: &mut Context<Self>) -> Result<(), String> {
+        let Some(root) = self.file_tree.read(cx).root_path().cloned() else {
+            return Err("请先打开文件夹".to_string());
+        };
+        if !self.check_project_settings(&root, cx) {
+            self.tool_panel.update(cx, |panel, cx| panel.select_page("problems", cx));
+            return Err("项目设置有误，已列在问题页".to_string());
+        }
+        let reply = self.editor.update(cx, |editor, _| {
+            let content = editor.core.content.to_string();
+            editor.lsp_manager.compile_project(root.clone(), &content)
+        });
+        self.output_panel.update(cx, |panel, cx| panel.set_channel(OutputChannel::Build, cx));
+        self.output_visible = true;
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(message) => {
+                log_channel(OutputChannel::Build, &message);
+                self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(message.clone()), cx));
+                cx.notify();
+                return Err(message);
+            }
+        };
+        log_channel(OutputChannel::Build, format!("编译 {}", root.display()));
+        self.status_bar.update(cx, |bar, cx| bar.set_busy(Some("正在编译…".to_string()), cx));
+        self.compile_task = Some(cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
+            let mut cx = cx.clone();
+            async move {
+                // 被新的编译取代时这里不再继续，忙碌指示由新的编译负责
+                let Some(result) = reply.recv().await else {
+                    return;
+                };
+                let problems = match result {
+                    Ok((files, lint)) => Ok(cx
+                        .background_executor()
+                        .spawn(async move {
+                            project_problems(&files, lint.diagnostics, |path| fs::read_to_string(path).ok())
+                        })
+                        .await),
+                    Err(err) => Err(err),
+                };
+                view.update(&mut cx, |this, cx| {
+                    this.status_bar.update(cx, |bar, cx| bar.set_busy(None, cx));
+                    match problems {
+                        Ok(problems) => this.show_compile_problems(problems, cx),
+                        Err(err) => {
+                            log_channel(OutputChannel::Build, format!("编译失败：{:#}", err));
+                            this.status_bar.update(cx, |bar, cx| bar.flash("编译失败".to_string(), cx));
+                        }
+                    }
+                })
+                .ok();
+            }
+        }));
+        cx.notify();
+        Ok(())
+    }
+
+    /// 把编译得到的诊断写到输出面板并列在问题页中，有错误时切到问题页
+    fn show_compile_problems(&mut self, problems: Vec<(PathBuf, Vec<Problem>)>, cx: &mut Context<Self>) {
+        let (mut errors, mut warnings) = (0, 0);
+        for (path, file_problems) in &problems {
+            for problem in file_problems {
+                let label = match problem.severity {
+                    ProblemSeverity::Error => {
+                        errors += 1;
+                        "错误"
+                    }
+                    ProblemSeverity::Warning => {
+                        warnings += 1;
+                        "警告"
+                    }
+                };
+                log_channel(
+                    OutputChannel::Build,
+                    format!("{}:{}:{}: {}：{}", path.display(), problem.line + 1, problem.column + 1, label, problem.message),
+                );
+            }
+        }
+        log_channel(OutputChannel::Build, format!("检查完成：错误 {}，警告 {}", errors, warnings));
+        self.problems_panel.update(cx, |panel, cx| {
+            for (path, file_problems) in problems {
+                panel.set_problems(path, file_problems, cx);
+            }
+        });
+        if errors > 0 {
+            self.tool_panel.update(cx, |panel, cx| panel.select_page("problems", cx));
+        }
+        self.status_bar.update(cx, |bar, cx| bar.flash(format!("编译完成：错误 {}，警告 {}", errors, warnings), cx));
+        cx.notify();
+    }
+
+    fn go_to_line_action(&mut self, _: &GoToLine, _window: &mut Window, cx: &mut Context<Self>) {
+        self.go_to_line(cx);
+    }
+
+    /// 打开命令面板并填入 `:`，输入行号后跳转
+    fn go_to_line(&mut self, cx: &mut Context<Self>) -> bool {
+        if !self.active_tab.as_ref().is_some_and(Self::is_text_path) {
+            return false;
+        }
+        self.command_palette.update(cx, |palette, cx| palette.show_prefixed(":", cx));
+        self.needs_palette_focus = true;
+        cx.notify();
+        true
+    }
+
+    fn toggle_toolbar_pin(&mut self, command: &str, cx: &mut Context<Self>) {
+        self.toolbar.toggle_pin(command);
+        // 固定命令时显示被隐藏的工具栏，否则看不到效果
+        if self.toolbar.is_pinned(command) {
+            self.toolbar.visible = true;
+        }
+        let pinned = self.toolbar.commands.clone();
+        self.command_palette.update(cx, |palette, cx| palette.set_pinned(pinned, cx));
+        self.save_session(cx);
+        cx.notify();
+    }
+
+    /// 命令当前能否执行；工具栏上不能执行的按钮显示为灰色
+    fn command_enabled(&self, command: &str, cx: &App) -> bool {
+        let text_tab = self.active_tab.as_ref().is_some_and(Self::is_text_path);
+        match command {
+            "core.save" | "core.save_as" | "core.cut" | "core.copy" | "core.paste" | "core.select_all"
+            | "editor.toggle_read_only" | "editor.revert_hunk" | "editor.go_to_line" | "file.toggle_bom"
+            | "editor.fold_all" | "editor.unfold_all" | "editor.fold_level_1" | "editor.convert_to_lf"
+            | "editor.convert_to_crlf" => text_tab,
+            "core.undo" => text_tab && self.undo_depth(cx) > 0,
+            "core.close" => self.active_tab.is_some(),
+            "file.reveal_in_file_manager" | "file.open_terminal" | "file_tree.reveal_active" => {
+                self.active_tab.as_ref().is_some_and(|path| path.exists())
+            }
+            "editor.recover_discarded" => !self.discarded_tabs.is_empty(),
+            _ => true,
+        }
+    }
+
+    /// 标题栏下方的快捷工具栏：按下按钮后拖到其它按钮上调整顺序，没有拖动时松开执行命令
+    fn render_toolbar(&self, cx: &mut Context<Self>) -> AnyElement {
+        let theme = component::theme(cx);
+        if !self.toolbar.visible || self.toolbar.commands.is_empty() {
+            return div().into_any_element();
+        }
+        let plugin_manager = self.plugin_manager.read(cx);
+        let keybindings = plugin_manager.keymap.labels();
+        // 插件卸载后命令不再存在，保留固定以便之后恢复
+        let contributions: Vec<CommandContribution> = self
+            .toolbar
+            .commands
+            .iter()
+            .filter_map(|command| plugin_manager.command_registry.get(command).cloned())
+            .collect();
+        let mut bar = div()
+            .w_full()
+            .h(px(30.0))
+            .px(px(6.0))
+            .flex()
+            .items_center()
+            .gap(px(2.0))
+            .bg(theme.header)
+            .border_b_1()
+            .border_color(theme.border)
+            .focus_region(&self.toolbar_focus);
+        for contribution in contributions {
+            let command = &contribution.command;
+            let enabled = self.command_enabled(command, cx);
+            let dragging = self.toolbar_drag.as_ref().is_some_and(|(c, _)| c == command);
+            let icon = match contribution.icon.clone().or_else(|| builtin_icon(command).map(str::to_string)) {
+                Some(icon) => tie_svg()
+                    .path(icon)
+                    .size(px(16.0))
+                    .original_colors(contribution.icon.is_some())
+                    .text_color(theme.text)
+                    .into_any_element(),
+                None => div()
+                    .text_size(px(12.0))
+                    .text_color(theme.text)
+                    .child(contribution.title.chars().next().map(String::from).unwrap_or_default())
+                    .into_any_element(),
+            };
+            let title = contribution.title.clone();
+            let key = keybindings.get(command).cloned();
+            let press = command.clone();
+            let over = command.clone();
+            let click = command.clone();
+            let mut button = div()
+                .id(SharedString::from(format!("toolbar-{}", command)))
+                .focus_ring(cx)
+                .size(px(26.0))
+                .flex()
+                .items_center()
+                .justify_center()
+                .rounded_md();
+            if dragging {
+                button = button.bg(theme.list_active);
+            }
+            button = if enabled {
+                button.cursor_pointer().hover(|s| s.bg(theme.list_hover))
+            } else {
+                button.opacity(0.4)
+            };
+            bar = bar.child(
+                button
+                    .child(icon)
+                    .tooltip(move |_window, cx| {
+                        let (title, key) = (title.clone(), key.clone());
+                        cx.new(|_| ToolbarTooltip { title, key }).into()
+                    })
+                    .on_mouse_down(MouseButton::Left, cx.listener(move |this, _, _window, cx| {
+                        this.toolbar_drag = Some((press.clone(), false));
+                        cx.notify();
+                    }))
+                    .on_mouse_move(cx.listener(move |this, event: &MouseMoveEvent, _window, cx| {
+                        if event.pressed_button != Some(MouseButton::Left) {
+                            return;
+                        }
+                        let Some((dragged, moved)) = this.toolbar_drag.as_mut() else {
+                            return;
+                        };
+                        if this.toolbar.move_to(dragged, &over) {
+                            *moved = true;
+                            cx.notify();
+                        }
+                    }))
+                    .on_click(cx.listener(move |this, _, _window, cx| {
+                        let moved = this.toolbar_drag.take().is_some_and(|(_, moved)| moved);
+                        if moved {
+                            this.save_session(cx);
+                        } else if this.command_enabled(&click, cx) {
+                            this.execute_command(&click, None, cx);
+                        }
+                        cx.notify();
+                    })),
+            );
+        }
+        bar.into_any_element()
+    }
+
+    /// 当前编辑器中文档的撤销步数
+    fn undo_depth(&self, cx: &App) -> usize {
+        self.editor.read(cx).core.history.undo_depth()
+    }
+
+    /// 在命令面板中列出最近执行的命令，选中后重新执行
+    fn show_command_history(&mut self, cx: &mut Context<Self>) {
+        let registry = &self.plugin_manager.read(cx).command_registry;
+        let now = SystemTime::now();
+        let active = self.active_tab.as_ref();
+        let depth = self.undo_depth(cx);
+        let items = self
+            .command_journal
+            .entries()
+            .map(|entry| {
+                let command = registry.list().into_iter().find(|c| c.command == entry.command).cloned().unwrap_or(CommandContribution {
+                    command: entry.command.clone(),
+                    title: entry.command.clone(),
+                    category: None,
+                    icon: None,
+                });
+                let mut detail = elapsed_label(entry.at, now);
+                match &entry.outcome {
+                    CommandOutcome::Failed(error) => detail.push_str(&format!("　失败：{}", error)),
+                    CommandOutcome::Unhandled => detail.push_str("　未执行"),
+                    CommandOutcome::Done if entry.undoable(active, depth) => detail.push_str("　可撤销"),
+                    CommandOutcome::Done => {}
+                }
+                PaletteItem { command, detail: Some(detail) }
+            })
+            .collect();
+        let pinned = self.toolbar.commands.clone();
+        self.command_palette.update(cx, |palette, cx| {
+            palette.set_pinned(pinned, cx);
+            palette.show_history(items, cx);
+        });
+    }
+
+    /// 执行命令并记入命令日志；`argument` 交给插件命令，例如文件树菜单中点击的路径
+    fn execute_command(&mut self, command_id: &str, argument: Option<&str>, cx: &mut Context<Self>) {
+        if command_id == "command.history" {
+            self.show_command_history(cx);
+            return;
+        }
+        let active = self.active_tab.clone();
+        let depth = self.undo_depth(cx);
+        let outcome = self.run_command(command_id, argument, cx);
+        // 命令在当前文档中增加了可撤销的修改时记下撤销步数
+        let undo = active
+            .filter(|path| self.active_tab.as_ref() == Some(path) && self.undo_depth(cx) > depth)
+            .map(|path| UndoMark { path, depth: self.undo_depth(cx) });
+        let entry = self.command_journal.record(JournalEntry {
+            command: command_id.to_string(),
+            at: SystemTime::now(),
+            outcome,
+            undo,
+        });
+        info!("Command {}", entry.log_line());
+    }
+
+    fn run_command(&mut self, command_id: &str, argument: Option<&str>, cx: &mut Context<Self>) -> CommandOutcome {
         match command_id {
             "file_tree.toggle" => {
                 self.file_tree_visible = !self.file_tree_visible;
                 cx.notify();
             }
+            "file_tree.reveal_active" => {
+                if !self.reveal_active_file(cx) {
+                    return CommandOutcome::Failed("无法在文件树中显示当前文件".to_string());
+                }
+            }
+            "view.toggle_output" => {
+                self.output_visible = !self.output_visible;
+                cx.notify();
+            }
+            "view.toggle_performance" => {
+                self.performance_visible = !self.performance_visible;
+                cx.notify();
+            }
+            "view.toggle_toolbar" => {
+                self.toolbar.visible = !self.toolbar.visible;
+                self.save_session(cx);
+                cx.notify();
+            }
+            "file_tree.toggle_hidden" => {
+                self.file_tree.update(cx, |tree, cx| tree.toggle_hidden(cx));
+            }
+            "workspace.add_folder" => self.pick_workspace_folder(cx),
+            "workspace.quick_open" => {
+                if !self.quick_open(cx) {
+                    return CommandOutcome::Failed("请先打开文件夹".to_string());
+                }
+            }
+            "git.review_changes" => self.start_review(cx),
+            "file.reveal_in_file_manager" => match self.active_file_on_disk(cx) {
+                Some(path) => self.reveal_in_file_manager(&path, cx),
+                None => return CommandOutcome::Failed("没有打开的文件".to_string()),
+            },
+            "file.open_terminal" => match self.active_file_on_disk(cx) {
+                Some(path) => self.open_terminal_at(&path, cx),
+                None => return CommandOutcome::Failed("没有打开的文件".to_string()),
+            },
             "core.undo" => {
                 self.editor.update(cx, |editor, cx| {
                     editor.perform_undo(cx);
@@ -601,15 +4658,111 @@ impl StartWindow {
                     editor.perform_select_all(cx);
                 });
             }
-            "core.save" => {
-                self.save_file(cx);
+            "editor.toggle_read_only" => {
+                self.editor.update(cx, |editor, cx| editor.toggle_read_only(cx));
+            }
+            "editor.revert_hunk" => {
+                self.editor.update(cx, |editor, cx| editor.revert_hunk(cx));
+            }
+            "editor.fold_all" => {
+                self.editor.update(cx, |editor, cx| editor.fold_all(cx));
+            }
+            "editor.unfold_all" => {
+                self.editor.update(cx, |editor, cx| editor.unfold_all(cx));
+            }
+            "editor.fold_level_1" => {
+                self.editor.update(cx, |editor, cx| editor.fold_top_level(cx));
+            }
+            "editor.convert_to_lf" => {
+                self.editor.update(cx, |editor, cx| editor.set_line_ending(LineEnding::Lf, cx));
+            }
+            "editor.convert_to_crlf" => {
+                self.editor.update(cx, |editor, cx| editor.set_line_ending(LineEnding::Crlf, cx));
+            }
+            "editor.go_to_line" => {
+                if !self.go_to_line(cx) {
+                    return CommandOutcome::Failed("没有打开的文本文件".to_string());
+                }
+            }
+            "project.compile" => {
+                if let Err(message) = self.compile_project(cx) {
+                    return CommandOutcome::Failed(message);
+                }
+            }
+            "editor.recover_discarded" => {
+                if !self.recover_discarded(cx) {
+                    return CommandOutcome::Failed("没有可恢复的修改".to_string());
+                }
+            }
+            "core.new_file" => {
+                self.open_untitled(cx);
+            }
+            "window.new" => {
+                cx.defer(|cx| open_start_window(cx, None, Instant::now(), false));
+            }
+            "core.open_file" => {
+                self.pick_and_open(false, cx);
+            }
+            "core.open_folder" => {
+                self.pick_and_open(true, cx);
+            }
+            "core.save" => {
+                self.save_file(cx);
+            }
+            "core.save_as" => {
+                self.save_as(cx);
+            }
+            "core.cycle_save_error_check" => {
+                self.save_error_check = self.save_error_check.next();
+                self.pending_save = None;
+                let mut settings = EditorSettings::load();
+                settings.save_error_check = self.save_error_check;
+                settings.save();
+                let message = format!("保存时检查错误：{}", self.save_error_check.label());
+                self.status_bar.update(cx, |bar, cx| bar.flash(message, cx));
+                cx.notify();
+            }
+            "files.autosave.toggle" => {
+                // 每次开启时重新读取延迟设置
+                self.autosave.set_delay(EditorSettings::load().autosave_delay());
+                let enabled = self.autosave.toggle();
+                let message = if enabled { "自动保存：开" } else { "自动保存：关" };
+                self.status_bar.update(cx, |bar, cx| {
+                    bar.set_autosave(enabled, cx);
+                    bar.flash(message.to_string(), cx);
+                });
+                if enabled {
+                    self.autosave_active(cx);
+                }
+            }
+            "editor.toggle_format_on_save" => {
+                self.format_on_save = !self.format_on_save;
+                let message = if self.format_on_save { "保存时格式化：开" } else { "保存时格式化：关" };
+                self.status_bar.update(cx, |bar, cx| bar.flash(message.to_string(), cx));
+            }
+            "workspace.prepare_commit" => {
+                self.prepare_commit(cx);
             }
             "core.close" => {
                 self.close_active_tab(cx);
             }
             "core.exit" => {
+                self.save_session(cx);
                 std::process::exit(0);
             }
+            "core.clear_session" => {
+                Session::clear();
+                self.session_cleared = true;
+            }
+            "view.switch_last_editor" => {
+                self.switch_to_last_editor(cx);
+            }
+            "file.toggle_bom" => {
+                self.toggle_bom(cx);
+            }
+            "view.toggle_theme" => {
+                component::toggle_theme(cx);
+            }
             "view.set_background" => {
                 let executor = cx.background_executor().clone();
                 cx.spawn(move |view: WeakEntity<StartWindow>, cx: &mut AsyncApp| {
@@ -653,33 +4806,62 @@ impl StartWindow {
                             }).await;
 
                             view.update(&mut cx, |this: &mut StartWindow, cx: &mut Context<StartWindow>| {
-                                this.background_image = Some(final_path);
-                                this.file_tree.update(cx, |tree: &mut FileTree, cx: &mut Context<FileTree>| {
-                                    tree.set_transparent(true, cx);
-                                });
-                                cx.notify();
+                                this.set_background_image(final_path, cx);
                             }).ok();
                         }
                     }
                 }).detach();
             }
+            id if id.starts_with(ICON_THEME_COMMAND_PREFIX) => {
+                self.select_icon_theme(&id[ICON_THEME_COMMAND_PREFIX.len()..], cx);
+            }
+            id if id.starts_with(DISABLE_PLUGIN_COMMAND_PREFIX) => {
+                let plugin_id = &id[DISABLE_PLUGIN_COMMAND_PREFIX.len()..];
+                if !self.set_plugin_enabled(plugin_id, false, cx) {
+                    return CommandOutcome::Failed(format!("找不到插件 {}", plugin_id));
+                }
+            }
             _ => {
-                println!("Executing command: {}", command_id);
-                // Future: Delegate to plugin manager
+                self.emit_plugin_event(ActivationEvent::Command(command_id.to_string()), cx);
+                let invocation = self.plugin_manager.update(cx, |manager, _| manager.execute(command_id, argument));
+                match invocation {
+                    Ok(PluginInvocation::Unknown) => {
+                        println!("Unknown command: {}", command_id);
+                        return CommandOutcome::Unhandled;
+                    }
+                    Ok(PluginInvocation::Builtin(action)) => return self.run_command(&action, None, cx),
+                    Ok(PluginInvocation::Spawned(pid)) => info!("Command {} started process {}", command_id, pid),
+                    Ok(PluginInvocation::Native(command)) => self.run_native_command(command, cx),
+                    Err(err) => {
+                        let message = format!("{:#}", err);
+                        warn!("{}", message);
+                        self.status_bar.update(cx, |bar, cx| bar.set_warning(Some(message.clone()), cx));
+                        return CommandOutcome::Failed(message);
+                    }
+                }
             }
         }
+        CommandOutcome::Done
     }
 }
 
 impl Render for StartWindow {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if self.startup.first_paint().is_none() {
+            let view = cx.entity().downgrade();
+            window.on_next_frame(move |_window, cx| {
+                view.update(cx, |this, cx| this.start_deferred_startup(cx)).ok();
+            });
+        }
         let has_bg = self.background_image.is_some();
         let alpha = if has_bg { 0xcc } else { 0xff };
-        let tabs_bar_bg = if has_bg { rgba(0x1f242800 | alpha) } else { rgb(0xff1f2428) };
-        let tab_active_bg = if has_bg { rgba(0x2d353b00 | alpha) } else { rgb(0xff2d353b) };
-        let title_bar_bg = if has_bg { rgba(0x232a2e00 | alpha) } else { rgb(0xff232a2e) };
-        let main_content_bg = if has_bg { rgba(0x2d353b00 | alpha) } else { rgb(0xff2d353b) };
-        let file_tree_bg = if has_bg { rgba(0x25252600 | alpha) } else { rgb(0xff252526) };
+        let theme = component::theme(cx);
+        let bg_opacity = alpha as f32 / 255.0;
+        let tabs_bar_bg = theme.surface.opacity(bg_opacity);
+        let tab_active_bg = theme.panel.opacity(bg_opacity);
+        let title_bar_bg = theme.header.opacity(bg_opacity);
+        let main_content_bg = theme.panel.opacity(bg_opacity);
+        let file_tree_bg = theme.sidebar.opacity(bg_opacity);
         
         let view = cx.entity();
         let view_for_focus = view.clone();
@@ -688,16 +4870,30 @@ impl Render for StartWindow {
         let _file_tree_for_drop = file_tree.clone();
         let is_dragging = file_tree_view.is_dragging();
         let drag_source = file_tree_view.drag_source();
+        let drag_count = file_tree_view.drag_count();
         let mouse_position = file_tree_view.mouse_position();
         let context_menu_path = self.context_menu_path.clone();
         let context_menu_is_dir = self.context_menu_is_dir;
+        let has_file_clipboard = self.file_clipboard.is_some();
+        // 在顶层文件夹上右键时提供工作区操作
+        let (context_menu_on_root, context_menu_on_extra_root) = match context_menu_path.as_ref() {
+            Some(path) => (
+                file_tree_view.roots().contains(path),
+                file_tree_view.extra_roots().contains(path),
+            ),
+            None => (false, false),
+        };
         let context_menu_position = self.context_menu_position;
+        let context_menu_plugin_items = match context_menu_path.as_ref() {
+            Some(path) => self.plugin_manager.read(cx).menu_items(FILE_TREE_CONTEXT_MENU, path, context_menu_is_dir),
+            None => Vec::new(),
+        };
         let confirm_action = self.confirm_action.clone();
         let confirm_open = self.confirm_open;
         let view_for_confirm = view.clone();
         let view_for_cancel = view_for_confirm.clone();
         let view_for_dismiss = view_for_confirm.clone();
-        let open_tabs = self.open_tabs.clone();
+        let open_tabs: Vec<PathBuf> = self.open_tabs.iter().map(|t| t.path.clone()).collect();
         let active_tab = self.active_tab.clone();
         let view_for_menu = view.clone();
         let external_drag_position = self.external_drag_position;
@@ -714,51 +4910,66 @@ impl Render for StartWindow {
             .items_center()
             .bg(tabs_bar_bg)
             .border_b_1()
-            .border_color(rgb(0xff3c474d))
-            .px(px(6.0));
+            .border_color(theme.border)
+            .px(px(6.0))
+            .focus_region(&self.tab_bar_focus);
 
         for path in open_tabs {
-            let label = path
-                .file_name()
-                .map(|name| name.to_string_lossy().to_string())
-                .unwrap_or_else(|| path.to_string_lossy().to_string());
+            let label = Self::tab_label(&path);
             let is_active = active_tab.as_ref().map(|p| p == &path).unwrap_or(false);
+            let tab = self.find_tab(&path);
+            let is_deleted = tab.is_some_and(|t| t.deleted);
+            let is_missing = is_deleted || tab.is_some_and(|t| t.missing);
+            let label = if is_deleted {
+                format!("{}（已删除）", label)
+            } else if is_missing {
+                format!("{}（不在此分支）", label)
+            } else {
+                label
+            };
             let view_for_tab = view.clone();
             let view_for_close = view_for_tab.clone();
+            let view_for_key = view_for_tab.clone();
             let path_clone = path.clone();
             let path_for_close = path.clone();
+            let path_for_key = path.clone();
             let tab = div()
+                .id(SharedString::from(format!("tab-{}", path.display())))
+                .focus_ring(cx)
                 .mr(px(4.0))
                 .px(px(10.0))
                 .py(px(4.0))
                 .rounded_md()
                 .cursor_pointer()
                 .text_size(px(12.0))
-                .text_color(if is_active {
-                    rgb(0xffe6e0d9)
+                .text_color(if is_missing {
+                    theme.muted_text.opacity(0.7)
+                } else if is_active {
+                    theme.text
                 } else {
-                    rgb(0xffa9b1b6)
+                    theme.muted_text
                 })
                 .bg(if is_active {
                     tab_active_bg
                 } else {
-                    rgba(0x00000000)
+                    transparent_black()
                 })
-                .hover(|s| s.bg(rgba(0xffffff12)))
+                .hover(move |s| s.bg(theme.list_hover))
                 .flex()
                 .items_center()
+                .child(div().mr(px(6.0)).child(file_icon(&path.to_string_lossy(), cx)))
                 .child(label)
                 .child(
                     div()
                         .ml(px(6.0))
                         .text_size(px(12.0))
-                        .text_color(rgb(0xff8b949e))
-                        .hover(|s| s.text_color(rgb(0xffe6e0d9)))
+                        .text_color(theme.muted_text)
+                        .hover(move |s| s.text_color(theme.text))
                         .child("×")
                         .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
                             cx.stop_propagation();
                             view_for_close.update(cx, |this, cx| {
-                                this.close_tab(&path_for_close, cx);
+                                this.request_close_tab(&path_for_close, cx);
                             });
                         }),
                 )
@@ -766,47 +4977,171 @@ impl Render for StartWindow {
                     view_for_tab.update(cx, |this, cx| {
                         this.open_file_path(path_clone.clone(), cx);
                     });
+                })
+                .on_click(move |event, _window, cx| {
+                    if event.is_keyboard() {
+                        view_for_key.update(cx, |this, cx| {
+                            this.open_file_path(path_for_key.clone(), cx);
+                        });
+                    }
                 });
             tabs_bar = tabs_bar.child(tab);
         }
+        if let Some(preview) = &self.preview {
+            let label = preview
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            tabs_bar = tabs_bar.child(
+                div()
+                    .mr(px(4.0))
+                    .px(px(10.0))
+                    .py(px(4.0))
+                    .rounded_md()
+                    .text_size(px(12.0))
+                    .italic()
+                    .text_color(theme.text)
+                    .bg(tab_active_bg)
+                    .child(label),
+            );
+        }
 
+        let (cancel_label, confirm_label) = match &confirm_action {
+            Some(ConfirmAction::ReloadExternal { .. }) => ("保留编辑器内容", "重新载入"),
+            Some(ConfirmAction::OverwriteExternal { .. }) => ("取消", "仍然保存"),
+            Some(ConfirmAction::UpdateReferences { .. }) => ("只重命名", "更新引用"),
+            Some(ConfirmAction::OpenLargeFile { .. }) => ("取消", "只读打开"),
+            _ => ("取消", "确定"),
+        };
         let (confirm_title, confirm_body) = match &confirm_action {
-            Some(ConfirmAction::Move { src, dst }) => (
+            Some(ConfirmAction::Move { moves }) => (
                 "确认移动".to_string(),
                 div()
                     .flex()
                     .flex_col()
-                    .child("将")
-                    .child(
+                    .child(if moves.len() > 1 { format!("将以下 {} 项", moves.len()) } else { "将".to_string() })
+                    .children(moves.iter().map(|(src, _)| {
                         div()
                             .mt(px(4.0))
-                            .text_color(rgb(0xffe6e0d9))
-                            .child(src.to_string_lossy().to_string()),
-                    )
+                            .text_color(theme.text)
+                            .child(src.to_string_lossy().to_string())
+                    }))
                     .child(div().mt(px(6.0)).child("移动到"))
                     .child(
                         div()
                             .mt(px(4.0))
-                            .text_color(rgb(0xffe6e0d9))
-                            .child(dst.to_string_lossy().to_string()),
+                            .text_color(theme.text)
+                            .child(match moves.as_slice() {
+                                [(_, dst)] => dst.to_string_lossy().to_string(),
+                                // 多项移到同一个文件夹，只显示文件夹
+                                _ => moves
+                                    .first()
+                                    .and_then(|(_, dst)| dst.parent())
+                                    .map(|dir| dir.to_string_lossy().to_string())
+                                    .unwrap_or_default(),
+                            }),
                     )
                     .into_any_element(),
             ),
-            Some(ConfirmAction::Delete { path, is_dir }) => (
+            Some(ConfirmAction::Delete { entries }) => (
                 "确认删除".to_string(),
+                div()
+                    .flex()
+                    .flex_col()
+                    .child(match entries.as_slice() {
+                        [(_, is_dir)] => format!("确定删除{}：", if *is_dir { "文件夹" } else { "文件" }),
+                        _ => format!("确定删除以下 {} 项：", entries.len()),
+                    })
+                    .children(entries.iter().map(|(path, _)| {
+                        div()
+                            .mt(px(6.0))
+                            .text_color(theme.text)
+                            .child(path.to_string_lossy().to_string())
+                    }))
+                    .into_any_element(),
+            ),
+            Some(ConfirmAction::RemoveRoot { root }) => (
+                "从工作区移除文件夹".to_string(),
+                div()
+                    .flex()
+                    .flex_col()
+                    .child(format!(
+                        "{} 中有 {} 个文件尚未保存，移除文件夹会关闭这些文件，修改将丢失。",
+                        Self::tab_label(root),
+                        self.unsaved_tabs_under(root, cx).len()
+                    ))
+                    .into_any_element(),
+            ),
+            Some(ConfirmAction::UpdateReferences { from, to, files }) => (
+                "更新引用".to_string(),
                 div()
                     .flex()
                     .flex_col()
                     .child(format!(
-                        "确定删除{}：",
-                        if *is_dir { "文件夹" } else { "文件" }
+                        "已将 {} 重命名为 {}，以下文件中引用了它，是否一并更新？",
+                        Self::tab_label(from),
+                        Self::tab_label(to)
                     ))
+                    .children(files.iter().map(|(path, count)| {
+                        div()
+                            .mt(px(6.0))
+                            .flex()
+                            .justify_between()
+                            .gap(px(12.0))
+                            .child(path.to_string_lossy().to_string())
+                            .child(div().flex_none().text_color(theme.muted_text).child(format!("{} 处", count)))
+                    }))
+                    .into_any_element(),
+            ),
+            Some(ConfirmAction::CloseUnsaved { path }) => (
+                "关闭未保存的文件".to_string(),
+                div()
+                    .flex()
+                    .flex_col()
+                    .child(format!("{} 尚未保存，关闭后在本次运行期间可以用 Recover Discarded Changes 命令找回。", Self::tab_label(path)))
+                    .into_any_element(),
+            ),
+            Some(ConfirmAction::ReloadExternal { path }) => (
+                "文件已在外部修改".to_string(),
+                div()
+                    .flex()
+                    .flex_col()
+                    .child(format!("{} 已被其它程序修改，但编辑器中有未保存的修改。", Self::tab_label(path)))
+                    .child(div().mt(px(6.0)).child("重新载入会丢弃编辑器中的修改；保留则之后保存时覆盖磁盘上的内容。"))
+                    .into_any_element(),
+            ),
+            Some(ConfirmAction::OverwriteExternal { path }) => (
+                "保存冲突".to_string(),
+                div()
+                    .flex()
+                    .flex_col()
+                    .child(format!("{} 在载入后已被另一个窗口或其它程序保存。", Self::tab_label(path)))
+                    .child(div().mt(px(6.0)).child("仍然保存会覆盖那些修改；取消后可以重新载入磁盘上的内容。"))
+                    .into_any_element(),
+            ),
+            Some(ConfirmAction::OpenLargeFile { path, size }) => (
+                "大文件".to_string(),
+                div()
+                    .flex()
+                    .flex_col()
+                    .child(format!("{} 大小为 {}，超过了大文件阈值。", Self::tab_label(path), file_guard::format_size(*size)))
+                    .child(div().mt(px(6.0)).child("以只读方式打开，不做语法高亮、差异标记和语言服务分析。"))
+                    .into_any_element(),
+            ),
+            Some(ConfirmAction::RestartLanguageService { settings }) => (
+                "重启结绳服务".to_string(),
+                div()
+                    .flex()
+                    .flex_col()
+                    .child("项目设置已更改，需要重启结绳服务才能生效。")
                     .child(
                         div()
                             .mt(px(6.0))
-                            .text_color(rgb(0xffe6e0d9))
-                            .child(path.to_string_lossy().to_string()),
+                            .text_color(theme.text)
+                            .child(settings.to_string_lossy().to_string()),
                     )
+                    .child(div().mt(px(6.0)).child("重启会丢弃正在进行的分析。"))
                     .into_any_element(),
             ),
             None => ("确认".to_string(), div().into_any_element()),
@@ -820,6 +5155,33 @@ impl Render for StartWindow {
             .h_full()
             .on_children_prepainted(move |_, window, cx| {
                 view_for_focus.update(cx, |this, cx| {
+                    this.restore_overlay_focus(window, cx);
+                    if this.needs_palette_focus {
+                        this.needs_palette_focus = false;
+                        let handle = this.command_palette.read(cx).focus_handle.clone();
+                        handle.focus(window);
+                        return;
+                    }
+                    if this.needs_review_focus && !this.command_palette.read(cx).is_visible() {
+                        this.needs_review_focus = false;
+                        this.needs_focus_restore = false;
+                        this.review_panel.read(cx).focus(window);
+                        return;
+                    }
+                    if this.needs_references_focus && !this.command_palette.read(cx).is_visible() {
+                        this.needs_references_focus = false;
+                        this.needs_focus_restore = false;
+                        this.references_panel.read(cx).focus(window);
+                        return;
+                    }
+                    if this.needs_git_focus && !this.command_palette.read(cx).is_visible() {
+                        this.needs_git_focus = false;
+                        this.needs_focus_restore = false;
+                        if let Some(git_panel) = this.tool_panel.read(cx).git_panel() {
+                            git_panel.update(cx, |panel, cx| panel.focus_commit_input(window, cx));
+                        }
+                        return;
+                    }
                     if (this.needs_focus_restore || this.needs_initial_focus)
                         && !this.command_palette.read(cx).is_visible()
                     {
@@ -830,6 +5192,27 @@ impl Render for StartWindow {
                     }
                 });
             })
+            .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, _window, cx| {
+                // 拖到文件树和放置区域以外时拖动图标仍跟随鼠标
+                if this.file_tree.read(cx).is_dragging() {
+                    let position = event.position;
+                    this.file_tree.update(cx, |tree, cx| tree.drag_moved(position, cx));
+                }
+            }))
+            .on_mouse_up(MouseButton::Left, cx.listener(|this, _: &MouseUpEvent, _window, cx| {
+                // 在文件树和放置区域以外松开时取消拖动
+                if this.file_tree.read(cx).is_dragging() {
+                    this.tree_drop_target = None;
+                    this.file_tree.update(cx, |tree, cx| tree.cancel_drag(cx));
+                }
+                // 工具栏按钮拖到按钮以外松开，顺序已经改好
+                if let Some((_, moved)) = this.toolbar_drag.take() {
+                    if moved {
+                        this.save_session(cx);
+                    }
+                    cx.notify();
+                }
+            }))
             .on_drag_move(cx.listener(|this, event: &DragMoveEvent<ExternalPaths>, _window, cx| {
                 let paths = event.drag(cx).paths();
                 this.external_drag_position = event.event.position;
@@ -845,18 +5228,7 @@ impl Render for StartWindow {
                 if let Some(path) = paths.paths().first() {
                      if path.is_dir() {
                          println!("Dropping folder: {:?}", path);
-                         this.file_tree.update(cx, |tree, cx| {
-                             tree.set_root_path(path.clone(), cx);
-                         });
-                         
-                         let path_clone = path.clone();
-                         this.tool_panel.update(cx, |panel, cx| {
-                            if let Some(git_panel) = panel.git_panel() {
-                                git_panel.update(cx, |gp, cx| {
-                                    gp.set_repo_root(path_clone, cx);
-                                });
-                            }
-                         });
+                         this.open_folder(path.clone(), cx);
                      }
                 }
                 cx.notify();
@@ -874,10 +5246,11 @@ impl Render for StartWindow {
                         div()
                             .w_16()
                             .h_full()
-                            .bg(rgb(0xfffbfafd))
+                            .bg(title_bar_bg)
                             .window_control_area(WindowControlArea::Max),
                     ),
             )
+            .child(self.render_toolbar(cx))
             .child(
                 div()
                     .flex_1()
@@ -890,7 +5263,7 @@ impl Render for StartWindow {
                                 .w(px(260.0))
                                 .h_full()
                                 .border_r_1()
-                                .border_color(rgb(0xff3c474d))
+                                .border_color(theme.border)
                                 .bg(file_tree_bg)
                                 .child(self.tool_panel.clone())
                         } else {
@@ -903,10 +5276,22 @@ impl Render for StartWindow {
                             .flex()
                             .flex_col()
                             .h_full()
-                            .child(tabs_bar)
-                            .child({
+                            .child(self.tree_drop_zone(TreeDropTarget::TabBar, tabs_bar.into_any_element(), cx))
+                            .child(self.render_save_banner(cx))
+                            .child(self.render_recovered_banner(cx))
+                            .child(self.tree_drop_zone(TreeDropTarget::Editor, {
                                 let is_image = self.active_tab.as_ref().map(|p| Self::is_image_path(p)).unwrap_or(false);
-                                if is_image {
+                                let is_diff = self.active_tab.as_ref().map(Self::is_diff_path).unwrap_or(false);
+                                let binary = self
+                                    .active_tab
+                                    .as_ref()
+                                    .and_then(|active| self.binary_tabs.iter().find(|(p, _)| p == active))
+                                    .cloned();
+                                if is_diff {
+                                    div().flex_1().child(self.diff_viewer.clone())
+                                } else if let Some((path, size)) = binary {
+                                    self.render_binary_notice(&path, size, cx)
+                                } else if is_image {
                                     div().flex_1().child(self.image_viewer.clone())
                                 } else {
                                     let is_md = self.active_tab.as_ref().map(|p| Self::is_markdown_path(p)).unwrap_or(false);
@@ -916,9 +5301,47 @@ impl Render for StartWindow {
                                         div().flex_1().child(self.editor.clone())
                                     }
                                 }
-                            })
+                                .into_any_element()
+                            }, cx).flex_1())
+                    )
+                    .child(
+                        if self.review_visible {
+                            div()
+                                .w(px(280.0))
+                                .h_full()
+                                .border_l_1()
+                                .border_color(theme.border)
+                                .bg(file_tree_bg)
+                                .child(self.review_panel.clone())
+                        } else {
+                            div()
+                        }
                     ),
             )
+            .child(if self.references_visible {
+                div()
+                    .w_full()
+                    .h(px(220.0))
+                    .flex_none()
+                    .border_t_1()
+                    .border_color(theme.border)
+                    .bg(main_content_bg)
+                    .child(self.references_panel.clone())
+            } else {
+                div()
+            })
+            .child(if self.output_visible {
+                div()
+                    .w_full()
+                    .h(px(200.0))
+                    .flex_none()
+                    .border_t_1()
+                    .border_color(theme.border)
+                    .bg(main_content_bg)
+                    .child(self.output_panel.clone())
+            } else {
+                div()
+            })
             .child(self.status_bar.clone())
             .child(
                 if is_dragging {
@@ -930,19 +5353,23 @@ impl Render for StartWindow {
                             .left(mouse_position.x + px(10.0))
                             .flex()
                             .items_center()
-                            .bg(rgb(0x2d353b))
+                            .bg(theme.panel)
                             .border_1()
-                            .border_color(rgb(0x454545))
+                            .border_color(theme.border)
                             .rounded_md()
                             .p(px(4.0))
                             .opacity(0.8)
-                            .child(file_icon(&name))
+                            .child(if path.is_dir() {
+                                folder_icon(&name, false, cx).into_any_element()
+                            } else {
+                                file_icon(&name, cx).into_any_element()
+                            })
                             .child(
                                 div()
                                     .ml(px(4.0))
                                     .text_size(px(12.0))
-                                    .text_color(rgb(0xffffff))
-                                    .child(name)
+                                    .text_color(theme.text)
+                                    .child(if drag_count > 1 { format!("{} 等 {} 项", name, drag_count) } else { name })
                             )
                             .into_any_element()
                     } else {
@@ -955,54 +5382,78 @@ impl Render for StartWindow {
             .child(
                 modal()
                     .open(confirm_open)
+                    .track_focus(&self.confirm_focus)
                     .title(confirm_title)
                     .child(
                         div()
                             .text_size(px(13.0))
-                            .text_color(rgb(0xffe6e0d9))
+                            .text_color(theme.text)
                             .child(confirm_body),
                     )
                     .footer(
                         div()
                             .flex()
                             .justify_end()
-                            .child(
+                            .child({
+                                let view_for_key = view_for_cancel.clone();
                                 div()
+                                    .id("confirm-cancel")
+                                    .focus_ring(cx)
                                     .px(px(12.0))
                                     .py(px(6.0))
                                     .rounded_md()
-                                    .bg(rgb(0xff3c474d))
+                                    .bg(theme.border)
                                     .text_size(px(12.0))
-                                    .text_color(rgb(0xffe6e0d9))
+                                    .text_color(theme.text)
                                     .cursor_pointer()
-                                    .hover(|s| s.bg(rgba(0xffffff12)))
+                                    .hover(|s| s.bg(theme.list_hover))
                                     .mr(px(8.0))
-                                    .child("取消")
+                                    .child(cancel_label)
                                     .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
                                         view_for_cancel.update(cx, |this, cx| {
                                             this.cancel_confirm(cx);
                                         });
-                                    }),
-                            )
+                                    })
+                                    .on_click(move |event, _window, cx| {
+                                        if event.is_keyboard() {
+                                            view_for_key.update(cx, |this, cx| this.cancel_confirm(cx));
+                                        }
+                                    })
+                            })
                             .child({
                                 let view_for_confirm = view.clone();
+                                let view_for_key = view.clone();
                                 div()
+                                    .id("confirm-default")
+                                    .track_focus(&self.confirm_default_focus)
+                                    .focus_ring(cx)
                                     .px(px(12.0))
                                     .py(px(6.0))
                                     .rounded_md()
-                                    .bg(rgb(0xff2d6cdf))
+                                    .bg(theme.primary_button)
                                     .text_size(px(12.0))
-                                    .text_color(rgb(0xffffffff))
+                                    .text_color(theme.primary_button_text)
                                     .cursor_pointer()
-                                    .hover(|s| s.bg(rgb(0xff3b7bff)))
-                                    .child("确定")
+                                    .hover(|s| s.bg(theme.primary_button_hover))
+                                    .child(confirm_label)
                                     .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
                                         view_for_confirm.update(cx, |this, cx| {
                                             this.apply_confirm(cx);
                                         });
                                     })
+                                    .on_click(move |event, _window, cx| {
+                                        if event.is_keyboard() {
+                                            view_for_key.update(cx, |this, cx| this.apply_confirm(cx));
+                                        }
+                                    })
                             }),
                     )
+                    .on_confirm({
+                        let view = view.clone();
+                        move |_window, cx| {
+                            view.update(cx, |this, cx| this.apply_confirm(cx));
+                        }
+                    })
                     .on_dismiss(move |_window, cx| {
                         view_for_dismiss.update(cx, |this, cx| {
                             this.cancel_confirm(cx);
@@ -1042,9 +5493,9 @@ impl Render for StartWindow {
                     .absolute()
                     .top(external_drag_position.y + px(12.0))
                     .left(external_drag_position.x + px(12.0))
-                    .bg(rgba(0x1f2428e6))
+                    .bg(theme.surface.opacity(0.9))
                     .border_1()
-                    .border_color(rgba(0xffffff24))
+                    .border_color(theme.list_active)
                     .rounded_md()
                     .px(px(10.0))
                     .py(px(8.0))
@@ -1058,14 +5509,14 @@ impl Render for StartWindow {
                             .child(
                                 div()
                                     .text_size(px(12.0))
-                                    .text_color(rgb(0xffe6e0d9))
+                                    .text_color(theme.text)
                                     .child(title),
                             )
                             .child(
                                 div()
                                     .mt(px(2.0))
                                     .text_size(px(11.0))
-                                    .text_color(rgb(0xffa9b1b6))
+                                    .text_color(theme.muted_text)
                                     .child(subtitle),
                             ),
                     )
@@ -1073,8 +5524,65 @@ impl Render for StartWindow {
             } else {
                 div().into_any_element()
             })
+            .child(match &self.tab_switcher {
+                Some(switcher) if switcher.overlay_visible => {
+                    let mut list = div()
+                        .flex()
+                        .flex_col()
+                        .min_w(px(280.0))
+                        .bg(theme.surface)
+                        .border_1()
+                        .border_color(theme.border)
+                        .rounded_md()
+                        .shadow(theme.overlay_shadow())
+                        .p(px(6.0));
+                    for (index, path) in self.tab_mru.iter().enumerate() {
+                        let name = Self::tab_label(path);
+                        let selected = index == switcher.index;
+                        list = list.child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .px(px(8.0))
+                                .py(px(4.0))
+                                .rounded_md()
+                                .text_size(px(12.0))
+                                .text_color(if selected { theme.text } else { theme.muted_text })
+                                .bg(if selected { theme.list_selection } else { transparent_black() })
+                                .child(div().mr(px(6.0)).child(file_icon(&name, cx)))
+                                .child(name),
+                        );
+                    }
+                    div()
+                        .absolute()
+                        .top(px(60.0))
+                        .left_0()
+                        .right_0()
+                        .flex()
+                        .justify_center()
+                        .child(list)
+                        .into_any_element()
+                }
+                _ => div().into_any_element(),
+            })
+            .child(self.render_prepare_commit_toast(cx))
+            .child(self.render_performance_overlay(cx))
             .child(self.command_palette.clone())
             .on_action(cx.listener(Self::show_command_palette))
+            .on_action(cx.listener(Self::quick_open_action))
+            .on_action(cx.listener(Self::run_command_action))
+            .on_action(cx.listener(Self::go_to_line_action))
+            .on_action(cx.listener(Self::compile_project_action))
+            .on_action(cx.listener(Self::switch_tab))
+            .on_action(cx.listener(Self::new_file))
+            .on_action(cx.listener(Self::focus_next_region))
+            .on_action(cx.listener(Self::focus_previous_region))
+            .on_action(cx.listener(Self::focus_next_element))
+            .on_action(cx.listener(Self::focus_previous_element))
+            .on_action(cx.listener(Self::focus_file_tree_filter))
+            .on_action(cx.listener(Self::open_file_action))
+            .on_action(cx.listener(Self::open_folder_action))
+            .on_modifiers_changed(cx.listener(Self::on_modifiers_changed))
             /*
             .child(
                 modal()
@@ -1083,7 +5591,7 @@ impl Render for StartWindow {
                     .child(
                         div()
                             .text_size(px(13.0))
-                            .text_color(rgb(0xffe6e0d9))
+                            .text_color(theme.text)
                             .child("点击了按钮，弹窗已打开"),
                     )
                     .on_dismiss(move |_window, cx| {
@@ -1097,6 +5605,7 @@ impl Render for StartWindow {
             .child(
                 popover()
                     .open(self.context_menu_open)
+                    .track_focus(&self.context_menu_focus)
                     .position(context_menu_position)
                     .w(px(180.0))
                     .child(
@@ -1109,13 +5618,15 @@ impl Render for StartWindow {
                                 let path = context_menu_path.clone();
                                 let label = if context_menu_is_dir { "展开/折叠" } else { "打开" };
                                 div()
+                                    .id(("context-menu-item", 0usize))
+                                    .focus_ring(cx)
                                     .cursor_pointer()
                                     .p(px(6.0))
                                     .text_size(px(13.0))
-                                    .text_color(rgb(0xffe6e0d9))
-                                    .hover(|s| s.bg(rgba(0xffffff12)))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
                                     .child(label)
-                                    .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
+                                    .on_click(move |_, _window, cx| {
                                         if let Some(path) = path.clone() {
                                             if context_menu_is_dir {
                                                 file_tree.update(cx, |tree, cx| {
@@ -1139,13 +5650,15 @@ impl Render for StartWindow {
                                 let file_tree = file_tree.clone();
                                 let path = context_menu_path.clone();
                                 div()
+                                    .id(("context-menu-item", 1usize))
+                                    .focus_ring(cx)
                                     .cursor_pointer()
                                     .p(px(6.0))
                                     .text_size(px(13.0))
-                                    .text_color(rgb(0xffe6e0d9))
-                                    .hover(|s| s.bg(rgba(0xffffff12)))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
                                     .child("新建文件")
-                                    .on_mouse_down(MouseButton::Left, move |_, window, cx| {
+                                    .on_click(move |_, window, cx| {
                                         if let Some(path) = path.clone() {
                                             file_tree.update(cx, |tree, cx| {
                                                 tree.begin_inline_create(
@@ -1169,13 +5682,15 @@ impl Render for StartWindow {
                                 let file_tree = file_tree.clone();
                                 let path = context_menu_path.clone();
                                 div()
+                                    .id(("context-menu-item", 2usize))
+                                    .focus_ring(cx)
                                     .cursor_pointer()
                                     .p(px(6.0))
                                     .text_size(px(13.0))
-                                    .text_color(rgb(0xffe6e0d9))
-                                    .hover(|s| s.bg(rgba(0xffffff12)))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
                                     .child("新建文件夹")
-                                    .on_mouse_down(MouseButton::Left, move |_, window, cx| {
+                                    .on_click(move |_, window, cx| {
                                         if let Some(path) = path.clone() {
                                             file_tree.update(cx, |tree, cx| {
                                                 tree.begin_inline_create(
@@ -1194,17 +5709,191 @@ impl Render for StartWindow {
                                         });
                                     })
                             })
+                            .children(if context_menu_is_dir {
+                                [("在此文件夹中查找…", false), ("从搜索中排除此文件夹", true)]
+                                    .into_iter()
+                                    .map(|(label, exclude)| {
+                                        let view = view_for_menu.clone();
+                                        let path = context_menu_path.clone();
+                                        div()
+                                            .id(("context-menu-item", 3usize))
+                                            .focus_ring(cx)
+                                            .cursor_pointer()
+                                            .p(px(6.0))
+                                            .text_size(px(13.0))
+                                            .text_color(theme.text)
+                                            .hover(|s| s.bg(theme.list_hover))
+                                            .child(label)
+                                            .on_click(move |_, window, cx| {
+                                                view.update(cx, |this, cx| {
+                                                    if let Some(path) = path.clone() {
+                                                        this.search_in_folder(path, exclude, window, cx);
+                                                    }
+                                                    this.context_menu_open = false;
+                                                    this.context_menu_path = None;
+                                                    cx.notify();
+                                                });
+                                            })
+                                    })
+                                    .collect::<Vec<_>>()
+                            } else {
+                                Vec::new()
+                            })
+                            .children(
+                                [
+                                    ("添加文件夹到工作区…", context_menu_on_root, false),
+                                    ("从工作区移除文件夹", context_menu_on_extra_root, true),
+                                ]
+                                .into_iter()
+                                .filter(|(_, shown, _)| *shown)
+                                .map(|(label, _, remove)| {
+                                    let view = view_for_menu.clone();
+                                    let path = context_menu_path.clone();
+                                    div()
+                                        .id(("context-menu-item", 4usize))
+                                        .focus_ring(cx)
+                                        .cursor_pointer()
+                                        .p(px(6.0))
+                                        .text_size(px(13.0))
+                                        .text_color(theme.text)
+                                        .hover(|s| s.bg(theme.list_hover))
+                                        .child(label)
+                                        .on_click(move |_, _window, cx| {
+                                            view.update(cx, |this, cx| {
+                                                match path.clone() {
+                                                    Some(path) if remove => {
+                                                        this.request_remove_workspace_folder(path, cx)
+                                                    }
+                                                    _ => this.pick_workspace_folder(cx),
+                                                }
+                                                this.context_menu_open = false;
+                                                this.context_menu_path = None;
+                                                cx.notify();
+                                            });
+                                        })
+                                })
+                                .collect::<Vec<_>>(),
+                            )
+                            .child({
+                                let view = view_for_menu.clone();
+                                let path = context_menu_path.clone();
+                                div()
+                                    .id(("context-menu-item", 5usize))
+                                    .focus_ring(cx)
+                                    .cursor_pointer()
+                                    .p(px(6.0))
+                                    .text_size(px(13.0))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
+                                    .child("复制")
+                                    .on_click(move |_, _window, cx| {
+                                        if let Some(path) = path.clone() {
+                                            cx.write_to_clipboard(ClipboardItem::new_string(
+                                                path.to_string_lossy().to_string(),
+                                            ));
+                                            view.update(cx, |this, _| {
+                                                this.file_clipboard =
+                                                    Some(FileClipboard { path, op: ClipboardOp::Copy });
+                                            });
+                                        }
+                                        view.update(cx, |this, cx| {
+                                            this.context_menu_open = false;
+                                            this.context_menu_path = None;
+                                            cx.notify();
+                                        });
+                                    })
+                            })
+                            .child({
+                                let view = view_for_menu.clone();
+                                let path = context_menu_path.clone();
+                                div()
+                                    .id(("context-menu-item", 6usize))
+                                    .focus_ring(cx)
+                                    .cursor_pointer()
+                                    .p(px(6.0))
+                                    .text_size(px(13.0))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
+                                    .child("剪切")
+                                    .on_click(move |_, _window, cx| {
+                                        if let Some(path) = path.clone() {
+                                            view.update(cx, |this, _| {
+                                                this.file_clipboard =
+                                                    Some(FileClipboard { path, op: ClipboardOp::Cut });
+                                            });
+                                        }
+                                        view.update(cx, |this, cx| {
+                                            this.context_menu_open = false;
+                                            this.context_menu_path = None;
+                                            cx.notify();
+                                        });
+                                    })
+                            })
+                            .children(
+                                context_menu_path
+                                    .clone()
+                                    .filter(|_| context_menu_is_dir && has_file_clipboard)
+                                    .map(|path| {
+                                        let view = view_for_menu.clone();
+                                        div()
+                                            .id(("context-menu-item", 7usize))
+                                            .focus_ring(cx)
+                                            .cursor_pointer()
+                                            .p(px(6.0))
+                                            .text_size(px(13.0))
+                                            .text_color(theme.text)
+                                            .hover(|s| s.bg(theme.list_hover))
+                                            .child("粘贴")
+                                            .on_click(move |_, _window, cx| {
+                                                view.update(cx, |this, cx| {
+                                                    this.context_menu_open = false;
+                                                    this.context_menu_path = None;
+                                                    this.paste_into(&path, cx);
+                                                    cx.notify();
+                                                });
+                                            })
+                                    }),
+                            )
+                            .child({
+                                let view = view_for_menu.clone();
+                                let file_tree = file_tree.clone();
+                                let path = context_menu_path.clone();
+                                div()
+                                    .id(("context-menu-item", 8usize))
+                                    .focus_ring(cx)
+                                    .cursor_pointer()
+                                    .p(px(6.0))
+                                    .text_size(px(13.0))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
+                                    .child("重命名")
+                                    .on_click(move |_, window, cx| {
+                                        if let Some(path) = path.clone() {
+                                            file_tree.update(cx, |tree, cx| {
+                                                tree.begin_inline_rename(path.clone(), cx);
+                                            });
+                                            file_tree.read(cx).focus(window);
+                                        }
+                                        view.update(cx, |this, cx| {
+                                            this.context_menu_open = false;
+                                            this.context_menu_path = None;
+                                            cx.notify();
+                                        });
+                                    })
+                            })
                             .child({
                                 let view = view_for_menu.clone();
                                 let path = context_menu_path.clone();
                                 div()
+                                    .id(("context-menu-item", 9usize))
+                                    .focus_ring(cx)
                                     .cursor_pointer()
                                     .p(px(6.0))
                                     .text_size(px(13.0))
-                                    .text_color(rgb(0xffe6e0d9))
-                                    .hover(|s| s.bg(rgba(0xffffff12)))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
                                     .child("复制路径")
-                                    .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
+                                    .on_click(move |_, _window, cx| {
                                         if let Some(path) = path.clone() {
                                             cx.write_to_clipboard(ClipboardItem::new_string(
                                                 path.to_string_lossy().to_string(),
@@ -1221,29 +5910,104 @@ impl Render for StartWindow {
                                 let view = view_for_menu.clone();
                                 let path = context_menu_path.clone();
                                 div()
+                                    .id(("context-menu-item", 10usize))
+                                    .focus_ring(cx)
+                                    .cursor_pointer()
+                                    .p(px(6.0))
+                                    .text_size(px(13.0))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
+                                    .child("在资源管理器中显示")
+                                    .on_click(move |_, _window, cx| {
+                                        view.update(cx, |this, cx| {
+                                            this.context_menu_open = false;
+                                            this.context_menu_path = None;
+                                            if let Some(path) = path.clone() {
+                                                this.reveal_in_file_manager(&path, cx);
+                                            }
+                                            cx.notify();
+                                        });
+                                    })
+                            })
+                            .child({
+                                let view = view_for_menu.clone();
+                                let path = context_menu_path.clone();
+                                div()
+                                    .id(("context-menu-item", 11usize))
+                                    .focus_ring(cx)
+                                    .cursor_pointer()
+                                    .p(px(6.0))
+                                    .text_size(px(13.0))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
+                                    .child("在终端中打开")
+                                    .on_click(move |_, _window, cx| {
+                                        view.update(cx, |this, cx| {
+                                            this.context_menu_open = false;
+                                            this.context_menu_path = None;
+                                            if let Some(path) = path.clone() {
+                                                this.open_terminal_at(&path, cx);
+                                            }
+                                            cx.notify();
+                                        });
+                                    })
+                            })
+                            .child({
+                                let view = view_for_menu.clone();
+                                let path = context_menu_path.clone();
+                                div()
+                                    .id(("context-menu-item", 12usize))
+                                    .focus_ring(cx)
                                     .cursor_pointer()
                                     .p(px(6.0))
                                     .text_size(px(13.0))
-                                    .text_color(rgb(0xffe6e0d9))
-                                    .hover(|s| s.bg(rgba(0xffffff12)))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
                                     .child("删除")
-                                    .on_mouse_down(MouseButton::Left, move |_, _window, cx| {
+                                    .on_click(move |_, _window, cx| {
                                         if let Some(path) = path.clone() {
                                             view.update(cx, |this, cx| {
-                                                this.request_confirm(
-                                                    ConfirmAction::Delete {
-                                                        path: path.clone(),
-                                                        is_dir: context_menu_is_dir,
-                                                    },
-                                                    cx,
-                                                );
+                                                // 在多选上右键时删除所有选中项
+                                                let entries = if this.context_menu_selection.is_empty() {
+                                                    vec![(path.clone(), context_menu_is_dir)]
+                                                } else {
+                                                    std::mem::take(&mut this.context_menu_selection)
+                                                };
+                                                this.request_confirm(ConfirmAction::Delete { entries }, cx);
                                                 this.context_menu_open = false;
                                                 this.context_menu_path = None;
                                                 cx.notify();
                                             });
                                         }
                                     })
-                            }),
+                            })
+                            .children(
+                                (!context_menu_plugin_items.is_empty())
+                                    .then(|| div().h(px(1.0)).my(px(2.0)).bg(theme.border)),
+                            )
+                            // 插件提供的菜单项，以点击的路径为命令参数
+                            .children(context_menu_plugin_items.into_iter().enumerate().map(|(index, item)| {
+                                let view = view_for_menu.clone();
+                                let path = context_menu_path.clone();
+                                div()
+                                    .id(("context-menu-plugin-item", index))
+                                    .focus_ring(cx)
+                                    .cursor_pointer()
+                                    .p(px(6.0))
+                                    .text_size(px(13.0))
+                                    .text_color(theme.text)
+                                    .hover(|s| s.bg(theme.list_hover))
+                                    .child(item.title)
+                                    .on_click(move |_, _window, cx| {
+                                        view.update(cx, |this, cx| {
+                                            this.context_menu_open = false;
+                                            this.context_menu_path = None;
+                                            let argument = path.as_ref().map(|path| path.to_string_lossy().to_string());
+                                            this.execute_command(&item.command, argument.as_deref(), cx);
+                                            cx.notify();
+                                        });
+                                    })
+                            })),
                     )
                     .on_dismiss(move |_window, cx| {
                         view_for_menu.update(cx, |this, cx| {
@@ -1267,12 +6031,12 @@ impl Render for StartWindow {
                     div()
                         .absolute()
                         .size_full()
-                        .bg(rgba(0x00000080))
+                        .bg(theme.overlay_bg)
                 )
                 .child(content)
                 .into_any_element()
         } else {
-            content.bg(rgb(0xFFFFFFFF)).into_any_element()
+            content.bg(theme.surface).into_any_element()
         }
     }
 }
@@ -0,0 +1,202 @@
+//! 所有窗口中已打开的文档：路径对应打开它的窗口和是否有未保存的修改。
+//! 在一个窗口中打开另一个窗口已打开的文件时，默认切换到那个窗口的标签，避免两份缓冲区保存时互相覆盖。
+//! 项目设置中打开 `window.shareDocuments` 后多个窗口可以同时编辑同一文件，各窗口定时把当前标签的内容
+//! 与其它窗口同步，看到的是同一份内容和同一个修改状态
+//!
+//! ```json
+//! { "window": { "shareDocuments": true } }
+//! ```
+
+use gpui::{AnyWindowHandle, Global};
+use log::warn;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::lsp::tiec::settings::PROJECT_SETTINGS_FILE;
+
+/// 进程中唯一的文档表，以 gpui 全局状态保存
+pub type OpenDocuments = DocumentRegistry<AnyWindowHandle>;
+
+impl Global for OpenDocuments {}
+
+#[derive(Debug)]
+pub struct DocumentRegistry<W> {
+    documents: HashMap<PathBuf, Vec<(W, bool)>>,
+    shared: HashMap<PathBuf, SharedContent>,
+}
+
+impl<W> Default for DocumentRegistry<W> {
+    fn default() -> Self {
+        Self { documents: HashMap::new(), shared: HashMap::new() }
+    }
+}
+
+/// 共享编辑时最后同步的内容；`version` 每次发布加一
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedContent {
+    pub version: u64,
+    pub content: Arc<str>,
+    hash: u64,
+}
+
+/// 同步当前标签时要做的事
+#[derive(Debug, Clone, PartialEq)]
+pub enum SharedSync {
+    /// 其它窗口发布了更新的内容，替换本窗口的缓冲区
+    Pull(SharedContent),
+    /// 本窗口有新的修改，已发布为这个版本
+    Published(u64),
+    Unchanged,
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<W: Copy + PartialEq> DocumentRegistry<W> {
+    /// 其它窗口中打开了 `path` 时返回其中一个，以及它是否有未保存的修改
+    pub fn open_elsewhere(&self, path: &Path, window: W) -> Option<(W, bool)> {
+        self.documents.get(path)?.iter().find(|(owner, _)| *owner != window).copied()
+    }
+
+    /// 记录 `window` 打开了 `path`
+    pub fn open(&mut self, path: &Path, window: W) {
+        let owners = self.documents.entry(path.to_path_buf()).or_default();
+        if !owners.iter().any(|(owner, _)| *owner == window) {
+            owners.push((window, false));
+        }
+    }
+
+    /// 让 `window` 的记录与它当前的标签一致，已有记录的修改状态保留
+    pub fn sync_window(&mut self, window: W, paths: &[PathBuf]) {
+        for (path, owners) in &mut self.documents {
+            if !paths.contains(path) {
+                owners.retain(|(owner, _)| *owner != window);
+            }
+        }
+        for path in paths {
+            self.open(path, window);
+        }
+        self.documents.retain(|_, owners| !owners.is_empty());
+        let documents = &self.documents;
+        self.shared.retain(|path, _| documents.get(path).is_some_and(|owners| owners.len() > 1));
+    }
+
+    pub fn set_dirty(&mut self, path: &Path, window: W, dirty: bool) {
+        if let Some(entry) = self.documents.get_mut(path).and_then(|owners| owners.iter_mut().find(|(o, _)| *o == window)) {
+            entry.1 = dirty;
+        }
+    }
+
+    /// 窗口关闭后去掉它的所有记录
+    pub fn close_window(&mut self, window: W) {
+        self.sync_window(window, &[]);
+    }
+
+    /// 共享编辑：以 `content` 作为 `path` 的最新内容
+    pub fn publish(&mut self, path: &Path, content: &str) -> u64 {
+        let version = self.shared.get(path).map_or(1, |shared| shared.version + 1);
+        let shared = SharedContent { version, content: Arc::from(content), hash: content_hash(content) };
+        self.shared.insert(path.to_path_buf(), shared);
+        version
+    }
+
+    /// 共享编辑：`seen` 为本窗口最后同步的版本，`buffer` 为本窗口当前的内容。
+    /// 其它窗口发布了更新的版本时取回它，否则在本窗口有修改时发布
+    pub fn sync_shared(&mut self, path: &Path, seen: u64, buffer: &str) -> SharedSync {
+        match self.shared.get(path) {
+            Some(shared) if shared.version > seen => SharedSync::Pull(shared.clone()),
+            Some(shared) if shared.hash == content_hash(buffer) => SharedSync::Unchanged,
+            _ => SharedSync::Published(self.publish(path, buffer)),
+        }
+    }
+}
+
+/// 把 `old` 改为 `new` 的一处编辑：去掉相同的开头和结尾，内容相同时为 None
+pub fn minimal_edit(old: &str, new: &str) -> Option<(Range<usize>, String)> {
+    if old == new {
+        return None;
+    }
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(old.len().min(new.len()), |((i, _), _)| i);
+    // 结尾不与开头重叠，且落在字符边界上
+    let max_suffix = (old.len() - prefix).min(new.len() - prefix);
+    let mut suffix = old.as_bytes()[old.len() - max_suffix..]
+        .iter()
+        .rev()
+        .zip(new.as_bytes()[new.len() - max_suffix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+    Some((prefix..old.len() - suffix, new[prefix..new.len() - suffix].to_string()))
+}
+
+/// 项目设置中是否允许多个窗口共享同一文件，默认不允许
+pub fn load_share_documents(project_root: &Path) -> bool {
+    let Ok(content) = std::fs::read_to_string(project_root.join(PROJECT_SETTINGS_FILE)) else {
+        return false;
+    };
+    let (share, warnings) = parse_share_documents(&content);
+    for warning in warnings {
+        warn!("{}", warning);
+    }
+    share
+}
+
+/// 解析设置文件中的 `window.shareDocuments`，同时返回面向用户的警告
+pub fn parse_share_documents(content: &str) -> (bool, Vec<String>) {
+    let value = serde_json::from_str::<Value>(content).ok();
+    match value.as_ref().and_then(|value| value.get("window")?.get("shareDocuments")) {
+        None => (false, Vec::new()),
+        Some(Value::Bool(share)) => (*share, Vec::new()),
+        Some(_) => (false, vec!["window.shareDocuments 应为 true 或 false".to_string()]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracks_documents_and_shares_content() {
+        let path = PathBuf::from("/p/源代码/启动窗口.t");
+        let other = PathBuf::from("/p/源代码/工具类.t");
+        let mut registry = DocumentRegistry::<u32>::default();
+        registry.sync_window(1, &[path.clone(), other.clone()]);
+        registry.set_dirty(&path, 1, true);
+        assert_eq!(registry.open_elsewhere(&path, 1), None);
+        assert_eq!(registry.open_elsewhere(&path, 2), Some((1, true)));
+
+        // 共享编辑：后打开的窗口先取回已有的内容，之后各自的修改互相同步
+        registry.open(&path, 2);
+        registry.publish(&path, "类 启动窗口\n");
+        assert!(matches!(registry.sync_shared(&path, 0, "类 启动窗口"), SharedSync::Pull(shared) if shared.version == 1));
+        assert_eq!(registry.sync_shared(&path, 1, "类 启动窗口\n"), SharedSync::Unchanged);
+        assert_eq!(registry.sync_shared(&path, 1, "类 启动窗口\n变量"), SharedSync::Published(2));
+        assert!(matches!(registry.sync_shared(&path, 1, "类 启动窗口\n"), SharedSync::Pull(shared) if &*shared.content == "类 启动窗口\n变量"));
+
+        registry.close_window(1);
+        assert_eq!(registry.open_elsewhere(&path, 2), None);
+        assert_eq!(registry.open_elsewhere(&other, 2), None);
+        registry.close_window(2);
+        assert!(registry.shared.is_empty());
+
+        assert_eq!(minimal_edit("变量 甲 : 整数", "变量 乙 : 整数"), Some((7..10, "乙".to_string())));
+        assert_eq!(minimal_edit("aaa", "aaaa"), Some((3..3, "a".to_string())));
+        assert_eq!(minimal_edit("结绳", "结绳"), None);
+        assert_eq!(parse_share_documents(r#"{ "window": { "shareDocuments": true } }"#), (true, Vec::new()));
+        assert!(!parse_share_documents(r#"{ "window": { "shareDocuments": "yes" } }"#).1.is_empty());
+    }
+}
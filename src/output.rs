@@ -0,0 +1,215 @@
+//! 输出面板的内容：按来源分频道保存的日志行。窗口程序没有控制台，日志经 [`OutputLogger`] 同时写到
+//! 控制台（按 `RUST_LOG` 过滤）和这里，本程序 info 及以上的日志按所在模块归入频道；
+//! [`log_channel`] 直接写到指定的频道
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+
+/// 每个频道保留的行数，更早的行被丢弃
+pub const CAPACITY: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputChannel {
+    App,
+    Git,
+    Lsp,
+    Plugins,
+    Build,
+}
+
+impl OutputChannel {
+    pub const ALL: [OutputChannel; 5] =
+        [OutputChannel::App, OutputChannel::Git, OutputChannel::Lsp, OutputChannel::Plugins, OutputChannel::Build];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OutputChannel::App => "应用",
+            OutputChannel::Git => "Git",
+            OutputChannel::Lsp => "语言服务",
+            OutputChannel::Plugins => "插件",
+            OutputChannel::Build => "编译",
+        }
+    }
+
+    /// [`log_channel`] 写入控制台时的日志目标
+    fn target(self) -> &'static str {
+        match self {
+            OutputChannel::App => "output::app",
+            OutputChannel::Git => "output::git",
+            OutputChannel::Lsp => "output::lsp",
+            OutputChannel::Plugins => "output::plugins",
+            OutputChannel::Build => "output::build",
+        }
+    }
+
+    /// 日志所在模块对应的频道；不是本程序的日志时为 None
+    fn for_module(target: &str) -> Option<Self> {
+        if target != "tiecode" && !target.starts_with("tiecode::") {
+            return None;
+        }
+        let channel = if target.starts_with("tiecode::lsp")
+            || target.starts_with("tiecode::plugin::lsp")
+            || target.starts_with("tiecode::editor::lsp")
+        {
+            OutputChannel::Lsp
+        } else if target.starts_with("tiecode::component::git") {
+            OutputChannel::Git
+        } else if target.starts_with("tiecode::plugin") {
+            OutputChannel::Plugins
+        } else {
+            OutputChannel::App
+        };
+        Some(channel)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug)]
+struct ChannelLog {
+    lines: VecDeque<String>,
+    /// 写入过的总行数，包括已丢弃和清空的
+    total: u64,
+}
+
+impl ChannelLog {
+    const fn new() -> Self {
+        Self { lines: VecDeque::new(), total: 0 }
+    }
+}
+
+#[derive(Debug)]
+pub struct OutputLog {
+    channels: [ChannelLog; 5],
+}
+
+impl Default for OutputLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutputLog {
+    pub const fn new() -> Self {
+        Self { channels: [ChannelLog::new(), ChannelLog::new(), ChannelLog::new(), ChannelLog::new(), ChannelLog::new()] }
+    }
+
+    pub fn push(&mut self, channel: OutputChannel, line: String) {
+        let log = &mut self.channels[channel.index()];
+        if log.lines.len() == CAPACITY {
+            log.lines.pop_front();
+        }
+        log.lines.push_back(line);
+        log.total += 1;
+    }
+
+    /// 已取到 `seen` 行之后新写入、仍保留着的行，以及目前写入的总行数
+    pub fn since(&self, channel: OutputChannel, seen: u64) -> (Vec<String>, u64) {
+        let log = &self.channels[channel.index()];
+        let first = log.total - log.lines.len() as u64;
+        let skip = seen.saturating_sub(first) as usize;
+        (log.lines.iter().skip(skip).cloned().collect(), log.total)
+    }
+
+    pub fn clear(&mut self, channel: OutputChannel) {
+        self.channels[channel.index()].lines.clear();
+    }
+}
+
+static OUTPUT: Mutex<OutputLog> = Mutex::new(OutputLog::new());
+
+fn with_output<T>(f: impl FnOnce(&mut OutputLog) -> T) -> T {
+    f(&mut OUTPUT.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// 写一行到输出面板的频道，同时按 info 级别写入日志
+pub fn log_channel(channel: OutputChannel, line: impl fmt::Display) {
+    let line = line.to_string();
+    log::info!(target: channel.target(), "{}", line);
+    with_output(|output| output.push(channel, line));
+}
+
+/// 见 [`OutputLog::since`]
+pub fn lines_since(channel: OutputChannel, seen: u64) -> (Vec<String>, u64) {
+    with_output(|output| output.since(channel, seen))
+}
+
+pub fn clear(channel: OutputChannel) {
+    with_output(|output| output.clear(channel));
+}
+
+/// 把本程序的日志记入输出面板，再交给 env_logger 写到控制台
+pub struct OutputLogger {
+    console: env_logger::Logger,
+}
+
+impl OutputLogger {
+    /// 代替 `env_logger::init`，之后 info 及以上的日志总会记入输出面板
+    pub fn init() {
+        let console = env_logger::Builder::from_default_env().build();
+        let max_level = console.filter().max(LevelFilter::Info);
+        if log::set_boxed_logger(Box::new(OutputLogger { console })).is_ok() {
+            log::set_max_level(max_level);
+        }
+    }
+}
+
+impl Log for OutputLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.console.enabled(metadata)
+            || (metadata.level() <= Level::Info && OutputChannel::for_module(metadata.target()).is_some())
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() <= Level::Info {
+            if let Some(channel) = OutputChannel::for_module(record.target()) {
+                let line = match record.level() {
+                    Level::Error | Level::Warn => format!("[{}] {}", record.level(), record.args()),
+                    _ => record.args().to_string(),
+                };
+                with_output(|output| output.push(channel, line));
+            }
+        }
+        self.console.log(record);
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channels_keep_recent_lines() {
+        assert_eq!(OutputChannel::for_module("tiecode::editor::lsp_integration"), Some(OutputChannel::Lsp));
+        assert_eq!(OutputChannel::for_module("tiecode::component::git_panel"), Some(OutputChannel::Git));
+        assert_eq!(OutputChannel::for_module("tiecode::plugin::manager"), Some(OutputChannel::Plugins));
+        assert_eq!(OutputChannel::for_module("tiecode"), Some(OutputChannel::App));
+        assert_eq!(OutputChannel::for_module("output::git"), None);
+        assert_eq!(OutputChannel::for_module("gpui::window"), None);
+
+        let mut output = OutputLog::new();
+        output.push(OutputChannel::Git, "a".to_string());
+        output.push(OutputChannel::Git, "b".to_string());
+        assert_eq!(output.since(OutputChannel::Git, 0), (vec!["a".to_string(), "b".to_string()], 2));
+        assert_eq!(output.since(OutputChannel::Git, 1), (vec!["b".to_string()], 2));
+        assert_eq!(output.since(OutputChannel::App, 0), (Vec::new(), 0));
+
+        // 超出容量时丢弃最早的行，清空后只取之后写入的
+        for index in 0..CAPACITY {
+            output.push(OutputChannel::Git, index.to_string());
+        }
+        let (lines, total) = output.since(OutputChannel::Git, 0);
+        assert_eq!((lines.len(), lines[0].as_str(), total), (CAPACITY, "0", CAPACITY as u64 + 2));
+        output.clear(OutputChannel::Git);
+        output.push(OutputChannel::Git, "c".to_string());
+        assert_eq!(output.since(OutputChannel::Git, total), (vec!["c".to_string()], total + 1));
+    }
+}
@@ -19,16 +19,12 @@ pub fn init() {
         error!("{}", error_msg);
         eprintln!("{}", error_msg);
 
-        // Write to crash.log (append mode)
-        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open("crash.log") {
-            let _ = writeln!(file, "----------------------------------------");
-            let _ = writeln!(file, "Timestamp: {:?}", std::time::SystemTime::now());
-            let _ = writeln!(file, "{}", error_msg);
-            // Capture backtrace if possible
-            let backtrace = std::backtrace::Backtrace::capture();
-            if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
-                 let _ = writeln!(file, "Backtrace:\n{}", backtrace);
-            }
+        // Capture backtrace if possible
+        let backtrace = std::backtrace::Backtrace::capture();
+        if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            write_crash_log(&format!("{}\nBacktrace:\n{}", error_msg, backtrace));
+        } else {
+            write_crash_log(&error_msg);
         }
 
         // Show Message Box on Windows
@@ -58,3 +54,12 @@ pub fn init() {
         }
     }));
 }
+
+/// Append an entry to crash.log; also used for crashes caught inside the compiler library
+pub fn write_crash_log(report: &str) {
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open("crash.log") {
+        let _ = writeln!(file, "----------------------------------------");
+        let _ = writeln!(file, "Timestamp: {:?}", std::time::SystemTime::now());
+        let _ = writeln!(file, "{}", report);
+    }
+}
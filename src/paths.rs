@@ -0,0 +1,164 @@
+//! 用户填写的路径的展开：`~`、`$VAR`、`${VAR}`，Windows 上另有 `%VAR%`
+
+use log::warn;
+use std::path::{Path, PathBuf};
+
+/// 展开路径开头的 `~` 和其中的环境变量；找不到的变量原样保留并记录警告
+pub fn expand_path(raw: &str) -> PathBuf {
+    let home = std::env::var(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+        .ok()
+        .filter(|home| !home.is_empty());
+    let (expanded, missing) = expand_with(raw, home.as_deref(), cfg!(windows), |name| {
+        std::env::var(name).ok()
+    });
+    for name in missing {
+        warn!("Unresolved variable `{name}` in path {raw:?}");
+    }
+    PathBuf::from(expanded)
+}
+
+/// 对已是 [`PathBuf`] 的路径展开；无法按 UTF-8 读取的路径原样返回
+pub fn expand_path_buf(path: &Path) -> PathBuf {
+    path.to_str().map(expand_path).unwrap_or_else(|| path.to_path_buf())
+}
+
+/// 本应用的配置目录：Windows 为 %APPDATA%\tiecode，macOS 为 ~/Library/Application Support/tiecode，
+/// 其余平台为 $XDG_CONFIG_HOME/tiecode 或 ~/.config/tiecode
+pub fn config_dir() -> Option<PathBuf> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let base = if cfg!(windows) {
+        var("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        var("XDG_CONFIG_HOME").or_else(|| var("HOME").map(|home| home.join(".config")))
+    };
+    base.map(|base| base.join("tiecode"))
+}
+
+/// 展开逻辑本身，环境由调用方提供。返回展开结果和未能展开的变量名
+fn expand_with(
+    raw: &str,
+    home: Option<&str>,
+    percent_vars: bool,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(raw.len());
+    let mut missing = Vec::new();
+    let mut rest = raw;
+
+    if let Some(after) = raw.strip_prefix('~') {
+        if after.is_empty() || after.starts_with(['/', '\\']) {
+            match home {
+                Some(home) => {
+                    out.push_str(home);
+                    rest = after;
+                }
+                None => missing.push("~".to_string()),
+            }
+        }
+    }
+
+    let mut substitute = |name: &str, original: &str, out: &mut String| match lookup(name) {
+        Some(value) => out.push_str(&value),
+        None => {
+            out.push_str(original);
+            missing.push(name.to_string());
+        }
+    };
+
+    let mut i = 0;
+    while i < rest.len() {
+        let tail = &rest[i..];
+        if let Some(after) = tail.strip_prefix("${") {
+            if let Some(end) = after.find('}') {
+                let name = &after[..end];
+                if is_var_name(name) {
+                    substitute(name, &tail[..end + 3], &mut out);
+                    i += end + 3;
+                    continue;
+                }
+            }
+        } else if let Some(after) = tail.strip_prefix('$') {
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            if is_var_name(&after[..len]) {
+                substitute(&after[..len], &tail[..len + 1], &mut out);
+                i += len + 1;
+                continue;
+            }
+        } else if let Some(after) = tail.strip_prefix('%').filter(|_| percent_vars) {
+            // Windows 变量名可以带括号，例如 %ProgramFiles(x86)%
+            if let Some(end) = after.find('%') {
+                let name = &after[..end];
+                if !name.is_empty() && !name.contains(char::is_whitespace) {
+                    substitute(name, &tail[..end + 2], &mut out);
+                    i += end + 2;
+                    continue;
+                }
+            }
+        }
+        let c = tail.chars().next().unwrap_or_default();
+        out.push(c);
+        i += c.len_utf8();
+    }
+
+    (out, missing)
+}
+
+fn is_var_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/me".to_string()),
+            "USERPROFILE" => Some(r"C:\Users\me".to_string()),
+            "ProgramFiles(x86)" => Some(r"C:\Program Files (x86)".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expands_tilde_and_dollar_variables() {
+        assert_eq!(expand_with("~/projects", Some("/home/me"), false, env).0, "/home/me/projects");
+        assert_eq!(expand_with("~", Some("/home/me"), false, env).0, "/home/me");
+        // 只有开头的 ~ 表示主目录
+        assert_eq!(expand_with("a/~b", Some("/home/me"), false, env).0, "a/~b");
+        assert_eq!(expand_with("$HOME/.config", None, false, env).0, "/home/me/.config");
+        assert_eq!(expand_with("${HOME}_x/y", None, false, env).0, "/home/me_x/y");
+    }
+
+    #[test]
+    fn test_expands_percent_variables_only_when_enabled() {
+        assert_eq!(
+            expand_with(r"%USERPROFILE%\code", None, true, env).0,
+            r"C:\Users\me\code"
+        );
+        assert_eq!(
+            expand_with(r"%ProgramFiles(x86)%\tool", None, true, env).0,
+            r"C:\Program Files (x86)\tool"
+        );
+        assert_eq!(expand_with(r"%USERPROFILE%\code", None, false, env).0, r"%USERPROFILE%\code");
+    }
+
+    #[test]
+    fn test_missing_variables_are_left_intact_and_reported() {
+        let (path, missing) = expand_with("$NOPE/${ALSO_NOPE}/%X%/~", None, true, env);
+        assert_eq!(path, "$NOPE/${ALSO_NOPE}/%X%/~");
+        assert_eq!(missing, vec!["NOPE", "ALSO_NOPE", "X"]);
+
+        let (path, missing) = expand_with("~/a", None, false, env);
+        assert_eq!(path, "~/a");
+        assert_eq!(missing, vec!["~"]);
+        // 不构成变量名的 $ 保持原样
+        assert_eq!(expand_with("cost$5/$", None, false, env), ("cost$5/$".to_string(), vec![]));
+    }
+}
@@ -0,0 +1,130 @@
+//! 动态库插件使用的 [`EditorHost`]：插件在后台线程调用，每次调用作为一个 [`EditorRequest`]
+//! 经通道交给主窗口，由界面线程上的任务取出后更新编辑器，再把结果送回等待中的插件线程
+
+use anyhow::{anyhow, bail, Result};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread::ThreadId;
+use tiecode_plugin_api::EditorHost;
+
+/// 请求的结果，界面线程处理完请求后发回
+pub struct Reply<T>(flume::Sender<Result<T, String>>);
+
+impl<T> Reply<T> {
+    pub fn send(self, result: Result<T, String>) {
+        // 插件线程不再等待时丢弃结果
+        let _ = self.0.send(result);
+    }
+}
+
+pub enum EditorRequest {
+    GetText(Reply<String>),
+    ReplaceRange(Range<usize>, String, Reply<()>),
+    CursorOffset(Reply<usize>),
+    Selections(Reply<Vec<(usize, usize)>>),
+    SetSelections(Vec<(usize, usize)>, Reply<()>),
+    ActiveFilePath(Reply<Option<PathBuf>>),
+}
+
+impl EditorRequest {
+    /// 无法处理请求时以 `message` 回复
+    pub fn fail(self, message: &str) {
+        let message = message.to_string();
+        match self {
+            EditorRequest::GetText(reply) => reply.send(Err(message)),
+            EditorRequest::ReplaceRange(_, _, reply) => reply.send(Err(message)),
+            EditorRequest::CursorOffset(reply) => reply.send(Err(message)),
+            EditorRequest::Selections(reply) => reply.send(Err(message)),
+            EditorRequest::SetSelections(_, reply) => reply.send(Err(message)),
+            EditorRequest::ActiveFilePath(reply) => reply.send(Err(message)),
+        }
+    }
+}
+
+pub struct ChannelEditorHost {
+    requests: flume::Sender<EditorRequest>,
+    /// 处理请求的线程；在这个线程上等待结果会卡住界面
+    ui_thread: ThreadId,
+}
+
+/// 在界面线程上调用：返回交给插件的编辑器接口，以及界面线程取出请求的一端
+pub fn channel() -> (Arc<ChannelEditorHost>, flume::Receiver<EditorRequest>) {
+    let (requests, receiver) = flume::unbounded();
+    (Arc::new(ChannelEditorHost { requests, ui_thread: std::thread::current().id() }), receiver)
+}
+
+impl ChannelEditorHost {
+    fn call<T>(&self, make: impl FnOnce(Reply<T>) -> EditorRequest) -> Result<T> {
+        if std::thread::current().id() == self.ui_thread {
+            bail!("不能在界面线程上调用编辑器接口");
+        }
+        let (sender, receiver) = flume::bounded(1);
+        self.requests.send(make(Reply(sender))).map_err(|_| anyhow!("编辑器已关闭"))?;
+        receiver.recv().map_err(|_| anyhow!("编辑器已关闭"))?.map_err(|err| anyhow!(err))
+    }
+}
+
+impl EditorHost for ChannelEditorHost {
+    fn get_text(&self) -> Result<String> {
+        self.call(EditorRequest::GetText)
+    }
+
+    fn replace_range(&self, range: Range<usize>, text: &str) -> Result<()> {
+        self.call(|reply| EditorRequest::ReplaceRange(range, text.to_string(), reply))
+    }
+
+    fn cursor_offset(&self) -> Result<usize> {
+        self.call(EditorRequest::CursorOffset)
+    }
+
+    fn selections(&self) -> Result<Vec<(usize, usize)>> {
+        self.call(EditorRequest::Selections)
+    }
+
+    fn set_selections(&self, selections: Vec<(usize, usize)>) -> Result<()> {
+        self.call(|reply| EditorRequest::SetSelections(selections, reply))
+    }
+
+    fn active_file_path(&self) -> Result<Option<PathBuf>> {
+        self.call(EditorRequest::ActiveFilePath)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forwards_calls_to_ui_thread() {
+        let (host, requests) = channel();
+        assert!(host.get_text().is_err());
+
+        let plugin = {
+            let host = host.clone();
+            std::thread::spawn(move || {
+                let text = host.get_text()?;
+                host.replace_range(0..text.len(), &text.to_uppercase())?;
+                host.cursor_offset()
+            })
+        };
+        let mut text = "abc".to_string();
+        for request in requests.iter().take(3) {
+            match request {
+                EditorRequest::GetText(reply) => reply.send(Ok(text.clone())),
+                EditorRequest::ReplaceRange(range, new_text, reply) => {
+                    text.replace_range(range, &new_text);
+                    reply.send(Ok(()));
+                }
+                request => request.fail("没有打开文本文件"),
+            }
+        }
+        assert_eq!(text, "ABC");
+        assert_eq!(plugin.join().unwrap().unwrap_err().to_string(), "没有打开文本文件");
+
+        drop(requests);
+        let host = host.clone();
+        let error = std::thread::spawn(move || host.selections().unwrap_err().to_string()).join().unwrap();
+        assert_eq!(error, "编辑器已关闭");
+    }
+}
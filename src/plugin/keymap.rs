@@ -0,0 +1,139 @@
+//! 命令对应的快捷键：来自程序内置的按键绑定和插件清单的 keybindings（启动时绑定到 gpui），
+//! 在命令面板中显示在命令旁边
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    /// 命令 id 到 gpui 格式的按键，例如 `ctrl-shift-p`、`ctrl-k ctrl-o`
+    keys: HashMap<String, String>,
+}
+
+impl Keymap {
+    /// 同一命令已有快捷键时保留先注册的，内置绑定先于插件注册
+    pub fn register(&mut self, command: impl Into<String>, key: impl Into<String>) {
+        self.keys.entry(command.into()).or_insert_with(|| key.into());
+    }
+
+    /// 停用插件时撤下它注册的快捷键；命令的快捷键已是别的时保持不变
+    pub fn remove(&mut self, command: &str, key: &str) {
+        if self.keys.get(command).is_some_and(|registered| registered == key) {
+            self.keys.remove(command);
+        }
+    }
+
+    /// 所有命令按当前平台格式化后的快捷键
+    pub fn labels(&self) -> HashMap<String, String> {
+        let mac = cfg!(target_os = "macos");
+        self.keys.iter().map(|(command, key)| (command.clone(), format_keystrokes(key, mac))).collect()
+    }
+}
+
+/// 插件清单中的按键写法（`ctrl+shift+g`、`Ctrl+K Ctrl+O`）转为 gpui 格式（`ctrl-shift-g`、`ctrl-k ctrl-o`）；
+/// 已是 gpui 格式的保持不变
+pub fn normalize_keystrokes(key: &str) -> String {
+    key.split_whitespace().map(normalize_keystroke).collect::<Vec<_>>().join(" ")
+}
+
+fn normalize_keystroke(stroke: &str) -> String {
+    let stroke = stroke.to_lowercase();
+    if !stroke.contains('+') || stroke == "+" {
+        return stroke;
+    }
+    // 末尾的 `+` 本身是按键，例如 `ctrl++`
+    let (modifiers, key) = match stroke.strip_suffix("++") {
+        Some(rest) => (rest, "+"),
+        None => stroke.rsplit_once('+').unwrap_or(("", &stroke)),
+    };
+    let mut parts: Vec<&str> = modifiers
+        .split('+')
+        .filter(|m| !m.is_empty())
+        .map(|modifier| match modifier {
+            "control" => "ctrl",
+            "option" => "alt",
+            "command" | "meta" => "cmd",
+            other => other,
+        })
+        .collect();
+    parts.push(key);
+    parts.join("-")
+}
+
+/// 插件清单中 `when` 对应的 gpui 按键上下文，None 表示在整个窗口中生效；不认识的值返回 Err
+pub fn binding_context(when: Option<&str>) -> Result<Option<&'static str>, String> {
+    match when.map(str::trim) {
+        None | Some("" | "global" | "always") => Ok(None),
+        Some("editor" | "editorFocus" | "editorTextFocus" | "CodeEditor") => Ok(Some("CodeEditor")),
+        Some(other) => Err(other.to_string()),
+    }
+}
+
+/// 显示用的快捷键：macOS 上用 ⌘⇧ 等符号，其它平台写成 Ctrl+Shift+P；多个按键之间用空格分开
+pub fn format_keystrokes(key: &str, mac: bool) -> String {
+    key.split_whitespace().map(|stroke| format_keystroke(stroke, mac)).collect::<Vec<_>>().join(" ")
+}
+
+fn format_keystroke(stroke: &str, mac: bool) -> String {
+    // 末尾的 `-` 本身是按键，例如 `ctrl--`
+    let (modifiers, key) = match stroke.strip_suffix("--") {
+        Some(rest) => (rest, "-"),
+        None => stroke.rsplit_once('-').unwrap_or(("", stroke)),
+    };
+    let mut parts: Vec<String> = modifiers
+        .split('-')
+        .filter(|m| !m.is_empty())
+        .map(|modifier| {
+            let label = match (modifier, mac) {
+                ("cmd" | "super" | "win", true) => "⌘",
+                ("ctrl", true) => "⌃",
+                ("shift", true) => "⇧",
+                ("alt", true) => "⌥",
+                ("cmd" | "super" | "win", false) => "Win",
+                ("ctrl", false) => "Ctrl",
+                ("shift", false) => "Shift",
+                ("alt", false) => "Alt",
+                (other, _) => other,
+            };
+            label.to_string()
+        })
+        .collect();
+    let mut chars = key.chars();
+    parts.push(match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    });
+    parts.join(if mac { "" } else { "+" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_keystrokes_per_platform() {
+        assert_eq!(format_keystrokes("ctrl-shift-p", false), "Ctrl+Shift+P");
+        assert_eq!(format_keystrokes("cmd-shift-p", true), "⌘⇧P");
+        assert_eq!(format_keystrokes("ctrl-k ctrl-o", false), "Ctrl+K Ctrl+O");
+        assert_eq!(format_keystrokes("alt-f12", false), "Alt+F12");
+        assert_eq!(format_keystrokes("ctrl--", false), "Ctrl+-");
+        assert_eq!(format_keystrokes("f6", true), "F6");
+
+        let mut keymap = Keymap::default();
+        keymap.register("core.undo", "ctrl-z");
+        keymap.register("core.undo", "ctrl-u");
+        assert_eq!(keymap.keys.get("core.undo").map(String::as_str), Some("ctrl-z"));
+        assert_eq!(keymap.labels().len(), 1);
+    }
+
+    #[test]
+    fn test_normalizes_plugin_keys() {
+        assert_eq!(normalize_keystrokes("ctrl+shift+g"), "ctrl-shift-g");
+        assert_eq!(normalize_keystrokes("Ctrl+K Ctrl+O"), "ctrl-k ctrl-o");
+        assert_eq!(normalize_keystrokes("Control+Option+F12"), "ctrl-alt-f12");
+        assert_eq!(normalize_keystrokes("ctrl++"), "ctrl-+");
+        assert_eq!(normalize_keystrokes("alt-f12"), "alt-f12");
+        assert_eq!(binding_context(Some("editor")), Ok(Some("CodeEditor")));
+        assert_eq!(binding_context(None), Ok(None));
+        assert_eq!(binding_context(Some("terminalFocus")), Err("terminalFocus".to_string()));
+    }
+}
@@ -1,33 +1,42 @@
 use anyhow::Result;
 use serde_json::Value;
 use std::sync::Arc;
-use log::info;
+use log::{info, warn};
+use crate::lsp::tiec::ffi_log;
+use crate::output::{log_channel, OutputChannel};
+use crate::lsp::tiec::settings::TiecSettings;
 use crate::lsp::tiec::wrapper::{TiecLoader, TiecIdeService};
 use crate::lsp::tiec::types::{
-    CompilerOptions, CompletionParams, CursorParams, Position, SearchPrefixes,
+    CompilerOptions, CompletionParams, LintResult, Position, SearchPrefixes,
+    SourceElementsResult, TextChange, WorkspaceElementsResult,
 };
 use url::Url;
 use std::path::PathBuf;
 
+/// 依次尝试加载 tiec 库的位置，相对于当前目录
+const LIBRARY_PATHS: [&str; 4] = ["tiec.dll", "bin/tiec.dll", "libs/tiec.dll", "../tiec.dll"];
+
+/// 找不到 tiec 库时的提示，列出查找过的位置
+pub fn missing_library_message() -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let searched: Vec<String> = LIBRARY_PATHS.iter().map(|path| cwd.join(path).display().to_string()).collect();
+    format!("找不到 tiec 库，已查找：{}", searched.join("、"))
+}
+
 pub struct LspPlugin {
     name: String,
     service: Option<Arc<TiecIdeService>>,
     loader: Arc<TiecLoader>,
     root_uri: Option<String>,
     dll_path: Option<PathBuf>,
+    /// 创建当前服务时生效的项目设置
+    applied_settings: Option<TiecSettings>,
 }
 
 impl LspPlugin {
     pub unsafe fn load_default() -> Result<Option<Self>> {
         // Try to load tiec.dll from common locations
-        let paths = [
-            "tiec.dll",
-            "bin/tiec.dll", 
-            "libs/tiec.dll",
-            "../tiec.dll",
-        ];
-        
-        for path in paths {
+        for path in LIBRARY_PATHS {
             if let Ok(loader) = TiecLoader::new(path) {
                 let dll_path = std::fs::canonicalize(path).ok();
                 return Ok(Some(Self {
@@ -36,6 +45,7 @@ impl LspPlugin {
                     loader: Arc::new(loader),
                     root_uri: None,
                     dll_path,
+                    applied_settings: None,
                 }));
             }
         }
@@ -51,6 +61,11 @@ impl LspPlugin {
         &self.name
     }
 
+    /// 已初始化的 IDE 服务，可以交给后台线程调用
+    pub fn service(&self) -> Option<Arc<TiecIdeService>> {
+        self.service.clone()
+    }
+
     fn find_sdk_path(&self) -> Option<String> {
         let candidates = [
             self.dll_path.as_ref().and_then(|p| p.parent()).map(|p| p.join("sdk")),
@@ -71,6 +86,50 @@ impl LspPlugin {
 
     pub fn initialize(&mut self, root_uri: &str, doc_uri: &str, content: &str) -> Result<()> {
         println!("DEBUG: initialize called for root: {}, doc: {}", root_uri, doc_uri);
+        let (service, created) = self.ensure_service(root_uri)?;
+
+        // Scan and compile project files
+        if let Some(path) = created.then(|| self.root_path()).flatten() {
+            let mut files = Vec::new();
+            info!("Scanning project files in: {}", path);
+            scan_files(std::path::Path::new(&path), &mut files);
+            if !files.is_empty() {
+                println!("DEBUG: Compiling {} files", files.len());
+                if let Err(e) = service.compile_files(&files) {
+                    log_channel(OutputChannel::Lsp, format!("Compilation failed: {:?}", e));
+                } else {
+                    println!("DEBUG: Compilation success");
+                }
+            } else {
+                println!("DEBUG: No .t files found in project root");
+            }
+        }
+
+        // Register the source file
+        // Note: create_source might fail if it already exists, so we might want to try delete first or ignore error
+        let _ = service.delete_source(doc_uri); // Ensure clean state
+        println!("DEBUG: Calling create_source for {}", doc_uri);
+        if let Err(e) = service.create_source(doc_uri, content) {
+            println!("DEBUG: create_source failed: {:?}", e);
+            return Err(e);
+        }
+
+        // Trigger initial compilation/analysis
+        // tc_ide_service_compile_files could be used here if we had file path
+        // But create_source should be enough for single file analysis
+        Ok(())
+    }
+
+    fn root_path(&self) -> Option<String> {
+        self.root_uri
+            .as_deref()
+            .and_then(|uri| Url::parse(uri).ok())
+            .and_then(|u| u.to_file_path().ok())
+            .map(|p| p.to_string_lossy().to_string())
+    }
+
+    /// 项目 `root_uri` 的 IDE 服务，还没有或项目变了时重新创建；同时返回是否新建
+    fn ensure_service(&mut self, root_uri: &str) -> Result<(Arc<TiecIdeService>, bool)> {
         // Check if root has changed
         let root_changed = self.root_uri.as_deref() != Some(root_uri);
         
@@ -94,9 +153,9 @@ impl LspPlugin {
             
             let sdk_path = self.find_sdk_path();
             if sdk_path.is_none() {
-                 println!("Warning: TieCode SDK not found. Compiler may fail.");
+                 log_channel(OutputChannel::Lsp, "Warning: TieCode SDK not found. Compiler may fail.");
             } else {
-                 println!("Using SDK path: {:?}", sdk_path);
+                 log_channel(OutputChannel::Lsp, format!("Using SDK path: {:?}", sdk_path));
             }
 
             let mut options = CompilerOptions {
@@ -108,52 +167,78 @@ impl LspPlugin {
                 ..Default::default()
             };
 
+            self.applied_settings = None;
             if let Some(path) = &root_path {
                  options.search_prefixes = Some(SearchPrefixes {
                      source: Some(vec![path.clone()]),
                      ..Default::default()
                  });
+                 self.applied_settings = Self::apply_project_settings(&mut options, std::path::Path::new(path));
             }
             
             let context = self.loader.create_context(&serde_json::to_value(&options)?)?;
             let service = context.create_ide_service()?;
             let service = Arc::new(service);
             self.service = Some(service.clone());
+            return Ok((service, true));
+        }
 
-            // Scan and compile project files
-            if let Some(path) = root_path {
-                let mut files = Vec::new();
-                info!("Scanning project files in: {}", path);
-                scan_files(std::path::Path::new(&path), &mut files);
-                if !files.is_empty() {
-                    println!("DEBUG: Compiling {} files", files.len());
-                    if let Err(e) = service.compile_files(&files) {
-                        println!("DEBUG: Compilation failed: {:?}", e);
-                    } else {
-                        println!("DEBUG: Compilation success");
-                    }
-                } else {
-                    println!("DEBUG: No .t files found in project root");
+        let service = self.service.clone().ok_or_else(|| anyhow::anyhow!("tiec service is not initialized"))?;
+        Ok((service, false))
+    }
+
+    /// 编译 `root` 下的全部结绳文件并对整个项目查错，返回编译的文件和诊断，进度写到输出面板的编译频道；
+    /// 已有 IDE 服务时沿用它，否则以 `root` 为项目创建
+    pub fn compile_project(&mut self, root: &std::path::Path) -> Result<(Vec<PathBuf>, LintResult)> {
+        let service = match self.service.clone() {
+            Some(service) => service,
+            None => self.ensure_service(&crate::editor::lsp_integration::default_doc_uri(root))?.0,
+        };
+        let mut files = Vec::new();
+        scan_files(root, &mut files);
+        log_channel(OutputChannel::Build, format!("正在编译 {} 个文件…", files.len()));
+        let started = std::time::Instant::now();
+        service.compile_files(&files)?;
+        log_channel(OutputChannel::Build, format!("编译完成，用时 {:.1} 秒，正在检查整个项目…", started.elapsed().as_secs_f64()));
+        let lint = service.lint_all()?;
+        Ok((files.into_iter().map(PathBuf::from).collect(), lint))
+    }
+
+    /// 项目设置中的 tiec 选项覆盖默认值，返回生效的设置；设置有误时整体忽略并记录原因
+    fn apply_project_settings(options: &mut CompilerOptions, root: &std::path::Path) -> Option<TiecSettings> {
+        match TiecSettings::load(root) {
+            Ok(Some(settings)) => {
+                ffi_log::set_enabled(settings.log_ffi_calls);
+                let errors = settings.validate(root);
+                if errors.is_empty() {
+                    settings.apply_to(options, root);
+                    return Some(settings);
                 }
+                warn!("Ignoring invalid tiec project settings: {}", errors.join("; "));
             }
-        }
-
-        if let Some(service) = &self.service {
-            // Register the source file
-            // Note: create_source might fail if it already exists, so we might want to try delete first or ignore error
-            let _ = service.delete_source(doc_uri); // Ensure clean state
-            println!("DEBUG: Calling create_source for {}", doc_uri);
-            if let Err(e) = service.create_source(doc_uri, content) {
-                println!("DEBUG: create_source failed: {:?}", e);
-                return Err(e);
+            Ok(None) => ffi_log::set_enabled(false),
+            Err(err) => {
+                ffi_log::set_enabled(false);
+                warn!("{err:#}");
             }
-            
-            // Trigger initial compilation/analysis
-            // tc_ide_service_compile_files could be used here if we had file path
-            // But create_source should be enough for single file analysis
         }
+        None
+    }
 
-        Ok(())
+    /// 当前服务创建之后项目设置是否改过，例如保存设置时没有确认重启
+    pub fn settings_changed(&self) -> bool {
+        let Some(root) = self.service.as_ref().and(self.root_path()) else {
+            return false;
+        };
+        let root = std::path::Path::new(&root);
+        let current = TiecSettings::load(root).ok().flatten().filter(|settings| settings.validate(root).is_empty());
+        current != self.applied_settings
+    }
+
+    /// 丢弃当前的 IDE 服务，下次 initialize 时按最新的项目设置重新创建
+    pub fn reset(&mut self) {
+        self.service = None;
+        self.root_uri = None;
     }
 
     pub fn did_change(&mut self, doc_uri: &str, _version: i32, content: &str) -> Result<()> {
@@ -163,6 +248,16 @@ impl LspPlugin {
         Ok(())
     }
 
+    /// 按编辑的先后逐条增量修改文档
+    pub fn did_change_incremental(&mut self, doc_uri: &str, changes: &[TextChange]) -> Result<()> {
+        if let Some(service) = &self.service {
+            for change in changes {
+                service.edit_source_incremental(doc_uri, change)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn did_create_file(&mut self, doc_uri: &str, initial_text: &str) -> Result<()> {
         if let Some(service) = &self.service {
             service.create_source(doc_uri, initial_text)?;
@@ -218,18 +313,28 @@ impl LspPlugin {
             }))
     }
 
-    pub fn hover(&mut self, doc_uri: &str, line: usize, character: usize, _index: usize) -> Result<Value> {
+    /// 换行后下一行的缩进层级增量；服务尚未初始化时返回 None
+    pub fn indent_advance(&self, line_text: &str, column: usize) -> Result<Option<i32>> {
         if let Some(service) = &self.service {
-            let params = CursorParams {
-                uri: doc_uri.to_string(),
-                position: Position { line, column: character },
-                line_text: None,
-            };
-            
-            let result = service.hover(&params)?;
-            return Ok(serde_json::to_value(result)?);
+            return Ok(Some(service.indent_advance(line_text, column)?));
+        }
+        Ok(None)
+    }
+
+    /// 文件的符号结构；服务尚未初始化时返回 None
+    pub fn source_elements(&mut self, doc_uri: &str) -> Result<Option<SourceElementsResult>> {
+        if let Some(service) = &self.service {
+            return Ok(Some(service.source_elements(doc_uri)?));
         }
-        Ok(serde_json::Value::Null)
+        Ok(None)
+    }
+
+    /// 整个项目中名称包含关键词的符号；服务尚未初始化时返回 None
+    pub fn workspace_elements(&mut self, keyword: &str) -> Result<Option<WorkspaceElementsResult>> {
+        if let Some(service) = &self.service {
+            return Ok(Some(service.workspace_elements(keyword)?));
+        }
+        Ok(None)
     }
 }
 
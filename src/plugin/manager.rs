@@ -1,6 +1,33 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
-use tiecode_plugin_api::{PluginManifest, CommandContribution};
+use anyhow::{anyhow, bail, Context, Result};
+use libloading::Library;
+use log::{info, warn};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use tiecode_plugin_api::{
+    CommandContribution, CommandHandler, EditorHost, EnterRuleContribution, KeybindingContribution, Plugin,
+    PluginDeclaration, PluginEvent, PluginEventKind, PluginManifest, StatusAlignment, API_VERSION, DECLARATION_SYMBOL,
+    FILE_TREE_CONTEXT_MENU,
+};
+use super::keymap::{normalize_keystrokes, Keymap};
+use super::menus::MenuItem;
+use super::status_items::StatusItems;
+use crate::editor::grammar::PluginGrammar;
+
+/// 可切换的文件图标主题，对应命令 `view.icon_theme.<id>`
+#[derive(Clone)]
+pub struct IconThemeEntry {
+    pub id: String,
+    pub label: String,
+    pub path: PathBuf,
+}
+
+pub const ICON_THEME_COMMAND_PREFIX: &str = "view.icon_theme.";
+/// 停用插件的命令 `plugin.disable.<插件 id>`
+pub const DISABLE_PLUGIN_COMMAND_PREFIX: &str = "plugin.disable.";
 
 #[derive(Clone)]
 pub struct ToolPageContribution {
@@ -24,6 +51,10 @@ impl CommandRegistry {
         self.commands.insert(command.command.clone(), command);
     }
 
+    pub fn unregister(&mut self, id: &str) {
+        self.commands.remove(id);
+    }
+
     pub fn get(&self, id: &str) -> Option<&CommandContribution> {
         self.commands.get(id)
     }
@@ -33,11 +64,216 @@ impl CommandRegistry {
     }
 }
 
+/// 插件的运行状态：读取清单后为 Discovered，收到清单中声明的激活事件后为 Activated
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginState {
+    Discovered,
+    Activated,
+    /// 激活或停止时出错，值为原因；之后不再激活
+    Failed(String),
+    Disabled,
+}
+
+/// 可能激活插件的事件，对应清单 `activation_events` 中的写法
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActivationEvent {
+    /// 插件发现完成；匹配 `onStartup`、`onStartupFinished` 和 `*`
+    Startup,
+    /// 执行了命令；匹配 `onCommand:<命令 id>`
+    Command(String),
+    /// 打开了文件，值为不含点的扩展名；匹配 `onFileOpen` 和 `onLanguage:<扩展名>`
+    FileOpen(String),
+}
+
+impl ActivationEvent {
+    pub fn matches(&self, declared: &str) -> bool {
+        match self {
+            ActivationEvent::Startup => matches!(declared, "*" | "onStartup" | "onStartupFinished"),
+            ActivationEvent::Command(id) => declared.strip_prefix("onCommand:") == Some(id.as_str()),
+            ActivationEvent::FileOpen(extension) => {
+                declared == "onFileOpen"
+                    || declared.strip_prefix("onLanguage:").is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+            }
+        }
+    }
+}
+
+/// 已加载的插件
+struct LoadedPlugin {
+    manifest: PluginManifest,
+    dir: PathBuf,
+    state: PluginState,
+    /// 清单提供 `entry` 时激活后加载的动态库；执行中的命令也持有它，结束后才卸载
+    native: Option<Arc<NativePlugin>>,
+    /// 动态库插件订阅的事件，停用时丢弃
+    events: Option<EventSubscription>,
+    /// 注册时被跳过的项
+    problems: Vec<String>,
+}
+
+/// 插件页中的一项
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginSummary {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    /// 清单中提供的命令数
+    pub commands: usize,
+    pub state: PluginState,
+    pub problems: Vec<String>,
+}
+
+/// 从动态库创建的插件对象；字段按声明顺序释放，先释放插件再卸载库
+struct NativePlugin {
+    plugin: Box<dyn Plugin>,
+    /// 测试中直接创建的插件没有动态库
+    _library: Option<Library>,
+}
+
+impl NativePlugin {
+    /// 加载 `path` 处的动态库，检查接口版本，创建插件并调用 `activate`
+    fn load(path: &Path) -> Result<Self> {
+        let native = unsafe {
+            let library = Library::new(path).with_context(|| format!("无法加载 {:?}", path))?;
+            let declaration = library
+                .get::<*const PluginDeclaration>(DECLARATION_SYMBOL)
+                .with_context(|| format!("{:?} 没有导出插件入口", path))?;
+            let declaration = &**declaration;
+            if declaration.api_version != API_VERSION {
+                bail!("{:?} 使用接口版本 {}，编辑器支持的是 {}", path, declaration.api_version, API_VERSION);
+            }
+            let raw = (declaration.create)();
+            if raw.is_null() {
+                bail!("{:?} 没有创建插件", path);
+            }
+            NativePlugin { plugin: *Box::from_raw(raw), _library: Some(library) }
+        };
+        guarded("activate", || native.plugin.activate())?;
+        Ok(native)
+    }
+}
+
+/// 插件订阅的事件：每个插件一个投递线程，事件按发布顺序送达，处理得慢只会拖慢这个插件自己的队列。
+/// 丢弃时取消订阅，队列中还没送达的事件不再投递
+struct EventSubscription {
+    kinds: Vec<PluginEventKind>,
+    sender: mpsc::Sender<PluginEvent>,
+    active: Arc<AtomicBool>,
+}
+
+impl EventSubscription {
+    /// 插件没有订阅事件时返回 None
+    fn start(plugin_id: &str, native: Arc<NativePlugin>) -> Option<Self> {
+        let kinds = native.plugin.subscriptions();
+        if kinds.is_empty() {
+            return None;
+        }
+        let (sender, receiver) = mpsc::channel::<PluginEvent>();
+        let active = Arc::new(AtomicBool::new(true));
+        let worker_active = active.clone();
+        let id = plugin_id.to_string();
+        // 投递线程持有插件，正在处理的事件返回之前不会卸载动态库
+        let spawned = std::thread::Builder::new().name(format!("plugin-events-{}", id)).spawn(move || {
+            for event in receiver {
+                if !worker_active.load(Ordering::Acquire) {
+                    break;
+                }
+                if let Err(err) = guarded("on_event", || native.plugin.on_event(&event)) {
+                    warn!("Plugin {} failed to handle {:?}: {:#}", id, event.kind(), err);
+                }
+            }
+        });
+        if let Err(err) = spawned {
+            warn!("Failed to start event delivery for plugin {}: {}", plugin_id, err);
+            return None;
+        }
+        Some(Self { kinds, sender, active })
+    }
+
+    fn send(&self, event: &PluginEvent) {
+        if self.kinds.contains(&event.kind()) {
+            let _ = self.sender.send(event.clone());
+        }
+    }
+}
+
+impl Drop for EventSubscription {
+    fn drop(&mut self) {
+        self.active.store(false, Ordering::Release);
+    }
+}
+
+/// 调用插件代码，把 panic 转为错误，避免插件拖垮编辑器；动态库内的 panic 由 `declare_plugin!` 生成的入口捕获
+fn guarded<T>(call: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|_| Err(anyhow!("{} panicked", call)))
+}
+
+/// 执行插件命令的结果
+#[derive(Debug, PartialEq)]
+pub enum PluginInvocation {
+    /// 不是插件提供的命令
+    Unknown,
+    /// 交给编辑器执行的内置命令
+    Builtin(String),
+    /// 已启动外部程序，值为进程号
+    Spawned(u32),
+    /// 由动态库插件执行的命令
+    Native(NativeCommand),
+}
+
+/// 交给动态库插件执行的命令。插件会等待编辑器接口的结果，须在后台线程调用 [`NativeCommand::run`]
+#[derive(Clone)]
+pub struct NativeCommand {
+    pub command: String,
+    pub argument: Option<String>,
+    plugin: Arc<NativePlugin>,
+}
+
+impl NativeCommand {
+    pub fn run(&self, editor: Arc<dyn EditorHost>) -> Result<()> {
+        guarded("execute_command", || {
+            self.plugin.plugin.execute_command(&self.command, self.argument.as_deref(), editor)
+        })
+    }
+}
+
+impl std::fmt::Debug for NativeCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("NativeCommand").field(&self.command).field(&self.argument).finish()
+    }
+}
+
+impl PartialEq for NativeCommand {
+    fn eq(&self, other: &Self) -> bool {
+        self.command == other.command && self.argument == other.argument && Arc::ptr_eq(&self.plugin, &other.plugin)
+    }
+}
+
 pub struct PluginManager {
-    plugins: HashMap<String, PluginManifest>,
+    plugins: HashMap<String, LoadedPlugin>,
     plugin_dirs: Vec<PathBuf>,
+    /// 插件命令 id 到提供它的插件 id
+    command_owners: HashMap<String, String>,
+    handlers: HashMap<String, CommandHandler>,
     pub command_registry: CommandRegistry,
+    /// 命令的快捷键，在命令面板中显示
+    pub keymap: Keymap,
     pub tool_pages: Vec<ToolPageContribution>,
+    pub icon_themes: Vec<IconThemeEntry>,
+    /// 插件提供的回车补全块结构规则，及提供它的插件 id
+    enter_rules: Vec<(String, EnterRuleContribution)>,
+    /// 插件清单中的快捷键及提供它的插件 id，启动时绑定到 gpui
+    keybindings: Vec<(String, KeybindingContribution)>,
+    /// 内置功能和插件的状态栏项
+    status_items: StatusItems,
+    /// 插件提供的语法，路径已换成完整路径
+    grammars: Vec<PluginGrammar>,
+    /// 插件提供的菜单项，按注册顺序
+    menu_items: Vec<MenuItem>,
+    /// 停用的插件 id，发现时不注册它们提供的内容
+    disabled: BTreeSet<String>,
+    /// 无法读取的插件清单
+    load_errors: Vec<String>,
 }
 
 impl PluginManager {
@@ -45,49 +281,429 @@ impl PluginManager {
         Self {
             plugins: HashMap::new(),
             plugin_dirs: Vec::new(),
+            command_owners: HashMap::new(),
+            handlers: HashMap::new(),
             command_registry: CommandRegistry::new(),
+            keymap: Keymap::default(),
             tool_pages: Vec::new(),
+            icon_themes: Vec::new(),
+            enter_rules: Vec::new(),
+            keybindings: Vec::new(),
+            status_items: StatusItems::default(),
+            grammars: Vec::new(),
+            menu_items: Vec::new(),
+            disabled: BTreeSet::new(),
+            load_errors: Vec::new(),
         }
     }
 
+    /// 目录可以写成 `~/plugins` 或 `$VAR/plugins` 等形式
     pub fn add_plugin_dir(&mut self, path: PathBuf) {
-        self.plugin_dirs.push(path);
+        self.plugin_dirs.push(crate::paths::expand_path_buf(&path));
     }
 
+    /// 读取插件目录中的清单并注册新的插件；再次调用时已加载的插件保持不变，目录中已删除的插件被移除
     pub fn discover_plugins(&mut self) {
-        for dir in &self.plugin_dirs {
-            if let Ok(entries) = std::fs::read_dir(dir) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        let manifest_path = path.join("package.json");
-                        if manifest_path.exists() {
-                            match crate::plugin::manifest::PluginManifestLoader::load(&manifest_path) {
-                                Ok(manifest) => {
-                                    println!("Found plugin: {} ({})", manifest.id, manifest.version);
-                                    
-                                    // Auto-register commands from manifest
-                                    for cmd in &manifest.contributes.commands {
-                                        self.command_registry.register(cmd.clone());
-                                    }
-                                    
-                                    self.plugins.insert(manifest.id.clone(), manifest);
-                                }
-                                Err(e) => {
-                                    eprintln!("Failed to load plugin manifest at {:?}: {}", manifest_path, e);
-                                }
-                            }
+        self.load_errors.clear();
+        let mut found = HashSet::new();
+        for dir in self.plugin_dirs.clone() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let manifest_path = path.join("package.json");
+                if !path.is_dir() || !manifest_path.exists() {
+                    continue;
+                }
+                match crate::plugin::manifest::PluginManifestLoader::load(&manifest_path) {
+                    Ok(manifest) => {
+                        found.insert(manifest.id.clone());
+                        if self.plugins.get(&manifest.id).is_some_and(|plugin| plugin.dir == path) {
+                            continue;
+                        }
+                        println!("Found plugin: {} ({})", manifest.id, manifest.version);
+                        for problem in self.register_plugin(manifest, path) {
+                            warn!("{}", problem);
                         }
                     }
+                    Err(e) => {
+                        eprintln!("Failed to load plugin manifest at {:?}: {}", manifest_path, e);
+                        self.load_errors.push(format!("{}：{:#}", manifest_path.display(), e));
+                    }
                 }
             }
         }
+        let removed: Vec<String> = self.plugins.keys().filter(|id| !found.contains(*id)).cloned().collect();
+        for id in removed {
+            info!("Plugin {} was removed", id);
+            self.deactivate_plugin(&id);
+            self.unregister_contributions(&id);
+            self.plugins.remove(&id);
+        }
+    }
+
+    /// 记录插件，插件没有停用时注册它提供的内容，返回被跳过的项
+    pub fn register_plugin(&mut self, manifest: PluginManifest, dir: PathBuf) -> Vec<String> {
+        if self.plugins.contains_key(&manifest.id) {
+            return vec![format!("Plugin {} is already loaded, skipping {:?}", manifest.id, dir)];
+        }
+        let id = manifest.id.clone();
+        let disabled = self.disabled.contains(&id);
+        let state = if disabled { PluginState::Disabled } else { PluginState::Discovered };
+        let plugin = LoadedPlugin { manifest, dir, state, native: None, events: None, problems: Vec::new() };
+        self.plugins.insert(id.clone(), plugin);
+        if !disabled {
+            self.register_contributions(&id);
+        }
+        self.plugins[&id].problems.clone()
+    }
+
+    /// 注册插件提供的命令、快捷键、图标主题、命令处理方式和回车规则，跳过的项记在插件上。
+    /// 命令 id 与内置命令或先加载的插件重复时保留先注册的
+    fn register_contributions(&mut self, plugin_id: &str) {
+        let Some(plugin) = self.plugins.get(plugin_id) else {
+            return;
+        };
+        let (manifest, dir) = (plugin.manifest.clone(), plugin.dir.clone());
+        let mut problems = Vec::new();
+        for cmd in &manifest.contributes.commands {
+            if self.command_registry.get(&cmd.command).is_some() {
+                let owner = self.command_owners.get(&cmd.command).map(String::as_str).unwrap_or("built-in");
+                problems.push(format!(
+                    "Plugin {} contributes command {} already provided by {}",
+                    manifest.id, cmd.command, owner
+                ));
+                continue;
+            }
+            let mut cmd = cmd.clone();
+            // 图标相对插件目录，注册时换成完整路径
+            cmd.icon = cmd.icon.map(|icon| dir.join(icon).to_string_lossy().to_string());
+            self.command_owners.insert(cmd.command.clone(), manifest.id.clone());
+            self.command_registry.register(cmd);
+        }
+        for handler in &manifest.contributes.handlers {
+            if self.command_owners.get(&handler.command) != Some(&manifest.id) {
+                problems.push(format!(
+                    "Plugin {} registers a handler for {}, which it does not contribute",
+                    manifest.id, handler.command
+                ));
+            } else if self.handlers.insert(handler.command.clone(), handler.handler.clone()).is_some() {
+                problems.push(format!("Plugin {} registers {} more than once", manifest.id, handler.command));
+            }
+        }
+        self.enter_rules.extend(manifest.contributes.enter_rules.iter().map(|rule| (manifest.id.clone(), rule.clone())));
+        for binding in &manifest.contributes.keybindings {
+            self.keymap.register(binding.command.clone(), normalize_keystrokes(&binding.key));
+        }
+        self.keybindings
+            .extend(manifest.contributes.keybindings.iter().map(|binding| (manifest.id.clone(), binding.clone())));
+        self.grammars.extend(manifest.contributes.grammars.iter().map(|grammar| PluginGrammar {
+            plugin_id: manifest.id.clone(),
+            path: dir.join(&grammar.path),
+            extensions: grammar.extensions.clone(),
+        }));
+        for (menu, items) in &manifest.contributes.menus {
+            if menu != FILE_TREE_CONTEXT_MENU {
+                problems.push(format!("Plugin {} contributes to unknown menu {}", manifest.id, menu));
+                continue;
+            }
+            self.menu_items.extend(items.iter().map(|item| MenuItem {
+                plugin_id: manifest.id.clone(),
+                menu: menu.clone(),
+                command: item.command.clone(),
+                title: item.title.clone(),
+                when: item.when.clone(),
+            }));
+        }
+        for item in &manifest.contributes.status_items {
+            self.status_items.register(&item.id, item.alignment, item.priority);
+            self.status_items.set_text(&item.id, item.text.clone(), item.tooltip.clone());
+            self.status_items.set_command(&item.id, item.command.clone());
+        }
+        for theme in &manifest.contributes.icon_themes {
+            Self::add_icon_theme(
+                &mut self.icon_themes,
+                &mut self.command_registry,
+                IconThemeEntry {
+                    id: theme.id.clone(),
+                    label: theme.label.clone(),
+                    path: dir.join(&theme.path),
+                },
+            );
+        }
+
+        self.command_registry.register(CommandContribution {
+            command: format!("{}{}", DISABLE_PLUGIN_COMMAND_PREFIX, manifest.id),
+            title: format!("Disable Plugin: {}", manifest.name),
+            category: Some("Plugins".to_string()),
+            icon: None,
+        });
+        if let Some(plugin) = self.plugins.get_mut(plugin_id) {
+            plugin.problems = problems;
+        }
     }
 
-    pub fn activate_plugin(&self, plugin_id: &str) {
+    /// 撤下插件提供的内容，之后它的命令和快捷键不再可用
+    fn unregister_contributions(&mut self, plugin_id: &str) {
+        let owned: Vec<String> = self
+            .command_owners
+            .iter()
+            .filter(|(_, owner)| owner.as_str() == plugin_id)
+            .map(|(command, _)| command.clone())
+            .collect();
+        for command in owned {
+            self.command_registry.unregister(&command);
+            self.command_owners.remove(&command);
+            self.handlers.remove(&command);
+        }
+        self.command_registry.unregister(&format!("{}{}", DISABLE_PLUGIN_COMMAND_PREFIX, plugin_id));
+        for (_, binding) in self.keybindings.iter().filter(|(owner, _)| owner == plugin_id) {
+            self.keymap.remove(&binding.command, &normalize_keystrokes(&binding.key));
+        }
+        self.keybindings.retain(|(owner, _)| owner != plugin_id);
+        self.enter_rules.retain(|(owner, _)| owner != plugin_id);
+        self.grammars.retain(|grammar| grammar.plugin_id != plugin_id);
+        self.menu_items.retain(|item| item.plugin_id != plugin_id);
         if let Some(plugin) = self.plugins.get(plugin_id) {
-            println!("Activating plugin: {}", plugin.name);
-            // In a real implementation, this would start the plugin host/WASM module
+            for item in &plugin.manifest.contributes.status_items {
+                self.status_items.remove(&item.id);
+            }
+            for theme in &plugin.manifest.contributes.icon_themes {
+                self.command_registry.unregister(&format!("{}{}", ICON_THEME_COMMAND_PREFIX, theme.id));
+                self.icon_themes.retain(|entry| entry.id != theme.id);
+            }
+        }
+    }
+
+    /// 在状态栏登记一项，之后用 [`set_status_item_text`](Self::set_status_item_text) 显示文字；
+    /// 同一侧优先级高的靠左
+    pub fn register_status_item(&mut self, id: &str, alignment: StatusAlignment, priority: i32) {
+        self.status_items.register(id, alignment, priority);
+    }
+
+    /// 更新状态栏项的文字和提示，文字为空时隐藏；返回显示的内容是否有变化
+    pub fn set_status_item_text(&mut self, id: &str, text: impl Into<String>, tooltip: Option<String>) -> bool {
+        self.status_items.set_text(id, text.into(), tooltip)
+    }
+
+    /// 点击状态栏项时执行的命令
+    pub fn set_status_item_command(&mut self, id: &str, command: Option<String>) {
+        self.status_items.set_command(id, command);
+    }
+
+    pub fn status_items(&self) -> &StatusItems {
+        &self.status_items
+    }
+
+    /// 已启用的插件提供的回车规则
+    pub fn enter_rules(&self) -> Vec<EnterRuleContribution> {
+        self.enter_rules.iter().map(|(_, rule)| rule.clone()).collect()
+    }
+
+    /// 已启用的插件提供的语法
+    pub fn grammars(&self) -> &[PluginGrammar] {
+        &self.grammars
+    }
+
+    /// 已启用的插件在 `menu` 中提供的、对 `path` 显示的菜单项
+    pub fn menu_items(&self, menu: &str, path: &Path, is_dir: bool) -> Vec<MenuItem> {
+        self.menu_items
+            .iter()
+            .filter(|item| item.menu == menu && item.matches(path, is_dir))
+            .cloned()
+            .collect()
+    }
+
+    /// 已启用的插件提供的快捷键
+    pub fn keybindings(&self) -> Vec<KeybindingContribution> {
+        self.keybindings.iter().map(|(_, binding)| binding.clone()).collect()
+    }
+
+    /// 插件页的内容，按名称排列
+    pub fn summaries(&self) -> Vec<PluginSummary> {
+        let mut summaries: Vec<PluginSummary> = self
+            .plugins
+            .values()
+            .map(|plugin| PluginSummary {
+                id: plugin.manifest.id.clone(),
+                name: plugin.manifest.name.clone(),
+                version: plugin.manifest.version.clone(),
+                commands: plugin.manifest.contributes.commands.len(),
+                state: plugin.state.clone(),
+                problems: plugin.problems.clone(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        summaries
+    }
+
+    /// 上次扫描时无法读取的清单
+    pub fn load_errors(&self) -> &[String] {
+        &self.load_errors
+    }
+
+    pub fn disabled_plugins(&self) -> &BTreeSet<String> {
+        &self.disabled
+    }
+
+    /// 设置停用的插件，在发现插件之前调用
+    pub fn set_disabled_plugins(&mut self, disabled: BTreeSet<String>) {
+        self.disabled = disabled;
+    }
+
+    pub fn plugin_state(&self, plugin_id: &str) -> Option<&PluginState> {
+        self.plugins.get(plugin_id).map(|plugin| &plugin.state)
+    }
+
+    /// 激活清单中声明了这个事件、还没有激活的插件，返回尝试激活的插件 id（含失败的），按 id 排列
+    pub fn emit_activation_event(&mut self, event: &ActivationEvent) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .plugins
+            .values()
+            .filter(|plugin| plugin.state == PluginState::Discovered)
+            .filter(|plugin| plugin.manifest.activation_events.iter().any(|declared| event.matches(declared)))
+            .map(|plugin| plugin.manifest.id.clone())
+            .collect();
+        ids.sort();
+        for id in &ids {
+            self.activate_plugin(id, &format!("{:?}", event));
+        }
+        ids
+    }
+
+    /// 把事件交给订阅了它的已激活插件，立即返回
+    pub fn publish(&self, event: &PluginEvent) {
+        for plugin in self.plugins.values() {
+            if let Some(events) = &plugin.events {
+                events.send(event);
+            }
+        }
+    }
+
+    /// 激活插件，提供 `entry` 时加载动态库并调用 `activate`；返回是否是这次激活的。
+    /// 失败时记下原因并不再激活，不影响编辑器
+    pub fn activate_plugin(&mut self, plugin_id: &str, reason: &str) -> bool {
+        let Some(plugin) = self.plugins.get_mut(plugin_id) else {
+            return false;
+        };
+        if plugin.state != PluginState::Discovered {
+            return false;
+        }
+        info!("Activating plugin: {} ({})", plugin.manifest.name, reason);
+        if let Some(entry) = &plugin.manifest.entry {
+            let loaded = match plugin.manifest.api_version {
+                Some(API_VERSION) => NativePlugin::load(&plugin.dir.join(entry)),
+                Some(version) => Err(anyhow!("清单的接口版本为 {}，编辑器支持的是 {}", version, API_VERSION)),
+                None => Err(anyhow!("清单提供了 entry，但没有填写 api_version")),
+            };
+            match loaded {
+                Ok(native) => {
+                    let native = Arc::new(native);
+                    plugin.events = EventSubscription::start(plugin_id, native.clone());
+                    plugin.native = Some(native);
+                }
+                Err(err) => {
+                    warn!("Plugin {} failed to activate: {:#}", plugin_id, err);
+                    plugin.state = PluginState::Failed(format!("{:#}", err));
+                    return false;
+                }
+            }
+        }
+        plugin.state = PluginState::Activated;
+        true
+    }
+
+    /// 调用动态库插件的 `deactivate` 并卸载库
+    fn deactivate_plugin(&mut self, plugin_id: &str) {
+        let Some(plugin) = self.plugins.get_mut(plugin_id).filter(|plugin| plugin.state == PluginState::Activated) else {
+            return;
+        };
+        info!("Deactivating plugin: {}", plugin.manifest.name);
+        plugin.state = PluginState::Discovered;
+        plugin.events = None;
+        if let Some(native) = plugin.native.take() {
+            if let Err(err) = guarded("deactivate", || native.plugin.deactivate()) {
+                warn!("Plugin {} failed to deactivate: {:#}", plugin_id, err);
+                plugin.state = PluginState::Failed(format!("{:#}", err));
+            }
+        }
+    }
+
+    /// 启用或停用插件：停用时停止运行并撤下它提供的命令和快捷键；重新启用时再次注册，
+    /// 之前出错的插件可以重新激活。返回插件是否存在
+    pub fn set_plugin_enabled(&mut self, plugin_id: &str, enabled: bool) -> bool {
+        let Some(plugin) = self.plugins.get(plugin_id) else {
+            return false;
+        };
+        let was_enabled = plugin.state != PluginState::Disabled;
+        if enabled {
+            self.disabled.remove(plugin_id);
+        } else {
+            self.disabled.insert(plugin_id.to_string());
+        }
+        if was_enabled == enabled {
+            return true;
+        }
+        if enabled {
+            self.plugins.get_mut(plugin_id).unwrap().state = PluginState::Discovered;
+            self.register_contributions(plugin_id);
+        } else {
+            self.deactivate_plugin(plugin_id);
+            self.unregister_contributions(plugin_id);
+            self.plugins.get_mut(plugin_id).unwrap().state = PluginState::Disabled;
+        }
+        true
+    }
+
+    /// 退出时停止所有插件
+    pub fn shutdown(&mut self) {
+        let ids: Vec<String> = self.plugins.keys().cloned().collect();
+        for id in ids {
+            self.deactivate_plugin(&id);
+        }
+    }
+
+    /// 执行插件提供的命令：提供命令的插件还没有激活时先激活它（不论是否声明了 `onCommand`），
+    /// 再调用插件注册的处理方式。`argument` 交给外部程序和动态库插件，转到内置命令时不使用
+    pub fn execute(&mut self, command_id: &str, argument: Option<&str>) -> Result<PluginInvocation> {
+        let Some(plugin_id) = self.command_owners.get(command_id).cloned() else {
+            return Ok(PluginInvocation::Unknown);
+        };
+        self.activate_plugin(&plugin_id, &format!("onCommand:{}", command_id));
+        let plugin = &self.plugins[&plugin_id];
+        match &plugin.state {
+            PluginState::Disabled => bail!("插件 {} 已停用", plugin.manifest.name),
+            PluginState::Failed(error) => bail!("插件 {} 无法运行：{}", plugin.manifest.name, error),
+            PluginState::Discovered | PluginState::Activated => {}
+        }
+        let Some(handler) = self.handlers.get(command_id) else {
+            bail!("插件 {} 没有为命令 {} 注册处理方式", plugin.manifest.name, command_id);
+        };
+        match handler {
+            CommandHandler::Action { action } => {
+                // 只能转到内置命令，避免插件命令互相调用形成循环
+                if self.command_owners.contains_key(action) {
+                    bail!("命令 {} 只能转到内置命令，{} 由插件提供", command_id, action);
+                }
+                Ok(PluginInvocation::Builtin(action.clone()))
+            }
+            CommandHandler::Process { program, args } => {
+                let args: Vec<String> = args.iter().cloned().chain(argument.map(str::to_string)).collect();
+                let pid = spawn_handler(program, &args, &plugin.dir)
+                    .with_context(|| format!("插件 {} 无法启动 {}", plugin.manifest.name, program))?;
+                Ok(PluginInvocation::Spawned(pid))
+            }
+            CommandHandler::Native => {
+                let Some(native) = &plugin.native else {
+                    bail!("插件 {} 没有提供动态库，无法执行 {}", plugin.manifest.name, command_id);
+                };
+                Ok(PluginInvocation::Native(NativeCommand {
+                    command: command_id.to_string(),
+                    argument: argument.map(str::to_string),
+                    plugin: native.clone(),
+                }))
+            }
         }
     }
 
@@ -102,4 +718,319 @@ impl PluginManager {
     pub fn list_tool_pages(&self) -> &[ToolPageContribution] {
         &self.tool_pages
     }
+
+    pub fn register_icon_theme(&mut self, id: impl Into<String>, label: impl Into<String>, path: PathBuf) {
+        let entry = IconThemeEntry { id: id.into(), label: label.into(), path };
+        Self::add_icon_theme(&mut self.icon_themes, &mut self.command_registry, entry);
+    }
+
+    fn add_icon_theme(themes: &mut Vec<IconThemeEntry>, commands: &mut CommandRegistry, entry: IconThemeEntry) {
+        commands.register(CommandContribution {
+            command: format!("{}{}", ICON_THEME_COMMAND_PREFIX, entry.id),
+            title: format!("File Icon Theme: {}", entry.label),
+            category: Some("View".to_string()),
+            icon: None,
+        });
+        themes.retain(|theme| theme.id != entry.id);
+        themes.push(entry);
+    }
+
+    pub fn icon_theme(&self, id: &str) -> Option<&IconThemeEntry> {
+        self.icon_themes.iter().find(|theme| theme.id == id)
+    }
+}
+
+/// 在插件目录中启动外部程序；程序相对插件目录时也能找到。在后台等待退出，失败时记入日志
+fn spawn_handler(program: &str, args: &[String], dir: &Path) -> Result<u32> {
+    let local = dir.join(program);
+    let program = if local.is_file() { local } else { PathBuf::from(program) };
+    let mut child = Command::new(&program).args(args).current_dir(dir).spawn()?;
+    let pid = child.id();
+    std::thread::spawn(move || match child.wait() {
+        Ok(status) if status.success() => info!("Plugin handler {:?} exited", program),
+        Ok(status) => warn!("Plugin handler {:?} failed: {}", program, status),
+        Err(e) => warn!("Plugin handler {:?} could not be awaited: {}", program, e),
+    });
+    Ok(pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiecode_plugin_api::{CommandHandlerContribution, Contributions, MenuContribution};
+
+    fn plugin(id: &str, commands: &[&str], handlers: Vec<(&str, CommandHandler)>) -> PluginManifest {
+        PluginManifest {
+            id: id.to_string(),
+            name: id.to_string(),
+            version: "1.0.0".to_string(),
+            api_version: None,
+            entry: None,
+            activation_events: Vec::new(),
+            contributes: Contributions {
+                commands: commands
+                    .iter()
+                    .map(|command| CommandContribution {
+                        command: command.to_string(),
+                        title: command.to_string(),
+                        category: None,
+                        icon: None,
+                    })
+                    .collect(),
+                handlers: handlers
+                    .into_iter()
+                    .map(|(command, handler)| CommandHandlerContribution { command: command.to_string(), handler })
+                    .collect(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_routes_plugin_commands() {
+        let mut manager = PluginManager::new();
+        manager.command_registry.register(CommandContribution {
+            command: "core.save".to_string(),
+            title: "Save".to_string(),
+            category: None,
+            icon: None,
+        });
+        let save = CommandHandler::Action { action: "core.save".to_string() };
+        let missing = CommandHandler::Process { program: "no-such-program-xyz".to_string(), args: Vec::new() };
+        let problems = manager.register_plugin(
+            plugin("demo", &["demo.save", "demo.run", "demo.idle", "core.save"], vec![
+                ("demo.save", save.clone()),
+                ("demo.run", missing),
+                ("other.cmd", save.clone()),
+            ]),
+            PathBuf::from("/plugins/demo"),
+        );
+        // 与内置命令重复的 id 和不属于自己的处理方式被跳过
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("core.save already provided by built-in"));
+
+        let problems = manager.register_plugin(
+            plugin("copy", &["demo.save", "copy.loop"], vec![
+                ("copy.loop", CommandHandler::Action { action: "demo.save".to_string() }),
+            ]),
+            PathBuf::from("/plugins/copy"),
+        );
+        assert_eq!(problems, vec!["Plugin copy contributes command demo.save already provided by demo"]);
+
+        assert_eq!(manager.execute("core.save", None).unwrap(), PluginInvocation::Unknown);
+        assert_eq!(manager.execute("nothing.here", None).unwrap(), PluginInvocation::Unknown);
+        assert_eq!(manager.plugins["demo"].state, PluginState::Discovered);
+        assert_eq!(manager.execute("demo.save", None).unwrap(), PluginInvocation::Builtin("core.save".to_string()));
+        assert_eq!(manager.plugins["demo"].state, PluginState::Activated);
+        assert!(manager.execute("demo.idle", None).unwrap_err().to_string().contains("没有为命令 demo.idle 注册处理方式"));
+        assert!(manager.execute("demo.run", None).is_err());
+        assert!(manager.execute("copy.loop", None).is_err());
+        assert_eq!(manager.plugins["copy"].state, PluginState::Activated);
+    }
+
+    #[test]
+    fn test_native_plugin_failures_and_disable() {
+        let mut manager = PluginManager::new();
+        let save = CommandHandler::Action { action: "core.save".to_string() };
+        let mut broken = plugin("broken", &["broken.run"], vec![("broken.run", save.clone())]);
+        broken.entry = Some("libbroken.so".to_string());
+        broken.api_version = Some(API_VERSION);
+        let mut old = plugin("old", &["old.run"], vec![("old.run", save.clone())]);
+        old.entry = Some("libold.so".to_string());
+        old.api_version = Some(API_VERSION + 1);
+        for manifest in [broken, old, plugin("demo", &["demo.run"], vec![("demo.run", save)])] {
+            let dir = PathBuf::from("/plugins").join(&manifest.id);
+            assert!(manager.register_plugin(manifest, dir).is_empty());
+        }
+        assert!(manager.command_registry.get("plugin.disable.demo").is_some());
+
+        // 动态库加载失败时插件记为出错，不再激活
+        let err = manager.execute("broken.run", None).unwrap_err().to_string();
+        assert!(err.contains("无法运行") && err.contains("无法加载"));
+        assert!(matches!(manager.plugins["broken"].state, PluginState::Failed(_)));
+        assert!(!manager.activate_plugin("broken", "retry"));
+        assert!(manager.execute("old.run", None).unwrap_err().to_string().contains(&format!("接口版本为 {}", API_VERSION + 1)));
+
+        assert_eq!(manager.execute("demo.run", None).unwrap(), PluginInvocation::Builtin("core.save".to_string()));
+        assert!(manager.set_plugin_enabled("demo", false));
+        assert_eq!(manager.plugins["demo"].state, PluginState::Disabled);
+        // 停用后命令被撤下
+        assert!(manager.command_registry.get("demo.run").is_none());
+        assert_eq!(manager.execute("demo.run", None).unwrap(), PluginInvocation::Unknown);
+        assert!(manager.set_plugin_enabled("demo", true));
+        assert_eq!(manager.execute("demo.run", None).unwrap(), PluginInvocation::Builtin("core.save".to_string()));
+        assert!(!manager.set_plugin_enabled("missing", false));
+        manager.shutdown();
+    }
+
+    #[test]
+    fn test_disabled_plugins_contribute_nothing() {
+        let mut manager = PluginManager::new();
+        manager.set_disabled_plugins(["demo".to_string()].into_iter().collect());
+        let mut manifest = plugin("demo", &["demo.run"], vec![("demo.run", CommandHandler::Action { action: "core.save".to_string() })]);
+        manifest.contributes.keybindings = vec![KeybindingContribution {
+            command: "demo.run".to_string(),
+            key: "ctrl+shift+g".to_string(),
+            when: None,
+        }];
+        assert!(manager.register_plugin(manifest, PathBuf::from("/plugins/demo")).is_empty());
+        assert!(manager.command_registry.get("demo.run").is_none());
+        assert!(manager.keybindings().is_empty());
+        assert_eq!(manager.summaries()[0].state, PluginState::Disabled);
+        assert_eq!(manager.summaries()[0].commands, 1);
+
+        assert!(manager.set_plugin_enabled("demo", true));
+        assert!(manager.disabled_plugins().is_empty());
+        assert!(manager.command_registry.get("demo.run").is_some());
+        assert_eq!(manager.keymap.labels().len(), 1);
+        assert_eq!(manager.keybindings().len(), 1);
+
+        assert!(manager.set_plugin_enabled("demo", false));
+        assert!(manager.command_registry.get("plugin.disable.demo").is_none());
+        assert!(manager.keymap.labels().is_empty());
+        assert!(manager.keybindings().is_empty());
+
+        // 重新扫描时目录中已没有的插件被移除
+        manager.discover_plugins();
+        assert!(manager.summaries().is_empty());
+    }
+
+    #[test]
+    fn test_menu_items_follow_plugin_state() {
+        let mut manager = PluginManager::new();
+        let mut manifest = plugin("demo", &["demo.zip"], vec![]);
+        let item = |when: Option<&str>| MenuContribution {
+            command: "demo.zip".to_string(),
+            title: "压缩".to_string(),
+            when: when.map(str::to_string),
+        };
+        manifest.contributes.menus = [
+            (FILE_TREE_CONTEXT_MENU.to_string(), vec![item(Some("isDir")), item(Some("*.log"))]),
+            ("editor/title".to_string(), vec![item(None)]),
+        ]
+        .into_iter()
+        .collect();
+        let problems = manager.register_plugin(manifest, PathBuf::from("/plugins/demo"));
+        assert_eq!(problems, vec!["Plugin demo contributes to unknown menu editor/title"]);
+
+        let items = |manager: &PluginManager, path: &str, is_dir| {
+            manager.menu_items(FILE_TREE_CONTEXT_MENU, Path::new(path), is_dir)
+        };
+        assert_eq!(items(&manager, "/p/src", true).len(), 1);
+        assert_eq!(items(&manager, "/p/build.log", false)[0].when.as_deref(), Some("*.log"));
+        assert!(items(&manager, "/p/main.t", false).is_empty());
+
+        assert!(manager.set_plugin_enabled("demo", false));
+        assert!(items(&manager, "/p/src", true).is_empty());
+    }
+
+    #[test]
+    fn test_activation_events() {
+        let mut manager = PluginManager::new();
+        let mut manifests = vec![
+            plugin("lang", &[], Vec::new()),
+            plugin("startup", &[], Vec::new()),
+            plugin("any_file", &[], Vec::new()),
+            plugin("on_build", &[], Vec::new()),
+        ];
+        manifests[0].activation_events = vec!["onLanguage:t".to_string()];
+        manifests[1].activation_events = vec!["onStartup".to_string()];
+        manifests[2].activation_events = vec!["onFileOpen".to_string()];
+        manifests[3].activation_events = vec!["onCommand:core.build".to_string()];
+        for manifest in manifests {
+            let dir = PathBuf::from("/plugins").join(&manifest.id);
+            manager.register_plugin(manifest, dir);
+        }
+        // 发现插件时不激活
+        assert!(manager.plugins.values().all(|plugin| plugin.state == PluginState::Discovered));
+
+        assert_eq!(manager.emit_activation_event(&ActivationEvent::Startup), vec!["startup"]);
+        assert_eq!(manager.emit_activation_event(&ActivationEvent::FileOpen("md".to_string())), vec!["any_file"]);
+        assert_eq!(manager.plugin_state("lang"), Some(&PluginState::Discovered));
+        assert_eq!(manager.emit_activation_event(&ActivationEvent::FileOpen("T".to_string())), vec!["lang"]);
+        assert_eq!(manager.plugin_state("lang"), Some(&PluginState::Activated));
+        assert!(manager.emit_activation_event(&ActivationEvent::FileOpen("t".to_string())).is_empty());
+
+        assert!(manager.emit_activation_event(&ActivationEvent::Command("core.save".to_string())).is_empty());
+        assert_eq!(manager.emit_activation_event(&ActivationEvent::Command("core.build".to_string())), vec!["on_build"]);
+    }
+
+    /// 把事件原样转发出去；`gate` 不为空时处理事件前等到它关闭，模拟很慢的插件
+    struct Recorder {
+        kinds: Vec<PluginEventKind>,
+        received: mpsc::Sender<PluginEvent>,
+        gate: Option<std::sync::Mutex<mpsc::Receiver<()>>>,
+    }
+
+    impl Plugin for Recorder {
+        fn activate(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn deactivate(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn subscriptions(&self) -> Vec<PluginEventKind> {
+            self.kinds.clone()
+        }
+
+        fn on_event(&self, event: &PluginEvent) -> Result<()> {
+            if let Some(gate) = &self.gate {
+                let _ = gate.lock().unwrap().recv();
+            }
+            self.received.send(event.clone())?;
+            Ok(())
+        }
+    }
+
+    fn attach(manager: &mut PluginManager, id: &str, recorder: Recorder) {
+        manager.register_plugin(plugin(id, &[], Vec::new()), PathBuf::from("/plugins").join(id));
+        let native = Arc::new(NativePlugin { plugin: Box::new(recorder), _library: None });
+        let loaded = manager.plugins.get_mut(id).unwrap();
+        loaded.events = EventSubscription::start(id, native.clone());
+        loaded.native = Some(native);
+        loaded.state = PluginState::Activated;
+    }
+
+    #[test]
+    fn test_publishes_events_in_order() {
+        let mut manager = PluginManager::new();
+        let (counter_sender, counter) = mpsc::channel();
+        let kinds = vec![PluginEventKind::FileOpened, PluginEventKind::BufferChanged];
+        attach(&mut manager, "counter", Recorder { kinds, received: counter_sender, gate: None });
+        let (slow_sender, slow) = mpsc::channel();
+        let (release, gate) = mpsc::channel();
+        let kinds = vec![PluginEventKind::FileOpened, PluginEventKind::FileSaved, PluginEventKind::BufferChanged];
+        attach(&mut manager, "slow", Recorder { kinds, received: slow_sender, gate: Some(gate.into()) });
+
+        let path = PathBuf::from("/p/源代码/启动窗口.t");
+        let events = vec![
+            PluginEvent::FileOpened(path.clone()),
+            PluginEvent::FileSaved(path.clone()),
+            PluginEvent::BufferChanged { path: path.clone(), version: 1 },
+            PluginEvent::BufferChanged { path: path.clone(), version: 2 },
+            PluginEvent::WorkspaceOpened(PathBuf::from("/p")),
+        ];
+        for event in &events {
+            manager.publish(event);
+        }
+
+        // 慢的插件还卡在第一个事件上，另一个插件照常按顺序收到订阅的事件
+        let timeout = std::time::Duration::from_secs(5);
+        let expected = vec![events[0].clone(), events[2].clone(), events[3].clone()];
+        let received: Vec<PluginEvent> = (0..3).map(|_| counter.recv_timeout(timeout).unwrap()).collect();
+        assert_eq!(received, expected);
+
+        // 停用后取消订阅，投递线程结束
+        assert!(manager.set_plugin_enabled("counter", false));
+        manager.publish(&events[0]);
+        assert_eq!(counter.recv_timeout(timeout), Err(mpsc::RecvTimeoutError::Disconnected));
+
+        // 关闭 gate 之后不再等待
+        drop(release);
+        let received: Vec<PluginEvent> = (0..4).map(|_| slow.recv_timeout(timeout).unwrap()).collect();
+        assert_eq!(received, events[..4].to_vec());
+        manager.shutdown();
+    }
 }
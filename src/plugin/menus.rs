@@ -0,0 +1,54 @@
+//! 插件在清单的 `contributes.menus` 中提供的菜单项。文件树右键菜单在内置项之后列出对点击的文件或
+//! 文件夹显示的插件项，执行命令时以点击的路径为参数
+
+use crate::editor::overrides::glob_match;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    pub plugin_id: String,
+    /// 菜单位置，例如 [`FILE_TREE_CONTEXT_MENU`](tiecode_plugin_api::FILE_TREE_CONTEXT_MENU)
+    pub menu: String,
+    pub command: String,
+    pub title: String,
+    pub when: Option<String>,
+}
+
+impl MenuItem {
+    /// 在 `path` 上打开菜单时是否显示：`isDir`、`isFile` 按类型判断，其他按文件名通配
+    pub fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        match self.when.as_deref().map(str::trim) {
+            None | Some("") => true,
+            Some("isDir") => is_dir,
+            Some("isFile") => !is_dir,
+            Some(pattern) => path
+                .file_name()
+                .is_some_and(|name| glob_match(pattern, &name.to_string_lossy())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_when_filters_by_kind_and_file_name() {
+        let item = |when: Option<&str>| MenuItem {
+            plugin_id: "demo".to_string(),
+            menu: "fileTree/context".to_string(),
+            command: "demo.run".to_string(),
+            title: "运行".to_string(),
+            when: when.map(str::to_string),
+        };
+        let (file, dir) = (Path::new("/p/配置.json"), Path::new("/p/src"));
+        assert!(item(None).matches(file, false) && item(None).matches(dir, true));
+        assert!(item(Some("isDir")).matches(dir, true));
+        assert!(!item(Some("isDir")).matches(file, false));
+        assert!(item(Some("isFile")).matches(file, false));
+        assert!(!item(Some("isFile")).matches(dir, true));
+        assert!(item(Some("*.json")).matches(file, false));
+        assert!(!item(Some("*.json")).matches(dir, true));
+        assert!(item(Some("s?c")).matches(dir, true));
+    }
+}
@@ -1,3 +1,8 @@
+pub mod editor_host;
+pub mod keymap;
 pub mod manager;
 pub mod manifest;
+pub mod menus;
 pub mod lsp;
+pub mod settings;
+pub mod status_items;
@@ -0,0 +1,75 @@
+//! 插件的启用状态，在插件页中切换，保存在配置目录的 plugins.json 中：
+//!
+//! ```json
+//! { "disabled": ["demo.plugin"] }
+//! ```
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+const PLUGIN_SETTINGS_FILE: &str = "plugins.json";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginSettings {
+    /// 停用的插件 id
+    #[serde(default)]
+    pub disabled: BTreeSet<String>,
+}
+
+impl PluginSettings {
+    /// 没有记录或无法解析时所有插件都启用
+    pub fn load() -> Self {
+        settings_path().map(|path| Self::load_from(&path)).unwrap_or_default()
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|err| {
+            warn!("Ignoring unreadable plugin settings {:?}: {}", path, err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = settings_path() {
+            if let Err(err) = self.save_to(&path) {
+                warn!("Failed to save plugin settings to {:?}: {}", path, err);
+            }
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, content)
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    crate::paths::config_dir().map(|dir| dir.join(PLUGIN_SETTINGS_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_disabled_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config").join(PLUGIN_SETTINGS_FILE);
+        assert_eq!(PluginSettings::load_from(&path), PluginSettings::default());
+
+        let settings = PluginSettings { disabled: ["demo".to_string()].into_iter().collect() };
+        settings.save_to(&path).unwrap();
+        assert_eq!(PluginSettings::load_from(&path), settings);
+
+        std::fs::write(&path, "{ \"disabled\": 1 }").unwrap();
+        assert_eq!(PluginSettings::load_from(&path), PluginSettings::default());
+    }
+}
@@ -0,0 +1,105 @@
+//! 状态栏项：内置功能和插件都可以在状态栏左右两侧放一段文字，点击时像命令面板一样执行关联的命令。
+//! 插件在清单的 `contributes.status_items` 中声明，内置功能通过 [`PluginManager`](super::manager::PluginManager)
+//! 登记并随时更新文字
+
+use tiecode_plugin_api::StatusAlignment;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusItem {
+    pub id: String,
+    pub alignment: StatusAlignment,
+    pub priority: i32,
+    /// 为空时不显示
+    pub text: String,
+    pub tooltip: Option<String>,
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct StatusItems {
+    /// 按登记顺序
+    items: Vec<StatusItem>,
+}
+
+impl StatusItems {
+    /// 登记一项，文字为空；id 已存在时只更新位置
+    pub fn register(&mut self, id: &str, alignment: StatusAlignment, priority: i32) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.id == id) {
+            item.alignment = alignment;
+            item.priority = priority;
+            return;
+        }
+        self.items.push(StatusItem {
+            id: id.to_string(),
+            alignment,
+            priority,
+            text: String::new(),
+            tooltip: None,
+            command: None,
+        });
+    }
+
+    /// 返回显示的内容是否有变化；没有登记的项忽略
+    pub fn set_text(&mut self, id: &str, text: String, tooltip: Option<String>) -> bool {
+        let Some(item) = self.items.iter_mut().find(|item| item.id == id) else {
+            return false;
+        };
+        if item.text == text && item.tooltip == tooltip {
+            return false;
+        }
+        item.text = text;
+        item.tooltip = tooltip;
+        true
+    }
+
+    pub fn set_command(&mut self, id: &str, command: Option<String>) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.id == id) {
+            item.command = command;
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.items.retain(|item| item.id != id);
+    }
+
+    /// 一侧要显示的项，从左到右排列：优先级高的在前，相同时先登记的在前
+    pub fn aligned(&self, alignment: StatusAlignment) -> Vec<&StatusItem> {
+        let mut items: Vec<&StatusItem> =
+            self.items.iter().filter(|item| item.alignment == alignment && !item.text.is_empty()).collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.priority));
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orders_items_by_priority() {
+        let mut items = StatusItems::default();
+        items.register("git", StatusAlignment::Left, 100);
+        items.register("demo", StatusAlignment::Left, 100);
+        items.register("errors", StatusAlignment::Left, 200);
+        items.register("clock", StatusAlignment::Right, 0);
+        assert!(items.set_text("git", "Git: main".to_string(), None));
+        assert!(!items.set_text("git", "Git: main".to_string(), None));
+        assert!(!items.set_text("missing", "x".to_string(), None));
+        items.set_text("demo", "Demo".to_string(), Some("示例".to_string()));
+        items.set_text("clock", "12:00".to_string(), None);
+
+        // 没有文字的项不显示
+        let left: Vec<&str> = items.aligned(StatusAlignment::Left).iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(left, vec!["git", "demo"]);
+        items.set_text("errors", "错误：2".to_string(), None);
+        let left: Vec<&str> = items.aligned(StatusAlignment::Left).iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(left, vec!["errors", "git", "demo"]);
+
+        // 再次登记只移动位置，保留文字和登记顺序
+        items.register("git", StatusAlignment::Right, 0);
+        let right: Vec<&str> = items.aligned(StatusAlignment::Right).iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(right, vec!["Git: main", "12:00"]);
+        items.remove("clock");
+        assert_eq!(items.aligned(StatusAlignment::Right).len(), 1);
+    }
+}
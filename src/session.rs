@@ -0,0 +1,255 @@
+//! 上次会话的工作区状态：打开的文件夹（含后来加入工作区的文件夹）、标签、当前标签、背景图、提交信息草稿、
+//! 各文件夹在文件树中的展开状态和快捷工具栏，保存在配置目录下的 session.json
+
+use crate::component::toolbar::Toolbar;
+use crate::paths::expand_path_buf;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const SESSION_FILE: &str = "session.json";
+
+/// 标签的光标和滚动位置；行列从 0 开始，列为字节偏移
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TabView {
+    pub line: usize,
+    pub column: usize,
+    pub scroll_y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TabState {
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view: Option<TabView>,
+}
+
+/// 文件树中一个根目录的展开状态和滚动位置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeState {
+    /// 展开的文件夹，相对根目录；根目录本身为空路径
+    #[serde(default)]
+    pub expanded: Vec<PathBuf>,
+    /// 顶部可见的行及其向上滚出的距离；只对打开的文件夹记录
+    #[serde(default)]
+    pub scroll_item: usize,
+    #[serde(default)]
+    pub scroll_offset: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    #[serde(default)]
+    pub root: Option<PathBuf>,
+    /// 后来加入工作区的文件夹；只打开一个文件夹时不写入
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_roots: Vec<PathBuf>,
+    #[serde(default)]
+    pub tabs: Vec<TabState>,
+    #[serde(default)]
+    pub active: Option<PathBuf>,
+    #[serde(default)]
+    pub background_image: Option<PathBuf>,
+    /// 各仓库尚未提交的提交信息
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub commit_drafts: BTreeMap<PathBuf, String>,
+    /// 各文件夹的文件树状态，包括已经关闭的文件夹，重新打开时恢复
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tree_states: BTreeMap<PathBuf, TreeState>,
+    /// 快捷工具栏上固定的命令及是否显示
+    #[serde(default)]
+    pub toolbar: Toolbar,
+}
+
+impl Session {
+    /// 展开手工写入会话文件的 `~` 和环境变量，例如 `"root": "~/projects/demo"`
+    pub fn expand_paths(&mut self) {
+        let expand = |path: &PathBuf| expand_path_buf(path);
+        self.root = self.root.as_ref().map(expand);
+        for root in &mut self.extra_roots {
+            *root = expand(root);
+        }
+        for tab in &mut self.tabs {
+            tab.path = expand(&tab.path);
+        }
+        self.active = self.active.as_ref().map(expand);
+        self.background_image = self.background_image.as_ref().map(expand);
+        self.commit_drafts = std::mem::take(&mut self.commit_drafts)
+            .into_iter()
+            .map(|(repo, draft)| (expand(&repo), draft))
+            .collect();
+        self.tree_states = std::mem::take(&mut self.tree_states)
+            .into_iter()
+            .map(|(root, state)| (expand(&root), state))
+            .collect();
+    }
+
+    /// 去掉已不存在的文件夹、文件和背景图
+    pub fn retain_existing(&mut self) {
+        if self.root.as_ref().is_some_and(|root| !root.is_dir()) {
+            self.root = None;
+        }
+        self.extra_roots.retain(|root| root.is_dir());
+        self.tabs.retain(|tab| tab.path.is_file());
+        if let Some(active) = &self.active {
+            if !self.tabs.iter().any(|tab| &tab.path == active) {
+                self.active = None;
+            }
+        }
+        if self.background_image.as_ref().is_some_and(|image| !image.is_file()) {
+            self.background_image = None;
+        }
+        self.commit_drafts.retain(|repo, _| repo.is_dir());
+        self.tree_states.retain(|root, state| {
+            state.expanded.retain(|dir| root.join(dir).is_dir());
+            root.is_dir()
+        });
+    }
+
+    /// 读取上次的会话，展开路径并去掉失效的项；没有记录或无法解析时返回 None
+    pub fn load() -> Option<Self> {
+        Self::load_from(&session_path()?)
+    }
+
+    fn load_from(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        match serde_json::from_str::<Session>(&content) {
+            Ok(mut session) => {
+                session.expand_paths();
+                session.retain_existing();
+                Some(session)
+            }
+            Err(err) => {
+                warn!("Ignoring unreadable session file {:?}: {}", path, err);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = session_path() {
+            if let Err(err) = self.save_to(&path) {
+                warn!("Failed to save session to {:?}: {}", path, err);
+            }
+        }
+    }
+
+    fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, content)
+    }
+
+    /// 删除保存的会话，下次启动时不再恢复
+    pub fn clear() {
+        if let Some(path) = session_path() {
+            if let Err(err) = std::fs::remove_file(&path) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Failed to remove session file {:?}: {}", path, err);
+                }
+            }
+        }
+    }
+}
+
+fn session_path() -> Option<PathBuf> {
+    crate::paths::config_dir().map(|dir| dir.join(SESSION_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_skips_files_that_no_longer_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("a.t");
+        let gone = dir.path().join("b.t");
+        std::fs::write(&kept, "").unwrap();
+        let view = TabView { line: 3, column: 2, scroll_y: -40.0 };
+        let session = Session {
+            root: Some(dir.path().to_path_buf()),
+            extra_roots: vec![dir.path().to_path_buf(), dir.path().join("removed")],
+            tabs: vec![
+                TabState { path: kept.clone(), view: Some(view) },
+                TabState { path: gone.clone(), view: None },
+            ],
+            active: Some(gone),
+            background_image: Some(dir.path().join("missing.png")),
+            commit_drafts: BTreeMap::from([
+                (dir.path().to_path_buf(), "fix: half-written".to_string()),
+                (dir.path().join("gone"), "lost".to_string()),
+            ]),
+            tree_states: BTreeMap::from([
+                (
+                    dir.path().to_path_buf(),
+                    TreeState {
+                        expanded: vec![PathBuf::new(), PathBuf::from("config"), PathBuf::from("removed")],
+                        scroll_item: 4,
+                        scroll_offset: 6.0,
+                    },
+                ),
+                (dir.path().join("gone"), TreeState::default()),
+            ]),
+            toolbar: Toolbar { commands: vec!["core.save".to_string()], visible: false },
+        };
+        let file = dir.path().join("config").join(SESSION_FILE);
+        session.save_to(&file).unwrap();
+        assert!(std::fs::read_to_string(&file).unwrap().contains("treeStates"));
+
+        let restored = Session::load_from(&file).unwrap();
+        assert_eq!(restored.root.as_deref(), Some(dir.path()));
+        assert_eq!(restored.extra_roots, vec![dir.path().to_path_buf()]);
+        assert_eq!(restored.tabs, vec![TabState { path: kept, view: Some(view) }]);
+        assert_eq!(restored.active, None);
+        assert_eq!(restored.background_image, None);
+        assert_eq!(
+            restored.commit_drafts,
+            BTreeMap::from([(dir.path().to_path_buf(), "fix: half-written".to_string())])
+        );
+        assert_eq!(restored.toolbar, session.toolbar);
+        // 已删除的文件夹和根目录一并去掉
+        assert_eq!(
+            restored.tree_states,
+            BTreeMap::from([(
+                dir.path().to_path_buf(),
+                TreeState { expanded: vec![PathBuf::new(), PathBuf::from("config")], scroll_item: 4, scroll_offset: 6.0 },
+            )])
+        );
+
+        std::fs::write(&file, "not json").unwrap();
+        assert_eq!(Session::load_from(&file), None);
+    }
+
+    #[test]
+    fn test_load_expands_variables_in_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(SESSION_FILE);
+        // 运行测试时 cargo 设置了 CARGO_MANIFEST_DIR
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        std::fs::write(
+            &file,
+            r#"{
+                "root": "${CARGO_MANIFEST_DIR}",
+                "tabs": [{ "path": "$CARGO_MANIFEST_DIR/Cargo.toml" }],
+                "active": "$CARGO_MANIFEST_DIR/Cargo.toml",
+                "backgroundImage": "$TIECODE_UNSET_TEST_VARIABLE/bg.png"
+            }"#,
+        )
+        .unwrap();
+
+        let restored = Session::load_from(&file).unwrap();
+        assert_eq!(restored.root, Some(manifest_dir.clone()));
+        assert_eq!(restored.tabs, vec![TabState { path: manifest_dir.join("Cargo.toml"), view: None }]);
+        assert_eq!(restored.active, Some(manifest_dir.join("Cargo.toml")));
+        // 未能展开的变量原样保留，指向的文件不存在而被去掉
+        assert_eq!(restored.background_image, None);
+    }
+}
@@ -0,0 +1,102 @@
+//! 启动耗时：首次绘制前同步完成的各阶段、首次绘制后排队执行的各阶段，
+//! 以及首次绘制和可交互的时间点，写入日志并显示在性能面板中
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupPhase {
+    pub name: String,
+    pub duration: Duration,
+    /// 首次绘制后才执行
+    pub deferred: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StartupTimeline {
+    started: Instant,
+    phases: Vec<StartupPhase>,
+    first_paint: Option<Duration>,
+    interactive: Option<Duration>,
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{} ms", duration.as_millis())
+}
+
+impl StartupTimeline {
+    /// `started` 为进程开始的时间
+    pub fn new(started: Instant) -> Self {
+        Self { started, phases: Vec::new(), first_paint: None, interactive: None }
+    }
+
+    /// 执行 `f` 并记为一个阶段
+    pub fn measure<T>(&mut self, name: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let value = f();
+        self.record(name, start.elapsed(), false);
+        value
+    }
+
+    /// 同名的阶段分多次执行时累加耗时，例如逐个编译的语法
+    pub fn record(&mut self, name: &str, duration: Duration, deferred: bool) {
+        match self.phases.iter_mut().find(|p| p.name == name && p.deferred == deferred) {
+            Some(phase) => phase.duration += duration,
+            None => self.phases.push(StartupPhase { name: name.to_string(), duration, deferred }),
+        }
+    }
+
+    /// 记录首次绘制；只有第一次调用返回 true
+    pub fn mark_first_paint(&mut self) -> bool {
+        if self.first_paint.is_some() {
+            return false;
+        }
+        self.first_paint = Some(self.started.elapsed());
+        true
+    }
+
+    pub fn mark_interactive(&mut self) {
+        self.interactive.get_or_insert_with(|| self.started.elapsed());
+    }
+
+    pub fn first_paint(&self) -> Option<Duration> {
+        self.first_paint
+    }
+
+    pub fn interactive(&self) -> Option<Duration> {
+        self.interactive
+    }
+
+    /// 性能面板和日志中的各行
+    pub fn lines(&self) -> Vec<String> {
+        let point = |label: &str, at: Option<Duration>| {
+            format!("{} {}", label, at.map(millis).unwrap_or_else(|| "…".to_string()))
+        };
+        let mut lines = vec![point("首次绘制", self.first_paint), point("可交互", self.interactive)];
+        for phase in &self.phases {
+            let suffix = if phase.deferred { "（延后）" } else { "" };
+            lines.push(format!("  {}{} {}", phase.name, suffix, millis(phase.duration)));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline_accumulates_phases() {
+        let mut timeline = StartupTimeline::new(Instant::now());
+        assert_eq!(timeline.measure("编辑器", || 7), 7);
+        timeline.record("语法", Duration::from_millis(3), true);
+        timeline.record("语法", Duration::from_millis(4), true);
+        assert_eq!(timeline.lines().len(), 4);
+
+        assert_eq!(timeline.lines()[0], "首次绘制 …");
+        assert!(timeline.mark_first_paint());
+        assert!(!timeline.mark_first_paint());
+        timeline.mark_interactive();
+        assert!(timeline.interactive() >= timeline.first_paint());
+        assert_eq!(timeline.lines()[3], "  语法（延后） 7 ms");
+    }
+}
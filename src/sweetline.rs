@@ -7,6 +7,7 @@ include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 use libc::intptr_t;
 use std::ffi::{CStr, CString};
 use std::slice;
+use std::sync::{Mutex, MutexGuard};
 
 /// 错误类型
 #[derive(Debug)]
@@ -37,20 +38,27 @@ impl From<sl_error_t> for SweetLineError {
     }
 }
 
+/// 引擎的语法表和文档表不是线程安全的，所有调用都持有 `lock`，后台线程也能载入文档
 pub struct Engine {
     handle: intptr_t,
+    lock: Mutex<()>,
 }
 
 impl Engine {
     pub fn new(show_index: bool) -> Self {
         unsafe {
             let handle = sl_create_engine(show_index);
-            Self { handle }
+            Self { handle, lock: Mutex::new(()) }
         }
     }
 
+    fn guard(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
     pub fn compile_json(&self, json: &str) -> Result<(), SweetLineError> {
         let c_json = CString::new(json).map_err(|_| SweetLineError::JsonInvalid)?;
+        let _guard = self.guard();
         unsafe {
             let err = sl_engine_compile_json(self.handle, c_json.as_ptr());
             if err.err_code == sl_error_SL_OK {
@@ -63,6 +71,11 @@ impl Engine {
     }
 
     pub fn load_document(&self, doc: &Document) -> DocumentAnalyzer {
+        let _guard = self.guard();
+        self.load_document_locked(doc)
+    }
+
+    fn load_document_locked(&self, doc: &Document) -> DocumentAnalyzer {
         unsafe {
             let analyzer_handle = sl_engine_load_document(self.handle, doc.handle);
             DocumentAnalyzer {
@@ -71,7 +84,19 @@ impl Engine {
         }
     }
 
+    /// 替换 `uri` 已载入的文档。移除和载入之间不释放锁，不会拿到其它线程同时载入的旧分析器
+    pub fn reload_document(&self, uri: &str, doc: &Document) -> DocumentAnalyzer {
+        let _guard = self.guard();
+        let _ = self.remove_document_locked(uri);
+        self.load_document_locked(doc)
+    }
+
     pub fn remove_document(&self, uri: &str) -> Result<(), SweetLineError> {
+        let _guard = self.guard();
+        self.remove_document_locked(uri)
+    }
+
+    fn remove_document_locked(&self, uri: &str) -> Result<(), SweetLineError> {
         let c_uri = CString::new(uri).map_err(|_| SweetLineError::JsonInvalid)?;
         unsafe {
             let err = sl_engine_remove_document(self.handle, c_uri.as_ptr());
@@ -84,6 +109,7 @@ impl Engine {
     }
 
     pub fn get_style_name(&self, style_id: u32) -> Option<String> {
+        let _guard = self.guard();
         unsafe {
             let ptr = sl_engine_get_style_name(self.handle, style_id as i32);
             if ptr.is_null() {
@@ -0,0 +1,131 @@
+//! 在系统文件管理器中显示文件、在系统终端中打开文件夹。
+//! 各平台的命令先整理成候选列表，依次尝试启动，找不到程序时换下一个
+
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Os {
+    Windows,
+    MacOs,
+    Linux,
+}
+
+impl Os {
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Self::Windows
+        } else if cfg!(target_os = "macos") {
+            Self::MacOs
+        } else {
+            Self::Linux
+        }
+    }
+}
+
+/// 一条待启动的命令
+#[derive(Debug, Clone, PartialEq)]
+pub struct Launch {
+    pub program: String,
+    pub args: Vec<OsString>,
+    pub dir: Option<PathBuf>,
+}
+
+impl Launch {
+    fn new(program: &str, args: impl IntoIterator<Item = OsString>) -> Self {
+        Self { program: program.to_string(), args: args.into_iter().collect(), dir: None }
+    }
+
+    fn in_dir(mut self, dir: &Path) -> Self {
+        self.dir = Some(dir.to_path_buf());
+        self
+    }
+}
+
+/// 在文件管理器中选中 `path`；Linux 上没有通用的选中方式，打开其所在的文件夹
+pub fn reveal_launches(os: Os, path: &Path) -> Vec<Launch> {
+    match os {
+        Os::Windows => {
+            let mut select = OsString::from("/select,");
+            select.push(path);
+            vec![Launch::new("explorer", [select])]
+        }
+        Os::MacOs => vec![Launch::new("open", ["-R".into(), path.into()])],
+        Os::Linux => {
+            let dir = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+            vec![Launch::new("xdg-open", [dir.into()])]
+        }
+    }
+}
+
+/// 以 `dir` 为工作目录打开终端；Linux 上优先使用 `$TERMINAL`，再依次尝试常见的终端
+pub fn terminal_launches(os: Os, dir: &Path, env_terminal: Option<&str>) -> Vec<Launch> {
+    match os {
+        Os::Windows => vec![
+            Launch::new("cmd", ["/C".into(), "start".into(), "".into(), "cmd".into()]).in_dir(dir),
+        ],
+        Os::MacOs => vec![Launch::new("open", ["-a".into(), "Terminal".into(), dir.into()])],
+        Os::Linux => env_terminal
+            .filter(|terminal| !terminal.is_empty())
+            .into_iter()
+            .chain(["x-terminal-emulator", "gnome-terminal", "konsole", "xfce4-terminal", "xterm"])
+            .map(|terminal| Launch::new(terminal, []).in_dir(dir))
+            .collect(),
+    }
+}
+
+/// 依次尝试启动候选命令，直到有一个成功
+fn spawn_first(launches: Vec<Launch>) -> Result<(), String> {
+    let mut tried = Vec::new();
+    for launch in launches {
+        let mut command = Command::new(&launch.program);
+        command.args(&launch.args);
+        if let Some(dir) = &launch.dir {
+            command.current_dir(dir);
+        }
+        match command.spawn() {
+            Ok(_) => return Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => tried.push(launch.program),
+            Err(e) => return Err(format!("无法启动 {}: {}", launch.program, e)),
+        }
+    }
+    Err(format!("找不到可用的程序（尝试了 {}）", tried.join("、")))
+}
+
+pub fn reveal_in_file_manager(path: &Path) -> Result<(), String> {
+    spawn_first(reveal_launches(Os::current(), path))
+}
+
+/// 在终端中打开文件夹；给出文件时打开其所在的文件夹
+pub fn open_terminal(path: &Path) -> Result<(), String> {
+    let dir = if path.is_dir() { path } else { path.parent().unwrap_or(path) };
+    let terminal = std::env::var("TERMINAL").ok();
+    spawn_first(terminal_launches(Os::current(), dir, terminal.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launches_per_platform() {
+        let file = Path::new("/p/src/main.t");
+        assert_eq!(reveal_launches(Os::Windows, file)[0].args, vec![OsString::from("/select,/p/src/main.t")]);
+        assert_eq!(reveal_launches(Os::MacOs, file)[0].args, vec![OsString::from("-R"), file.into()]);
+        let linux = &reveal_launches(Os::Linux, file)[0];
+        assert_eq!((linux.program.as_str(), linux.args.clone()), ("xdg-open", vec![OsString::from("/p/src")]));
+
+        let dir = Path::new("/p/src");
+        let windows = &terminal_launches(Os::Windows, dir, None)[0];
+        assert_eq!(windows.dir.as_deref(), Some(dir));
+        let linux = terminal_launches(Os::Linux, dir, Some("alacritty"));
+        assert_eq!(linux[0].program, "alacritty");
+        assert!(linux.iter().all(|launch| launch.dir.as_deref() == Some(dir)));
+        assert_eq!(terminal_launches(Os::Linux, dir, Some(""))[0].program, "x-terminal-emulator");
+
+        let missing = spawn_first(vec![Launch::new("tiecode-no-such-program", [])]);
+        assert!(missing.unwrap_err().contains("tiecode-no-such-program"));
+    }
+}
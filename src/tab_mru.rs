@@ -0,0 +1,80 @@
+//! 最近使用的标签顺序，ctrl+tab 切换和关闭当前标签后选择下一个标签时使用
+
+use std::path::{Path, PathBuf};
+
+/// 第一个为当前标签，其后按最近激活的时间排列
+#[derive(Debug, Default)]
+pub struct TabMru {
+    paths: Vec<PathBuf>,
+}
+
+impl TabMru {
+    /// 激活标签，移到最前
+    pub fn activate(&mut self, path: &Path) {
+        self.remove(path);
+        self.paths.insert(0, path.to_path_buf());
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.paths.retain(|p| p != path);
+    }
+
+    /// 标签改名后保留原来的位置
+    pub fn rename(&mut self, src: &Path, dst: &Path) {
+        if let Some(path) = self.paths.iter_mut().find(|p| p.as_path() == src) {
+            *path = dst.to_path_buf();
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&PathBuf> {
+        self.paths.get(index)
+    }
+
+    pub fn first(&self) -> Option<&PathBuf> {
+        self.paths.first()
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PathBuf> {
+        self.paths.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(mru: &TabMru) -> Vec<&str> {
+        mru.iter().map(|p| p.to_str().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_activate_close_reopen() {
+        let mut mru = TabMru::default();
+        for name in ["a.t", "b.t", "c.t"] {
+            mru.activate(Path::new(name));
+        }
+        assert_eq!(order(&mru), ["c.t", "b.t", "a.t"]);
+
+        // 再次激活已打开的标签只是移到最前
+        mru.activate(Path::new("a.t"));
+        assert_eq!(order(&mru), ["a.t", "c.t", "b.t"]);
+        assert_eq!(mru.get(1).unwrap(), Path::new("c.t"));
+
+        // 关闭当前标签后，最近使用的标签排到最前
+        mru.remove(Path::new("a.t"));
+        assert_eq!(order(&mru), ["c.t", "b.t"]);
+        assert_eq!(mru.first().unwrap(), Path::new("c.t"));
+
+        // 重新打开的标签不会保留关闭前的位置
+        mru.activate(Path::new("b.t"));
+        mru.activate(Path::new("a.t"));
+        assert_eq!(order(&mru), ["a.t", "b.t", "c.t"]);
+
+        mru.rename(Path::new("b.t"), Path::new("d.t"));
+        assert_eq!(order(&mru), ["a.t", "d.t", "c.t"]);
+    }
+}
@@ -0,0 +1,375 @@
+//! 无界面的测试环境：在 gpui 的测试平台上用 [`build_start_window`] 打开主窗口，绑定与程序相同的按键，
+//! 用模拟的按键、输入和动作驱动编辑器、文件树、命令面板和插件管理器，再检查缓冲区、选区、标签和
+//! 各组件发出的事件。测试平台不排版字形，每次模拟输入后都会重新绘制并执行完等待中的任务
+
+use gpui::{Action, Entity, EventEmitter, TestAppContext, VisualTestContext, WindowOptions};
+use std::cell::RefCell;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::component::command_palette::CommandPalette;
+use crate::component::file_tree::FileTree;
+use crate::editor::CodeEditor;
+use crate::plugin::manager::PluginManager;
+use crate::{build_start_window, key_bindings, StartWindow};
+
+pub struct Harness {
+    pub cx: &'static mut VisualTestContext,
+    pub window: Entity<StartWindow>,
+}
+
+impl Harness {
+    /// 打开一个主窗口；不读取会话，也不扫描插件目录
+    pub fn new(cx: &mut TestAppContext) -> Self {
+        let handle = cx
+            .update(|cx| {
+                cx.bind_keys(key_bindings());
+                cx.open_window(WindowOptions::default(), |window, cx| {
+                    build_start_window(window, cx, false, Instant::now(), false)
+                })
+            })
+            .expect("open test window");
+        let window = handle.root(cx).expect("test window root");
+        let cx = VisualTestContext::from_window(handle.into(), cx).into_mut();
+        cx.run_until_parked();
+        Self { cx, window }
+    }
+
+    pub fn editor(&self) -> Entity<CodeEditor> {
+        self.window.read_with(&*self.cx, |this, _| this.editor.clone())
+    }
+
+    pub fn file_tree(&self) -> Entity<FileTree> {
+        self.window.read_with(&*self.cx, |this, _| this.file_tree.clone())
+    }
+
+    pub fn command_palette(&self) -> Entity<CommandPalette> {
+        self.window.read_with(&*self.cx, |this, _| this.command_palette.clone())
+    }
+
+    pub fn plugin_manager(&self) -> Entity<PluginManager> {
+        self.window.read_with(&*self.cx, |this, _| this.plugin_manager.clone())
+    }
+
+    /// 在文件树中打开文件夹
+    pub fn open_folder(&mut self, root: &Path) {
+        let root = root.to_path_buf();
+        self.file_tree().update(self.cx, |tree, cx| tree.set_root_path(root, cx));
+        self.cx.run_until_parked();
+    }
+
+    /// 像在文件树中点击一样打开文件
+    pub fn open_file(&mut self, path: &Path) {
+        let path = path.to_path_buf();
+        self.window.update(self.cx, |this, cx| this.open_file_path(path, cx));
+        self.cx.run_until_parked();
+    }
+
+    /// 执行命令面板中的命令
+    pub fn run_command(&mut self, command: &str) {
+        self.window.update(self.cx, |this, cx| this.execute_command(command, None, cx));
+        self.cx.run_until_parked();
+    }
+
+    pub fn focus_editor(&mut self) {
+        let handle = self.editor().read_with(&*self.cx, |editor, _| editor.focus_handle.clone());
+        self.cx.update(|window, _| handle.focus(window));
+        self.cx.run_until_parked();
+    }
+
+    pub fn focus_file_tree(&mut self) {
+        let tree = self.file_tree();
+        self.cx.update(|window, cx| tree.read(cx).focus(window));
+        self.cx.run_until_parked();
+    }
+
+    /// 以空格分隔的按键，例如 `"ctrl-shift-p enter"`，按绑定分发动作
+    pub fn keys(&mut self, keystrokes: &str) {
+        self.cx.simulate_keystrokes(keystrokes);
+    }
+
+    /// 逐字输入文本，交给焦点所在控件的输入处理
+    pub fn type_text(&mut self, text: &str) {
+        self.cx.simulate_input(text);
+    }
+
+    pub fn dispatch<A: Action>(&mut self, action: A) {
+        self.cx.dispatch_action(action);
+    }
+
+    /// 记录 `entity` 之后发出的事件
+    pub fn record_events<E: Clone + 'static, T: EventEmitter<E>>(&mut self, entity: &Entity<T>) -> Rc<RefCell<Vec<E>>> {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let recorded = events.clone();
+        self.cx.update(|_, cx| {
+            cx.subscribe(entity, move |_, event: &E, _| recorded.borrow_mut().push(event.clone())).detach();
+        });
+        events
+    }
+
+    pub fn buffer_text(&self) -> String {
+        self.editor().read_with(&*self.cx, |editor, _| editor.core.content.to_string())
+    }
+
+    /// 主选区的字节范围
+    pub fn selection(&self) -> Range<usize> {
+        self.editor().read_with(&*self.cx, |editor, _| editor.core.primary_selection().range())
+    }
+
+    pub fn open_tabs(&self) -> Vec<PathBuf> {
+        self.window.read_with(&*self.cx, |this, _| this.open_tabs.iter().map(|tab| tab.path.clone()).collect())
+    }
+
+    pub fn active_tab(&self) -> Option<PathBuf> {
+        self.window.read_with(&*self.cx, |this, _| this.active_tab.clone())
+    }
+
+    /// 有未保存修改的标签
+    pub fn unsaved_tabs(&self, root: &Path) -> Vec<PathBuf> {
+        let root = root.to_path_buf();
+        self.window.read_with(&*self.cx, |this, cx| this.unsaved_tabs_under(&root, cx))
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::component::command_palette::CommandPaletteEvent;
+    use crate::component::file_tree::FileTreeEvent;
+    use crate::editor::{DeleteLine, Enter, Paste, Undo};
+    use gpui::ClipboardItem;
+    use crate::editor_settings::SaveErrorCheck;
+    use crate::workspace::PrepareOutcome;
+    use crate::ConfirmAction;
+    use std::time::Duration;
+
+    #[gpui::test]
+    fn test_type_and_save_writes_disk(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("启动窗口.t");
+        std::fs::write(&path, "结束 类\n").unwrap();
+
+        let mut harness = Harness::new(cx);
+        harness.open_folder(dir.path());
+        harness.open_file(&path);
+        assert_eq!(harness.active_tab(), Some(path.clone()));
+        assert_eq!(harness.buffer_text(), "结束 类\n");
+
+        harness.focus_editor();
+        harness.type_text("class");
+        harness.dispatch(Enter);
+        assert_eq!(harness.buffer_text(), "class\n结束 类\n");
+        assert_eq!(harness.selection(), 6..6);
+        assert_eq!(harness.unsaved_tabs(dir.path()), vec![path.clone()]);
+
+        harness.run_command("core.save");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "class\n结束 类\n");
+        assert!(harness.unsaved_tabs(dir.path()).is_empty());
+    }
+
+    #[gpui::test]
+    fn test_palette_executes_command(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("工具类.t");
+        std::fs::write(&path, "").unwrap();
+
+        let mut harness = Harness::new(cx);
+        harness.open_file(&path);
+        let palette = harness.command_palette();
+        let events = harness.record_events(&palette);
+        assert!(harness.plugin_manager().read_with(&*harness.cx, |manager, _| {
+            manager.command_registry.list().iter().any(|command| command.command == "editor.toggle_read_only")
+        }));
+
+        harness.keys("secondary-shift-p");
+        assert!(palette.read_with(&*harness.cx, |palette, _| palette.is_visible()));
+        harness.type_text("toggle read");
+        harness.keys("enter");
+
+        assert!(events.borrow().contains(&CommandPaletteEvent::ExecuteCommand("editor.toggle_read_only".to_string())));
+        assert!(harness.editor().read_with(&*harness.cx, |editor, _| editor.is_read_only()));
+    }
+
+    #[gpui::test]
+    fn test_file_tree_rename_updates_tabs(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("a.t");
+        let to = dir.path().join("b.t");
+        std::fs::write(&from, "类 甲\n").unwrap();
+
+        let mut harness = Harness::new(cx);
+        harness.open_folder(dir.path());
+        harness.open_file(&from);
+        let tree = harness.file_tree();
+        let events = harness.record_events(&tree);
+        tree.update(harness.cx, |tree, cx| {
+            tree.expand_and_reveal(&from, cx);
+            tree.begin_inline_rename(from.clone(), cx);
+        });
+
+        // 重命名时选中文件名中扩展名之前的部分，输入的内容替换它
+        harness.focus_file_tree();
+        harness.type_text("b");
+        harness.keys("enter");
+
+        assert!(to.exists() && !from.exists());
+        assert_eq!(*events.borrow(), vec![FileTreeEvent::Renamed { from: from.clone(), to: to.clone() }]);
+        assert_eq!(harness.open_tabs(), vec![to.clone()]);
+        assert_eq!(harness.active_tab(), Some(to));
+        assert_eq!(harness.buffer_text(), "类 甲\n");
+    }
+
+    #[gpui::test]
+    fn test_branch_switch_keeps_unsaved_edits(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.t");
+        std::fs::write(&path, "甲\n").unwrap();
+
+        let mut harness = Harness::new(cx);
+        harness.open_folder(dir.path());
+        harness.open_file(&path);
+
+        // 没有修改的标签直接换成新分支上的内容，撤销一次回到切换之前
+        std::fs::write(&path, "乙\n").unwrap();
+        harness.window.update(harness.cx, |this, cx| this.refresh_workspace_content(cx));
+        assert_eq!(harness.buffer_text(), "乙\n");
+        assert!(harness.unsaved_tabs(dir.path()).is_empty());
+        harness.focus_editor();
+        harness.dispatch(Undo);
+        assert_eq!(harness.buffer_text(), "甲\n");
+        harness.dispatch(Undo);
+        assert_eq!(harness.buffer_text(), "甲\n");
+
+        // 有未保存修改的标签先询问，不覆盖缓冲区
+        std::fs::write(&path, "丙\n").unwrap();
+        harness.window.update(harness.cx, |this, cx| this.refresh_workspace_content(cx));
+        assert_eq!(harness.buffer_text(), "甲\n");
+        assert!(harness.window.read_with(&*harness.cx, |this, _| {
+            matches!(&this.confirm_action, Some(ConfirmAction::ReloadExternal { path: p }) if p == &path)
+        }));
+    }
+
+    #[gpui::test]
+    fn test_each_keystroke_notifies_editor_at_most_once(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("重绘.t");
+        std::fs::write(&path, "类 启动窗口\n结束 类\n").unwrap();
+
+        let mut harness = Harness::new(cx);
+        harness.open_folder(dir.path());
+        harness.open_file(&path);
+        harness.focus_editor();
+        let notify_count = |harness: &Harness| harness.editor().read_with(&*harness.cx, |editor, _| editor.notify_count());
+
+        for ch in "变量 计数 = 1".chars() {
+            let before = notify_count(&harness);
+            harness.type_text(&ch.to_string());
+            assert!(notify_count(&harness) - before <= 1, "typing {:?} notified more than once", ch);
+        }
+        for keys in ["enter", "backspace", "left", "shift-right"] {
+            let before = notify_count(&harness);
+            harness.keys(keys);
+            assert!(notify_count(&harness) - before <= 1, "{} notified more than once", keys);
+        }
+        assert!(harness.buffer_text().starts_with("变量 计数 = 1类"));
+    }
+
+    #[gpui::test]
+    fn test_quick_open_previews_selection_after_delay(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("a.t");
+        let second = dir.path().join("b.t");
+        std::fs::write(&first, "类 甲\n").unwrap();
+        std::fs::write(&second, "类 乙\n").unwrap();
+
+        let mut harness = Harness::new(cx);
+        harness.open_folder(dir.path());
+        harness.open_file(&first);
+        let palette = harness.command_palette();
+        let events = harness.record_events(&palette);
+        let previewed = |events: &Rc<RefCell<Vec<CommandPaletteEvent>>>| {
+            events.borrow().iter().any(|e| matches!(e, CommandPaletteEvent::PreviewMatch(_)))
+        };
+
+        harness.run_command("workspace.quick_open");
+        harness.keys("down");
+        // 停留不到 200ms 时不预览
+        harness.cx.executor().advance_clock(Duration::from_millis(100));
+        harness.cx.run_until_parked();
+        assert!(!previewed(&events));
+
+        harness.cx.executor().advance_clock(Duration::from_millis(150));
+        harness.cx.run_until_parked();
+        assert!(events.borrow().contains(&CommandPaletteEvent::PreviewMatch(second.clone())));
+        assert_eq!(harness.buffer_text(), "类 乙\n");
+        assert_eq!(harness.open_tabs(), vec![first.clone()]);
+
+        harness.keys("escape");
+        assert!(events.borrow().contains(&CommandPaletteEvent::CancelPreview));
+        assert_eq!(harness.active_tab(), Some(first));
+        assert_eq!(harness.buffer_text(), "类 甲\n");
+    }
+
+    #[gpui::test]
+    fn test_prepare_commit_reports_each_file(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("a.t");
+        let second = dir.path().join("b.t");
+        std::fs::write(&first, "甲\n").unwrap();
+        std::fs::write(&second, "乙\n").unwrap();
+
+        let mut harness = Harness::new(cx);
+        harness.open_folder(dir.path());
+        harness.window.update(harness.cx, |this, _| {
+            this.save_error_check = SaveErrorCheck::Off;
+            this.format_on_save = true;
+        });
+        harness.open_file(&first);
+        harness.focus_editor();
+        harness.type_text("丙");
+        harness.open_file(&second);
+        harness.focus_editor();
+        harness.type_text("丁");
+
+        harness.run_command("workspace.prepare_commit");
+        // 后台标签和当前标签都先经过格式化再写入，提示框逐个列出结果
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "丙甲\n");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "丁乙\n");
+        assert!(harness.unsaved_tabs(dir.path()).is_empty());
+        harness.window.read_with(&*harness.cx, |this, _| {
+            let toast = this.prepare_commit_toast.as_ref().expect("prepare commit toast");
+            assert!(toast.finished);
+            assert_eq!(
+                toast.results,
+                vec![("a.t".to_string(), Some(PrepareOutcome::Saved)), ("b.t".to_string(), Some(PrepareOutcome::Saved))]
+            );
+        });
+    }
+
+    #[gpui::test]
+    fn test_editing_large_document_never_resyncs_the_whole_buffer(cx: &mut TestAppContext) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("大文件.t");
+        std::fs::write(&path, "变量 计数 为 整数 = 0\n".repeat(20_000)).unwrap();
+
+        let mut harness = Harness::new(cx);
+        harness.open_folder(dir.path());
+        harness.open_file(&path);
+        harness.focus_editor();
+        let full_syncs = |harness: &Harness| harness.editor().read_with(&*harness.cx, |editor, _| editor.full_sync_count());
+        let before = full_syncs(&harness);
+
+        // 输入、删除、换行、粘贴、删除整行和撤销都只把编辑交给 sweetline 和语言服务
+        harness.type_text("如果 计数 > 0 则");
+        harness.keys("backspace delete enter");
+        harness.cx.write_to_clipboard(ClipboardItem::new_string("计数 = 计数 + 1".to_string()));
+        harness.dispatch(Paste);
+        harness.dispatch(DeleteLine);
+        harness.dispatch(Undo);
+        harness.cx.run_until_parked();
+
+        assert_eq!(full_syncs(&harness), before);
+        assert!(harness.buffer_text().starts_with("如果 计数 > 0 "));
+    }
+}
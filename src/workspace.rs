@@ -0,0 +1,145 @@
+//! 工作区内容变化（例如切换 git 分支）后，对已打开文件做统一检查
+
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+pub enum OpenFileState {
+    /// 磁盘内容与编辑器一致，或该文件不在编辑器中（激活时会重新读取）
+    Unchanged,
+    /// 磁盘内容与编辑器不同，附带新内容
+    Changed(String),
+    /// 文件在当前分支上不存在
+    Missing,
+}
+
+/// 检查一个已打开的文件；`buffer` 为编辑器中该文件的当前内容
+pub fn check_open_file(path: &Path, buffer: Option<&str>) -> OpenFileState {
+    if !path.is_file() {
+        return OpenFileState::Missing;
+    }
+    let Some(buffer) = buffer else {
+        return OpenFileState::Unchanged;
+    };
+    match std::fs::read_to_string(path) {
+        Ok(raw) => {
+            // 缓冲区不含 BOM，比较前同样去掉
+            let content = tiecode_buffer::strip_bom(&raw).0;
+            if content != buffer {
+                OpenFileState::Changed(content.to_string())
+            } else {
+                OpenFileState::Unchanged
+            }
+        }
+        // 读取失败时保留编辑器内容，不当作文件消失
+        Err(_) => OpenFileState::Unchanged,
+    }
+}
+
+/// “准备提交”中单个文件的处理结果
+#[derive(Debug, PartialEq)]
+pub enum PrepareOutcome {
+    Saved,
+    /// 没有未保存的修改
+    Unchanged,
+    /// 有意不处理，例如文件不在当前分支上
+    Skipped(String),
+    Failed(String),
+}
+
+/// 汇总“准备提交”的结果，显示在提示框底部
+pub fn prepare_summary<'a>(outcomes: impl IntoIterator<Item = &'a PrepareOutcome>) -> String {
+    let (mut saved, mut failed) = (0, 0);
+    for outcome in outcomes {
+        match outcome {
+            PrepareOutcome::Saved => saved += 1,
+            PrepareOutcome::Failed(_) => failed += 1,
+            _ => {}
+        }
+    }
+    match (saved, failed) {
+        (0, 0) => "没有需要保存的文件".to_string(),
+        (saved, 0) => format!("已保存 {} 个文件", saved),
+        (saved, failed) => format!("已保存 {} 个文件，{} 个失败", saved, failed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::git_panel::{checkout_local_branch, current_branch_name};
+    use git2::{Repository, Signature};
+    use std::fs;
+
+    fn commit_all(repo: &Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.update_all(["*"], None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap();
+    }
+
+    /// main: a.t = "main"；feature: a.t = "feature"，另有 b.t
+    fn two_branch_repo() -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join("a.t"), "main").unwrap();
+        commit_all(&repo, "init");
+        let main = current_branch_name(dir.path()).unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("feature", &head, false).unwrap();
+        checkout_local_branch(dir.path(), "feature").unwrap();
+        fs::write(dir.path().join("a.t"), "feature").unwrap();
+        fs::write(dir.path().join("b.t"), "only on feature").unwrap();
+        commit_all(&repo, "feature");
+        (dir, main)
+    }
+
+    #[test]
+    fn test_branch_switch_reports_changed_and_missing_files() {
+        let (dir, main) = two_branch_repo();
+        let a = dir.path().join("a.t");
+        let b = dir.path().join("b.t");
+
+        checkout_local_branch(dir.path(), &main).unwrap();
+        assert_eq!(current_branch_name(dir.path()).as_deref(), Some(main.as_str()));
+        assert_eq!(check_open_file(&a, Some("feature")), OpenFileState::Changed("main".to_string()));
+        assert_eq!(check_open_file(&b, Some("only on feature")), OpenFileState::Missing);
+        // 不在编辑器中的标签只需知道文件还在
+        assert_eq!(check_open_file(&a, None), OpenFileState::Unchanged);
+    }
+
+    #[test]
+    fn test_prepare_summary_counts_saved_and_failed() {
+        assert_eq!(prepare_summary(&[PrepareOutcome::Unchanged]), "没有需要保存的文件");
+        assert_eq!(
+            prepare_summary(&[
+                PrepareOutcome::Saved,
+                PrepareOutcome::Skipped("不在当前分支".to_string()),
+                PrepareOutcome::Failed("有 2 个错误".to_string()),
+                PrepareOutcome::Saved,
+            ]),
+            "已保存 2 个文件，1 个失败"
+        );
+    }
+
+    #[test]
+    fn test_files_reattach_when_switching_back() {
+        let (dir, main) = two_branch_repo();
+        let b = dir.path().join("b.t");
+
+        checkout_local_branch(dir.path(), &main).unwrap();
+        assert_eq!(check_open_file(&b, None), OpenFileState::Missing);
+
+        checkout_local_branch(dir.path(), "feature").unwrap();
+        assert_eq!(check_open_file(&b, Some("only on feature")), OpenFileState::Unchanged);
+        assert_eq!(
+            check_open_file(&dir.path().join("a.t"), Some("main")),
+            OpenFileState::Changed("feature".to_string())
+        );
+    }
+}